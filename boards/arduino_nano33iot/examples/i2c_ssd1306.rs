@@ -21,7 +21,6 @@ use hal::delay::Delay;
 use hal::entry;
 use hal::pac::{CorePeripherals, Peripherals};
 use hal::prelude::*;
-use hal::time::KiloHertz;
 
 use ssd1306::{mode::displaymode::DisplayModeTrait, prelude::*, Builder, I2CDIBuilder};
 
@@ -47,7 +46,7 @@ fn main() -> ! {
 
     let i2c = hal::i2c_master(
         &mut clocks,
-        KiloHertz(400),
+        400.kHz(),
         peripherals.SERCOM4,
         &mut peripherals.PM,
         pins.sda,
@@ -10,7 +10,6 @@ use hal::delay::Delay;
 use hal::entry;
 use hal::pac::{CorePeripherals, Peripherals};
 use hal::prelude::*;
-use hal::time::MegaHertz;
 
 use embedded_graphics::{
     image::{Image, ImageRaw, ImageRawLE},
@@ -41,7 +40,7 @@ fn main() -> ! {
 
     let spi = hal::spi_master(
         &mut clocks,
-        MegaHertz(16),
+        16.MHz(),
         peripherals.SERCOM1,
         &mut peripherals.PM,
         pins.led_sck,
@@ -3,6 +3,7 @@
 
 extern crate arduino_nano33iot as hal;
 extern crate cortex_m;
+#[cfg(not(feature = "panic-usb"))]
 extern crate panic_halt;
 extern crate usb_device;
 extern crate usbd_serial;
@@ -0,0 +1,64 @@
+#![no_std]
+#![no_main]
+
+/// Toggles the red LED whenever a key of a NEC remote control is pressed.
+/// Held keys are ignored. The marks are captured by TC3.
+extern crate circuit_playground_express as hal;
+extern crate cortex_m_rt;
+extern crate panic_halt;
+
+use hal::clock::GenericClockController;
+use hal::eic::{
+    pin::{EicPin, ExternalInterrupt, Sense},
+    EIC,
+};
+use hal::ir::{nec::NecDecoder, IrCaptureReceiver};
+use hal::pac::Peripherals;
+use hal::timer::TimerCounter3;
+
+use cortex_m_rt::entry;
+
+#[entry]
+fn main() -> ! {
+    let mut peripherals = Peripherals::take().unwrap();
+    let mut clocks = GenericClockController::with_internal_32kosc(
+        peripherals.GCLK,
+        &mut peripherals.PM,
+        &mut peripherals.SYSCTRL,
+        &mut peripherals.NVMCTRL,
+    );
+    let mut pins = hal::Pins::new(peripherals.PORT);
+    let mut red_led = pins.d13.into_push_pull_output(&mut pins.port);
+
+    let gclk0 = clocks.gclk0();
+    let eic_clock = clocks.eic(&gclk0).unwrap();
+    let mut eic = EIC::init(&mut peripherals.PM, eic_clock, peripherals.EIC);
+    let mut ir_rx = pins.ir_rx.into_floating_ei(&mut pins.port);
+    ir_rx.sense(&mut eic, Sense::HIGH);
+    ir_rx.enable_event(&mut eic);
+
+    let timer = TimerCounter3::tc3_(
+        &clocks.tcc2_tc3(&gclk0).unwrap(),
+        peripherals.TC3,
+        &mut peripherals.PM,
+    );
+    // 48MHz divided by 64 captures up to 87ms. The output of the receiver is
+    // low during marks.
+    let capture = timer.into_pulse_capture(
+        &mut peripherals.EVSYS,
+        &mut peripherals.PM,
+        ir_rx.id(),
+        0,
+        64,
+        true,
+    );
+    let mut receiver = IrCaptureReceiver::new(capture, NecDecoder::new());
+
+    loop {
+        if let Some(command) = receiver.on_capture() {
+            if !command.repeat {
+                red_led.toggle();
+            }
+        }
+    }
+}
@@ -62,6 +62,11 @@ define_pins!(
 
     pin accel_sda = a0,
     pin accel_scl = a1,
+
+    /// The IR transmitter LED. `TCC0/WO[5]` in function F.
+    pin ir_tx = a23,
+    /// The output of the IR receiver, low while a carrier is received
+    pin ir_rx = a12,
 );
 
 /// Convenience for accessing the on-board SPI Flash device.
@@ -87,7 +92,7 @@ pub fn flash_spi_master(
     let gclk0 = clocks.gclk0();
     let flash = SPIMaster3::new(
         &clocks.sercom3_core(&gclk0).unwrap(),
-        48.mhz(),
+        48.MHz(),
         hal::hal::spi::Mode {
             phase: hal::hal::spi::Phase::CaptureOnFirstTransition,
             polarity: hal::hal::spi::Polarity::IdleLow,
@@ -13,7 +13,7 @@ use edgebadge::{self as hal, entry, pac, Pins};
 use panic_halt as _;
 
 use hal::prelude::*;
-use hal::{clock::GenericClockController, delay::Delay, timer::TimerCounter};
+use hal::{clock::GenericClockController, delay::Delay, time::Hertz, timer::TimerCounter};
 use pac::{CorePeripherals, Peripherals};
 use smart_leds::hsv::SmartLedsWrite;
 use smart_leds::hsv::{hsv2rgb, Hsv};
@@ -34,7 +34,7 @@ fn main() -> ! {
     let gclk0 = clocks.gclk0();
     let timer_clock = clocks.tc2_tc3(&gclk0).unwrap();
     let mut timer = TimerCounter::tc3_(&timer_clock, peripherals.TC3, &mut peripherals.MCLK);
-    timer.start(3.mhz());
+    timer.start(Hertz::MHz(3).into_duration());
 
     let mut neopixel = pins.neopixel.init(timer, &mut pins.port);
     let mut delay = Delay::new(core.SYST, &mut clocks);
@@ -11,7 +11,6 @@ use edgebadge::{self as hal, entry, pac, Pins};
 use panic_halt as _;
 
 use hal::prelude::*;
-use hal::time::KiloHertz;
 use hal::timer::SpinTimer;
 use hal::{clock::GenericClockController, delay::Delay};
 use lis3dh::{accelerometer::Accelerometer, Lis3dh};
@@ -42,7 +41,7 @@ fn main() -> ! {
     // i2c
     let i2c = pins.i2c.init(
         &mut clocks,
-        KiloHertz(400),
+        400.kHz(),
         peripherals.SERCOM2,
         &mut peripherals.MCLK,
         &mut pins.port,
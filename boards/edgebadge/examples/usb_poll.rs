@@ -5,6 +5,7 @@
 #![no_main]
 
 use edgebadge::{self as hal, entry, pac, Pins};
+#[cfg(not(any(feature = "panic-usb", feature = "panic-watchdog")))]
 use panic_halt as _;
 
 use hal::clock::GenericClockController;
@@ -15,6 +15,7 @@
 //! opt-level = 2 to profile.dev in Cargo.toml
 
 use edgebadge::{self as hal, entry, pac, Pins};
+#[cfg(not(any(feature = "panic-usb", feature = "panic-watchdog")))]
 use panic_halt as _;
 
 use cortex_m::interrupt::free as disable_interrupts;
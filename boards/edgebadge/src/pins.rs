@@ -333,7 +333,7 @@ impl Display {
         let gclk0 = clocks.gclk0();
         let tft_spi = SPIMaster4::new(
             &clocks.sercom4_core(&gclk0).ok_or(())?,
-            16.mhz(),
+            16.MHz(),
             spi::Mode {
                 phase: spi::Phase::CaptureOnFirstTransition,
                 polarity: spi::Polarity::IdleLow,
@@ -361,7 +361,7 @@ impl Display {
         let tft_backlight = self.tft_backlight.into_function_e(port);
         let mut pwm2 = Pwm2::new(
             &clocks.tc2_tc3(&gclk0).ok_or(())?,
-            1.khz(),
+            1.kHz(),
             timer2,
             hal::pwm::TC2Pinout::Pa1(tft_backlight),
             mclk,
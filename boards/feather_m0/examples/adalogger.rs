@@ -22,7 +22,6 @@ use hal::delay::Delay;
 use hal::pac::{interrupt, CorePeripherals, Peripherals};
 use hal::prelude::*;
 use hal::rtc;
-use hal::time::U32Ext;
 use hal::usb::UsbBus;
 
 use heapless::consts::U1024;
@@ -88,7 +87,7 @@ fn main() -> ! {
     // Now work on the SD peripherals. Slow SPI speed required on init
     let spi = bsp::spi_master(
         &mut clocks,
-        400_u32.khz(),
+        400_u32.kHz(),
         peripherals.SERCOM4,
         &mut peripherals.PM,
         pins.sclk,
@@ -125,7 +124,7 @@ fn main() -> ! {
     match controller.device().init() {
         Ok(_) => {
             // speed up SPI and read out some info
-            controller.device().spi().reconfigure(|c| c.baud(4.mhz()));
+            controller.device().spi().reconfigure(|c| c.baud(4.MHz()));
             usbserial_write!("OK!\r\nCard size...\r\n");
             match controller.device().card_size_bytes() {
                 Ok(size) => usbserial_write!("{} bytes\r\n", size),
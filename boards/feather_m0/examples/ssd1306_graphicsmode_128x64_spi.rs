@@ -68,7 +68,6 @@ use bsp::entry;
 use hal::clock::GenericClockController;
 use hal::delay::Delay;
 use hal::prelude::*;
-use hal::time::MegaHertz;
 use pac::{CorePeripherals, Peripherals};
 
 #[entry]
@@ -87,7 +86,7 @@ fn main() -> ! {
 
     let spi = bsp::spi_master(
         &mut clocks,
-        MegaHertz(10),
+        10.MHz(),
         peripherals.SERCOM4,
         &mut peripherals.PM,
         pins.sclk,
@@ -1,6 +1,7 @@
 #![no_std]
 #![no_main]
 
+#[cfg(not(feature = "panic-usb"))]
 use panic_halt as _;
 
 use cortex_m::asm::delay as cycle_delay;
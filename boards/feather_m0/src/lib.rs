@@ -7,6 +7,8 @@ pub use atsamd_hal as hal;
 pub use embedded_hal as ehal;
 pub use hal::pac;
 
+#[cfg(feature = "unproven")]
+use hal::adc::Adc;
 use hal::clock::GenericClockController;
 use hal::sercom::v2::{spi, Sercom4};
 use hal::sercom::{I2CMaster3, UART0};
@@ -15,6 +17,24 @@ use hal::time::Hertz;
 #[cfg(feature = "usb")]
 use hal::usb::{usb_device::bus::UsbBusAllocator, UsbBus};
 
+// The board variants wire PA06, PA08 and PA09 differently, so at most one of
+// their features may be enabled
+#[cfg(all(feature = "rfm", feature = "express"))]
+compile_error!(
+    "The `rfm` and `express` features both claim PA06, PA08 and PA09, for the radio and for \
+     the neopixel and external flash. No Feather M0 has both."
+);
+#[cfg(all(feature = "rfm", feature = "adalogger"))]
+compile_error!(
+    "The `rfm` and `adalogger` features both claim PA08, as the radio reset and as the SD \
+     card chip select. No Feather M0 has both."
+);
+#[cfg(all(feature = "express", feature = "adalogger"))]
+compile_error!(
+    "The `express` and `adalogger` features both claim PA08, as the flash MOSI and as the SD \
+     card chip select. No Feather M0 has both."
+);
+
 hal::bsp_pins!(
     PA03 {
         /// AREF pin - has 1uF capacitor to ground
@@ -76,8 +96,12 @@ hal::bsp_pins!(
         name: d6
     }
     PA07 {
-        /// Pin 9, PWM capable.  Also analog input (A7)
+        /// Pin 9, PWM capable.  Also analog input (A7), which measures the
+        /// battery voltage through a divider
         name: d9
+        aliases: {
+            AlternateB: Vbat
+        }
     }
     PA18 {
         /// Pin 10, PWM capable
@@ -151,34 +175,34 @@ hal::bsp_pins!(
         }
     }
 
-    #[cfg(all(feature = "rfm", not(feature = "express")))]
+    #[cfg(feature = "rfm")]
     PA06 {
         /// SPI chip select for the RFM module
         name: rfm_cs
     }
-    #[cfg(all(feature = "rfm", not(feature = "express"), not(feature = "adalogger")))]
+    #[cfg(feature = "rfm")]
     PA08 {
         /// Reset for the RFM module
         name: rfm_reset
     }
-    #[cfg(all(feature = "rfm", not(feature = "express")))]
+    #[cfg(feature = "rfm")]
     PA09 {
         /// Interrupt from the RFM module
         name: rfm_irq
     }
 
-    #[cfg(all(feature = "express", not(feature = "rfm")))]
+    #[cfg(feature = "express")]
     PA06 {
         /// Neopixel data
         name: neopixel
     }
 
-    #[cfg(all(feature = "express", not(feature = "rfm")))]
+    #[cfg(feature = "express")]
     PA09 {
         /// SPI clock for the external flash
         name: flash_sclk
     }
-    #[cfg(all(feature = "express", not(feature = "rfm"), not(feature = "adalogger")))]
+    #[cfg(feature = "express")]
     PA08 {
         /// SPI MOSI for the external flash
         name: flash_mosi
@@ -194,7 +218,7 @@ hal::bsp_pins!(
         name: flash_cs
     }
 
-    #[cfg(all(feature = "adalogger", not(feature = "rfm"), not(feature = "express")))]
+    #[cfg(feature = "adalogger")]
     PA08 {
         /// SD card SPI chip select
         name: sd_cs
@@ -279,6 +303,22 @@ pub fn uart(
     UART0::new(clock, baud, sercom0, pm, pads)
 }
 
+/// Supply voltage of the ADC, in millivolts
+pub const VDDANA_MV: u16 = 3300;
+
+/// Ratio of the divider between the battery and the [`Vbat`] pin
+pub const VBAT_DIVIDER: u16 = 2;
+
+/// Reads the battery voltage, in millivolts. The ADC is left with a 12 bit
+/// resolution and a full scale of VDDANA.
+///
+/// Without a battery, this reads the charger output, around 4.2V with USB
+/// power.
+#[cfg(feature = "unproven")]
+pub fn battery_voltage(adc: &mut Adc<pac::ADC>, vbat: &mut Vbat) -> u16 {
+    adc.read_millivolts(vbat, VDDANA_MV, VBAT_DIVIDER)
+}
+
 #[cfg(feature = "usb")]
 pub fn usb_allocator(
     usb: pac::USB,
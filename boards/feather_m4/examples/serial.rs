@@ -16,7 +16,6 @@ use hal::pac::gclk::pchctrl::GEN_A;
 use hal::pac::{CorePeripherals, Peripherals};
 use hal::prelude::*;
 use hal::sercom::{PadPin, Sercom5Pad0, Sercom5Pad1, UART5};
-use hal::time::Hertz;
 
 #[entry]
 fn main() -> ! {
@@ -51,7 +50,7 @@ fn main() -> ! {
 
     let mut uart = UART5::new(
         &uart_clk,
-        Hertz(19200),
+        19200.Hz(),
         peripherals.SERCOM5,
         &mut peripherals.MCLK,
         (rx, tx),
@@ -38,7 +38,7 @@ fn main() -> ! {
     //Instantiate a timer object for the TC2 peripheral
     let mut timer = TimerCounter::tc2_(&timer_clock, peripherals.TC2, &mut peripherals.MCLK);
     // Start the timer such that it runs at 50Hz
-    timer.start(50u32.hz());
+    timer.start(20.millis());
 
     // Toggle the red LED at the frequency set by the timer above.
     loop {
@@ -2,6 +2,7 @@
 #![no_main]
 
 extern crate feather_m4 as hal;
+#[cfg(not(feature = "panic-usb"))]
 extern crate panic_halt;
 
 use hal::clock::GenericClockController;
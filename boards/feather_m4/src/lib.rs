@@ -48,6 +48,9 @@ define_pins!(
     pin a4 = a4,
     /// Analog Pin 5
     pin a5 = a6,
+    /// Analog Pin 6, which measures the battery voltage through a
+    /// divider
+    pin vbat = b1,
 
     /// Pin 0, rx
     pin d0 = b17,
@@ -175,6 +178,23 @@ pub fn uart<F: Into<Hertz>>(
     )
 }
 
+/// Supply voltage of the ADC, in millivolts
+pub const VDDANA_MV: u16 = 3300;
+
+/// Ratio of the divider between the battery and the `vbat` pin
+pub const VBAT_DIVIDER: u16 = 2;
+
+/// Reads the battery voltage, in millivolts, from the `vbat` pin in
+/// function B. The ADC is left with a 12 bit resolution and the VDDANA
+/// reference.
+///
+/// Without a battery, this reads the charger output, around 4.2V with USB
+/// power.
+#[cfg(feature = "unproven")]
+pub fn battery_voltage(adc: &mut adc::Adc<pac::ADC0>, vbat: &mut gpio::Pb1<gpio::PfB>) -> u16 {
+    adc.read_millivolts(vbat, VDDANA_MV, VBAT_DIVIDER)
+}
+
 #[cfg(feature = "usb")]
 pub fn usb_allocator(
     dm: impl AnyPin<Id = PA24>,
@@ -0,0 +1,76 @@
+//! Joins the CAN bus at 500 kbit/s, and sends a frame with a counter every
+//! second. Received frames are sent back with their identifier plus one, and
+//! the red LED toggles on each of them.
+
+#![no_std]
+#![no_main]
+
+extern crate feather_m4_can as hal;
+extern crate panic_halt;
+
+use hal::can::{Frame, MessageRam};
+use hal::clock::GenericClockController;
+use hal::delay::Delay;
+use hal::entry;
+use hal::pac::{CorePeripherals, Peripherals};
+use hal::prelude::*;
+use hal::time::Hertz;
+
+use embedded_hal::can::nb::Can;
+use embedded_hal::can::{Frame as _, Id, StandardId};
+
+static mut RAM: MessageRam<8, 4> = MessageRam::new();
+
+#[entry]
+fn main() -> ! {
+    let mut peripherals = Peripherals::take().unwrap();
+    let core = CorePeripherals::take().unwrap();
+    let mut clocks = GenericClockController::with_external_32kosc(
+        peripherals.GCLK,
+        &mut peripherals.MCLK,
+        &mut peripherals.OSC32KCTRL,
+        &mut peripherals.OSCCTRL,
+        &mut peripherals.NVMCTRL,
+    );
+    let mut pins = hal::Pins::new(peripherals.PORT);
+    let mut red_led = pins.d13.into_open_drain_output(&mut pins.port);
+    let mut delay = Delay::new(core.SYST, &mut clocks);
+
+    // Safety: RAM is only used by the CAN driver
+    let ram = unsafe { &mut RAM };
+    let mut can = hal::can(
+        &mut clocks,
+        peripherals.CAN1,
+        &mut peripherals.MCLK,
+        ram,
+        Hertz::kHz(500),
+        pins.can_tx,
+        pins.can_rx,
+        pins.can_standby,
+        pins.boost_en,
+        &mut pins.port,
+    )
+    .unwrap();
+
+    let id = StandardId::new(0x100).unwrap();
+    let mut count = 0u32;
+    loop {
+        let frame = Frame::new(id, &count.to_le_bytes()).unwrap();
+        nb::block!(can.can.transmit(&frame)).unwrap();
+        count = count.wrapping_add(1);
+
+        for _ in 0..100 {
+            if let Ok(frame) = can.can.receive() {
+                let reply = match frame.id() {
+                    Id::Standard(id) => StandardId::new((id.as_raw() + 1) & 0x7ff).map(Id::from),
+                    Id::Extended(_) => None,
+                };
+                if let Some(reply) = reply.and_then(|id| Frame::new(id, frame.data())) {
+                    let _ = can.can.transmit(&reply);
+                }
+                red_led.toggle();
+            }
+            delay.delay_ms(10u16);
+        }
+    }
+}
@@ -15,6 +15,7 @@
 //! opt-level = 2 to profile.dev in Cargo.toml
 
 use grand_central_m4 as hal;
+#[cfg(not(feature = "panic-usb"))]
 use panic_halt as _;
 
 use cortex_m::interrupt::free as disable_interrupts;
@@ -12,7 +12,6 @@ use hal::delay::Delay;
 use hal::entry;
 use hal::pac::{CorePeripherals, Peripherals};
 use hal::prelude::*;
-use hal::time::MegaHertz;
 
 use embedded_graphics::pixelcolor::BinaryColor;
 use embedded_graphics::prelude::*;
@@ -37,7 +36,7 @@ fn main() -> ! {
 
     let spi = hal::spi_master(
         &mut clocks,
-        MegaHertz(10),
+        10.MHz(),
         peripherals.SERCOM4,
         &mut peripherals.PM,
         pins.sck,
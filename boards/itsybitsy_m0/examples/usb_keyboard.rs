@@ -0,0 +1,122 @@
+#![no_std]
+#![no_main]
+
+//! A four key USB keyboard.
+//!
+//! Wire a 2x2 matrix of switches with the rows on D7 and D9 and the columns
+//! on D10 and D11. The keys type `a`, `b`, `c` and left shift.
+
+extern crate cortex_m;
+extern crate itsybitsy_m0 as hal;
+extern crate panic_halt;
+extern crate usb_device;
+
+use hal::clock::GenericClockController;
+use hal::entry;
+use hal::gpio::v2::{DynPin, Pin};
+use hal::keypad::{Keypad, Scanner};
+use hal::pac::{interrupt, CorePeripherals, Peripherals, TC4};
+use hal::prelude::*;
+use hal::time::Microseconds;
+use hal::timer::{TimerCounter, TimerCounter3, TimerTick};
+use hal::usb_hid::HidKeyboard;
+
+use hal::usb::UsbBus;
+use usb_device::bus::UsbBusAllocator;
+use usb_device::prelude::*;
+
+use cortex_m::peripheral::NVIC;
+
+#[entry]
+fn main() -> ! {
+    let mut peripherals = Peripherals::take().unwrap();
+    let mut core = CorePeripherals::take().unwrap();
+    let mut clocks = GenericClockController::with_internal_32kosc(
+        peripherals.GCLK,
+        &mut peripherals.PM,
+        &mut peripherals.SYSCTRL,
+        &mut peripherals.NVMCTRL,
+    );
+    let pins = hal::Pins::new(peripherals.PORT);
+
+    let bus_allocator = unsafe {
+        USB_ALLOCATOR = Some(hal::usb_allocator(
+            peripherals.USB,
+            &mut clocks,
+            &mut peripherals.PM,
+            pins.usb_dm,
+            pins.usb_dp,
+        ));
+        USB_ALLOCATOR.as_ref().unwrap()
+    };
+
+    // Debounce the keys against TC4, counting at 48MHz / 64
+    let gclk0 = clocks.gclk0();
+    let tick_clock = clocks.tc4_tc5(&gclk0).unwrap();
+    let tick: Tick =
+        TimerCounter::tc4_(&tick_clock, peripherals.TC4, &mut peripherals.PM).into_tick();
+    let debounce = Microseconds::from_ticks(5_000);
+
+    let rows: [DynPin; 2] = [Pin::from(pins.d7).into(), Pin::from(pins.d9).into()];
+    let cols: [DynPin; 2] = [Pin::from(pins.d10).into(), Pin::from(pins.d11).into()];
+    let keypad = Keypad::new(rows, cols, Scanner::new::<Tick, _>(debounce));
+
+    // HID keyboard usage IDs: a, b / c, left shift
+    let keymap = [[0x04, 0x05], [0x06, 0xE1]];
+
+    unsafe {
+        KEYBOARD = Some(hal::usb_keyboard(
+            bus_allocator,
+            UsbVidPid(0x16c0, 0x27db),
+            keypad,
+            keymap,
+        ));
+        TICK = Some(tick);
+    }
+
+    // Scan the keypad once per millisecond.
+    let timer_clock = clocks.tcc2_tc3(&gclk0).unwrap();
+    let mut timer = TimerCounter::tc3_(&timer_clock, peripherals.TC3, &mut peripherals.PM);
+    timer.start(1.millis());
+    timer.enable_interrupt();
+    unsafe {
+        TIMER = Some(timer);
+    }
+
+    // Both interrupts touch the keyboard, so give them the same priority to
+    // prevent one from preempting the other.
+    unsafe {
+        core.NVIC.set_priority(interrupt::USB, 1);
+        NVIC::unmask(interrupt::USB);
+        core.NVIC.set_priority(interrupt::TC3, 1);
+        NVIC::unmask(interrupt::TC3);
+    }
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+type Tick = TimerTick<TC4, 750_000>;
+
+static mut USB_ALLOCATOR: Option<UsbBusAllocator<UsbBus>> = None;
+static mut KEYBOARD: Option<HidKeyboard<'static, UsbBus, 2, 2>> = None;
+static mut TICK: Option<Tick> = None;
+static mut TIMER: Option<TimerCounter3> = None;
+
+#[interrupt]
+fn USB() {
+    unsafe {
+        KEYBOARD.as_mut().map(|keyboard| keyboard.poll());
+    }
+}
+
+#[interrupt]
+fn TC3() {
+    // Acknowledge the timer, then scan the keypad.
+    unsafe {
+        TIMER.as_mut().map(|timer| timer.wait().ok());
+        let tick = TICK.as_ref().unwrap();
+        KEYBOARD.as_mut().map(|keyboard| keyboard.scan(tick).ok());
+    }
+}
@@ -0,0 +1,123 @@
+#![no_std]
+#![no_main]
+
+//! A USB mouse moved with switches.
+//!
+//! Wire switches to ground on D7 (up), D9 (down), D10 (left) and D11
+//! (right) to move the pointer, and on D12 and D5 for the left and right
+//! buttons.
+
+extern crate cortex_m;
+extern crate itsybitsy_m0 as hal;
+extern crate panic_halt;
+extern crate usb_device;
+
+use hal::clock::GenericClockController;
+use hal::entry;
+use hal::gpio::v2::{DynPin, Pin};
+use hal::pac::{interrupt, CorePeripherals, Peripherals};
+use hal::prelude::*;
+use hal::timer::{TimerCounter, TimerCounter3};
+use hal::usb_hid::{mouse_buttons, HidMouse};
+
+use hal::usb::UsbBus;
+use usb_device::bus::UsbBusAllocator;
+use usb_device::prelude::*;
+
+use cortex_m::peripheral::NVIC;
+
+/// Pointer movement per report, at 100 reports per second
+const SPEED: i8 = 4;
+
+#[entry]
+fn main() -> ! {
+    let mut peripherals = Peripherals::take().unwrap();
+    let mut core = CorePeripherals::take().unwrap();
+    let mut clocks = GenericClockController::with_internal_32kosc(
+        peripherals.GCLK,
+        &mut peripherals.PM,
+        &mut peripherals.SYSCTRL,
+        &mut peripherals.NVMCTRL,
+    );
+    let pins = hal::Pins::new(peripherals.PORT);
+
+    let bus_allocator = unsafe {
+        USB_ALLOCATOR = Some(hal::usb_allocator(
+            peripherals.USB,
+            &mut clocks,
+            &mut peripherals.PM,
+            pins.usb_dm,
+            pins.usb_dp,
+        ));
+        USB_ALLOCATOR.as_ref().unwrap()
+    };
+
+    let mut switches: [DynPin; 6] = [
+        Pin::from(pins.d7).into(),
+        Pin::from(pins.d9).into(),
+        Pin::from(pins.d10).into(),
+        Pin::from(pins.d11).into(),
+        Pin::from(pins.d12).into(),
+        Pin::from(pins.d5).into(),
+    ];
+    for switch in switches.iter_mut() {
+        switch.into_pull_up_input();
+    }
+
+    unsafe {
+        MOUSE = Some(hal::usb_mouse(bus_allocator, UsbVidPid(0x16c0, 0x27da)));
+        SWITCHES = Some(switches);
+    }
+
+    // Report the switches 100 times per second.
+    let gclk0 = clocks.gclk0();
+    let timer_clock = clocks.tcc2_tc3(&gclk0).unwrap();
+    let mut timer = TimerCounter::tc3_(&timer_clock, peripherals.TC3, &mut peripherals.PM);
+    timer.start(10.millis());
+    timer.enable_interrupt();
+    unsafe {
+        TIMER = Some(timer);
+    }
+
+    // Both interrupts touch the mouse, so give them the same priority to
+    // prevent one from preempting the other.
+    unsafe {
+        core.NVIC.set_priority(interrupt::USB, 1);
+        NVIC::unmask(interrupt::USB);
+        core.NVIC.set_priority(interrupt::TC3, 1);
+        NVIC::unmask(interrupt::TC3);
+    }
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+static mut USB_ALLOCATOR: Option<UsbBusAllocator<UsbBus>> = None;
+static mut MOUSE: Option<HidMouse<'static, UsbBus>> = None;
+static mut SWITCHES: Option<[DynPin; 6]> = None;
+static mut TIMER: Option<TimerCounter3> = None;
+
+#[interrupt]
+fn USB() {
+    unsafe {
+        MOUSE.as_mut().map(|mouse| mouse.poll());
+    }
+}
+
+#[interrupt]
+fn TC3() {
+    unsafe {
+        TIMER.as_mut().map(|timer| timer.wait().ok());
+        let switches = SWITCHES.as_ref().unwrap();
+        let pressed = |i: usize| switches[i].is_low().unwrap_or(false);
+        let axis = |minus: usize, plus: usize| match (pressed(minus), pressed(plus)) {
+            (true, false) => -SPEED,
+            (false, true) => SPEED,
+            _ => 0,
+        };
+        let (x, y) = (axis(2, 3), axis(0, 1));
+        let buttons = mouse_buttons(&switches[4..]).unwrap_or(0);
+        MOUSE.as_mut().map(|mouse| mouse.report(x, y, buttons).ok());
+    }
+}
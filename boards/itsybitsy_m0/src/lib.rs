@@ -24,10 +24,16 @@ use hal::time::Hertz;
 
 #[cfg(feature = "usb")]
 use gpio::v2::{AnyPin, PA24, PA25};
+#[cfg(feature = "keyboard")]
+use hal::keypad::Keypad;
 #[cfg(feature = "usb")]
 use hal::usb::usb_device::bus::UsbBusAllocator;
+#[cfg(feature = "keyboard")]
+use hal::usb::usb_device::prelude::UsbVidPid;
 #[cfg(feature = "usb")]
 pub use hal::usb::UsbBus;
+#[cfg(feature = "keyboard")]
+use hal::usb_hid::{HidKeyboard, HidMouse};
 
 /// Convenience for setting up the externally labelled SPI.
 /// This powers up SERCOM4 and configures it for use as an
@@ -87,7 +93,7 @@ pub fn flash_spi_master(
     let gclk0 = clocks.gclk0();
     let flash = SPIMaster5::new(
         &clocks.sercom5_core(&gclk0).unwrap(),
-        48.mhz(),
+        48.MHz(),
         hal::hal::spi::Mode {
             phase: hal::hal::spi::Phase::CaptureOnFirstTransition,
             polarity: hal::hal::spi::Polarity::IdleLow,
@@ -171,3 +177,31 @@ pub fn usb_allocator(
 
     UsbBusAllocator::new(UsbBus::new(usb_clock, pm, dm, dp, usb))
 }
+
+/// Convenience for setting up a USB keyboard scanning `keypad`, with the HID
+/// usage ID of each key in `keymap`. See [`usb_hid`](hal::usb_hid).
+#[cfg(feature = "keyboard")]
+pub fn usb_keyboard<const R: usize, const C: usize>(
+    bus_allocator: &UsbBusAllocator<UsbBus>,
+    vid_pid: UsbVidPid,
+    keypad: Keypad<R, C>,
+    keymap: [[u8; C]; R],
+) -> HidKeyboard<'_, UsbBus, R, C> {
+    HidKeyboard::new(
+        bus_allocator,
+        vid_pid,
+        "Adafruit",
+        "ItsyBitsy M0 Keyboard",
+        keypad,
+        keymap,
+    )
+}
+
+/// Convenience for setting up a USB mouse. See [`usb_hid`](hal::usb_hid).
+#[cfg(feature = "keyboard")]
+pub fn usb_mouse(
+    bus_allocator: &UsbBusAllocator<UsbBus>,
+    vid_pid: UsbVidPid,
+) -> HidMouse<'_, UsbBus> {
+    HidMouse::new(bus_allocator, vid_pid, "Adafruit", "ItsyBitsy M0 Mouse")
+}
@@ -11,6 +11,7 @@
 /// $> sudo bash -c "echo 'G' > /dev/ttyACM0"
 /// $> sudo bash -c "echo 'O' > /dev/ttyACM0"
 extern crate itsybitsy_m4 as hal;
+#[cfg(not(feature = "panic-usb"))]
 extern crate panic_halt;
 
 use hal::clock::GenericClockController;
@@ -27,7 +28,7 @@ use usb_device::prelude::*;
 use usbd_serial::{SerialPort, USB_CLASS_CDC};
 
 use hal::dbgprint;
-use hal::time::Hertz;
+use hal::time::RateExtU32;
 use hal::uart;
 
 use hal::timer::SpinTimer;
@@ -54,7 +55,7 @@ fn main() -> ! {
     uart(
         pins.uart,
         &mut clocks,
-        Hertz(115200),
+        115200.Hz(),
         peripherals.SERCOM3,
         &mut peripherals.MCLK,
         &mut pins.port,
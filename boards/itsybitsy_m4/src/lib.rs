@@ -22,6 +22,12 @@ use hal::clock::GenericClockController;
 use hal::gpio::*;
 use hal::sercom::{I2CMaster2, SPIMaster1, UART3};
 use hal::time::Hertz;
+#[cfg(feature = "usb-midi")]
+use hal::usb::usb_device::{bus::UsbBusAllocator, prelude::UsbVidPid};
+#[cfg(feature = "usb-midi")]
+use hal::usb::UsbBus;
+#[cfg(feature = "usb-midi")]
+use hal::usb_midi::UsbMidi;
 
 /// Convenience for setting up the labelled SPI peripheral.
 /// This powers up SERCOM1 and configures it for use as an
@@ -53,6 +59,15 @@ pub fn dotstar_bitbang<T: CountDown + Periodic>(
     pins.dotstar(port, timer)
 }
 
+/// Convenience for setting up the dotstar LED with the HAL helper, which
+/// bit-bangs it without a timer.
+pub fn dotstar(
+    pins: pins::Dotstar,
+    port: &mut Port,
+) -> hal::dotstar::DotStar<hal::dotstar::BitBang<Pb2<Output<PushPull>>, Pb3<Output<PushPull>>>> {
+    pins.led(port)
+}
+
 /// Convenience for setting up the labelled SDA, SCL pins to
 /// operate as an I2C master running at the specified frequency.
 pub fn i2c_master<F: Into<Hertz>>(
@@ -84,3 +99,14 @@ pub fn uart<F: Into<Hertz>>(
 > {
     pins.uart(clocks, baud, sercom3, mclk, port)
 }
+
+/// Convenience for setting up a USB MIDI device with queues of `N` events,
+/// at least 16. Poll it from the `USB_OTHER`, `USB_TRCPT0` and `USB_TRCPT1`
+/// interrupts, see [`usb_midi`](hal::usb_midi).
+#[cfg(feature = "usb-midi")]
+pub fn usb_midi<const N: usize>(
+    bus_allocator: &UsbBusAllocator<UsbBus>,
+    vid_pid: UsbVidPid,
+) -> UsbMidi<'_, UsbBus, N> {
+    UsbMidi::new(bus_allocator, vid_pid, "Adafruit", "ItsyBitsy M4")
+}
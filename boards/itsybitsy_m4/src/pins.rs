@@ -11,6 +11,7 @@ use hal::time::Hertz;
 use hal::clock::GenericClockController;
 
 use apa102_spi::Apa102;
+use hal::dotstar::{BitBang, DotStar};
 
 #[cfg(feature = "usb")]
 use hal::usb::usb_device::bus::UsbBusAllocator;
@@ -355,6 +356,16 @@ impl Dotstar {
         let spi = bitbang_hal::spi::SPI::new(apa102_spi::MODE, nc, di, ci, timer);
         Apa102::new_with_custom_postamble(spi, 4, false)
     }
+
+    /// Sets up the DotStar with the HAL helper, bit-banged without a timer
+    pub fn led(
+        self,
+        port: &mut Port,
+    ) -> DotStar<BitBang<Pb2<Output<PushPull>>, Pb3<Output<PushPull>>>> {
+        let ci = self.ci.into_push_pull_output(port);
+        let di = self.di.into_push_pull_output(port);
+        DotStar::new(BitBang::new(ci, di))
+    }
 }
 
 /// QSPI flash pins
@@ -0,0 +1,90 @@
+#![no_std]
+#![no_main]
+
+//! Draws colour gradients on a 64x32 HUB75 panel plugged in the matrix
+//! connector, refreshed by the DMAC while the CPU sleeps.
+
+use matrix_portal_m4::{self as hal, entry, pac, Hub75, Pins};
+use panic_halt as _;
+
+use cortex_m::peripheral::NVIC;
+use hal::clock::GenericClockController;
+use hal::dmac::{Ch0, DmaController, PriorityLevel};
+use hal::matrix::{self, Matrix, FRAME_LEN, HEIGHT, WIDTH};
+use hal::timer::TimerCounter;
+use pac::{interrupt, Peripherals};
+
+static mut FRAME: [u8; FRAME_LEN] = [0; FRAME_LEN];
+static mut MATRIX: Option<Matrix<Ch0>> = None;
+
+#[entry]
+fn main() -> ! {
+    let mut peripherals = Peripherals::take().unwrap();
+    let mut clocks = GenericClockController::with_external_32kosc(
+        peripherals.GCLK,
+        &mut peripherals.MCLK,
+        &mut peripherals.OSC32KCTRL,
+        &mut peripherals.OSCCTRL,
+        &mut peripherals.NVMCTRL,
+    );
+    let mut pins = Pins::new(peripherals.PORT);
+
+    let gclk0 = clocks.gclk0();
+    let timer_clock = clocks.tc2_tc3(&gclk0).unwrap();
+    let timer = TimerCounter::tc2_(&timer_clock, peripherals.TC2, &mut peripherals.MCLK);
+
+    let mut dmac = DmaController::init(peripherals.DMAC, &mut peripherals.PM);
+    let channels = dmac.split();
+    let channel = channels.0.init(PriorityLevel::LVL0);
+
+    let hub75 = Hub75 {
+        r1: pins.r1,
+        g1: pins.g1,
+        b1: pins.b1,
+        r2: pins.r2,
+        g2: pins.g2,
+        b2: pins.b2,
+        clk: pins.clk,
+        addr_a: pins.addr_a,
+        addr_b: pins.addr_b,
+        addr_c: pins.addr_c,
+        addr_d: pins.addr_d,
+        addr_e: pins.addr_e,
+        lat: pins.lat,
+        oe: pins.oe,
+    };
+    let mut matrix = matrix::new(
+        hub75,
+        &mut pins.port,
+        unsafe { &mut FRAME },
+        channel,
+        timer,
+        16,
+    );
+
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let r = (x * 255 / (WIDTH - 1)) as u8;
+            let b = (y * 255 / (HEIGHT - 1)) as u8;
+            matrix.frame().set_pixel(x, y, r, 255 - r, b);
+        }
+    }
+
+    unsafe {
+        MATRIX = Some(matrix);
+        NVIC::unmask(interrupt::DMAC_0);
+    }
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[interrupt]
+fn DMAC_0() {
+    unsafe {
+        if let Some(matrix) = MATRIX.as_mut() {
+            matrix.on_interrupt();
+        }
+    }
+}
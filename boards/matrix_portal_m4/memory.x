@@ -0,0 +1,7 @@
+MEMORY
+{
+  /* Leave 16k for the UF2 bootloader on the Adafruit Matrix Portal M4 */
+  FLASH (rx) : ORIGIN = 0x00000000 + 16K, LENGTH = 512K - 16K
+  RAM (xrw)  : ORIGIN = 0x20000000, LENGTH = 192K
+}
+_stack_start = ORIGIN(RAM) + LENGTH(RAM);
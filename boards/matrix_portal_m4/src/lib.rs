@@ -0,0 +1,296 @@
+#![no_std]
+#![recursion_limit = "1024"]
+
+extern crate atsamd_hal as hal;
+
+#[cfg(feature = "rt")]
+extern crate cortex_m_rt;
+#[cfg(feature = "rt")]
+pub use cortex_m_rt::entry;
+
+use hal::prelude::*;
+use hal::*;
+
+pub use hal::common::*;
+pub use hal::samd51::*;
+pub use hal::target_device as pac;
+
+use gpio::{Floating, Input, Output, PfC, PfD, Port, PushPull};
+use hal::clock::GenericClockController;
+use hal::sercom::{I2CMaster5, PadPin, SPIMaster3, UART2};
+use hal::time::Hertz;
+
+#[cfg(feature = "usb")]
+use gpio::v2::{AnyPin, PA24, PA25};
+#[cfg(feature = "usb")]
+use hal::usb::usb_device::bus::UsbBusAllocator;
+#[cfg(feature = "usb")]
+pub use hal::usb::UsbBus;
+
+#[cfg(feature = "dma")]
+pub mod matrix;
+
+define_pins!(
+    /// Maps the pins to their arduino names and
+    /// the labels printed on the board.
+    struct Pins,
+    target_device: target_device,
+
+    /// Analog pin 0
+    pin a0 = a2,
+    /// Analog Pin 1
+    pin a1 = a5,
+    /// Analog Pin 2
+    pin a2 = a4,
+    /// Analog Pin 3
+    pin a3 = a6,
+    /// Analog Pin 4
+    pin a4 = a7,
+
+    /// Pin 0, rx
+    pin rx = a0,
+    /// Pin 1, tx
+    pin tx = a1,
+
+    /// The red LED labelled L
+    pin led = a14,
+    /// Neopixel Pin
+    pin neopixel = a23,
+
+    /// The UP button, which pulls the pin low
+    pin button_up = b22,
+    /// The DOWN button, which pulls the pin low
+    pin button_down = b23,
+
+    /// The I2C data line, also on the STEMMA QT connector
+    pin sda = b31,
+    /// The I2C clock line, also on the STEMMA QT connector
+    pin scl = b30,
+    /// Interrupt line of the LIS3DH accelerometer
+    pin accel_int = a27,
+
+    /// The SPI SCK, shared with the ESP32
+    pin sck = a16,
+    /// The SPI MOSI, shared with the ESP32
+    pin mosi = a19,
+    /// The SPI MISO, shared with the ESP32
+    pin miso = a17,
+
+    /// Chip select of the ESP32
+    pin esp_cs = b17,
+    /// Busy line of the ESP32, high while it can't take a command
+    pin esp_busy = a22,
+    /// Reset line of the ESP32, active low
+    pin esp_reset = a21,
+    /// GPIO0 of the ESP32, which selects its bootloader on reset while low
+    pin esp_gpio0 = a20,
+    /// UART TX to the ESP32
+    pin esp_tx = a12,
+    /// UART RX from the ESP32
+    pin esp_rx = a13,
+
+    /// HUB75 upper half red line
+    pin r1 = b0,
+    /// HUB75 upper half green line
+    pin g1 = b1,
+    /// HUB75 upper half blue line
+    pin b1 = b2,
+    /// HUB75 lower half red line
+    pin r2 = b3,
+    /// HUB75 lower half green line
+    pin g2 = b4,
+    /// HUB75 lower half blue line
+    pin b2 = b5,
+    /// HUB75 clock
+    pin clk = b6,
+    /// HUB75 row address A
+    pin addr_a = b7,
+    /// HUB75 row address B
+    pin addr_b = b8,
+    /// HUB75 row address C
+    pin addr_c = b9,
+    /// HUB75 row address D
+    pin addr_d = b15,
+    /// HUB75 row address E, only used by panels of 64 rows
+    pin addr_e = b13,
+    /// HUB75 latch
+    pin lat = b14,
+    /// HUB75 output enable, active low
+    pin oe = b12,
+
+    /// The USB D- pad
+    pin usb_dm = a24,
+    /// The USB D+ pad
+    pin usb_dp = a25,
+);
+
+/// The pins of the HUB75 connector.
+///
+/// The colour lines, the clock and address A are PB00 to PB07, so that they
+/// can all be written at once by the low byte of the PORT B `OUT` register.
+pub struct Hub75 {
+    pub r1: gpio::Pb0<Input<Floating>>,
+    pub g1: gpio::Pb1<Input<Floating>>,
+    pub b1: gpio::Pb2<Input<Floating>>,
+    pub r2: gpio::Pb3<Input<Floating>>,
+    pub g2: gpio::Pb4<Input<Floating>>,
+    pub b2: gpio::Pb5<Input<Floating>>,
+    pub clk: gpio::Pb6<Input<Floating>>,
+    pub addr_a: gpio::Pb7<Input<Floating>>,
+    pub addr_b: gpio::Pb8<Input<Floating>>,
+    pub addr_c: gpio::Pb9<Input<Floating>>,
+    pub addr_d: gpio::Pb15<Input<Floating>>,
+    pub addr_e: gpio::Pb13<Input<Floating>>,
+    pub lat: gpio::Pb14<Input<Floating>>,
+    pub oe: gpio::Pb12<Input<Floating>>,
+}
+
+/// The control lines of the ESP32 WiFi co-processor, which is an SPI device
+/// on the labelled SPI bus
+pub struct Esp32 {
+    /// Chip select, high while deselected
+    pub cs: gpio::Pb17<Output<PushPull>>,
+    /// Busy line
+    pub busy: gpio::Pa22<Input<Floating>>,
+    /// Reset line, high while running
+    pub reset: gpio::Pa21<Output<PushPull>>,
+    /// GPIO0, high to boot the firmware
+    pub gpio0: gpio::Pa20<Output<PushPull>>,
+}
+
+impl Esp32 {
+    /// Resets the ESP32 into its firmware. It takes about a second before
+    /// it answers commands.
+    pub fn reset<D: hal::hal::blocking::delay::DelayMs<u16>>(&mut self, delay: &mut D) {
+        self.gpio0.set_high().unwrap();
+        self.cs.set_high().unwrap();
+        self.reset.set_low().unwrap();
+        delay.delay_ms(10);
+        self.reset.set_high().unwrap();
+        delay.delay_ms(750);
+    }
+}
+
+/// Convenience for setting up the control lines of the ESP32, which is left
+/// in reset
+pub fn esp32(
+    cs: gpio::Pb17<Input<Floating>>,
+    busy: gpio::Pa22<Input<Floating>>,
+    reset: gpio::Pa21<Input<Floating>>,
+    gpio0: gpio::Pa20<Input<Floating>>,
+    port: &mut Port,
+) -> Esp32 {
+    let mut cs = cs.into_push_pull_output(port);
+    cs.set_high().unwrap();
+    let mut reset = reset.into_push_pull_output(port);
+    reset.set_low().unwrap();
+    let mut gpio0 = gpio0.into_push_pull_output(port);
+    gpio0.set_high().unwrap();
+    Esp32 {
+        cs,
+        busy,
+        reset,
+        gpio0,
+    }
+}
+
+/// Convenience for setting up the labelled SPI peripheral.
+/// This powers up SERCOM3 and configures it for use as an
+/// SPI Master in SPI Mode 0.
+pub fn spi_master<F: Into<Hertz>>(
+    clocks: &mut GenericClockController,
+    bus_speed: F,
+    sercom3: pac::SERCOM3,
+    mclk: &mut pac::MCLK,
+    sck: gpio::Pa16<Input<Floating>>,
+    mosi: gpio::Pa19<Input<Floating>>,
+    miso: gpio::Pa17<Input<Floating>>,
+    port: &mut Port,
+) -> SPIMaster3<
+    hal::sercom::Sercom3Pad0<gpio::Pa17<PfD>>,
+    hal::sercom::Sercom3Pad3<gpio::Pa19<PfD>>,
+    hal::sercom::Sercom3Pad1<gpio::Pa16<PfD>>,
+> {
+    let gclk0 = clocks.gclk0();
+    SPIMaster3::new(
+        &clocks.sercom3_core(&gclk0).unwrap(),
+        bus_speed.into(),
+        hal::hal::spi::Mode {
+            phase: hal::hal::spi::Phase::CaptureOnFirstTransition,
+            polarity: hal::hal::spi::Polarity::IdleLow,
+        },
+        sercom3,
+        mclk,
+        (miso.into_pad(port), mosi.into_pad(port), sck.into_pad(port)),
+    )
+}
+
+/// I2C address of the LIS3DH accelerometer
+pub const ACCELEROMETER_ADDRESS: u8 = 0x19;
+
+/// Convenience for setting up the labelled SDA, SCL pins to
+/// operate as an I2C master running at the specified frequency.
+pub fn i2c_master<F: Into<Hertz>>(
+    clocks: &mut GenericClockController,
+    bus_speed: F,
+    sercom5: pac::SERCOM5,
+    mclk: &mut pac::MCLK,
+    sda: gpio::Pb31<Input<Floating>>,
+    scl: gpio::Pb30<Input<Floating>>,
+    port: &mut Port,
+) -> I2CMaster5<hal::sercom::Sercom5Pad0<gpio::Pb31<PfD>>, hal::sercom::Sercom5Pad1<gpio::Pb30<PfD>>>
+{
+    let gclk0 = clocks.gclk0();
+    I2CMaster5::new(
+        &clocks.sercom5_core(&gclk0).unwrap(),
+        bus_speed.into(),
+        sercom5,
+        mclk,
+        sda.into_pad(port),
+        scl.into_pad(port),
+    )
+}
+
+/// Convenience for setting up the UART to the ESP32, e.g. to pass its
+/// firmware updates through
+pub fn esp32_uart<F: Into<Hertz>>(
+    clocks: &mut GenericClockController,
+    baud: F,
+    sercom2: pac::SERCOM2,
+    mclk: &mut pac::MCLK,
+    esp_rx: gpio::Pa13<Input<Floating>>,
+    esp_tx: gpio::Pa12<Input<Floating>>,
+    port: &mut Port,
+) -> UART2<
+    hal::sercom::Sercom2Pad1<gpio::Pa13<PfC>>,
+    hal::sercom::Sercom2Pad0<gpio::Pa12<PfC>>,
+    (),
+    (),
+> {
+    let gclk0 = clocks.gclk0();
+
+    UART2::new(
+        &clocks.sercom2_core(&gclk0).unwrap(),
+        baud.into(),
+        sercom2,
+        mclk,
+        (esp_rx.into_pad(port), esp_tx.into_pad(port)),
+    )
+}
+
+#[cfg(feature = "usb")]
+pub fn usb_allocator(
+    dm: impl AnyPin<Id = PA24>,
+    dp: impl AnyPin<Id = PA25>,
+    usb: pac::USB,
+    clocks: &mut GenericClockController,
+    mclk: &mut pac::MCLK,
+) -> UsbBusAllocator<UsbBus> {
+    use pac::gclk::{genctrl::SRC_A, pchctrl::GEN_A};
+
+    clocks.configure_gclk_divider_and_source(GEN_A::GCLK2, 1, SRC_A::DFLL, false);
+    let usb_gclk = clocks.get_gclk(GEN_A::GCLK2).unwrap();
+    let usb_clock = &clocks.usb(&usb_gclk).unwrap();
+
+    UsbBusAllocator::new(UsbBus::new(usb_clock, mclk, dm, dp, usb))
+}
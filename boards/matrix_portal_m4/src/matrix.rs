@@ -0,0 +1,91 @@
+//! DMA refresh of a HUB75 RGB LED matrix
+//!
+//! The colour lines, the clock and address A of the HUB75 connector are PB00
+//! to PB07, so the low byte of the PORT B `OUT` register drives them all at
+//! once with the [`hub75`](hal::hub75) driver. The DMAC is paced by TC2:
+//!
+//! ```
+//! static mut FRAME: [u8; FRAME_LEN] = [0; FRAME_LEN];
+//!
+//! let timer = TimerCounter::tc2_(&timer_clock, peripherals.TC2, &mut peripherals.MCLK);
+//! let mut matrix = matrix::new(hub75, &mut pins.port, unsafe { &mut FRAME }, channels.0, timer, 16);
+//! matrix.frame().set_pixel(0, 0, 255, 0, 0);
+//!
+//! #[interrupt]
+//! fn DMAC_0() {
+//!     matrix.on_interrupt();
+//! }
+//! ```
+
+use crate::pac;
+use crate::Hub75;
+
+use hal::dmac::{ChId, Channel, Ready};
+use hal::gpio::{self, Output, Port, PushPull};
+use hal::hal::digital::v2::OutputPin;
+use hal::hub75::{self, buffer_len, FrameBuffer, Layout, OutByte, RowPins};
+use hal::timer::TimerCounter;
+
+/// Width of the panel, in pixels
+pub const WIDTH: usize = 64;
+/// Height of the panel, in pixels
+pub const HEIGHT: usize = 32;
+/// Length of the frame buffer of one panel
+pub const FRAME_LEN: usize = buffer_len(WIDTH, HEIGHT, 1);
+
+/// The lines of the connector in the low byte of PORT B `OUT`
+pub const LAYOUT: Layout = Layout {
+    rgb1: [1 << 0, 1 << 1, 1 << 2],
+    rgb2: [1 << 3, 1 << 4, 1 << 5],
+    clk: 1 << 6,
+    addr: [1 << 7, 0, 0, 0, 0],
+};
+
+/// The row lines of the connector
+pub type Rows = RowPins<
+    gpio::Pb7<Output<PushPull>>,
+    gpio::Pb8<Output<PushPull>>,
+    gpio::Pb9<Output<PushPull>>,
+    gpio::Pb15<Output<PushPull>>,
+    gpio::Pb13<Output<PushPull>>,
+    gpio::Pb14<Output<PushPull>>,
+    gpio::Pb12<Output<PushPull>>,
+>;
+
+/// A 64x32 HUB75 panel refreshed by the DMAC
+pub type Matrix<Id> = hub75::Matrix<Id, pac::TC2, Rows>;
+
+/// Starts refreshing a 64x32 panel from `frame`, see
+/// [`hub75::Matrix::new`]
+pub fn new<Id: ChId>(
+    pins: Hub75,
+    port: &mut Port,
+    frame: &'static mut [u8; FRAME_LEN],
+    channel: Channel<Id, Ready>,
+    timer: TimerCounter<pac::TC2>,
+    ticks: u8,
+) -> Matrix<Id> {
+    let mut oe = pins.oe.into_push_pull_output(port);
+    oe.set_high().ok();
+    let rows = RowPins {
+        addr_a: pins.addr_a.into_push_pull_output(port),
+        addr_b: pins.addr_b.into_push_pull_output(port),
+        addr_c: pins.addr_c.into_push_pull_output(port),
+        addr_d: pins.addr_d.into_push_pull_output(port),
+        addr_e: pins.addr_e.into_push_pull_output(port),
+        lat: pins.lat.into_push_pull_output(port),
+        oe,
+    };
+    pins.r1.into_push_pull_output(port);
+    pins.g1.into_push_pull_output(port);
+    pins.b1.into_push_pull_output(port);
+    pins.r2.into_push_pull_output(port);
+    pins.g2.into_push_pull_output(port);
+    pins.b2.into_push_pull_output(port);
+    pins.clk.into_push_pull_output(port);
+
+    let frame = FrameBuffer::new(frame, WIDTH, HEIGHT, 1, LAYOUT);
+    // SAFETY: The pins of PORT B are only changed through OUTSET and OUTCLR
+    let out = unsafe { OutByte::new(&(*pac::PORT::ptr()).group1, 0) };
+    hub75::Matrix::new(frame, out, rows, channel, timer.into_8bit(), ticks)
+}
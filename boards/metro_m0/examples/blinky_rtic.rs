@@ -66,7 +66,7 @@ const APP: () = {
             &mut device.PM,
         );
         dbgprint!("start timer");
-        tc3.start(1.hz());
+        tc3.start(1.secs());
         tc3.enable_interrupt();
 
         dbgprint!("done init");
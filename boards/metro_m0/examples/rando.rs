@@ -78,7 +78,7 @@ const APP: () = {
 
     #[init]
     fn init(c: init::Context) -> init::LateResources {
-        let interval = 1.hz();
+        let interval = 1.secs();
 
         let mut device = c.device;
         let core = CorePeripherals::take().unwrap();
@@ -116,7 +116,7 @@ const APP: () = {
         /*
             let mut spi = SPIMaster4::new(
                 &clocks.sercom4_core(&gclk0).unwrap(),
-                24.mhz(),
+                24.MHz(),
                 hal::hal::spi::Mode {
                     phase: hal::hal::spi::Phase::CaptureOnFirstTransition,
                     polarity: hal::hal::spi::Polarity::IdleLow,
@@ -180,7 +180,7 @@ const APP: () = {
 
         let mut i2c = hal::i2c_master(
             &mut clocks,
-            400.khz(),
+            400.kHz(),
             device.SERCOM3,
             &mut device.PM,
             pins.sda,
@@ -2,6 +2,7 @@
 #![no_main]
 
 extern crate metro_m0 as hal;
+#[cfg(not(any(feature = "panic-usb", feature = "panic-watchdog")))]
 extern crate panic_halt as _;
 
 use hal::clock::GenericClockController;
@@ -162,7 +162,7 @@ pub fn flash_spi_master(
     let gclk0 = clocks.gclk0();
     let flash = SPIMaster5::new(
         &clocks.sercom5_core(&gclk0).unwrap(),
-        48.mhz(),
+        48.MHz(),
         hal::hal::spi::Mode {
             phase: hal::hal::spi::Phase::CaptureOnFirstTransition,
             polarity: hal::hal::spi::Polarity::IdleLow,
@@ -0,0 +1,133 @@
+//! Measures how fast a pin toggles through the different GPIO paths.
+//!
+//! The SysTick counts the core clock cycles taken by 32 back-to-back
+//! writes through each path, and the results are printed over
+//! semihosting. The LED pin then toggles through the fast path forever,
+//! so that the frequency can be checked with a scope or a logic analyser.
+//!
+//! The Cortex-M4 of the 120MHz SAMD51 has no single-cycle IOBUS, so every
+//! path goes through the APB bridge, and each write takes a few cycles. The
+//! fast path saves the overhead around the write, and always compiles down
+//! to a single store.
+
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+extern crate cortex_m_semihosting;
+extern crate metro_m4 as hal;
+#[cfg(not(feature = "use_semihosting"))]
+extern crate panic_halt;
+#[cfg(feature = "use_semihosting")]
+extern crate panic_semihosting;
+
+use cortex_m::peripheral::syst::SystClkSource;
+use cortex_m::peripheral::SYST;
+use cortex_m_semihosting::hprintln;
+use hal::clock::GenericClockController;
+use hal::entry;
+use hal::gpio::v2::{write_masked, DynGroup, Pin, PushPullOutput, PA16};
+use hal::pac::{CorePeripherals, Peripherals};
+use hal::prelude::*;
+use hal::time::Hertz;
+
+/// Number of writes per measurement
+const WRITES: u32 = 32;
+
+/// Repeats a statement 8 times
+macro_rules! repeat8 {
+    ($e:expr) => {{
+        $e;
+        $e;
+        $e;
+        $e;
+        $e;
+        $e;
+        $e;
+        $e;
+    }};
+}
+
+/// Repeats a statement 32 times, to measure it without the loop overhead
+macro_rules! repeat32 {
+    ($e:expr) => {{
+        repeat8!($e);
+        repeat8!($e);
+        repeat8!($e);
+        repeat8!($e);
+    }};
+}
+
+/// Returns the number of core clock cycles taken by `f`
+fn cycles(f: impl FnOnce()) -> u32 {
+    let start = SYST::get_current();
+    f();
+    let end = SYST::get_current();
+    // The SysTick counts down
+    start.wrapping_sub(end) & 0x00ff_ffff
+}
+
+fn report(name: &str, cycles: u32, hz: u32) {
+    // Each write is half a period of the square wave
+    let toggle_hz = hz / 2 * WRITES / cycles;
+    hprintln!(
+        "{}: {} cycles for {} writes, {} Hz",
+        name,
+        cycles,
+        WRITES,
+        toggle_hz
+    )
+    .ok();
+}
+
+#[entry]
+fn main() -> ! {
+    let mut peripherals = Peripherals::take().unwrap();
+    let mut core = CorePeripherals::take().unwrap();
+    let mut clocks = GenericClockController::with_external_32kosc(
+        peripherals.GCLK,
+        &mut peripherals.MCLK,
+        &mut peripherals.OSC32KCTRL,
+        &mut peripherals.OSCCTRL,
+        &mut peripherals.NVMCTRL,
+    );
+    let hz: Hertz = clocks.gclk0().into();
+    let mut pins = hal::Pins::new(peripherals.PORT);
+    let mut led: Pin<PA16, PushPullOutput> = pins.d13.into_push_pull_output(&mut pins.port).into();
+
+    core.SYST.set_clock_source(SystClkSource::Core);
+    core.SYST.set_reload(0x00ff_ffff);
+    core.SYST.clear_current();
+    core.SYST.enable_counter();
+
+    let toggle = cycles(|| repeat32!(led.toggle().unwrap()));
+    report("toggle", toggle, hz.to_Hz());
+    let set = cycles(|| {
+        repeat32!({
+            led.set_high().unwrap();
+            led.set_low().unwrap();
+        })
+    }) / 2;
+    report("set_high/set_low", set, hz.to_Hz());
+    let toggle_fast = cycles(|| repeat32!(led.toggle_fast()));
+    report("toggle_fast", toggle_fast, hz.to_Hz());
+    let set_fast = cycles(|| {
+        repeat32!({
+            led.set_high_fast();
+            led.set_low_fast();
+        })
+    }) / 2;
+    report("set_high_fast/set_low_fast", set_fast, hz.to_Hz());
+    // Only one of the two stores of each masked write changes the pin
+    let masked = cycles(|| {
+        repeat32!(unsafe {
+            write_masked(DynGroup::A, 1 << 16, 1 << 16);
+            write_masked(DynGroup::A, 1 << 16, 0);
+        })
+    }) / 2;
+    report("write_masked", masked, hz.to_Hz());
+
+    loop {
+        repeat32!(led.toggle_fast());
+    }
+}
@@ -0,0 +1,48 @@
+#![no_std]
+#![no_main]
+
+//! Runs the Metro M4 at 200MHz, and blinks the red LED.
+//!
+//! This is outside of the specification of the SAMD51. Build with
+//! `--features overclock`.
+
+extern crate cortex_m;
+extern crate metro_m4 as hal;
+extern crate panic_halt;
+
+use hal::clock::{GenericClockController, OutOfSpec, Overclock};
+use hal::delay::Delay;
+use hal::entry;
+use hal::pac::{CorePeripherals, Peripherals};
+use hal::prelude::*;
+
+#[entry]
+fn main() -> ! {
+    let mut peripherals = Peripherals::take().unwrap();
+    let core = CorePeripherals::take().unwrap();
+    let mut clocks = GenericClockController::with_external_32kosc(
+        peripherals.GCLK,
+        &mut peripherals.MCLK,
+        &mut peripherals.OSC32KCTRL,
+        &mut peripherals.OSCCTRL,
+        &mut peripherals.NVMCTRL,
+    );
+    clocks.overclock(
+        Overclock::Mhz200,
+        &mut peripherals.NVMCTRL,
+        &mut peripherals.OSCCTRL,
+        &mut peripherals.SUPC,
+        unsafe { OutOfSpec::acknowledge() },
+    );
+
+    let mut pins = hal::Pins::new(peripherals.PORT);
+    let mut red_led = pins.d13.into_open_drain_output(&mut pins.port);
+    // Created after overclocking, so that it counts at 200MHz
+    let mut delay = Delay::new(core.SYST, &mut clocks);
+    loop {
+        delay.delay_ms(200u8);
+        red_led.set_high().unwrap();
+        delay.delay_ms(200u8);
+        red_led.set_low().unwrap();
+    }
+}
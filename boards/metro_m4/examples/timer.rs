@@ -9,6 +9,7 @@ extern crate panic_halt;
 
 use crate::hal::clock::GenericClockController;
 use crate::hal::pac::Peripherals;
+use crate::hal::time::Hertz;
 use crate::hal::timer::TimerCounter;
 use cortex_m_rt::entry;
 use hal::prelude::*;
@@ -29,7 +30,7 @@ fn main() -> ! {
     let gclk0 = clocks.gclk0();
     let timer_clock = clocks.tc2_tc3(&gclk0).unwrap();
     let mut timer = TimerCounter::tc3_(&timer_clock, peripherals.TC3, &mut peripherals.MCLK);
-    timer.start(9600u32.hz());
+    timer.start(Hertz::Hz(9600).into_duration());
     let mut d0 = pins.d0.into_push_pull_output(&mut pins.port);
     loop {
         d0.set_high().unwrap();
@@ -3,6 +3,7 @@
 
 extern crate cortex_m;
 extern crate metro_m4 as hal;
+#[cfg(not(feature = "panic-usb"))]
 extern crate panic_halt;
 extern crate usb_device;
 extern crate usbd_serial;
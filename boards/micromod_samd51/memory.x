@@ -0,0 +1,7 @@
+MEMORY
+{
+  /* Leave 16k for the UF2 bootloader on the SparkFun MicroMod SAMD51 Processor */
+  FLASH (rx) : ORIGIN = 0x00000000 + 16K, LENGTH = 1024K - 16K
+  RAM (xrw)  : ORIGIN = 0x20000000, LENGTH = 256K
+}
+_stack_start = ORIGIN(RAM) + LENGTH(RAM);
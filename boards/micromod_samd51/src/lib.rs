@@ -0,0 +1,223 @@
+#![no_std]
+#![recursion_limit = "1024"]
+
+extern crate atsamd_hal as hal;
+
+#[cfg(feature = "rt")]
+extern crate cortex_m_rt;
+#[cfg(feature = "rt")]
+pub use cortex_m_rt::entry;
+
+use hal::prelude::*;
+use hal::*;
+
+pub use hal::common::*;
+pub use hal::samd51::*;
+pub use hal::target_device as pac;
+
+use gpio::{Floating, Input, PfC, PfD, Port};
+use hal::clock::GenericClockController;
+use hal::hal::blocking::i2c::WriteRead;
+use hal::sercom::{I2CMaster2, PadPin, SPIMaster0, UART1};
+use hal::time::Hertz;
+
+#[cfg(feature = "usb")]
+use gpio::v2::{AnyPin, PA24, PA25};
+#[cfg(feature = "usb")]
+use hal::usb::usb_device::bus::UsbBusAllocator;
+#[cfg(feature = "usb")]
+pub use hal::usb::UsbBus;
+
+define_pins!(
+    /// Maps the pins to the names of the MicroMod connector
+    struct Pins,
+    target_device: target_device,
+
+    /// Analog pin 0
+    pin a0 = a2,
+    /// Analog pin 1
+    pin a1 = b0,
+    /// PWM pin 0
+    pin pwm0 = b1,
+    /// PWM pin 1
+    pin pwm1 = b2,
+    /// Digital pin 0
+    pin d0 = b4,
+    /// Digital pin 1
+    pin d1 = b5,
+
+    /// The status LED of the processor board
+    pin led = a23,
+
+    /// UART1 TX
+    pin tx1 = a16,
+    /// UART1 RX
+    pin rx1 = a17,
+
+    /// The I2C data line, shared with the fuel gauge of the carrier
+    pin sda = a12,
+    /// The I2C clock line, shared with the fuel gauge of the carrier
+    pin scl = a13,
+    /// The I2C interrupt line
+    pin i2c_int = a18,
+
+    /// The SPI SCK
+    pin sck = a5,
+    /// The SPI COPI
+    pin copi = a4,
+    /// The SPI CIPO
+    pin cipo = a6,
+    /// The SPI chip select
+    pin cs = a7,
+
+    /// The QSPI flash SCK
+    pin flash_sck = b10,
+    /// The QSPI flash chip select
+    pin flash_cs = b11,
+    /// The QSPI flash data line 0
+    pin flash_d0 = a8,
+    /// The QSPI flash data line 1
+    pin flash_d1 = a9,
+    /// The QSPI flash data line 2
+    pin flash_d2 = a10,
+    /// The QSPI flash data line 3
+    pin flash_d3 = a11,
+
+    /// The USB D- pad
+    pin usb_dm = a24,
+    /// The USB D+ pad
+    pin usb_dp = a25,
+);
+
+/// Convenience for setting up the labelled SPI peripheral.
+/// This powers up SERCOM0 and configures it for use as an
+/// SPI Master in SPI Mode 0.
+pub fn spi_master<F: Into<Hertz>>(
+    clocks: &mut GenericClockController,
+    bus_speed: F,
+    sercom0: pac::SERCOM0,
+    mclk: &mut pac::MCLK,
+    sck: gpio::Pa5<Input<Floating>>,
+    copi: gpio::Pa4<Input<Floating>>,
+    cipo: gpio::Pa6<Input<Floating>>,
+    port: &mut Port,
+) -> SPIMaster0<
+    hal::sercom::Sercom0Pad2<gpio::Pa6<PfD>>,
+    hal::sercom::Sercom0Pad0<gpio::Pa4<PfD>>,
+    hal::sercom::Sercom0Pad1<gpio::Pa5<PfD>>,
+> {
+    let gclk0 = clocks.gclk0();
+    SPIMaster0::new(
+        &clocks.sercom0_core(&gclk0).unwrap(),
+        bus_speed.into(),
+        hal::hal::spi::Mode {
+            phase: hal::hal::spi::Phase::CaptureOnFirstTransition,
+            polarity: hal::hal::spi::Polarity::IdleLow,
+        },
+        sercom0,
+        mclk,
+        (cipo.into_pad(port), copi.into_pad(port), sck.into_pad(port)),
+    )
+}
+
+/// Convenience for setting up the labelled SDA, SCL pins to
+/// operate as an I2C master running at the specified frequency.
+pub fn i2c_master<F: Into<Hertz>>(
+    clocks: &mut GenericClockController,
+    bus_speed: F,
+    sercom2: pac::SERCOM2,
+    mclk: &mut pac::MCLK,
+    sda: gpio::Pa12<Input<Floating>>,
+    scl: gpio::Pa13<Input<Floating>>,
+    port: &mut Port,
+) -> I2CMaster2<hal::sercom::Sercom2Pad0<gpio::Pa12<PfC>>, hal::sercom::Sercom2Pad1<gpio::Pa13<PfC>>>
+{
+    let gclk0 = clocks.gclk0();
+    I2CMaster2::new(
+        &clocks.sercom2_core(&gclk0).unwrap(),
+        bus_speed.into(),
+        sercom2,
+        mclk,
+        sda.into_pad(port),
+        scl.into_pad(port),
+    )
+}
+
+/// Convenience for setting up the labelled RX1, TX1 pins to
+/// operate as a UART device running at the specified baud.
+pub fn uart<F: Into<Hertz>>(
+    clocks: &mut GenericClockController,
+    baud: F,
+    sercom1: pac::SERCOM1,
+    mclk: &mut pac::MCLK,
+    rx1: gpio::Pa17<Input<Floating>>,
+    tx1: gpio::Pa16<Input<Floating>>,
+    port: &mut Port,
+) -> UART1<
+    hal::sercom::Sercom1Pad1<gpio::Pa17<PfC>>,
+    hal::sercom::Sercom1Pad0<gpio::Pa16<PfC>>,
+    (),
+    (),
+> {
+    let gclk0 = clocks.gclk0();
+
+    UART1::new(
+        &clocks.sercom1_core(&gclk0).unwrap(),
+        baud.into(),
+        sercom1,
+        mclk,
+        (rx1.into_pad(port), tx1.into_pad(port)),
+    )
+}
+
+/// Convenience for setting up the QSPI flash of the processor board
+pub fn qspi_flash(
+    mclk: &mut pac::MCLK,
+    qspi: pac::QSPI,
+    sck: gpio::Pb10<Input<Floating>>,
+    cs: gpio::Pb11<Input<Floating>>,
+    data0: gpio::Pa8<Input<Floating>>,
+    data1: gpio::Pa9<Input<Floating>>,
+    data2: gpio::Pa10<Input<Floating>>,
+    data3: gpio::Pa11<Input<Floating>>,
+) -> hal::qspi::Qspi<hal::qspi::OneShot> {
+    hal::qspi::Qspi::new(mclk, qspi, sck, cs, data0, data1, data2, data3)
+}
+
+/// I2C address of the MAX17048 fuel gauge of the carrier boards
+pub const FUEL_GAUGE_ADDRESS: u8 = 0x36;
+
+/// Reads the battery voltage, in millivolts, from the fuel gauge of the
+/// carrier board, on the I2C bus of the connector
+pub fn battery_voltage<I2C: WriteRead>(i2c: &mut I2C) -> Result<u16, I2C::Error> {
+    let mut vcell = [0; 2];
+    i2c.write_read(FUEL_GAUGE_ADDRESS, &[0x02], &mut vcell)?;
+    // 78.125µV per bit
+    Ok((u16::from_be_bytes(vcell) as u32 * 5 / 64) as u16)
+}
+
+/// Reads the state of charge of the battery, in percent, from the fuel gauge
+/// of the carrier board
+pub fn battery_charge<I2C: WriteRead>(i2c: &mut I2C) -> Result<u8, I2C::Error> {
+    let mut soc = [0; 2];
+    i2c.write_read(FUEL_GAUGE_ADDRESS, &[0x04], &mut soc)?;
+    // The low byte holds 1/256 of a percent
+    Ok(soc[0])
+}
+
+#[cfg(feature = "usb")]
+pub fn usb_allocator(
+    dm: impl AnyPin<Id = PA24>,
+    dp: impl AnyPin<Id = PA25>,
+    usb: pac::USB,
+    clocks: &mut GenericClockController,
+    mclk: &mut pac::MCLK,
+) -> UsbBusAllocator<UsbBus> {
+    use pac::gclk::{genctrl::SRC_A, pchctrl::GEN_A};
+
+    clocks.configure_gclk_divider_and_source(GEN_A::GCLK2, 1, SRC_A::DFLL, false);
+    let usb_gclk = clocks.get_gclk(GEN_A::GCLK2).unwrap();
+    let usb_clock = &clocks.usb(&usb_gclk).unwrap();
+
+    UsbBusAllocator::new(UsbBus::new(usb_clock, mclk, dm, dp, usb))
+}
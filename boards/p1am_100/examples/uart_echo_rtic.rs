@@ -36,7 +36,7 @@ const APP: () = {
 
         let mut uart = hal::uart(
             &mut clocks,
-            9600.hz(),
+            9600.Hz(),
             peripherals.SERCOM5,
             &mut peripherals.PM,
             pins.d13.into(),
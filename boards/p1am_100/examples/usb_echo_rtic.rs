@@ -3,6 +3,7 @@
 
 extern crate cortex_m;
 extern crate p1am_100 as hal;
+#[cfg(not(any(feature = "panic-usb", feature = "panic-watchdog")))]
 extern crate panic_halt;
 extern crate usb_device;
 extern crate usbd_serial;
@@ -198,7 +198,7 @@ bsp_pins!(
     }
 );
 
-const BASE_CONTROLLER_FREQ: Hertz = Hertz(1000000);
+const BASE_CONTROLLER_FREQ: Hertz = Hertz::from_raw(1_000_000);
 const BASE_CONTROLLER_SPI_MODE: embedded_hal::spi::Mode = spi::MODE_2;
 
 pub type Spi0Pads = spi::Pads<Sercom1, Spi0Miso, Spi0Mosi, Spi0Sck>;
@@ -13,7 +13,6 @@ use pygamer::{self as hal, entry, pac, Pins};
 
 use hal::prelude::*;
 use hal::sercom::PadPin;
-use hal::time::MegaHertz;
 use hal::{clock::GenericClockController, delay::Delay};
 use pac::{CorePeripherals, Peripherals};
 use smart_leds::hsv::{hsv2rgb, Hsv};
@@ -40,7 +39,7 @@ fn main() -> ! {
         hal::sercom::Sercom2Pad1<hal::gpio::Pa13<hal::gpio::PfC>>,
     > = hal::sercom::SPIMaster2::new(
         &clocks.sercom2_core(&gclk).unwrap(),
-        MegaHertz(3),
+        3.MHz(),
         embedded_hal::spi::Mode {
             phase: embedded_hal::spi::Phase::CaptureOnFirstTransition,
             polarity: embedded_hal::spi::Polarity::IdleLow,
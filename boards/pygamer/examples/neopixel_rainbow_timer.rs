@@ -15,7 +15,7 @@ use pygamer::{self as hal, entry, Pins};
 
 use hal::pac::{CorePeripherals, Peripherals};
 use hal::prelude::*;
-use hal::{clock::GenericClockController, delay::Delay, timer::TimerCounter};
+use hal::{clock::GenericClockController, delay::Delay, time::Hertz, timer::TimerCounter};
 use smart_leds::hsv::{hsv2rgb, Hsv};
 use smart_leds::SmartLedsWrite;
 
@@ -35,7 +35,7 @@ fn main() -> ! {
     let gclk0 = clocks.gclk0();
     let timer_clock = clocks.tc2_tc3(&gclk0).unwrap();
     let mut timer = TimerCounter::tc3_(&timer_clock, peripherals.TC3, &mut peripherals.MCLK);
-    timer.start(3.mhz());
+    timer.start(Hertz::MHz(3).into_duration());
 
     let mut neopixel = pins.neopixel.init(timer, &mut pins.port);
     let mut delay = Delay::new(core.SYST, &mut clocks);
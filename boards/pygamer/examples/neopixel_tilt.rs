@@ -12,7 +12,6 @@ use panic_halt as _;
 use pygamer::{self as hal, entry, pac, Pins};
 
 use hal::prelude::*;
-use hal::time::KiloHertz;
 use hal::timer::SpinTimer;
 use hal::{clock::GenericClockController, delay::Delay};
 use lis3dh::{accelerometer::Accelerometer, Lis3dh};
@@ -43,7 +42,7 @@ fn main() -> ! {
     // i2c
     let i2c = pins.i2c.init(
         &mut clocks,
-        KiloHertz(400),
+        400.kHz(),
         peripherals.SERCOM2,
         &mut peripherals.MCLK,
         &mut pins.port,
@@ -27,7 +27,6 @@ use embedded_sdmmc::{TimeSource, Timestamp, VolumeIdx};
 use hal::clock::GenericClockController;
 use hal::delay::Delay;
 use hal::prelude::*;
-use hal::time::MegaHertz;
 use pac::{CorePeripherals, Peripherals};
 
 #[entry]
@@ -50,7 +49,7 @@ fn main() -> ! {
     let sdmmc_cs: OldOutputPin<_> = pins.sd_cs_pin.into_push_pull_output(&mut pins.port).into();
     let sdmmc_spi = pins.spi.init(
         &mut clocks,
-        MegaHertz(3),
+        3.MHz(),
         peripherals.SERCOM1,
         &mut peripherals.MCLK,
         &mut pins.port,
@@ -29,7 +29,7 @@ fn main() -> ! {
     let gclk0 = clocks.gclk0();
     let timer_clock = clocks.tc2_tc3(&gclk0).unwrap();
     let mut timer = TimerCounter::tc3_(&timer_clock, peripherals.TC3, &mut peripherals.MCLK);
-    timer.start(250.khz());
+    timer.start(4.micros());
     let mut d5 = pins.d5.into_push_pull_output(&mut pins.port);
 
     //50% duty cycle, so 500khz period
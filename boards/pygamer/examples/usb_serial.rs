@@ -14,7 +14,11 @@
 #![no_std]
 #![no_main]
 
-#[cfg(not(feature = "panic_led"))]
+#[cfg(not(any(
+    feature = "panic_led",
+    feature = "panic-usb",
+    feature = "panic-watchdog"
+)))]
 use panic_halt as _;
 use pygamer::{self as hal, entry, pac, Pins};
 
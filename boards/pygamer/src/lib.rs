@@ -32,7 +32,11 @@ pub mod util {
     }
 }
 
-#[cfg(feature = "panic_led")]
+// The panic handler of the HAL takes precedence
+#[cfg(all(
+    feature = "panic_led",
+    not(any(feature = "panic-usb", feature = "panic-watchdog"))
+))]
 #[inline(never)]
 #[panic_handler]
 fn panic(_info: &core::panic::PanicInfo) -> ! {
@@ -31,6 +31,17 @@ use hal::pwm::Pwm2;
 #[cfg(feature = "unproven")]
 use pac::{ADC0, ADC1};
 
+#[cfg(feature = "dma")]
+use embedded_hal::{blocking::spi::Write as SpiWrite, digital::v2::OutputPin};
+#[cfg(feature = "dma")]
+use hal::dmac::{ChId, Channel, Ready};
+#[cfg(feature = "dma")]
+use hal::sercom::v2::{self, IoSet1, Sercom4};
+#[cfg(feature = "dma")]
+use hal::spi_display::DmaDisplay;
+#[cfg(feature = "dma")]
+use hal::typelevel::NoneT;
+
 define_pins!(
     /// Maps the pins to their arduino names and
     /// the numbers printed on the board.
@@ -344,7 +355,7 @@ impl Display {
         let gclk0 = clocks.gclk0();
         let tft_spi = SPIMaster4::new(
             &clocks.sercom4_core(&gclk0).ok_or(())?,
-            16.mhz(),
+            16.MHz(),
             spi::Mode {
                 phase: spi::Phase::CaptureOnFirstTransition,
                 polarity: spi::Polarity::IdleLow,
@@ -372,7 +383,7 @@ impl Display {
         let tft_backlight = self.tft_backlight.into_function_e(port);
         let mut pwm2 = Pwm2::new(
             &clocks.tc2_tc3(&gclk0).ok_or(())?,
-            1.khz(),
+            1.kHz(),
             timer2,
             hal::pwm::TC2Pinout::Pa1(tft_backlight),
             mclk,
@@ -384,6 +395,105 @@ impl Display {
     }
 }
 
+/// SPI pads of the display, without MISO
+#[cfg(feature = "dma")]
+pub type TftPads = v2::spi::PadsFromIds<Sercom4, IoSet1, NoneT, gpio::v2::PB15, gpio::v2::PB13>;
+
+/// The on board display, drawn with DMA transfers on channel `Id`
+#[cfg(feature = "dma")]
+pub type DmaTft<Id> =
+    DmaDisplay<v2::spi::Config<TftPads>, Id, Pb5<Output<PushPull>>, Pb12<Output<PushPull>>>;
+
+/// Lends the SPI bus and the pins to the ST7735 driver while it initializes
+/// the display
+#[cfg(feature = "dma")]
+struct Lent<'a, T>(&'a mut T);
+
+#[cfg(feature = "dma")]
+impl<T: SpiWrite<u8>> SpiWrite<u8> for Lent<'_, T> {
+    type Error = T::Error;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), T::Error> {
+        self.0.write(words)
+    }
+}
+
+#[cfg(feature = "dma")]
+impl<T: OutputPin> OutputPin for Lent<'_, T> {
+    type Error = T::Error;
+
+    fn set_low(&mut self) -> Result<(), T::Error> {
+        self.0.set_low()
+    }
+
+    fn set_high(&mut self) -> Result<(), T::Error> {
+        self.0.set_high()
+    }
+}
+
+#[cfg(feature = "dma")]
+impl Display {
+    /// Convenience for setting up the on board display, drawn with DMA
+    /// transfers on `channel` through its `flush_region` method.
+    ///
+    /// The ST7735 driver initializes the display like [`Display::init`], then
+    /// the SPI bus and the pins are handed to the [`DmaDisplay`]. The SPI bus
+    /// has no MISO, so the accelerometer interrupt pin is left unused.
+    pub fn init_dma<Id: ChId>(
+        self,
+        clocks: &mut GenericClockController,
+        sercom4: pac::SERCOM4,
+        mclk: &mut pac::MCLK,
+        timer2: pac::TC2,
+        delay: &mut hal::delay::Delay,
+        port: &mut Port,
+        channel: Channel<Id, Ready>,
+    ) -> Result<(DmaTft<Id>, Pwm2<gpio::v2::PA01>), ()> {
+        let gclk0 = clocks.gclk0();
+        let clock = clocks.sercom4_core(&gclk0).ok_or(())?;
+        let pads = v2::spi::Pads::default()
+            .data_out(self.tft_mosi)
+            .sclk(self.tft_sck);
+        let mut tft_spi = v2::spi::Config::new(mclk, sercom4, pads, clock.freq())
+            .cpol(spi::Polarity::IdleLow)
+            .cpha(spi::Phase::CaptureOnFirstTransition)
+            .baud(Hertz::MHz(16))
+            .enable();
+
+        let mut tft_cs = self.tft_cs.into_push_pull_output(port);
+        tft_cs.set_low()?;
+
+        let mut tft_dc = self.tft_dc.into_push_pull_output(port);
+        let mut tft_reset = self.tft_reset.into_push_pull_output(port);
+
+        let mut display = st7735_lcd::ST7735::new(
+            Lent(&mut tft_spi),
+            Lent(&mut tft_dc),
+            Lent(&mut tft_reset),
+            true,
+            false,
+            160,
+            128,
+        );
+        display.init(delay)?;
+        display.set_orientation(&Orientation::LandscapeSwapped)?;
+        tft_cs.set_high()?;
+
+        let tft_backlight = self.tft_backlight.into_function_e(port);
+        let mut pwm2 = Pwm2::new(
+            &clocks.tc2_tc3(&gclk0).ok_or(())?,
+            1.kHz(),
+            timer2,
+            hal::pwm::TC2Pinout::Pa1(tft_backlight),
+            mclk,
+        );
+
+        pwm2.set_duty(pwm2.get_max_duty());
+
+        Ok((DmaDisplay::new(tft_spi, tft_dc, tft_cs, channel), pwm2))
+    }
+}
+
 /// Neopixel pins
 pub struct Neopixel {
     pub neopixel: Pa15<Input<Floating>>,
@@ -650,6 +760,12 @@ impl Joystick {
     }
 }
 
+/// Supply voltage of the ADC, in millivolts
+pub const VDDANA_MV: u16 = 3300;
+
+/// Ratio of the divider between the battery and its measurement pin
+pub const BATTERY_DIVIDER: u16 = 2;
+
 /// Battery Reader
 #[cfg(feature = "unproven")]
 pub struct BatteryReader {
@@ -665,6 +781,12 @@ impl BatteryReader {
         let result: f32 = (data as f32 / 4095.0) * 2.0 * 3.3;
         result
     }
+
+    /// Returns the voltage of the battery in millivolts. The ADC is left with
+    /// a 12 bit resolution and the VDDANA reference.
+    pub fn battery_voltage(&mut self, adc: &mut hal::adc::Adc<ADC0>) -> u16 {
+        adc.read_millivolts(&mut self.battery, VDDANA_MV, BATTERY_DIVIDER)
+    }
 }
 
 /// Battery pin
@@ -0,0 +1,179 @@
+#![no_std]
+#![no_main]
+
+//! Turns the NeoTrellis M4 into a USB MIDI controller.
+//!
+//! Each key sends a note, from C2 for the top left key up, and lights up
+//! while it is held. Notes received from the host light up the
+//! corresponding keys in blue.
+
+#[allow(unused_imports)]
+use panic_halt;
+use trellis_m4 as hal;
+use ws2812_timer_delay as ws2812;
+
+use core::cell::RefCell;
+
+use cortex_m::interrupt::{free as disable_interrupts, Mutex};
+use cortex_m::peripheral::NVIC;
+use embedded_hal::digital::v1_compat::OldOutputPin;
+use embedded_hal::digital::v2::InputPin;
+
+use hal::entry;
+use hal::pac::{interrupt, CorePeripherals, Peripherals};
+use hal::prelude::*;
+use hal::timer::SpinTimer;
+use hal::usb::UsbBus;
+use hal::usb_midi::{MidiEvent, MidiMessage, UsbMidi};
+use hal::{clock::GenericClockController, delay::Delay};
+
+use smart_leds::{brightness, colors, hsv::RGB8, SmartLedsWrite};
+use usb_device::bus::UsbBusAllocator;
+use usb_device::prelude::*;
+
+/// Note of the top left key
+const BASE_NOTE: u8 = 36;
+
+static mut USB_ALLOCATOR: Option<UsbBusAllocator<UsbBus>> = None;
+static MIDI: Mutex<RefCell<Option<UsbMidi<UsbBus, 32>>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    let mut peripherals = Peripherals::take().unwrap();
+    let mut core = CorePeripherals::take().unwrap();
+
+    let mut clocks = GenericClockController::with_internal_32kosc(
+        peripherals.GCLK,
+        &mut peripherals.MCLK,
+        &mut peripherals.OSC32KCTRL,
+        &mut peripherals.OSCCTRL,
+        &mut peripherals.NVMCTRL,
+    );
+
+    let mut delay = Delay::new(core.SYST, &mut clocks);
+
+    let mut pins = hal::Pins::new(peripherals.PORT).split();
+
+    // neopixels
+    let timer = SpinTimer::new(4);
+    let neopixel_pin: OldOutputPin<_> = pins.neopixel.into_push_pull_output(&mut pins.port).into();
+    let mut neopixel = ws2812::Ws2812::new(timer, neopixel_pin);
+    let mut color_values = [RGB8::default(); hal::NEOPIXEL_COUNT];
+
+    // keypad
+    let keypad = hal::Keypad::new(pins.keypad, &mut pins.port);
+    let keypad_inputs = keypad.decompose();
+    let mut pressed = [false; hal::NEOPIXEL_COUNT];
+    let mut playing = [false; hal::NEOPIXEL_COUNT];
+
+    let bus_allocator = unsafe {
+        USB_ALLOCATOR = Some(pins.usb.usb_allocator(
+            peripherals.USB,
+            &mut clocks,
+            &mut peripherals.MCLK,
+        ));
+        USB_ALLOCATOR.as_ref().unwrap()
+    };
+    let midi = hal::usb_midi(bus_allocator, UsbVidPid(0x16c0, 0x27dd));
+    disable_interrupts(|cs| MIDI.borrow(cs).replace(Some(midi)));
+
+    unsafe {
+        core.NVIC.set_priority(interrupt::USB_OTHER, 1);
+        core.NVIC.set_priority(interrupt::USB_TRCPT0, 1);
+        core.NVIC.set_priority(interrupt::USB_TRCPT1, 1);
+        NVIC::unmask(interrupt::USB_OTHER);
+        NVIC::unmask(interrupt::USB_TRCPT0);
+        NVIC::unmask(interrupt::USB_TRCPT1);
+    }
+
+    loop {
+        for (i, state) in pressed.iter_mut().enumerate() {
+            let keypad_button: &dyn InputPin<Error = ()> = &keypad_inputs[i / 8][i % 8];
+            let down = keypad_button.is_high().unwrap();
+            if down == *state {
+                continue;
+            }
+            *state = down;
+            let note = BASE_NOTE + i as u8;
+            let message = if down {
+                MidiMessage::NoteOn {
+                    channel: 0,
+                    note,
+                    velocity: 100,
+                }
+            } else {
+                MidiMessage::NoteOff {
+                    channel: 0,
+                    note,
+                    velocity: 0,
+                }
+            };
+            // Drop the event if the host is not listening
+            disable_interrupts(|cs| {
+                if let Some(midi) = MIDI.borrow(cs).borrow_mut().as_mut() {
+                    midi.send(MidiEvent::new(message)).ok();
+                }
+            });
+        }
+
+        while let Some(event) = disable_interrupts(|cs| {
+            MIDI.borrow(cs)
+                .borrow_mut()
+                .as_mut()
+                .and_then(|midi| midi.receive())
+        }) {
+            match event.message {
+                MidiMessage::NoteOn { note, velocity, .. } => {
+                    if let Some(key) = playing.get_mut(note.wrapping_sub(BASE_NOTE) as usize) {
+                        // A note on with no velocity is a note off
+                        *key = velocity > 0;
+                    }
+                }
+                MidiMessage::NoteOff { note, .. } => {
+                    if let Some(key) = playing.get_mut(note.wrapping_sub(BASE_NOTE) as usize) {
+                        *key = false;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (i, value) in color_values.iter_mut().enumerate() {
+            *value = if pressed[i] {
+                colors::WHITE
+            } else if playing[i] {
+                colors::BLUE
+            } else {
+                RGB8::default()
+            };
+        }
+        neopixel
+            .write(brightness(color_values.iter().cloned(), 32))
+            .unwrap();
+
+        delay.delay_ms(5u8);
+    }
+}
+
+fn poll_usb() {
+    disable_interrupts(|cs| {
+        if let Some(midi) = MIDI.borrow(cs).borrow_mut().as_mut() {
+            midi.poll();
+        }
+    });
+}
+
+#[interrupt]
+fn USB_OTHER() {
+    poll_usb();
+}
+
+#[interrupt]
+fn USB_TRCPT0() {
+    poll_usb();
+}
+
+#[interrupt]
+fn USB_TRCPT1() {
+    poll_usb();
+}
@@ -8,7 +8,7 @@ pub mod pins;
 pub use adxl343;
 
 #[cfg(feature = "keypad-unproven")]
-pub use keypad;
+pub use ::keypad;
 
 use atsamd_hal as hal;
 
@@ -28,6 +28,12 @@ use gpio::Port;
 use hal::clock::GenericClockController;
 use hal::sercom::{I2CMaster4, UART4};
 use hal::time::Hertz;
+#[cfg(feature = "usb-midi")]
+use hal::usb::usb_device::{bus::UsbBusAllocator, prelude::UsbVidPid};
+#[cfg(feature = "usb-midi")]
+use hal::usb::UsbBus;
+#[cfg(feature = "usb-midi")]
+use hal::usb_midi::UsbMidi;
 
 #[cfg(feature = "keypad-unproven")]
 use embedded_hal::digital::v1_compat::{OldInputPin, OldOutputPin};
@@ -123,3 +129,14 @@ pub fn uart<F: Into<Hertz>>(
 > {
     pins.uart(clocks, baud, sercom4, mclk, port)
 }
+
+/// Convenience for setting up a USB MIDI device with queues of `N` events,
+/// at least 16. Poll it from the `USB_OTHER`, `USB_TRCPT0` and `USB_TRCPT1`
+/// interrupts, see [`usb_midi`](hal::usb_midi).
+#[cfg(feature = "usb-midi")]
+pub fn usb_midi<const N: usize>(
+    bus_allocator: &UsbBusAllocator<UsbBus>,
+    vid_pid: UsbVidPid,
+) -> UsbMidi<'_, UsbBus, N> {
+    UsbMidi::new(bus_allocator, vid_pid, "Adafruit", "NeoTrellis M4")
+}
@@ -13,6 +13,11 @@ use hal::{prelude::*, sercom::I2CError};
 #[cfg(feature = "adxl343")]
 use adxl343::Adxl343;
 
+#[cfg(feature = "usb")]
+use hal::usb::usb_device::bus::UsbBusAllocator;
+#[cfg(feature = "usb")]
+pub use hal::usb::UsbBus;
+
 define_pins!(
     /// Maps the pins to their arduino names and
     /// the numbers printed on the board.
@@ -73,6 +78,11 @@ define_pins!(
     pin dotstar_ci = b2,
     /// APA102 (RGB LED control) MOSI
     pin dotstar_di = b3,
+
+    /// USB D- pin
+    pin usb_dm = a24,
+    /// USB D+ pin
+    pin usb_dp = a25,
 );
 
 impl Pins {
@@ -119,6 +129,11 @@ impl Pins {
             row3: self.row3,
         };
 
+        let usb = USB {
+            dm: self.usb_dm,
+            dp: self.usb_dp,
+        };
+
         Sets {
             accel,
             analog,
@@ -127,6 +142,7 @@ impl Pins {
             stemma,
             keypad,
             neopixel: self.neopixel,
+            usb,
             port: self.port,
         }
     }
@@ -155,6 +171,9 @@ pub struct Sets {
     /// Neopixel pins
     pub neopixel: NeoPixel,
 
+    /// USB pins
+    pub usb: USB,
+
     /// Port
     pub port: Port,
 }
@@ -183,7 +202,7 @@ impl Accelerometer {
         >,
         adxl343::accelerometer::Error<I2CError>,
     > {
-        Adxl343::new(self.i2c_master(clocks, 100.khz(), sercom, mclk, port))
+        Adxl343::new(self.i2c_master(clocks, 100.kHz(), sercom, mclk, port))
     }
 
     /// Configure accelerometer's SDA and SCL pins as an I2C master"
@@ -299,3 +318,27 @@ pub struct Keypad {
 
 /// NeoPixel pin
 pub type NeoPixel = Pa27<Input<Floating>>;
+
+/// USB pins
+pub struct USB {
+    pub dm: Pa24<Input<Floating>>,
+    pub dp: Pa25<Input<Floating>>,
+}
+
+impl USB {
+    #[cfg(feature = "usb")]
+    pub fn usb_allocator(
+        self,
+        usb: super::pac::USB,
+        clocks: &mut GenericClockController,
+        mclk: &mut MCLK,
+    ) -> UsbBusAllocator<UsbBus> {
+        use super::pac::gclk::{genctrl::SRC_A, pchctrl::GEN_A};
+
+        clocks.configure_gclk_divider_and_source(GEN_A::GCLK2, 1, SRC_A::DFLL, false);
+        let usb_gclk = clocks.get_gclk(GEN_A::GCLK2).unwrap();
+        let usb_clock = &clocks.usb(&usb_gclk).unwrap();
+
+        UsbBusAllocator::new(UsbBus::new(usb_clock, mclk, self.dm, self.dp, usb))
+    }
+}
@@ -1,6 +1,7 @@
 #![no_std]
 #![no_main]
 
+#[cfg(not(any(feature = "panic-usb", feature = "panic-watchdog")))]
 use panic_halt as _;
 use trinket_m0 as hal;
 
@@ -22,13 +22,21 @@ use hal::time::Hertz;
 use apa102_spi::Apa102;
 #[cfg(feature = "unproven")]
 use embedded_hal::timer::{CountDown, Periodic};
+#[cfg(feature = "unproven")]
+use hal::dotstar::{BitBang, DotStar};
 
 #[cfg(feature = "usb")]
 use gpio::v2::{AnyPin, PA24, PA25};
+#[cfg(feature = "keyboard")]
+use hal::keypad::Keypad;
 #[cfg(feature = "usb")]
 use hal::usb::usb_device::bus::UsbBusAllocator;
+#[cfg(feature = "keyboard")]
+use hal::usb::usb_device::prelude::UsbVidPid;
 #[cfg(feature = "usb")]
 pub use hal::usb::UsbBus;
+#[cfg(feature = "keyboard")]
+use hal::usb_hid::{HidKeyboard, HidMouse};
 
 define_pins!(
     /// Maps the pins to their arduino names and
@@ -154,6 +162,17 @@ impl Dotstar {
         let spi = bitbang_hal::spi::SPI::new(apa102_spi::MODE, nc, di, ci, timer);
         Apa102::new_with_custom_postamble(spi, 4, false)
     }
+
+    /// Sets up the DotStar with the HAL helper, bit-banged without a timer
+    #[cfg(feature = "unproven")]
+    pub fn led(
+        self,
+        port: &mut Port,
+    ) -> DotStar<BitBang<gpio::Pa1<Output<PushPull>>, gpio::Pa0<Output<PushPull>>>> {
+        let ci = self.ci.into_push_pull_output(port);
+        let di = self.di.into_push_pull_output(port);
+        DotStar::new(BitBang::new(ci, di))
+    }
 }
 
 /// Convenience function for setting up the A7/D3/SCK, A6/D4/MOSI, and
@@ -331,3 +350,31 @@ pub fn usb_allocator(
 
     UsbBusAllocator::new(UsbBus::new(usb_clock, pm, dm, dp, usb))
 }
+
+/// Convenience for setting up a USB keyboard scanning `keypad`, with the HID
+/// usage ID of each key in `keymap`. See [`usb_hid`](hal::usb_hid).
+#[cfg(feature = "keyboard")]
+pub fn usb_keyboard<const R: usize, const C: usize>(
+    bus_allocator: &UsbBusAllocator<UsbBus>,
+    vid_pid: UsbVidPid,
+    keypad: Keypad<R, C>,
+    keymap: [[u8; C]; R],
+) -> HidKeyboard<'_, UsbBus, R, C> {
+    HidKeyboard::new(
+        bus_allocator,
+        vid_pid,
+        "Adafruit",
+        "Trinket M0 Keyboard",
+        keypad,
+        keymap,
+    )
+}
+
+/// Convenience for setting up a USB mouse. See [`usb_hid`](hal::usb_hid).
+#[cfg(feature = "keyboard")]
+pub fn usb_mouse(
+    bus_allocator: &UsbBusAllocator<UsbBus>,
+    vid_pid: UsbVidPid,
+) -> HidMouse<'_, UsbBus> {
+    HidMouse::new(bus_allocator, vid_pid, "Adafruit", "Trinket M0 Mouse")
+}
@@ -5,6 +5,7 @@ extern crate cortex_m;
 extern crate cortex_m_semihosting;
 extern crate embedded_hal;
 extern crate numtoa;
+#[cfg(not(feature = "panic-usb"))]
 extern crate panic_halt;
 extern crate usb_device;
 extern crate usbd_serial;
@@ -49,7 +49,7 @@ fn main() -> ! {
             peripherals.SERCOM7,
             &mut peripherals.MCLK,
             &mut sets.port,
-            58.mhz(),
+            58.MHz(),
             &mut delay,
         )
         .unwrap();
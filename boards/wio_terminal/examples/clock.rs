@@ -55,7 +55,7 @@ fn main() -> ! {
 
     // Configure the RTC. a 1024 Hz clock is configured for us when enabling our
     // main clock
-    let rtc = rtc::Rtc::clock_mode(peripherals.RTC, 1024.hz(), &mut peripherals.MCLK);
+    let rtc = rtc::Rtc::clock_mode(peripherals.RTC, 1024.Hz(), &mut peripherals.MCLK);
 
     unsafe {
         RTC = Some(rtc);
@@ -70,7 +70,7 @@ fn main() -> ! {
             peripherals.SERCOM7,
             &mut peripherals.MCLK,
             &mut sets.port,
-            58.mhz(),
+            58.MHz(),
             &mut delay,
         )
         .unwrap();
@@ -58,7 +58,7 @@ fn main() -> ! {
             peripherals.SERCOM7,
             &mut peripherals.MCLK,
             &mut sets.port,
-            24.mhz(),
+            24.MHz(),
             &mut delay,
         )
         .unwrap();
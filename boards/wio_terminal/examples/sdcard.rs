@@ -63,7 +63,7 @@ fn main() -> ! {
             peripherals.SERCOM7,
             &mut peripherals.MCLK,
             &mut sets.port,
-            24.mhz(),
+            24.MHz(),
             &mut delay,
         )
         .unwrap();
@@ -75,7 +75,7 @@ fn main() -> ! {
             Ok(_) => {
                 // Now that we have initialized, we can run the SPI bus at
                 // a reasonable speed.
-                cont.set_baud(20.mhz());
+                cont.set_baud(20.MHz());
 
                 let mut data = String::<U128>::new();
                 write!(data, "OK! ").unwrap();
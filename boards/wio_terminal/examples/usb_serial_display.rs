@@ -4,6 +4,7 @@
 /// Makes the wio_terminal appear as a USB serial port. The screen can
 /// be written to by sending messages down the serial port.
 use embedded_graphics as eg;
+#[cfg(not(feature = "panic-usb"))]
 use panic_halt as _;
 use wio_terminal as wio;
 
@@ -59,7 +60,7 @@ fn main() -> ! {
             peripherals.SERCOM7,
             &mut peripherals.MCLK,
             &mut sets.port,
-            58.mhz(),
+            58.MHz(),
             &mut delay,
         )
         .unwrap();
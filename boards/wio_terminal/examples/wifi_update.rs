@@ -0,0 +1,74 @@
+#![no_std]
+#![no_main]
+
+/// Bridges the RTL8720 bootloader to a USB serial port, so that its firmware
+/// can be updated from the host with the Realtek flashing tools.
+use panic_halt as _;
+use wio_terminal as wio;
+
+use wio::hal::clock::GenericClockController;
+use wio::hal::delay::Delay;
+use wio::hal::dmac::{DmaController, PriorityLevel};
+use wio::pac::{CorePeripherals, Peripherals};
+use wio::prelude::*;
+use wio::{entry, Pins, Sets, WifiFirmwareUpdate, WifiUpdateBuffer, WIFI_UPDATE_BUFFER_LEN};
+
+use usb_device::prelude::*;
+use usbd_serial::{SerialPort, USB_CLASS_CDC};
+
+static mut BUFFER: WifiUpdateBuffer = [0; WIFI_UPDATE_BUFFER_LEN];
+
+#[entry]
+fn main() -> ! {
+    let mut peripherals = Peripherals::take().unwrap();
+    let core = CorePeripherals::take().unwrap();
+
+    let mut clocks = GenericClockController::with_external_32kosc(
+        peripherals.GCLK,
+        &mut peripherals.MCLK,
+        &mut peripherals.OSC32KCTRL,
+        &mut peripherals.OSCCTRL,
+        &mut peripherals.NVMCTRL,
+    );
+    let mut delay = Delay::new(core.SYST, &mut clocks);
+
+    let pins = Pins::new(peripherals.PORT);
+    let mut sets: Sets = pins.split();
+
+    let bus_allocator = sets
+        .usb
+        .usb_allocator(peripherals.USB, &mut clocks, &mut peripherals.MCLK);
+    let mut serial = SerialPort::new(&bus_allocator);
+    let mut usb_dev = UsbDeviceBuilder::new(&bus_allocator, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("RTL8720 bootloader")
+        .serial_number("TEST")
+        .device_class(USB_CLASS_CDC)
+        .build();
+
+    let mut dmac = DmaController::init(peripherals.DMAC, &mut peripherals.PM);
+    let channels = dmac.split();
+    let channel = channels.0.init(PriorityLevel::LVL0);
+
+    let mut update = WifiFirmwareUpdate::enter(
+        sets.wifi.pwr,
+        sets.wifi.rxd,
+        sets.wifi.txd,
+        peripherals.SERCOM1,
+        channel,
+        unsafe { &mut BUFFER },
+        &mut clocks,
+        &mut peripherals.MCLK,
+        &mut sets.port,
+        &mut delay,
+    )
+    .unwrap();
+
+    let mut user_led = sets.user_led.into_push_pull_output(&mut sets.port);
+    user_led.set_high().unwrap();
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+        update.poll(&mut serial, &mut peripherals.MCLK);
+    }
+}
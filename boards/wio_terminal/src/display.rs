@@ -1,4 +1,6 @@
 use atsamd_hal::clock::GenericClockController;
+#[cfg(feature = "dma")]
+use atsamd_hal::dmac::{ChId, Channel, Ready};
 use atsamd_hal::gpio::v2::{PB19, PB20};
 use atsamd_hal::gpio::*;
 use atsamd_hal::hal::blocking::delay::DelayMs;
@@ -6,6 +8,8 @@ use atsamd_hal::hal::spi::{Phase, Polarity};
 use atsamd_hal::prelude::*;
 use atsamd_hal::sercom::v2::spi::{self, Spi};
 use atsamd_hal::sercom::v2::{IoSet4, Sercom7};
+#[cfg(feature = "dma")]
+use atsamd_hal::spi_display::DmaDisplay;
 use atsamd_hal::target_device::{MCLK, SERCOM7};
 use atsamd_hal::time::Hertz;
 use atsamd_hal::typelevel::NoneT;
@@ -44,6 +48,11 @@ pub type LCD = Ili9341<
     Pc7<Output<PushPull>>,
 >;
 
+/// Type alias for the LCD display drawn with DMA transfers on channel `Id`.
+#[cfg(feature = "dma")]
+pub type DmaLCD<Id> =
+    DmaDisplay<spi::Config<SpiPads>, Id, Pc6<Output<PushPull>>, Pb21<Output<PushPull>>>;
+
 pub use ili9341::Scroller;
 
 impl Display {
@@ -94,3 +103,16 @@ impl Display {
         Ok((ili9341, backlight))
     }
 }
+
+/// Convert an initialized display driver into a display drawn with DMA
+/// transfers on `channel`, using its `flush_region` method. Return a tuple
+/// containing the DMA display and the reset pin, which must be kept high.
+#[cfg(feature = "dma")]
+pub fn into_dma_display<Id: ChId>(
+    lcd: LCD,
+    channel: Channel<Id, Ready>,
+) -> (DmaLCD<Id>, Pc7<Output<PushPull>>) {
+    let (interface, reset) = lcd.release();
+    let (spi, dc, cs) = interface.release();
+    (DmaDisplay::new(spi, dc, cs, channel), reset)
+}
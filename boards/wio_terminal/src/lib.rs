@@ -18,6 +18,7 @@ pub use cortex_m_rt::entry;
 
 // The accelerometer crate contains a number of traits and types which may be
 // useful to the user.
+#[cfg(feature = "accelerometer")]
 pub use lis3dh::accelerometer;
 
 // `prelude` is the only module from this crate which is public, as the
@@ -43,7 +44,11 @@ pub use storage::*;
 #[cfg(feature = "wifi")]
 mod wifi;
 #[cfg(feature = "wifi")]
-pub use wifi::{rpcs as wifi_rpcs, wifi_prelude, Wifi, WifiPins};
+pub use wifi::{rpcs as wifi_rpcs, wifi_prelude, RpcError, Wifi, WifiPins};
+#[cfg(all(feature = "wifi", feature = "usb", feature = "dma"))]
+mod wifi_update;
+#[cfg(all(feature = "wifi", feature = "usb", feature = "dma"))]
+pub use wifi_update::*;
 #[cfg(feature = "wifi")]
 pub mod wifi_types {
     pub use seeed_erpc::{BssType, IPInfo, L3Interface, Security, WifiMode, BSSID, SSID};
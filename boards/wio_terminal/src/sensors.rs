@@ -1,13 +1,36 @@
+#[cfg(feature = "light-sensor")]
 use atsamd_hal::adc::Adc;
+#[cfg(any(feature = "accelerometer", feature = "light-sensor"))]
 use atsamd_hal::clock::GenericClockController;
-use atsamd_hal::gpio::{Floating, Input, Pa12, Pa13, Pd1, PfB, PfD, Port};
+#[cfg(feature = "light-sensor")]
+use atsamd_hal::gpio::PfB;
+#[cfg(feature = "accelerometer")]
+use atsamd_hal::gpio::PfD;
+#[cfg(any(feature = "accelerometer", feature = "light-sensor"))]
+use atsamd_hal::gpio::Port;
+use atsamd_hal::gpio::{Floating, Input, Pa12, Pa13, Pd1};
+#[cfg(any(feature = "accelerometer", feature = "light-sensor"))]
 use atsamd_hal::prelude::*;
+#[cfg(feature = "accelerometer")]
 use atsamd_hal::sercom::{I2CMaster4, PadPin, Sercom4Pad0, Sercom4Pad1};
+#[cfg(feature = "light-sensor")]
 use atsamd_hal::target_device::gclk::pchctrl::GEN_A::GCLK11;
-use atsamd_hal::target_device::{ADC1, MCLK, SERCOM4};
+#[cfg(feature = "light-sensor")]
+use atsamd_hal::target_device::ADC1;
+#[cfg(any(feature = "accelerometer", feature = "light-sensor"))]
+use atsamd_hal::target_device::MCLK;
+#[cfg(feature = "accelerometer")]
+use atsamd_hal::target_device::SERCOM4;
 
+#[cfg(feature = "accelerometer")]
 use lis3dh::{Lis3dh, SlaveAddr};
 
+use atsamd_hal::hal::blocking::i2c::WriteRead;
+
+/// The LIS3DH accelerometer, on the `I2C0` bus
+#[cfg(feature = "accelerometer")]
+pub type Lis3dhAccelerometer = Lis3dh<I2CMaster4<Sercom4Pad0<Pa13<PfD>>, Sercom4Pad1<Pa12<PfD>>>>;
+
 /// I2C Accelerometer pins (uses `SERCOM4`)
 pub struct Accelerometer {
     /// `I2C0` bus clock pin
@@ -17,6 +40,7 @@ pub struct Accelerometer {
     pub sda: Pa13<Input<Floating>>,
 }
 
+#[cfg(feature = "accelerometer")]
 impl Accelerometer {
     /// Initialize the LIS3DH accelerometer using the correct pins and
     // peripherals. Use the driver's default settings.
@@ -26,14 +50,14 @@ impl Accelerometer {
         sercom4: SERCOM4,
         mclk: &mut MCLK,
         port: &mut Port,
-    ) -> Lis3dh<I2CMaster4<Sercom4Pad0<Pa13<PfD>>, Sercom4Pad1<Pa12<PfD>>>> {
+    ) -> Lis3dhAccelerometer {
         // The accelerometer is connected to the Wio Terminal's `I2C0` bus, so
         // based on the possible padouts listed in the datasheet it must use
         // `SERCOM4` and in turn `I2CMaster4`.
         let gclk0 = clocks.gclk0();
         let i2c = I2CMaster4::new(
             &clocks.sercom4_core(&gclk0).unwrap(),
-            400.khz(),
+            400.kHz(),
             sercom4,
             mclk,
             self.sda.into_pad(port),
@@ -52,6 +76,7 @@ pub struct LightSensor {
     pub pd1: Pd1<Input<Floating>>,
 }
 
+#[cfg(feature = "light-sensor")]
 impl LightSensor {
     /// Initialize Pd1 as an ADC input, and return a Tuple containing the ADC
     /// peripheral and the configured pin.
@@ -67,4 +92,54 @@ impl LightSensor {
 
         (adc1, pd1)
     }
+
+    /// Initialize the light sensor, and return a driver which owns the ADC
+    /// and the pin.
+    pub fn init_reader(
+        self,
+        adc: ADC1,
+        clocks: &mut GenericClockController,
+        mclk: &mut MCLK,
+        port: &mut Port,
+    ) -> LightSensorReader {
+        let (adc, pin) = self.init(adc, clocks, mclk, port);
+        LightSensorReader { adc, pin }
+    }
+}
+
+/// The front light sensor, read through `ADC1`
+#[cfg(feature = "light-sensor")]
+pub struct LightSensorReader {
+    adc: Adc<ADC1>,
+    pin: Pd1<PfB>,
+}
+
+#[cfg(feature = "light-sensor")]
+impl LightSensorReader {
+    /// Returns the brightness, as the raw 12-bit ADC reading. Higher values
+    /// mean more light.
+    pub fn read(&mut self) -> u16 {
+        nb::block!(self.adc.read(&mut self.pin)).unwrap()
+    }
+
+    /// Releases the ADC and the pin
+    pub fn free(self) -> (Adc<ADC1>, Pd1<PfB>) {
+        (self.adc, self.pin)
+    }
+}
+
+/// I2C address of the BQ27441 fuel gauge of the battery chassis
+pub const BATTERY_GAUGE_ADDRESS: u8 = 0x55;
+
+/// Reads the battery voltage, in millivolts, from the fuel gauge of the
+/// battery chassis, on the `I2C1` bus shared by the 40-pin header and the
+/// Grove I2C port
+///
+/// Unlike the Feathers, the Wio Terminal has no divider from the battery to
+/// an ADC pin: the chassis measures the voltage itself.
+pub fn battery_voltage<I2C: WriteRead>(i2c: &mut I2C) -> Result<u16, I2C::Error> {
+    let mut voltage = [0; 2];
+    // The Voltage() standard command
+    i2c.write_read(BATTERY_GAUGE_ADDRESS, &[0x04], &mut voltage)?;
+    Ok(u16::from_le_bytes(voltage))
 }
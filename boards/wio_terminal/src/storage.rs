@@ -109,7 +109,7 @@ impl SDCard {
         let sercom6_clk = clocks.sercom6_core(&gclk0).ok_or(())?;
         let spi = SPIMaster6::new(
             &sercom6_clk,
-            400.khz(),
+            400.kHz(),
             spi::Mode {
                 phase: spi::Phase::CaptureOnFirstTransition,
                 polarity: spi::Polarity::IdleLow,
@@ -1,6 +1,7 @@
 use atsamd_hal::clock::GenericClockController;
 use atsamd_hal::delay::Delay;
 use atsamd_hal::gpio::*;
+use atsamd_hal::hal::timer::CountDown;
 use atsamd_hal::prelude::*;
 use atsamd_hal::target_device::{interrupt, MCLK};
 
@@ -22,6 +23,27 @@ use seeed_erpc as erpc;
 
 use crate::WIFI_UART_BAUD;
 
+/// Length of the eRPC frame header (payload length and CRC16).
+const FRAME_HEADER_LEN: usize = 4;
+/// Largest frame payload we accept. A header claiming a longer payload can
+/// only be the result of line noise, so it is skipped while resynchronizing.
+const MAX_FRAME_LEN: usize = 2048;
+
+/// Errors which can occur when issuing an RPC with a timeout.
+#[derive(Debug)]
+pub enum RpcError<E> {
+    /// No valid response was recieved before the timer expired.
+    Timeout,
+    /// The RPC failed to transmit or its response could not be decoded.
+    Rpc(erpc::Err<E>),
+}
+
+/// Drops the first `n` bytes of the recieve window.
+fn discard(frame: &mut [u8], have: &mut usize, n: usize) {
+    frame.copy_within(n..*have, 0);
+    *have -= n;
+}
+
 /// The set of pins which are connected to the RTL8720 in some way
 pub struct WifiPins {
     pub pwr: Pa18<Input<Floating>>,
@@ -66,7 +88,7 @@ impl Wifi {
         let rx: Sercom0Pad2<_> = pins.miso.into_pad(port);
         let uart = UART0::new(
             &clocks.sercom0_core(&gclk0).ok_or(())?,
-            Hertz(WIFI_UART_BAUD),
+            Hertz::from_raw(WIFI_UART_BAUD),
             sercom0,
             mclk,
             (rx, tx),
@@ -156,18 +178,16 @@ impl Wifi {
     pub fn _handle_rx(&mut self) {
         match self.uart.read() {
             Ok(b) => {
+                // If the buffer is full the byte is dropped; the frame it
+                // belonged to will fail its CRC check and be skipped.
                 if let Ok(mut wgr) = self.rx_buff_isr.grant_exact(1) {
                     wgr[0] = b;
                     wgr.commit(1);
-                } else {
-                    panic!("overrun");
-                }
-            }
-            Err(e) => {
-                if e != nb::Error::WouldBlock {
-                    panic!("unrecoverable read error");
                 }
             }
+            // Framing errors are discarded by the UART itself, so there is
+            // nothing more to do here.
+            Err(_) => {}
         };
     }
 
@@ -188,75 +208,114 @@ impl Wifi {
     /// Issues an RPC, blocking till a response is recieved.
     pub fn blocking_rpc<'a, RPC: erpc::RPC>(
         &mut self,
-        mut rpc: RPC,
+        rpc: RPC,
     ) -> Result<RPC::ReturnValue, erpc::Err<RPC::Error>> {
+        self.transact(rpc, || false).map_err(|e| match e {
+            RpcError::Rpc(e) => e,
+            RpcError::Timeout => unreachable!(),
+        })
+    }
+
+    /// Issues an RPC, blocking till a response is recieved or the provided
+    /// timer expires.
+    ///
+    /// A response which arrives after the timeout is recognised by its
+    /// sequence number and discarded by a later call, so the link remains
+    /// usable after a timeout.
+    pub fn blocking_rpc_timeout<RPC, TIM, T>(
+        &mut self,
+        rpc: RPC,
+        timer: &mut TIM,
+        timeout: T,
+    ) -> Result<RPC::ReturnValue, RpcError<RPC::Error>>
+    where
+        RPC: erpc::RPC,
+        TIM: CountDown,
+        T: Into<TIM::Time>,
+    {
+        timer.start(timeout);
+        self.transact(rpc, || timer.wait().is_ok())
+    }
+
+    fn transact<RPC: erpc::RPC, F: FnMut() -> bool>(
+        &mut self,
+        mut rpc: RPC,
+        mut expired: F,
+    ) -> Result<RPC::ReturnValue, RpcError<RPC::Error>> {
         // Transmit the request.
         let mut tx_buff = heapless::Vec::new();
         tx_buff
             .extend_from_slice(&rpc.header(self.next_seq()).as_bytes())
-            .map_err(|_| erpc::Err::TXErr)?;
+            .map_err(|_| RpcError::Rpc(erpc::Err::TXErr))?;
         rpc.args(&mut tx_buff);
-        self.write_frame(&tx_buff).map_err(|_| erpc::Err::TXErr)?;
-
+        self.write_frame(&tx_buff)
+            .map_err(|_| RpcError::Rpc(erpc::Err::TXErr))?;
+
+        // `frame[..have]` holds the bytes recieved so far which have not yet
+        // been accepted or discarded. Whenever the data at the front of the
+        // window fails to validate, it is shifted along by a byte and parsing
+        // is retried, so a dropped or corrupted byte costs us one frame rather
+        // than the whole link.
+        let mut frame = [0u8; FRAME_HEADER_LEN + MAX_FRAME_LEN];
+        let mut have = 0;
         loop {
-            let result = self.recieve_rpc_response(&mut rpc);
-            if let Err(erpc::Err::NotOurs) = result {
-                continue;
-            };
-            break result;
-        }
-    }
+            self.recieve_into(&mut frame, &mut have, FRAME_HEADER_LEN, &mut expired)?;
 
-    fn recieve_rpc_response<'a, RPC: erpc::RPC>(
-        &mut self,
-        rpc: &mut RPC,
-    ) -> Result<RPC::ReturnValue, erpc::Err<RPC::Error>> {
-        let fh = self.recieve_frame_header(rpc)?; // Read the frame header
+            let fh = match erpc::FrameHeader::parse(&frame[..FRAME_HEADER_LEN]) {
+                Ok((_, fh)) if fh.msg_length as usize <= MAX_FRAME_LEN => fh,
+                _ => {
+                    discard(&mut frame, &mut have, 1);
+                    continue;
+                }
+            };
 
-        // Read the payload, check CRC, hand off to underlying trait to decode
-        let mut buffer = [0u8; 2048];
-        let sz = fh.msg_length as usize;
-        self.recieve_bytes(&mut buffer[..sz]);
+            let end = FRAME_HEADER_LEN + fh.msg_length as usize;
+            self.recieve_into(&mut frame, &mut have, end, &mut expired)?;
 
-        fh.check_crc(&buffer[..sz])?;
-        rpc.parse(&buffer[..sz])
-    }
+            let payload = &frame[FRAME_HEADER_LEN..end];
+            let crc: Result<(), erpc::Err<RPC::Error>> = fh.check_crc(payload);
+            if crc.is_err() {
+                discard(&mut frame, &mut have, 1);
+                continue;
+            }
 
-    fn recieve_frame_header<'a, RPC: erpc::RPC>(
-        &mut self,
-        _rpc: &mut RPC,
-    ) -> Result<erpc::FrameHeader, erpc::Err<RPC::Error>> {
-        let mut buffer = [0u8; 4];
-        self.recieve_bytes(&mut buffer);
-
-        match erpc::FrameHeader::parse(&buffer[..]) {
-            Err(e) => Err(erpc::Err::Parsing(e)),
-            Ok(fh) => Ok(fh.1),
+            match rpc.parse(payload) {
+                // A valid frame carrying the response to an earlier request
+                // (most likely one which timed out); skip over it entirely.
+                Err(erpc::Err::NotOurs) => discard(&mut frame, &mut have, end),
+                result => break result.map_err(RpcError::Rpc),
+            }
         }
     }
 
-    fn recieve_bytes(&mut self, mut buffer: &mut [u8]) {
-        while buffer.len() > 0 {
+    /// Reads from the recieve buffer until `frame` holds at least `want`
+    /// bytes, or `expired` reports that we have waited too long.
+    fn recieve_into<E, F: FnMut() -> bool>(
+        &mut self,
+        frame: &mut [u8],
+        have: &mut usize,
+        want: usize,
+        expired: &mut F,
+    ) -> Result<(), RpcError<E>> {
+        while *have < want {
             let r = match self.rx_buff_input.read() {
                 Ok(r) => r,
                 Err(_) => {
+                    if expired() {
+                        return Err(RpcError::Timeout);
+                    }
                     continue;
                 }
             };
             let b = r.buf();
-            let copy_amt = if b.len() < buffer.len() {
-                b.len()
-            } else {
-                buffer.len()
-            };
+            let copy_amt = core::cmp::min(b.len(), want - *have);
 
-            for (i, b) in b[..copy_amt].iter().enumerate() {
-                buffer[i] = *b;
-            }
-            buffer = &mut buffer[copy_amt..];
+            frame[*have..*have + copy_amt].copy_from_slice(&b[..copy_amt]);
+            *have += copy_amt;
 
             r.release(copy_amt);
         }
+        Ok(())
     }
 
     fn write_frame(&mut self, msg: &heapless::Vec<u8, heapless::consts::U64>) -> Result<(), ()> {
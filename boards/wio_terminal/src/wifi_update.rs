@@ -0,0 +1,285 @@
+//! Passthrough from USB to the RTL8720 bootloader, to update the WiFi
+//! firmware
+//!
+//! The RTL8720 enters its UART download mode when its log UART transmit line
+//! is held low while it comes out of reset. [`WifiFirmwareUpdate::enter`]
+//! does so, and then [`WifiFirmwareUpdate::poll`] bridges the log UART
+//! (`SERCOM1`, on PC22/PC23) to a USB serial port, so that the Realtek
+//! flashing tools running on the host can talk to the bootloader directly.
+//! The UART follows the baud rate set by the host on the serial port.
+//!
+//! The firmware sent by the host is fed to the UART by a DMA channel, one USB
+//! packet at a time, from a static buffer. The replies of the bootloader are
+//! short and read by `poll`, which has no buffering beyond the two bytes of
+//! the SERCOM, so it must be called in a tight loop, which also polls the USB
+//! device:
+//!
+//! ```
+//! static mut BUFFER: WifiUpdateBuffer = [0; WIFI_UPDATE_BUFFER_LEN];
+//!
+//! let mut update = WifiFirmwareUpdate::enter(
+//!     sets.wifi.pwr,
+//!     sets.wifi.rxd,
+//!     sets.wifi.txd,
+//!     peripherals.SERCOM1,
+//!     channels.0.init(PriorityLevel::LVL0),
+//!     unsafe { &mut BUFFER },
+//!     &mut clocks,
+//!     &mut peripherals.MCLK,
+//!     &mut sets.port,
+//!     &mut delay,
+//! )
+//! .unwrap();
+//! loop {
+//!     usb_dev.poll(&mut [&mut serial]);
+//!     update.poll(&mut serial, &mut peripherals.MCLK);
+//! }
+//! ```
+
+use atsamd_hal::clock::{GenericClockController, Sercom1CoreClock};
+use atsamd_hal::delay::Delay;
+use atsamd_hal::dmac::{Buffer, BufferPair, Busy, ChId, Channel, Ready, Transfer};
+use atsamd_hal::gpio::*;
+use atsamd_hal::prelude::*;
+use atsamd_hal::sercom::{PadPin, Sercom1Pad0, Sercom1Pad1, UART1Rx, UART1Tx, UART1};
+use atsamd_hal::target_device::{MCLK, SERCOM1};
+use atsamd_hal::time::Hertz;
+use atsamd_hal::usb::UsbBus;
+
+use usbd_serial::SerialPort;
+
+/// Baud rate of the RTL8720 bootloader after reset
+pub const WIFI_BOOTLOADER_BAUD: u32 = 115_200;
+
+/// Size of the buffers in each direction, one USB full speed packet
+pub const WIFI_UPDATE_BUFFER_LEN: usize = 64;
+
+/// Buffer of the bytes sent from the host to the RTL8720
+pub type WifiUpdateBuffer = [u8; WIFI_UPDATE_BUFFER_LEN];
+
+/// The RTL8720 log UART, as seen from the SAMD51
+pub type WifiLogUART = UART1<Sercom1Pad1<Pc23<PfC>>, Sercom1Pad0<Pc22<PfC>>, (), ()>;
+
+type WifiLogRx = UART1Rx<Sercom1Pad1<Pc23<PfC>>, ()>;
+type WifiLogTx = UART1Tx<Sercom1Pad0<Pc22<PfC>>, ()>;
+
+/// The bytes received from the host in a [`WifiUpdateBuffer`], as the source
+/// of a DMA transfer
+struct Packet {
+    buf: &'static mut WifiUpdateBuffer,
+    len: usize,
+}
+
+unsafe impl Buffer for Packet {
+    type Beat = u8;
+
+    #[inline]
+    fn dma_ptr(&mut self) -> *mut Self::Beat {
+        // One past the last byte, as the buffer is incrementing
+        unsafe { self.buf.as_mut_ptr().add(self.len) }
+    }
+
+    #[inline]
+    fn incrementing(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn buffer_len(&self) -> usize {
+        self.len
+    }
+}
+
+type PacketTransfer<C> = Transfer<Channel<C, Busy>, BufferPair<Packet, WifiLogTx>>;
+
+enum ToWifi<C: ChId> {
+    Idle(Channel<C, Ready>, WifiLogTx, Packet),
+    Sending(PacketTransfer<C>),
+    Moving,
+}
+
+/// Bridge between a USB serial port and the RTL8720 bootloader
+pub struct WifiFirmwareUpdate<C: ChId> {
+    pwr: Pa18<Output<PushPull>>,
+    // Only `None` while the baud rate is changed
+    rx: Option<WifiLogRx>,
+    to_wifi: ToWifi<C>,
+    clock: Sercom1CoreClock,
+    baud: u32,
+
+    // Bytes received from the RTL8720, not yet sent to the host
+    to_host: [u8; WIFI_UPDATE_BUFFER_LEN],
+    to_host_len: usize,
+}
+
+impl<C: ChId> WifiFirmwareUpdate<C> {
+    /// Resets the RTL8720 into its UART download mode, and sets up its log
+    /// UART at [`WIFI_BOOTLOADER_BAUD`]. `rxd` and `txd` are named from the
+    /// side of the RTL8720. `channel` sends the bytes received from the host
+    /// in `buffer` to the RTL8720.
+    #[allow(clippy::too_many_arguments)]
+    pub fn enter(
+        pwr: Pa18<Input<Floating>>,
+        rxd: Pc22<Input<Floating>>,
+        txd: Pc23<Input<Floating>>,
+        sercom1: SERCOM1,
+        channel: Channel<C, Ready>,
+        buffer: &'static mut WifiUpdateBuffer,
+        clocks: &mut GenericClockController,
+        mclk: &mut MCLK,
+        port: &mut Port,
+        delay: &mut Delay,
+    ) -> Result<Self, ()> {
+        // The RTL8720 samples its log transmit line as a boot strap when it
+        // comes out of reset; low selects the download mode.
+        let mut strap = txd.into_push_pull_output(port);
+        let mut pwr = pwr.into_push_pull_output(port);
+        strap.set_low()?;
+        pwr.set_low()?;
+        delay.delay_ms(100u8);
+        pwr.set_high()?;
+        delay.delay_ms(200u8);
+        let txd = strap.into_floating_input(port);
+
+        let gclk0 = clocks.gclk0();
+        let clock = clocks.sercom1_core(&gclk0).ok_or(())?;
+        let uart: WifiLogUART = UART1::new(
+            &clock,
+            Hertz::from_raw(WIFI_BOOTLOADER_BAUD),
+            sercom1,
+            mclk,
+            (txd.into_pad(port), rxd.into_pad(port)),
+        );
+        let (tx, rx) = uart.split();
+        let packet = Packet {
+            buf: buffer,
+            len: 0,
+        };
+
+        Ok(Self {
+            pwr,
+            rx: Some(rx),
+            to_wifi: ToWifi::Idle(channel, tx, packet),
+            clock,
+            baud: WIFI_BOOTLOADER_BAUD,
+            to_host: [0; WIFI_UPDATE_BUFFER_LEN],
+            to_host_len: 0,
+        })
+    }
+
+    /// Moves the pending bytes in both directions, and follows the baud rate
+    /// set by the host
+    pub fn poll(&mut self, serial: &mut SerialPort<UsbBus>, mclk: &mut MCLK) {
+        let baud = serial.line_coding().data_rate();
+        if baud != 0 && baud != self.baud {
+            self.set_baud(baud, mclk);
+        }
+        let rx = match self.rx.as_mut() {
+            Some(rx) => rx,
+            None => return,
+        };
+
+        // RTL8720 to host
+        while self.to_host_len < WIFI_UPDATE_BUFFER_LEN {
+            match rx.read() {
+                Ok(byte) => {
+                    self.to_host[self.to_host_len] = byte;
+                    self.to_host_len += 1;
+                }
+                Err(nb::Error::WouldBlock) => break,
+                // Framing errors are expected while the baud rates of both
+                // sides are changed; drop the byte
+                Err(nb::Error::Other(_)) => {}
+            }
+        }
+        if self.to_host_len > 0 {
+            if let Ok(sent) = serial.write(&self.to_host[..self.to_host_len]) {
+                self.to_host.copy_within(sent..self.to_host_len, 0);
+                self.to_host_len -= sent;
+            }
+        }
+
+        // Host to RTL8720, once the previous packet was sent
+        if !self.is_sent() {
+            return;
+        }
+        if let ToWifi::Idle(channel, tx, mut packet) =
+            core::mem::replace(&mut self.to_wifi, ToWifi::Moving)
+        {
+            self.to_wifi = match serial.read(&mut packet.buf[..]) {
+                Ok(received) if received > 0 => {
+                    packet.len = received;
+                    ToWifi::Sending(tx.send_with_dma(packet, channel))
+                }
+                _ => ToWifi::Idle(channel, tx, packet),
+            };
+        }
+    }
+
+    /// Returns true once the last packet from the host was sent to the
+    /// RTL8720
+    fn is_sent(&mut self) -> bool {
+        if let ToWifi::Sending(xfer) = &mut self.to_wifi {
+            if !xfer.complete() {
+                return false;
+            }
+            if let ToWifi::Sending(xfer) = core::mem::replace(&mut self.to_wifi, ToWifi::Moving) {
+                let (channel, packet, tx) = xfer.stop();
+                self.to_wifi = ToWifi::Idle(channel, tx, packet);
+            }
+        }
+        true
+    }
+
+    /// Changes the baud rate once the pending packet was sent, so the change
+    /// is retried by `poll` until then
+    fn set_baud(&mut self, baud: u32, mclk: &mut MCLK) {
+        if !self.is_sent() {
+            return;
+        }
+        if let (ToWifi::Idle(channel, tx, packet), Some(rx)) = (
+            core::mem::replace(&mut self.to_wifi, ToWifi::Moving),
+            self.rx.take(),
+        ) {
+            let (padout, sercom1) = UART1::join(tx, rx).free();
+            let uart: WifiLogUART =
+                UART1::new(&self.clock, Hertz::from_raw(baud), sercom1, mclk, padout);
+            let (tx, rx) = uart.split();
+            self.rx = Some(rx);
+            self.to_wifi = ToWifi::Idle(channel, tx, packet);
+        }
+        self.baud = baud;
+    }
+
+    /// Resets the RTL8720 into its normal mode, e.g. to run the new firmware,
+    /// once the pending packet was sent. Returns its power pin and log UART,
+    /// along with the DMA channel and buffer.
+    #[allow(clippy::type_complexity)]
+    pub fn exit(
+        mut self,
+        delay: &mut Delay,
+    ) -> Result<
+        (
+            Pa18<Output<PushPull>>,
+            WifiLogUART,
+            Channel<C, Ready>,
+            &'static mut WifiUpdateBuffer,
+        ),
+        (),
+    > {
+        while !self.is_sent() {}
+        let (channel, tx, packet) = match self.to_wifi {
+            ToWifi::Idle(channel, tx, packet) => (channel, tx, packet),
+            _ => return Err(()),
+        };
+        let uart = UART1::join(tx, self.rx.take().ok_or(())?);
+
+        // The log transmit line is an input again, and pulled up by the
+        // RTL8720
+        self.pwr.set_low()?;
+        delay.delay_ms(100u8);
+        self.pwr.set_high()?;
+        delay.delay_ms(200u8);
+        Ok((self.pwr, uart, channel, packet.buf))
+    }
+}
@@ -2,6 +2,7 @@
 #![no_main]
 
 extern crate cortex_m;
+#[cfg(not(feature = "panic-usb"))]
 extern crate panic_halt;
 extern crate usb_device;
 extern crate usbd_serial;
@@ -0,0 +1,27 @@
+//! ADC inputs shared by both chip families
+//!
+//! The ADC of the SAMD11 and SAMD21 is named `ADC`, while the SAMD51 has
+//! `ADC0` and `ADC1`. The `adc` module of the SAMD11 and SAMD21 also names it
+//! `ADC0`, so that drivers can be written once for the first ADC:
+//!
+//! ```
+//! use atsamd_hal::adc::{Adc, AdcChannel, ADC0};
+//! use atsamd_hal::prelude::*;
+//!
+//! fn read<C: AdcChannel<ADC0>>(adc: &mut Adc<ADC0>, input: &mut C) -> u16 {
+//!     nb::block!(adc.read(input)).unwrap()
+//! }
+//! ```
+
+use crate::hal::adc::Channel;
+
+/// An input of the ADC `ADC`
+///
+/// This is implemented by the inputs of the ADCs: the pins in alternate mode
+/// B, and the internal sources of the `adc` module, like `Bandgap`. The `Adc`
+/// drivers read any `AdcChannel`, whose [`Channel::channel`] is its
+/// [`MUXPOS`](AdcChannel::MUXPOS).
+pub trait AdcChannel<ADC>: Channel<ADC, ID = u8> {
+    /// The value of the `MUXPOS` field of `INPUTCTRL` selecting the input
+    const MUXPOS: u8;
+}
@@ -0,0 +1,268 @@
+//! Bit-banged I2C master
+//!
+//! A software I2C master for when no SERCOM is free to drive the wanted pins.
+//! It is generic over the embedded-hal digital traits, but is meant to be used
+//! with pins in [`OpenDrainPullUpOutput`](crate::gpio::v2::OpenDrainPullUpOutput)
+//! mode, which can both release the bus and read it back. The internal
+//! pull-ups are weak (roughly 40kΩ), so external pull-ups are still needed
+//! for anything faster than a few tens of kHz or on long wires.
+//!
+//! The bus timing comes from a periodic [`CountDown`] timer, which is
+//! started at twice the bus frequency, i.e. once per half clock period.
+//! Clock stretching by the slave is supported.
+//!
+//! ```
+//! let scl = pins.pa08.into_open_drain_pull_up_output();
+//! let sda = pins.pa09.into_open_drain_pull_up_output();
+//! let mut i2c = I2c::new(scl, sda, timer, 100.kHz());
+//! i2c.write(0x3c, &[0x00, 0xaf])?;
+//! ```
+
+use crate::time::{Hertz, Nanoseconds};
+use hal::blocking::i2c::{Read, Write, WriteRead};
+use hal::digital::v2::{InputPin, OutputPin};
+use hal::timer::{CountDown, Periodic};
+
+/// Number of half clock periods to wait for a slave stretching the clock
+const STRETCH_TIMEOUT: u32 = 1_000;
+
+/// Errors of the bit-banged I2C master
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Error<E> {
+    /// A pin reported an error
+    Pin(E),
+    /// The slave did not acknowledge the address or a data byte
+    Nack,
+    /// SDA did not read back as high while released, so another master
+    /// is driving the bus
+    ArbitrationLost,
+    /// The slave held SCL low for too long
+    Timeout,
+}
+
+/// Bit-banged I2C master
+pub struct I2c<SCL, SDA, TIM> {
+    scl: SCL,
+    sda: SDA,
+    timer: TIM,
+}
+
+impl<SCL, SDA, TIM, E> I2c<SCL, SDA, TIM>
+where
+    SCL: OutputPin<Error = E> + InputPin<Error = E>,
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+    TIM: CountDown + Periodic,
+    TIM::Time: From<Nanoseconds>,
+{
+    /// Creates a new I2C master, releasing both lines and starting the timer
+    /// at twice the bus frequency.
+    pub fn new<F: Into<Hertz>>(mut scl: SCL, mut sda: SDA, mut timer: TIM, freq: F) -> Self {
+        scl.set_high().ok();
+        sda.set_high().ok();
+        let half_period: Nanoseconds = (freq.into() * 2).into_duration();
+        timer.start(half_period);
+        Self { scl, sda, timer }
+    }
+
+    /// Releases the pins and the timer
+    pub fn free(self) -> (SCL, SDA, TIM) {
+        (self.scl, self.sda, self.timer)
+    }
+
+    /// Clocks out up to nine pulses on SCL to release a slave stuck in the
+    /// middle of a transfer, then sends a STOP condition.
+    pub fn recover(&mut self) -> Result<(), Error<E>> {
+        self.sda.set_high().map_err(Error::Pin)?;
+        for _ in 0..9 {
+            if self.sda.is_high().map_err(Error::Pin)? {
+                break;
+            }
+            self.scl.set_low().map_err(Error::Pin)?;
+            self.wait();
+            self.release_scl()?;
+            self.wait();
+        }
+        self.scl.set_low().map_err(Error::Pin)?;
+        self.sda.set_low().map_err(Error::Pin)?;
+        self.wait();
+        self.stop()
+    }
+
+    fn wait(&mut self) {
+        nb::block!(self.timer.wait()).ok();
+    }
+
+    /// Releases SCL and waits for any clock stretching to end
+    fn release_scl(&mut self) -> Result<(), Error<E>> {
+        self.scl.set_high().map_err(Error::Pin)?;
+        for _ in 0..STRETCH_TIMEOUT {
+            if self.scl.is_high().map_err(Error::Pin)? {
+                return Ok(());
+            }
+            self.wait();
+        }
+        Err(Error::Timeout)
+    }
+
+    fn start(&mut self) -> Result<(), Error<E>> {
+        // Also serves as a repeated START, where SCL is low on entry
+        self.sda.set_high().map_err(Error::Pin)?;
+        self.wait();
+        self.release_scl()?;
+        self.wait();
+        if self.sda.is_low().map_err(Error::Pin)? {
+            return Err(Error::ArbitrationLost);
+        }
+        self.sda.set_low().map_err(Error::Pin)?;
+        self.wait();
+        self.scl.set_low().map_err(Error::Pin)
+    }
+
+    fn stop(&mut self) -> Result<(), Error<E>> {
+        self.sda.set_low().map_err(Error::Pin)?;
+        self.wait();
+        self.release_scl()?;
+        self.wait();
+        self.sda.set_high().map_err(Error::Pin)?;
+        self.wait();
+        Ok(())
+    }
+
+    fn write_bit(&mut self, bit: bool) -> Result<(), Error<E>> {
+        if bit {
+            self.sda.set_high().map_err(Error::Pin)?;
+        } else {
+            self.sda.set_low().map_err(Error::Pin)?;
+        }
+        self.wait();
+        self.release_scl()?;
+        self.wait();
+        if bit && self.sda.is_low().map_err(Error::Pin)? {
+            return Err(Error::ArbitrationLost);
+        }
+        self.scl.set_low().map_err(Error::Pin)
+    }
+
+    fn read_bit(&mut self) -> Result<bool, Error<E>> {
+        self.sda.set_high().map_err(Error::Pin)?;
+        self.wait();
+        self.release_scl()?;
+        self.wait();
+        let bit = self.sda.is_high().map_err(Error::Pin)?;
+        self.scl.set_low().map_err(Error::Pin)?;
+        Ok(bit)
+    }
+
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error<E>> {
+        for i in (0..8).rev() {
+            self.write_bit(byte & (1 << i) != 0)?;
+        }
+        // A low level on SDA is an ACK
+        if self.read_bit()? {
+            Err(Error::Nack)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn read_byte(&mut self, ack: bool) -> Result<u8, Error<E>> {
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = (byte << 1) | self.read_bit()? as u8;
+        }
+        self.write_bit(!ack)?;
+        Ok(byte)
+    }
+
+    fn write_bytes(&mut self, address: u8, bytes: &[u8]) -> Result<(), Error<E>> {
+        self.start()?;
+        self.write_byte(address << 1)?;
+        for &byte in bytes {
+            self.write_byte(byte)?;
+        }
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error<E>> {
+        self.start()?;
+        self.write_byte((address << 1) | 1)?;
+        let last = buffer.len().saturating_sub(1);
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            // NACK the last byte to tell the slave the read is over
+            *byte = self.read_byte(i != last)?;
+        }
+        Ok(())
+    }
+
+    /// Sends a STOP after a failed transfer. Losing arbitration means the bus
+    /// belongs to another master, which will end the transfer itself.
+    fn finish<T>(&mut self, result: Result<T, Error<E>>) -> Result<T, Error<E>> {
+        match result {
+            Err(Error::ArbitrationLost) => {
+                self.scl.set_high().ok();
+                self.sda.set_high().ok();
+                result
+            }
+            Err(_) => {
+                self.stop().ok();
+                result
+            }
+            Ok(_) => {
+                self.stop()?;
+                result
+            }
+        }
+    }
+}
+
+impl<SCL, SDA, TIM, E> Write for I2c<SCL, SDA, TIM>
+where
+    SCL: OutputPin<Error = E> + InputPin<Error = E>,
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+    TIM: CountDown + Periodic,
+    TIM::Time: From<Nanoseconds>,
+{
+    type Error = Error<E>;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        let result = self.write_bytes(address, bytes);
+        self.finish(result)
+    }
+}
+
+impl<SCL, SDA, TIM, E> Read for I2c<SCL, SDA, TIM>
+where
+    SCL: OutputPin<Error = E> + InputPin<Error = E>,
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+    TIM: CountDown + Periodic,
+    TIM::Time: From<Nanoseconds>,
+{
+    type Error = Error<E>;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        let result = self.read_bytes(address, buffer);
+        self.finish(result)
+    }
+}
+
+impl<SCL, SDA, TIM, E> WriteRead for I2c<SCL, SDA, TIM>
+where
+    SCL: OutputPin<Error = E> + InputPin<Error = E>,
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+    TIM: CountDown + Periodic,
+    TIM::Time: From<Nanoseconds>,
+{
+    type Error = Error<E>;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let result = self
+            .write_bytes(address, bytes)
+            .and_then(|_| self.read_bytes(address, buffer));
+        self.finish(result)
+    }
+}
@@ -0,0 +1,232 @@
+//! Support for bootloaders
+//!
+//! A bootloader usually lives at the start of flash, and hands off to an
+//! application at a fixed offset. This module provides the pieces needed to
+//! write one in Rust:
+//!
+//! * [`Application`] checks the vector table of the application and its
+//!   CRC-32, computed in hardware by the Device Service Unit, then jumps to
+//!   it, optionally with the watchdog running so that an application which
+//!   hangs early resets back into the bootloader.
+//! * [`BootRequest`] lets the application ask to stay in the bootloader after
+//!   the next reset, e.g. to receive a firmware update.
+//!
+//! ```
+//! let request = unsafe { BootRequest::new(0x2000_7ffc) };
+//! let app = Application::new(0x2000);
+//! if !request.take() && app.is_valid() && app.verify_crc(len, &mut dsu, &mut pac1) == Ok(true) {
+//!     unsafe { app.start_with_watchdog(Watchdog::new(wdt), WatchdogTimeout::Cycles16K as u8) };
+//! }
+//! // ...stay in the bootloader...
+//! ```
+
+use core::arch::asm;
+
+use cortex_m::peripheral::{NVIC, SCB, SYST};
+
+use crate::target_device::DSU;
+#[cfg(feature = "min-samd51g")]
+use crate::target_device::PAC;
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+use crate::target_device::PAC1 as PAC;
+
+#[cfg(feature = "unproven")]
+use crate::watchdog::Watchdog;
+#[cfg(feature = "unproven")]
+use hal::watchdog::WatchdogEnable;
+
+/// Value marking a request to stay in the bootloader. It is the same as used
+/// by the UF2 bootloader for a double tap of the reset button.
+const BOOT_MAGIC: u32 = 0xf016_69ef;
+
+/// End of the largest SRAM of the family
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+const SRAM_END: u32 = 0x2000_8000;
+#[cfg(feature = "min-samd51g")]
+const SRAM_END: u32 = 0x2004_0000;
+
+/// End of the largest flash of the family
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+const FLASH_END: u32 = 0x0004_0000;
+#[cfg(feature = "min-samd51g")]
+const FLASH_END: u32 = 0x0010_0000;
+
+/// Errors of a CRC computation by the DSU
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CrcError {
+    /// The range is not word-aligned
+    Alignment,
+    /// The DSU reported a bus error, e.g. because the range is outside of
+    /// flash or the device is protected
+    BusError,
+}
+
+/// An application image in flash
+#[derive(Debug, Clone, Copy)]
+pub struct Application {
+    base: u32,
+}
+
+impl Application {
+    /// An application whose vector table is at `base`
+    pub const fn new(base: u32) -> Self {
+        Self { base }
+    }
+
+    /// Returns the initial stack pointer and the reset vector of the
+    /// application
+    fn vectors(&self) -> (u32, u32) {
+        let table = self.base as *const u32;
+        unsafe { (table.read_volatile(), table.add(1).read_volatile()) }
+    }
+
+    /// Checks that the vector table looks sane: the initial stack pointer
+    /// must point into SRAM and the reset vector must be a Thumb address in
+    /// flash, after the vector table. Erased flash fails this check.
+    pub fn is_valid(&self) -> bool {
+        let (sp, reset) = self.vectors();
+        sp & 0b11 == 0
+            && sp > 0x2000_0000
+            && sp <= SRAM_END
+            && reset & 1 == 1
+            && reset > self.base
+            && reset < FLASH_END
+    }
+
+    /// Computes the CRC-32 of the `len` bytes of flash starting at the
+    /// vector table, using the DSU. The result is the standard (IEEE 802.3)
+    /// CRC-32.
+    ///
+    /// The DSU is write-protected through the PAC after reset, so its
+    /// protection is cleared first.
+    pub fn crc32(&self, len: u32, dsu: &mut DSU, pac: &mut PAC) -> Result<u32, CrcError> {
+        if (self.base | len) & 0b11 != 0 {
+            return Err(CrcError::Alignment);
+        }
+        #[cfg(any(feature = "samd11", feature = "samd21"))]
+        pac.wpclr.write(|w| unsafe { w.bits(1 << 1) });
+        #[cfg(feature = "min-samd51g")]
+        pac.wrctrl
+            .write(|w| unsafe { w.perid().bits(33).key().clr() });
+
+        dsu.statusa
+            .write(|w| w.done().set_bit().berr().set_bit().fail().set_bit());
+        dsu.addr.write(|w| unsafe { w.addr().bits(self.base >> 2) });
+        dsu.length.write(|w| unsafe { w.length().bits(len >> 2) });
+        dsu.data.write(|w| unsafe { w.data().bits(0xffff_ffff) });
+        dsu.ctrl.write(|w| w.crc().set_bit());
+        loop {
+            let status = dsu.statusa.read();
+            if status.berr().bit_is_set() {
+                return Err(CrcError::BusError);
+            }
+            if status.done().bit_is_set() {
+                break;
+            }
+        }
+        Ok(!dsu.data.read().data().bits())
+    }
+
+    /// Checks the CRC of an image of `len` bytes, whose last word holds the
+    /// CRC-32 of the preceding bytes, as appended by the build tooling.
+    pub fn verify_crc(&self, len: u32, dsu: &mut DSU, pac: &mut PAC) -> Result<bool, CrcError> {
+        if len < 4 {
+            return Err(CrcError::Alignment);
+        }
+        let crc = self.crc32(len - 4, dsu, pac)?;
+        let expected = unsafe { ((self.base + len - 4) as *const u32).read_volatile() };
+        Ok(crc == expected)
+    }
+
+    /// Jumps to the application
+    ///
+    /// All interrupts are disabled and cleared in the NVIC, the SysTick is
+    /// stopped, and the vector table is moved to the application's before
+    /// jumping. Peripherals used by the bootloader, in particular the USB,
+    /// should be disabled before.
+    ///
+    /// The application starts with interrupts masked by PRIMASK, so that no
+    /// exception can use the bootloader's stack in the middle of the jump.
+    /// It must unmask them, e.g. with `cortex_m::interrupt::enable`, once it
+    /// is initialized. RTIC applications do this after `init`.
+    ///
+    /// # Safety
+    ///
+    /// The application must be valid, see [`is_valid`](Self::is_valid) and
+    /// [`verify_crc`](Self::verify_crc). Nothing of the bootloader's state
+    /// survives the jump.
+    pub unsafe fn start(self) -> ! {
+        cortex_m::interrupt::disable();
+        let nvic = &*NVIC::ptr();
+        for i in 0..nvic.icer.len() {
+            nvic.icer[i].write(0xffff_ffff);
+            nvic.icpr[i].write(0xffff_ffff);
+        }
+        (*SYST::PTR).csr.write(0);
+        (*SCB::ptr()).vtor.write(self.base);
+
+        let (sp, reset) = self.vectors();
+        // The stack pointer must be set in the same asm block as the branch,
+        // as the compiler could otherwise use the stack in between
+        asm!(
+            "msr msp, {sp}",
+            "bx {reset}",
+            sp = in(reg) sp,
+            reset = in(reg) reset,
+            options(noreturn, nomem, nostack),
+        )
+    }
+
+    /// Starts the watchdog with the given period, then jumps to the
+    /// application, which must feed the watchdog from then on. An
+    /// application which hangs before doing so resets the device, and the
+    /// bootloader gets a chance to run again.
+    ///
+    /// # Safety
+    ///
+    /// See [`start`](Self::start).
+    #[cfg(feature = "unproven")]
+    pub unsafe fn start_with_watchdog(self, mut wdt: Watchdog, period: u8) -> ! {
+        wdt.start(period);
+        self.start()
+    }
+}
+
+/// A request from the application to stay in the bootloader after a reset
+///
+/// The request is a magic value stored in a word of SRAM which keeps its
+/// content across a reset. Both the bootloader and the application must
+/// agree on its address, and exclude it from the RAM region of their
+/// `memory.x`. The last word of SRAM is the usual choice.
+pub struct BootRequest {
+    addr: *mut u32,
+}
+
+impl BootRequest {
+    /// Uses the SRAM word at `addr`
+    ///
+    /// # Safety
+    ///
+    /// The word must not be used for anything else.
+    pub const unsafe fn new(addr: u32) -> Self {
+        Self {
+            addr: addr as *mut u32,
+        }
+    }
+
+    /// Requests to stay in the bootloader, and resets the device
+    pub fn reset_to_bootloader(&self) -> ! {
+        unsafe { self.addr.write_volatile(BOOT_MAGIC) };
+        SCB::sys_reset()
+    }
+
+    /// Returns true if the application requested to stay in the bootloader,
+    /// and clears the request
+    pub fn take(&self) -> bool {
+        unsafe {
+            let requested = self.addr.read_volatile() == BOOT_MAGIC;
+            self.addr.write_volatile(0);
+            requested
+        }
+    }
+}
@@ -0,0 +1,548 @@
+//! CCM* authenticated encryption over a block cipher
+//!
+//! CCM* is the mode used by the IEEE 802.15.4 and Bluetooth LE link layers. It
+//! encrypts the payload with AES in counter mode, and authenticates it along
+//! with unencrypted header data (the "additional authenticated data") with a
+//! CBC-MAC. Unlike plain CCM, the message integrity code (MIC) may be left
+//! out, for frames which are encrypted but not authenticated.
+//!
+//! Only the encryption of single blocks is needed, which the AES peripheral of
+//! the SAMD51 does in hardware, see the `aes` module. A cipher may also stream
+//! the whole blocks of the payload in counter mode, as the AES peripheral
+//! does with the DMAC, by overriding [`BlockCipher::apply_keystream`]. Radio
+//! frames are usually
+//! assembled from several buffers, so the header and payload are given as
+//! lists of slices, processed in order as if they were a single buffer:
+//!
+//! ```
+//! let mut ccm = Ccm::new(&mut aes, 4)?;
+//! let mut mic = [0; 4];
+//! ccm.encrypt(&nonce, &[&header, &aux_header], &mut [&mut payload], &mut mic)?;
+//! ```
+
+/// A block cipher with 16-byte blocks, e.g. AES
+pub trait BlockCipher {
+    /// Encrypts `block` in place with the current key
+    fn encrypt_block(&mut self, block: &mut [u8; 16]);
+
+    /// Encrypts or decrypts `data`, a whole number of blocks, in counter mode,
+    /// with `counter` as the counter block of the first block. The counter is
+    /// incremented as a big-endian number for each block.
+    ///
+    /// The default encrypts the counter blocks one at a time, see
+    /// [`keystream`].
+    fn apply_keystream(&mut self, counter: &[u8; 16], data: &mut [u8]) {
+        keystream(self, counter, data)
+    }
+}
+
+/// Applies the counter mode key stream to `data` one block at a time, with
+/// [`BlockCipher::encrypt_block`]. This is the default of
+/// [`BlockCipher::apply_keystream`].
+pub fn keystream<C: BlockCipher + ?Sized>(cipher: &mut C, counter: &[u8; 16], data: &mut [u8]) {
+    let mut counter = *counter;
+    for block in data.chunks_mut(BLOCK) {
+        let mut stream = counter;
+        cipher.encrypt_block(&mut stream);
+        for (byte, s) in block.iter_mut().zip(&stream) {
+            *byte ^= s;
+        }
+        advance(&mut counter, 1);
+    }
+}
+
+/// Errors of the CCM* operations
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Error {
+    /// The MIC length is not 0, 4, 6, 8, 10, 12, 14 or 16 bytes
+    MicLength,
+    /// The nonce is not 7 to 13 bytes long
+    NonceLength,
+    /// The payload is too long for its length to fit in the length field,
+    /// which takes the 15 bytes of a block not used by the nonce
+    PayloadLength,
+    /// The decrypted payload does not match its MIC
+    Authentication,
+}
+
+const BLOCK: usize = 16;
+
+/// Streaming CBC-MAC, for data split across several buffers
+struct CbcMac {
+    x: [u8; BLOCK],
+    pos: usize,
+}
+
+impl CbcMac {
+    /// Starts the CBC-MAC with the first block, `B0`
+    fn new<C: BlockCipher>(cipher: &mut C, mut b0: [u8; BLOCK]) -> Self {
+        cipher.encrypt_block(&mut b0);
+        Self { x: b0, pos: 0 }
+    }
+
+    fn update<C: BlockCipher>(&mut self, cipher: &mut C, data: &[u8]) {
+        for &byte in data {
+            self.x[self.pos] ^= byte;
+            self.pos += 1;
+            if self.pos == BLOCK {
+                cipher.encrypt_block(&mut self.x);
+                self.pos = 0;
+            }
+        }
+    }
+
+    /// Pads the data fed so far with zeros to a whole block
+    fn pad<C: BlockCipher>(&mut self, cipher: &mut C) {
+        if self.pos != 0 {
+            cipher.encrypt_block(&mut self.x);
+            self.pos = 0;
+        }
+    }
+}
+
+/// Streaming counter mode, for data split across several buffers
+struct Ctr {
+    /// Counter block of the next key stream block
+    next: [u8; BLOCK],
+    stream: [u8; BLOCK],
+    pos: usize,
+}
+
+impl Ctr {
+    /// Starts at block `A1`, after `A0` which encrypts the MIC
+    fn new(mut a0: [u8; BLOCK]) -> Self {
+        advance(&mut a0, 1);
+        Self {
+            next: a0,
+            stream: [0; BLOCK],
+            pos: BLOCK,
+        }
+    }
+
+    fn apply<C: BlockCipher>(&mut self, cipher: &mut C, data: &mut [u8]) {
+        // Use up the key stream block left over by the previous buffer
+        let used = (BLOCK - self.pos).min(data.len());
+        let (head, data) = data.split_at_mut(used);
+        self.xor(head);
+        // The whole blocks are left to the cipher, which may stream them
+        let whole = data.len() - data.len() % BLOCK;
+        let (blocks, tail) = data.split_at_mut(whole);
+        if !blocks.is_empty() {
+            cipher.apply_keystream(&self.next, blocks);
+            advance(&mut self.next, blocks.len() / BLOCK);
+        }
+        if !tail.is_empty() {
+            self.stream = self.next;
+            cipher.encrypt_block(&mut self.stream);
+            advance(&mut self.next, 1);
+            self.pos = 0;
+            self.xor(tail);
+        }
+    }
+
+    /// XORs `data`, at most the rest of the current key stream block, with it
+    fn xor(&mut self, data: &mut [u8]) {
+        for (byte, s) in data.iter_mut().zip(&self.stream[self.pos..]) {
+            *byte ^= s;
+        }
+        self.pos += data.len();
+    }
+}
+
+/// Adds `blocks` to the big-endian counter at the end of a block. It never
+/// overflows into the nonce, as the payload length is checked.
+fn advance(block: &mut [u8; BLOCK], blocks: usize) {
+    let mut carry = blocks as u64;
+    for byte in block.iter_mut().rev() {
+        if carry == 0 {
+            break;
+        }
+        let sum = *byte as u64 + (carry & 0xff);
+        *byte = sum as u8;
+        carry = (carry >> 8) + (sum >> 8);
+    }
+}
+
+/// CCM* with a given MIC length
+pub struct Ccm<'a, C: BlockCipher> {
+    cipher: &'a mut C,
+    mic_len: usize,
+}
+
+impl<'a, C: BlockCipher> Ccm<'a, C> {
+    /// Uses `cipher`, with a key already set, to produce MICs of `mic_len`
+    /// bytes. A length of zero encrypts without authenticating.
+    pub fn new(cipher: &'a mut C, mic_len: usize) -> Result<Self, Error> {
+        if mic_len > BLOCK || mic_len == 2 || mic_len % 2 != 0 {
+            return Err(Error::MicLength);
+        }
+        Ok(Self { cipher, mic_len })
+    }
+
+    /// Encrypts the payload in place, and writes its MIC to `mic`, which must
+    /// be as long as the MIC
+    pub fn encrypt(
+        &mut self,
+        nonce: &[u8],
+        aad: &[&[u8]],
+        payload: &mut [&mut [u8]],
+        mic: &mut [u8],
+    ) -> Result<(), Error> {
+        if mic.len() != self.mic_len {
+            return Err(Error::MicLength);
+        }
+        let (mut mac, mut ctr, s0) = self.start(nonce, aad, payload)?;
+        for chunk in payload.iter_mut() {
+            mac.update(self.cipher, chunk);
+            ctr.apply(self.cipher, chunk);
+        }
+        mac.pad(self.cipher);
+        for ((m, t), s) in mic.iter_mut().zip(&mac.x).zip(&s0) {
+            *m = t ^ s;
+        }
+        Ok(())
+    }
+
+    /// Decrypts the payload in place, and checks it against `mic`. On an
+    /// authentication error, the decrypted payload must be discarded.
+    pub fn decrypt(
+        &mut self,
+        nonce: &[u8],
+        aad: &[&[u8]],
+        payload: &mut [&mut [u8]],
+        mic: &[u8],
+    ) -> Result<(), Error> {
+        if mic.len() != self.mic_len {
+            return Err(Error::MicLength);
+        }
+        let (mut mac, mut ctr, s0) = self.start(nonce, aad, payload)?;
+        for chunk in payload.iter_mut() {
+            ctr.apply(self.cipher, chunk);
+            mac.update(self.cipher, chunk);
+        }
+        mac.pad(self.cipher);
+        // Compare every byte, so that the time taken doesn't depend on the
+        // position of the first difference
+        let diff = mic
+            .iter()
+            .zip(&mac.x)
+            .zip(&s0)
+            .fold(0, |diff, ((m, t), s)| diff | (m ^ t ^ s));
+        if diff != 0 {
+            return Err(Error::Authentication);
+        }
+        Ok(())
+    }
+
+    /// Authenticates the nonce, lengths and additional data, and returns the
+    /// CBC-MAC and counter states, and the key stream block `S0`
+    fn start(
+        &mut self,
+        nonce: &[u8],
+        aad: &[&[u8]],
+        payload: &[&mut [u8]],
+    ) -> Result<(CbcMac, Ctr, [u8; BLOCK]), Error> {
+        if !(7..=13).contains(&nonce.len()) {
+            return Err(Error::NonceLength);
+        }
+        // Size of the length field
+        let l = BLOCK - 1 - nonce.len();
+        let len: usize = payload.iter().map(|chunk| chunk.len()).sum();
+        let len_bytes = (len as u64).to_be_bytes();
+        if len_bytes[..8 - l].iter().any(|&b| b != 0) {
+            return Err(Error::PayloadLength);
+        }
+        let aad_len: usize = aad.iter().map(|chunk| chunk.len()).sum();
+
+        let mut b0 = [0; BLOCK];
+        let mic_field = if self.mic_len == 0 {
+            0
+        } else {
+            (self.mic_len as u8 - 2) / 2
+        };
+        b0[0] = (((aad_len > 0) as u8) << 6) | (mic_field << 3) | (l as u8 - 1);
+        b0[1..=nonce.len()].copy_from_slice(nonce);
+        b0[BLOCK - l..].copy_from_slice(&len_bytes[8 - l..]);
+        let mut mac = CbcMac::new(self.cipher, b0);
+
+        if aad_len > 0 {
+            if aad_len < 0xff00 {
+                mac.update(self.cipher, &(aad_len as u16).to_be_bytes());
+            } else {
+                mac.update(self.cipher, &[0xff, 0xfe]);
+                mac.update(self.cipher, &(aad_len as u32).to_be_bytes());
+            }
+            for chunk in aad {
+                mac.update(self.cipher, chunk);
+            }
+            mac.pad(self.cipher);
+        }
+
+        let mut a0 = [0; BLOCK];
+        a0[0] = l as u8 - 1;
+        a0[1..=nonce.len()].copy_from_slice(nonce);
+        let mut s0 = a0;
+        self.cipher.encrypt_block(&mut s0);
+        Ok((mac, Ctr::new(a0), s0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SBOX: [u8; 256] = [
+        0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab,
+        0x76, 0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4,
+        0x72, 0xc0, 0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71,
+        0xd8, 0x31, 0x15, 0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2,
+        0xeb, 0x27, 0xb2, 0x75, 0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6,
+        0xb3, 0x29, 0xe3, 0x2f, 0x84, 0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb,
+        0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf, 0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45,
+        0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8, 0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5,
+        0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2, 0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44,
+        0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73, 0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a,
+        0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb, 0xe0, 0x32, 0x3a, 0x0a, 0x49,
+        0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79, 0xe7, 0xc8, 0x37, 0x6d,
+        0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08, 0xba, 0x78, 0x25,
+        0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a, 0x70, 0x3e,
+        0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e, 0xe1,
+        0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+        0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb,
+        0x16,
+    ];
+
+    /// Software AES-128, to check the mode against published vectors
+    struct Aes128 {
+        round_keys: [[u8; 16]; 11],
+    }
+
+    impl Aes128 {
+        fn new(key: &[u8; 16]) -> Self {
+            let mut round_keys = [[0; 16]; 11];
+            round_keys[0] = *key;
+            let mut rcon = 1u8;
+            for round in 1..11 {
+                let prev = round_keys[round - 1];
+                let mut word = [prev[13], prev[14], prev[15], prev[12]];
+                for byte in word.iter_mut() {
+                    *byte = SBOX[*byte as usize];
+                }
+                word[0] ^= rcon;
+                rcon = xtime(rcon);
+                let key = &mut round_keys[round];
+                for i in 0..16 {
+                    let w = if i < 4 { word[i] } else { key[i - 4] };
+                    key[i] = prev[i] ^ w;
+                }
+            }
+            Self { round_keys }
+        }
+    }
+
+    fn xtime(b: u8) -> u8 {
+        (b << 1) ^ if b & 0x80 != 0 { 0x1b } else { 0 }
+    }
+
+    impl BlockCipher for Aes128 {
+        fn encrypt_block(&mut self, block: &mut [u8; 16]) {
+            let add_key = |block: &mut [u8; 16], key: &[u8; 16]| {
+                for (b, k) in block.iter_mut().zip(key) {
+                    *b ^= k;
+                }
+            };
+            add_key(block, &self.round_keys[0]);
+            for round in 1..11 {
+                // SubBytes and ShiftRows, the state being column-major
+                let mut state = [0; 16];
+                for col in 0..4 {
+                    for row in 0..4 {
+                        state[4 * col + row] = SBOX[block[4 * ((col + row) % 4) + row] as usize];
+                    }
+                }
+                if round < 10 {
+                    for col in state.chunks_mut(4) {
+                        let [a, b, c, d] = [col[0], col[1], col[2], col[3]];
+                        let all = a ^ b ^ c ^ d;
+                        col[0] ^= all ^ xtime(a ^ b);
+                        col[1] ^= all ^ xtime(b ^ c);
+                        col[2] ^= all ^ xtime(c ^ d);
+                        col[3] ^= all ^ xtime(d ^ a);
+                    }
+                }
+                *block = state;
+                add_key(block, &self.round_keys[round]);
+            }
+        }
+    }
+
+    /// Streams the whole blocks itself, like a DMA-driven cipher
+    struct Streaming {
+        aes: Aes128,
+        streamed: usize,
+    }
+
+    impl BlockCipher for Streaming {
+        fn encrypt_block(&mut self, block: &mut [u8; 16]) {
+            self.aes.encrypt_block(block)
+        }
+
+        fn apply_keystream(&mut self, counter: &[u8; 16], data: &mut [u8]) {
+            self.streamed += data.len();
+            keystream(&mut self.aes, counter, data)
+        }
+    }
+
+    fn hex<const N: usize>(s: &str) -> [u8; N] {
+        assert_eq!(s.len(), 2 * N);
+        let mut bytes = [0; N];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap();
+        }
+        bytes
+    }
+
+    /// The key of the RFC 3610 packet vectors
+    const KEY: [u8; 16] = [
+        0xc0, 0xc1, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xcb, 0xcc, 0xcd, 0xce,
+        0xcf,
+    ];
+
+    /// RFC 3610 packet vector #1: an 8-byte header, 23 bytes of payload and
+    /// an 8-byte MIC
+    const NONCE: [u8; 13] = [
+        0x00, 0x00, 0x00, 0x03, 0x02, 0x01, 0x00, 0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5,
+    ];
+    const HEADER: [u8; 8] = [0, 1, 2, 3, 4, 5, 6, 7];
+    const CIPHERTEXT: &str = "588c979a61c663d2f066d0c2c0f989806d5f6b61dac384";
+    const MIC: &str = "17e8d12cfdf926e0";
+
+    /// The payload of the vectors counts up from 8, after the header
+    fn plaintext<const N: usize>() -> [u8; N] {
+        let mut bytes = [0; N];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = 8 + i as u8;
+        }
+        bytes
+    }
+
+    #[test]
+    fn aes_fips_197() {
+        let mut aes = Aes128::new(&hex("000102030405060708090a0b0c0d0e0f"));
+        let mut block = hex("00112233445566778899aabbccddeeff");
+        aes.encrypt_block(&mut block);
+        assert_eq!(block, hex("69c4e0d86a7b0430d8cdb78070b4c55a"));
+    }
+
+    #[test]
+    fn rfc_3610_vector_1() {
+        let mut aes = Aes128::new(&KEY);
+        let mut ccm = Ccm::new(&mut aes, 8).unwrap();
+        let mut payload = plaintext::<23>();
+        let mut mic = [0; 8];
+        ccm.encrypt(&NONCE, &[&HEADER], &mut [&mut payload], &mut mic)
+            .unwrap();
+        assert_eq!(payload, hex(CIPHERTEXT));
+        assert_eq!(mic, hex(MIC));
+
+        ccm.decrypt(&NONCE, &[&HEADER], &mut [&mut payload], &mic)
+            .unwrap();
+        assert_eq!(payload, plaintext());
+    }
+
+    #[test]
+    fn rfc_3610_vector_2() {
+        // 24 bytes of payload, a whole number of blocks after the first 8
+        let nonce = hex::<13>("00000004030201a0a1a2a3a4a5");
+        let mut aes = Aes128::new(&KEY);
+        let mut ccm = Ccm::new(&mut aes, 8).unwrap();
+        let mut payload = plaintext::<24>();
+        let mut mic = [0; 8];
+        ccm.encrypt(&nonce, &[&HEADER], &mut [&mut payload], &mut mic)
+            .unwrap();
+        assert_eq!(
+            payload,
+            hex("72c91a36e135f8cf291ca894085c87e3cc15c439c9e43a3b")
+        );
+        assert_eq!(mic, hex("a091d56e10400916"));
+    }
+
+    #[test]
+    fn chunking_does_not_matter() {
+        let mut aes = Streaming {
+            aes: Aes128::new(&KEY),
+            streamed: 0,
+        };
+        let mut ccm = Ccm::new(&mut aes, 8).unwrap();
+        let mut payload = plaintext::<23>();
+        let (a, rest) = payload.split_at_mut(0);
+        let (b, c) = rest.split_at_mut(18);
+        let mut mic = [0; 8];
+        ccm.encrypt(
+            &NONCE,
+            &[&HEADER[..2], &[], &HEADER[2..]],
+            &mut [a, b, c],
+            &mut mic,
+        )
+        .unwrap();
+        assert_eq!(payload, hex(CIPHERTEXT));
+        assert_eq!(mic, hex(MIC));
+        // The first block of the second buffer is streamed, and the next key
+        // stream block serves the 2 bytes after it and the third buffer
+        assert_eq!(aes.streamed, 16);
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let mut aes = Aes128::new(&KEY);
+        let mut ccm = Ccm::new(&mut aes, 8).unwrap();
+        let mic = hex::<8>(MIC);
+
+        let mut tampered = hex::<23>(CIPHERTEXT);
+        tampered[3] ^= 1;
+        assert_eq!(
+            ccm.decrypt(&NONCE, &[&HEADER], &mut [&mut tampered], &mic),
+            Err(Error::Authentication)
+        );
+        let mut payload = hex::<23>(CIPHERTEXT);
+        assert_eq!(
+            ccm.decrypt(&NONCE, &[&HEADER[..7]], &mut [&mut payload], &mic),
+            Err(Error::Authentication)
+        );
+    }
+
+    #[test]
+    fn encryption_only() {
+        // The key stream doesn't depend on the MIC length, so CCM* without a
+        // MIC gives the ciphertext of the vector
+        let mut aes = Aes128::new(&KEY);
+        let mut ccm = Ccm::new(&mut aes, 0).unwrap();
+        let mut payload = plaintext::<23>();
+        ccm.encrypt(&NONCE, &[&HEADER], &mut [&mut payload], &mut [])
+            .unwrap();
+        assert_eq!(payload, hex(CIPHERTEXT));
+        ccm.decrypt(&NONCE, &[&HEADER], &mut [&mut payload], &[])
+            .unwrap();
+        assert_eq!(payload, plaintext());
+    }
+
+    #[test]
+    fn invalid_lengths() {
+        let mut aes = Aes128::new(&KEY);
+        assert!(matches!(Ccm::new(&mut aes, 2), Err(Error::MicLength)));
+        assert!(matches!(Ccm::new(&mut aes, 5), Err(Error::MicLength)));
+        assert!(matches!(Ccm::new(&mut aes, 18), Err(Error::MicLength)));
+        let mut ccm = Ccm::new(&mut aes, 4).unwrap();
+        let mut mic = [0; 4];
+        assert_eq!(
+            ccm.encrypt(&NONCE[..6], &[], &mut [], &mut mic),
+            Err(Error::NonceLength)
+        );
+        // A 13-byte nonce leaves 2 bytes for the payload length
+        let mut big = [0; 0x1_0000];
+        assert_eq!(
+            ccm.encrypt(&NONCE, &[], &mut [&mut big], &mut mic),
+            Err(Error::PayloadLength)
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Runtime CPU clock scaling
+//!
+//! GCLK0 clocks the CPU, and usually also the peripherals set up with the
+//! `GClock` returned by `GenericClockController::gclk0`. Dividing it saves
+//! power during slow phases of the application, but the bit rates of these
+//! peripherals and the periods of their timers scale with it, which silently
+//! breaks a UART in the middle of a conversation.
+//!
+//! [`ClockScaling`] changes the divider of GCLK0, keeps the flash wait states
+//! in step, and notifies the [`ClockListener`]s it is given so that each of
+//! them recomputes its baud or period registers for the new frequency:
+//!
+//! ```
+//! let mut scaling = ClockScaling::new(&mut clocks);
+//! // Slow down to 6MHz while idle
+//! scaling.set_divider(
+//!     8,
+//!     &mut clocks,
+//!     &mut peripherals.NVMCTRL,
+//!     &mut [&mut uart, &mut timer, &mut delay],
+//! );
+//! // ...and back to full speed
+//! scaling.set_divider(
+//!     1,
+//!     &mut clocks,
+//!     &mut peripherals.NVMCTRL,
+//!     &mut [&mut uart, &mut timer, &mut delay],
+//! );
+//! ```
+//!
+//! Only pass the peripherals whose clock comes from GCLK0. Those on other
+//! clock generators keep running unchanged. The registers are rescaled from
+//! their current values, so a listener keeps whatever rate it was last set
+//! to. Rates which become unreachable at the lower frequency are clamped,
+//! e.g. a UART can't run faster than a sixteenth of its clock.
+//!
+//! The peripherals on another clock generator are scaled the same way, with
+//! the `ClockScaling` returned by [`ClockScaling::generator`]. The flash wait
+//! states only follow GCLK0, which clocks the CPU.
+
+use crate::clock::{ClockGenId, GenericClockController};
+use crate::nvmctrl::FlashConfig;
+use crate::target_device::NVMCTRL;
+use crate::time::Hertz;
+
+/// A peripheral whose rate or period depends on the frequency of its clock
+pub trait ClockListener {
+    /// Called after the frequency of the clock of the peripheral changed from
+    /// `old` to `new`, to restore its rate or period
+    fn clock_changed(&mut self, old: Hertz, new: Hertz);
+}
+
+/// Scales the divider of a clock generator, GCLK0 by default. See the
+/// [module](self) documentation.
+pub struct ClockScaling {
+    gclk: ClockGenId,
+    full_speed: Hertz,
+    divider: u16,
+}
+
+impl ClockScaling {
+    /// Takes the current frequency of GCLK0 as the full speed. GCLK0 must be
+    /// undivided, as the clock controller leaves it.
+    pub fn new(clocks: &mut GenericClockController) -> Self {
+        Self {
+            gclk: ClockGenId::GCLK0,
+            full_speed: clocks.gclk0().into(),
+            divider: 1,
+        }
+    }
+
+    /// Scales the clock generator `gclk` instead of GCLK0, taking its current
+    /// frequency as the full speed. It must have been configured with a
+    /// divider of 1. Returns `None` if it has not been configured.
+    pub fn generator(clocks: &mut GenericClockController, gclk: ClockGenId) -> Option<Self> {
+        let full_speed = clocks.get_gclk(gclk)?.into();
+        Some(Self {
+            gclk,
+            full_speed,
+            divider: 1,
+        })
+    }
+
+    /// Returns the current divider of the clock generator
+    pub fn divider(&self) -> u16 {
+        self.divider
+    }
+
+    /// Returns the current frequency of the clock generator
+    pub fn freq(&self) -> Hertz {
+        self.full_speed / self.divider as u32
+    }
+
+    /// Divides the full speed by `divider`, from 1 to 255, and notifies the
+    /// `listeners`. Returns the new frequency of the clock generator.
+    pub fn set_divider(
+        &mut self,
+        divider: u16,
+        clocks: &mut GenericClockController,
+        nvmctrl: &mut NVMCTRL,
+        listeners: &mut [&mut dyn ClockListener],
+    ) -> Hertz {
+        assert!(divider > 0 && divider < 256);
+        let old = self.freq();
+        let new = self.full_speed / divider as u32;
+
+        if self.gclk == ClockGenId::GCLK0 {
+            // The flash must be slowed down before the CPU speeds up, and may
+            // only be sped up once the CPU has slowed down
+            let mut flash = FlashConfig::read(nvmctrl);
+            flash.wait_states = crate::nvmctrl::wait_states(old.max(new));
+            flash.apply(nvmctrl);
+            clocks.set_gclk_divider(self.gclk, divider, new);
+            flash.wait_states = crate::nvmctrl::wait_states(new);
+            flash.apply(nvmctrl);
+        } else {
+            clocks.set_gclk_divider(self.gclk, divider, new);
+        }
+
+        self.divider = divider;
+        for listener in listeners.iter_mut() {
+            listener.clock_changed(old, new);
+        }
+        new
+    }
+}
+
+/// Scales a number of clock cycles from `old` to `new`
+pub(crate) fn rescale(cycles: u32, old: Hertz, new: Hertz) -> u32 {
+    (cycles as u64 * new.to_Hz() as u64 / old.to_Hz() as u64).min(u32::MAX as u64) as u32
+}
+
+/// Rescales the BAUD value of a UART in arithmetic mode, whose rate is
+/// proportional to `(65536 - BAUD) * freq`
+pub(crate) fn rescale_uart_baud(baud: u16, old: Hertz, new: Hertz) -> u16 {
+    let step = rescale(65536 - baud as u32, new, old).max(1).min(65536);
+    (65536 - step) as u16
+}
+
+/// Rescales the BAUD value of a synchronous SERCOM, such as an SPI or I2C
+/// master, whose rate is `freq / (2 * (BAUD + 1))`. The divider is rounded
+/// up, so that the rate does not exceed the original one.
+pub(crate) fn rescale_sync_baud(baud: u8, old: Hertz, new: Hertz) -> u8 {
+    let divider = (baud as u64 + 1) * new.to_Hz() as u64;
+    let divider = (divider + old.to_Hz() as u64 - 1) / old.to_Hz() as u64;
+    (divider.max(1).min(256) - 1) as u8
+}
@@ -0,0 +1,30 @@
+//! Single-core implementation of the `critical-section` crate
+//!
+//! Enabled by the `critical-section-single-core` feature. All of the
+//! supported chips have a single core, so masking interrupts through PRIMASK
+//! is enough to guarantee exclusive access. Only one implementation may be
+//! linked into a binary, so do not enable this feature alongside another
+//! provider, such as the `critical-section-single-core` feature of the
+//! `cortex-m` crate.
+
+use cortex_m::interrupt;
+use cortex_m::register::primask;
+
+struct SingleCoreCriticalSection;
+critical_section::set_impl!(SingleCoreCriticalSection);
+
+unsafe impl critical_section::Impl for SingleCoreCriticalSection {
+    unsafe fn acquire() -> critical_section::RawRestoreState {
+        // Remember whether interrupts were enabled, so that nested critical
+        // sections only re-enable them when the outermost one is released
+        let was_active = primask::read().is_active();
+        interrupt::disable();
+        was_active
+    }
+
+    unsafe fn release(was_active: critical_section::RawRestoreState) {
+        if was_active {
+            interrupt::enable();
+        }
+    }
+}
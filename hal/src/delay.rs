@@ -2,13 +2,30 @@
 
 use cortex_m::peripheral::syst::SystClkSource;
 use cortex_m::peripheral::SYST;
+#[cfg(feature = "min-samd51g")]
+use cortex_m::peripheral::{DCB, DWT};
 
 use crate::clock::GenericClockController;
-use crate::time::Hertz;
+use crate::clock_scaling::ClockListener;
+use crate::tick::{Tick, Timeout};
+#[cfg(feature = "min-samd51g")]
+use crate::time::Nanoseconds;
+use crate::time::{Hertz, Microseconds};
 use hal::blocking::delay::{DelayMs, DelayUs};
 
-/// System timer (SysTick) as a delay provider
-pub struct Delay {
+/// Busy-waiting delay provider
+///
+/// By default, the delays are measured with the system timer (SysTick).
+/// [`Delay::from_tick`] measures them against a [`Tick`] source instead,
+/// such as the RTC, so that the SysTick can stay stopped in low-power
+/// designs.
+pub struct Delay<S = SysTick> {
+    source: S,
+}
+
+/// The system timer (SysTick), clocked by GCLK0, as the time source of a
+/// [`Delay`]
+pub struct SysTick {
     sysclock: Hertz,
     syst: SYST,
 }
@@ -19,41 +36,57 @@ impl Delay {
         syst.set_clock_source(SystClkSource::Core);
 
         Delay {
-            syst,
-            sysclock: clocks.gclk0().into(),
+            source: SysTick {
+                syst,
+                sysclock: clocks.gclk0().into(),
+            },
         }
     }
 
     /// Releases the system timer (SysTick) resource
     pub fn free(self) -> SYST {
-        self.syst
+        self.source.syst
     }
 }
 
-impl DelayMs<u32> for Delay {
-    fn delay_ms(&mut self, ms: u32) {
-        self.delay_us(ms * 1_000);
+impl<T: Tick> Delay<T> {
+    /// Uses a [`Tick`] source as a delay provider
+    pub fn from_tick(tick: T) -> Self {
+        Delay { source: tick }
+    }
+
+    /// Releases the tick source
+    pub fn free_tick(self) -> T {
+        self.source
     }
 }
 
-impl DelayMs<u16> for Delay {
-    fn delay_ms(&mut self, ms: u16) {
-        self.delay_ms(ms as u32);
+impl<S: DelaySource> Delay<S> {
+    /// Delays for a duration, such as `10.millis()`, rounded down to the
+    /// microsecond
+    pub fn delay(&mut self, duration: Microseconds) {
+        self.delay_us(duration.ticks());
     }
 }
 
-impl DelayMs<u8> for Delay {
-    fn delay_ms(&mut self, ms: u8) {
-        self.delay_ms(ms as u32);
+impl ClockListener for Delay {
+    fn clock_changed(&mut self, _old: Hertz, new: Hertz) {
+        self.source.sysclock = new;
     }
 }
 
-impl DelayUs<u32> for Delay {
-    fn delay_us(&mut self, us: u32) {
+/// A time source of a [`Delay`]
+pub trait DelaySource {
+    /// Busy-waits for `us` microseconds
+    fn wait_us(&mut self, us: u32);
+}
+
+impl DelaySource for SysTick {
+    fn wait_us(&mut self, us: u32) {
         // The SysTick Reload Value register supports values between 1 and 0x00FFFFFF.
         const MAX_RVR: u32 = 0x00FF_FFFF;
 
-        let mut total_rvr = us * (self.sysclock.0 / 1_000_000);
+        let mut total_rvr = us * self.sysclock.to_MHz();
 
         while total_rvr != 0 {
             let current_rvr = if total_rvr <= MAX_RVR {
@@ -76,13 +109,176 @@ impl DelayUs<u32> for Delay {
     }
 }
 
-impl DelayUs<u16> for Delay {
+impl<T: Tick> DelaySource for T {
+    fn wait_us(&mut self, us: u32) {
+        // Split long delays so that each chunk fits within half the counter
+        // period. A fast and narrow counter still waits 1us per chunk.
+        let chunk_us = ((T::MAX as u64 / 2 + 1) * 1_000_000 / T::HZ as u64)
+            .max(1)
+            .min(u32::MAX as u64) as u32;
+        let mut remaining = us;
+        while remaining != 0 {
+            let us = remaining.min(chunk_us);
+            let timeout = Timeout::new(&*self, Microseconds::from_ticks(us));
+            while !timeout.expired(&*self) {}
+            remaining -= us;
+        }
+    }
+}
+
+impl<S: DelaySource> DelayMs<u32> for Delay<S> {
+    fn delay_ms(&mut self, ms: u32) {
+        // Wait in steps of one second, so that the microseconds of each step
+        // fit in 32 bits
+        for _ in 0..ms / 1_000 {
+            self.delay_us(1_000_000u32);
+        }
+        self.delay_us((ms % 1_000) * 1_000);
+    }
+}
+
+impl<S: DelaySource> DelayMs<u16> for Delay<S> {
+    fn delay_ms(&mut self, ms: u16) {
+        self.delay_ms(ms as u32);
+    }
+}
+
+impl<S: DelaySource> DelayMs<u8> for Delay<S> {
+    fn delay_ms(&mut self, ms: u8) {
+        self.delay_ms(ms as u32);
+    }
+}
+
+impl<S: DelaySource> DelayUs<u32> for Delay<S> {
+    fn delay_us(&mut self, us: u32) {
+        self.source.wait_us(us);
+    }
+}
+
+impl<S: DelaySource> DelayUs<u16> for Delay<S> {
+    fn delay_us(&mut self, us: u16) {
+        self.delay_us(us as u32)
+    }
+}
+
+impl<S: DelaySource> DelayUs<u8> for Delay<S> {
+    fn delay_us(&mut self, us: u8) {
+        self.delay_us(us as u32)
+    }
+}
+
+/// Cycle counter (DWT CYCCNT) as a delay provider
+///
+/// Busy-waits on the cycle counter of the Cortex-M4, which leaves the
+/// SysTick free and resolves delays down to a CPU cycle, e.g. for the strobes
+/// of bit-banged protocols. The delays are converted to cycles at the
+/// frequency of GCLK0, read from the clock controller, so call
+/// [`update_clock`](Self::update_clock) after changing it.
+///
+/// The counter is compared with wrapping arithmetic, so it may be shared
+/// with other users such as profilers, as long as none of them resets it.
+#[cfg(feature = "min-samd51g")]
+pub struct CycleDelay {
+    sysclock: Hertz,
+    dwt: DWT,
+}
+
+#[cfg(feature = "min-samd51g")]
+impl CycleDelay {
+    /// Enables the cycle counter and configures it as a delay provider
+    pub fn new(mut dwt: DWT, dcb: &mut DCB, clocks: &mut GenericClockController) -> Self {
+        dcb.enable_trace();
+        dwt.enable_cycle_counter();
+
+        CycleDelay {
+            dwt,
+            sysclock: clocks.gclk0().into(),
+        }
+    }
+
+    /// Reads the frequency of GCLK0 again, after it was changed
+    pub fn update_clock(&mut self, clocks: &mut GenericClockController) {
+        self.sysclock = clocks.gclk0().into();
+    }
+
+    /// Delays for `cycles` CPU cycles, plus the few cycles of the call
+    #[inline(always)]
+    pub fn delay_cycles(&mut self, cycles: u32) {
+        let start = DWT::cycle_count();
+        while DWT::cycle_count().wrapping_sub(start) < cycles {}
+    }
+
+    /// Delays for `ns` nanoseconds, rounded up to a CPU cycle
+    pub fn delay_ns(&mut self, ns: u32) {
+        let cycles = (ns as u64 * self.sysclock.to_Hz() as u64 + 999_999_999) / 1_000_000_000;
+        self.delay_cycles(cycles as u32);
+    }
+
+    /// Delays for a duration, such as `250.nanos()`
+    pub fn delay(&mut self, duration: Nanoseconds) {
+        self.delay_ns(duration.ticks());
+    }
+
+    /// Releases the DWT resource. The cycle counter keeps running.
+    pub fn free(self) -> DWT {
+        self.dwt
+    }
+}
+
+#[cfg(feature = "min-samd51g")]
+impl ClockListener for CycleDelay {
+    fn clock_changed(&mut self, _old: Hertz, new: Hertz) {
+        self.sysclock = new;
+    }
+}
+
+#[cfg(feature = "min-samd51g")]
+impl DelayMs<u32> for CycleDelay {
+    fn delay_ms(&mut self, ms: u32) {
+        for _ in 0..ms {
+            self.delay_us(1_000u32);
+        }
+    }
+}
+
+#[cfg(feature = "min-samd51g")]
+impl DelayMs<u16> for CycleDelay {
+    fn delay_ms(&mut self, ms: u16) {
+        self.delay_ms(ms as u32);
+    }
+}
+
+#[cfg(feature = "min-samd51g")]
+impl DelayMs<u8> for CycleDelay {
+    fn delay_ms(&mut self, ms: u8) {
+        self.delay_ms(ms as u32);
+    }
+}
+
+#[cfg(feature = "min-samd51g")]
+impl DelayUs<u32> for CycleDelay {
+    fn delay_us(&mut self, us: u32) {
+        // Wait in steps of one second, so that the number of cycles of each
+        // step fits in the 32-bit counter
+        let mut us = us;
+        while us > 0 {
+            let step = us.min(1_000_000);
+            let cycles = step as u64 * self.sysclock.to_Hz() as u64 / 1_000_000;
+            self.delay_cycles(cycles as u32);
+            us -= step;
+        }
+    }
+}
+
+#[cfg(feature = "min-samd51g")]
+impl DelayUs<u16> for CycleDelay {
     fn delay_us(&mut self, us: u16) {
         self.delay_us(us as u32)
     }
 }
 
-impl DelayUs<u8> for Delay {
+#[cfg(feature = "min-samd51g")]
+impl DelayUs<u8> for CycleDelay {
     fn delay_us(&mut self, us: u8) {
         self.delay_us(us as u32)
     }
@@ -190,6 +190,28 @@ impl<Id: ChId, S: Status> Channel<Id, S> {
         InterruptFlags::from_bytes([cleared])
     }
 
+    /// Captures the registers of the channel, for debugging
+    pub fn debug_state(&mut self) -> ChannelState {
+        #[cfg(any(feature = "samd11", feature = "samd21"))]
+        let (chctrla, chctrlb) = (
+            self.regs.chctrla.read().bits().into(),
+            self.regs.chctrlb.read().bits(),
+        );
+        #[cfg(feature = "min-samd51g")]
+        let (chctrla, chctrlb) = (
+            self.regs.chctrla.read().bits(),
+            self.regs.chctrlb.read().bits().into(),
+        );
+        ChannelState {
+            chctrla,
+            chctrlb,
+            chintflag: self.regs.chintflag.read().bits(),
+            chstatus: self.regs.chstatus.read().bits(),
+            busy: self.regs.busych.read_bit(),
+            pending: self.regs.pendch.read_bit(),
+        }
+    }
+
     #[inline]
     fn _reset_private(&mut self) {
         // Reset the channel to its startup state and wait for reset to complete
@@ -238,6 +260,13 @@ impl<Id: ChId> Channel<Id, Ready> {
             .modify(|_, w| w.burstlen().bits(burst_length as u8));
     }
 
+    /// Keep the channel running in standby sleep
+    #[cfg(feature = "min-samd51g")]
+    #[inline]
+    pub(crate) fn set_run_in_standby(&mut self, enable: bool) {
+        self.regs.chctrla.modify(|_, w| w.runstdby().bit(enable));
+    }
+
     /// Start transfer on channel using the specified trigger source.
     ///
     /// # Return
@@ -305,6 +334,9 @@ impl<Id: ChId> Channel<Id, Busy> {
     pub(crate) fn free(mut self) -> Channel<Id, Ready> {
         self.regs.chctrla.modify(|_, w| w.enable().clear_bit());
         while !self.xfer_complete() {}
+        // Running in standby is an option of each transfer
+        #[cfg(feature = "min-samd51g")]
+        self.regs.chctrla.modify(|_, w| w.runstdby().clear_bit());
         Channel {
             regs: self.regs,
             _status: PhantomData,
@@ -374,3 +406,21 @@ pub struct InterruptFlags {
     #[skip]
     _reserved: B5,
 }
+
+/// The registers of a DMA channel, read at one point in time
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChannelState {
+    /// CHCTRLA, including the enable bit
+    pub chctrla: u32,
+    /// CHCTRLB, including the trigger source and action on the SAMD11/21
+    pub chctrlb: u32,
+    /// CHINTFLAG, the transfer error, complete and suspended flags
+    pub chintflag: u8,
+    /// CHSTATUS, including the pending, busy and fetch error bits
+    pub chstatus: u8,
+    /// Whether the channel is transferring, from BUSYCH
+    pub busy: bool,
+    /// Whether the channel has a pending transfer, from PENDCH
+    pub pending: bool,
+}
@@ -14,6 +14,12 @@
 //! Using the [`DmaController::split`] method will return
 //! a struct containing handles to individual channels.
 //!
+//! # Trigger sources
+//!
+//! Using the [`DmaController::triggers`] method will return the tokens of
+//! the peripheral trigger sources, see the [`trigger`](super::trigger)
+//! module.
+//!
 //! # Releasing the DMAC
 //!
 //! Using the [`DmaController::free`] method will
@@ -39,6 +45,7 @@ pub use crate::target_device::dmac::channel::{
 
 use super::{
     channel::{new_chan, Channel, Uninitialized},
+    trigger::Triggers,
     DESCRIPTOR_SECTION, WRITEBACK,
 };
 use crate::target_device::{DMAC, PM};
@@ -79,6 +86,7 @@ with_num_channels!(define_channels_struct);
 /// Initialized DMA Controller
 pub struct DmaController {
     dmac: DMAC,
+    triggers_taken: bool,
 }
 
 /// Mask representing which priority levels should be enabled/disabled
@@ -168,7 +176,23 @@ impl DmaController {
         // Enable DMA controller
         dmac.ctrl.modify(|_, w| w.dmaenable().set_bit());
 
-        Self { dmac }
+        Self {
+            dmac,
+            triggers_taken: false,
+        }
+    }
+
+    /// Returns the tokens of the peripheral trigger sources the first time
+    /// it is called, and `None` afterwards
+    #[inline]
+    pub fn triggers(&mut self) -> Option<Triggers> {
+        if self.triggers_taken {
+            return None;
+        }
+        self.triggers_taken = true;
+        // SAFETY: The tokens are only created once for this controller,
+        // which is itself created from the singleton DMAC
+        Some(unsafe { Triggers::new() })
     }
 
     /// Enable multiple priority levels simultaneously
@@ -221,6 +245,20 @@ impl DmaController {
         }
     }
 
+    /// Set the quality of service of the DMAC on the AHB, for the channels
+    /// of priority level `level`. See [`Qos`](crate::nvmctrl::Qos).
+    #[cfg(feature = "min-samd51g")]
+    #[inline]
+    pub fn set_qos(&mut self, level: PriorityLevel, qos: crate::nvmctrl::Qos) {
+        let qos = u8::from(qos);
+        self.dmac.prictrl0.modify(|_, w| match level {
+            PriorityLevel::LVL0 => w.qos0().bits(qos),
+            PriorityLevel::LVL1 => w.qos1().bits(qos),
+            PriorityLevel::LVL2 => w.qos2().bits(qos),
+            PriorityLevel::LVL3 => w.qos3().bits(qos),
+        });
+    }
+
     /// Release the DMAC and return the register block.
     ///
     /// **Note**: The [`Channels`] struct is consumed by this method. This means
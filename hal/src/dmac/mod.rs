@@ -189,6 +189,47 @@
 //! }
 //! ```
 //!
+//! # Filling buffers
+//!
+//! [`Transfer::fill`] sets every beat of a buffer to the same value, by
+//! reading it again and again from a non-incrementing source. This offloads
+//! clearing a frame buffer, for instance. A block transfer is limited to 65535
+//! beats, so large buffers are best filled with word beats, using
+//! [`fill_pattern_u8`] or [`fill_pattern_u16`] to repeat a smaller value
+//! over a word.
+//!
+//! ```
+//! static mut BLACK: u32 = fill_pattern_u16(0x0000);
+//! static mut FRAME: [u32; 320 * 240 / 2] = [0; 320 * 240 / 2];
+//!
+//! let xfer = Transfer::fill(chan0, unsafe { &mut BLACK }, unsafe { &mut FRAME[..] })
+//!     .unwrap()
+//!     .with_waker(frame_cleared)
+//!     .begin(TriggerSource::DISABLE, TriggerAction::BLOCK);
+//! ```
+//!
+//! # Trigger tokens
+//!
+//! A trigger source can also be given by the token returned for it by
+//! [`DmaController::triggers`], with [`Transfer::begin_triggered`]. The
+//! transfer holds the token until it ends, so that no other channel can be
+//! started on the same trigger. See the [`trigger`] module.
+//!
+//! # Transfers in standby sleep (SAMD51/SAME5x only)
+//!
+//! `Transfer::run_in_standby` keeps a transfer running while the CPU is in
+//! standby sleep, e.g. to collect a block of ADC results into RAM and only
+//! wake up on the transfer complete interrupt. Both buffers must be
+//! accessible in standby: memory buffers always are, peripherals only once
+//! their `RUNSTDBY` bit is set, otherwise `Error::NotStandbyCapable` is
+//! returned. The option is cleared when the channel is released.
+//!
+//! ```
+//! let mut xfer = Transfer::new(chan0, adc_result, unsafe { &mut SAMPLES }, false)?;
+//! xfer.run_in_standby()?;
+//! let xfer = xfer.begin(ADC0::DMA_RESRDY_TRIGGER, TriggerAction::BEAT);
+//! ```
+//!
 //! ## RTIC example
 //!
 //! The [RTIC] framework provides a convenient way to store a `static`ally
@@ -256,6 +297,7 @@ use modular_bitfield::prelude::*;
 pub use channel::*;
 pub use dma_controller::*;
 pub use transfer::*;
+pub use trigger::{TriggerId, TriggerToken, TriggeredTransfer, Triggers};
 
 #[derive(Debug)]
 /// Runtime errors that may occur when dealing with DMA transfers.
@@ -271,6 +313,16 @@ pub enum Error {
 
     /// Operation is not valid in the current state of the object.
     InvalidState,
+
+    /// Buffer is longer than the 65535 beats of a single block transfer
+    BufferTooLong,
+
+    /// Buffer is empty, while a block transfer moves at least one beat
+    BufferEmpty,
+
+    /// A buffer of a transfer set to run in standby sleep can't be accessed
+    /// in standby
+    NotStandbyCapable,
 }
 
 /// Result for DMAC operations
@@ -394,3 +446,4 @@ static mut DESCRIPTOR_SECTION: [DmacDescriptor; NUM_CHANNELS] = [DEFAULT_DESCRIP
 pub mod channel;
 pub mod dma_controller;
 pub mod transfer;
+pub mod trigger;
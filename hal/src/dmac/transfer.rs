@@ -16,6 +16,9 @@
 //! * Fixed-source to fixed-destination (normally used for
 //! peripheral-to-peripheral transfers)
 //!
+//! A fixed source and an incrementing destination in memory also fills a
+//! buffer with a single value, see [`Transfer::fill`].
+//!
 //! # Beat sizes
 //!
 //! A beat is an atomic, uninterruptible transfer size.Three beat sizes are
@@ -151,6 +154,14 @@ pub unsafe trait Buffer {
     fn incrementing(&self) -> bool;
     /// Buffer length in beats
     fn buffer_len(&self) -> usize;
+    /// Return whether the buffer can be accessed in standby sleep. Memory
+    /// buffers always can, peripherals only when they are set to run in
+    /// standby. Defaults to `false`.
+    #[cfg(feature = "min-samd51g")]
+    #[inline]
+    fn standby_capable(&self) -> bool {
+        false
+    }
 }
 
 unsafe impl<T: Beat, const N: usize> Buffer for &mut [T; N] {
@@ -174,6 +185,12 @@ unsafe impl<T: Beat, const N: usize> Buffer for &mut [T; N] {
     fn buffer_len(&self) -> usize {
         N
     }
+
+    #[cfg(feature = "min-samd51g")]
+    #[inline]
+    fn standby_capable(&self) -> bool {
+        true
+    }
 }
 
 unsafe impl<T: Beat> Buffer for &mut [T] {
@@ -197,6 +214,12 @@ unsafe impl<T: Beat> Buffer for &mut [T] {
     fn buffer_len(&self) -> usize {
         self.len()
     }
+
+    #[cfg(feature = "min-samd51g")]
+    #[inline]
+    fn standby_capable(&self) -> bool {
+        true
+    }
 }
 
 unsafe impl<T: Beat> Buffer for &mut T {
@@ -215,6 +238,12 @@ unsafe impl<T: Beat> Buffer for &mut T {
     fn buffer_len(&self) -> usize {
         1
     }
+
+    #[cfg(feature = "min-samd51g")]
+    #[inline]
+    fn standby_capable(&self) -> bool {
+        true
+    }
 }
 
 //==============================================================================
@@ -513,6 +542,29 @@ where
         }
     }
 }
+#[cfg(feature = "min-samd51g")]
+impl<C, S, D, W> Transfer<C, BufferPair<S, D>, W>
+where
+    S: Buffer,
+    D: Buffer<Beat = S::Beat>,
+    C: AnyChannel<Status = Ready>,
+{
+    /// Keep the transfer running in standby sleep, e.g. to collect ADC
+    /// results into RAM while the CPU sleeps.
+    ///
+    /// Both buffers must be accessible in standby, see
+    /// [`Buffer::standby_capable`]. The clocks of the peripherals must also
+    /// run in standby, which can't be checked here.
+    #[inline]
+    pub fn run_in_standby(&mut self) -> Result<()> {
+        if !self.buffers.source.standby_capable() || !self.buffers.destination.standby_capable() {
+            return Err(Error::NotStandbyCapable);
+        }
+        self.chan.as_mut().set_run_in_standby(true);
+        Ok(())
+    }
+}
+
 impl<B, C, const N: usize> Transfer<C, BufferPair<&'static mut [B; N]>>
 where
     B: 'static + Beat,
@@ -535,6 +587,42 @@ where
     }
 }
 
+impl<B, C> Transfer<C, BufferPair<&'static mut B, &'static mut [B]>>
+where
+    B: 'static + Beat,
+    C: AnyChannel<Status = Ready>,
+{
+    /// Create a new `Transfer` which sets every beat of `destination` to the
+    /// value of `pattern`. The source address does not increment, so the same
+    /// beat is read for the whole block.
+    ///
+    /// Returns [`Error::BufferEmpty`] if `destination` is empty, as a BTCNT of
+    /// zero is not a valid block, and [`Error::BufferTooLong`] if it is longer
+    /// than 65535 beats.
+    #[inline]
+    pub fn fill(chan: C, pattern: &'static mut B, destination: &'static mut [B]) -> Result<Self> {
+        if destination.is_empty() {
+            return Err(Error::BufferEmpty);
+        }
+        if destination.len() > u16::MAX as usize {
+            return Err(Error::BufferTooLong);
+        }
+        Ok(unsafe { Self::new_unchecked(chan, pattern, destination, false) })
+    }
+}
+
+/// Repeats a byte over a word, to fill byte buffers with word beats
+#[inline]
+pub const fn fill_pattern_u8(value: u8) -> u32 {
+    u32::from_ne_bytes([value; 4])
+}
+
+/// Repeats a halfword over a word, to fill halfword buffers with word beats
+#[inline]
+pub const fn fill_pattern_u16(value: u16) -> u32 {
+    (value as u32) << 16 | value as u32
+}
+
 impl<S, D, C, W> Transfer<C, BufferPair<S, D>, W>
 where
     S: Buffer,
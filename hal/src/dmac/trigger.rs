@@ -0,0 +1,231 @@
+//! # Type-level trigger sources
+//!
+//! [`Transfer::begin`] takes its [`TriggerSource`] at run-time, so two
+//! channels can be started on the same peripheral trigger by mistake, and
+//! then race for its requests. [`DmaController::triggers`] returns a
+//! [`TriggerToken`] for each trigger source instead, once. A transfer started
+//! with [`Transfer::begin_triggered`] keeps the token of its trigger until it
+//! is waited for or stopped, so the trigger can't be given to a second
+//! channel in the meantime:
+//!
+//! ```
+//! let triggers = dmac.triggers().unwrap();
+//! let xfer = Transfer::new(chan0, source, destination, false)?
+//!     .begin_triggered(triggers.sercom0_tx, TriggerAction::BEAT);
+//! // `triggers.sercom0_tx` has moved into `xfer`
+//! let (chan0, source, destination, sercom0_tx) = xfer.wait();
+//! ```
+//!
+//! The type-level trigger sources are named after the variants of
+//! [`TriggerSource`], e.g. `SERCOM0_TX` becomes [`Sercom0Tx`] and the
+//! `sercom0_tx` field of [`Triggers`].
+//!
+//! [`DmaController::triggers`]: super::DmaController::triggers
+
+use core::marker::PhantomData;
+
+use paste::paste;
+
+use super::{
+    channel::{AnyChannel, Busy, Channel, ChannelId, Ready},
+    dma_controller::{TriggerAction, TriggerSource},
+    transfer::{Buffer, BufferPair, Transfer},
+};
+use crate::typelevel::Sealed;
+
+/// Type-level enum of the peripheral trigger sources
+pub trait TriggerId: Sealed {
+    /// The trigger source
+    const SOURCE: TriggerSource;
+}
+
+/// Singleton token which allows a transfer to be started on the trigger `T`
+pub struct TriggerToken<T: TriggerId> {
+    trigger: PhantomData<T>,
+}
+
+impl<T: TriggerId> TriggerToken<T> {
+    /// Safety: there must be only one token for each trigger source
+    unsafe fn new() -> Self {
+        Self {
+            trigger: PhantomData,
+        }
+    }
+
+    /// Returns the trigger source
+    pub fn source(&self) -> TriggerSource {
+        T::SOURCE
+    }
+}
+
+macro_rules! triggers {
+    ($($Source:ident),+ $(,)?) => {
+        paste! {
+            $(
+                #[doc = "Type-level variant of [`TriggerId`] for `" $Source "`"]
+                pub enum [<$Source:camel>] {}
+                impl Sealed for [<$Source:camel>] {}
+                impl TriggerId for [<$Source:camel>] {
+                    const SOURCE: TriggerSource = TriggerSource::$Source;
+                }
+            )+
+
+            /// The tokens of all the peripheral trigger sources
+            #[allow(missing_docs)]
+            pub struct Triggers {
+                $(
+                    pub [<$Source:lower>]: TriggerToken<[<$Source:camel>]>,
+                )+
+            }
+
+            impl Triggers {
+                /// Safety: must only be called once
+                pub(super) unsafe fn new() -> Self {
+                    Self {
+                        $(
+                            [<$Source:lower>]: TriggerToken::new(),
+                        )+
+                    }
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "samd11")]
+triggers! {
+    SERCOM0_RX, SERCOM0_TX,
+    SERCOM1_RX, SERCOM1_TX,
+    SERCOM2_RX, SERCOM2_TX,
+    TCC0_OVF, TCC0_MC0, TCC0_MC1, TCC0_MC2, TCC0_MC3,
+    TC1_OVF, TC1_MC0, TC1_MC1,
+    TC2_OVF, TC2_MC0, TC2_MC1,
+    ADC_RESRDY,
+    DAC_EMPTY,
+}
+
+#[cfg(feature = "samd21")]
+triggers! {
+    SERCOM0_RX, SERCOM0_TX,
+    SERCOM1_RX, SERCOM1_TX,
+    SERCOM2_RX, SERCOM2_TX,
+    SERCOM3_RX, SERCOM3_TX,
+    SERCOM4_RX, SERCOM4_TX,
+    SERCOM5_RX, SERCOM5_TX,
+    TCC0_OVF, TCC0_MC0, TCC0_MC1, TCC0_MC2, TCC0_MC3,
+    TCC1_OVF, TCC1_MC0, TCC1_MC1,
+    TCC2_OVF, TCC2_MC0, TCC2_MC1,
+    TC3_OVF, TC3_MC0, TC3_MC1,
+    TC4_OVF, TC4_MC0, TC4_MC1,
+    TC5_OVF, TC5_MC0, TC5_MC1,
+    TC6_OVF, TC6_MC0, TC6_MC1,
+    TC7_OVF, TC7_MC0, TC7_MC1,
+    ADC_RESRDY,
+    DAC_EMPTY,
+    I2S_RX_0, I2S_RX_1, I2S_TX_0, I2S_TX_1,
+    TCC3_OVF, TCC3_MC0, TCC3_MC1, TCC3_MC2, TCC3_MC3,
+}
+
+#[cfg(feature = "min-samd51g")]
+triggers! {
+    RTC_TIMESTAMP,
+    DSU_DCC0, DSU_DCC1,
+    SERCOM0_RX, SERCOM0_TX,
+    SERCOM1_RX, SERCOM1_TX,
+    SERCOM2_RX, SERCOM2_TX,
+    SERCOM3_RX, SERCOM3_TX,
+    SERCOM4_RX, SERCOM4_TX,
+    SERCOM5_RX, SERCOM5_TX,
+    SERCOM6_RX, SERCOM6_TX,
+    SERCOM7_RX, SERCOM7_TX,
+    CAN0_DEBUG,
+    CAN1_DEBUG,
+    TCC0_OVF, TCC0_MC_0, TCC0_MC_1, TCC0_MC_2, TCC0_MC_3, TCC0_MC_4, TCC0_MC_5,
+    TCC1_OVF, TCC1_MC_0, TCC1_MC_1, TCC1_MC_2, TCC1_MC_3,
+    TCC2_OVF, TCC2_MC_0, TCC2_MC_1, TCC2_MC_2,
+    TCC3_OVF, TCC3_MC_0, TCC3_MC_1,
+    TCC4_OVF, TCC4_MC_0, TCC4_MC_1,
+    TC0_OVF, TC0_MC_0, TC0_MC_1,
+    TC1_OVF, TC1_MC_0, TC1_MC_1,
+    TC2_OVF, TC2_MC_0, TC2_MC_1,
+    TC3_OVF, TC3_MC_0, TC3_MC_1,
+    TC4_OVF, TC4_MC_0, TC4_MC_1,
+    TC5_OVF, TC5_MC_0, TC5_MC_1,
+    TC6_OVF, TC6_MC_0, TC6_MC_1,
+    TC7_OVF, TC7_MC_0, TC7_MC_1,
+    ADC0_RESRDY, ADC0_SEQ,
+    ADC1_RESRDY, ADC1_SEQ,
+    DAC_EMPTY_0, DAC_EMPTY_1, DAC_RESRDY_0, DAC_RESRDY_1,
+    I2S_RX_0, I2S_RX_1, I2S_TX_0, I2S_TX_1,
+    PCC_RX,
+    AES_WR, AES_RD,
+    QSPI_RX, QSPI_TX,
+}
+
+/// A transfer started on the trigger `T`, which holds its [`TriggerToken`]
+/// until it ends. Created by [`Transfer::begin_triggered`].
+pub struct TriggeredTransfer<T, S, D, C, W = ()>
+where
+    T: TriggerId,
+    S: Buffer,
+    D: Buffer<Beat = S::Beat>,
+    C: AnyChannel<Status = Busy>,
+{
+    transfer: Transfer<C, BufferPair<S, D>, W>,
+    token: TriggerToken<T>,
+}
+
+impl<C, S, D, W> Transfer<C, BufferPair<S, D>, W>
+where
+    S: Buffer,
+    D: Buffer<Beat = S::Beat>,
+    C: AnyChannel<Status = Ready>,
+{
+    /// Begins the transfer on the trigger of `token`, like
+    /// [`begin`](Transfer::begin). The token is returned once the transfer
+    /// is waited for or stopped.
+    #[inline]
+    pub fn begin_triggered<T: TriggerId>(
+        self,
+        token: TriggerToken<T>,
+        trig_act: TriggerAction,
+    ) -> TriggeredTransfer<T, S, D, Channel<ChannelId<C>, Busy>, W> {
+        TriggeredTransfer {
+            transfer: self.begin(T::SOURCE, trig_act),
+            token,
+        }
+    }
+}
+
+impl<T, S, D, C, W> TriggeredTransfer<T, S, D, C, W>
+where
+    T: TriggerId,
+    S: Buffer,
+    D: Buffer<Beat = S::Beat>,
+    C: AnyChannel<Status = Busy>,
+{
+    /// Gives access to the running transfer, e.g. to check whether it is
+    /// complete or to recycle its buffers
+    #[inline]
+    pub fn transfer(&mut self) -> &mut Transfer<C, BufferPair<S, D>, W> {
+        &mut self.transfer
+    }
+
+    /// Waits for the transfer to complete, see [`Transfer::wait`], and
+    /// returns the token of the trigger along with the other resources
+    ///
+    /// # Blocking: This method may block
+    #[inline]
+    pub fn wait(self) -> (Channel<ChannelId<C>, Ready>, S, D, TriggerToken<T>) {
+        let (chan, source, destination) = self.transfer.wait();
+        (chan, source, destination, self.token)
+    }
+
+    /// Stops the transfer immediately, see [`Transfer::stop`], and returns
+    /// the token of the trigger along with the other resources
+    #[inline]
+    pub fn stop(self) -> (Channel<ChannelId<C>, Ready>, S, D, TriggerToken<T>) {
+        let (chan, source, destination) = self.transfer.stop();
+        (chan, source, destination, self.token)
+    }
+}
@@ -0,0 +1,186 @@
+//! DMX512 output on a SERCOM UART
+//!
+//! DMX512 sends a universe of up to 512 channel slots, preceded by a start
+//! code, as 250 kbaud 8N2 characters. Each packet starts with a break of at
+//! least 88µs followed by a mark-after-break of at least 8µs.
+//!
+//! [`DmxOutput`] generates the break and mark-after-break on the UART, then
+//! lets the DMAC feed the whole packet to the SERCOM, so the CPU is only
+//! involved once per packet. The refresh rate is set by how often
+//! [`DmxOutput::refresh`] is given an expired timer; a full universe takes
+//! about 23ms to send, i.e. up to 44 packets per second.
+//!
+//! The SERCOM is configured as a UART first, then released:
+//!
+//! ```
+//! let uart = UART0::new(&clock, 250.kHz(), peripherals.SERCOM0, &mut pm, (rx, tx));
+//! let (_pads, sercom) = uart.free();
+//! let mut dmx = DmxOutput::new(sercom, channels.0, unsafe { &mut UNIVERSE });
+//! timer.start(40.millis());
+//! loop {
+//!     if let Some(slots) = dmx.slots() {
+//!         slots[0] = dimmer;
+//!     }
+//!     dmx.refresh(&mut timer);
+//! }
+//! ```
+
+use crate::dmac::{
+    channel::{Busy, Channel, Ready},
+    transfer::{Buffer, BufferPair},
+    ChId, Transfer,
+};
+use crate::hal::timer::CountDown;
+use crate::sercom::v2::Sercom;
+use crate::spi_display::TRIGGER_ACTION;
+
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+use crate::thumbv6m::sercom::v1::uart::send_break;
+#[cfg(feature = "min-samd51g")]
+use crate::thumbv7em::sercom::v1::uart::send_break;
+
+/// Number of bytes of a full DMX packet: the start code and 512 slots
+pub const PACKET_LEN: usize = 513;
+
+/// A DMX packet: the start code, followed by the channel slots
+pub type Universe = &'static mut [u8; PACKET_LEN];
+
+/// DMA destination writing to the `DATA` register of a SERCOM UART
+pub struct UartData {
+    data: *mut u8,
+}
+
+unsafe impl Buffer for UartData {
+    type Beat = u8;
+
+    #[inline]
+    fn dma_ptr(&mut self) -> *mut Self::Beat {
+        self.data
+    }
+
+    #[inline]
+    fn incrementing(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn buffer_len(&self) -> usize {
+        1
+    }
+}
+
+type DmxTransfer<C> = Transfer<Channel<C, Busy>, BufferPair<Universe, UartData>>;
+
+enum State<C: ChId> {
+    Idle(Channel<C, Ready>, Universe, UartData),
+    Sending(DmxTransfer<C>),
+    Moving,
+}
+
+/// A DMX512 transmitter
+pub struct DmxOutput<S: Sercom, C: ChId> {
+    sercom: S,
+    state: State<C>,
+}
+
+impl<S: Sercom, C: ChId> DmxOutput<S, C> {
+    /// Switches a SERCOM configured as a 250 kbaud UART to two stop bits,
+    /// and uses `channel` to send `universe`. The start code is set to zero,
+    /// i.e. dimmer data.
+    pub fn new(sercom: S, channel: Channel<C, Ready>, universe: Universe) -> Self {
+        universe[0] = 0;
+        #[cfg(any(feature = "samd11", feature = "samd21"))]
+        let usart = sercom.usart();
+        #[cfg(feature = "min-samd51g")]
+        let usart = sercom.usart_int();
+        // CTRLB is enable-protected
+        usart.ctrla.modify(|_, w| w.enable().clear_bit());
+        while usart.syncbusy.read().enable().bit_is_set() {}
+        usart.ctrlb.modify(|_, w| w.sbmode().set_bit());
+        while usart.syncbusy.read().ctrlb().bit_is_set() {}
+        usart.ctrla.modify(|_, w| w.enable().set_bit());
+        while usart.syncbusy.read().enable().bit_is_set() {}
+        // Breaks wait for the end of the previous character, so send one.
+        // Receivers ignore anything before the first break.
+        usart.data.write(|w| unsafe { w.bits(0xff) });
+        let data = UartData {
+            data: usart.data.as_ptr() as *mut u8,
+        };
+        Self {
+            sercom,
+            state: State::Idle(channel, universe, data),
+        }
+    }
+
+    /// Returns true if no packet is being sent
+    pub fn is_idle(&mut self) -> bool {
+        if let State::Sending(xfer) = &mut self.state {
+            if !xfer.complete() {
+                return false;
+            }
+            if let State::Sending(xfer) = core::mem::replace(&mut self.state, State::Moving) {
+                let (channel, universe, data) = xfer.stop();
+                self.state = State::Idle(channel, universe, data);
+            }
+        }
+        true
+    }
+
+    /// Gives access to the start code and the slots, unless a packet is
+    /// being sent
+    pub fn packet(&mut self) -> Option<&mut [u8; PACKET_LEN]> {
+        self.is_idle();
+        match &mut self.state {
+            State::Idle(_, universe, _) => Some(&mut **universe),
+            _ => None,
+        }
+    }
+
+    /// Gives access to the 512 slots, unless a packet is being sent
+    pub fn slots(&mut self) -> Option<&mut [u8]> {
+        self.packet().map(|packet| &mut packet[1..])
+    }
+
+    /// Sends a break and mark-after-break, then starts the DMA transfer of
+    /// the packet. Returns false if the previous packet is still being sent.
+    pub fn send(&mut self) -> bool {
+        if !self.is_idle() {
+            return false;
+        }
+        if let State::Idle(channel, universe, data) =
+            core::mem::replace(&mut self.state, State::Moving)
+        {
+            // A character was sent by `new`, or by the previous packet
+            let mut sent = true;
+            #[cfg(any(feature = "samd11", feature = "samd21"))]
+            send_break(self.sercom.usart(), &mut sent);
+            #[cfg(feature = "min-samd51g")]
+            send_break(self.sercom.usart_int(), &mut sent);
+            // SAFETY: The universe is 'static, and the transfer is never
+            // dropped while running
+            let xfer = unsafe { Transfer::new_unchecked(channel, universe, data, false) }
+                .begin(S::DMA_TX_TRIGGER, TRIGGER_ACTION);
+            self.state = State::Sending(xfer);
+        }
+        true
+    }
+
+    /// Sends a packet if `timer`, a periodic timer started at the refresh
+    /// rate, has expired. Returns true if a packet was started.
+    pub fn refresh<T: CountDown>(&mut self, timer: &mut T) -> bool {
+        timer.wait().is_ok() && self.send()
+    }
+
+    /// Waits for the packet being sent, and releases the SERCOM, the DMA
+    /// channel and the universe
+    pub fn free(self) -> (S, Channel<C, Ready>, Universe) {
+        match self.state {
+            State::Idle(channel, universe, _) => (self.sercom, channel, universe),
+            State::Sending(xfer) => {
+                let (channel, universe, _) = xfer.wait();
+                (self.sercom, channel, universe)
+            }
+            State::Moving => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! APA102 "DotStar" RGB LEDs
+//!
+//! DotStars are shifted in like an SPI device without chip select: a start
+//! frame of 32 zero bits, then 32 bits for each LED, `0b111` followed by a
+//! 5-bit global brightness and the blue, green and red levels, then an end
+//! frame of at least one clock edge per two LEDs. [`encode`] builds such a
+//! frame, so that it can be sent by any means, and [`DotStar`] sends it
+//! through any `embedded-hal` blocking SPI write:
+//!
+//! ```
+//! let ci = pins.pa01.into_push_pull_output();
+//! let di = pins.pa00.into_push_pull_output();
+//! let mut dotstar = DotStar::new(BitBang::new(ci, di));
+//! dotstar.set_brightness(8);
+//! dotstar.write([Color::new(255, 0, 128)].iter().copied())?;
+//! ```
+//!
+//! [`BitBang`] drives the clock and data lines from any two output pins, for
+//! the boards whose LED is not wired to SERCOM pads. With the `dma` feature,
+//! [`DmaDotStar`] streams long strips from a v2 [`Spi`] instead.
+//!
+//! The levels are corrected by [`GAMMA8`] by default, so that they are
+//! perceived as linear. The global brightness is applied by the LEDs
+//! themselves, with a slow PWM which does not cost any colour resolution, but
+//! which can flicker on camera at low levels.
+//!
+//! [`Spi`]: crate::sercom::v2::spi::Spi
+
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+#[cfg(feature = "dma")]
+use crate::dmac::{
+    channel::{Channel, Ready},
+    transfer::Buffer,
+    ChId, Transfer,
+};
+#[cfg(feature = "dma")]
+use crate::sercom::v2::spi::{AnySpi, Spi, ValidConfig};
+#[cfg(feature = "dma")]
+use crate::sercom::v2::Sercom;
+#[cfg(feature = "dma")]
+use crate::spi_display::TRIGGER_ACTION;
+
+/// Gamma correction of 2.8, from linear levels to the PWM duty cycle
+pub const GAMMA8: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 5, 5, 5,
+    5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8, 9, 9, 9, 10, 10, 10, 11, 11, 11, 12, 12, 13, 13, 13, 14,
+    14, 15, 15, 16, 16, 17, 17, 18, 18, 19, 19, 20, 20, 21, 21, 22, 22, 23, 24, 24, 25, 25, 26, 27,
+    27, 28, 29, 29, 30, 31, 32, 32, 33, 34, 35, 35, 36, 37, 38, 39, 39, 40, 41, 42, 43, 44, 45, 46,
+    47, 48, 49, 50, 50, 51, 52, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 66, 67, 68, 69, 70, 72,
+    73, 74, 75, 77, 78, 79, 81, 82, 83, 85, 86, 87, 89, 90, 92, 93, 95, 96, 98, 99, 101, 102, 104,
+    105, 107, 109, 110, 112, 114, 115, 117, 119, 120, 122, 124, 126, 127, 129, 131, 133, 135, 137,
+    138, 140, 142, 144, 146, 148, 150, 152, 154, 156, 158, 160, 162, 164, 167, 169, 171, 173, 175,
+    177, 180, 182, 184, 186, 189, 191, 193, 196, 198, 200, 203, 205, 208, 210, 213, 215, 218, 220,
+    223, 225, 228, 231, 233, 236, 239, 241, 244, 247, 249, 252, 255,
+];
+
+/// The highest global brightness
+pub const MAX_BRIGHTNESS: u8 = 31;
+
+const START_FRAME: [u8; 4] = [0; 4];
+
+/// The colour of an LED
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+}
+
+/// Length of the end frame of `leds` LEDs, in bytes
+const fn end_len(leds: usize) -> usize {
+    // Each LED delays the data by half a clock period, so the last one needs
+    // an extra clock edge for every two LEDs
+    (leds + 15) / 16
+}
+
+/// Length of the frame of `leds` LEDs, in bytes
+pub const fn frame_len(leds: usize) -> usize {
+    START_FRAME.len() + 4 * leds + end_len(leds)
+}
+
+/// The 4 bytes of an LED
+fn led_frame(color: Color, brightness: u8, gamma: bool) -> [u8; 4] {
+    let level = |l: u8| if gamma { GAMMA8[l as usize] } else { l };
+    [
+        0xe0 | brightness.min(MAX_BRIGHTNESS),
+        level(color.b),
+        level(color.g),
+        level(color.r),
+    ]
+}
+
+/// Writes the frame of `colors` into `buf`, at the global `brightness`, from
+/// 0 to 31, and corrected by [`GAMMA8`] if `gamma` is set. Returns the length
+/// of the frame.
+///
+/// Panics if `buf` is shorter than [`frame_len`].
+pub fn encode<I>(buf: &mut [u8], colors: I, brightness: u8, gamma: bool) -> usize
+where
+    I: IntoIterator<Item = Color>,
+{
+    buf[..START_FRAME.len()].copy_from_slice(&START_FRAME);
+    let mut len = START_FRAME.len();
+    let mut leds = 0;
+    for color in colors {
+        buf[len..len + 4].copy_from_slice(&led_frame(color, brightness, gamma));
+        len += 4;
+        leds += 1;
+    }
+    let end = end_len(leds);
+    for byte in &mut buf[len..len + end] {
+        *byte = 0xff;
+    }
+    len + end
+}
+
+/// A strip of DotStars on an SPI bus, see the [module](self) documentation
+pub struct DotStar<SPI> {
+    spi: SPI,
+    brightness: u8,
+    gamma: bool,
+}
+
+impl<SPI: Write<u8>> DotStar<SPI> {
+    /// Sends the frames on `spi`, which must be in SPI mode 0 or 3, at full
+    /// brightness and with gamma correction
+    pub fn new(spi: SPI) -> Self {
+        Self {
+            spi,
+            brightness: MAX_BRIGHTNESS,
+            gamma: true,
+        }
+    }
+
+    /// Sets the global brightness, from 0 to 31, of the next frames
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness.min(MAX_BRIGHTNESS);
+    }
+
+    /// Enables or disables the [`GAMMA8`] correction of the next frames
+    pub fn set_gamma(&mut self, gamma: bool) {
+        self.gamma = gamma;
+    }
+
+    /// Sends `colors` to the strip, starting with the first LED
+    pub fn write<I>(&mut self, colors: I) -> Result<(), SPI::Error>
+    where
+        I: IntoIterator<Item = Color>,
+    {
+        self.spi.write(&START_FRAME)?;
+        let mut leds = 0;
+        for color in colors {
+            self.spi
+                .write(&led_frame(color, self.brightness, self.gamma))?;
+            leds += 1;
+        }
+        for _ in 0..end_len(leds) {
+            self.spi.write(&[0xff])?;
+        }
+        Ok(())
+    }
+
+    /// Releases the SPI bus
+    pub fn free(self) -> SPI {
+        self.spi
+    }
+}
+
+/// A write-only SPI master in mode 0, bit-banged as fast as the pins can be
+/// toggled. The DotStars latch the data on the rising edge of the clock, and
+/// follow clocks of several MHz.
+pub struct BitBang<CI, DI> {
+    ci: CI,
+    di: DI,
+}
+
+impl<CI, DI, E> BitBang<CI, DI>
+where
+    CI: OutputPin<Error = E>,
+    DI: OutputPin<Error = E>,
+{
+    /// Drives the clock `ci` and data `di`, and sets the clock low
+    pub fn new(mut ci: CI, di: DI) -> Self {
+        ci.set_low().ok();
+        Self { ci, di }
+    }
+
+    /// Releases the pins
+    pub fn free(self) -> (CI, DI) {
+        (self.ci, self.di)
+    }
+}
+
+impl<CI, DI, E> Write<u8> for BitBang<CI, DI>
+where
+    CI: OutputPin<Error = E>,
+    DI: OutputPin<Error = E>,
+{
+    type Error = E;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), E> {
+        for &word in words {
+            for bit in (0..8).rev() {
+                if word >> bit & 1 != 0 {
+                    self.di.set_high()?;
+                } else {
+                    self.di.set_low()?;
+                }
+                self.ci.set_high()?;
+                self.ci.set_low()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A strip of DotStars whose frames are sent by the DMAC.
+///
+/// The frame is encoded into a buffer of at least [`frame_len`] bytes, then
+/// streamed to the SERCOM, so that long strips are refreshed at the full
+/// speed of the bus. Only the v2 SPI master with 8-bit transactions is
+/// supported.
+#[cfg(feature = "dma")]
+pub struct DmaDotStar<C, Id>
+where
+    C: ValidConfig,
+    Id: ChId,
+{
+    /// Moved into the DMA transfer while the frame is sent
+    parts: Option<(Spi<C>, Channel<Id, Ready>)>,
+    buf: &'static mut [u8],
+    brightness: u8,
+    gamma: bool,
+}
+
+#[cfg(feature = "dma")]
+impl<C, Id> DmaDotStar<C, Id>
+where
+    C: ValidConfig,
+    Id: ChId,
+    Spi<C>: Buffer<Beat = u8>,
+{
+    /// Uses `channel` to send the frames encoded into `buf` on `spi`, at full
+    /// brightness and with gamma correction
+    pub fn new(spi: Spi<C>, channel: Channel<Id, Ready>, buf: &'static mut [u8]) -> Self {
+        Self {
+            parts: Some((spi, channel)),
+            buf,
+            brightness: MAX_BRIGHTNESS,
+            gamma: true,
+        }
+    }
+
+    /// Sets the global brightness, from 0 to 31, of the next frames
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness.min(MAX_BRIGHTNESS);
+    }
+
+    /// Enables or disables the [`GAMMA8`] correction of the next frames
+    pub fn set_gamma(&mut self, gamma: bool) {
+        self.gamma = gamma;
+    }
+
+    /// Sends `colors` to the strip, and waits until the DMAC has copied the
+    /// whole frame to the SERCOM.
+    ///
+    /// Panics if the frame does not fit in the buffer.
+    pub fn write<I>(&mut self, colors: I)
+    where
+        I: IntoIterator<Item = Color>,
+    {
+        let len = encode(self.buf, colors, self.brightness, self.gamma);
+        let (mut spi, mut channel) = self.parts.take().unwrap();
+        for chunk in self.buf[..len].chunks_mut(u16::MAX as usize) {
+            // SAFETY: The transfer is waited on before the chunk goes out of
+            // scope, so it is never dropped while running
+            let xfer = unsafe { Transfer::new_unchecked(channel, chunk, spi, false) }
+                .begin(<Spi<C> as AnySpi>::Sercom::DMA_TX_TRIGGER, TRIGGER_ACTION);
+            let (ready, _, dest) = xfer.wait();
+            channel = ready;
+            spi = dest;
+        }
+        self.parts = Some((spi, channel));
+    }
+
+    /// Releases the SPI bus, the DMA channel and the buffer
+    pub fn free(self) -> (Spi<C>, Channel<Id, Ready>, &'static mut [u8]) {
+        let (spi, channel) = self.parts.unwrap();
+        (spi, channel, self.buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_of_two_leds() {
+        let mut buf = [0; frame_len(2)];
+        let colors = [Color::new(1, 2, 3), Color::new(255, 0, 0)];
+        let len = encode(&mut buf, colors.iter().copied(), 40, false);
+        assert_eq!(len, 13);
+        assert_eq!(buf, [0, 0, 0, 0, 0xff, 3, 2, 1, 0xff, 0, 0, 255, 0xff]);
+    }
+
+    #[test]
+    fn gamma_and_brightness() {
+        let mut buf = [0; frame_len(1)];
+        encode(&mut buf, Some(Color::new(128, 255, 0)), 4, true);
+        assert_eq!(&buf[4..8], &[0xe4, 0, 255, GAMMA8[128]]);
+        assert_eq!(GAMMA8[0], 0);
+        assert!(GAMMA8.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn end_frame_length() {
+        assert_eq!(frame_len(0), 4);
+        assert_eq!(frame_len(16), 4 + 64 + 1);
+        assert_eq!(frame_len(17), 4 + 68 + 2);
+    }
+}
@@ -0,0 +1,282 @@
+//! Double-buffered drawing for SPI displays
+//!
+//! [`DoubleBuffered`] keeps two frame buffers for a [`DmaDisplay`]. The front
+//! buffer is sent to the display by the DMAC in the background, while the
+//! application draws the next frame into the back buffer. [`swap`] presents
+//! the back buffer: the buffers are exchanged once the front buffer has been
+//! sent completely, so that a frame is never drawn while it is being sent.
+//!
+//! A frame is sent in blocks of at most 65535 bytes, the longest DMA transfer,
+//! so [`poll`] must be called regularly to start the next block, e.g. from
+//! the main loop or from the DMAC interrupt:
+//!
+//! ```
+//! let front = Rgb565Buffer::new(unsafe { &mut FRONT }, 160, 128);
+//! let back = Rgb565Buffer::new(unsafe { &mut BACK }, 160, 128);
+//! let mut frames = DoubleBuffered::new(display, front, back);
+//! loop {
+//!     if let Some(back) = frames.back() {
+//!         draw_frame(back);
+//!         frames.swap();
+//!     }
+//!     frames.poll()?;
+//! }
+//! ```
+//!
+//! After a swap, the back buffer holds the frame before the one which was
+//! presented, so it must be redrawn completely. Two RGB565 frames of a 320x240
+//! display take 300kB, more than any SAMD51 has, so large displays need a
+//! smaller window or a smaller pixel format.
+//!
+//! With the `graphics` feature, [`Rgb565Buffer`] is an `embedded-graphics`
+//! `DrawTarget`.
+//!
+//! [`swap`]: DoubleBuffered::swap
+//! [`poll`]: DoubleBuffered::poll
+
+use core::slice;
+
+use crate::dmac::{
+    channel::{Busy, Channel, Ready},
+    transfer::{Buffer, BufferPair},
+    ChId, Transfer,
+};
+use crate::hal::blocking::serial::Write;
+use crate::hal::digital::v2::OutputPin;
+use crate::sercom::v2::spi::{AnySpi, Spi, ValidConfig};
+use crate::sercom::v2::Sercom;
+use crate::spi_display::{DmaDisplay, Error, Region, BYTES_PER_PIXEL, MAX_CHUNK, TRIGGER_ACTION};
+
+#[cfg(feature = "graphics")]
+use embedded_graphics::{
+    drawable::Pixel,
+    geometry::Size,
+    pixelcolor::{
+        raw::{RawData, RawU16},
+        Rgb565,
+    },
+    DrawTarget,
+};
+
+/// Storage of a frame, as big-endian RGB565 pixels, row by row
+///
+/// # Safety
+///
+/// The DMAC reads the pixels while the frame buffer is moved around, so the
+/// bytes returned by [`pixels`](FrameBuffer::pixels) must live for `'static`,
+/// and must not move with the frame buffer.
+pub unsafe trait FrameBuffer {
+    /// Width and height of the frame, in pixels
+    fn size(&self) -> (u16, u16);
+
+    /// The bytes of the pixels, 2 per pixel
+    fn pixels(&mut self) -> &mut [u8];
+}
+
+/// A frame buffer of big-endian RGB565 pixels
+pub struct Rgb565Buffer {
+    data: &'static mut [u8],
+    width: u16,
+    height: u16,
+}
+
+impl Rgb565Buffer {
+    /// Uses `data` to store a frame of `width` by `height` pixels.
+    ///
+    /// Panics if `data` is not 2 bytes per pixel long.
+    pub fn new(data: &'static mut [u8], width: u16, height: u16) -> Self {
+        assert_eq!(
+            data.len(),
+            width as usize * height as usize * BYTES_PER_PIXEL
+        );
+        Self {
+            data,
+            width,
+            height,
+        }
+    }
+
+    /// Sets the pixel at column `x` and row `y` to `color`. Pixels outside of
+    /// the frame are ignored.
+    pub fn set_pixel(&mut self, x: u16, y: u16, color: u16) {
+        if x < self.width && y < self.height {
+            let i = (y as usize * self.width as usize + x as usize) * BYTES_PER_PIXEL;
+            self.data[i..i + BYTES_PER_PIXEL].copy_from_slice(&color.to_be_bytes());
+        }
+    }
+
+    /// Sets all of the pixels to `color`
+    pub fn fill(&mut self, color: u16) {
+        let bytes = color.to_be_bytes();
+        for pixel in self.data.chunks_exact_mut(BYTES_PER_PIXEL) {
+            pixel.copy_from_slice(&bytes);
+        }
+    }
+
+    /// Releases the storage
+    pub fn free(self) -> &'static mut [u8] {
+        self.data
+    }
+}
+
+unsafe impl FrameBuffer for Rgb565Buffer {
+    fn size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    fn pixels(&mut self) -> &mut [u8] {
+        self.data
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl DrawTarget<Rgb565> for Rgb565Buffer {
+    type Error = core::convert::Infallible;
+
+    fn draw_pixel(&mut self, item: Pixel<Rgb565>) -> Result<(), Self::Error> {
+        let Pixel(point, color) = item;
+        if point.x >= 0 && point.y >= 0 {
+            self.set_pixel(
+                point.x as u16,
+                point.y as u16,
+                RawU16::from(color).into_inner(),
+            );
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> Size {
+        Size::new(self.width as u32, self.height as u32)
+    }
+
+    fn clear(&mut self, color: Rgb565) -> Result<(), Self::Error> {
+        self.fill(RawU16::from(color).into_inner());
+        Ok(())
+    }
+}
+
+type FrameTransfer<C, Id> = Transfer<Channel<Id, Busy>, BufferPair<&'static mut [u8], Spi<C>>>;
+
+enum State<C, Id>
+where
+    C: ValidConfig,
+    Id: ChId,
+    Spi<C>: Buffer<Beat = u8>,
+{
+    /// The SPI bus and the DMA channel are in the display
+    Idle,
+    /// A block is being sent, followed by the bytes from the offset
+    Sending(FrameTransfer<C, Id>, usize),
+}
+
+/// A display drawn from two frame buffers, see the [module](self)
+/// documentation
+pub struct DoubleBuffered<FB, C, Id, DC, CS>
+where
+    C: ValidConfig,
+    Id: ChId,
+    Spi<C>: Buffer<Beat = u8>,
+{
+    display: DmaDisplay<C, Id, DC, CS>,
+    front: FB,
+    back: FB,
+    state: State<C, Id>,
+    swap_pending: bool,
+}
+
+impl<FB, C, Id, DC, CS, E> DoubleBuffered<FB, C, Id, DC, CS>
+where
+    FB: FrameBuffer,
+    C: ValidConfig,
+    Id: ChId,
+    Spi<C>: Buffer<Beat = u8> + Write<u8, Error = E>,
+    DC: OutputPin,
+    CS: OutputPin,
+{
+    /// Draws `display` from `front` and `back`, two frame buffers of the same
+    /// size. Nothing is sent until the first [`swap`](Self::swap).
+    pub fn new(display: DmaDisplay<C, Id, DC, CS>, front: FB, back: FB) -> Self {
+        assert_eq!(front.size(), back.size());
+        Self {
+            display,
+            front,
+            back,
+            state: State::Idle,
+            swap_pending: false,
+        }
+    }
+
+    /// The buffer to draw the next frame into, unless it was presented with
+    /// [`swap`](Self::swap) and is waiting for the front buffer to be sent
+    pub fn back(&mut self) -> Option<&mut FB> {
+        if self.swap_pending {
+            None
+        } else {
+            Some(&mut self.back)
+        }
+    }
+
+    /// Presents the back buffer. It becomes the front buffer, and is sent,
+    /// once the current front buffer has been sent.
+    pub fn swap(&mut self) {
+        self.swap_pending = true;
+    }
+
+    /// Returns true if no frame is being sent or waiting to be sent
+    pub fn is_idle(&self) -> bool {
+        matches!(self.state, State::Idle) && !self.swap_pending
+    }
+
+    /// Starts the next block of the front buffer when the previous one is
+    /// complete, and swaps the buffers once the whole frame has been sent
+    pub fn poll(&mut self) -> Result<(), Error<E>> {
+        if let State::Sending(xfer, _) = &mut self.state {
+            if !xfer.complete() {
+                return Ok(());
+            }
+        }
+        if let State::Sending(xfer, offset) = core::mem::replace(&mut self.state, State::Idle) {
+            let (channel, _, spi) = xfer.stop();
+            if offset < self.front.pixels().len() {
+                self.send_block(spi, channel, offset);
+                return Ok(());
+            }
+            self.display.parts = Some((spi, channel));
+            self.display.end_write()?;
+        }
+        if self.swap_pending {
+            core::mem::swap(&mut self.front, &mut self.back);
+            self.swap_pending = false;
+            let (width, height) = self.front.size();
+            self.display
+                .begin_write(Region::new(0, 0, width - 1, height - 1))?;
+            let (spi, channel) = self.display.parts.take().unwrap();
+            self.send_block(spi, channel, 0);
+        }
+        Ok(())
+    }
+
+    /// Starts sending the front buffer from `offset`
+    fn send_block(&mut self, spi: Spi<C>, channel: Channel<Id, Ready>, offset: usize) {
+        let pixels = self.front.pixels();
+        let end = pixels.len().min(offset + MAX_CHUNK);
+        // SAFETY: The pixels of a frame buffer live for 'static, and the front
+        // buffer isn't accessed until the transfer is complete
+        let block =
+            unsafe { slice::from_raw_parts_mut(pixels.as_mut_ptr().add(offset), end - offset) };
+        // SAFETY: The transfer is stopped once complete, and never dropped
+        // while running, see `free`
+        let xfer = unsafe { Transfer::new_unchecked(channel, block, spi, false) }
+            .begin(<Spi<C> as AnySpi>::Sercom::DMA_TX_TRIGGER, TRIGGER_ACTION);
+        self.state = State::Sending(xfer, end);
+    }
+
+    /// Sends the frame being sent and the presented frame, if any, then
+    /// releases the display and the frame buffers
+    pub fn free(mut self) -> Result<(DmaDisplay<C, Id, DC, CS>, FB, FB), Error<E>> {
+        while !self.is_idle() {
+            self.poll()?;
+        }
+        Ok((self.display, self.front, self.back))
+    }
+}
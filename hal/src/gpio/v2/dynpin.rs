@@ -101,6 +101,7 @@ pub enum DynInterrupt {
 pub enum DynOutput {
     PushPull,
     Readable,
+    OpenDrainPullUp,
 }
 
 /// Value-level `enum` for alternate peripheral function configurations
@@ -167,6 +168,10 @@ pub const DYN_PULL_UP_INTERRUPT: DynPinMode = DynPinMode::Interrupt(DynInterrupt
 pub const DYN_PUSH_PULL_OUTPUT: DynPinMode = DynPinMode::Output(DynOutput::PushPull);
 /// Value-level variant of [`DynPinMode`] for readable push-pull output mode
 pub const DYN_READABLE_OUTPUT: DynPinMode = DynPinMode::Output(DynOutput::Readable);
+/// Value-level variant of [`DynPinMode`] for open-drain output mode with the
+/// internal pull-up enabled
+pub const DYN_OPEN_DRAIN_PULL_UP_OUTPUT: DynPinMode =
+    DynPinMode::Output(DynOutput::OpenDrainPullUp);
 
 macro_rules! dyn_alternate {
     ( $($Letter:ident),+ ) => {
@@ -376,6 +381,13 @@ impl DynPin {
         self.into_mode(DYN_READABLE_OUTPUT);
     }
 
+    /// Configure the pin to operate as an open-drain output with the internal
+    /// pull-up enabled
+    #[inline]
+    pub fn into_open_drain_pull_up_output(&mut self) {
+        self.into_mode(DYN_OPEN_DRAIN_PULL_UP_OUTPUT);
+    }
+
     /// Configure the pin to operate as the corresponding peripheral function.
     ///
     /// The `config` argument indicates the desired peripheral function.
@@ -403,28 +415,37 @@ impl DynPin {
     #[inline]
     fn _read(&self) -> Result<bool, Error> {
         match self.mode {
-            DynPinMode::Input(_) | DYN_READABLE_OUTPUT => Ok(self.regs.read_pin()),
+            DynPinMode::Input(_) | DYN_READABLE_OUTPUT | DYN_OPEN_DRAIN_PULL_UP_OUTPUT => {
+                Ok(self.regs.read_pin())
+            }
             _ => Err(Error::InvalidPinType),
         }
     }
     #[inline]
     fn _write(&mut self, bit: bool) -> Result<(), Error> {
         match self.mode {
-            DynPinMode::Output(_) => Ok(self.regs.write_pin(bit)),
-            _ => Err(Error::InvalidPinType),
+            DYN_OPEN_DRAIN_PULL_UP_OUTPUT => self.regs.write_open_drain(bit),
+            DynPinMode::Output(_) => self.regs.write_pin(bit),
+            _ => return Err(Error::InvalidPinType),
         }
+        Ok(())
     }
     #[inline]
     fn _toggle(&mut self) -> Result<(), Error> {
         match self.mode {
-            DynPinMode::Output(_) => Ok(self.regs.toggle_pin()),
-            _ => Err(Error::InvalidPinType),
+            DYN_OPEN_DRAIN_PULL_UP_OUTPUT => {
+                let bit = self.regs.read_out_pin();
+                self.regs.write_open_drain(!bit);
+            }
+            DynPinMode::Output(_) => self.regs.toggle_pin(),
+            _ => return Err(Error::InvalidPinType),
         }
+        Ok(())
     }
     #[inline]
     fn _read_out(&self) -> Result<bool, Error> {
         match self.mode {
-            DYN_READABLE_OUTPUT => Ok(self.regs.read_out_pin()),
+            DYN_READABLE_OUTPUT | DYN_OPEN_DRAIN_PULL_UP_OUTPUT => Ok(self.regs.read_out_pin()),
             _ => Err(Error::InvalidPinType),
         }
     }
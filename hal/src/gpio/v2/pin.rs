@@ -247,7 +247,8 @@ pub type PullUpInterrupt = Interrupt<PullUp>;
 
 /// Type-level enum for output configurations
 ///
-/// The valid options are [`PushPull`] and [`Readable`]. See the [type-level
+/// The valid options are [`PushPull`], [`Readable`] and [`OpenDrainPullUp`].
+/// See the [type-level
 /// enum] documentation for more details on the pattern.
 ///
 /// [type-level enum]: crate::typelevel#type-level-enum
@@ -261,9 +262,18 @@ pub enum PushPull {}
 /// Type-level variant of [`OutputConfig`] for a readable push-pull
 /// configuration
 pub enum Readable {}
+/// Type-level variant of [`OutputConfig`] for an open-drain configuration
+/// with the internal pull-up enabled
+///
+/// The PORT has no true open-drain mode. Instead, the pin is driven low for
+/// a low level and switched to a pulled-up input for a high level. The pin
+/// can always be read, which makes this mode suitable for bit-banged I2C and
+/// other wired-AND buses.
+pub enum OpenDrainPullUp {}
 
 impl Sealed for PushPull {}
 impl Sealed for Readable {}
+impl Sealed for OpenDrainPullUp {}
 
 impl OutputConfig for PushPull {
     const DYN: DynOutput = DynOutput::PushPull;
@@ -271,10 +281,14 @@ impl OutputConfig for PushPull {
 impl OutputConfig for Readable {
     const DYN: DynOutput = DynOutput::Readable;
 }
+impl OutputConfig for OpenDrainPullUp {
+    const DYN: DynOutput = DynOutput::OpenDrainPullUp;
+}
 
 /// Type-level variant of [`PinMode`] for output modes
 ///
-/// Type `C` is one of two output configurations: [`PushPull`] or [`Readable`]
+/// Type `C` is one of three output configurations: [`PushPull`], [`Readable`]
+/// or [`OpenDrainPullUp`]
 pub struct Output<C: OutputConfig> {
     cfg: PhantomData<C>,
 }
@@ -287,6 +301,10 @@ pub type PushPullOutput = Output<PushPull>;
 /// Type-level variant of [`PinMode`] for readable push-pull output mode
 pub type ReadableOutput = Output<Readable>;
 
+/// Type-level variant of [`PinMode`] for open-drain output mode with the
+/// internal pull-up enabled
+pub type OpenDrainPullUpOutput = Output<OpenDrainPullUp>;
+
 //==============================================================================
 //  Alternate configurations
 //==============================================================================
@@ -589,6 +607,13 @@ where
         self.into_mode()
     }
 
+    /// Configure the pin to operate as an open-drain output with the internal
+    /// pull-up enabled
+    #[inline]
+    pub fn into_open_drain_pull_up_output(self) -> Pin<I, OpenDrainPullUpOutput> {
+        self.into_mode()
+    }
+
     /// Configure the pin to operate as the corresponding peripheral function.
     ///
     /// The type `C` indicates the desired peripheral function.
@@ -625,17 +650,30 @@ where
 
     #[inline]
     pub(crate) fn _set_low(&mut self) {
-        self.regs.write_pin(false);
+        if M::DYN == DYN_OPEN_DRAIN_PULL_UP_OUTPUT {
+            self.regs.write_open_drain(false);
+        } else {
+            self.regs.write_pin(false);
+        }
     }
 
     #[inline]
     pub(crate) fn _set_high(&mut self) {
-        self.regs.write_pin(true);
+        if M::DYN == DYN_OPEN_DRAIN_PULL_UP_OUTPUT {
+            self.regs.write_open_drain(true);
+        } else {
+            self.regs.write_pin(true);
+        }
     }
 
     #[inline]
     pub(crate) fn _toggle(&mut self) {
-        self.regs.toggle_pin();
+        if M::DYN == DYN_OPEN_DRAIN_PULL_UP_OUTPUT {
+            let bit = self.regs.read_out_pin();
+            self.regs.write_open_drain(!bit);
+        } else {
+            self.regs.toggle_pin();
+        }
     }
 
     #[inline]
@@ -649,6 +687,75 @@ where
     }
 }
 
+//==============================================================================
+//  Fast paths
+//==============================================================================
+
+impl<I, C> Pin<I, Output<C>>
+where
+    I: PinId,
+    C: OutputConfig,
+{
+    /// Drive the pin high through the fast path
+    ///
+    /// This is always inlined and compiles down to a single store to a
+    /// constant address. On the SAMD11 and SAMD21, the store goes through the
+    /// single-cycle IOBUS, so back-to-back calls toggle the pin every CPU
+    /// cycle. On the SAMD51, the store goes through the APB bridge and takes a
+    /// few cycles, which is still deterministic. See the `gpio_toggle`
+    /// example of the Metro M0 and M4 boards for measured toggle frequencies.
+    ///
+    /// Open-drain outputs take the regular, slower path.
+    #[inline(always)]
+    pub fn set_high_fast(&mut self) {
+        if C::DYN == DynOutput::OpenDrainPullUp {
+            self.regs.write_open_drain(true);
+        } else {
+            self.regs.write_pin_fast(true);
+        }
+    }
+
+    /// Drive the pin low through the fast path. See
+    /// [`set_high_fast`](Self::set_high_fast).
+    #[inline(always)]
+    pub fn set_low_fast(&mut self) {
+        if C::DYN == DynOutput::OpenDrainPullUp {
+            self.regs.write_open_drain(false);
+        } else {
+            self.regs.write_pin_fast(false);
+        }
+    }
+
+    /// Toggle the pin through the fast path. See
+    /// [`set_high_fast`](Self::set_high_fast).
+    #[inline(always)]
+    pub fn toggle_fast(&mut self) {
+        if C::DYN == DynOutput::OpenDrainPullUp {
+            self._toggle();
+        } else {
+            self.regs.toggle_pin_fast();
+        }
+    }
+}
+
+/// Drive several push-pull output pins of the same group at once, through the
+/// fast path of [`Pin::set_high_fast`]
+///
+/// The pins of `group` selected by `mask` take the levels of the
+/// corresponding bits of `value`. The pins driven high all change on the
+/// same cycle, followed by the pins driven low on the next write. This suits
+/// bit-banged parallel buses, e.g. writing a byte to eight consecutive pins.
+///
+/// # Safety
+///
+/// The caller must own every pin selected by `mask`, e.g. by holding them as
+/// [`PushPullOutput`] pins, and must not use them through any other path
+/// concurrently.
+#[inline(always)]
+pub unsafe fn write_masked(group: DynGroup, mask: u32, value: u32) {
+    super::reg::write_masked(group, mask, value);
+}
+
 //==============================================================================
 //  PinMode conversions
 //==============================================================================
@@ -729,6 +836,7 @@ impl_core_convert_from!(
     PullUpInput,
     PushPullOutput,
     ReadableOutput,
+    OpenDrainPullUpOutput,
     FloatingInterrupt,
     PullUpInterrupt,
     PullDownInterrupt,
@@ -904,6 +1012,22 @@ where
     }
 }
 
+#[cfg(feature = "unproven")]
+impl<I> InputPin for Pin<I, OpenDrainPullUpOutput>
+where
+    I: PinId,
+{
+    type Error = Infallible;
+    #[inline]
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(self._is_high())
+    }
+    #[inline]
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(self._is_low())
+    }
+}
+
 #[cfg(feature = "unproven")]
 impl<I, C> InputPin for Pin<I, Input<C>>
 where
@@ -11,9 +11,52 @@ use crate::target_device::port::group::{
 };
 
 use crate::target_device::PORT;
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+use crate::target_device::PORT_IOBUS;
 
 use super::dynpin::*;
 
+/// Pointer to the array of [`GROUP`] register blocks used by the fast paths
+///
+/// The Cortex-M0+ can reach the PORT through its single-cycle IOBUS, which
+/// mirrors the APB registers. The Cortex-M4 has no such port, so the fast
+/// paths use the APB registers themselves.
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+const FAST_GROUPS: *mut GROUP = PORT_IOBUS::ptr() as *mut _;
+#[cfg(feature = "min-samd51g")]
+const FAST_GROUPS: *mut GROUP = PORT::ptr() as *mut _;
+
+/// Offset of a [`DynGroup`] in the array of [`GROUP`] register blocks
+#[inline(always)]
+fn group_offset(group: DynGroup) -> usize {
+    match group {
+        DynGroup::A => 0,
+        #[cfg(any(feature = "samd21", feature = "min-samd51g"))]
+        DynGroup::B => 1,
+        #[cfg(feature = "min-samd51n")]
+        DynGroup::C => 2,
+        #[cfg(feature = "min-samd51p")]
+        DynGroup::D => 3,
+    }
+}
+
+/// Drive the pins of `group` selected by `mask` to the levels of the
+/// corresponding bits of `value`, through the fast path
+///
+/// The pins that are set and those that are cleared each change with a
+/// single write, set first.
+///
+/// # Safety
+///
+/// The caller must own every pin selected by `mask`, and they must all be
+/// push-pull outputs.
+#[inline(always)]
+pub(super) unsafe fn write_masked(group: DynGroup, mask: u32, value: u32) {
+    let group = FAST_GROUPS.add(group_offset(group));
+    (*group).outset.write(|w| w.bits(value & mask));
+    (*group).outclr.write(|w| w.bits(!value & mask));
+}
+
 //==============================================================================
 //  ModeFields
 //==============================================================================
@@ -99,6 +142,14 @@ impl From<DynPinMode> for ModeFields {
                     Readable => {
                         fields.inen = true;
                     }
+                    OpenDrainPullUp => {
+                        // Start released, with the pin pulled up. The pin is
+                        // only ever driven low, by setting DIR.
+                        fields.dir = false;
+                        fields.inen = true;
+                        fields.pullen = true;
+                        fields.out = true;
+                    }
                 }
             }
             Alternate(config) => {
@@ -229,16 +280,7 @@ pub(super) unsafe trait RegisterInterface {
 
     #[inline]
     fn group(&self) -> *const GROUP {
-        let offset = match self.id().group {
-            DynGroup::A => 0,
-            #[cfg(any(feature = "samd21", feature = "min-samd51g"))]
-            DynGroup::B => 1,
-            #[cfg(feature = "min-samd51n")]
-            DynGroup::C => 2,
-            #[cfg(feature = "min-samd51p")]
-            DynGroup::D => 3,
-        };
-        unsafe { Self::GROUPS.add(offset) }
+        unsafe { Self::GROUPS.add(group_offset(self.id().group)) }
     }
 
     #[inline]
@@ -288,34 +330,9 @@ pub(super) unsafe trait RegisterInterface {
     /// particular PinId/DynPinId.
     #[inline]
     fn change_mode(&mut self, mode: DynPinMode) {
-        let group = self.group_mut();
-        let ModeFields {
-            dir,
-            inen,
-            pullen,
-            out,
-            pmuxen,
-            pmux,
-        } = mode.into();
-        unsafe {
-            // The bit patterns here are guaranteed to be safe, because they can
-            // ultimately be traced back to associated constants defined on the
-            // `PinId` and `PinMode` traits, which are guaranteed to be correct.
-            (*group).wrconfig.write(|w| {
-                w.hwsel().bit(self.hwsel());
-                w.wrpincfg().set_bit();
-                w.wrpmux().set_bit();
-                w.pmux().bits(pmux);
-                w.pullen().bit(pullen);
-                w.inen().bit(inen);
-                w.pmuxen().bit(pmuxen);
-                w.pinmask().bits(self.mask_16())
-            });
-        }
-        self.set_dir(dir);
-        if pullen {
-            self.write_pin(out)
-        };
+        // Safe because the implementing type has control of the pin ID, and
+        // `&mut self` guarantees exclusive access to it
+        unsafe { change_mode(self.id(), mode) }
     }
 
     /// Set the direction of a pin
@@ -362,6 +379,26 @@ pub(super) unsafe trait RegisterInterface {
         }
     }
 
+    /// Write the logic level of an open-drain output pin
+    ///
+    /// The PORT has no open-drain mode, so it is emulated by switching the
+    /// pin between a low output and a pulled-up input. OUT doubles as the
+    /// pull direction while the pin is an input, so the order of the writes
+    /// matters: the pull-up is disabled before driving low and only enabled
+    /// once the pin is an input again. That way the pin is never driven high.
+    #[inline]
+    fn write_open_drain(&mut self, bit: bool) {
+        if bit {
+            self.set_dir(false);
+            self.write_pin(true);
+            self.pincfg_mut().modify(|_, w| w.pullen().set_bit());
+        } else {
+            self.pincfg_mut().modify(|_, w| w.pullen().clear_bit());
+            self.write_pin(false);
+            self.set_dir(true);
+        }
+    }
+
     /// Toggle the logic level of an output pin
     #[inline]
     fn toggle_pin(&mut self) {
@@ -394,4 +431,85 @@ pub(super) unsafe trait RegisterInterface {
     fn write_drive_strength(&mut self, bit: bool) {
         self.pincfg_mut().modify(|_, w| w.drvstr().bit(bit));
     }
+
+    /// Write the logic level of an output pin through the fast path
+    ///
+    /// Unlike [`write_pin`](Self::write_pin), this is always inlined, so with
+    /// a type-level pin ID it compiles down to a single store to a constant
+    /// address.
+    #[inline(always)]
+    fn write_pin_fast(&mut self, bit: bool) {
+        let group = unsafe { FAST_GROUPS.add(group_offset(self.id().group)) };
+        let mask = self.mask_32();
+        // Same as `write_pin`, through the fast path
+        unsafe {
+            if bit {
+                (*group).outset.write(|w| w.bits(mask));
+            } else {
+                (*group).outclr.write(|w| w.bits(mask));
+            }
+        }
+    }
+
+    /// Toggle the logic level of an output pin through the fast path
+    #[inline(always)]
+    fn toggle_pin_fast(&mut self) {
+        let group = unsafe { FAST_GROUPS.add(group_offset(self.id().group)) };
+        let mask = self.mask_32();
+        // Same as `toggle_pin`, through the fast path
+        unsafe { (*group).outtgl.write(|w| w.bits(mask)) };
+    }
+}
+
+/// Registers of a pin, identified by its [`DynPinId`] alone
+struct IdRegisters {
+    id: DynPinId,
+}
+
+// Only created by `change_mode`, whose caller guarantees control of the pin ID
+unsafe impl RegisterInterface for IdRegisters {
+    #[inline]
+    fn id(&self) -> DynPinId {
+        self.id
+    }
+}
+
+/// Change the mode of a pin, see [`RegisterInterface::change_mode`]
+///
+/// This is not inlined, so that a single copy of the register writes is shared
+/// by all of the pins and modes, rather than one for each combination. This
+/// matters on the SAMD11, with only 16 kB of flash.
+///
+/// # Safety
+///
+/// The caller must have control of the pin ID.
+#[inline(never)]
+unsafe fn change_mode(id: DynPinId, mode: DynPinMode) {
+    let mut regs = IdRegisters { id };
+    let group = regs.group_mut();
+    let ModeFields {
+        dir,
+        inen,
+        pullen,
+        out,
+        pmuxen,
+        pmux,
+    } = mode.into();
+    // The bit patterns here are guaranteed to be safe, because they can
+    // ultimately be traced back to associated constants defined on the
+    // `PinId` and `PinMode` traits, which are guaranteed to be correct.
+    (*group).wrconfig.write(|w| {
+        w.hwsel().bit(regs.hwsel());
+        w.wrpincfg().set_bit();
+        w.wrpmux().set_bit();
+        w.pmux().bits(pmux);
+        w.pullen().bit(pullen);
+        w.inen().bit(inen);
+        w.pmuxen().bit(pmuxen);
+        w.pinmask().bits(regs.mask_16())
+    });
+    regs.set_dir(dir);
+    if pullen {
+        regs.write_pin(out)
+    };
 }
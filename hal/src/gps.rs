@@ -0,0 +1,357 @@
+//! GPS time keeping: NMEA sentences and PPS discipline of the RTC
+//!
+//! GPS receivers send their position and time as NMEA sentences on a UART,
+//! and mark the start of each UTC second with a pulse on their PPS pin. The
+//! sentences arrive a few hundred milliseconds late, but the pulse is
+//! accurate to a microsecond or so.
+//!
+//! * [`NmeaBuffer`] assembles the received bytes into sentences, and checks
+//!   their checksum.
+//! * [`UtcTime::from_rmc`] extracts the time of the last pulse from an `RMC`
+//!   sentence.
+//! * [`PpsDiscipline`] captures the RTC counter on each pulse, trims the RTC
+//!   with its `FREQCORR` register so that it counts exactly its nominal
+//!   frequency, and turns later RTC counts into UTC timestamps.
+//!
+//! The PPS pin is an EIC input with a rising edge interrupt, whose handler
+//! captures the RTC counter as early as possible:
+//!
+//! ```
+//! // In the EIC interrupt
+//! pps_pin.clear_interrupt();
+//! pps.on_pps(&mut rtc);
+//!
+//! // In the main loop
+//! if let Ok(byte) = uart.read() {
+//!     if let Some(time) = nmea.push(byte).and_then(UtcTime::from_rmc) {
+//!         pps.set_time(time);
+//!     }
+//! }
+//! let timestamp = pps.timestamp(rtc.count32());
+//! ```
+
+#[cfg(feature = "device")]
+use crate::rtc::{Count32Mode, Rtc};
+
+/// Longest NMEA sentence, including the `$` and the line ending
+pub const MAX_SENTENCE_LEN: usize = 82;
+
+/// Assembles NMEA sentences from the bytes received from a GPS
+pub struct NmeaBuffer {
+    buf: [u8; MAX_SENTENCE_LEN],
+    len: usize,
+    receiving: bool,
+}
+
+impl NmeaBuffer {
+    /// Creates a buffer waiting for the start of a sentence
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; MAX_SENTENCE_LEN],
+            len: 0,
+            receiving: false,
+        }
+    }
+
+    /// Adds a received byte. Once a sentence with a valid checksum is
+    /// complete, returns its content between the `$` and the `*`, such as
+    /// `GPRMC,123519,A,...`.
+    pub fn push(&mut self, byte: u8) -> Option<&str> {
+        match byte {
+            b'$' => {
+                self.receiving = true;
+                self.len = 0;
+                None
+            }
+            b'\r' => None,
+            b'\n' if self.receiving => {
+                self.receiving = false;
+                checked_sentence(&self.buf[..self.len])
+            }
+            _ if self.receiving => {
+                if self.len < MAX_SENTENCE_LEN {
+                    self.buf[self.len] = byte;
+                    self.len += 1;
+                } else {
+                    // Drop overlong sentences
+                    self.receiving = false;
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Default for NmeaBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn checked_sentence(line: &[u8]) -> Option<&str> {
+    if line.len() < 3 || line[line.len() - 3] != b'*' {
+        return None;
+    }
+    let (data, checksum) = line.split_at(line.len() - 3);
+    let expected = (hex_digit(checksum[1])? << 4) | hex_digit(checksum[2])?;
+    if data.iter().fold(0, |sum, b| sum ^ b) != expected {
+        return None;
+    }
+    core::str::from_utf8(data).ok()
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        _ => None,
+    }
+}
+
+fn two_digits(s: &[u8]) -> Option<u8> {
+    match s {
+        [a @ b'0'..=b'9', b @ b'0'..=b'9', ..] => Some((a - b'0') * 10 + (b - b'0')),
+        _ => None,
+    }
+}
+
+/// A UTC date and time, to the second
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct UtcTime {
+    /// Year, from 2000 to 2099
+    pub year: u16,
+    /// Month, from 1 to 12
+    pub month: u8,
+    /// Day of the month, from 1 to 31
+    pub day: u8,
+    /// Hour, from 0 to 23
+    pub hour: u8,
+    /// Minute, from 0 to 59
+    pub minute: u8,
+    /// Second, from 0 to 60
+    pub second: u8,
+}
+
+impl UtcTime {
+    /// Parses the time and date of an `RMC` sentence, as returned by
+    /// [`NmeaBuffer::push`], from any talker. Returns `None` for other
+    /// sentences, or if the receiver has no fix.
+    pub fn from_rmc(sentence: &str) -> Option<Self> {
+        let mut fields = sentence.split(',');
+        if !fields.next()?.ends_with("RMC") {
+            return None;
+        }
+        let time = fields.next()?.as_bytes();
+        if fields.next()? != "A" {
+            return None;
+        }
+        let date = fields.nth(6)?.as_bytes();
+        if time.len() < 6 || date.len() < 6 {
+            return None;
+        }
+        Some(Self {
+            year: 2000 + two_digits(&date[4..])? as u16,
+            month: two_digits(&date[2..])?,
+            day: two_digits(date)?,
+            hour: two_digits(time)?,
+            minute: two_digits(&time[2..])?,
+            second: two_digits(&time[4..])?,
+        })
+    }
+
+    /// Returns the number of seconds since midnight
+    pub fn seconds_of_day(&self) -> u32 {
+        self.hour as u32 * 3600 + self.minute as u32 * 60 + self.second as u32
+    }
+}
+
+/// An RTC count converted to UTC
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Timestamp {
+    /// Time of the last PPS pulse
+    pub utc: UtcTime,
+    /// Time since the last PPS pulse, in nanoseconds
+    pub nanos: u32,
+}
+
+/// Frequency correction of the RTC from a PPS signal
+///
+/// The RTC counter is captured on each pulse. Once `window` consecutive
+/// pulses have been captured, the number of RTC ticks is compared with the
+/// nominal frequency, and the error is added to the correction. A pulse
+/// arriving more than 0.4% early or late, e.g. because the receiver lost its
+/// fix, restarts the window.
+///
+/// A 32.768kHz RTC counts one tick in 30ppm, so the window must be at least
+/// 32 seconds long for a resolution of 1ppm.
+pub struct PpsDiscipline {
+    hz: u32,
+    window: u32,
+    correction: i8,
+    start: u32,
+    last: Option<u32>,
+    pulses: u32,
+    ticks_per_second: u32,
+    utc: Option<UtcTime>,
+}
+
+/// Correction steps per unit of relative frequency error
+const STEPS_PER_UNIT: i64 = 4096 * 240;
+
+impl PpsDiscipline {
+    /// Disciplines an RTC counting at `hz`, updating the correction every
+    /// `window` pulses
+    pub const fn new(hz: u32, window: u32) -> Self {
+        Self {
+            hz,
+            window,
+            correction: 0,
+            start: 0,
+            last: None,
+            pulses: 0,
+            ticks_per_second: hz,
+            utc: None,
+        }
+    }
+
+    /// The current correction, to be written to the RTC with
+    /// [`set_frequency_correction`](crate::rtc::Rtc::set_frequency_correction)
+    pub fn correction(&self) -> i8 {
+        self.correction
+    }
+
+    /// Accounts for a PPS pulse, captured at the RTC `count`. Returns the new
+    /// correction at the end of each window.
+    pub fn pps(&mut self, count: u32) -> Option<i8> {
+        // The time of this pulse is not known yet
+        self.utc = None;
+        let last = match self.last.replace(count) {
+            Some(last) => last,
+            None => {
+                self.restart(count);
+                return None;
+            }
+        };
+        let delta = count.wrapping_sub(last);
+        let tolerance = self.hz / 256;
+        if delta < self.hz - tolerance || delta > self.hz + tolerance {
+            self.restart(count);
+            return None;
+        }
+        self.ticks_per_second = delta;
+        self.pulses += 1;
+        if self.pulses < self.window {
+            return None;
+        }
+        let elapsed = count.wrapping_sub(self.start) as i64;
+        self.restart(count);
+        let expected = self.hz as i64 * self.window as i64;
+        let steps = (elapsed - expected) * STEPS_PER_UNIT / expected;
+        let correction = (self.correction as i64 + steps).clamp(-127, 127);
+        self.correction = correction as i8;
+        Some(self.correction)
+    }
+
+    fn restart(&mut self, count: u32) {
+        self.start = count;
+        self.pulses = 0;
+    }
+
+    /// Sets the UTC time of the last pulse, usually from the `RMC` sentence
+    /// following it
+    pub fn set_time(&mut self, utc: UtcTime) {
+        self.utc = Some(utc);
+    }
+
+    /// Converts an RTC count to UTC. Returns `None` until the time of the
+    /// last pulse is set, or if more than a second has passed since then.
+    pub fn timestamp(&self, count: u32) -> Option<Timestamp> {
+        let utc = self.utc?;
+        let elapsed = count.wrapping_sub(self.last?);
+        if elapsed >= self.ticks_per_second {
+            return None;
+        }
+        let nanos = elapsed as u64 * 1_000_000_000 / self.ticks_per_second as u64;
+        Some(Timestamp {
+            utc,
+            nanos: nanos as u32,
+        })
+    }
+}
+
+#[cfg(feature = "device")]
+impl PpsDiscipline {
+    /// Captures the RTC counter for a PPS pulse, and applies the new
+    /// correction at the end of each window. Call it from the EIC interrupt
+    /// of the PPS pin.
+    pub fn on_pps(&mut self, rtc: &mut Rtc<Count32Mode>) -> Option<i8> {
+        let correction = self.pps(rtc.count32())?;
+        rtc.set_frequency_correction(correction);
+        Some(correction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RMC: &[u8] = b"$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A\r\n";
+
+    #[test]
+    fn nmea_checksum() {
+        let mut nmea = NmeaBuffer::new();
+        let (last, bytes) = RMC.split_last().unwrap();
+        for &byte in bytes {
+            assert_eq!(nmea.push(byte), None);
+        }
+        let sentence = nmea.push(*last).unwrap();
+        assert!(sentence.starts_with("GPRMC,123519"));
+
+        let mut corrupted = [0; 70];
+        corrupted.copy_from_slice(RMC);
+        corrupted[10] = b'6';
+        assert!(corrupted.iter().all(|&byte| nmea.push(byte).is_none()));
+    }
+
+    #[test]
+    fn rmc_time() {
+        let time =
+            UtcTime::from_rmc("GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W")
+                .unwrap();
+        assert_eq!((time.year, time.month, time.day), (2094, 3, 23));
+        assert_eq!(time.seconds_of_day(), 12 * 3600 + 35 * 60 + 19);
+        assert_eq!(UtcTime::from_rmc("GPRMC,123519,V,,,,,,,230394,,"), None);
+        assert_eq!(UtcTime::from_rmc("GPGGA,123519,4807.038,N"), None);
+    }
+
+    #[test]
+    fn pps_correction() {
+        let mut pps = PpsDiscipline::new(32_768, 64);
+        // The RTC runs 10ppm fast
+        let mut count = 0u32;
+        assert_eq!(pps.pps(count), None);
+        for second in 1..=64 {
+            count = count.wrapping_add(32_768 + if second <= 21 { 1 } else { 0 });
+            let correction = pps.pps(count);
+            assert_eq!(correction.is_some(), second == 64);
+        }
+        assert_eq!(pps.correction(), 9);
+        // A missed pulse restarts the window
+        assert_eq!(pps.pps(count.wrapping_add(2 * 32_768)), None);
+    }
+
+    #[test]
+    fn pps_timestamp() {
+        let mut pps = PpsDiscipline::new(32_768, 32);
+        let time = UtcTime::from_rmc("GNRMC,000001.00,A,,,,,,,010121,,").unwrap();
+        pps.pps(1000);
+        assert_eq!(pps.timestamp(1000), None);
+        pps.set_time(time);
+        let timestamp = pps.timestamp(1000 + 16_384).unwrap();
+        assert_eq!(timestamp.utc, time);
+        assert_eq!(timestamp.nanos, 500_000_000);
+        assert_eq!(pps.timestamp(1000 + 32_768), None);
+    }
+}
@@ -0,0 +1,336 @@
+//! HC-SR04 ultrasonic distance sensor
+//!
+//! The sensor is started by a pulse of at least 10µs on its trigger pin. It
+//! then sends an ultrasonic burst and holds its echo pin high for as long as
+//! the sound takes to come back, or for about 38ms if nothing was in range.
+//!
+//! [`Ranger`] fires the trigger through any [`Trigger`] and times the echo
+//! against a [`Tick`] source. The trigger can be a plain output pin
+//! ([`PinTrigger`]) or a TCC channel in one-shot mode
+//! ([`PulseTrigger`](crate::pwm::PulseTrigger)), which generates the pulse in
+//! hardware.
+//!
+//! The echo can be timed in three ways:
+//!
+//! * [`Ranger::measure`] busy-waits on the echo pin.
+//! * [`Ranger::start`] fires the trigger and returns immediately. Configure
+//!   the echo pin as an external interrupt sensing both edges, call
+//!   [`Ranger::on_echo_edge`] from the EIC interrupt handler, and collect the
+//!   result with [`Ranger::poll`].
+//! * [`Ranger::start_capture`] fires the trigger, and a timer counter
+//!   captures the width of the echo in hardware, see
+//!   `TimerCounter::into_pulse_capture`. Collect the result with
+//!   [`Ranger::poll_capture`].
+//!
+//! The resolution of the measurement is that of the tick source: a 32.768kHz
+//! RTC resolves about 5mm, a 1MHz timer counter about 0.2mm. With a capture,
+//! it is that of the capturing timer instead.
+
+use crate::tick::{Tick, Timeout};
+use crate::time::Microseconds;
+use crate::timer_traits::PulseCapture;
+use hal::digital::v2::{InputPin, OutputPin};
+
+/// Speed of sound in air at 20°C, in mm/s
+const SPEED_OF_SOUND: u64 = 343_000;
+
+/// Longest echo pulse the sensor produces, when no obstacle is in range
+const MAX_ECHO: Microseconds = Microseconds::from_ticks(38_000);
+
+/// Maximum delay between the trigger and the start of the echo pulse
+const MAX_ECHO_DELAY: Microseconds = Microseconds::from_ticks(10_000);
+
+/// Width of the trigger pulse
+const TRIGGER_WIDTH: Microseconds = Microseconds::from_ticks(10);
+
+/// A way of sending the trigger pulse to the sensor
+pub trait Trigger {
+    type Error;
+
+    /// Sends a pulse of at least 10µs
+    fn trigger<T: Tick>(&mut self, tick: &T) -> Result<(), Self::Error>;
+}
+
+/// Bit-banged trigger pulse on an output pin
+pub struct PinTrigger<P> {
+    pin: P,
+}
+
+impl<P: OutputPin> PinTrigger<P> {
+    /// Drives the pin low and wraps it
+    pub fn new(mut pin: P) -> Result<Self, P::Error> {
+        pin.set_low()?;
+        Ok(Self { pin })
+    }
+
+    /// Releases the pin
+    pub fn free(self) -> P {
+        self.pin
+    }
+}
+
+impl<P: OutputPin> Trigger for PinTrigger<P> {
+    type Error = P::Error;
+
+    fn trigger<T: Tick>(&mut self, tick: &T) -> Result<(), Self::Error> {
+        self.pin.set_high()?;
+        // One extra tick ensures the full width elapses, even if the counter
+        // increments right after the pulse starts
+        let ticks = T::to_ticks(TRIGGER_WIDTH) + 1;
+        let start = tick.ticks();
+        while tick.ticks_since(start) < ticks {}
+        self.pin.set_low()
+    }
+}
+
+/// Errors of a distance measurement
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Error<E> {
+    /// The trigger or the echo pin reported an error
+    Pin(E),
+    /// The echo did not start or end in time. Either the sensor is not
+    /// connected, or nothing is in range.
+    Timeout,
+    /// A measurement is already in progress
+    Busy,
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    Idle,
+    Triggered { at: u32 },
+    Echo { start: u32 },
+    Done { ticks: u32 },
+}
+
+/// Distance measurement with an HC-SR04 sensor
+pub struct Ranger<TRIG, T> {
+    trigger: TRIG,
+    tick: T,
+    state: State,
+}
+
+impl<TRIG, T, E> Ranger<TRIG, T>
+where
+    TRIG: Trigger<Error = E>,
+    T: Tick,
+{
+    /// Creates a new ranger
+    pub fn new(trigger: TRIG, tick: T) -> Self {
+        Self {
+            trigger,
+            tick,
+            state: State::Idle,
+        }
+    }
+
+    /// Releases the trigger and the tick source
+    pub fn free(self) -> (TRIG, T) {
+        (self.trigger, self.tick)
+    }
+
+    /// Fires the trigger, then times the echo by polling the echo pin.
+    /// Returns the distance in millimeters.
+    pub fn measure<ECHO>(&mut self, echo: &ECHO) -> Result<u32, Error<E>>
+    where
+        ECHO: InputPin<Error = E>,
+    {
+        if let State::Triggered { .. } | State::Echo { .. } = self.state {
+            return Err(Error::Busy);
+        }
+        self.trigger.trigger(&self.tick).map_err(Error::Pin)?;
+
+        let timeout = Timeout::new(&self.tick, MAX_ECHO_DELAY);
+        while echo.is_low().map_err(Error::Pin)? {
+            if timeout.expired(&self.tick) {
+                return Err(Error::Timeout);
+            }
+        }
+        let start = self.tick.ticks();
+        let timeout = Timeout::new(&self.tick, MAX_ECHO);
+        while echo.is_high().map_err(Error::Pin)? {
+            if timeout.expired(&self.tick) {
+                return Err(Error::Timeout);
+            }
+        }
+        Ok(Self::distance(self.tick.ticks_since(start)))
+    }
+
+    /// Fires the trigger and starts an interrupt-driven measurement
+    pub fn start(&mut self) -> Result<(), Error<E>> {
+        if let State::Triggered { .. } | State::Echo { .. } = self.state {
+            return Err(Error::Busy);
+        }
+        self.trigger.trigger(&self.tick).map_err(Error::Pin)?;
+        self.state = State::Triggered {
+            at: self.tick.ticks(),
+        };
+        Ok(())
+    }
+
+    /// Records an edge of the echo pin. Call this from the interrupt handler
+    /// of the external interrupt the echo pin is connected to.
+    pub fn on_echo_edge(&mut self) {
+        let now = self.tick.ticks();
+        self.state = match self.state {
+            State::Triggered { .. } => State::Echo { start: now },
+            State::Echo { start } => State::Done {
+                ticks: now.wrapping_sub(start) & T::MAX,
+            },
+            state => state,
+        };
+    }
+
+    /// Returns the distance in millimeters once the measurement started by
+    /// [`start`](Self::start) is complete.
+    ///
+    /// Returns `WouldBlock` while no measurement is in progress, as there is
+    /// no distance to report, so do not block on it before `start`.
+    pub fn poll(&mut self) -> nb::Result<u32, Error<E>> {
+        match self.state {
+            State::Idle => Err(nb::Error::WouldBlock),
+            State::Triggered { at } => {
+                if self.tick.ticks_since(at) > T::to_ticks(MAX_ECHO_DELAY) {
+                    self.state = State::Idle;
+                    Err(nb::Error::Other(Error::Timeout))
+                } else {
+                    Err(nb::Error::WouldBlock)
+                }
+            }
+            State::Echo { start } => {
+                if self.tick.ticks_since(start) > T::to_ticks(MAX_ECHO) {
+                    self.state = State::Idle;
+                    Err(nb::Error::Other(Error::Timeout))
+                } else {
+                    Err(nb::Error::WouldBlock)
+                }
+            }
+            State::Done { ticks } => {
+                self.state = State::Idle;
+                Ok(Self::distance(ticks))
+            }
+        }
+    }
+
+    /// Fires the trigger and starts a measurement timed by `capture`, which
+    /// must capture the pulses of the echo pin. Any width captured before is
+    /// dropped.
+    pub fn start_capture<C: PulseCapture>(&mut self, capture: &mut C) -> Result<(), Error<E>> {
+        capture.read_width();
+        capture.read_period();
+        self.start()
+    }
+
+    /// Returns the distance in millimeters once `capture` captured the echo
+    /// of the measurement started by [`start_capture`](Self::start_capture).
+    ///
+    /// Returns `WouldBlock` while no measurement is in progress, as there is
+    /// no distance to report.
+    pub fn poll_capture<C: PulseCapture>(&mut self, capture: &mut C) -> nb::Result<u32, Error<E>> {
+        let at = match self.state {
+            State::Triggered { at } => at,
+            _ => return Err(nb::Error::WouldBlock),
+        };
+        if let Some(width) = capture.read_width() {
+            self.state = State::Idle;
+            // The sound travels to the obstacle and back
+            return Ok((width as u64 * SPEED_OF_SOUND / 2 / 1_000_000) as u32);
+        }
+        let timeout = MAX_ECHO_DELAY + MAX_ECHO;
+        if self.tick.ticks_since(at) > T::to_ticks(timeout) {
+            self.state = State::Idle;
+            Err(nb::Error::Other(Error::Timeout))
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Converts the width of the echo pulse to a distance in millimeters
+    fn distance(ticks: u32) -> u32 {
+        // The sound travels to the obstacle and back
+        (ticks as u64 * SPEED_OF_SOUND / 2 / T::HZ as u64) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+    use core::convert::Infallible;
+
+    struct FakeTick(Cell<u32>);
+
+    impl Tick for FakeTick {
+        const HZ: u32 = 1_000_000;
+
+        fn ticks(&self) -> u32 {
+            self.0.get()
+        }
+    }
+
+    struct FakeTrigger;
+
+    impl Trigger for FakeTrigger {
+        type Error = Infallible;
+
+        fn trigger<T: Tick>(&mut self, _tick: &T) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn interrupt_driven_measurement() {
+        let tick = FakeTick(Cell::new(0));
+        let mut ranger = Ranger::new(FakeTrigger, &tick);
+        ranger.start().unwrap();
+        assert_eq!(ranger.start(), Err(Error::Busy));
+        tick.0.set(500);
+        ranger.on_echo_edge();
+        assert_eq!(ranger.poll(), Err(nb::Error::WouldBlock));
+        // A 5831µs echo is a round trip of about one meter
+        tick.0.set(6_331);
+        ranger.on_echo_edge();
+        assert_eq!(ranger.poll(), Ok(1_000));
+    }
+
+    struct FakeCapture(Option<u32>);
+
+    impl PulseCapture for FakeCapture {
+        fn read_width(&mut self) -> Option<u32> {
+            self.0.take()
+        }
+
+        fn read_period(&mut self) -> Option<u32> {
+            None
+        }
+    }
+
+    #[test]
+    fn captured_measurement() {
+        let tick = FakeTick(Cell::new(0));
+        let mut ranger = Ranger::new(FakeTrigger, &tick);
+        let mut capture = FakeCapture(Some(100));
+        assert_eq!(
+            ranger.poll_capture(&mut capture),
+            Err(nb::Error::WouldBlock)
+        );
+        // The stale width is dropped
+        ranger.start_capture(&mut capture).unwrap();
+        assert_eq!(
+            ranger.poll_capture(&mut capture),
+            Err(nb::Error::WouldBlock)
+        );
+        capture.0 = Some(2_915);
+        assert_eq!(ranger.poll_capture(&mut capture), Ok(499));
+        assert_eq!(ranger.poll(), Err(nb::Error::WouldBlock));
+    }
+
+    #[test]
+    fn missing_echo_times_out() {
+        let tick = FakeTick(Cell::new(0));
+        let mut ranger = Ranger::new(FakeTrigger, &tick);
+        ranger.start().unwrap();
+        tick.0.set(20_000);
+        assert_eq!(ranger.poll(), Err(nb::Error::Other(Error::Timeout)));
+    }
+}
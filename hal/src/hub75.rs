@@ -0,0 +1,421 @@
+//! DMA refresh of HUB75 RGB LED matrices
+//!
+//! A HUB75 panel shows two rows at a time, one from each half, whose pixels
+//! are shifted in on six colour lines. The [`FrameBuffer`] holds the bytes to
+//! write to one byte of a PORT group `OUT` register, two per column, to shift
+//! in a pair of rows for one bit of the colours. The colour lines and the
+//! clock, and optionally some address lines, must be in that byte, as
+//! described by a [`Layout`]. The DMAC copies one such block to the register
+//! at a time, one byte per overflow of an 8-bit timer, and the CPU only
+//! latches the rows from the DMAC interrupt:
+//!
+//! ```
+//! static mut DATA: [u8; buffer_len(64, 32, 2)] = [0; buffer_len(64, 32, 2)];
+//!
+//! let frame = FrameBuffer::new(unsafe { &mut DATA }, 64, 32, 2, LAYOUT);
+//! let out = unsafe { OutByte::new(&(*PORT::ptr()).group1, 0) };
+//! let timer = TimerCounter::tc2_(&timer_clock, peripherals.TC2, &mut peripherals.MCLK);
+//! let mut matrix = Matrix::new(frame, out, rows, channels.0, timer.into_8bit(), 16);
+//! matrix.frame().set_pixel(0, 0, 255, 0, 0);
+//!
+//! #[interrupt]
+//! fn DMAC_0() {
+//!     matrix.on_interrupt();
+//! }
+//! ```
+//!
+//! Panels can be chained, the output of one plugged into the input of the
+//! next. The chain is drawn as a single panel `chain` times as wide, the
+//! panel plugged into the microcontroller being the leftmost one.
+//!
+//! The colours are displayed with binary-coded modulation: each block is
+//! shifted in while the previous one is displayed, and the timer is slowed
+//! down for the more significant bits, so that each bit is displayed twice as
+//! long as the previous one. The frame buffer is drawn into while it is
+//! displayed, so there can be some tearing.
+//!
+//! With the `graphics` feature, [`FrameBuffer`] is an `embedded-graphics`
+//! `DrawTarget`.
+
+use crate::dmac::{
+    BufferPair, Busy, ChId, Channel, InterruptFlags, Ready, Transfer, TriggerSource,
+};
+use crate::hal::digital::v2::OutputPin;
+use crate::spi_display::TRIGGER_ACTION;
+use crate::target_device::port::GROUP;
+use crate::target_device::{TC2, TC3};
+#[cfg(feature = "min-samd51j")]
+use crate::target_device::{TC4, TC5};
+use crate::timer::{Count8, TimerCounter8};
+
+#[cfg(feature = "graphics")]
+use embedded_graphics::{
+    drawable::Pixel,
+    geometry::Size,
+    pixelcolor::{Rgb888, RgbColor},
+    DrawTarget,
+};
+
+/// Bits of each colour channel which are displayed, the most significant
+pub const PLANES: usize = 4;
+
+/// Length of the frame buffer of `chain` panels of `width` by `height`
+/// pixels, in bytes
+pub const fn buffer_len(width: usize, height: usize, chain: usize) -> usize {
+    // Two bytes per column, for each pair of rows and each bit
+    2 * width * chain * (height / 2) * PLANES
+}
+
+/// The bits of the `OUT` byte which drive the lines of the connector
+#[derive(Clone, Copy)]
+pub struct Layout {
+    /// R1, G1 and B1, the colours of the upper half
+    pub rgb1: [u8; 3],
+    /// R2, G2 and B2, the colours of the lower half
+    pub rgb2: [u8; 3],
+    /// The clock, which shifts the colours in on its rising edge
+    pub clk: u8,
+    /// Address lines A to E which are in the byte too, or 0 for those which
+    /// are not
+    pub addr: [u8; 5],
+}
+
+/// The byte of a PORT group `OUT` register which is written by the DMAC
+pub struct OutByte(&'static mut u8);
+
+impl OutByte {
+    /// Byte `byte` of the `OUT` register of `group`, 0 for pins 0 to 7.
+    ///
+    /// # Safety
+    ///
+    /// Nothing else may write the `OUT` register of the group while it is
+    /// refreshed by the DMAC. The other pins of the group can still be
+    /// changed through `OUTSET`, `OUTCLR` and `OUTTGL`, which is what the
+    /// [`gpio`](crate::gpio) pins do.
+    pub unsafe fn new(group: &'static GROUP, byte: usize) -> Self {
+        assert!(byte < 4);
+        Self(&mut *(group.out.as_ptr() as *mut u8).add(byte))
+    }
+}
+
+/// The bytes written to the HUB75 connector, see the [module](self)
+/// documentation
+pub struct FrameBuffer {
+    data: &'static mut [u8],
+    width: usize,
+    height: usize,
+    chain: usize,
+    layout: Layout,
+}
+
+impl FrameBuffer {
+    /// Uses `data` to store the frame of `chain` panels of `width` by
+    /// `height` pixels, and clears it.
+    ///
+    /// Panics if `data` is not [`buffer_len`] long, or if the panels have
+    /// more than 64 rows.
+    pub fn new(
+        data: &'static mut [u8],
+        width: usize,
+        height: usize,
+        chain: usize,
+        layout: Layout,
+    ) -> Self {
+        assert!(height % 2 == 0 && height <= 64);
+        assert_eq!(data.len(), buffer_len(width, height, chain));
+        let mut frame = Self {
+            data,
+            width,
+            height,
+            chain,
+            layout,
+        };
+        frame.clear();
+        frame
+    }
+
+    /// Width and height of the chain, in pixels
+    pub fn size(&self) -> (usize, usize) {
+        (self.width * self.chain, self.height)
+    }
+
+    fn scan_rows(&self) -> usize {
+        self.height / 2
+    }
+
+    fn block_len(&self) -> usize {
+        2 * self.width * self.chain
+    }
+
+    fn block_mut(&mut self, row: usize, plane: usize) -> &mut [u8] {
+        let len = self.block_len();
+        let start = (row * PLANES + plane) * len;
+        &mut self.data[start..start + len]
+    }
+
+    /// The block shifted in for `row` and `plane`, as a DMA source
+    fn block(&mut self, row: usize, plane: usize) -> &'static mut [u8] {
+        // SAFETY: The data is borrowed for 'static by the frame buffer, and
+        // the DMAC only reads the blocks
+        unsafe { &mut *(self.block_mut(row, plane) as *mut [u8]) }
+    }
+
+    /// Turns all the pixels off
+    pub fn clear(&mut self) {
+        let scan_rows = self.scan_rows();
+        let clk = self.layout.clk;
+        let lines = self.layout.addr;
+        for row in 0..scan_rows {
+            for plane in 0..PLANES {
+                // The address lines in the byte must keep the row which is
+                // displayed while this block is shifted in, i.e. the row of
+                // the previous block
+                let shown = if plane == 0 {
+                    (row + scan_rows - 1) % scan_rows
+                } else {
+                    row
+                };
+                let addr = lines
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| shown >> i & 1 != 0)
+                    .fold(0, |addr, (_, &bit)| addr | bit);
+                for pair in self.block_mut(row, plane).chunks_mut(2) {
+                    pair[0] = addr;
+                    pair[1] = addr | clk;
+                }
+            }
+        }
+    }
+
+    /// Sets the pixel at column `x` and row `y`. Pixels outside of the frame
+    /// are ignored. Only the [`PLANES`] most significant bits of each channel
+    /// are displayed.
+    pub fn set_pixel(&mut self, x: usize, y: usize, r: u8, g: u8, b: u8) {
+        let (width, height) = self.size();
+        if x >= width || y >= height {
+            return;
+        }
+        // The first column shifted in ends up in the last panel of the chain
+        let panel = x / self.width;
+        let column = (self.chain - 1 - panel) * self.width + x % self.width;
+        // The lower half of the panel is on the second set of colour lines
+        let scan_rows = self.scan_rows();
+        let lines = if y < scan_rows {
+            self.layout.rgb1
+        } else {
+            self.layout.rgb2
+        };
+        let mask = lines[0] | lines[1] | lines[2];
+        for plane in 0..PLANES {
+            let bit = 8 - PLANES + plane;
+            let mut rgb = 0;
+            for (&channel, &line) in [r, g, b].iter().zip(lines.iter()) {
+                if channel >> bit & 1 != 0 {
+                    rgb |= line;
+                }
+            }
+            let block = self.block_mut(y % scan_rows, plane);
+            for byte in &mut block[2 * column..2 * column + 2] {
+                *byte = *byte & !mask | rgb;
+            }
+        }
+    }
+
+    /// Releases the storage
+    pub fn free(self) -> &'static mut [u8] {
+        self.data
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl DrawTarget<Rgb888> for FrameBuffer {
+    type Error = core::convert::Infallible;
+
+    fn draw_pixel(&mut self, item: Pixel<Rgb888>) -> Result<(), Self::Error> {
+        let Pixel(point, color) = item;
+        if point.x >= 0 && point.y >= 0 {
+            self.set_pixel(
+                point.x as usize,
+                point.y as usize,
+                color.r(),
+                color.g(),
+                color.b(),
+            );
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> Size {
+        let (width, height) = FrameBuffer::size(self);
+        Size::new(width as u32, height as u32)
+    }
+}
+
+/// The row address, latch and output enable lines, which are driven by the
+/// CPU between blocks
+pub trait Rows {
+    /// Blanks the panel, selects `row`, latches the rows which were shifted
+    /// in and displays them
+    fn latch(&mut self, row: usize);
+}
+
+/// [`Rows`] driven by output pins. Panels of 32 rows or less ignore address
+/// E, and the pins of the address lines which are in the [`Layout`] are
+/// driven to the same level as by the DMAC.
+pub struct RowPins<A, B, C, D, E, LAT, OE> {
+    pub addr_a: A,
+    pub addr_b: B,
+    pub addr_c: C,
+    pub addr_d: D,
+    pub addr_e: E,
+    /// Latch, active high
+    pub lat: LAT,
+    /// Output enable, active low
+    pub oe: OE,
+}
+
+fn set<P: OutputPin>(pin: &mut P, high: bool) {
+    if high {
+        pin.set_high().ok();
+    } else {
+        pin.set_low().ok();
+    }
+}
+
+impl<A, B, C, D, E, LAT, OE> Rows for RowPins<A, B, C, D, E, LAT, OE>
+where
+    A: OutputPin,
+    B: OutputPin,
+    C: OutputPin,
+    D: OutputPin,
+    E: OutputPin,
+    LAT: OutputPin,
+    OE: OutputPin,
+{
+    fn latch(&mut self, row: usize) {
+        self.oe.set_high().ok();
+        set(&mut self.addr_a, row & 1 != 0);
+        set(&mut self.addr_b, row & 2 != 0);
+        set(&mut self.addr_c, row & 4 != 0);
+        set(&mut self.addr_d, row & 8 != 0);
+        set(&mut self.addr_e, row & 16 != 0);
+        self.lat.set_high().ok();
+        self.lat.set_low().ok();
+        self.oe.set_low().ok();
+    }
+}
+
+/// A timer counter whose overflow triggers the DMAC
+pub trait OverflowTrigger: Count8 {
+    /// The trigger source of the overflow
+    const TRIGGER: TriggerSource;
+}
+
+macro_rules! overflow_trigger {
+    ($($TC:ident: $TRIGGER:ident,)+) => {
+        $(
+impl OverflowTrigger for $TC {
+    const TRIGGER: TriggerSource = TriggerSource::$TRIGGER;
+}
+        )+
+    }
+}
+
+overflow_trigger! {
+    TC2: TC2_OVF,
+    TC3: TC3_OVF,
+}
+
+#[cfg(feature = "min-samd51j")]
+overflow_trigger! {
+    TC4: TC4_OVF,
+    TC5: TC5_OVF,
+}
+
+/// A chain of HUB75 panels refreshed by the DMAC, see the [module](self)
+/// documentation
+pub struct Matrix<Id: ChId, TC, R> {
+    frame: FrameBuffer,
+    transfer: Transfer<Channel<Id, Busy>, BufferPair<&'static mut [u8], &'static mut u8>>,
+    timer: TimerCounter8<TC>,
+    ticks: u8,
+    rows: R,
+    /// The block being shifted in
+    row: usize,
+    plane: usize,
+}
+
+impl<Id, TC, R> Matrix<Id, TC, R>
+where
+    Id: ChId,
+    TC: OverflowTrigger,
+    R: Rows,
+{
+    /// Starts refreshing the panels from `frame`.
+    ///
+    /// A byte is written to `out` every `ticks` cycles of the timer clock
+    /// while the least significant bit is displayed, twice as slowly for the
+    /// next bit, and so on. `ticks` must be between 1 and 32. With the 120MHz
+    /// GCLK0, 16 ticks refresh a 64x32 panel about 240 times per second.
+    ///
+    /// The pins of the colour lines and the clock must be outputs. The
+    /// transfer complete interrupt of the channel is enabled, and the DMAC
+    /// interrupt of the channel must call [`Matrix::on_interrupt`].
+    pub fn new(
+        mut frame: FrameBuffer,
+        out: OutByte,
+        mut rows: R,
+        mut channel: Channel<Id, Ready>,
+        mut timer: TimerCounter8<TC>,
+        ticks: u8,
+    ) -> Self {
+        assert!(ticks > 0 && ticks as usize <= 256 >> (PLANES - 1));
+
+        rows.latch(frame.scan_rows() - 1);
+        let block = frame.block(0, 0);
+        channel.enable_interrupts(InterruptFlags::new().with_tcmpl(true));
+        timer.start_ticks(1, ticks - 1);
+        // SAFETY: The transfer lives as long as the matrix, which is never
+        // dropped while the transfer is running
+        let transfer = unsafe { Transfer::new_unchecked(channel, block, out.0, false) }
+            .begin(TC::TRIGGER, TRIGGER_ACTION);
+
+        Self {
+            frame,
+            transfer,
+            timer,
+            ticks,
+            rows,
+            row: 0,
+            plane: 0,
+        }
+    }
+
+    /// Latches the block which was shifted in, and starts shifting the next
+    /// one. Must be called from the DMAC interrupt of the channel.
+    pub fn on_interrupt(&mut self) {
+        if !self.transfer.block_transfer_interrupt() {
+            return;
+        }
+        self.rows.latch(self.row);
+        // Display the latched bit for as long as 2^plane bytes take
+        let ticks = (self.ticks as u16) << self.plane;
+        self.timer.set_period((ticks - 1) as u8);
+
+        self.plane += 1;
+        if self.plane == PLANES {
+            self.plane = 0;
+            self.row = (self.row + 1) % self.frame.scan_rows();
+        }
+        let block = self.frame.block(self.row, self.plane);
+        // The blocks have the same length and the transfer has completed, so
+        // it can't fail
+        let _ = self.transfer.recycle_destination(block);
+    }
+
+    /// The frame buffer which is displayed, to draw into
+    pub fn frame(&mut self) -> &mut FrameBuffer {
+        &mut self.frame
+    }
+}
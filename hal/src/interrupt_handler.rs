@@ -0,0 +1,184 @@
+//! Interrupt handlers sharing a peripheral with the main program
+//!
+//! Driving a peripheral from an interrupt usually means moving it into a
+//! `static mut Option<T>` and unwrapping it in the handler, with `unsafe` on
+//! both sides. A [`Handle`] replaces the `static mut`: it is installed from
+//! the main program and locked from the handler, both inside critical
+//! sections.
+//!
+//! The macros of this module generate the `#[interrupt]` function for a
+//! handle, take care of the flag handling of the peripheral, and call a
+//! callback with a `&mut` reference to the peripheral:
+//!
+//! * [`interrupt_handler!`](crate::interrupt_handler!) calls the callback on
+//!   each interrupt, without touching any flag.
+//! * [`uart_interrupt_handler!`](crate::uart_interrupt_handler!) reads all of
+//!   the received bytes, and calls the callback once per byte.
+//! * [`spi_interrupt_handler!`](crate::spi_interrupt_handler!) reads and
+//!   clears the interrupt flags of a v2 SPI, and passes them to the callback.
+//!
+//! The `interrupt` attribute of the PAC is used, so the `rt` feature of the
+//! BSP or PAC must be enabled. On the SAMD51, each SERCOM has four interrupt
+//! lines, e.g. `SERCOM3_2` for the receive complete interrupt of SERCOM3.
+//! The macros accept several lines sharing the same handle and callback, as
+//! a list: `[SERCOM3_2, SERCOM3_3]`.
+//!
+//! ```
+//! type Uart = hal::sercom::UART3<Sercom3Pad1<PA23>, Sercom3Pad0<PA22>, (), ()>;
+//!
+//! static UART: Handle<Uart> = Handle::new();
+//!
+//! fn on_byte(uart: &mut Uart, byte: u8) {
+//!     // Echo back
+//!     uart.write(byte).ok();
+//! }
+//!
+//! uart_interrupt_handler!(SERCOM3, UART, on_byte);
+//!
+//! #[entry]
+//! fn main() -> ! {
+//!     // ...
+//!     uart.intenset(|w| w.rxc().set_bit());
+//!     UART.install(uart);
+//!     unsafe { NVIC::unmask(interrupt::SERCOM3) };
+//!     loop {}
+//! }
+//! ```
+
+use core::cell::RefCell;
+use cortex_m::interrupt::{self, Mutex};
+
+/// A peripheral shared between the main program and an interrupt handler
+pub struct Handle<T> {
+    inner: Mutex<RefCell<Option<T>>>,
+}
+
+impl<T> Handle<T> {
+    /// An empty handle, to be stored in a `static`
+    pub const fn new() -> Self {
+        Self {
+            inner: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    /// Moves `value` into the handle, returning the previous value if any
+    pub fn install(&self, value: T) -> Option<T> {
+        interrupt::free(|cs| self.inner.borrow(cs).replace(Some(value)))
+    }
+
+    /// Moves the value out of the handle
+    pub fn take(&self) -> Option<T> {
+        interrupt::free(|cs| self.inner.borrow(cs).borrow_mut().take())
+    }
+
+    /// Calls `f` with the value, in a critical section. Returns `None` if no
+    /// value is installed.
+    ///
+    /// Panics if called from `f` itself.
+    pub fn lock<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> Option<R> {
+        interrupt::free(|cs| self.inner.borrow(cs).borrow_mut().as_mut().map(f))
+    }
+}
+
+/// Generates the `#[interrupt]` function for one or more interrupt lines,
+/// calling `callback(&mut T)` with the value of a [`Handle<T>`] on each
+/// interrupt. Interrupts which occur before the value is installed are
+/// ignored.
+///
+/// [`Handle<T>`]: crate::interrupt_handler::Handle
+///
+/// ```
+/// interrupt_handler!(TC4, TIMER, on_timeout);
+/// ```
+#[macro_export]
+macro_rules! interrupt_handler {
+    ($irq:ident, $handle:path, $callback:path) => {
+        $crate::interrupt_handler!([$irq], $handle, $callback);
+    };
+    ([$($irq:ident),+], $handle:path, $callback:path) => {
+        $(
+            const _: () = {
+                use $crate::target_device::interrupt;
+
+                #[interrupt]
+                fn $irq() {
+                    $handle.lock(|value| $callback(value));
+                }
+            };
+        )+
+    };
+}
+
+/// UARTs whose error flags can be cleared by
+/// [`uart_interrupt_handler!`](crate::uart_interrupt_handler!)
+pub trait ClearStatus {
+    /// Clears the error flags of the STATUS register and the `ERROR`
+    /// interrupt flag
+    fn clear_status(&mut self);
+}
+
+/// Generates the `#[interrupt]` function for a UART, which reads all of the
+/// received bytes and calls `callback(&mut T, u8)` for each of them
+///
+/// `T` may be any type implementing [`serial::Read<u8>`] and
+/// [`ClearStatus`], such as a whole UART or its receiving half. The receive
+/// complete interrupt must be enabled. When a read fails, the STATUS register
+/// is cleared before returning, so that the error doesn't raise the
+/// interrupt again.
+///
+/// [`serial::Read<u8>`]: crate::hal::serial::Read
+#[macro_export]
+macro_rules! uart_interrupt_handler {
+    ($irq:ident, $handle:path, $callback:path) => {
+        $crate::uart_interrupt_handler!([$irq], $handle, $callback);
+    };
+    ([$($irq:ident),+], $handle:path, $callback:path) => {
+        $(
+            const _: () = {
+                use $crate::target_device::interrupt;
+
+                #[interrupt]
+                fn $irq() {
+                    $handle.lock(|uart| loop {
+                        match $crate::hal::serial::Read::<u8>::read(uart) {
+                            Ok(byte) => $callback(uart, byte),
+                            Err($crate::nb::Error::WouldBlock) => break,
+                            Err($crate::nb::Error::Other(_)) => {
+                                $crate::interrupt_handler::ClearStatus::clear_status(uart);
+                                break;
+                            }
+                        }
+                    });
+                }
+            };
+        )+
+    };
+}
+
+/// Generates the `#[interrupt]` function for a v2 SPI, which reads and
+/// clears the interrupt flags, then calls `callback(&mut T, Flags)`
+///
+/// The `DRE` and `RXC` flags can't be cleared this way; the callback must
+/// write or read the data to clear them, or disable their interrupts.
+#[macro_export]
+macro_rules! spi_interrupt_handler {
+    ($irq:ident, $handle:path, $callback:path) => {
+        $crate::spi_interrupt_handler!([$irq], $handle, $callback);
+    };
+    ([$($irq:ident),+], $handle:path, $callback:path) => {
+        $(
+            const _: () = {
+                use $crate::target_device::interrupt;
+
+                #[interrupt]
+                fn $irq() {
+                    $handle.lock(|spi| {
+                        let flags = spi.read_flags();
+                        spi.clear_flags(flags);
+                        $callback(spi, flags);
+                    });
+                }
+            };
+        )+
+    };
+}
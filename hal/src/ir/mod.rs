@@ -0,0 +1,75 @@
+//! Infrared remote control
+//!
+//! Remote controls send frames as pulse trains, alternating marks (bursts of
+//! a carrier, usually around 38kHz) and spaces (no carrier). Receivers such as
+//! the TSOP family demodulate the carrier, so that their output is low during
+//! marks and high during spaces. A protocol then encodes bits in the lengths
+//! of the marks and spaces.
+//!
+//! Pulse trains are handled as durations in microseconds, starting with a
+//! mark and alternating with spaces. Two protocols are supported:
+//!
+//! * [`nec`]: pulse distance encoding, used by most cheap remotes
+//! * [`rc5`]: Manchester encoding, used by Philips
+//!
+//! [`IrReceiver`] times the edges of the receiver output against a
+//! [`Tick`](crate::tick::Tick) source, such as a timer counter, and feeds
+//! them to a [`Decoder`]. [`IrCaptureReceiver`] lets a timer counter capture
+//! the marks in hardware instead, so the CPU only reads the captured widths
+//! and periods. On the SAMD21, [`IrTransmitter`] generates the
+//! carrier with a TCC and lets the DMAC switch it on and off at the overflows
+//! of a timer counter, so a whole frame is sent without the CPU.
+//!
+//! The Circuit Playground Express has its IR LED on PA23 (`TCC0/WO[5]`) and
+//! its receiver on PA12 (`EXTINT[12]`).
+
+pub mod nec;
+pub mod rc5;
+
+mod rx;
+pub use rx::*;
+
+#[cfg(all(feature = "samd21", feature = "unproven", feature = "dma"))]
+mod tx;
+#[cfg(all(feature = "samd21", feature = "unproven", feature = "dma"))]
+pub use tx::*;
+
+/// An infrared remote protocol
+pub trait Protocol {
+    /// The content of a frame
+    type Command;
+
+    /// Carrier frequency, in Hz
+    const CARRIER: u32;
+
+    /// Largest number of marks and spaces in a frame
+    const MAX_PULSES: usize;
+
+    /// Writes the durations of the marks and spaces of a frame to `pulses`,
+    /// in microseconds and starting with a mark. Returns the number of
+    /// durations written, which is odd, as frames end with a mark.
+    ///
+    /// Panics if `pulses` is shorter than [`MAX_PULSES`](Self::MAX_PULSES).
+    fn encode(command: &Self::Command, pulses: &mut [u16]) -> usize;
+}
+
+/// Decodes frames from the durations of their marks and spaces
+pub trait Decoder {
+    /// The content of a frame
+    type Command;
+
+    /// Feeds the duration of a mark or a space, in microseconds. Returns the
+    /// command once a frame is complete.
+    fn push(&mut self, mark: bool, duration: u32) -> Option<Self::Command>;
+
+    /// Drops the frame being decoded
+    fn reset(&mut self);
+}
+
+/// Returns true if `duration` is within 25% of `nominal`, the tolerance of
+/// typical receivers
+#[inline]
+fn matches(duration: u32, nominal: u32) -> bool {
+    let margin = nominal / 4;
+    duration >= nominal - margin && duration <= nominal + margin
+}
@@ -0,0 +1,245 @@
+//! NEC protocol
+//!
+//! A frame starts with a 9ms mark and a 4.5ms space, followed by 32 bits,
+//! least significant first: the address, its complement, the command and its
+//! complement. Each bit is a 562.5µs mark followed by a 562.5µs space for a
+//! zero, or a 1687.5µs space for a one. A final mark ends the frame.
+//!
+//! While a key is held, the remote sends a repeat code every 108ms instead:
+//! a 9ms mark, a 2.25ms space and a final mark. The extended variant of the
+//! protocol uses the complement of the address as the high byte of a 16-bit
+//! address.
+
+use super::{matches, Decoder, Protocol};
+
+const LEADER_MARK: u16 = 9000;
+const LEADER_SPACE: u16 = 4500;
+const REPEAT_SPACE: u16 = 2250;
+const BIT_MARK: u16 = 562;
+const ZERO_SPACE: u16 = 562;
+const ONE_SPACE: u16 = 1687;
+
+/// Number of marks and spaces of a repeat code
+pub const REPEAT_PULSES: usize = 3;
+
+/// A NEC frame
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NecCommand {
+    /// Device address. Addresses above 255 use the extended protocol.
+    pub address: u16,
+    /// Key code
+    pub command: u8,
+    /// True for a repeat code, sent while the key is held
+    pub repeat: bool,
+}
+
+/// The NEC protocol
+pub struct Nec;
+
+impl Protocol for Nec {
+    type Command = NecCommand;
+
+    const CARRIER: u32 = 38_000;
+    const MAX_PULSES: usize = 67;
+
+    fn encode(command: &NecCommand, pulses: &mut [u16]) -> usize {
+        pulses[0] = LEADER_MARK;
+        if command.repeat {
+            pulses[1] = REPEAT_SPACE;
+            pulses[2] = BIT_MARK;
+            return REPEAT_PULSES;
+        }
+        pulses[1] = LEADER_SPACE;
+
+        let address = if command.address > 0xff {
+            command.address as u32
+        } else {
+            command.address as u32 | (!command.address as u32 & 0xff) << 8
+        };
+        let data = address | (command.command as u32) << 16 | (!command.command as u32) << 24;
+        for bit in 0..32 {
+            pulses[2 + bit * 2] = BIT_MARK;
+            pulses[3 + bit * 2] = if data & (1 << bit) != 0 {
+                ONE_SPACE
+            } else {
+                ZERO_SPACE
+            };
+        }
+        pulses[66] = BIT_MARK;
+        Self::MAX_PULSES
+    }
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    Idle,
+    Leader,
+    Repeat,
+    Mark { data: u32, bits: u8 },
+    Space { data: u32, bits: u8 },
+}
+
+/// Decoder of NEC frames and repeat codes
+pub struct NecDecoder {
+    state: State,
+    last: Option<NecCommand>,
+}
+
+impl NecDecoder {
+    /// Creates a new decoder
+    pub const fn new() -> Self {
+        Self {
+            state: State::Idle,
+            last: None,
+        }
+    }
+
+    fn frame(data: u32) -> Option<NecCommand> {
+        let command = (data >> 16) as u8;
+        if (data >> 24) as u8 != !command {
+            return None;
+        }
+        let low = data as u8;
+        let high = (data >> 8) as u8;
+        let address = if high == !low {
+            low as u16
+        } else {
+            data as u16
+        };
+        Some(NecCommand {
+            address,
+            command,
+            repeat: false,
+        })
+    }
+}
+
+impl Default for NecDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for NecDecoder {
+    type Command = NecCommand;
+
+    fn push(&mut self, mark: bool, duration: u32) -> Option<NecCommand> {
+        let mut result = None;
+        self.state = match (self.state, mark) {
+            (State::Leader, false) if matches(duration, LEADER_SPACE as u32) => {
+                State::Mark { data: 0, bits: 0 }
+            }
+            (State::Leader, false) if matches(duration, REPEAT_SPACE as u32) => State::Repeat,
+            (State::Repeat, true) if matches(duration, BIT_MARK as u32) => {
+                result = self.last.map(|last| NecCommand {
+                    repeat: true,
+                    ..last
+                });
+                State::Idle
+            }
+            (State::Mark { data, bits: 32 }, true) if matches(duration, BIT_MARK as u32) => {
+                result = Self::frame(data);
+                if result.is_some() {
+                    self.last = result;
+                }
+                State::Idle
+            }
+            (State::Mark { data, bits }, true) if matches(duration, BIT_MARK as u32) => {
+                State::Space { data, bits }
+            }
+            (State::Space { data, bits }, false) if matches(duration, ZERO_SPACE as u32) => {
+                State::Mark {
+                    data,
+                    bits: bits + 1,
+                }
+            }
+            (State::Space { data, bits }, false) if matches(duration, ONE_SPACE as u32) => {
+                State::Mark {
+                    data: data | 1 << bits,
+                    bits: bits + 1,
+                }
+            }
+            // Anything unexpected drops the frame, but a leader mark starts
+            // the next one right away
+            (_, true) if matches(duration, LEADER_MARK as u32) => State::Leader,
+            _ => State::Idle,
+        };
+        result
+    }
+
+    fn reset(&mut self) {
+        self.state = State::Idle;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(decoder: &mut NecDecoder, pulses: &[u16]) -> Option<NecCommand> {
+        let mut result = None;
+        for (i, &duration) in pulses.iter().enumerate() {
+            if let Some(command) = decoder.push(i % 2 == 0, duration as u32) {
+                result = Some(command);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut pulses = [0; Nec::MAX_PULSES];
+        let mut decoder = NecDecoder::new();
+        for &address in &[0x00, 0x04, 0xff, 0x1234] {
+            let command = NecCommand {
+                address,
+                command: 0x08,
+                repeat: false,
+            };
+            let len = Nec::encode(&command, &mut pulses);
+            assert_eq!(len, Nec::MAX_PULSES);
+            assert_eq!(decode(&mut decoder, &pulses[..len]), Some(command));
+        }
+
+        let repeat = NecCommand {
+            address: 0,
+            command: 0,
+            repeat: true,
+        };
+        let len = Nec::encode(&repeat, &mut pulses);
+        assert_eq!(len, REPEAT_PULSES);
+        assert_eq!(
+            decode(&mut decoder, &pulses[..len]),
+            Some(NecCommand {
+                address: 0x1234,
+                command: 0x08,
+                repeat: true
+            })
+        );
+    }
+
+    #[test]
+    fn tolerates_timing_errors() {
+        let mut pulses = [0; Nec::MAX_PULSES];
+        let command = NecCommand {
+            address: 0x04,
+            command: 0x45,
+            repeat: false,
+        };
+        let len = Nec::encode(&command, &mut pulses);
+        // Receivers typically stretch the marks and shorten the spaces
+        for (i, duration) in pulses.iter_mut().enumerate() {
+            if i % 2 == 0 {
+                *duration += 100;
+            } else {
+                *duration -= 100;
+            }
+        }
+        let mut decoder = NecDecoder::new();
+        assert_eq!(decode(&mut decoder, &pulses[..len]), Some(command));
+
+        // A corrupted command is rejected
+        pulses[2 + 16 * 2 + 1] = ONE_SPACE + ZERO_SPACE - pulses[2 + 16 * 2 + 1];
+        assert_eq!(decode(&mut decoder, &pulses[..len]), None);
+    }
+}
@@ -0,0 +1,236 @@
+//! Philips RC5 protocol
+//!
+//! A frame is 14 Manchester encoded bits of 1.778ms, sent most significant
+//! first on a 36kHz carrier: two start bits, a toggle bit, a 5-bit address
+//! and a 6-bit command. A one is a space followed by a mark, a zero a mark
+//! followed by a space. The second start bit is the inverse of the seventh
+//! command bit, in the extended variant of the protocol with 128 commands.
+//!
+//! The toggle bit changes each time a key is pressed, so that a held key can
+//! be told apart from repeated presses. Frames are repeated every 114ms while
+//! the key is held.
+
+use super::{matches, Decoder, Protocol};
+
+/// Duration of half a bit
+const HALF_BIT: u16 = 889;
+
+/// Number of half bits of a frame
+const HALF_BITS: u8 = 28;
+
+/// An RC5 frame
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Rc5Command {
+    /// Device address, from 0 to 31
+    pub address: u8,
+    /// Key code, from 0 to 127
+    pub command: u8,
+    /// Toggled on each key press
+    pub toggle: bool,
+}
+
+impl Rc5Command {
+    fn to_bits(self) -> u16 {
+        let start = if self.command & 0x40 != 0 { 0b10 } else { 0b11 };
+        (start << 12)
+            | (self.toggle as u16) << 11
+            | (self.address as u16 & 0x1f) << 6
+            | (self.command as u16 & 0x3f)
+    }
+
+    fn from_bits(bits: u16) -> Option<Self> {
+        if bits & (1 << 13) == 0 {
+            return None;
+        }
+        let extended = if bits & (1 << 12) == 0 { 0x40 } else { 0 };
+        Some(Self {
+            address: (bits >> 6) as u8 & 0x1f,
+            command: (bits as u8 & 0x3f) | extended,
+            toggle: bits & (1 << 11) != 0,
+        })
+    }
+}
+
+/// The RC5 protocol
+pub struct Rc5;
+
+impl Protocol for Rc5 {
+    type Command = Rc5Command;
+
+    const CARRIER: u32 = 36_000;
+    const MAX_PULSES: usize = 27;
+
+    fn encode(command: &Rc5Command, pulses: &mut [u16]) -> usize {
+        let bits = command.to_bits();
+        let mut len = 0;
+        let mut mark = false;
+        for half in 0..HALF_BITS {
+            // Ones are a space followed by a mark
+            let one = bits & (1 << (13 - half / 2)) != 0;
+            let level = one == (half % 2 == 1);
+            // The space before the first mark is the idle line
+            if len == 0 && !level {
+                continue;
+            }
+            if len == 0 || level != mark {
+                pulses[len] = 0;
+                len += 1;
+                mark = level;
+            }
+            pulses[len - 1] += HALF_BIT;
+        }
+        // So is the space after the last mark
+        if !mark {
+            len -= 1;
+        }
+        len
+    }
+}
+
+/// Decoder of RC5 frames
+pub struct Rc5Decoder {
+    /// Levels of the half bits received so far, mark is one
+    halves: u32,
+    count: u8,
+}
+
+impl Rc5Decoder {
+    /// Creates a new decoder
+    pub const fn new() -> Self {
+        Self {
+            halves: 0,
+            count: 0,
+        }
+    }
+
+    fn append(&mut self, mark: bool, halves: u8) {
+        for _ in 0..halves {
+            self.halves = self.halves << 1 | mark as u32;
+        }
+        self.count += halves;
+    }
+
+    fn frame(&self) -> Option<Rc5Command> {
+        let mut bits = 0;
+        for bit in 0..14 {
+            let pair = (self.halves >> (26 - bit * 2)) & 0b11;
+            bits <<= 1;
+            match pair {
+                0b01 => bits |= 1,
+                0b10 => {}
+                _ => return None,
+            }
+        }
+        Rc5Command::from_bits(bits)
+    }
+}
+
+impl Default for Rc5Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for Rc5Decoder {
+    type Command = Rc5Command;
+
+    fn push(&mut self, mark: bool, duration: u32) -> Option<Rc5Command> {
+        let halves = if matches(duration, HALF_BIT as u32) {
+            1
+        } else if matches(duration, 2 * HALF_BIT as u32) {
+            2
+        } else {
+            0
+        };
+
+        if self.count == 0 {
+            // A frame starts with the mark of the first start bit, after
+            // the implicit space of its first half
+            if mark && halves > 0 {
+                self.append(false, 1);
+                self.append(true, halves);
+            }
+            return None;
+        }
+        if halves == 0 || self.count + halves > HALF_BITS {
+            self.reset();
+            return None;
+        }
+
+        self.append(mark, halves);
+        // A frame ending with a zero has its final space merged with the idle
+        // line, so it is complete one half bit early
+        if mark && self.count == HALF_BITS - 1 {
+            self.append(false, 1);
+        }
+        if self.count == HALF_BITS {
+            let result = self.frame();
+            self.reset();
+            return result;
+        }
+        None
+    }
+
+    fn reset(&mut self) {
+        self.halves = 0;
+        self.count = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(decoder: &mut Rc5Decoder, pulses: &[u16]) -> Option<Rc5Command> {
+        let mut result = None;
+        for (i, &duration) in pulses.iter().enumerate() {
+            if let Some(command) = decoder.push(i % 2 == 0, duration as u32) {
+                result = Some(command);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut decoder = Rc5Decoder::new();
+        for &(address, command, toggle) in &[
+            (0, 0, false),
+            (5, 12, true),
+            (31, 63, false),
+            (20, 0x55, true),
+            (31, 127, true),
+        ] {
+            let command = Rc5Command {
+                address,
+                command,
+                toggle,
+            };
+            let mut pulses = [0; Rc5::MAX_PULSES];
+            let len = Rc5::encode(&command, &mut pulses);
+            assert_eq!(len % 2, 1);
+            assert!(pulses[..len]
+                .iter()
+                .all(|&d| d == HALF_BIT || d == 2 * HALF_BIT));
+            assert_eq!(decode(&mut decoder, &pulses[..len]), Some(command));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_timing() {
+        let command = Rc5Command {
+            address: 5,
+            command: 12,
+            toggle: false,
+        };
+        let mut pulses = [0; Rc5::MAX_PULSES];
+        let len = Rc5::encode(&command, &mut pulses);
+        pulses[3] = 1300;
+        let mut decoder = Rc5Decoder::new();
+        assert_eq!(decode(&mut decoder, &pulses[..len]), None);
+        // The decoder recovers after the gap before the next frame
+        decoder.push(false, 100_000);
+        let len = Rc5::encode(&command, &mut pulses);
+        assert_eq!(decode(&mut decoder, &pulses[..len]), Some(command));
+    }
+}
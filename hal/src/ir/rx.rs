@@ -0,0 +1,157 @@
+use super::Decoder;
+use crate::tick::Tick;
+use crate::timer_traits::PulseCapture;
+
+/// Receives frames from a demodulating IR receiver
+///
+/// Configure the receiver output pin as an external interrupt sensing both
+/// edges, and call [`on_edge`](Self::on_edge) from the EIC interrupt handler.
+/// The duration of each mark and space is measured against a [`Tick`]
+/// source, e.g. a timer counter at 1MHz, or the RTC for receivers that must
+/// run in standby. The period of the counter must be longer than the longest
+/// mark or space of the protocol, 9ms for NEC.
+pub struct IrReceiver<T, D> {
+    tick: T,
+    decoder: D,
+    last_edge: u32,
+}
+
+impl<T: Tick, D: Decoder> IrReceiver<T, D> {
+    /// Creates a new receiver
+    pub fn new(tick: T, decoder: D) -> Self {
+        let last_edge = tick.ticks();
+        Self {
+            tick,
+            decoder,
+            last_edge,
+        }
+    }
+
+    /// Releases the tick source and the decoder
+    pub fn free(self) -> (T, D) {
+        (self.tick, self.decoder)
+    }
+
+    /// Handles an edge of the receiver output. `level` is the level of the
+    /// output after the edge: the output is low during marks, so a falling
+    /// edge ends a space and a rising edge ends a mark. Returns the command
+    /// once a frame is complete.
+    pub fn on_edge(&mut self, level: bool) -> Option<D::Command> {
+        let now = self.tick.ticks();
+        let ticks = now.wrapping_sub(self.last_edge) & T::MAX;
+        self.last_edge = now;
+        let duration = (ticks as u64 * 1_000_000 / T::HZ as u64) as u32;
+        // The output is high after a mark
+        self.decoder.push(level, duration)
+    }
+
+    /// Drops the frame being decoded, e.g. after a timeout
+    pub fn reset(&mut self) {
+        self.decoder.reset();
+    }
+}
+
+/// Receives frames from a demodulating IR receiver, timed by a timer counter
+///
+/// The timer counter captures the marks in hardware, see
+/// `TimerCounter::into_pulse_capture` with `invert` set, as the output of the
+/// receiver is low during marks. Call [`on_capture`](Self::on_capture) from
+/// the timer interrupt handler, or poll it often enough to see every capture.
+/// The longest period captured by the timer must be longer than a mark and
+/// the space after it, 13.5ms for the NEC leader.
+pub struct IrCaptureReceiver<C, D> {
+    capture: C,
+    decoder: D,
+    mark: Option<u32>,
+}
+
+impl<C: PulseCapture, D: Decoder> IrCaptureReceiver<C, D> {
+    /// Creates a new receiver
+    pub fn new(capture: C, decoder: D) -> Self {
+        Self {
+            capture,
+            decoder,
+            mark: None,
+        }
+    }
+
+    /// Releases the capture and the decoder
+    pub fn free(self) -> (C, D) {
+        (self.capture, self.decoder)
+    }
+
+    /// Feeds the marks and spaces captured since the last call to the
+    /// decoder. Returns the command once a frame is complete.
+    pub fn on_capture(&mut self) -> Option<D::Command> {
+        let mut command = None;
+        // The period is captured at the start of a mark, and spans the mark
+        // before it and the space in between
+        if let Some(period) = self.capture.read_period() {
+            if let Some(mark) = self.mark.take() {
+                command = self.decoder.push(false, period.saturating_sub(mark));
+            }
+        }
+        if let Some(width) = self.capture.read_width() {
+            self.mark = Some(width);
+            command = command.or(self.decoder.push(true, width));
+        }
+        command
+    }
+
+    /// Drops the frame being decoded, e.g. after a timeout
+    pub fn reset(&mut self) {
+        self.mark = None;
+        self.decoder.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::nec::{Nec, NecCommand, NecDecoder};
+    use crate::ir::Protocol;
+
+    struct FakeCapture {
+        width: Option<u32>,
+        period: Option<u32>,
+    }
+
+    impl PulseCapture for FakeCapture {
+        fn read_width(&mut self) -> Option<u32> {
+            self.width.take()
+        }
+
+        fn read_period(&mut self) -> Option<u32> {
+            self.period.take()
+        }
+    }
+
+    #[test]
+    fn captured_frame() {
+        let command = NecCommand {
+            address: 0x04,
+            command: 0x45,
+            repeat: false,
+        };
+        let mut pulses = [0; Nec::MAX_PULSES];
+        let len = Nec::encode(&command, &mut pulses);
+
+        let capture = FakeCapture {
+            width: None,
+            // The period of the last frame, long before this one
+            period: Some(40_000),
+        };
+        let mut receiver = IrCaptureReceiver::new(capture, NecDecoder::new());
+        let mut result = None;
+        for mark in (0..len).step_by(2) {
+            receiver.capture.width = Some(pulses[mark] as u32);
+            if mark > 0 {
+                receiver.capture.period = Some((pulses[mark - 2] + pulses[mark - 1]) as u32);
+            }
+            if let Some(command) = receiver.on_capture() {
+                result = Some(command);
+            }
+        }
+        assert_eq!(result, Some(command));
+    }
+}
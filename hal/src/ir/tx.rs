@@ -0,0 +1,349 @@
+use super::Protocol;
+use crate::dmac::{
+    channel::{Busy, Channel, Ready},
+    transfer::{Buffer, BufferPair},
+    ChId, Transfer, TriggerAction, TriggerSource,
+};
+use crate::hal::Pwm;
+use crate::pwm::Channel as PwmChannel;
+use crate::target_device::tcc0::RegisterBlock as TccRegisterBlock;
+use crate::target_device::{TC3, TC4, TC5};
+use crate::timer::{Count16, TimerCounter};
+
+/// Pattern generator setting letting the carrier through to the outputs
+const MARK: u16 = 0x0000;
+
+/// Pattern generator setting forcing all the outputs low
+const SPACE: u16 = 0x00ff;
+
+/// Pattern generator settings written at each overflow of the envelope
+/// timer. The first pulse is a mark, so the first overflow starts a space.
+static LEVELS: [u16; 2] = [SPACE, MARK];
+
+/// Value written to the envelope timer after the last mark, to hold the
+/// output low until the transfer is stopped
+const END_GAP: u16 = 0xffff;
+
+/// Highest rate of the envelope timer. At 4MHz, its 16-bit counter still
+/// covers the 9ms leader mark of the NEC protocol.
+const MAX_ENVELOPE_HZ: u32 = 4_000_000;
+
+/// Buffer of the mark and space durations of a frame
+pub type Pulses = &'static mut [u16];
+
+/// A TCC configured as a PWM, generating the carrier
+pub trait CarrierTcc: Pwm<Channel = PwmChannel, Duty = u32> {
+    #[doc(hidden)]
+    fn tcc(&self) -> &TccRegisterBlock;
+}
+
+/// A timer counter whose overflows can trigger DMA transfers
+pub trait EnvelopeTc: Count16 {
+    /// DMA trigger source of the overflow of the timer
+    const DMA_OVF_TRIGGER: TriggerSource;
+}
+
+impl EnvelopeTc for TC3 {
+    const DMA_OVF_TRIGGER: TriggerSource = TriggerSource::TC3_OVF;
+}
+
+impl EnvelopeTc for TC4 {
+    const DMA_OVF_TRIGGER: TriggerSource = TriggerSource::TC4_OVF;
+}
+
+impl EnvelopeTc for TC5 {
+    const DMA_OVF_TRIGGER: TriggerSource = TriggerSource::TC5_OVF;
+}
+
+/// DMA destination writing to a 16-bit peripheral register
+struct Register {
+    reg: *mut u16,
+}
+
+unsafe impl Buffer for Register {
+    type Beat = u16;
+
+    #[inline]
+    fn dma_ptr(&mut self) -> *mut Self::Beat {
+        self.reg
+    }
+
+    #[inline]
+    fn incrementing(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn buffer_len(&self) -> usize {
+        1
+    }
+}
+
+/// DMA source reading the durations of a frame, in timer ticks
+struct Durations {
+    start: *mut u16,
+    len: usize,
+}
+
+unsafe impl Buffer for Durations {
+    type Beat = u16;
+
+    #[inline]
+    fn dma_ptr(&mut self) -> *mut Self::Beat {
+        // Incrementing buffers point past their end
+        unsafe { self.start.add(self.len) }
+    }
+
+    #[inline]
+    fn incrementing(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn buffer_len(&self) -> usize {
+        self.len
+    }
+}
+
+/// DMA source reading [`LEVELS`] in a loop
+struct Levels;
+
+unsafe impl Buffer for Levels {
+    type Beat = u16;
+
+    #[inline]
+    fn dma_ptr(&mut self) -> *mut Self::Beat {
+        // The DMAC only reads from the buffer
+        LEVELS.as_ptr_range().end as *mut u16
+    }
+
+    #[inline]
+    fn incrementing(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn buffer_len(&self) -> usize {
+        LEVELS.len()
+    }
+}
+
+type DurationTransfer<C> = Transfer<Channel<C, Busy>, BufferPair<Durations, Register>>;
+type LevelTransfer<C> = Transfer<Channel<C, Busy>, BufferPair<Levels, Register>>;
+
+enum State<D: ChId, L: ChId> {
+    Idle(Channel<D, Ready>, Channel<L, Ready>),
+    Sending(DurationTransfer<D>, LevelTransfer<L>),
+    Moving,
+}
+
+/// Errors of [`IrTransmitter::send`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SendError {
+    /// A frame is being sent
+    Busy,
+    /// The frame does not end with a mark, or leaves no room in the buffer
+    /// for the final gap
+    InvalidLength,
+}
+
+/// Sends IR frames without the CPU
+///
+/// A TCC, configured as a PWM at the carrier frequency, generates the carrier
+/// with a duty cycle of one third. Its pattern generator forces its outputs
+/// low during the spaces. A timer counter in match frequency mode times each
+/// mark and space: at every overflow, one DMA channel loads the duration of
+/// the next pulse into the timer, and another one switches the pattern
+/// generator. As the pattern generator is buffered, the marks start and end
+/// on carrier periods.
+///
+/// All the outputs of the TCC carry the modulated carrier, so the TCC cannot
+/// be shared with other PWM outputs.
+///
+/// ```
+/// let carrier = Pwm0::new(&clocks.tcc0_tcc1(&gclk0).unwrap(), Nec::CARRIER.Hz(), peripherals.TCC0, &mut pm);
+/// let _led = pins.ir_tx.into_function_f(&mut pins.port);
+/// let envelope = TimerCounter4::tc4_(&clocks.tc4_tc5(&gclk0).unwrap(), peripherals.TC4, &mut pm);
+/// let mut ir = IrTransmitter::new(carrier, Channel::_1, envelope, unsafe { &mut PULSES }, channels.0, channels.1);
+/// ir.send_command::<Nec>(&command).unwrap();
+/// while !ir.poll() {}
+/// ```
+pub struct IrTransmitter<P, TC, D: ChId, L: ChId> {
+    carrier: P,
+    envelope: TimerCounter<TC>,
+    pulses: Pulses,
+    state: State<D, L>,
+    ticks_hz: u32,
+}
+
+impl<P, TC, D, L> IrTransmitter<P, TC, D, L>
+where
+    P: CarrierTcc,
+    TC: EnvelopeTc,
+    D: ChId,
+    L: ChId,
+{
+    /// Sets up the carrier on `channel` of the TCC, and the envelope timer.
+    /// `pulses` is the buffer of the frames to send, and `durations` and
+    /// `levels` are the DMA channels feeding the timer and the pattern
+    /// generator.
+    pub fn new(
+        mut carrier: P,
+        channel: PwmChannel,
+        envelope: TimerCounter<TC>,
+        pulses: Pulses,
+        durations: Channel<D, Ready>,
+        levels: Channel<L, Ready>,
+    ) -> Self {
+        let tcc = carrier.tcc();
+        tcc.patt.write(|w| unsafe { w.bits(SPACE) });
+        while tcc.syncbusy.read().patt().bit_is_set() {}
+        let duty = carrier.get_max_duty() / 3;
+        carrier.set_duty(channel, duty);
+        carrier.enable(channel);
+
+        let freq = envelope.freq().to_Hz();
+        let divider = [1, 2, 4, 8, 16, 64, 256, 1024]
+            .iter()
+            .copied()
+            .find(|divider| freq / divider <= MAX_ENVELOPE_HZ)
+            .unwrap_or(1024);
+        let count = envelope.tc().count_16();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.status.read().syncbusy().bit_is_set() {}
+        count.ctrla.write(|w| w.swrst().set_bit());
+        // the SVD erroneously marks swrst as write-only, so we
+        // need to manually read the bit here
+        while count.ctrla.read().bits() & 1 != 0 {}
+        count.ctrla.modify(|_, w| {
+            crate::timer::prescale(w, divider);
+            w.wavegen().mfrq()
+        });
+        while count.status.read().syncbusy().bit_is_set() {}
+
+        Self {
+            carrier,
+            envelope,
+            pulses,
+            state: State::Idle(durations, levels),
+            ticks_hz: freq / divider,
+        }
+    }
+
+    /// Gives access to the pulse buffer, unless a frame is being sent
+    pub fn pulses(&mut self) -> Option<&mut [u16]> {
+        if self.poll() {
+            Some(&mut *self.pulses)
+        } else {
+            None
+        }
+    }
+
+    /// Encodes `command` into the pulse buffer and sends it
+    pub fn send_command<PR: Protocol>(&mut self, command: &PR::Command) -> Result<(), SendError> {
+        if !self.poll() {
+            return Err(SendError::Busy);
+        }
+        let len = PR::encode(command, &mut self.pulses);
+        self.send(len)
+    }
+
+    /// Sends the first `len` durations of the pulse buffer, in microseconds.
+    ///
+    /// The durations are converted in place to timer ticks, so the buffer
+    /// must be filled again before the next frame. `len` must be odd, and the
+    /// buffer must hold at least one more duration, which is overwritten by
+    /// the gap ending the frame.
+    pub fn send(&mut self, len: usize) -> Result<(), SendError> {
+        if !self.poll() {
+            return Err(SendError::Busy);
+        }
+        if len % 2 == 0 || len >= self.pulses.len() {
+            return Err(SendError::InvalidLength);
+        }
+        // The timer overflows one tick after reaching its top value
+        for duration in self.pulses[..len].iter_mut() {
+            let ticks = *duration as u64 * self.ticks_hz as u64 / 1_000_000;
+            *duration = ticks.saturating_sub(1).min(u16::MAX as u64 - 1) as u16;
+        }
+        self.pulses[len] = END_GAP;
+
+        if let State::Idle(durations, levels) = core::mem::replace(&mut self.state, State::Moving) {
+            let tcc = self.carrier.tcc();
+            let count = self.envelope.tc().count_16();
+            tcc.patt.write(|w| unsafe { w.bits(MARK) });
+            while tcc.syncbusy.read().patt().bit_is_set() {}
+            count.count.write(|w| unsafe { w.count().bits(0) });
+            while count.status.read().syncbusy().bit_is_set() {}
+            count.cc[0].write(|w| unsafe { w.cc().bits(self.pulses[0]) });
+            while count.status.read().syncbusy().bit_is_set() {}
+
+            let timer = Register {
+                reg: count.cc[0].as_ptr() as *mut u16,
+            };
+            let pattern = Register {
+                reg: tcc.pattb.as_ptr() as *mut u16,
+            };
+            let source = Durations {
+                start: unsafe { self.pulses.as_mut_ptr().add(1) },
+                len,
+            };
+            // SAFETY: The pulse buffer is 'static, and only given out again
+            // once the transfers are stopped. The transfers are never dropped
+            // while running.
+            let durations = unsafe { Transfer::new_unchecked(durations, source, timer, false) }
+                .begin(TC::DMA_OVF_TRIGGER, TriggerAction::BEAT);
+            let levels = unsafe { Transfer::new_unchecked(levels, Levels, pattern, true) }
+                .begin(TC::DMA_OVF_TRIGGER, TriggerAction::BEAT);
+
+            count.ctrla.modify(|_, w| w.enable().set_bit());
+            self.state = State::Sending(durations, levels);
+        }
+        Ok(())
+    }
+
+    /// Returns true if no frame is being sent. Once the last mark is sent,
+    /// this must be called within 16ms, or the carrier comes back on.
+    pub fn poll(&mut self) -> bool {
+        if let State::Sending(durations, _) = &mut self.state {
+            if !durations.complete() {
+                return false;
+            }
+            if let State::Sending(durations, levels) =
+                core::mem::replace(&mut self.state, State::Moving)
+            {
+                let count = self.envelope.tc().count_16();
+                count.ctrla.modify(|_, w| w.enable().clear_bit());
+                let tcc = self.carrier.tcc();
+                tcc.patt.write(|w| unsafe { w.bits(SPACE) });
+                while tcc.syncbusy.read().patt().bit_is_set() {}
+                let (durations, _, _) = durations.stop();
+                let (levels, _, _) = levels.stop();
+                while count.status.read().syncbusy().bit_is_set() {}
+                self.state = State::Idle(durations, levels);
+            }
+        }
+        true
+    }
+
+    /// Waits for the frame being sent, and releases the carrier, the timer,
+    /// the pulse buffer and the DMA channels
+    #[allow(clippy::type_complexity)]
+    pub fn free(
+        mut self,
+    ) -> (
+        P,
+        TimerCounter<TC>,
+        Pulses,
+        Channel<D, Ready>,
+        Channel<L, Ready>,
+    ) {
+        while !self.poll() {}
+        match self.state {
+            State::Idle(durations, levels) => {
+                (self.carrier, self.envelope, self.pulses, durations, levels)
+            }
+            _ => unreachable!(),
+        }
+    }
+}
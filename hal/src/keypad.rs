@@ -0,0 +1,465 @@
+//! Keyboard matrix scanning
+//!
+//! The keys of a matrix keypad join one row line to one column line each.
+//! [`Keypad`] drives the rows low one at a time and reads the columns back
+//! through their pull-ups, so that a low column is a pressed key of the
+//! selected row:
+//!
+//! ```
+//! let rows: [DynPin; 4] = [pins.pa02.into(), pins.pa03.into(), pins.pa04.into(), pins.pa05.into()];
+//! let cols: [DynPin; 3] = [pins.pa06.into(), pins.pa07.into(), pins.pa08.into()];
+//! let tick = rtc.into_tick::<1024>();
+//! let debounce = Microseconds::from_ticks(5_000);
+//! let mut keypad = Keypad::new(rows, cols, Scanner::new::<RtcTick<1024>, _>(debounce));
+//! loop {
+//!     keypad.scan(&tick)?;
+//!     while let Some(event) = keypad.pop() {
+//!         report(event.row, event.col, event.pressed);
+//!     }
+//!     delay.delay_ms(1u8);
+//! }
+//! ```
+//!
+//! [`Scanner`] debounces the raw scans, however they were acquired, with a
+//! [`Debouncer`] per key: a key changes state once it has read the same for
+//! the debounce period, measured against a [`Tick`] source. Each change is
+//! queued as an [`Event`], and any number of keys can be down at once.
+//!
+//! While the queue is full, the keys whose state differs from their last
+//! event are reported once there is room. A key which goes back to the state
+//! of its last event before then has nothing left to report, so its press and
+//! release are both lost: pop the events at least as often as the keys can
+//! change, or the queue only holds the net changes.
+//!
+//! Without a diode per key, three keys at the corners of a rectangle also
+//! connect the fourth corner, which reads as pressed: a ghost. Unless the
+//! matrix has diodes, new presses on any two rows sharing two pressed columns
+//! are held back until the ambiguity clears. Releases are always reported.
+//!
+//! Between key presses, [`Keypad::idle`] drives all the rows low, so that a
+//! press pulls its column low. With the columns configured as EIC wake
+//! sources sensing a low level, the device can sleep until a key is pressed,
+//! then [`Keypad::resume`] restores the scanning.
+
+use crate::tick::{Debouncer, Tick};
+use crate::time::Microseconds;
+
+#[cfg(all(feature = "device", feature = "unproven"))]
+use crate::gpio::v2::{DynPin, Error};
+#[cfg(all(feature = "device", feature = "unproven"))]
+use crate::hal::digital::v2::{InputPin, OutputPin};
+
+/// Number of events which can be queued
+pub const QUEUE_LEN: usize = 32;
+
+/// A key which changed state
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Event {
+    pub row: u8,
+    pub col: u8,
+    /// Whether the key was pressed, or released
+    pub pressed: bool,
+}
+
+const NO_EVENT: Event = Event {
+    row: 0,
+    col: 0,
+    pressed: false,
+};
+
+/// Debouncing, ghosting detection and event queue of a matrix of `R` rows
+/// and `C` columns, see the [module](self) documentation
+#[derive(Clone, Debug)]
+pub struct Scanner<const R: usize, const C: usize> {
+    diodes: bool,
+    keys: [[Debouncer; C]; R],
+    /// The state of each key in its last queued event
+    reported: [[bool; C]; R],
+    queue: [Event; QUEUE_LEN],
+    head: usize,
+    len: usize,
+}
+
+impl<const R: usize, const C: usize> Scanner<R, C> {
+    /// Creates a scanner where a key changes state once it has read the same
+    /// for `period`, measured against the tick source `T`. 5ms suits most
+    /// switches, with at least two scans in that time.
+    pub fn new<T: Tick, D: Into<Microseconds>>(period: D) -> Self {
+        let key = Debouncer::new::<T, _>(false, period);
+        Self {
+            diodes: false,
+            keys: [[key; C]; R],
+            reported: [[false; C]; R],
+            queue: [NO_EVENT; QUEUE_LEN],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Sets whether each key has a diode, which makes ghosting impossible,
+    /// so that ghosting detection can be skipped. Defaults to `false`.
+    pub fn diodes(mut self, diodes: bool) -> Self {
+        self.diodes = diodes;
+        self
+    }
+
+    /// Keys whose reading is ambiguous, as they may be ghosts
+    fn ghosts(&self, raw: &[[bool; C]; R]) -> [[bool; C]; R] {
+        let mut ghosts = [[false; C]; R];
+        if self.diodes {
+            return ghosts;
+        }
+        for r1 in 0..R {
+            for r2 in r1 + 1..R {
+                let shared = (0..C).filter(|&c| raw[r1][c] && raw[r2][c]).count();
+                if shared < 2 {
+                    continue;
+                }
+                for c in (0..C).filter(|&c| raw[r1][c] && raw[r2][c]) {
+                    ghosts[r1][c] = true;
+                    ghosts[r2][c] = true;
+                }
+            }
+        }
+        ghosts
+    }
+
+    /// Updates the keys from `raw`, a scan where `raw[row][col]` is set for
+    /// each key which reads as pressed, taken at the current time of `tick`
+    pub fn update<T: Tick>(&mut self, tick: &T, raw: &[[bool; C]; R]) {
+        let ghosts = self.ghosts(raw);
+        for (row, keys) in raw.iter().enumerate() {
+            for (col, &pressed) in keys.iter().enumerate() {
+                let key = &mut self.keys[row][col];
+                // A possible ghost reads as the current state, which holds
+                // back its press
+                let input = if pressed && ghosts[row][col] {
+                    key.state()
+                } else {
+                    pressed
+                };
+                key.update(tick, input);
+            }
+        }
+        for row in 0..R {
+            for col in 0..C {
+                let pressed = self.keys[row][col].state();
+                if pressed == self.reported[row][col] {
+                    continue;
+                }
+                let event = Event {
+                    row: row as u8,
+                    col: col as u8,
+                    pressed,
+                };
+                // Without room, the change is reported on a later scan
+                if self.push(event) {
+                    self.reported[row][col] = pressed;
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, event: Event) -> bool {
+        if self.len == QUEUE_LEN {
+            return false;
+        }
+        self.queue[(self.head + self.len) % QUEUE_LEN] = event;
+        self.len += 1;
+        true
+    }
+
+    /// Returns the oldest event which was not returned yet
+    pub fn pop(&mut self) -> Option<Event> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = self.queue[self.head];
+        self.head = (self.head + 1) % QUEUE_LEN;
+        self.len -= 1;
+        Some(event)
+    }
+
+    /// Whether the key at `row` and `col` is pressed, once debounced
+    pub fn is_pressed(&self, row: usize, col: usize) -> bool {
+        self.keys[row][col].state()
+    }
+
+    /// Whether any key is pressed, or changing state, or any event is queued
+    /// or waiting for room. Once idle, the device can sleep until a key is
+    /// pressed.
+    pub fn is_active(&self) -> bool {
+        self.len > 0
+            || self
+                .keys
+                .iter()
+                .zip(self.reported.iter())
+                .any(|(keys, reported)| {
+                    keys.iter()
+                        .zip(reported.iter())
+                        .any(|(key, &reported)| key.state() || key.is_pending() || reported)
+                })
+    }
+}
+
+/// A keypad scanned through [`DynPin`]s, see the [module](self)
+/// documentation
+#[cfg(all(feature = "device", feature = "unproven"))]
+pub struct Keypad<const R: usize, const C: usize> {
+    rows: [DynPin; R],
+    cols: [DynPin; C],
+    scanner: Scanner<R, C>,
+    settle: u32,
+}
+
+#[cfg(all(feature = "device", feature = "unproven"))]
+impl<const R: usize, const C: usize> Keypad<R, C> {
+    /// Scans the keys joining `rows` to `cols` with `scanner`. The rows are
+    /// left floating and the columns are pulled up until they are scanned.
+    pub fn new(mut rows: [DynPin; R], mut cols: [DynPin; C], scanner: Scanner<R, C>) -> Self {
+        for row in rows.iter_mut() {
+            row.into_floating_input();
+        }
+        for col in cols.iter_mut() {
+            col.into_pull_up_input();
+        }
+        Self {
+            rows,
+            cols,
+            scanner,
+            settle: 10,
+        }
+    }
+
+    /// Sets the number of CPU cycles to wait between driving a row low and
+    /// reading the columns, for long wires or slow pull-ups. Defaults to 10.
+    pub fn set_settle_cycles(&mut self, cycles: u32) {
+        self.settle = cycles;
+    }
+
+    /// Reads all of the keys once, and updates the scanner at the current
+    /// time of `tick`
+    pub fn scan<T: Tick>(&mut self, tick: &T) -> Result<(), Error> {
+        let mut raw = [[false; C]; R];
+        for (row, keys) in self.rows.iter_mut().zip(raw.iter_mut()) {
+            // Only the selected row is driven, so that two keys pressed in
+            // the same column don't short two rows together
+            row.into_push_pull_output();
+            row.set_low()?;
+            cortex_m::asm::delay(self.settle);
+            for (col, key) in self.cols.iter().zip(keys.iter_mut()) {
+                *key = col.is_low()?;
+            }
+            row.into_floating_input();
+        }
+        self.scanner.update(tick, &raw);
+        Ok(())
+    }
+
+    /// Returns the oldest event which was not returned yet
+    pub fn pop(&mut self) -> Option<Event> {
+        self.scanner.pop()
+    }
+
+    /// The debouncing state of the keys
+    pub fn scanner(&self) -> &Scanner<R, C> {
+        &self.scanner
+    }
+
+    /// Drives all the rows low, so that pressing any key pulls its column
+    /// low, and switches the columns to their external interrupt function,
+    /// still pulled up. The columns can then wake the device through the
+    /// EIC, until [`Keypad::resume`] is called.
+    pub fn idle(&mut self) -> Result<(), Error> {
+        for row in self.rows.iter_mut() {
+            row.into_push_pull_output();
+            row.set_low()?;
+        }
+        for col in self.cols.iter_mut() {
+            col.into_pull_up_interrupt();
+        }
+        Ok(())
+    }
+
+    /// Restores the pins for scanning after [`Keypad::idle`]
+    pub fn resume(&mut self) {
+        for row in self.rows.iter_mut() {
+            row.into_floating_input();
+        }
+        for col in self.cols.iter_mut() {
+            col.into_pull_up_input();
+        }
+    }
+
+    /// Releases the pins
+    pub fn free(self) -> ([DynPin; R], [DynPin; C]) {
+        (self.rows, self.cols)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    /// A tick source advanced by 1ms after each scan
+    struct FakeTick(Cell<u32>);
+
+    impl Tick for FakeTick {
+        const HZ: u32 = 1_000;
+
+        fn ticks(&self) -> u32 {
+            self.0.get()
+        }
+    }
+
+    /// A scanner which accepts a change on its second scan
+    fn scanner() -> (Scanner<3, 3>, FakeTick) {
+        let period = Microseconds::from_ticks(0);
+        (Scanner::new::<FakeTick, _>(period), FakeTick(Cell::new(0)))
+    }
+
+    fn press(
+        (scanner, tick): &mut (Scanner<3, 3>, FakeTick),
+        keys: &[(usize, usize)],
+        scans: usize,
+    ) {
+        let mut raw = [[false; 3]; 3];
+        for &(row, col) in keys {
+            raw[row][col] = true;
+        }
+        for _ in 0..scans {
+            scanner.update(tick, &raw);
+            tick.0.set(tick.0.get() + 1);
+        }
+    }
+
+    const ALL: [(usize, usize); 9] = [
+        (0, 0),
+        (0, 1),
+        (0, 2),
+        (1, 0),
+        (1, 1),
+        (1, 2),
+        (2, 0),
+        (2, 1),
+        (2, 2),
+    ];
+
+    #[test]
+    fn debounce() {
+        // A change is accepted 2ms after it is first read, on the third scan
+        let period = Microseconds::from_ticks(2_000);
+        let mut keypad = (
+            Scanner::<3, 3>::new::<FakeTick, _>(period),
+            FakeTick(Cell::new(0)),
+        );
+        press(&mut keypad, &[(1, 2)], 2);
+        assert_eq!(keypad.0.pop(), None);
+        // A bounce restarts the period
+        press(&mut keypad, &[], 1);
+        press(&mut keypad, &[(1, 2)], 2);
+        assert_eq!(keypad.0.pop(), None);
+        press(&mut keypad, &[(1, 2)], 1);
+        assert_eq!(
+            keypad.0.pop(),
+            Some(Event {
+                row: 1,
+                col: 2,
+                pressed: true
+            })
+        );
+        assert!(keypad.0.is_pressed(1, 2));
+        press(&mut keypad, &[], 3);
+        assert_eq!(
+            keypad.0.pop(),
+            Some(Event {
+                row: 1,
+                col: 2,
+                pressed: false
+            })
+        );
+        assert!(!keypad.0.is_active());
+    }
+
+    #[test]
+    fn ghosts_are_held_back() {
+        let mut keypad = scanner();
+        press(&mut keypad, &[(0, 0), (0, 1), (1, 0)], 2);
+        assert_eq!(keypad.0.pop().map(|e| (e.row, e.col)), Some((0, 0)));
+        assert_eq!(keypad.0.pop().map(|e| (e.row, e.col)), Some((0, 1)));
+        assert_eq!(keypad.0.pop().map(|e| (e.row, e.col)), Some((1, 0)));
+        // The three keys connect (1, 1), which is ambiguous
+        press(&mut keypad, &[(0, 0), (0, 1), (1, 0), (1, 1)], 3);
+        assert_eq!(keypad.0.pop(), None);
+        assert!(!keypad.0.is_pressed(1, 1));
+        // But releases are reported
+        press(&mut keypad, &[(0, 1), (1, 1)], 2);
+        assert_eq!(keypad.0.pop().map(|e| (e.row, e.col)), Some((0, 0)));
+        assert_eq!(
+            keypad.0.pop(),
+            Some(Event {
+                row: 1,
+                col: 0,
+                pressed: false
+            })
+        );
+        assert_eq!(keypad.0.pop().map(|e| (e.row, e.col)), Some((1, 1)));
+    }
+
+    #[test]
+    fn diodes_allow_any_keys() {
+        let (scanner, tick) = scanner();
+        let mut keypad = (scanner.diodes(true), tick);
+        press(&mut keypad, &[(0, 0), (0, 1), (1, 0), (1, 1)], 2);
+        assert_eq!((0..4).filter_map(|_| keypad.0.pop()).count(), 4);
+    }
+
+    #[test]
+    fn full_queue_delays_events() {
+        let (scanner, tick) = scanner();
+        let mut keypad = (scanner.diodes(true), tick);
+        // Two presses and releases of every key overflow the queue
+        for _ in 0..2 {
+            press(&mut keypad, &ALL, 2);
+            press(&mut keypad, &[], 2);
+        }
+        assert!(keypad.0.is_active());
+        let mut events = 0;
+        while keypad.0.pop().is_some() {
+            events += 1;
+        }
+        assert_eq!(events, QUEUE_LEN);
+        // The last four releases are reported once there is room
+        press(&mut keypad, &[], 1);
+        assert_eq!((0..QUEUE_LEN).filter_map(|_| keypad.0.pop()).count(), 4);
+        assert!(!keypad.0.is_active());
+    }
+
+    #[test]
+    fn full_queue_keeps_net_changes() {
+        let (scanner, tick) = scanner();
+        let mut keypad = (scanner.diodes(true), tick);
+        press(&mut keypad, &ALL, 2);
+        press(&mut keypad, &[], 2);
+        press(&mut keypad, &ALL, 2);
+        // Only the releases of the first five keys fit
+        press(&mut keypad, &[], 2);
+        // The last four keys are pressed again before there is room, so
+        // their release and press are lost
+        press(&mut keypad, &ALL, 2);
+        assert_eq!((0..QUEUE_LEN).filter_map(|_| keypad.0.pop()).count(), 32);
+        press(&mut keypad, &ALL, 1);
+        for &(row, col) in &ALL[..5] {
+            assert_eq!(
+                keypad.0.pop(),
+                Some(Event {
+                    row: row as u8,
+                    col: col as u8,
+                    pressed: true
+                })
+            );
+        }
+        assert_eq!(keypad.0.pop(), None);
+    }
+}
@@ -0,0 +1,569 @@
+//! Wear-levelled key-value store in the flash
+//!
+//! Emulating an EEPROM by rewriting a settings block in place erases the same
+//! flash rows on every change, until they wear out. A [`KvStore`] appends
+//! each change to a log instead, so that the rows are only erased once the
+//! whole log has been filled.
+//!
+//! The [`Storage`] is split into sectors, used in turn as a ring. Entries,
+//! made of a `u16` key and a value of up to
+//! [`max_value_len`](KvStore::max_value_len) bytes, are appended to the
+//! active sector. When it is full, the next sector becomes active, and the
+//! entries of the oldest sector which are still current are copied to it
+//! before the oldest sector is erased. This compaction keeps one sector free,
+//! and spreads the erases evenly over all the sectors. Each sector keeps the
+//! number of times it was erased, see [`erase_count`](KvStore::erase_count).
+//!
+//! ```
+//! let flash = unsafe { FlashRegion::new(peripherals.NVMCTRL, 0x3_8000, 0x8000) };
+//! let mut store = KvStore::new(flash, 4)?;
+//!
+//! let mut buf = [0; 4];
+//! let boots = match store.get(BOOTS, &mut buf)? {
+//!     Some(4) => u32::from_le_bytes(buf),
+//!     _ => 0,
+//! };
+//! store.set(BOOTS, &(boots + 1).to_le_bytes())?;
+//! ```
+//!
+//! Once the store is full, [`set`](KvStore::set) fails with
+//! [`Error::Full`], but keys can still be removed to make room.
+//!
+//! Entries and sector headers are checked with a CRC, so that a power
+//! failure during a change leaves the previous value of the key. The erase
+//! count of a sector is only lost if the power fails while it is erased.
+
+use crate::nvm::{crc32, Error, Storage, CRC_INIT};
+
+/// "KVST"
+const MAGIC: u32 = 0x5453_564b;
+
+/// Key, length and CRC
+const ENTRY_HEADER_LEN: usize = 8;
+
+/// Length of the entries recording a removed key
+const TOMBSTONE: u16 = 0xffff;
+
+/// Key which is not available, as its removal looks like erased flash
+const RESERVED_KEY: u16 = 0xffff;
+
+/// Largest [`Storage::WRITE_SIZE`] supported
+const MAX_WRITE_SIZE: usize = 64;
+
+/// State of a sector, read from its header
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Sector {
+    /// Erased, with its erase count
+    Free(u32),
+    /// Holding entries, with its erase count and its sequence number
+    Used(u32, u32),
+    /// Not formatted, or left by a power failure, with its erase count if
+    /// known
+    Invalid(Option<u32>),
+}
+
+/// Header of an entry
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Entry {
+    key: u16,
+    /// Length of the value, or `None` if the key was removed
+    len: Option<usize>,
+}
+
+/// Result of reading the log at an offset
+enum Step {
+    /// A valid entry, and the offset of the next one
+    Entry(Entry, usize),
+    /// Erased flash, where the next entry is written
+    End,
+    /// An entry left by a power failure
+    Corrupt,
+}
+
+/// A wear-levelled key-value store, see the [module](self) documentation
+pub struct KvStore<S: Storage> {
+    storage: S,
+    sectors: usize,
+    sector_len: usize,
+    active: usize,
+    seq: u32,
+    /// Offset of the next entry in the active sector
+    head: usize,
+}
+
+impl<S: Storage> KvStore<S> {
+    /// The first unit of a sector holds its erase count, and the second one
+    /// its sequence number, written when it becomes active
+    const DATA_START: usize = 2 * S::WRITE_SIZE;
+
+    /// Opens the store kept in `storage`, split in `sectors` sectors, and
+    /// formats the sectors which are not part of it yet
+    ///
+    /// Panics if there are less than two sectors, if they are too small, or
+    /// if the write units of `storage` are not between 16 and 64 bytes long.
+    pub fn new(storage: S, sectors: usize) -> Result<Self, Error> {
+        assert!(
+            S::WRITE_SIZE >= 16 && S::WRITE_SIZE <= MAX_WRITE_SIZE,
+            "unsupported write size"
+        );
+        let sector_len = storage.capacity() / sectors.max(1) / S::ERASE_SIZE * S::ERASE_SIZE;
+        assert!(
+            sectors >= 2 && sector_len >= Self::DATA_START + S::WRITE_SIZE,
+            "storage too small for a key-value store"
+        );
+        let mut store = Self {
+            storage,
+            sectors,
+            sector_len,
+            active: 0,
+            seq: 0,
+            head: Self::DATA_START,
+        };
+
+        let mut newest = None;
+        for sector in 0..sectors {
+            match store.sector(sector)? {
+                Sector::Used(_, seq) if newest.map_or(true, |(_, newest)| seq > newest) => {
+                    newest = Some((sector, seq))
+                }
+                Sector::Invalid(count) => store.format(sector, count.unwrap_or(0) + 1)?,
+                _ => {}
+            }
+        }
+        match newest {
+            Some((sector, seq)) => {
+                store.active = sector;
+                store.seq = seq;
+                store.head = store.end(sector)?;
+            }
+            None => store.activate(0, 0)?,
+        }
+        // Finish a compaction interrupted by a power failure
+        store.collect()?;
+        Ok(store)
+    }
+
+    /// Returns the number of sectors
+    pub fn sectors(&self) -> usize {
+        self.sectors
+    }
+
+    /// Returns the longest value which can be stored
+    pub fn max_value_len(&self) -> usize {
+        self.sector_len - Self::DATA_START - S::WRITE_SIZE - ENTRY_HEADER_LEN
+    }
+
+    /// Reads the value of `key` to the start of `buf`, and returns its
+    /// length, or `None` if the key is not set
+    pub fn get(&mut self, key: u16, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        match self.find(key)? {
+            Some((offset, Entry { len: Some(len), .. })) => {
+                let buf = buf.get_mut(..len).ok_or(Error::TooLong)?;
+                self.storage.read(offset + ENTRY_HEADER_LEN, buf)?;
+                Ok(Some(len))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Sets the value of `key`, which must not be `0xffff`
+    ///
+    /// If this fails, or if the power fails before it returns, `key` keeps
+    /// its previous value.
+    pub fn set(&mut self, key: u16, value: &[u8]) -> Result<(), Error> {
+        if value.len() > self.max_value_len() {
+            return Err(Error::TooLong);
+        }
+        self.append(key, Some(value))
+    }
+
+    /// Removes `key`, if it is set
+    pub fn remove(&mut self, key: u16) -> Result<(), Error> {
+        match self.find(key)? {
+            Some((_, Entry { len: Some(_), .. })) => self.append(key, None),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the number of times `sector` was erased, or `None` if it was
+    /// lost in a power failure
+    pub fn erase_count(&mut self, sector: usize) -> Result<Option<u32>, Error> {
+        match self.sector(sector)? {
+            Sector::Free(count) | Sector::Used(count, _) => Ok(Some(count)),
+            Sector::Invalid(count) => Ok(count),
+        }
+    }
+
+    /// Releases the storage
+    pub fn free(self) -> S {
+        self.storage
+    }
+
+    /// Appends an entry setting `key` to `value`, or removing it if `None`
+    fn append(&mut self, key: u16, value: Option<&[u8]>) -> Result<(), Error> {
+        if key == RESERVED_KEY {
+            return Err(Error::OutOfBounds);
+        }
+        let len = Self::entry_len(value.map_or(0, |value| value.len()));
+        // The last unit of each sector is kept for a removal. The removals
+        // are not copied by the compactions, so that there is always room
+        // for one after them, even once the store is full.
+        let reserved = if value.is_some() { S::WRITE_SIZE } else { 0 };
+        // Each compaction frees at least the entries replaced since the
+        // oldest sector was written, unless the store is full
+        for _ in 0..self.sectors {
+            if self.head + len + reserved <= self.sector_len {
+                return self.write_entry(key, value);
+            }
+            self.advance()?;
+        }
+        Err(Error::Full)
+    }
+
+    /// Writes an entry at the head of the active sector
+    fn write_entry(&mut self, key: u16, value: Option<&[u8]>) -> Result<(), Error> {
+        let data = value.unwrap_or(&[]);
+        let mut header = [0; ENTRY_HEADER_LEN];
+        header[..2].copy_from_slice(&key.to_le_bytes());
+        let len = value.map_or(TOMBSTONE, |value| value.len() as u16);
+        header[2..4].copy_from_slice(&len.to_le_bytes());
+        let crc = crc32(crc32(CRC_INIT, &header[..4]), data);
+        header[4..].copy_from_slice(&crc.to_le_bytes());
+
+        let mut unit = [0xff; MAX_WRITE_SIZE];
+        let unit = &mut unit[..S::WRITE_SIZE];
+        let (first, rest) = data.split_at(data.len().min(S::WRITE_SIZE - ENTRY_HEADER_LEN));
+        unit[..ENTRY_HEADER_LEN].copy_from_slice(&header);
+        unit[ENTRY_HEADER_LEN..ENTRY_HEADER_LEN + first.len()].copy_from_slice(first);
+
+        let offset = self.head;
+        let base = self.active * self.sector_len;
+        // Whatever happens, the area is not written again
+        self.head = self.sector_len;
+        self.storage.write(base + offset, unit)?;
+        if !rest.is_empty() {
+            self.storage.write(base + offset + S::WRITE_SIZE, rest)?;
+        }
+        self.commit(offset)
+    }
+
+    /// Moves the head past the entry just written at `offset` of the active
+    /// sector, once it is checked
+    fn commit(&mut self, offset: usize) -> Result<(), Error> {
+        match self.entry(self.active, offset)? {
+            Step::Entry(_, next) => {
+                self.head = next;
+                Ok(())
+            }
+            _ => Err(Error::Verify),
+        }
+    }
+
+    /// Makes the next sector active, and compacts the one after it
+    fn advance(&mut self) -> Result<(), Error> {
+        let next = (self.active + 1) % self.sectors;
+        self.activate(next, self.seq.wrapping_add(1))?;
+        self.collect()
+    }
+
+    /// Writes the sequence number of the free `sector`, and makes it active
+    fn activate(&mut self, sector: usize, seq: u32) -> Result<(), Error> {
+        let mut unit = [0; 8];
+        unit[..4].copy_from_slice(&seq.to_le_bytes());
+        let crc = crc32(CRC_INIT, &unit[..4]);
+        unit[4..].copy_from_slice(&crc.to_le_bytes());
+        self.storage
+            .write(sector * self.sector_len + S::WRITE_SIZE, &unit)?;
+        self.active = sector;
+        self.seq = seq;
+        self.head = Self::DATA_START;
+        Ok(())
+    }
+
+    /// Frees the sector after the active one, if needed, by copying its
+    /// current entries to the active sector and erasing it
+    fn collect(&mut self) -> Result<(), Error> {
+        let victim = (self.active + 1) % self.sectors;
+        let count = match self.sector(victim)? {
+            Sector::Free(_) => return Ok(()),
+            Sector::Invalid(count) => count.unwrap_or(0),
+            Sector::Used(count, _) => {
+                let base = victim * self.sector_len;
+                let mut offset = Self::DATA_START;
+                while let Step::Entry(entry, next) = self.entry(victim, offset)? {
+                    // The removals need not be kept past the oldest sector
+                    if entry.len.is_some()
+                        && self.find(entry.key)?.map(|(found, _)| found) == Some(base + offset)
+                    {
+                        self.copy_entry(base + offset, next - offset)?;
+                    }
+                    offset = next;
+                }
+                count
+            }
+        };
+        self.format(victim, count.wrapping_add(1))
+    }
+
+    /// Copies the `len` bytes of the entry at `from` to the head of the
+    /// active sector
+    fn copy_entry(&mut self, from: usize, len: usize) -> Result<(), Error> {
+        if self.head + len > self.sector_len {
+            return Err(Error::Full);
+        }
+        let offset = self.head;
+        let base = self.active * self.sector_len;
+        self.head = self.sector_len;
+        let mut unit = [0; MAX_WRITE_SIZE];
+        let unit = &mut unit[..S::WRITE_SIZE];
+        for done in (0..len).step_by(S::WRITE_SIZE) {
+            self.storage.read(from + done, unit)?;
+            self.storage.write(base + offset + done, unit)?;
+        }
+        self.commit(offset)
+    }
+
+    /// Erases `sector`, and writes its erase count
+    fn format(&mut self, sector: usize, count: u32) -> Result<(), Error> {
+        let base = sector * self.sector_len;
+        for offset in (base..base + self.sector_len).step_by(S::ERASE_SIZE) {
+            self.storage.erase(offset)?;
+        }
+        let mut unit = [0; 12];
+        unit[..4].copy_from_slice(&MAGIC.to_le_bytes());
+        unit[4..8].copy_from_slice(&count.to_le_bytes());
+        let crc = crc32(CRC_INIT, &unit[..8]);
+        unit[8..].copy_from_slice(&crc.to_le_bytes());
+        self.storage.write(base, &unit)
+    }
+
+    /// Reads the state of `sector`
+    fn sector(&mut self, sector: usize) -> Result<Sector, Error> {
+        let base = sector * self.sector_len;
+        let mut unit = [0; 12];
+        self.storage.read(base, &mut unit)?;
+        let count = word(&unit, 1);
+        if word(&unit, 0) != MAGIC || word(&unit, 2) != crc32(CRC_INIT, &unit[..8]) {
+            return Ok(Sector::Invalid(None));
+        }
+        let mut unit = [0; 8];
+        self.storage.read(base + S::WRITE_SIZE, &mut unit)?;
+        Ok(if unit == [0xff; 8] {
+            Sector::Free(count)
+        } else if word(&unit, 1) == crc32(CRC_INIT, &unit[..4]) {
+            Sector::Used(count, word(&unit, 0))
+        } else {
+            Sector::Invalid(Some(count))
+        })
+    }
+
+    /// Returns the offset where the next entry of `sector` is written, or
+    /// the end of the sector if an entry was left by a power failure
+    fn end(&mut self, sector: usize) -> Result<usize, Error> {
+        let mut offset = Self::DATA_START;
+        loop {
+            match self.entry(sector, offset)? {
+                Step::Entry(_, next) => offset = next,
+                Step::End => return Ok(offset),
+                Step::Corrupt => return Ok(self.sector_len),
+            }
+        }
+    }
+
+    /// Reads the entry at `offset` of `sector`, and checks its CRC
+    fn entry(&mut self, sector: usize, offset: usize) -> Result<Step, Error> {
+        if offset + ENTRY_HEADER_LEN > self.sector_len {
+            return Ok(Step::End);
+        }
+        let base = sector * self.sector_len;
+        let mut header = [0; ENTRY_HEADER_LEN];
+        self.storage.read(base + offset, &mut header)?;
+        if header == [0xff; ENTRY_HEADER_LEN] {
+            return Ok(Step::End);
+        }
+        let key = u16::from_le_bytes([header[0], header[1]]);
+        let len = match u16::from_le_bytes([header[2], header[3]]) {
+            TOMBSTONE => None,
+            len => Some(len as usize),
+        };
+        let next = offset + Self::entry_len(len.unwrap_or(0));
+        if next > self.sector_len {
+            return Ok(Step::Corrupt);
+        }
+
+        let mut crc = crc32(CRC_INIT, &header[..4]);
+        let mut chunk = [0; 32];
+        let mut done = 0;
+        while done < len.unwrap_or(0) {
+            let n = chunk.len().min(len.unwrap_or(0) - done);
+            let from = base + offset + ENTRY_HEADER_LEN + done;
+            self.storage.read(from, &mut chunk[..n])?;
+            crc = crc32(crc, &chunk[..n]);
+            done += n;
+        }
+        if crc != word(&header, 1) {
+            return Ok(Step::Corrupt);
+        }
+        Ok(Step::Entry(Entry { key, len }, next))
+    }
+
+    /// Returns the offset in the storage and the header of the newest entry
+    /// of `key`
+    fn find(&mut self, key: u16) -> Result<Option<(usize, Entry)>, Error> {
+        let mut found = None;
+        // From the oldest sector to the active one
+        for i in 1..=self.sectors {
+            let sector = (self.active + i) % self.sectors;
+            if let Sector::Used(..) = self.sector(sector)? {
+                let mut offset = Self::DATA_START;
+                while let Step::Entry(entry, next) = self.entry(sector, offset)? {
+                    if entry.key == key {
+                        found = Some((sector * self.sector_len + offset, entry));
+                    }
+                    offset = next;
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    /// Returns the length of an entry with a value of `len` bytes, in whole
+    /// write units
+    fn entry_len(len: usize) -> usize {
+        (ENTRY_HEADER_LEN + len + S::WRITE_SIZE - 1) / S::WRITE_SIZE * S::WRITE_SIZE
+    }
+}
+
+/// Returns the `i`th little endian word of `bytes`
+fn word(bytes: &[u8], i: usize) -> u32 {
+    let bytes = &bytes[4 * i..4 * i + 4];
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nvm::FakeFlash;
+
+    /// Four sectors of flash
+    type Flash = FakeFlash<512>;
+
+    fn get(store: &mut KvStore<Flash>, key: u16) -> Option<u32> {
+        let mut buf = [0; 4];
+        let len = store.get(key, &mut buf).unwrap()?;
+        assert_eq!(len, 4);
+        Some(u32::from_le_bytes(buf))
+    }
+
+    fn set(store: &mut KvStore<Flash>, key: u16, value: u32) -> Result<(), Error> {
+        store.set(key, &value.to_le_bytes())
+    }
+
+    #[test]
+    fn set_get_remove() {
+        let mut store = KvStore::new(Flash::new(), 4).unwrap();
+        assert_eq!(store.max_value_len(), 128 - 32 - 16 - 8);
+        assert_eq!(get(&mut store, 1), None);
+
+        set(&mut store, 1, 10).unwrap();
+        set(&mut store, 2, 20).unwrap();
+        set(&mut store, 1, 11).unwrap();
+        store.set(3, b"").unwrap();
+        assert_eq!(get(&mut store, 1), Some(11));
+        assert_eq!(get(&mut store, 2), Some(20));
+        assert_eq!(store.get(3, &mut []), Ok(Some(0)));
+
+        store.remove(2).unwrap();
+        assert_eq!(get(&mut store, 2), None);
+        assert_eq!(store.get(1, &mut [0; 3]), Err(Error::TooLong));
+        assert_eq!(store.set(0xffff, b""), Err(Error::OutOfBounds));
+        assert_eq!(store.set(4, &[0; 73]), Err(Error::TooLong));
+
+        let mut store = KvStore::new(store.free(), 4).unwrap();
+        assert_eq!(get(&mut store, 1), Some(11));
+        assert_eq!(get(&mut store, 2), None);
+        assert_eq!(store.get(3, &mut []), Ok(Some(0)));
+    }
+
+    #[test]
+    fn compaction_levels_wear() {
+        let mut store = KvStore::new(Flash::new(), 4).unwrap();
+        set(&mut store, 100, 7).unwrap();
+        for i in 0..1000 {
+            set(&mut store, (i % 3) as u16, i).unwrap();
+        }
+        assert_eq!(get(&mut store, 0), Some(999));
+        assert_eq!(get(&mut store, 1), Some(997));
+        assert_eq!(get(&mut store, 2), Some(998));
+        assert_eq!(get(&mut store, 100), Some(7));
+
+        let mut counts = [0; 4];
+        for (sector, count) in counts.iter_mut().enumerate() {
+            *count = store.erase_count(sector).unwrap().unwrap();
+        }
+        let (min, max) = (counts.iter().min(), counts.iter().max());
+        assert!(max.unwrap() - min.unwrap() <= 1, "{:?}", counts);
+        // Each erase makes room for several changes
+        assert!(counts.iter().sum::<u32>() < 1000 / 4, "{:?}", counts);
+
+        let flash = store.free();
+        // The counts kept in the sectors match the erases of the two units
+        // of each sector
+        assert_eq!(flash.erases, 2 * counts.iter().sum::<u32>() as usize);
+        let mut store = KvStore::new(flash, 4).unwrap();
+        assert_eq!(get(&mut store, 0), Some(999));
+        assert_eq!(get(&mut store, 100), Some(7));
+    }
+
+    #[test]
+    fn full() {
+        let mut store = KvStore::new(Flash::new(), 4).unwrap();
+        let mut key = 0;
+        let result = loop {
+            if let Err(error) = set(&mut store, key, key as u32) {
+                break error;
+            }
+            key += 1;
+        };
+        assert_eq!(result, Error::Full);
+        for key in 0..key {
+            assert_eq!(get(&mut store, key), Some(key as u32));
+        }
+        store.remove(0).unwrap();
+        set(&mut store, 0, 1).unwrap();
+        assert_eq!(get(&mut store, 0), Some(1));
+    }
+
+    #[test]
+    fn power_failure() {
+        for writes in 0..100 {
+            let mut flash = KvStore::new(Flash::new(), 4).unwrap().free();
+            flash.writes_left = writes;
+            let mut store = KvStore::new(flash, 4).unwrap();
+            let mut values = [None; 4];
+            let mut failed = None;
+            for i in 0..40 {
+                let key = i as usize % 4;
+                if set(&mut store, key as u16, i).is_err() {
+                    failed = Some((key, i));
+                    break;
+                }
+                values[key] = Some(i);
+            }
+
+            let mut flash = store.free();
+            flash.writes_left = usize::MAX;
+            let mut store = KvStore::new(flash, 4).unwrap();
+            for key in 0..4 {
+                let value = get(&mut store, key as u16);
+                match failed {
+                    // Either the old or the new value
+                    Some((failed, i)) if failed == key => {
+                        assert!(value == values[key] || value == Some(i))
+                    }
+                    _ => assert_eq!(value, values[key]),
+                }
+            }
+        }
+    }
+}
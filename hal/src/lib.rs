@@ -2,6 +2,7 @@
 
 pub extern crate embedded_hal as hal;
 
+pub use nb;
 pub use paste;
 
 pub mod typelevel;
@@ -78,25 +79,79 @@ macro_rules! dbgprint {
     ($($arg:tt)*) => {{}};
 }
 
+#[cfg(feature = "unproven")]
+pub mod adc_channel;
+#[cfg(feature = "unproven")]
+pub mod bitbang_i2c;
+#[cfg(feature = "device")]
+pub mod boot;
+pub mod ccm;
+#[cfg(feature = "device")]
+pub mod clock_scaling;
+#[cfg(feature = "critical-section-single-core")]
+mod critical_section_impl;
 #[cfg(feature = "device")]
 pub mod delay;
+#[cfg(feature = "unproven")]
+pub mod dotstar;
 #[cfg(feature = "device")]
 pub mod gpio;
+pub mod gps;
+#[cfg(feature = "unproven")]
+pub mod hc_sr04;
+#[cfg(feature = "device")]
+pub mod interrupt_handler;
+pub mod ir;
+pub mod keypad;
+pub mod kvstore;
+#[cfg(any(feature = "log-rtt", feature = "log-itm", feature = "defmt-itm"))]
+pub mod log;
+pub mod modbus;
+pub mod monotonic;
+#[cfg(feature = "device")]
+pub mod nvic;
+pub mod nvm;
+#[cfg(any(feature = "panic-usb", feature = "panic-watchdog"))]
+pub mod panic_persist;
+#[cfg(feature = "panic-usb")]
+pub mod panic_usb;
+#[cfg(feature = "panic-watchdog")]
+pub mod panic_watchdog;
 #[cfg(feature = "device")]
 pub mod prelude;
 #[cfg(feature = "device")]
 pub mod rtc;
+pub mod sbus;
 #[cfg(feature = "device")]
 pub mod sercom;
 pub mod sleeping_delay;
 #[cfg(feature = "device")]
 pub mod spi_common;
+pub mod thermal;
+pub mod tick;
 pub mod time;
 pub mod timer_params;
 pub mod timer_traits;
+pub mod touch;
+#[cfg(all(feature = "usb", not(feature = "samd11")))]
+pub mod usb_composite;
+#[cfg(feature = "usb-hid")]
+pub mod usb_hid;
+#[cfg(feature = "usb-midi")]
+pub mod usb_midi;
+#[cfg(feature = "usb")]
+pub mod usb_power;
 
 #[cfg(all(feature = "unproven", feature = "dma"))]
 pub mod dmac;
+#[cfg(all(feature = "unproven", feature = "dma"))]
+pub mod dmx;
+#[cfg(all(feature = "unproven", feature = "dma", feature = "min-samd51g"))]
+pub mod double_buffer;
+#[cfg(all(feature = "unproven", feature = "dma", feature = "min-samd51g"))]
+pub mod hub75;
+#[cfg(all(feature = "unproven", feature = "dma"))]
+pub mod spi_display;
 
 #[cfg(any(feature = "samd11", feature = "samd21"))]
 pub mod thumbv6m;
@@ -117,25 +172,77 @@ pub mod pad {
 // This module maintains backwards compatibility within this major release
 #[macro_use]
 pub mod common {
+    #[cfg(feature = "unproven")]
+    pub use crate::adc_channel;
+    #[cfg(feature = "unproven")]
+    pub use crate::bitbang_i2c;
+    #[cfg(feature = "device")]
+    pub use crate::boot;
+    pub use crate::ccm;
+    #[cfg(feature = "device")]
+    pub use crate::clock_scaling;
     #[cfg(feature = "device")]
     pub use crate::delay;
+    #[cfg(feature = "unproven")]
+    pub use crate::dotstar;
     #[cfg(feature = "device")]
     pub use crate::gpio;
+    pub use crate::gps;
+    #[cfg(feature = "unproven")]
+    pub use crate::hc_sr04;
+    #[cfg(feature = "device")]
+    pub use crate::interrupt_handler;
+    pub use crate::ir;
+    pub use crate::keypad;
+    pub use crate::kvstore;
+    #[cfg(any(feature = "log-rtt", feature = "log-itm", feature = "defmt-itm"))]
+    pub use crate::log;
+    pub use crate::modbus;
+    pub use crate::monotonic;
+    #[cfg(feature = "device")]
+    pub use crate::nvic;
+    pub use crate::nvm;
+    #[cfg(any(feature = "panic-usb", feature = "panic-watchdog"))]
+    pub use crate::panic_persist;
+    #[cfg(feature = "panic-usb")]
+    pub use crate::panic_usb;
+    #[cfg(feature = "panic-watchdog")]
+    pub use crate::panic_watchdog;
     #[cfg(feature = "device")]
     pub use crate::prelude;
     #[cfg(feature = "device")]
     pub use crate::rtc;
+    pub use crate::sbus;
     #[cfg(feature = "device")]
     pub use crate::sercom;
     pub use crate::sleeping_delay;
     #[cfg(feature = "device")]
     pub use crate::spi_common;
+    pub use crate::thermal;
+    pub use crate::tick;
     pub use crate::time;
     pub use crate::timer_params;
     pub use crate::timer_traits;
+    pub use crate::touch;
+    #[cfg(all(feature = "usb", not(feature = "samd11")))]
+    pub use crate::usb_composite;
+    #[cfg(feature = "usb-hid")]
+    pub use crate::usb_hid;
+    #[cfg(feature = "usb-midi")]
+    pub use crate::usb_midi;
+    #[cfg(feature = "usb")]
+    pub use crate::usb_power;
 
     #[cfg(all(feature = "unproven", feature = "dma"))]
     pub use crate::dmac;
+    #[cfg(all(feature = "unproven", feature = "dma"))]
+    pub use crate::dmx;
+    #[cfg(all(feature = "unproven", feature = "dma", feature = "min-samd51g"))]
+    pub use crate::double_buffer;
+    #[cfg(all(feature = "unproven", feature = "dma", feature = "min-samd51g"))]
+    pub use crate::hub75;
+    #[cfg(all(feature = "unproven", feature = "dma"))]
+    pub use crate::spi_display;
 
     #[cfg(any(feature = "samd11", feature = "samd21"))]
     pub use crate::thumbv6m;
@@ -0,0 +1,192 @@
+//! Logging over RTT or ITM
+//!
+//! This module registers a backend for the [`log`](::log) or `defmt` crates,
+//! so that examples can switch between logging backends through features
+//! alone:
+//!
+//! * `log-rtt`: `log` records are written to the J-Link RTT up channel. This
+//!   works on every chip, and is the usual choice for the Cortex-M0+ parts,
+//!   which have no ITM.
+//! * `log-itm`: `log` records are written to ITM stimulus port 0 and sent
+//!   over the SWO pin. Cortex-M4 parts only.
+//! * `defmt-itm`: `defmt` frames are written to ITM stimulus port 0.
+//!   Cortex-M4 parts only. For `defmt` over RTT, use the `defmt-rtt` crate.
+//!
+//! Each record is prefixed with a timestamp in microseconds, taken from the
+//! function registered with [`set_timestamp`], typically reading a
+//! [`MonotonicClock`](crate::monotonic::MonotonicClock).
+//!
+//! ```
+//! atsamd_hal::log::init_itm(&mut core.ITM, &mut core.DCB, &mut core.TPIU, 120_000_000, 2_000_000);
+//! atsamd_hal::log::set_timestamp(|| {
+//!     cortex_m::interrupt::free(|cs| match CLOCK.borrow(cs).borrow().as_ref() {
+//!         Some(clock) => clock.now().duration_since_epoch().to_micros(),
+//!         None => 0,
+//!     })
+//! });
+//! atsamd_hal::log::init(log::LevelFilter::Info);
+//! log::info!("hello");
+//! ```
+
+#[cfg(any(feature = "log-itm", feature = "defmt-itm"))]
+use cortex_m::peripheral::{DCB, ITM, TPIU};
+
+#[cfg(all(feature = "log-rtt", feature = "log-itm"))]
+compile_error!("Only one of the `log-rtt` and `log-itm` features may be enabled");
+
+#[cfg(all(
+    any(feature = "log-itm", feature = "defmt-itm"),
+    any(feature = "samd11", feature = "samd21")
+))]
+compile_error!(
+    "The `log-itm` and `defmt-itm` features need an ITM, which the SAMD11 and SAMD21 lack"
+);
+
+static mut TIMESTAMP: fn() -> u64 = no_timestamp;
+
+fn no_timestamp() -> u64 {
+    0
+}
+
+/// Registers the function providing the timestamp of each record, in
+/// microseconds. Without one, all timestamps are zero.
+pub fn set_timestamp(timestamp: fn() -> u64) {
+    // SAFETY: Pointer-sized writes are atomic on all of the supported cores
+    cortex_m::interrupt::free(|_| unsafe { TIMESTAMP = timestamp });
+}
+
+/// Returns the current timestamp, in microseconds
+pub fn timestamp() -> u64 {
+    unsafe { TIMESTAMP() }
+}
+
+/// Enables the ITM and configures the TPIU to send stimulus port 0 over SWO,
+/// as NRZ (UART) data at `swo_hz`.
+///
+/// The SWO pin, PB30 on the SAMD51, must also be switched to its trace
+/// function, alternate H.
+#[cfg(any(feature = "log-itm", feature = "defmt-itm"))]
+pub fn init_itm(itm: &mut ITM, dcb: &mut DCB, tpiu: &mut TPIU, core_hz: u32, swo_hz: u32) {
+    dcb.enable_trace();
+    unsafe {
+        tpiu.acpr.write(core_hz / swo_hz - 1);
+        // Asynchronous NRZ encoding, formatter bypassed
+        tpiu.sppr.write(2);
+        tpiu.ffcr.write(0x100);
+        // Unlock the ITM, then enable it with trace bus ID 1 and sync packets
+        itm.lar.write(0xC5AC_CE55);
+        itm.tcr.write(1 << 16 | 1 << 2 | 1);
+        itm.ter[0].write(1);
+    }
+}
+
+#[cfg(any(feature = "log-rtt", feature = "log-itm"))]
+struct Logger;
+
+#[cfg(any(feature = "log-rtt", feature = "log-itm"))]
+static LOGGER: Logger = Logger;
+
+#[cfg(any(feature = "log-rtt", feature = "log-itm"))]
+impl ::log::Log for Logger {
+    fn enabled(&self, metadata: &::log::Metadata) -> bool {
+        metadata.level() <= ::log::max_level()
+    }
+
+    fn log(&self, record: &::log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let micros = timestamp();
+        macro_rules! record_line {
+            () => {
+                format_args!(
+                    "{}.{:06} {:<5} {}\n",
+                    micros / 1_000_000,
+                    micros % 1_000_000,
+                    record.level(),
+                    record.args()
+                )
+            };
+        }
+        #[cfg(feature = "log-rtt")]
+        {
+            use core::fmt::Write;
+            jlink_rtt::NonBlockingOutput::new()
+                .write_fmt(record_line!())
+                .ok();
+        }
+        #[cfg(feature = "log-itm")]
+        cortex_m::interrupt::free(|_| {
+            // SAFETY: The stimulus port is only accessed in critical sections
+            let stim = unsafe { &mut (*ITM::PTR).stim[0] };
+            cortex_m::itm::write_fmt(stim, record_line!());
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+/// Registers the `log` backend and sets the maximum level. With `log-itm`,
+/// call [`init_itm`] first.
+#[cfg(any(feature = "log-rtt", feature = "log-itm"))]
+pub fn init(level: ::log::LevelFilter) {
+    // The Cortex-M0+ has no atomic read-modify-write, so neither
+    // `set_logger` nor `set_max_level` are available there. Interrupts are
+    // disabled instead.
+    cortex_m::interrupt::free(|_| unsafe {
+        ::log::set_logger_racy(&LOGGER).ok();
+        ::log::set_max_level_racy(level);
+    });
+}
+
+#[cfg(feature = "defmt-itm")]
+mod defmt_itm {
+    use cortex_m::interrupt;
+    use cortex_m::peripheral::ITM;
+    use cortex_m::register::primask;
+
+    #[defmt::global_logger]
+    struct Logger;
+
+    static mut TAKEN: bool = false;
+    static mut RESTORE: bool = false;
+    static mut ENCODER: defmt::Encoder = defmt::Encoder::new();
+
+    unsafe impl defmt::Logger for Logger {
+        fn acquire() {
+            let active = primask::read().is_active();
+            interrupt::disable();
+            unsafe {
+                if TAKEN {
+                    panic!("defmt logger taken reentrantly");
+                }
+                TAKEN = true;
+                RESTORE = active;
+                ENCODER.start_frame(write);
+            }
+        }
+
+        unsafe fn flush() {}
+
+        unsafe fn release() {
+            ENCODER.end_frame(write);
+            TAKEN = false;
+            if RESTORE {
+                interrupt::enable();
+            }
+        }
+
+        unsafe fn write(bytes: &[u8]) {
+            ENCODER.write(bytes, write);
+        }
+    }
+
+    fn write(bytes: &[u8]) {
+        // SAFETY: Only called while the logger is acquired, with interrupts
+        // disabled
+        let stim = unsafe { &mut (*ITM::PTR).stim[0] };
+        cortex_m::itm::write_all(stim, bytes);
+    }
+
+    defmt::timestamp!("{=u64:us}", super::timestamp());
+}
@@ -0,0 +1,287 @@
+//! Modbus RTU transport
+//!
+//! [`RtuTransport`] handles the serial line side of Modbus RTU, so that
+//! higher-level Modbus crates only deal with the protocol data units:
+//!
+//! * Frames are delimited by a silence of 3.5 character times, measured
+//!   with a timer restarted on each received byte.
+//! * The CRC-16 of received frames is checked and stripped, and appended to
+//!   sent frames.
+//! * The transmit enable pin of an RS-485 transceiver is raised while
+//!   sending, and released once the last character has left the UART.
+//!
+//! The frame format, usually 8E1 or 8N2, is configured on the UART itself.
+//!
+//! ```
+//! let t3_5 = modbus::t3_5_ns(19_200).nanos();
+//! let char_time = modbus::char_time_ns(19_200).nanos();
+//! let mut rtu = RtuTransport::new(uart, te_pin, timer, t3_5, char_time);
+//! loop {
+//!     if let Ok(request) = nb::block!(rtu.read_frame()) {
+//!         let len = handle_request(request, &mut response);
+//!         rtu.write_frame(&response[..len]).ok();
+//!     }
+//! }
+//! ```
+
+use crate::hal::digital::v2::OutputPin;
+use crate::hal::serial::{Read, Write};
+use crate::hal::timer::CountDown;
+
+/// Largest RTU frame, including the address and the CRC
+pub const MAX_FRAME_LEN: usize = 256;
+
+/// Errors of the RTU transport
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Error<E> {
+    /// Error of the UART
+    Serial(E),
+    /// The frame is shorter than an address, a function code and a CRC
+    TooShort,
+    /// The frame is longer than [`MAX_FRAME_LEN`]
+    Overrun,
+    /// The CRC of the frame is wrong
+    Crc,
+    /// The transmit enable pin could not be set
+    Pin,
+}
+
+/// Computes the Modbus CRC-16 of `data`. It is sent low byte first.
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xffff_u16;
+    for &byte in data {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xa001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// Returns the duration of an 11-bit character at `baud`, in nanoseconds
+pub const fn char_time_ns(baud: u32) -> u32 {
+    (11_000_000_000 / baud as u64) as u32
+}
+
+/// Returns the silence delimiting frames at `baud`, in nanoseconds. Above
+/// 19200 baud, the specification fixes it to 1.75ms.
+pub const fn t3_5_ns(baud: u32) -> u32 {
+    if baud > 19_200 {
+        1_750_000
+    } else {
+        char_time_ns(baud) * 7 / 2
+    }
+}
+
+/// A Modbus RTU transport over a UART, keying the transmit enable pin of an
+/// RS-485 transceiver
+pub struct RtuTransport<U, TE, T: CountDown> {
+    uart: U,
+    te: TE,
+    timer: T,
+    t3_5: T::Time,
+    char_time: T::Time,
+    buf: [u8; MAX_FRAME_LEN],
+    len: usize,
+    overrun: bool,
+    /// The frame was already reported as broken, and is dropped silently
+    discard: bool,
+    receiving: bool,
+}
+
+impl<U, TE, T, E> RtuTransport<U, TE, T>
+where
+    U: Read<u8, Error = E> + Write<u8, Error = E>,
+    TE: OutputPin,
+    T: CountDown,
+    T::Time: Copy,
+{
+    /// Creates the transport. `t3_5` is the silence delimiting frames, and
+    /// `char_time` the duration of a character, see [`t3_5_ns`] and
+    /// [`char_time_ns`].
+    pub fn new(uart: U, mut te: TE, timer: T, t3_5: T::Time, char_time: T::Time) -> Self {
+        te.set_low().ok();
+        Self {
+            uart,
+            te,
+            timer,
+            t3_5,
+            char_time,
+            buf: [0; MAX_FRAME_LEN],
+            len: 0,
+            overrun: false,
+            discard: false,
+            receiving: false,
+        }
+    }
+
+    /// Receives the available bytes, and returns the PDU of a complete frame,
+    /// i.e. the address and the data without the CRC, once the line has been
+    /// silent for 3.5 characters.
+    ///
+    /// A UART error is returned as soon as it occurs, and the rest of its
+    /// frame is then dropped without another error.
+    pub fn read_frame(&mut self) -> nb::Result<&[u8], Error<E>> {
+        loop {
+            match self.uart.read() {
+                Ok(byte) => {
+                    self.timer.start(self.t3_5);
+                    self.receiving = true;
+                    if self.len < MAX_FRAME_LEN {
+                        self.buf[self.len] = byte;
+                        self.len += 1;
+                    } else {
+                        self.overrun = true;
+                    }
+                }
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(e)) => {
+                    // Drop the frame, but keep waiting for its end
+                    self.timer.start(self.t3_5);
+                    self.receiving = true;
+                    self.discard = true;
+                    self.len = 0;
+                    return Err(nb::Error::Other(Error::Serial(e)));
+                }
+            }
+        }
+        if !self.receiving || self.timer.wait().is_err() {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.receiving = false;
+        let len = core::mem::replace(&mut self.len, 0);
+        let overrun = core::mem::replace(&mut self.overrun, false);
+        if core::mem::replace(&mut self.discard, false) {
+            return Err(nb::Error::WouldBlock);
+        }
+        if overrun {
+            return Err(nb::Error::Other(Error::Overrun));
+        }
+        if len < 4 {
+            return Err(nb::Error::Other(Error::TooShort));
+        }
+        // The CRC of a frame including its CRC is zero
+        if crc16(&self.buf[..len]) != 0 {
+            return Err(nb::Error::Other(Error::Crc));
+        }
+        Ok(&self.buf[..len - 2])
+    }
+
+    /// Sends a frame made of `pdu`, the address and the data, followed by its
+    /// CRC. Blocks until the last character has been sent.
+    pub fn write_frame(&mut self, pdu: &[u8]) -> Result<(), Error<E>> {
+        let crc = crc16(pdu).to_le_bytes();
+        self.te.set_high().map_err(|_| Error::Pin)?;
+        for &byte in pdu.iter().chain(crc.iter()) {
+            nb::block!(self.uart.write(byte)).map_err(Error::Serial)?;
+        }
+        nb::block!(self.uart.flush()).map_err(Error::Serial)?;
+        // The last character may still be shifting out
+        self.timer.start(self.char_time);
+        nb::block!(self.timer.wait()).ok();
+        self.te.set_low().map_err(|_| Error::Pin)?;
+        // Whatever was received in the meantime is our own echo
+        while self.uart.read().is_ok() {}
+        self.len = 0;
+        self.overrun = false;
+        self.discard = false;
+        self.receiving = false;
+        Ok(())
+    }
+
+    /// Releases the UART, the pin and the timer
+    pub fn free(self) -> (U, TE, T) {
+        (self.uart, self.te, self.timer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+
+    /// Returns the bytes of `rx`, with `None` standing for a UART error
+    struct FakeUart {
+        rx: &'static [Option<u8>],
+    }
+
+    impl Read<u8> for FakeUart {
+        type Error = ();
+        fn read(&mut self) -> nb::Result<u8, ()> {
+            match self.rx.split_first() {
+                Some((byte, rest)) => {
+                    self.rx = rest;
+                    byte.ok_or(nb::Error::Other(()))
+                }
+                None => Err(nb::Error::WouldBlock),
+            }
+        }
+    }
+
+    impl Write<u8> for FakeUart {
+        type Error = ();
+        fn write(&mut self, _: u8) -> nb::Result<(), ()> {
+            Ok(())
+        }
+        fn flush(&mut self) -> nb::Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    struct FakePin;
+
+    impl OutputPin for FakePin {
+        type Error = Infallible;
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    /// Expires as soon as it is waited on
+    struct FakeTimer;
+
+    impl CountDown for FakeTimer {
+        type Time = u32;
+        fn start<T: Into<u32>>(&mut self, _: T) {}
+        fn wait(&mut self) -> nb::Result<(), void::Void> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn serial_error_reported_once() {
+        let uart = FakeUart {
+            rx: &[Some(0x01), None, Some(0x03)],
+        };
+        let mut rtu = RtuTransport::new(uart, FakePin, FakeTimer, 0, 0);
+        assert_eq!(rtu.read_frame(), Err(nb::Error::Other(Error::Serial(()))));
+        assert_eq!(rtu.read_frame(), Err(nb::Error::WouldBlock));
+        assert_eq!(rtu.read_frame(), Err(nb::Error::WouldBlock));
+    }
+
+    #[test]
+    fn crc16_read_holding_registers() {
+        // Read 2 holding registers from address 0 of device 1
+        let frame = [0x01, 0x03, 0x00, 0x00, 0x00, 0x02];
+        assert_eq!(crc16(&frame).to_le_bytes(), [0xc4, 0x0b]);
+    }
+
+    #[test]
+    fn crc16_of_frame_with_crc_is_zero() {
+        let frame = [0x11, 0x06, 0x00, 0x01, 0x00, 0x03, 0x9a, 0x9b];
+        assert_eq!(crc16(&frame), 0);
+    }
+
+    #[test]
+    fn t3_5_fixed_above_19200() {
+        assert_eq!(t3_5_ns(9600), 4_010_415);
+        assert_eq!(t3_5_ns(115_200), 1_750_000);
+    }
+}
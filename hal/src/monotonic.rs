@@ -0,0 +1,191 @@
+//! Monotonic 64-bit timebase
+//!
+//! [`MonotonicClock`] extends a wrapping hardware counter, such as an
+//! [`RtcTick`](crate::rtc::RtcTick) or a
+//! [`TimerTick`](crate::timer::TimerTick), to 64 bits by counting its
+//! overflows. At 32.768kHz, a 64-bit count does not wrap for more than
+//! 17 million years, so instants can be compared and subtracted freely.
+//!
+//! Times are reported as [`fugit`] instants and durations at the tick rate,
+//! so the usual duration arithmetic and unit conversions are available.
+//!
+//! The overflow interrupt of the underlying counter must be unmasked in the
+//! NVIC, and its handler must call [`MonotonicClock::on_overflow`]:
+//!
+//! ```
+//! static CLOCK: Mutex<RefCell<Option<MonotonicClock<RtcTick<32_768>, 32_768>>>> =
+//!     Mutex::new(RefCell::new(None));
+//!
+//! let clock = MonotonicClock::new(rtc.into_tick::<32_768>());
+//! cortex_m::interrupt::free(|cs| CLOCK.borrow(cs).replace(Some(clock)));
+//! unsafe { NVIC::unmask(interrupt::RTC) };
+//!
+//! #[interrupt]
+//! fn RTC() {
+//!     cortex_m::interrupt::free(|cs| {
+//!         if let Some(clock) = CLOCK.borrow(cs).borrow_mut().as_mut() {
+//!             clock.on_overflow();
+//!         }
+//!     });
+//! }
+//! ```
+
+use crate::tick::Tick;
+
+/// A point in time measured by a [`MonotonicClock`] running at `HZ`
+pub type Instant<const HZ: u32> = fugit::TimerInstantU64<HZ>;
+
+/// A span of time measured by a [`MonotonicClock`] running at `HZ`
+pub type Duration<const HZ: u32> = fugit::TimerDurationU64<HZ>;
+
+/// A [`Tick`] source which can signal the wrapping of its counter
+pub trait OverflowTick: Tick {
+    /// Enables the overflow interrupt of the counter
+    fn enable_overflow_interrupt(&mut self);
+
+    /// Disables the overflow interrupt of the counter
+    fn disable_overflow_interrupt(&mut self);
+
+    /// Returns true if the counter has wrapped since the flag was last
+    /// cleared
+    fn overflow_pending(&self) -> bool;
+
+    /// Clears the overflow flag
+    fn clear_overflow(&mut self);
+}
+
+/// A 64-bit clock built on a wrapping [`OverflowTick`] source
+pub struct MonotonicClock<T, const HZ: u32> {
+    tick: T,
+    overflows: u32,
+}
+
+impl<T: OverflowTick, const HZ: u32> MonotonicClock<T, HZ> {
+    /// Starts counting from the current value of the tick source, and enables
+    /// its overflow interrupt.
+    ///
+    /// Panics if `HZ` does not match the rate of the tick source.
+    pub fn new(mut tick: T) -> Self {
+        assert_eq!(T::HZ, HZ, "tick source does not match clock rate");
+        tick.clear_overflow();
+        tick.enable_overflow_interrupt();
+        Self { tick, overflows: 0 }
+    }
+
+    /// Returns the current time
+    pub fn now(&self) -> Instant<HZ> {
+        let mut low = self.tick.ticks();
+        let mut high = self.overflows;
+        // If the counter wrapped but the interrupt has not been serviced yet,
+        // account for the wrap here. The counter is read again as the first
+        // read may have happened just before the wrap.
+        if self.tick.overflow_pending() {
+            low = self.tick.ticks();
+            high = high.wrapping_add(1);
+        }
+        Instant::from_ticks(combine::<T>(high, low))
+    }
+
+    /// Returns the time elapsed since `earlier`
+    pub fn elapsed(&self, earlier: Instant<HZ>) -> Duration<HZ> {
+        self.now() - earlier
+    }
+
+    /// Records a wrap of the counter. Call this from the interrupt handler of
+    /// the tick source.
+    pub fn on_overflow(&mut self) {
+        if self.tick.overflow_pending() {
+            self.tick.clear_overflow();
+            self.overflows = self.overflows.wrapping_add(1);
+        }
+    }
+
+    /// Disables the overflow interrupt and releases the tick source
+    pub fn free(mut self) -> T {
+        self.tick.disable_overflow_interrupt();
+        self.tick
+    }
+}
+
+/// Joins an overflow count and a counter value into a 64-bit tick count
+#[inline]
+fn combine<T: Tick>(overflows: u32, ticks: u32) -> u64 {
+    overflows as u64 * (T::MAX as u64 + 1) + ticks as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    struct FakeTick {
+        count: Cell<u32>,
+        overflow: Cell<bool>,
+    }
+
+    impl FakeTick {
+        fn set(&self, count: u32) {
+            if count < self.count.get() {
+                self.overflow.set(true);
+            }
+            self.count.set(count);
+        }
+    }
+
+    impl Tick for FakeTick {
+        const HZ: u32 = 1_000;
+        const MAX: u32 = 0xFFFF;
+
+        fn ticks(&self) -> u32 {
+            self.count.get()
+        }
+    }
+
+    impl OverflowTick for FakeTick {
+        fn enable_overflow_interrupt(&mut self) {}
+
+        fn disable_overflow_interrupt(&mut self) {}
+
+        fn overflow_pending(&self) -> bool {
+            self.overflow.get()
+        }
+
+        fn clear_overflow(&mut self) {
+            self.overflow.set(false);
+        }
+    }
+
+    fn clock(count: u32) -> MonotonicClock<FakeTick, 1_000> {
+        MonotonicClock::new(FakeTick {
+            count: Cell::new(count),
+            overflow: Cell::new(false),
+        })
+    }
+
+    #[test]
+    fn extends_past_wrap() {
+        let mut clock = clock(0xFFF0);
+        let start = clock.now();
+        assert_eq!(start.ticks(), 0xFFF0);
+        clock.tick.set(0x0010);
+        clock.on_overflow();
+        assert_eq!(clock.now().ticks(), 0x1_0010);
+        assert_eq!(clock.elapsed(start).ticks(), 0x20);
+    }
+
+    #[test]
+    fn accounts_for_pending_overflow() {
+        let mut clock = clock(0xFFFF);
+        clock.tick.set(0x0005);
+        assert_eq!(clock.now().ticks(), 0x1_0005);
+        clock.on_overflow();
+        assert_eq!(clock.now().ticks(), 0x1_0005);
+    }
+
+    #[test]
+    fn durations_convert() {
+        let clock = clock(0);
+        clock.tick.set(1_500);
+        assert_eq!(clock.now().duration_since_epoch().to_millis(), 1_500);
+    }
+}
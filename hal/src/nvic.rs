@@ -0,0 +1,173 @@
+//! Interrupt lines of the peripherals
+//!
+//! The names of the interrupt vectors differ between the chip families: a
+//! SERCOM has a single `SERCOM3` line on the SAMD11/21, but four lines from
+//! `SERCOM3_0` to `SERCOM3_OTHER` on the SAMD51. [`Interrupts`] maps the
+//! peripherals to their lines, so that interrupt-driven drivers can enable
+//! them without naming them:
+//!
+//! ```
+//! fn start<S: Sercom + Interrupts>(nvic: &mut NVIC) {
+//!     // Safety: the handlers of these lines don't share data with code
+//!     // relying on them being masked
+//!     unsafe { S::enable_in_nvic(nvic, 1) };
+//! }
+//! ```
+//!
+//! The `#[interrupt]` handlers themselves still need the names of the
+//! vectors, see the [`interrupt_handler`](crate::interrupt_handler) module.
+
+use crate::target_device as pac;
+use cortex_m::peripheral::NVIC;
+use pac::{Interrupt, NVIC_PRIO_BITS};
+
+/// Number of priority levels supported by the NVIC, 4 on the SAMD11/21 and
+/// 8 on the SAMD51
+pub const PRIORITY_LEVELS: u8 = 1 << NVIC_PRIO_BITS;
+
+/// A peripheral with one or more interrupt lines
+pub trait Interrupts {
+    /// The interrupt lines of the peripheral
+    const INTERRUPTS: &'static [Interrupt];
+
+    /// Sets the priority of all of the lines and unmasks them.
+    ///
+    /// `priority` goes from 0, the most urgent, to `PRIORITY_LEVELS - 1`,
+    /// and is shifted into the implemented bits of the priority registers.
+    ///
+    /// # Safety
+    ///
+    /// Unmasking an interrupt can break critical sections based on masking
+    /// it, and changing its priority can break priority-based critical
+    /// sections, see [`NVIC::unmask`] and [`NVIC::set_priority`].
+    unsafe fn enable_in_nvic(nvic: &mut NVIC, priority: u8) {
+        assert!(priority < PRIORITY_LEVELS);
+        for &irq in Self::INTERRUPTS {
+            nvic.set_priority(irq, priority << (8 - NVIC_PRIO_BITS));
+            NVIC::unmask(irq);
+        }
+    }
+
+    /// Masks all of the lines
+    fn disable_in_nvic() {
+        for &irq in Self::INTERRUPTS {
+            NVIC::mask(irq);
+        }
+    }
+
+    /// Clears the pending state of all of the lines
+    fn unpend() {
+        for &irq in Self::INTERRUPTS {
+            NVIC::unpend(irq);
+        }
+    }
+
+    /// Returns whether any of the lines is pending
+    fn is_pending() -> bool {
+        Self::INTERRUPTS.iter().any(|&irq| NVIC::is_pending(irq))
+    }
+}
+
+macro_rules! interrupts {
+    ($($(#[$cfg:meta])* $Periph:ident: [$($irq:ident),+];)+) => {
+        $(
+            $(#[$cfg])*
+            impl Interrupts for pac::$Periph {
+                const INTERRUPTS: &'static [Interrupt] = &[$(Interrupt::$irq),+];
+            }
+        )+
+    };
+}
+
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+interrupts! {
+    RTC: [RTC];
+    EIC: [EIC];
+    SERCOM0: [SERCOM0];
+    SERCOM1: [SERCOM1];
+    ADC: [ADC];
+    DMAC: [DMAC];
+    USB: [USB];
+}
+
+#[cfg(feature = "samd11")]
+interrupts! {
+    TCC0: [TCC0];
+    TC1: [TC1];
+    TC2: [TC2];
+    #[cfg(feature = "samd11d")]
+    SERCOM2: [SERCOM2];
+}
+
+#[cfg(feature = "samd21")]
+interrupts! {
+    SERCOM2: [SERCOM2];
+    SERCOM3: [SERCOM3];
+    TCC0: [TCC0];
+    TCC1: [TCC1];
+    TCC2: [TCC2];
+    TC3: [TC3];
+    TC4: [TC4];
+    TC5: [TC5];
+    #[cfg(feature = "min-samd21g")]
+    SERCOM4: [SERCOM4];
+    #[cfg(feature = "min-samd21g")]
+    SERCOM5: [SERCOM5];
+    #[cfg(feature = "min-samd21j")]
+    TC6: [TC6];
+    #[cfg(feature = "min-samd21j")]
+    TC7: [TC7];
+}
+
+#[cfg(feature = "min-samd51g")]
+interrupts! {
+    RTC: [RTC];
+    EIC: [
+        EIC_EXTINT_0, EIC_EXTINT_1, EIC_EXTINT_2, EIC_EXTINT_3,
+        EIC_EXTINT_4, EIC_EXTINT_5, EIC_EXTINT_6, EIC_EXTINT_7,
+        EIC_EXTINT_8, EIC_EXTINT_9, EIC_EXTINT_10, EIC_EXTINT_11,
+        EIC_EXTINT_12, EIC_EXTINT_13, EIC_EXTINT_14, EIC_EXTINT_15
+    ];
+    SERCOM0: [SERCOM0_0, SERCOM0_1, SERCOM0_2, SERCOM0_OTHER];
+    SERCOM1: [SERCOM1_0, SERCOM1_1, SERCOM1_2, SERCOM1_OTHER];
+    SERCOM2: [SERCOM2_0, SERCOM2_1, SERCOM2_2, SERCOM2_OTHER];
+    SERCOM3: [SERCOM3_0, SERCOM3_1, SERCOM3_2, SERCOM3_OTHER];
+    SERCOM4: [SERCOM4_0, SERCOM4_1, SERCOM4_2, SERCOM4_OTHER];
+    SERCOM5: [SERCOM5_0, SERCOM5_1, SERCOM5_2, SERCOM5_OTHER];
+    TCC0: [TCC0_OTHER, TCC0_MC0, TCC0_MC1, TCC0_MC2, TCC0_MC3, TCC0_MC4, TCC0_MC5];
+    TCC1: [TCC1_OTHER, TCC1_MC0, TCC1_MC1, TCC1_MC2, TCC1_MC3];
+    TCC2: [TCC2_OTHER, TCC2_MC0, TCC2_MC1, TCC2_MC2];
+    TC0: [TC0];
+    TC1: [TC1];
+    TC2: [TC2];
+    TC3: [TC3];
+    ADC0: [ADC0_OTHER, ADC0_RESRDY];
+    ADC1: [ADC1_OTHER, ADC1_RESRDY];
+    DMAC: [DMAC_0, DMAC_1, DMAC_2, DMAC_3, DMAC_OTHER];
+    USB: [USB_OTHER, USB_SOF_HSOF, USB_TRCPT0, USB_TRCPT1];
+    #[cfg(feature = "min-samd51j")]
+    TCC3: [TCC3_OTHER, TCC3_MC0, TCC3_MC1];
+    #[cfg(feature = "min-samd51j")]
+    TCC4: [TCC4_OTHER, TCC4_MC0, TCC4_MC1];
+    #[cfg(feature = "min-samd51j")]
+    TC4: [TC4];
+    #[cfg(feature = "min-samd51j")]
+    TC5: [TC5];
+    #[cfg(feature = "min-samd51n")]
+    SERCOM6: [SERCOM6_0, SERCOM6_1, SERCOM6_2, SERCOM6_OTHER];
+    #[cfg(feature = "min-samd51n")]
+    SERCOM7: [SERCOM7_0, SERCOM7_1, SERCOM7_2, SERCOM7_OTHER];
+    #[cfg(feature = "min-samd51n")]
+    TC6: [TC6];
+    #[cfg(feature = "min-samd51n")]
+    TC7: [TC7];
+}
+
+impl<TC: Interrupts> Interrupts for crate::timer::TimerCounter<TC> {
+    const INTERRUPTS: &'static [Interrupt] = TC::INTERRUPTS;
+}
+
+#[cfg(feature = "unproven")]
+impl<ADC: Interrupts> Interrupts for crate::adc::Adc<ADC> {
+    const INTERRUPTS: &'static [Interrupt] = ADC::INTERRUPTS;
+}
@@ -0,0 +1,370 @@
+//! Power-fail safe records in the flash
+//!
+//! Rewriting settings in place loses them if the power fails between the
+//! erase and the end of the write. A [`Journal`] splits its [`Storage`] in
+//! two slots instead, and each write is a transaction:
+//!
+//! 1. the slot which does not hold the current record is erased, and the new
+//!    record is written to it;
+//! 2. the record is read back and checked against its CRC;
+//! 3. the header of the slot, with a sequence number one above the current
+//!    one, is written last. This commits the record.
+//!
+//! When the journal is opened, the valid slot with the highest sequence
+//! number holds the current record. Until the header is written, the
+//! previous record stays current, so a power failure at any point leaves
+//! either the old or the new record.
+//!
+//! ```
+//! let flash = unsafe { FlashRegion::new(peripherals.NVMCTRL, 0x3_c000, 0x4000) };
+//! let mut journal = Journal::new(flash);
+//!
+//! let mut settings = [0; 16];
+//! if journal.read(&mut settings)?.is_none() {
+//!     settings = DEFAULT_SETTINGS;
+//! }
+//! // ...
+//! journal.write(&settings)?;
+//! ```
+//!
+//! The flash of each chip is a [`Storage`] with the `FlashRegion` of the
+//! `nvmctrl` module.
+
+/// Errors of the [`Storage`] and [`Journal`] operations
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Error {
+    /// An offset or a length is outside of the storage, or misaligned
+    OutOfBounds,
+    /// The addressed region of the flash is locked
+    Locked,
+    /// The NVM controller reported a programming error
+    Program,
+    /// The record read back does not match the record written
+    Verify,
+    /// The record does not fit in a slot, or in the buffer it is read to
+    TooLong,
+    /// The live records fill the storage
+    Full,
+}
+
+/// Memory which is erased, then written, in units of fixed sizes
+///
+/// Erased bytes read as `0xff`, and each unit must be written once between
+/// erases.
+pub trait Storage {
+    /// Size of the units erased at once, in bytes
+    const ERASE_SIZE: usize;
+    /// Size of the units written at once, in bytes
+    const WRITE_SIZE: usize;
+
+    /// Returns the size of the storage, in bytes, a multiple of
+    /// [`ERASE_SIZE`](Self::ERASE_SIZE)
+    fn capacity(&self) -> usize;
+
+    /// Fills `buf` with the bytes starting at `offset`
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), Error>;
+
+    /// Erases the unit starting at `offset`, a multiple of
+    /// [`ERASE_SIZE`](Self::ERASE_SIZE)
+    fn erase(&mut self, offset: usize) -> Result<(), Error>;
+
+    /// Writes `data` from `offset`, a multiple of
+    /// [`WRITE_SIZE`](Self::WRITE_SIZE). The last unit is padded with
+    /// `0xff`.
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), Error>;
+}
+
+/// "JRNL"
+const MAGIC: u32 = 0x4c4e_524a;
+
+/// Magic, sequence number, length, CRC of the record and CRC of the header
+const HEADER_LEN: usize = 20;
+
+/// The committed record of a [`Journal`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Record {
+    slot: usize,
+    seq: u32,
+    len: usize,
+}
+
+/// A record which is replaced atomically, see the [module](self)
+/// documentation
+pub struct Journal<S: Storage> {
+    storage: S,
+    slot_len: usize,
+    data_offset: usize,
+    current: Option<Record>,
+}
+
+impl<S: Storage> Journal<S> {
+    /// Opens the journal kept in `storage`, and finds its current record
+    ///
+    /// Panics if `storage` is smaller than two erase units.
+    pub fn new(storage: S) -> Self {
+        let slot_len = storage.capacity() / 2 / S::ERASE_SIZE * S::ERASE_SIZE;
+        let data_offset = (HEADER_LEN + S::WRITE_SIZE - 1) / S::WRITE_SIZE * S::WRITE_SIZE;
+        assert!(slot_len > data_offset, "storage too small for a journal");
+        let mut journal = Self {
+            storage,
+            slot_len,
+            data_offset,
+            current: None,
+        };
+        // A slot which cannot be read is ignored, as if it was never
+        // committed
+        let first = journal.check(0).unwrap_or(None);
+        let second = journal.check(1).unwrap_or(None);
+        journal.current = match (first, second) {
+            (Some(a), Some(b)) if (b.seq.wrapping_sub(a.seq) as i32) > 0 => Some(b),
+            (Some(a), _) => Some(a),
+            (None, b) => b,
+        };
+        journal
+    }
+
+    /// Returns the largest record which fits in a slot
+    pub fn capacity(&self) -> usize {
+        self.slot_len - self.data_offset
+    }
+
+    /// Returns the length of the current record, if any
+    pub fn record_len(&self) -> Option<usize> {
+        self.current.map(|record| record.len)
+    }
+
+    /// Reads the current record to the start of `buf`, and returns its
+    /// length, or `None` if nothing was committed yet
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        let record = match self.current {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let buf = buf.get_mut(..record.len).ok_or(Error::TooLong)?;
+        let offset = record.slot * self.slot_len + self.data_offset;
+        self.storage.read(offset, buf)?;
+        Ok(Some(record.len))
+    }
+
+    /// Replaces the current record with `data`
+    ///
+    /// If this fails, or if the power fails before it returns, the previous
+    /// record stays current.
+    pub fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        if data.len() > self.capacity() {
+            return Err(Error::TooLong);
+        }
+        let (slot, seq) = match self.current {
+            Some(record) => (1 - record.slot, record.seq.wrapping_add(1)),
+            None => (0, 0),
+        };
+        let base = slot * self.slot_len;
+        for offset in (base..base + self.slot_len).step_by(S::ERASE_SIZE) {
+            self.storage.erase(offset)?;
+        }
+        if !data.is_empty() {
+            self.storage.write(base + self.data_offset, data)?;
+        }
+        let crc = crc32(CRC_INIT, data);
+        if self.data_crc(base, data.len())? != crc {
+            return Err(Error::Verify);
+        }
+
+        let mut header = [0; HEADER_LEN];
+        for (word, value) in header
+            .chunks_mut(4)
+            .zip(&[MAGIC, seq, data.len() as u32, crc])
+        {
+            word.copy_from_slice(&value.to_le_bytes());
+        }
+        let header_crc = crc32(CRC_INIT, &header[..16]);
+        header[16..].copy_from_slice(&header_crc.to_le_bytes());
+        self.storage.write(base, &header)?;
+
+        let record = Record {
+            slot,
+            seq,
+            len: data.len(),
+        };
+        if self.check(slot)? != Some(record) {
+            return Err(Error::Verify);
+        }
+        self.current = Some(record);
+        Ok(())
+    }
+
+    /// Releases the storage
+    pub fn free(self) -> S {
+        self.storage
+    }
+
+    /// Returns the record committed in `slot`, if its header and its data
+    /// are intact
+    fn check(&mut self, slot: usize) -> Result<Option<Record>, Error> {
+        let base = slot * self.slot_len;
+        let mut header = [0; HEADER_LEN];
+        self.storage.read(base, &mut header)?;
+        let mut words = [0; 5];
+        for (value, word) in words.iter_mut().zip(header.chunks(4)) {
+            *value = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        let [magic, seq, len, crc, header_crc] = words;
+        if magic != MAGIC
+            || header_crc != crc32(CRC_INIT, &header[..16])
+            || len as usize > self.capacity()
+            || self.data_crc(base, len as usize)? != crc
+        {
+            return Ok(None);
+        }
+        Ok(Some(Record {
+            slot,
+            seq,
+            len: len as usize,
+        }))
+    }
+
+    /// Computes the CRC of the `len` bytes of the record in the slot at
+    /// `base`
+    fn data_crc(&mut self, base: usize, len: usize) -> Result<u32, Error> {
+        let mut crc = CRC_INIT;
+        let mut chunk = [0; 32];
+        let mut offset = 0;
+        while offset < len {
+            let n = chunk.len().min(len - offset);
+            self.storage
+                .read(base + self.data_offset + offset, &mut chunk[..n])?;
+            crc = crc32(crc, &chunk[..n]);
+            offset += n;
+        }
+        Ok(crc)
+    }
+}
+
+pub(crate) const CRC_INIT: u32 = 0;
+
+/// Continues the CRC-32 (IEEE 802.3) `crc` of the previous bytes over `data`
+pub(crate) fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                crc >> 1 ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Flash of `N` bytes for the tests, with 64 byte erase units and 16 byte
+/// write units, which counts its erases and loses power after `writes_left`
+/// write units
+#[cfg(test)]
+pub(crate) struct FakeFlash<const N: usize> {
+    pub mem: [u8; N],
+    pub writes_left: usize,
+    pub erases: usize,
+}
+
+#[cfg(test)]
+impl<const N: usize> FakeFlash<N> {
+    pub fn new() -> Self {
+        Self {
+            mem: [0xff; N],
+            writes_left: usize::MAX,
+            erases: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+impl<const N: usize> Storage for FakeFlash<N> {
+    const ERASE_SIZE: usize = 64;
+    const WRITE_SIZE: usize = 16;
+
+    fn capacity(&self) -> usize {
+        self.mem.len()
+    }
+
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), Error> {
+        buf.copy_from_slice(&self.mem[offset..offset + buf.len()]);
+        Ok(())
+    }
+
+    fn erase(&mut self, offset: usize) -> Result<(), Error> {
+        self.erases += 1;
+        self.mem[offset..offset + Self::ERASE_SIZE].fill(0xff);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), Error> {
+        for (i, unit) in data.chunks(Self::WRITE_SIZE).enumerate() {
+            if self.writes_left == 0 {
+                return Err(Error::Program);
+            }
+            self.writes_left -= 1;
+            let start = offset + i * Self::WRITE_SIZE;
+            for (cell, &byte) in self.mem[start..].iter_mut().zip(unit) {
+                *cell &= byte;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Flash = FakeFlash<256>;
+
+    fn read(journal: &mut Journal<Flash>) -> Option<[u8; 40]> {
+        let mut buf = [0; 40];
+        let len = journal.read(&mut buf).unwrap()?;
+        assert_eq!(len, 40);
+        Some(buf)
+    }
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(CRC_INIT, b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(crc32(CRC_INIT, b"1234"), b"56789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn write_and_reopen() {
+        let mut journal = Journal::new(Flash::new());
+        assert_eq!(journal.capacity(), 128 - 32);
+        assert_eq!(read(&mut journal), None);
+
+        journal.write(&[1; 40]).unwrap();
+        assert_eq!(read(&mut journal), Some([1; 40]));
+        journal.write(&[2; 40]).unwrap();
+        journal.write(&[3; 40]).unwrap();
+        assert_eq!(read(&mut journal), Some([3; 40]));
+
+        let mut journal = Journal::new(journal.free());
+        assert_eq!(read(&mut journal), Some([3; 40]));
+        assert_eq!(journal.write(&[0; 97]), Err(Error::TooLong));
+        assert_eq!(journal.read(&mut [0; 39]), Err(Error::TooLong));
+    }
+
+    #[test]
+    fn power_failure() {
+        // 3 units of data, then 2 units of header
+        for writes in 0..5 {
+            let mut journal = Journal::new(Flash::new());
+            journal.write(&[1; 40]).unwrap();
+            journal.write(&[2; 40]).unwrap();
+
+            let mut flash = journal.free();
+            flash.writes_left = writes;
+            let mut journal = Journal::new(flash);
+            assert_eq!(journal.write(&[3; 40]), Err(Error::Program));
+            let mut journal = Journal::new(journal.free());
+            assert_eq!(read(&mut journal), Some([2; 40]));
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Panic messages which survive a reset
+//!
+//! With the `panic-usb` or `panic-watchdog` feature, this module provides the
+//! panic handler. It saves the panic message to a RAM buffer, then resets
+//! the chip: through the watchdog with `panic-watchdog`, see
+//! [`panic_watchdog`](crate::panic_watchdog), or else with a software reset.
+//! After the reboot, [`PanicMessage::take`] recovers the message, which
+//! `panic-usb` reports over USB serial.
+//!
+//! Both features may be enabled together. No other panic handler crate,
+//! such as `panic-halt`, may be linked into the binary.
+//!
+//! The buffer is placed in the `.uninit` section, which `cortex-m-rt` leaves
+//! uninitialized at startup. Its content is random after a power cycle, so it
+//! is protected by a magic number.
+
+use core::fmt::{self, Write};
+use core::mem::MaybeUninit;
+use core::panic::PanicInfo;
+
+use cortex_m::interrupt;
+
+/// Capacity of the panic message buffer, in bytes
+pub const CAPACITY: usize = 256;
+
+/// Marks the buffer as holding a message written by the panic handler
+const MAGIC: u32 = 0x5041_4e43;
+
+struct PanicBuffer {
+    magic: u32,
+    len: usize,
+    data: [u8; CAPACITY],
+}
+
+impl Write for PanicBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        // Truncate messages which don't fit
+        let n = s.len().min(CAPACITY - self.len);
+        self.data[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+#[link_section = ".uninit.PANIC_PERSIST"]
+static mut PANIC_BUFFER: MaybeUninit<PanicBuffer> = MaybeUninit::uninit();
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    interrupt::disable();
+    save(info);
+    #[cfg(feature = "panic-watchdog")]
+    crate::panic_watchdog::reset();
+    #[cfg(not(feature = "panic-watchdog"))]
+    cortex_m::peripheral::SCB::sys_reset()
+}
+
+/// Saves the message of `info` for the next boot. Called by the panic
+/// handler, with interrupts disabled.
+fn save(info: &PanicInfo) {
+    // SAFETY: Interrupts are disabled and the panic handler does not return,
+    // so nothing else can access the buffer
+    let buffer = unsafe { &mut *PANIC_BUFFER.as_mut_ptr() };
+    buffer.magic = 0;
+    buffer.len = 0;
+    write!(buffer, "{}\r\n", info).ok();
+    buffer.magic = MAGIC;
+}
+
+/// The message of the panic which caused the last reset
+pub struct PanicMessage {
+    data: [u8; CAPACITY],
+    len: usize,
+}
+
+impl PanicMessage {
+    /// Returns the message saved by the panic handler, if the last reset was
+    /// caused by a panic, and clears it, so it is only reported once.
+    pub fn take() -> Option<Self> {
+        interrupt::free(|_| {
+            // SAFETY: Only the panic handler writes to the buffer, and the
+            // whole buffer is plain data, valid for any bit pattern
+            let buffer = unsafe { &mut *PANIC_BUFFER.as_mut_ptr() };
+            if buffer.magic != MAGIC || buffer.len > CAPACITY {
+                return None;
+            }
+            buffer.magic = 0;
+            Some(Self {
+                data: buffer.data,
+                len: buffer.len,
+            })
+        })
+    }
+
+    /// The panic message, possibly truncated to [`CAPACITY`] bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
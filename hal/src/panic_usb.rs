@@ -0,0 +1,121 @@
+//! Report the last panic over USB serial
+//!
+//! With the `panic-usb` feature, the panic handler of the
+//! [`panic_persist`](crate::panic_persist) module saves the panic message to
+//! a RAM buffer which survives a reset, then resets the chip. After the
+//! reboot, [`UsbSerial`] wraps the USB
+//! [`SerialPort`] and writes the message to it as soon as a host opens the
+//! port. [`UsbSerial::poll`] polls the `UsbDevice` and sends the message, so
+//! it replaces the usual call to `UsbDevice::poll`.
+//!
+//! [`PanicReport`] gives direct access to the message, for applications
+//! which don't use `UsbSerial`.
+//!
+//! This feature provides the `#[panic_handler]`, so no other panic handler
+//! crate, such as `panic-halt`, may be linked into the binary. The BSP
+//! examples only link `panic-halt` when neither `panic-usb` nor
+//! `panic-watchdog` is enabled; applications should gate their panic crate
+//! the same way:
+//!
+//! ```
+//! #[cfg(not(any(feature = "panic-usb", feature = "panic-watchdog")))]
+//! use panic_halt as _;
+//!
+//! let mut serial = UsbSerial::new(&usb_bus);
+//! loop {
+//!     if serial.poll(&mut usb_dev) {
+//!         // Read from and write to serial.port()
+//!     }
+//! }
+//! ```
+
+use usb_device::bus::{UsbBus, UsbBusAllocator};
+use usb_device::device::UsbDevice;
+use usbd_serial::SerialPort;
+
+use crate::panic_persist::PanicMessage;
+
+pub use crate::panic_persist::CAPACITY;
+
+/// The message of the panic which caused the last reset
+pub struct PanicReport {
+    message: PanicMessage,
+    sent: usize,
+}
+
+impl PanicReport {
+    /// Returns the message saved by the panic handler, if the last reset was
+    /// caused by a panic, and clears it, so it is only reported once.
+    pub fn take() -> Option<Self> {
+        PanicMessage::take().map(|message| Self { message, sent: 0 })
+    }
+
+    /// The panic message, possibly truncated to [`CAPACITY`] bytes
+    pub fn message(&self) -> &[u8] {
+        self.message.as_bytes()
+    }
+
+    /// Writes as much of the message as possible to the serial port, once the
+    /// host has opened it, i.e. set DTR. Call this after every poll of the
+    /// `UsbDevice`. Returns `true` once the whole message has been sent.
+    pub fn poll<B: UsbBus>(&mut self, serial: &mut SerialPort<'_, B>) -> bool {
+        let message = self.message.as_bytes();
+        if self.sent < message.len() && serial.dtr() {
+            if let Ok(n) = serial.write(&message[self.sent..]) {
+                self.sent += n;
+            }
+        }
+        self.sent == message.len()
+    }
+}
+
+/// A USB [`SerialPort`] which reports the panic that caused the last reset
+///
+/// The message is written to the port once the host opens it.
+pub struct UsbSerial<'a, B: UsbBus> {
+    port: SerialPort<'a, B>,
+    report: Option<PanicReport>,
+}
+
+impl<'a, B: UsbBus> UsbSerial<'a, B> {
+    /// Creates the serial port and takes the last panic message
+    pub fn new(alloc: &'a UsbBusAllocator<B>) -> Self {
+        Self::from_port(SerialPort::new(alloc))
+    }
+
+    /// Wraps an existing serial port and takes the last panic message
+    pub fn from_port(port: SerialPort<'a, B>) -> Self {
+        Self {
+            port,
+            report: PanicReport::take(),
+        }
+    }
+
+    /// The underlying serial port, to read and write application data
+    pub fn port(&mut self) -> &mut SerialPort<'a, B> {
+        &mut self.port
+    }
+
+    /// Polls the `UsbDevice`, then writes the pending panic message, if any.
+    /// Returns the result of `UsbDevice::poll`.
+    pub fn poll(&mut self, usb_dev: &mut UsbDevice<'a, B>) -> bool {
+        let ready = usb_dev.poll(&mut [&mut self.port]);
+        self.report();
+        ready
+    }
+
+    /// Writes the pending panic message, if any. Call this after every poll
+    /// of the `UsbDevice`, when it is not polled by [`UsbSerial::poll`].
+    pub fn report(&mut self) {
+        if let Some(report) = self.report.as_mut() {
+            if report.poll(&mut self.port) {
+                self.report = None;
+            }
+        }
+    }
+
+    /// Returns the serial port, dropping any unsent panic message
+    pub fn free(self) -> SerialPort<'a, B> {
+        self.port
+    }
+}
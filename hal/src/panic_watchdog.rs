@@ -0,0 +1,87 @@
+//! Recover from panics with a watchdog reset
+//!
+//! With the `panic-watchdog` feature, the panic handler of the
+//! [`panic_persist`](crate::panic_persist) module saves the panic message,
+//! then sets the watchdog to its shortest period, about 8ms, and waits for
+//! it to reset the chip. Unlike a
+//! software reset, this also recovers a device whose panic handler was
+//! entered with a corrupted state, and leaves a watchdog reset cause behind.
+//!
+//! After the reboot, [`last_panic`] returns the cause of the reset along
+//! with the panic message:
+//!
+//! ```
+//! if let Some((cause, message)) = panic_watchdog::last_panic(&peripherals.PM) {
+//!     // Log or send the message and the cause
+//! }
+//! ```
+//!
+//! If the watchdog was enabled in always-on mode, its configuration can't
+//! be changed, and the reset happens at the end of its current period. On
+//! the SAMD11/21, the watchdog is clocked by a generic clock, GCLK2 after a
+//! reset, which must be left running.
+//!
+//! This feature provides the `#[panic_handler]`, so no other panic handler
+//! crate, such as `panic-halt`, may be linked into the binary. It may be
+//! combined with `panic-usb`, which then reports the message after the
+//! watchdog reset.
+
+use crate::panic_persist::PanicMessage;
+use crate::target_device::WDT;
+use crate::{reset_cause, ResetCause};
+
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+use crate::target_device::PM as RCAUSE_CTRL;
+#[cfg(feature = "min-samd51g")]
+use crate::target_device::RSTC as RCAUSE_CTRL;
+
+/// Resets the chip through the watchdog. Called by the panic handler, with
+/// interrupts disabled, once the message is saved.
+pub(crate) fn reset() -> ! {
+    // SAFETY: The panic handler does not return, so it takes over the
+    // watchdog from whichever code owned it
+    let wdt = unsafe { &*WDT::ptr() };
+    shortest_period(wdt);
+    loop {
+        cortex_m::asm::nop();
+    }
+}
+
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+fn shortest_period(wdt: &crate::target_device::wdt::RegisterBlock) {
+    if wdt.ctrl.read().alwayson().bit_is_set() {
+        return;
+    }
+    // CONFIG may only be written while the watchdog is disabled
+    wdt.ctrl.write(|w| w.enable().clear_bit());
+    while wdt.status.read().syncbusy().bit_is_set() {}
+    // 8 cycles of the 1.024kHz clock
+    wdt.config.write(|w| unsafe { w.per().bits(0) });
+    wdt.ctrl.write(|w| w.enable().set_bit());
+    while wdt.status.read().syncbusy().bit_is_set() {}
+}
+
+#[cfg(feature = "min-samd51g")]
+fn shortest_period(wdt: &crate::target_device::wdt::RegisterBlock) {
+    if wdt.ctrla.read().alwayson().bit_is_set() {
+        return;
+    }
+    // CONFIG may only be written while the watchdog is disabled
+    wdt.ctrla.write(|w| w.enable().clear_bit());
+    while wdt.syncbusy.read().enable().bit_is_set() {}
+    // 8 cycles of the 1.024kHz clock
+    wdt.config.write(|w| unsafe { w.per().bits(0) });
+    wdt.ctrla.write(|w| w.enable().set_bit());
+    while wdt.syncbusy.read().enable().bit_is_set() {}
+}
+
+/// Returns the cause of the last reset and the panic message, if the last
+/// reset followed a panic. The message is cleared, so it is only reported
+/// once.
+///
+/// The cause is normally [`ResetCause::Watchdog`]. Another cause means that
+/// the chip was reset by other means, e.g. the reset button, while the
+/// panic handler waited for the watchdog.
+pub fn last_panic(rcause_ctrl: &RCAUSE_CTRL) -> Option<(ResetCause, PanicMessage)> {
+    PanicMessage::take().map(|message| (reset_cause(rcause_ctrl), message))
+}
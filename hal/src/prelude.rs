@@ -2,6 +2,8 @@
 pub use crate::eic::pin::EicPin;
 pub use crate::gpio::GpioExt as _atsamd21_hal_gpio_GpioExt;
 pub use crate::spi_common::CommonSpi as _atsamd_hal_spi_common_CommonSpi;
+pub use crate::time::ExtU32 as _atsamd_hal_time_ExtU32;
+pub use crate::time::RateExtU32 as _atsamd_hal_time_RateExtU32;
 pub use crate::time::U32Ext as _atsamd21_hal_time_U32Ext;
 pub use crate::timer_traits::InterruptDrivenTimer as _atsamd_hal_timer_traits_InterruptDrivenTimer;
 
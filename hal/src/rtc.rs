@@ -1,6 +1,8 @@
 //! Real-time clock/counter
+use crate::monotonic::OverflowTick;
 use crate::target_device::rtc::{MODE0, MODE2};
 use crate::target_device::RTC;
+use crate::tick::Tick;
 use crate::time::{Hertz, Nanoseconds};
 use crate::timer_traits::InterruptDrivenTimer;
 use crate::typelevel::Sealed;
@@ -85,6 +87,29 @@ impl From<Datetime> for Timestamp {
     }
 }
 
+/// The RTC tamper inputs, which can wake the device from the deep sleep
+/// modes.
+#[cfg(feature = "min-samd51g")]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TamperInput {
+    In0 = 0,
+    In1,
+    In2,
+    In3,
+    In4,
+}
+
+/// The level (or, with debouncing disabled, the edge) that triggers a tamper
+/// input.
+#[cfg(feature = "min-samd51g")]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TamperLevel {
+    /// Low level or falling edge
+    Low,
+    /// High level or rising edge
+    High,
+}
+
 /// Rtc represents the RTC peripheral for either clock/calendar or timer mode.
 pub struct Rtc<Mode: RtcMode> {
     rtc: RTC,
@@ -185,7 +210,11 @@ impl<Mode: RtcMode> Rtc<Mode> {
     /// clock to be running at 1024 Hz.
     pub fn into_clock_mode(mut self) -> Rtc<ClockMode> {
         // The max divisor is 1024, so to get 1 Hz, we need a 1024 Hz source.
-        assert_eq!(self.rtc_clock_freq.0, 1024_u32, "RTC clk not 1024 Hz!");
+        assert_eq!(
+            self.rtc_clock_freq.to_Hz(),
+            1024_u32,
+            "RTC clk not 1024 Hz!"
+        );
 
         self.sync();
         self.enable(false);
@@ -212,6 +241,61 @@ impl<Mode: RtcMode> Rtc<Mode> {
         self.into_mode()
     }
 
+    /// Configures a tamper input to wake the device from sleep, including
+    /// from the BACKUP and HIBERNATE modes. The input can optionally be
+    /// debounced, which is recommended for buttons.
+    ///
+    /// The tamper interrupt is enabled so that the event also wakes the
+    /// device from IDLE and STANDBY.
+    #[cfg(feature = "min-samd51g")]
+    pub fn enable_tamper_wakeup(&mut self, input: TamperInput, level: TamperLevel, debounce: bool) {
+        let n = input as u32;
+        // TAMPCTRL is enable-protected
+        self.enable(false);
+        self.mode0().tampctrl.modify(|r, w| {
+            let mut bits = r.bits();
+            // INnACT = WAKE
+            bits &= !(0b11 << (2 * n));
+            bits |= 0b01 << (2 * n);
+            // TAMLVLn
+            bits &= !(1 << (16 + n));
+            if level == TamperLevel::High {
+                bits |= 1 << (16 + n);
+            }
+            // DEBNCn
+            bits &= !(1 << (24 + n));
+            if debounce {
+                bits |= 1 << (24 + n);
+            }
+            unsafe { w.bits(bits) }
+        });
+        self.mode0().intenset.write(|w| w.tamper().set_bit());
+        self.enable(true);
+    }
+
+    /// Clears the tamper detection flags. This must be done after waking
+    /// from a tamper event so that the next one can be detected.
+    #[cfg(feature = "min-samd51g")]
+    pub fn clear_tamper(&mut self) {
+        self.mode0()
+            .tampid
+            .write(|w| unsafe { w.bits(0x8000_001f) });
+        self.mode0().intflag.write(|w| w.tamper().set_bit());
+    }
+
+    /// Sets the frequency correction, in steps of 1/(4096 × 240), about
+    /// 1.017ppm. Positive values slow the RTC down, and negative values speed
+    /// it up. The correction saturates at ±127 steps.
+    pub fn set_frequency_correction(&mut self, correction: i8) {
+        let value = correction.max(-127).unsigned_abs();
+        self.sync();
+        self.mode0().freqcorr.write(|w| unsafe {
+            w.sign().bit(correction < 0);
+            w.value().bits(value)
+        });
+        self.sync();
+    }
+
     /// Releases the RTC resource
     pub fn free(self) -> RTC {
         self.rtc
@@ -262,6 +346,18 @@ impl Rtc<Count32Mode> {
         self.enable(true);
     }
 
+    /// Converts the RTC into a [`Tick`] source running at `HZ`.
+    ///
+    /// Panics if `HZ` does not match the frequency of the RTC clock.
+    pub fn into_tick<const HZ: u32>(self) -> RtcTick<HZ> {
+        assert_eq!(
+            self.rtc_clock_freq.to_Hz(),
+            HZ,
+            "RTC clk does not match tick rate"
+        );
+        RtcTick { rtc: self }
+    }
+
     /// This resets the internal counter and sets the prescaler to match the
     /// provided timeout. You should configure the prescaler using the longest
     /// timeout you plan to measure.
@@ -269,7 +365,7 @@ impl Rtc<Count32Mode> {
         &mut self,
         timeout: T,
     ) -> &Self {
-        let params = TimerParams::new_us(timeout, self.rtc_clock_freq.0);
+        let params = TimerParams::new_us(timeout, self.rtc_clock_freq.to_Hz());
         let divider = params.divider;
 
         // Disable the timer while we reconfigure it
@@ -329,6 +425,46 @@ impl Rtc<ClockMode> {
     }
 }
 
+/// The RTC in 32-bit counter mode, used as a [`Tick`] source
+pub struct RtcTick<const HZ: u32> {
+    rtc: Rtc<Count32Mode>,
+}
+
+impl<const HZ: u32> RtcTick<HZ> {
+    /// Releases the RTC
+    pub fn free(self) -> Rtc<Count32Mode> {
+        self.rtc
+    }
+}
+
+impl<const HZ: u32> Tick for RtcTick<HZ> {
+    const HZ: u32 = HZ;
+
+    #[inline]
+    fn ticks(&self) -> u32 {
+        self.rtc.count32()
+    }
+}
+
+impl<const HZ: u32> OverflowTick for RtcTick<HZ> {
+    fn enable_overflow_interrupt(&mut self) {
+        self.rtc.mode0().intenset.write(|w| w.ovf().set_bit());
+    }
+
+    fn disable_overflow_interrupt(&mut self) {
+        self.rtc.mode0().intenclr.write(|w| w.ovf().set_bit());
+    }
+
+    fn overflow_pending(&self) -> bool {
+        self.rtc.mode0().intflag.read().ovf().bit_is_set()
+    }
+
+    fn clear_overflow(&mut self) {
+        // Writing a 1 clears the flag
+        self.rtc.mode0().intflag.write(|w| w.ovf().set_bit());
+    }
+}
+
 // --- Timer / Counter Functionality
 
 impl Periodic for Rtc<Count32Mode> {}
@@ -339,8 +475,8 @@ impl CountDown for Rtc<Count32Mode> {
     where
         T: Into<Self::Time>,
     {
-        let ticks: u32 =
-            (timeout.into().0 as u64 * self.rtc_clock_freq.0 as u64 / 1_000_000_000) as u32;
+        let ticks: u32 = (timeout.into().ticks() as u64 * self.rtc_clock_freq.to_Hz() as u64
+            / 1_000_000_000) as u32;
         let comp = self.count32().wrapping_add(ticks);
 
         // set cycles to compare to...
@@ -399,7 +535,7 @@ impl TimerParams {
         T: Into<Hertz>,
     {
         let timeout = timeout.into();
-        let ticks: u32 = src_freq / timeout.0.max(1);
+        let ticks: u32 = src_freq / timeout.to_Hz().max(1);
         Self::new_from_ticks(ticks)
     }
 
@@ -409,7 +545,7 @@ impl TimerParams {
         T: Into<Nanoseconds>,
     {
         let timeout = timeout.into();
-        let ticks: u32 = (timeout.0 as u64 * src_freq as u64 / 1_000_000_000_u64) as u32;
+        let ticks: u32 = (timeout.ticks() as u64 * src_freq as u64 / 1_000_000_000_u64) as u32;
         Self::new_from_ticks(ticks)
     }
 
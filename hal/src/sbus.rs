@@ -0,0 +1,205 @@
+//! SBUS RC receiver decoder
+//!
+//! SBUS receivers send a 25 byte frame every 7 or 14ms, at 100 kbaud with
+//! even parity and two stop bits, on an inverted line:
+//!
+//! * a header byte, `0x0F`
+//! * 16 channels of 11 bits, packed LSB first into 22 bytes
+//! * a flags byte: digital channels 17 and 18, frame lost and failsafe
+//! * a footer byte, `0x00`, or `0x04`, `0x14`, `0x24` or `0x34` for SBUS2
+//!
+//! The UART format is set, and the line inverted on the SAMD51, with a
+//! `UartConfig`. The SAMD21 can't invert its RX line, so an external
+//! inverter, such as a transistor, is needed there.
+//!
+//! ```
+//! let mut uart = UART0::new(&clock, 100_000.Hz(), peripherals.SERCOM0, &mut mclk, (rx, tx));
+//! uart.configure(
+//!     UartConfig::new()
+//!         .parity(Parity::Even)
+//!         .stop_bits(StopBits::Two)
+//!         .rx_inverted(true),
+//! );
+//! let mut sbus = SbusDecoder::new();
+//! loop {
+//!     if let Ok(byte) = uart.read() {
+//!         if let Some(frame) = sbus.push(byte) {
+//!             if !frame.failsafe {
+//!                 throttle = frame.channels[2];
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+
+/// Length of an SBUS frame
+pub const FRAME_LEN: usize = 25;
+
+const HEADER: u8 = 0x0f;
+
+/// A decoded SBUS frame
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SbusFrame {
+    /// The 16 proportional channels, from 0 to 2047. Most transmitters send
+    /// values from 172 to 1811.
+    pub channels: [u16; 16],
+    /// Digital channel 17
+    pub ch17: bool,
+    /// Digital channel 18
+    pub ch18: bool,
+    /// The receiver missed a frame from the transmitter
+    pub frame_lost: bool,
+    /// The receiver lost the transmitter, and sends its failsafe values
+    pub failsafe: bool,
+}
+
+impl SbusFrame {
+    /// Decodes a whole frame, or returns `None` if its header or footer is
+    /// wrong
+    pub fn decode(frame: &[u8; FRAME_LEN]) -> Option<Self> {
+        if frame[0] != HEADER || !is_footer(frame[24]) {
+            return None;
+        }
+        let mut channels = [0; 16];
+        let mut bits = 0u32;
+        let mut len = 0;
+        let mut data = frame[1..23].iter();
+        for channel in channels.iter_mut() {
+            while len < 11 {
+                bits |= (*data.next()? as u32) << len;
+                len += 8;
+            }
+            *channel = (bits & 0x7ff) as u16;
+            bits >>= 11;
+            len -= 11;
+        }
+        let flags = frame[23];
+        Some(Self {
+            channels,
+            ch17: flags & 0x01 != 0,
+            ch18: flags & 0x02 != 0,
+            frame_lost: flags & 0x04 != 0,
+            failsafe: flags & 0x08 != 0,
+        })
+    }
+}
+
+fn is_footer(byte: u8) -> bool {
+    byte == 0x00 || byte & 0xcf == 0x04
+}
+
+/// Assembles SBUS frames from the received bytes
+///
+/// Bytes are accumulated from a header byte on. If the footer of a frame is
+/// wrong, the decoder resynchronizes on the next header byte, which takes a
+/// few frames since `0x0F` may also appear in the channel data.
+pub struct SbusDecoder {
+    buf: [u8; FRAME_LEN],
+    len: usize,
+}
+
+impl SbusDecoder {
+    /// Creates a decoder waiting for a header byte
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; FRAME_LEN],
+            len: 0,
+        }
+    }
+
+    /// Drops the bytes received so far, e.g. after a UART error or a silence
+    /// between frames
+    pub fn reset(&mut self) {
+        self.len = 0;
+    }
+
+    /// Adds a received byte, and returns the frame it completes, if any
+    pub fn push(&mut self, byte: u8) -> Option<SbusFrame> {
+        if self.len == 0 && byte != HEADER {
+            return None;
+        }
+        self.buf[self.len] = byte;
+        self.len += 1;
+        if self.len < FRAME_LEN {
+            return None;
+        }
+        self.len = 0;
+        let frame = SbusFrame::decode(&self.buf);
+        if frame.is_none() {
+            // Look for a header among the bytes received
+            if let Some(i) = self.buf[1..].iter().position(|&b| b == HEADER) {
+                let start = i + 1;
+                self.buf.copy_within(start.., 0);
+                self.len = FRAME_LEN - start;
+            }
+        }
+        frame
+    }
+}
+
+impl Default for SbusDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(channels: &[u16; 16], flags: u8) -> [u8; FRAME_LEN] {
+        let mut frame = [0; FRAME_LEN];
+        frame[0] = HEADER;
+        for (i, &value) in channels.iter().enumerate() {
+            for bit in 0..11 {
+                if value & (1 << bit) != 0 {
+                    let n = i * 11 + bit;
+                    frame[1 + n / 8] |= 1 << (n % 8);
+                }
+            }
+        }
+        frame[23] = flags;
+        frame
+    }
+
+    #[test]
+    fn decode_channels_and_flags() {
+        let mut channels = [0; 16];
+        for (i, channel) in channels.iter_mut().enumerate() {
+            *channel = 172 + i as u16 * 100;
+        }
+        channels[15] = 0x7ff;
+        let frame = SbusFrame::decode(&encode(&channels, 0x09)).unwrap();
+        assert_eq!(frame.channels, channels);
+        assert!(frame.ch17);
+        assert!(!frame.ch18);
+        assert!(!frame.frame_lost);
+        assert!(frame.failsafe);
+    }
+
+    #[test]
+    fn decode_sbus2_footer() {
+        let mut bytes = encode(&[1024; 16], 0);
+        bytes[24] = 0x24;
+        assert!(SbusFrame::decode(&bytes).is_some());
+        bytes[24] = 0x05;
+        assert!(SbusFrame::decode(&bytes).is_none());
+    }
+
+    #[test]
+    fn decoder_resynchronizes() {
+        let bytes = encode(&[992; 16], 0);
+        let mut decoder = SbusDecoder::new();
+        // Noise, then the start of a truncated frame
+        for &byte in [0x55, HEADER, 0x12, 0x34].iter() {
+            assert_eq!(decoder.push(byte), None);
+        }
+        let frames = bytes
+            .iter()
+            .chain(bytes.iter())
+            .filter_map(|&byte| decoder.push(byte))
+            .count();
+        // The truncated frame is dropped, and both frames are decoded
+        assert_eq!(frames, 2);
+    }
+}
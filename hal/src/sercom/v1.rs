@@ -9,6 +9,27 @@
 pub mod pads;
 pub use pads::*;
 
+pub mod format;
+pub use format::*;
+
+pub mod rx_timeout;
+pub use rx_timeout::*;
+
+pub mod sync_uart;
+pub use sync_uart::*;
+
+pub mod async_uart;
+pub use async_uart::{AnyUart, AsyncUart};
+
+pub mod async_i2c;
+pub use async_i2c::{AnyI2c, AsyncI2c, I2cTransfer};
+
+pub mod i2c_slave;
+pub use i2c_slave::{I2CHandler, I2CSlave, I2CSlaveConfig};
+
+pub mod state;
+pub use state::*;
+
 #[cfg(any(feature = "samd11", feature = "samd21"))]
 pub use crate::common::thumbv6m::sercom::v1::*;
 
@@ -0,0 +1,612 @@
+//! Interrupt-driven async I2C master
+//!
+//! [`AsyncI2c`] wraps an `I2CMasterX`, so that transactions are futures which
+//! can be awaited by an executor, instead of spinning on the flags of the
+//! SERCOM. While a transaction is pending, it enables the MB, SB and ERROR
+//! interrupts of the SERCOM. The interrupt handlers of the SERCOM must call
+//! [`on_interrupt`], which disables the interrupts that fired and wakes the
+//! waiting task, see the [`wakers`](crate::sercom::v2::wakers) module:
+//!
+//! ```
+//! // MB, SB and ERROR on the SAMD51
+//! #[interrupt]
+//! fn SERCOM2_0() {
+//!     async_i2c::on_interrupt::<Sercom2>();
+//! }
+//!
+//! #[interrupt]
+//! fn SERCOM2_1() {
+//!     async_i2c::on_interrupt::<Sercom2>();
+//! }
+//!
+//! #[interrupt]
+//! fn SERCOM2_3() {
+//!     async_i2c::on_interrupt::<Sercom2>();
+//! }
+//!
+//! async fn temperature<SDA, SCL>(i2c: &mut AsyncI2c<I2CMaster2<SDA, SCL>>) -> Result<u16, I2CError> {
+//!     let mut raw = [0; 2];
+//!     i2c.write_read(0x48, &[0x00], &mut raw).await?;
+//!     Ok(u16::from_be_bytes(raw))
+//! }
+//! ```
+//!
+//! On the SAMD51, each SERCOM has four interrupt lines, `SERCOMx_0` to
+//! `SERCOMx_3`: `on_interrupt` must be called from the handlers of MB, SB and
+//! ERROR, i.e. `SERCOMx_0`, `SERCOMx_1` and `SERCOMx_3`. The lines must be
+//! unmasked in the NVIC, see
+//! [`Interrupts::enable_in_nvic`](crate::nvic::Interrupts::enable_in_nvic).
+//!
+//! Without an executor, an [`I2cTransfer`] runs the same transactions. It
+//! owns the I2C master and the buffers, and is advanced by its
+//! [`on_interrupt`](I2cTransfer::on_interrupt) method, which returns the
+//! result once the transaction is complete:
+//!
+//! ```
+//! static TRANSFER: Mutex<RefCell<Option<I2cTransfer<I2CMaster2<Sda, Scl>, [u8; 1], [u8; 2]>>>> =
+//!     Mutex::new(RefCell::new(None));
+//!
+//! let transfer = I2cTransfer::write_read(i2c, 0x48, [0x00], [0; 2]);
+//! interrupt::free(|cs| TRANSFER.borrow(cs).replace(Some(transfer)));
+//!
+//! fn advance() {
+//!     interrupt::free(|cs| {
+//!         if let Some(transfer) = TRANSFER.borrow(cs).borrow_mut().as_mut() {
+//!             if let Some(result) = transfer.on_interrupt() {
+//!                 // The raw temperature is ready
+//!             }
+//!         }
+//!     });
+//! }
+//!
+//! #[interrupt]
+//! fn SERCOM2_0() {
+//!     advance();
+//! }
+//!
+//! #[interrupt]
+//! fn SERCOM2_1() {
+//!     advance();
+//! }
+//!
+//! #[interrupt]
+//! fn SERCOM2_3() {
+//!     advance();
+//! }
+//! ```
+//!
+//! Errors are reported like the blocking traits do, but arbitration losses
+//! and a busy bus are not retried. Every transaction ends with a STOP, even a
+//! failed one. A future which is dropped before completion leaves the bus
+//! owned until the next transaction.
+//!
+//! With the `async` feature, [`AsyncI2c`] implements the `embedded-hal-async`
+//! `I2c` trait, whose transactions are any sequence of reads and writes.
+
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use crate::sercom::v1::I2CError;
+pub use crate::sercom::v2::wakers::on_interrupt;
+use crate::sercom::v2::{wakers, Sercom};
+use crate::target_device::sercom0::I2CM;
+
+const BUS_STATE_IDLE: u8 = 1;
+const BUS_STATE_OWNED: u8 = 2;
+const BUS_STATE_BUSY: u8 = 3;
+
+const MASTER_ACT_READ: u8 = 2;
+const MASTER_ACT_STOP: u8 = 3;
+
+// INTFLAG and INTENSET bits
+const MB: u8 = 0x01;
+const SB: u8 = 0x02;
+const ERROR: u8 = 0x80;
+
+/// An I2C master on a known SERCOM, implemented by the `I2CMasterX` types
+pub trait AnyI2c {
+    /// The SERCOM of the I2C master
+    type Sercom: Sercom;
+}
+
+// SAFETY: The I2C master is owned by an `AsyncI2c`. Its futures and the
+// interrupt handler only modify INTENSET and INTENCLR concurrently, which are
+// atomic.
+fn i2cm<S: Sercom>() -> &'static I2CM {
+    unsafe { S::registers() }.i2cm()
+}
+
+/// An I2C master whose transactions are awaited, see the [module](self)
+/// documentation
+pub struct AsyncI2c<I: AnyI2c> {
+    i2c: I,
+}
+
+impl<I: AnyI2c> AsyncI2c<I> {
+    /// Makes `i2c` asynchronous
+    pub fn new(i2c: I) -> Self {
+        Self { i2c }
+    }
+
+    /// Sends `bytes` to the device with address `addr`
+    pub async fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), I2CError> {
+        self.write_read(addr, bytes, &mut []).await
+    }
+
+    /// Fills `buffer` with bytes read from the device with address `addr`
+    pub async fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), I2CError> {
+        self.write_read(addr, &[], buffer).await
+    }
+
+    /// Sends `bytes` to the device with address `addr`, then fills `buffer`
+    /// with bytes read from it after a repeated START
+    pub async fn write_read(
+        &mut self,
+        addr: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), I2CError> {
+        let mut ops = WriteRead { bytes, buffer };
+        Transaction::<I::Sercom, _>::new(addr, &mut ops).await
+    }
+
+    /// Disables the interrupts and releases the I2C master
+    pub fn free(self) -> I {
+        disable_interrupts::<I::Sercom>();
+        wakers::release::<I::Sercom>();
+        self.i2c
+    }
+}
+
+/// The operations of a transaction, in order. Each one is a write or a read,
+/// and adjacent ones in the same direction are merged, without a repeated
+/// START between them.
+trait Ops {
+    /// Returns the number of operations
+    fn count(&self) -> usize;
+
+    /// Returns true if operation `op` is a read
+    fn is_read(&self, op: usize) -> bool;
+
+    /// Returns the number of bytes of operation `op`
+    fn op_len(&self, op: usize) -> usize;
+
+    /// Returns byte `index` of write `op`
+    fn byte(&self, op: usize, index: usize) -> u8;
+
+    /// Stores byte `index` of read `op`
+    fn set_byte(&mut self, op: usize, index: usize, byte: u8);
+}
+
+/// A write followed by a read, either of which may be empty
+struct WriteRead<'a> {
+    bytes: &'a [u8],
+    buffer: &'a mut [u8],
+}
+
+impl Ops for WriteRead<'_> {
+    fn count(&self) -> usize {
+        2
+    }
+
+    fn is_read(&self, op: usize) -> bool {
+        op == 1
+    }
+
+    fn op_len(&self, op: usize) -> usize {
+        if op == 0 {
+            self.bytes.len()
+        } else {
+            self.buffer.len()
+        }
+    }
+
+    fn byte(&self, _op: usize, index: usize) -> u8 {
+        self.bytes[index]
+    }
+
+    fn set_byte(&mut self, _op: usize, index: usize, byte: u8) {
+        self.buffer[index] = byte;
+    }
+}
+
+/// Returns the first operation from `op` on which has bytes, as empty ones
+/// are skipped
+fn next_op<O: Ops + ?Sized>(ops: &O, op: usize) -> Option<usize> {
+    (op..ops.count()).find(|&op| ops.op_len(op) > 0)
+}
+
+/// The progress of a transaction
+#[derive(Clone, Copy)]
+enum Step {
+    /// Nothing was sent yet
+    Start,
+    /// The address was sent for writing, then `total` bytes, of which `sent`
+    /// are from write `op`
+    Written {
+        op: usize,
+        sent: usize,
+        total: usize,
+    },
+    /// The address was sent for read `op`
+    ReadAddress(usize),
+    /// This number of bytes of read `op` were received
+    Read { op: usize, received: usize },
+}
+
+/// The state of a transaction
+#[derive(Clone, Copy)]
+struct Machine {
+    addr: u8,
+    step: Step,
+}
+
+impl Machine {
+    fn new(addr: u8) -> Self {
+        Self {
+            addr,
+            step: Step::Start,
+        }
+    }
+
+    /// Sends a START, or a repeated START, and the address for operation
+    /// `op`. Without an operation, the address is sent for an empty write.
+    fn address<S: Sercom, O: Ops + ?Sized>(&mut self, ops: &O, op: Option<usize>) {
+        let read = op.map_or(false, |op| ops.is_read(op));
+        let addr = (self.addr as u16) << 1 | read as u16;
+        i2cm::<S>().addr.write(|w| unsafe { w.addr().bits(addr) });
+        self.step = match op {
+            Some(op) if read => Step::ReadAddress(op),
+            _ => Step::Written {
+                op: op.unwrap_or_else(|| ops.count()),
+                sent: 0,
+                total: 0,
+            },
+        };
+    }
+
+    /// Runs the transaction until it must wait for the bus. Returns the
+    /// interrupts to wait for, as INTFLAG bits.
+    fn advance<S: Sercom, O: Ops + ?Sized>(&mut self, ops: &mut O) -> Result<Option<u8>, I2CError> {
+        let i2cm = i2cm::<S>();
+        loop {
+            let flags = i2cm.intflag.read();
+            match self.step {
+                Step::Start => {
+                    match i2cm.status.read().busstate().bits() {
+                        BUS_STATE_IDLE | BUS_STATE_OWNED => (),
+                        BUS_STATE_BUSY => return Err(I2CError::BusBusy),
+                        // Unknown, the state after the SERCOM is enabled
+                        _ => return Err(I2CError::BusError),
+                    }
+                    i2cm.intflag.write(|w| w.error().set_bit());
+                    self.address::<S, O>(ops, next_op(ops, 0));
+                }
+                Step::Written { op, sent, total } => {
+                    if flags.mb().bit_is_clear() && flags.error().bit_is_clear() {
+                        return Ok(Some(MB | ERROR));
+                    }
+                    status_to_err(
+                        i2cm,
+                        if total == 0 {
+                            I2CError::AddressNack
+                        } else {
+                            I2CError::DataNack
+                        },
+                    )?;
+                    if op < ops.count() && sent < ops.op_len(op) {
+                        let byte = ops.byte(op, sent);
+                        i2cm.data.write(|w| unsafe { w.bits(byte) });
+                        self.step = Step::Written {
+                            op,
+                            sent: sent + 1,
+                            total: total + 1,
+                        };
+                        continue;
+                    }
+                    match next_op(ops, op + 1) {
+                        None => return Ok(None),
+                        Some(next) if ops.is_read(next) => {
+                            self.address::<S, O>(ops, Some(next));
+                        }
+                        Some(next) => {
+                            self.step = Step::Written {
+                                op: next,
+                                sent: 0,
+                                total,
+                            };
+                        }
+                    }
+                }
+                Step::ReadAddress(op) => {
+                    // If the address was not acknowledged, or arbitration was
+                    // lost, MB is set instead of SB
+                    if flags.mb().bit_is_set() {
+                        status_to_err(i2cm, I2CError::AddressNack)?;
+                        return Err(I2CError::ArbitrationLost);
+                    }
+                    if flags.sb().bit_is_clear() && flags.error().bit_is_clear() {
+                        return Ok(Some(MB | SB | ERROR));
+                    }
+                    status_to_err(i2cm, I2CError::AddressNack)?;
+                    self.step = Step::Read { op, received: 0 };
+                }
+                Step::Read { op, received } => {
+                    if flags.error().bit_is_set() {
+                        status_to_err(i2cm, I2CError::BusError)?;
+                    }
+                    if flags.sb().bit_is_clear() {
+                        return Ok(Some(SB | ERROR));
+                    }
+                    ops.set_byte(op, received, i2cm.data.read().bits() as u8);
+                    let next = if received + 1 < ops.op_len(op) {
+                        Some(op)
+                    } else {
+                        next_op(ops, op + 1)
+                    };
+                    match next {
+                        Some(next) if ops.is_read(next) => {
+                            // ACK the byte so that the device sends another one
+                            i2cm.ctrlb.modify(|_, w| unsafe {
+                                w.ackact().clear_bit();
+                                w.cmd().bits(MASTER_ACT_READ)
+                            });
+                            wait_sync(i2cm);
+                            self.step = Step::Read {
+                                op: next,
+                                received: if next == op { received + 1 } else { 0 },
+                            };
+                        }
+                        next => {
+                            // NACK the last byte, along with the STOP or the
+                            // repeated START of the next write
+                            i2cm.ctrlb.modify(|_, w| w.ackact().set_bit());
+                            if next.is_none() {
+                                return Ok(None);
+                            }
+                            self.address::<S, O>(ops, next);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Ends a transaction, successful or not, with a STOP
+fn stop<S: Sercom>() {
+    let i2cm = i2cm::<S>();
+    i2cm.ctrlb
+        .modify(|_, w| unsafe { w.cmd().bits(MASTER_ACT_STOP) });
+    wait_sync(i2cm);
+}
+
+fn wait_sync(i2cm: &I2CM) {
+    while i2cm.syncbusy.read().sysop().bit_is_set() {}
+}
+
+/// Returns the error flagged in STATUS, if any. A NACK is reported as `nack`,
+/// as its meaning depends on the byte which was sent.
+fn status_to_err(i2cm: &I2CM, nack: I2CError) -> Result<(), I2CError> {
+    let status = i2cm.status.read();
+    if status.arblost().bit_is_set() {
+        return Err(I2CError::ArbitrationLost);
+    }
+    if status.buserr().bit_is_set() {
+        return Err(I2CError::BusError);
+    }
+    if status.rxnack().bit_is_set() {
+        return Err(nack);
+    }
+    if status.lowtout().bit_is_set()
+        || status.sexttout().bit_is_set()
+        || status.mexttout().bit_is_set()
+    {
+        return Err(I2CError::Timeout);
+    }
+    Ok(())
+}
+
+/// A transaction, awaited
+struct Transaction<'a, S, O: ?Sized> {
+    machine: Machine,
+    ops: &'a mut O,
+    sercom: PhantomData<fn() -> S>,
+}
+
+impl<'a, S: Sercom, O: Ops + ?Sized> Transaction<'a, S, O> {
+    fn new(addr: u8, ops: &'a mut O) -> Self {
+        Self {
+            machine: Machine::new(addr),
+            ops,
+            sercom: PhantomData,
+        }
+    }
+}
+
+impl<S: Sercom, O: Ops + ?Sized> Future for Transaction<'_, S, O> {
+    type Output = Result<(), I2CError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let i2cm = i2cm::<S>();
+        let result = match this.machine.advance::<S, O>(this.ops) {
+            Ok(Some(wait)) => {
+                wakers::register::<S>(cx.waker());
+                i2cm.intenset.write(|w| unsafe { w.bits(wait) });
+                return Poll::Pending;
+            }
+            Ok(None) => Ok(()),
+            Err(err) => Err(err),
+        };
+        stop::<S>();
+        Poll::Ready(result)
+    }
+}
+
+/// A write followed by a read, driven by the interrupts of the SERCOM
+/// without an executor, see the [module](self) documentation
+///
+/// An empty `bytes` makes it a read, and an empty `buffer` a write.
+pub struct I2cTransfer<I, W, R> {
+    i2c: I,
+    bytes: W,
+    buffer: R,
+    machine: Machine,
+    result: Option<Result<(), I2CError>>,
+}
+
+impl<I, W, R> I2cTransfer<I, W, R>
+where
+    I: AnyI2c,
+    W: AsRef<[u8]>,
+    R: AsMut<[u8]>,
+{
+    /// Starts sending `bytes` to the device with address `addr`, then
+    /// filling `buffer` with bytes read from it after a repeated START
+    pub fn write_read(i2c: I, addr: u8, bytes: W, buffer: R) -> Self {
+        let mut transfer = Self {
+            i2c,
+            bytes,
+            buffer,
+            machine: Machine::new(addr),
+            result: None,
+        };
+        transfer.step(true);
+        transfer
+    }
+
+    /// Advances the transaction, and returns its result once it is
+    /// complete. Must be called from the interrupt handlers of the SERCOM.
+    pub fn on_interrupt(&mut self) -> Option<Result<(), I2CError>> {
+        if self.result.is_none() {
+            self.step(true);
+        }
+        self.result
+    }
+
+    /// Returns true once the transaction is complete
+    pub fn is_done(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// Completes the transaction by polling the flags of the SERCOM, and
+    /// releases the I2C master and the buffers
+    pub fn wait(mut self) -> (Result<(), I2CError>, I, W, R) {
+        loop {
+            if let Some(result) = self.result {
+                return (result, self.i2c, self.bytes, self.buffer);
+            }
+            self.step(false);
+        }
+    }
+
+    /// Releases the I2C master and the buffers. A transaction which is not
+    /// complete is aborted with a STOP.
+    pub fn free(self) -> (I, W, R) {
+        if self.result.is_none() {
+            disable_interrupts::<I::Sercom>();
+            stop::<I::Sercom>();
+        }
+        (self.i2c, self.bytes, self.buffer)
+    }
+
+    /// Runs the transaction until it must wait for the bus, with the
+    /// interrupts it waits for enabled if `interrupts` is true
+    fn step(&mut self, interrupts: bool) {
+        disable_interrupts::<I::Sercom>();
+        let mut ops = WriteRead {
+            bytes: self.bytes.as_ref(),
+            buffer: self.buffer.as_mut(),
+        };
+        let result = match self.machine.advance::<I::Sercom, _>(&mut ops) {
+            Ok(Some(wait)) => {
+                if interrupts {
+                    i2cm::<I::Sercom>()
+                        .intenset
+                        .write(|w| unsafe { w.bits(wait) });
+                }
+                return;
+            }
+            Ok(None) => Ok(()),
+            Err(err) => Err(err),
+        };
+        stop::<I::Sercom>();
+        self.result = Some(result);
+    }
+}
+
+fn disable_interrupts<S: Sercom>() {
+    i2cm::<S>()
+        .intenclr
+        .write(|w| w.mb().set_bit().sb().set_bit().error().set_bit());
+}
+
+#[cfg(feature = "async")]
+mod hal_async {
+    use super::*;
+    use embedded_hal_async::i2c::{
+        ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation, SevenBitAddress,
+    };
+
+    impl embedded_hal_async::i2c::Error for I2CError {
+        #[allow(deprecated)]
+        fn kind(&self) -> ErrorKind {
+            match self {
+                I2CError::ArbitrationLost => ErrorKind::ArbitrationLoss,
+                I2CError::BusError => ErrorKind::Bus,
+                I2CError::AddressNack => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+                I2CError::DataNack => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data),
+                I2CError::Nack => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+                I2CError::BusBusy | I2CError::AddressError | I2CError::Timeout => ErrorKind::Other,
+            }
+        }
+    }
+
+    impl Ops for [Operation<'_>] {
+        fn count(&self) -> usize {
+            self.len()
+        }
+
+        fn is_read(&self, op: usize) -> bool {
+            matches!(self[op], Operation::Read(_))
+        }
+
+        fn op_len(&self, op: usize) -> usize {
+            match &self[op] {
+                Operation::Read(buffer) => buffer.len(),
+                Operation::Write(bytes) => bytes.len(),
+            }
+        }
+
+        fn byte(&self, op: usize, index: usize) -> u8 {
+            match &self[op] {
+                Operation::Write(bytes) => bytes[index],
+                Operation::Read(_) => unreachable!(),
+            }
+        }
+
+        fn set_byte(&mut self, op: usize, index: usize, byte: u8) {
+            match &mut self[op] {
+                Operation::Read(buffer) => buffer[index] = byte,
+                Operation::Write(_) => unreachable!(),
+            }
+        }
+    }
+
+    impl<I: AnyI2c> ErrorType for AsyncI2c<I> {
+        type Error = I2CError;
+    }
+
+    impl<I: AnyI2c> I2c<SevenBitAddress> for AsyncI2c<I> {
+        async fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), I2CError> {
+            Transaction::<I::Sercom, _>::new(address, operations).await
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Interrupt-driven async UART
+//!
+//! [`AsyncUart`] wraps a `UARTx`, so that reads and writes are futures which
+//! can be awaited by an executor, instead of spinning on `nb::block!`. While a
+//! future is pending, it enables the matching interrupt of the SERCOM. The
+//! interrupt handlers of the SERCOM must call [`on_interrupt`], which disables
+//! the interrupts that fired and wakes the waiting task, see the
+//! [`wakers`](crate::sercom::v2::wakers) module:
+//!
+//! ```
+//! #[interrupt]
+//! fn SERCOM5() {
+//!     async_uart::on_interrupt::<Sercom5>();
+//! }
+//!
+//! async fn echo<RX, TX>(uart: UART5<RX, TX, (), ()>) {
+//!     let mut uart = AsyncUart::new(uart);
+//!     let mut byte = [0];
+//!     loop {
+//!         if uart.read(&mut byte).await.is_ok() {
+//!             uart.write(&byte).await;
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! On the SAMD51, each SERCOM has four interrupt lines, `SERCOMx_0` to
+//! `SERCOMx_3`: `on_interrupt` must be called from the handlers of the first
+//! three, for DRE, TXC and RXC. The lines must be unmasked in the NVIC, see
+//! [`Interrupts::enable_in_nvic`](crate::nvic::Interrupts::enable_in_nvic).
+//!
+//! A future which is dropped before completion leaves its interrupt enabled
+//! until it fires once. The bytes it had received are lost.
+//!
+//! With the `async` feature, [`AsyncUart`] implements the `embedded-io-async`
+//! `Read` and `Write` traits.
+
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+pub use crate::sercom::v2::wakers::on_interrupt;
+use crate::sercom::v2::{wakers, Sercom};
+
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+use crate::target_device::sercom0::USART;
+#[cfg(feature = "min-samd51g")]
+use crate::target_device::sercom0::USART_INT as USART;
+
+/// A UART on a known SERCOM, implemented by the `UARTx` types
+pub trait AnyUart {
+    /// The SERCOM of the UART
+    type Sercom: Sercom;
+
+    /// Whether a character was written since the UART was created, as TXC is
+    /// only ever set after one
+    #[doc(hidden)]
+    fn sent(&mut self) -> &mut bool;
+}
+
+/// Errors of a received character
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Error {
+    /// The parity bit didn't match the character
+    Parity,
+    /// The stop bit was not detected
+    Framing,
+    /// Characters were lost because the receive buffer was full
+    Overflow,
+}
+
+// SAFETY: The UART is owned by an `AsyncUart`. Its futures and the interrupt
+// handler only modify INTENSET and INTENCLR concurrently, which are atomic.
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+fn usart<S: Sercom>() -> &'static USART {
+    unsafe { S::registers() }.usart()
+}
+
+#[cfg(feature = "min-samd51g")]
+fn usart<S: Sercom>() -> &'static USART {
+    unsafe { S::registers() }.usart_int()
+}
+
+/// A UART whose transfers are awaited, see the [module](self) documentation
+pub struct AsyncUart<U: AnyUart> {
+    uart: U,
+}
+
+impl<U: AnyUart> AsyncUart<U> {
+    /// Makes `uart` asynchronous
+    pub fn new(uart: U) -> Self {
+        Self { uart }
+    }
+
+    /// Receives characters until `buf` is full. Stops at the first erroneous
+    /// character, which is discarded.
+    pub async fn read(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        ReadFuture::<U::Sercom> {
+            buf,
+            received: 0,
+            sercom: PhantomData,
+        }
+        .await
+    }
+
+    /// Queues `buf` for sending. Completes once the last character has been
+    /// written to the transmit buffer, see [`flush`](Self::flush).
+    pub async fn write(&mut self, buf: &[u8]) {
+        WriteFuture::<U::Sercom> {
+            buf,
+            sent: 0,
+            any_sent: self.uart.sent(),
+            sercom: PhantomData,
+        }
+        .await
+    }
+
+    /// Waits until the last character has been shifted out. Completes at once
+    /// if nothing was sent since the UART was created.
+    pub async fn flush(&mut self) {
+        if !*self.uart.sent() {
+            return;
+        }
+        FlushFuture::<U::Sercom> {
+            sercom: PhantomData,
+        }
+        .await
+    }
+
+    /// Disables the interrupts and releases the UART
+    pub fn free(self) -> U {
+        usart::<U::Sercom>()
+            .intenclr
+            .write(|w| w.rxc().set_bit().dre().set_bit().txc().set_bit());
+        wakers::release::<U::Sercom>();
+        self.uart
+    }
+}
+
+/// Reads DATA, which clears RXC, and reports the errors of the character
+fn read_char(usart: &USART) -> Result<u8, Error> {
+    let status = usart.status.read();
+    let data = usart.data.read().bits() as u8;
+    if status.bufovf().bit_is_set() || status.ferr().bit_is_set() || status.perr().bit_is_set() {
+        usart
+            .status
+            .write(|w| w.bufovf().set_bit().ferr().set_bit().perr().set_bit());
+        return Err(if status.bufovf().bit_is_set() {
+            Error::Overflow
+        } else if status.ferr().bit_is_set() {
+            Error::Framing
+        } else {
+            Error::Parity
+        });
+    }
+    Ok(data)
+}
+
+struct ReadFuture<'a, S> {
+    buf: &'a mut [u8],
+    received: usize,
+    sercom: PhantomData<fn() -> S>,
+}
+
+impl<S: Sercom> Future for ReadFuture<'_, S> {
+    type Output = Result<(), Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let usart = usart::<S>();
+        while this.received < this.buf.len() {
+            if usart.intflag.read().rxc().bit_is_clear() {
+                wakers::register::<S>(cx.waker());
+                usart.intenset.write(|w| w.rxc().set_bit());
+                return Poll::Pending;
+            }
+            this.buf[this.received] = read_char(usart)?;
+            this.received += 1;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+struct WriteFuture<'a, S> {
+    buf: &'a [u8],
+    sent: usize,
+    /// The `sent` flag of the UART
+    any_sent: &'a mut bool,
+    sercom: PhantomData<fn() -> S>,
+}
+
+impl<S: Sercom> Future for WriteFuture<'_, S> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let this = self.get_mut();
+        let usart = usart::<S>();
+        while this.sent < this.buf.len() {
+            if usart.intflag.read().dre().bit_is_clear() {
+                wakers::register::<S>(cx.waker());
+                usart.intenset.write(|w| w.dre().set_bit());
+                return Poll::Pending;
+            }
+            let byte = this.buf[this.sent];
+            usart.data.write(|w| unsafe { w.bits(byte.into()) });
+            this.sent += 1;
+            *this.any_sent = true;
+        }
+        Poll::Ready(())
+    }
+}
+
+struct FlushFuture<S> {
+    sercom: PhantomData<fn() -> S>,
+}
+
+impl<S: Sercom> Future for FlushFuture<S> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let usart = usart::<S>();
+        if usart.intflag.read().txc().bit_is_set() {
+            return Poll::Ready(());
+        }
+        wakers::register::<S>(cx.waker());
+        usart.intenset.write(|w| w.txc().set_bit());
+        Poll::Pending
+    }
+}
+
+#[cfg(feature = "async")]
+mod io_async {
+    use super::*;
+    use embedded_io_async::{ErrorKind, ErrorType, Read, Write};
+
+    impl embedded_io_async::Error for Error {
+        fn kind(&self) -> ErrorKind {
+            match self {
+                Error::Parity | Error::Framing => ErrorKind::InvalidData,
+                Error::Overflow => ErrorKind::Other,
+            }
+        }
+    }
+
+    impl<U: AnyUart> ErrorType for AsyncUart<U> {
+        type Error = Error;
+    }
+
+    impl<U: AnyUart> Read for AsyncUart<U> {
+        /// Waits for a character, then takes the ones already received, up to
+        /// the length of `buf`
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            if buf.is_empty() {
+                return Ok(0);
+            }
+            AsyncUart::read(self, &mut buf[..1]).await?;
+            let usart = usart::<U::Sercom>();
+            let mut received = 1;
+            while received < buf.len() && usart.intflag.read().rxc().bit_is_set() {
+                buf[received] = read_char(usart)?;
+                received += 1;
+            }
+            Ok(received)
+        }
+    }
+
+    impl<U: AnyUart> Write for AsyncUart<U> {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            AsyncUart::write(self, buf).await;
+            Ok(buf.len())
+        }
+
+        async fn flush(&mut self) -> Result<(), Error> {
+            AsyncUart::flush(self).await;
+            Ok(())
+        }
+    }
+}
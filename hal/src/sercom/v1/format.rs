@@ -0,0 +1,147 @@
+//! UART character format and line options
+
+/// Parity bit of the UART characters
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Parity {
+    /// No parity bit
+    None,
+    /// Even parity
+    Even,
+    /// Odd parity
+    Odd,
+}
+
+/// Number of stop bits of the UART characters
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StopBits {
+    /// One stop bit
+    One,
+    /// Two stop bits
+    Two,
+}
+
+/// Number of data bits of the UART characters
+///
+/// With nine bits, the characters are read and written with the `read_word`
+/// and `write_word` methods of the UARTs. The ninth bit is often used to
+/// mark address characters on multidrop buses.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CharSize {
+    /// Eight data bits
+    Eight,
+    /// Nine data bits
+    Nine,
+}
+
+/// Character format and line options of a UART, applied at once with the
+/// `configure` method of the UARTs
+///
+/// ```
+/// let config = UartConfig::new()
+///     .parity(Parity::Even)
+///     .stop_bits(StopBits::Two)
+///     .rx_inverted(true);
+/// uart.configure(config);
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct UartConfig {
+    pub(crate) char_size: CharSize,
+    pub(crate) parity: Parity,
+    pub(crate) stop_bits: StopBits,
+    pub(crate) collision_detection: bool,
+    pub(crate) irda: Option<u8>,
+    pub(crate) auto_baud: bool,
+    #[cfg(feature = "min-samd51g")]
+    pub(crate) rx_inverted: bool,
+    #[cfg(feature = "min-samd51g")]
+    pub(crate) tx_inverted: bool,
+}
+
+impl UartConfig {
+    /// 8N1 characters, without collision detection, IrDA encoding,
+    /// auto-baud or inversion, as set up by the UART constructors
+    pub const fn new() -> Self {
+        Self {
+            char_size: CharSize::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            collision_detection: false,
+            irda: None,
+            auto_baud: false,
+            #[cfg(feature = "min-samd51g")]
+            rx_inverted: false,
+            #[cfg(feature = "min-samd51g")]
+            tx_inverted: false,
+        }
+    }
+
+    /// Sets the number of data bits
+    pub const fn char_size(mut self, char_size: CharSize) -> Self {
+        self.char_size = char_size;
+        self
+    }
+
+    /// Sets the parity bit
+    pub const fn parity(mut self, parity: Parity) -> Self {
+        self.parity = parity;
+        self
+    }
+
+    /// Sets the number of stop bits
+    pub const fn stop_bits(mut self, stop_bits: StopBits) -> Self {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    /// Enables collision detection, for half-duplex buses where TX and RX
+    /// are wired together. The transmitter compares each bit it sends with
+    /// the line, and aborts the character on a mismatch.
+    pub const fn collision_detection(mut self, enabled: bool) -> Self {
+        self.collision_detection = enabled;
+        self
+    }
+
+    /// Enables the IrDA SIR encoding, to drive an infrared transceiver
+    /// directly. Each zero is sent as a high pulse of 3/16 of a bit time, and
+    /// received pulses shorter than `rx_pulse_length + 2` periods of the
+    /// SERCOM core clock are dropped as noise.
+    ///
+    /// The encoding relies on the 16x oversampling set up by the UART
+    /// constructors.
+    pub const fn irda(mut self, rx_pulse_length: u8) -> Self {
+        self.irda = Some(rx_pulse_length);
+        self
+    }
+
+    /// Enables auto-baud: a break followed by the sync character `0x55`
+    /// sets the baud rate to the one measured on the sync character, which
+    /// is then returned by the `detected_baud` method of the UARTs. Neither
+    /// the break nor the sync character is received as data.
+    ///
+    /// The measurement relies on the 16x oversampling set up by the UART
+    /// constructors.
+    pub const fn auto_baud(mut self, enabled: bool) -> Self {
+        self.auto_baud = enabled;
+        self
+    }
+
+    /// Inverts the RX line: idle is low, and start bits are high
+    #[cfg(feature = "min-samd51g")]
+    pub const fn rx_inverted(mut self, inverted: bool) -> Self {
+        self.rx_inverted = inverted;
+        self
+    }
+
+    /// Inverts the TX line: idle is low, and start bits are high
+    #[cfg(feature = "min-samd51g")]
+    pub const fn tx_inverted(mut self, inverted: bool) -> Self {
+        self.tx_inverted = inverted;
+        self
+    }
+}
+
+impl Default for UartConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
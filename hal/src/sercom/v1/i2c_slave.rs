@@ -0,0 +1,392 @@
+//! I2C slave
+//!
+//! [`I2CSlaveConfig`] sets up a SERCOM as an I2C slave, so that the chip can
+//! be a device on a bus driven by another master. The SERCOM matches its own
+//! address in hardware, and holds SCL low after each address and byte until
+//! the software has answered, so the bus waits for the application instead
+//! of losing bytes:
+//!
+//! ```
+//! let gclk0 = clocks.gclk0();
+//! clocks.sercom3_core(&gclk0).unwrap();
+//! let sda = pins.pa22.into_pad(&mut pins.port);
+//! let scl = pins.pa23.into_pad(&mut pins.port);
+//! let mut i2c = I2CSlaveConfig::new(&peripherals.PM, peripherals.SERCOM3, sda, scl, Address::Single(0x42))
+//!     .general_call(true)
+//!     .enable();
+//! ```
+//!
+//! The requests of the master are either handled one [`Event`] at a time with
+//! [`I2CSlave::poll`], answering each with [`I2CSlave::ack`] or
+//! [`I2CSlave::send`], or by an [`I2CHandler`] given to
+//! [`I2CSlave::service`], which can be called from the SERCOM interrupt:
+//!
+//! ```
+//! struct Registers { regs: [u8; 16], index: usize }
+//!
+//! impl I2CHandler for Registers {
+//!     fn received(&mut self, byte: u8, first: bool) -> bool {
+//!         if first {
+//!             self.index = byte as usize % 16;
+//!         } else {
+//!             self.regs[self.index] = byte;
+//!             self.index = (self.index + 1) % 16;
+//!         }
+//!         true
+//!     }
+//!
+//!     fn transmit(&mut self) -> u8 {
+//!         let byte = self.regs[self.index];
+//!         self.index = (self.index + 1) % 16;
+//!         byte
+//!     }
+//! }
+//!
+//! #[interrupt]
+//! fn SERCOM3() {
+//!     i2c.service(&mut registers).ok();
+//! }
+//! ```
+//!
+//! The SERCOM core clock must be enabled before the slave, as it runs the
+//! bus state machine and the timeouts. Only 7-bit addresses are supported.
+
+use crate::sercom::v1::pads::CompatiblePad;
+use crate::sercom::v1::I2CError;
+use crate::sercom::v2::pad::{Pad0, Pad1};
+use crate::sercom::v2::Sercom;
+use crate::target_device::sercom0::I2CS;
+
+#[cfg(feature = "min-samd51g")]
+use crate::target_device::MCLK as APB_CLK_CTRL;
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+use crate::target_device::PM as APB_CLK_CTRL;
+
+/// Acknowledge action, then wait for any START
+const SLAVE_ACT_WAIT_START: u8 = 2;
+/// Acknowledge action, then receive or send the next byte
+const SLAVE_ACT_NEXT: u8 = 3;
+
+const AMODE_MASK: u8 = 0;
+const AMODE_2_ADDRS: u8 = 1;
+
+/// The addresses the slave answers to
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Address {
+    /// A single 7-bit address
+    Single(u8),
+    /// The 7-bit addresses which only differ from `addr` on the bits set in
+    /// `mask`
+    Masked { addr: u8, mask: u8 },
+    /// Two 7-bit addresses
+    Two(u8, u8),
+}
+
+/// When SCL is held low while the software answers
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StretchMode {
+    /// Before the ACK bit of each address and received byte, so that the
+    /// software chooses to ACK or NACK it. This is the default.
+    BeforeAck,
+    /// After the ACK bit, which is sent by the hardware according to the
+    /// previous answer. The bus is held for a shorter time, but a NACK only
+    /// applies to the next byte.
+    AfterAck,
+}
+
+/// A request of the master, returned by [`I2CSlave::poll`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Event {
+    /// The master sent a START, or a repeated START, and one of the
+    /// addresses of the slave, or the general call address 0 if enabled.
+    /// `read` is set if the master reads from the slave. Must be answered by
+    /// [`I2CSlave::ack`].
+    Address { addr: u8, read: bool },
+    /// The master wrote a byte. Must be answered by [`I2CSlave::ack`].
+    Received(u8),
+    /// The master reads a byte. Must be answered by [`I2CSlave::send`].
+    Transmit,
+    /// The master sent a STOP
+    Stop,
+}
+
+/// Handles the requests of the master with [`I2CSlave::service`]
+pub trait I2CHandler {
+    /// The master addressed the slave, see [`Event::Address`]. Returns
+    /// whether the address is acknowledged, which is the default.
+    fn address(&mut self, addr: u8, read: bool) -> bool {
+        let _ = (addr, read);
+        true
+    }
+
+    /// The master wrote `byte`, the first one since the address if `first`
+    /// is set. Returns whether the byte is acknowledged; the master must stop
+    /// writing after a NACK.
+    fn received(&mut self, byte: u8, first: bool) -> bool;
+
+    /// The master reads a byte, which is returned
+    fn transmit(&mut self) -> u8;
+
+    /// The master sent a STOP
+    fn stop(&mut self) {}
+}
+
+/// The configuration of an I2C slave, which is enabled by
+/// [`I2CSlaveConfig::enable`]
+pub struct I2CSlaveConfig<S, P0, P1>
+where
+    S: Sercom,
+    P0: CompatiblePad<Sercom = S, PadNum = Pad0>,
+    P1: CompatiblePad<Sercom = S, PadNum = Pad1>,
+{
+    slave: I2CSlave<S, P0, P1>,
+}
+
+impl<S, P0, P1> I2CSlaveConfig<S, P0, P1>
+where
+    S: Sercom,
+    P0: CompatiblePad<Sercom = S, PadNum = Pad0>,
+    P1: CompatiblePad<Sercom = S, PadNum = Pad1>,
+{
+    /// Resets `sercom` and configures it as an I2C slave answering to
+    /// `address`, with SDA on pad 0 and SCL on pad 1
+    pub fn new(apb: &APB_CLK_CTRL, mut sercom: S, sda: P0, scl: P1, address: Address) -> Self {
+        sercom.enable_apb_clock(apb);
+        let i2cs = sercom.i2cs();
+        i2cs.ctrla.modify(|_, w| w.enable().clear_bit());
+        while i2cs.syncbusy.read().enable().bit_is_set() {}
+        i2cs.ctrla.write(|w| w.swrst().set_bit());
+        while i2cs.syncbusy.read().swrst().bit_is_set() || i2cs.ctrla.read().swrst().bit_is_set() {}
+        i2cs.ctrla.write(|w| w.mode().i2c_slave());
+
+        let (amode, addr, mask) = match address {
+            Address::Single(addr) => (AMODE_MASK, addr, 0),
+            Address::Masked { addr, mask } => (AMODE_MASK, addr, mask),
+            Address::Two(first, second) => (AMODE_2_ADDRS, first, second),
+        };
+        i2cs.ctrlb.write(|w| unsafe { w.amode().bits(amode) });
+        i2cs.addr.write(|w| unsafe {
+            w.addr().bits((addr & 0x7f).into());
+            w.addrmask().bits((mask & 0x7f).into())
+        });
+
+        Self {
+            slave: I2CSlave {
+                sda,
+                scl,
+                sercom,
+                read: false,
+                received: 0,
+                sent: 0,
+            },
+        }
+    }
+
+    /// Whether the slave also answers to the general call address 0
+    pub fn general_call(self, enable: bool) -> Self {
+        self.slave.i2cs().addr.modify(|_, w| w.gencen().bit(enable));
+        self
+    }
+
+    /// When SCL is held low while the software answers
+    pub fn stretch_mode(self, mode: StretchMode) -> Self {
+        self.slave
+            .i2cs()
+            .ctrla
+            .modify(|_, w| w.sclsm().bit(mode == StretchMode::AfterAck));
+        self
+    }
+
+    /// Whether the slave releases the bus, and reports
+    /// `I2CError::Timeout`, if it held SCL low for more than 25ms over a
+    /// whole transaction, as required by SMBus
+    pub fn stretch_timeout(self, enable: bool) -> Self {
+        self.slave
+            .i2cs()
+            .ctrla
+            .modify(|_, w| w.sexttoen().bit(enable));
+        self
+    }
+
+    /// Whether the slave keeps running while the chip is in standby, so that
+    /// an address match can wake it up
+    pub fn run_in_standby(self, enable: bool) -> Self {
+        self.slave
+            .i2cs()
+            .ctrla
+            .modify(|_, w| w.runstdby().bit(enable));
+        self
+    }
+
+    /// Enables the slave
+    pub fn enable(self) -> I2CSlave<S, P0, P1> {
+        let i2cs = self.slave.i2cs();
+        i2cs.ctrla.modify(|_, w| w.enable().set_bit());
+        while i2cs.syncbusy.read().enable().bit_is_set() {}
+        self.slave
+    }
+}
+
+/// An enabled I2C slave, see the [module](self) documentation
+pub struct I2CSlave<S, P0, P1>
+where
+    S: Sercom,
+    P0: CompatiblePad<Sercom = S, PadNum = Pad0>,
+    P1: CompatiblePad<Sercom = S, PadNum = Pad1>,
+{
+    sda: P0,
+    scl: P1,
+    sercom: S,
+    /// Whether the master reads from the slave
+    read: bool,
+    /// Bytes received since the address
+    received: usize,
+    /// Bytes sent since the address
+    sent: usize,
+}
+
+impl<S, P0, P1> I2CSlave<S, P0, P1>
+where
+    S: Sercom,
+    P0: CompatiblePad<Sercom = S, PadNum = Pad0>,
+    P1: CompatiblePad<Sercom = S, PadNum = Pad1>,
+{
+    fn i2cs(&self) -> &I2CS {
+        self.sercom.i2cs()
+    }
+
+    /// Enables the interrupts of the events and errors, so that
+    /// [`I2CSlave::poll`] or [`I2CSlave::service`] can be called from the
+    /// SERCOM interrupt
+    pub fn enable_interrupts(&mut self) {
+        self.i2cs().intenset.write(|w| {
+            w.amatch().set_bit();
+            w.drdy().set_bit();
+            w.prec().set_bit();
+            w.error().set_bit()
+        });
+    }
+
+    /// Disables the interrupts
+    pub fn disable_interrupts(&mut self) {
+        self.i2cs().intenclr.write(|w| {
+            w.amatch().set_bit();
+            w.drdy().set_bit();
+            w.prec().set_bit();
+            w.error().set_bit()
+        });
+    }
+
+    /// Returns the next request of the master, which must be answered before
+    /// polling again, see [`Event`]. Errors are cleared once reported.
+    pub fn poll(&mut self) -> nb::Result<Event, I2CError> {
+        let i2cs = self.sercom.i2cs();
+        loop {
+            let flags = i2cs.intflag.read();
+            if flags.error().bit_is_set() {
+                let status = i2cs.status.read();
+                i2cs.status.write(|w| {
+                    w.buserr().set_bit();
+                    w.coll().set_bit();
+                    w.lowtout().set_bit();
+                    w.sexttout().set_bit()
+                });
+                i2cs.intflag.write(|w| w.error().set_bit());
+                let err = if status.coll().bit_is_set() {
+                    I2CError::ArbitrationLost
+                } else if status.lowtout().bit_is_set() || status.sexttout().bit_is_set() {
+                    I2CError::Timeout
+                } else {
+                    I2CError::BusError
+                };
+                return Err(nb::Error::Other(err));
+            }
+            if flags.amatch().bit_is_set() {
+                let status = i2cs.status.read();
+                self.read = status.dir().bit_is_set();
+                self.received = 0;
+                self.sent = 0;
+                // The address byte is left in DATA, with the R/W bit
+                let addr = (i2cs.data.read().bits() as u8) >> 1;
+                return Ok(Event::Address {
+                    addr,
+                    read: self.read,
+                });
+            }
+            if flags.drdy().bit_is_set() {
+                if !self.read {
+                    let byte = i2cs.data.read().bits() as u8;
+                    return Ok(Event::Received(byte));
+                }
+                if self.sent > 0 && i2cs.status.read().rxnack().bit_is_set() {
+                    // The master NACKed the last byte, so it reads no more
+                    i2cs.ctrlb
+                        .modify(|_, w| unsafe { w.cmd().bits(SLAVE_ACT_WAIT_START) });
+                    continue;
+                }
+                return Ok(Event::Transmit);
+            }
+            if flags.prec().bit_is_set() {
+                i2cs.intflag.write(|w| w.prec().set_bit());
+                return Ok(Event::Stop);
+            }
+            return Err(nb::Error::WouldBlock);
+        }
+    }
+
+    /// Answers an [`Event::Address`] or an [`Event::Received`] with an ACK,
+    /// or a NACK which ends the transfer
+    pub fn ack(&mut self, ack: bool) {
+        let wrote = !self.read && self.i2cs().intflag.read().drdy().bit_is_set();
+        if wrote {
+            self.received += 1;
+        }
+        let cmd = if ack {
+            SLAVE_ACT_NEXT
+        } else {
+            SLAVE_ACT_WAIT_START
+        };
+        self.i2cs().ctrlb.modify(|_, w| unsafe {
+            w.ackact().bit(!ack);
+            w.cmd().bits(cmd)
+        });
+    }
+
+    /// Answers an [`Event::Transmit`] with `byte`
+    pub fn send(&mut self, byte: u8) {
+        self.sent += 1;
+        // Writing DATA releases SCL
+        self.i2cs().data.write(|w| unsafe { w.bits(byte.into()) });
+    }
+
+    /// Handles all the pending requests of the master with `handler`
+    pub fn service<H: I2CHandler>(&mut self, handler: &mut H) -> Result<(), I2CError> {
+        loop {
+            match self.poll() {
+                Ok(Event::Address { addr, read }) => {
+                    let ack = handler.address(addr, read);
+                    self.ack(ack);
+                }
+                Ok(Event::Received(byte)) => {
+                    let ack = handler.received(byte, self.received == 0);
+                    self.ack(ack);
+                }
+                Ok(Event::Transmit) => {
+                    let byte = handler.transmit();
+                    self.send(byte);
+                }
+                Ok(Event::Stop) => handler.stop(),
+                Err(nb::Error::WouldBlock) => return Ok(()),
+                Err(nb::Error::Other(err)) => return Err(err),
+            }
+        }
+    }
+
+    /// Disables the slave and releases the pins and the SERCOM
+    pub fn free(self) -> (P0, P1, S) {
+        let i2cs = self.i2cs();
+        i2cs.ctrla.modify(|_, w| w.enable().clear_bit());
+        while i2cs.syncbusy.read().enable().bit_is_set() {}
+        (self.sda, self.scl, self.sercom)
+    }
+}
@@ -168,6 +168,15 @@ impl<P: IsPad> CompatiblePad for P {
     type PadNum = P::PadNum;
 }
 
+impl<S, N> CompatiblePad for DynPad<S, N>
+where
+    S: Sercom,
+    N: PadNum,
+{
+    type Sercom = S;
+    type PadNum = N;
+}
+
 //==============================================================================
 // PadPin
 //==============================================================================
@@ -0,0 +1,103 @@
+//! Inter-character timeout for UART receivers
+//!
+//! Protocols such as Modbus RTU delimit frames by a silence on the line
+//! rather than by a framing byte. [`RxTimeout`] pairs a UART receiver with a
+//! timer, restarted on each received byte, and reports the expiry of the
+//! timer as a distinct [`RxEvent::Idle`] event.
+//!
+//! ```
+//! let mut rx = RxTimeout::new(uart, timer, char_time_ns(19_200, 11) * 7 / 2);
+//! loop {
+//!     match nb::block!(rx.read()) {
+//!         Ok(RxEvent::Byte(byte)) => frame.push(byte),
+//!         Ok(RxEvent::Idle) => handle_frame(&mut frame),
+//!         Err(_) => frame.clear(),
+//!     }
+//! }
+//! ```
+
+use crate::hal::serial::Read;
+use crate::hal::timer::CountDown;
+
+/// An event reported by [`RxTimeout`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RxEvent {
+    /// A byte was received
+    Byte(u8),
+    /// Nothing was received during the timeout since the last byte
+    Idle,
+}
+
+/// Returns the duration of a character of `bits` bits, including the start,
+/// parity and stop bits, at `baud`, in nanoseconds
+pub const fn char_time_ns(baud: u32, bits: u32) -> u32 {
+    (bits as u64 * 1_000_000_000 / baud as u64) as u32
+}
+
+/// A UART receiver reporting inter-character timeouts
+pub struct RxTimeout<R, T: CountDown> {
+    rx: R,
+    timer: T,
+    timeout: T::Time,
+    armed: bool,
+}
+
+impl<R, T> RxTimeout<R, T>
+where
+    R: Read<u8>,
+    T: CountDown,
+    T::Time: Copy,
+{
+    /// Reports an [`RxEvent::Idle`] once `timeout` has passed without any
+    /// byte received after the last one
+    pub fn new(rx: R, timer: T, timeout: T::Time) -> Self {
+        Self {
+            rx,
+            timer,
+            timeout,
+            armed: false,
+        }
+    }
+
+    /// Changes the timeout, e.g. after a baud rate change
+    pub fn set_timeout(&mut self, timeout: T::Time) {
+        self.timeout = timeout;
+    }
+
+    /// Returns the next byte received, or [`RxEvent::Idle`] if the timeout
+    /// has expired since the last byte. The timeout is reported only once
+    /// per silence.
+    pub fn read(&mut self) -> nb::Result<RxEvent, R::Error> {
+        match self.rx.read() {
+            Ok(byte) => {
+                self.timer.start(self.timeout);
+                self.armed = true;
+                Ok(RxEvent::Byte(byte))
+            }
+            Err(nb::Error::WouldBlock) => {
+                if self.armed && self.timer.wait().is_ok() {
+                    self.armed = false;
+                    Ok(RxEvent::Idle)
+                } else {
+                    Err(nb::Error::WouldBlock)
+                }
+            }
+            Err(e) => {
+                // A garbled byte still counts as activity on the line
+                self.timer.start(self.timeout);
+                self.armed = true;
+                Err(e)
+            }
+        }
+    }
+
+    /// Gives access to the receiver
+    pub fn rx(&mut self) -> &mut R {
+        &mut self.rx
+    }
+
+    /// Releases the receiver and the timer
+    pub fn free(self) -> (R, T) {
+        (self.rx, self.timer)
+    }
+}
@@ -0,0 +1,36 @@
+//! Register snapshots for debugging
+//!
+//! The UART, SPI and I2C types have a `debug_state` method which captures
+//! the main registers of their SERCOM into a [`SercomState`]. It can be
+//! printed with `{:?}`, or with `defmt` when the `defmt` feature is enabled,
+//! which is usually enough to tell a misconfigured SERCOM from a stuck bus
+//! in a bug report:
+//!
+//! ```
+//! let state = uart.debug_state();
+//! log::debug!("{:?}", state);
+//! if state.status & 0x02 != 0 {
+//!     // framing error
+//! }
+//! ```
+//!
+//! The meaning of the bits depends on the mode of the SERCOM, see the SERCOM
+//! USART, SPI and I2C chapters of the datasheet.
+
+/// The main registers of a SERCOM, read at one point in time
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SercomState {
+    /// CTRLA, including the mode, the pad mapping and the enable bit
+    pub ctrla: u32,
+    /// CTRLB, including the character size and the RX/TX enables
+    pub ctrlb: u32,
+    /// BAUD, 16 bits for a UART, 8 bits for an SPI and 32 bits for I2C
+    pub baud: u32,
+    /// STATUS, including the error flags and the I2C bus state
+    pub status: u16,
+    /// INTFLAG, whether or not the interrupts are enabled
+    pub intflag: u8,
+    /// SYNCBUSY, the registers still being synchronized
+    pub syncbusy: u32,
+}
@@ -0,0 +1,312 @@
+//! USART in synchronous mode
+//!
+//! In synchronous mode, the USART sends its clock on the XCK pad along with
+//! the data as a master, or takes it from the XCK pad as a slave. Characters
+//! are still framed by start and stop bits, but no oversampling is needed, so
+//! a master can run at up to half the SERCOM clock. This suits legacy
+//! synchronous serial devices, and simplex peripherals such as shift
+//! registers or LED drivers which only need data and a clock.
+//!
+//! TX is always on pad 0 and XCK on pad 1, while RX may be on pad 2 or 3.
+//! Either data pad may be left out with [`NoneT`]:
+//!
+//! ```
+//! let gclk0 = clocks.gclk0();
+//! let clock = clocks.sercom0_core(&gclk0).unwrap();
+//! let config = SyncConfig::master(1.MHz()).msb_first(true);
+//! let mut usart = SyncUart::new(
+//!     &mclk,
+//!     peripherals.SERCOM0,
+//!     NoneT,
+//!     pins.a04.into_pad(&mut pins.port),
+//!     pins.a05.into_pad(&mut pins.port),
+//!     clock.freq(),
+//!     config,
+//! );
+//! usart.bwrite_all(&[0xa5, 0x5a]).unwrap();
+//! ```
+
+use crate::hal::blocking::serial::write::Default;
+use crate::hal::serial;
+use crate::sercom::v1::pads::Pad;
+use crate::sercom::v2::{IsPad, Pad0, Pad1, Pad2, Pad3, Sercom};
+use crate::target_device as pac;
+use crate::time::Hertz;
+use crate::typelevel::{NoneT, Sealed};
+
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+use pac::{sercom0::USART, PM as APB_CLK_CTRL};
+#[cfg(feature = "min-samd51g")]
+use pac::{sercom0::USART_INT as USART, MCLK as APB_CLK_CTRL};
+
+/// Edges of XCK on which the data changes and is sampled
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ClockPolarity {
+    /// TX changes on the rising edge, and RX is sampled on the falling edge
+    Rising,
+    /// TX changes on the falling edge, and RX is sampled on the rising edge
+    Falling,
+}
+
+/// Source of the XCK clock
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Clocking {
+    /// The USART drives XCK at the given rate
+    Master(Hertz),
+    /// XCK is driven by the other device
+    Slave,
+}
+
+/// Configuration of a [`SyncUart`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SyncConfig {
+    pub(crate) clocking: Clocking,
+    pub(crate) polarity: ClockPolarity,
+    pub(crate) msb_first: bool,
+}
+
+impl SyncConfig {
+    /// Drives XCK at `baud`, changing TX on its rising edges and sending the
+    /// least significant bit first
+    ///
+    /// # Panics
+    ///
+    /// Panics if `baud` is zero.
+    pub fn master(baud: impl Into<Hertz>) -> Self {
+        let baud = baud.into();
+        assert!(baud.to_Hz() > 0, "the baud rate of a master can't be zero");
+        Self {
+            clocking: Clocking::Master(baud),
+            polarity: ClockPolarity::Rising,
+            msb_first: false,
+        }
+    }
+
+    /// Takes the clock from XCK, changing TX on its rising edges and sending
+    /// the least significant bit first
+    pub const fn slave() -> Self {
+        Self {
+            clocking: Clocking::Slave,
+            polarity: ClockPolarity::Rising,
+            msb_first: false,
+        }
+    }
+
+    /// Sets the edges of XCK on which the data changes and is sampled
+    pub const fn polarity(mut self, polarity: ClockPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Sends and receives the most significant bit first
+    pub const fn msb_first(mut self, msb_first: bool) -> Self {
+        self.msb_first = msb_first;
+        self
+    }
+}
+
+/// A pad usable as the RX pad of a [`SyncUart`] of SERCOM `S`, or [`NoneT`]
+pub trait SyncRxPad<S: Sercom>: Sealed {
+    #[doc(hidden)]
+    const RXPO: u8;
+    #[doc(hidden)]
+    const ENABLED: bool;
+}
+
+impl<S: Sercom> SyncRxPad<S> for NoneT {
+    const RXPO: u8 = 0;
+    const ENABLED: bool = false;
+}
+
+impl<S, P> SyncRxPad<S> for Pad<S, Pad2, P>
+where
+    S: Sercom,
+    P: IsPad<Sercom = S, PadNum = Pad2>,
+{
+    const RXPO: u8 = 2;
+    const ENABLED: bool = true;
+}
+
+impl<S, P> SyncRxPad<S> for Pad<S, Pad3, P>
+where
+    S: Sercom,
+    P: IsPad<Sercom = S, PadNum = Pad3>,
+{
+    const RXPO: u8 = 3;
+    const ENABLED: bool = true;
+}
+
+/// A pad usable as the TX pad of a [`SyncUart`] of SERCOM `S`, or [`NoneT`]
+pub trait SyncTxPad<S: Sercom>: Sealed {
+    #[doc(hidden)]
+    const ENABLED: bool;
+}
+
+impl<S: Sercom> SyncTxPad<S> for NoneT {
+    const ENABLED: bool = false;
+}
+
+impl<S, P> SyncTxPad<S> for Pad<S, Pad0, P>
+where
+    S: Sercom,
+    P: IsPad<Sercom = S, PadNum = Pad0>,
+{
+    const ENABLED: bool = true;
+}
+
+/// A SERCOM USART in synchronous mode. See the [module](self) documentation.
+pub struct SyncUart<S, RX, TX, XCK> {
+    sercom: S,
+    rx: RX,
+    tx: TX,
+    xck: XCK,
+}
+
+impl<S, RX, TX, P> SyncUart<S, RX, TX, Pad<S, Pad1, P>>
+where
+    S: Sercom,
+    RX: SyncRxPad<S>,
+    TX: SyncTxPad<S>,
+    P: IsPad<Sercom = S, PadNum = Pad1>,
+{
+    /// Sets up the USART. `freq` is the frequency of the SERCOM core clock,
+    /// which must be at least twice the rate of a master.
+    pub fn new(
+        apb_clk_ctrl: &APB_CLK_CTRL,
+        mut sercom: S,
+        rx: RX,
+        tx: TX,
+        xck: Pad<S, Pad1, P>,
+        freq: impl Into<Hertz>,
+        config: SyncConfig,
+    ) -> Self {
+        sercom.enable_apb_clock(apb_clk_ctrl);
+        let usart = usart(&sercom);
+
+        usart.ctrla.write(|w| w.swrst().set_bit());
+        while usart.syncbusy.read().swrst().bit_is_set() || usart.ctrla.read().swrst().bit_is_set()
+        {
+            // wait for sync of CTRLA.SWRST
+        }
+
+        usart.ctrla.modify(|_, w| {
+            match config.clocking {
+                Clocking::Master(_) => w.mode().usart_int_clk(),
+                Clocking::Slave => w.mode().usart_ext_clk(),
+            };
+            w.cmode().set_bit();
+            w.cpol().bit(config.polarity == ClockPolarity::Falling);
+            w.dord().bit(!config.msb_first);
+            // TX on pad 0 and XCK on pad 1
+            unsafe {
+                w.txpo().bits(0);
+                w.rxpo().bits(RX::RXPO);
+                // 0 is no parity bits
+                w.form().bits(0)
+            };
+            w.runstdby().set_bit()
+        });
+
+        if let Clocking::Master(baud) = config.clocking {
+            let baud = sync_baud(freq.into().to_Hz(), baud.to_Hz());
+            usart.baud().write(|w| unsafe { w.baud().bits(baud) });
+        }
+
+        usart.ctrlb.modify(|_, w| {
+            unsafe { w.chsize().bits(0) };
+            w.sbmode().clear_bit();
+            w.txen().bit(TX::ENABLED);
+            w.rxen().bit(RX::ENABLED)
+        });
+        while usart.syncbusy.read().ctrlb().bit_is_set() {}
+
+        usart.ctrla.modify(|_, w| w.enable().set_bit());
+        while usart.syncbusy.read().enable().bit_is_set() {}
+
+        Self {
+            sercom,
+            rx,
+            tx,
+            xck,
+        }
+    }
+
+    /// Disables the USART and returns the SERCOM and the pads
+    pub fn free(self) -> (S, RX, TX, Pad<S, Pad1, P>) {
+        let usart = usart(&self.sercom);
+        usart.ctrla.modify(|_, w| w.enable().clear_bit());
+        while usart.syncbusy.read().enable().bit_is_set() {}
+        (self.sercom, self.rx, self.tx, self.xck)
+    }
+}
+
+impl<S, RX, TX, XCK> serial::Write<u8> for SyncUart<S, RX, TX, XCK>
+where
+    S: Sercom,
+    TX: SyncTxPad<S>,
+{
+    type Error = ();
+
+    fn write(&mut self, word: u8) -> nb::Result<(), ()> {
+        let usart = usart(&self.sercom);
+        if usart.intflag.read().dre().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+        usart.data.write(|w| unsafe { w.bits(word as _) });
+        Ok(())
+    }
+
+    fn flush(&mut self) -> nb::Result<(), ()> {
+        // Unlike the asynchronous UARTs, wait for the end of the character,
+        // so that the clock has stopped before the caller moves on
+        if usart(&self.sercom).intflag.read().txc().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+        Ok(())
+    }
+}
+
+impl<S, RX, TX, XCK> Default<u8> for SyncUart<S, RX, TX, XCK>
+where
+    S: Sercom,
+    TX: SyncTxPad<S>,
+{
+}
+
+impl<S, RX, TX, XCK> serial::Read<u8> for SyncUart<S, RX, TX, XCK>
+where
+    S: Sercom,
+    RX: SyncRxPad<S>,
+{
+    type Error = ();
+
+    fn read(&mut self) -> nb::Result<u8, ()> {
+        let usart = usart(&self.sercom);
+        if usart.status.read().ferr().bit_is_set() {
+            usart.data.read();
+            usart.status.write(|w| w.ferr().set_bit());
+            return Err(nb::Error::Other(()));
+        }
+        if usart.intflag.read().rxc().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+        Ok(usart.data.read().bits() as u8)
+    }
+}
+
+#[cfg(feature = "min-samd51g")]
+fn usart<S: Sercom>(sercom: &S) -> &USART {
+    sercom.usart_int()
+}
+
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+fn usart<S: Sercom>(sercom: &S) -> &USART {
+    sercom.usart()
+}
+
+/// Returns the BAUD value of a synchronous master, whose rate is
+/// `freq / (2 * (BAUD + 1))`, for the highest rate not above `baud`
+fn sync_baud(freq: u32, baud: u32) -> u16 {
+    let divider = (freq as u64 + 2 * baud as u64 - 1) / (2 * baud as u64);
+    (divider.max(1).min(1 << 16) - 1) as u16
+}
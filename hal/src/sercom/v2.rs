@@ -16,9 +16,11 @@ use pac::MCLK as APB_CLK_CTRL;
 #[cfg(any(feature = "samd11", feature = "samd21"))]
 use pac::PM as APB_CLK_CTRL;
 
-use pac::{sercom0, SERCOM0, SERCOM1};
+#[cfg(any(feature = "samd11d", feature = "samd21", feature = "min-samd51g"))]
+use pac::SERCOM2;
 #[cfg(any(feature = "samd21", feature = "min-samd51g"))]
-use pac::{SERCOM2, SERCOM3};
+use pac::SERCOM3;
+use pac::{sercom0, SERCOM0, SERCOM1};
 #[cfg(any(feature = "min-samd21g", feature = "min-samd51g"))]
 use pac::{SERCOM4, SERCOM5};
 #[cfg(feature = "min-samd51n")]
@@ -32,19 +34,29 @@ pub use crate::common::thumbv7em::sercom::v2::*;
 
 #[cfg(feature = "dma")]
 use crate::common::dmac::TriggerSource;
+use crate::nvic::Interrupts;
 use crate::typelevel::Sealed;
 
 pub mod pad;
 pub use pad::*;
 
+pub mod dyn_pad;
+pub use dyn_pad::*;
+
 pub mod spi_future;
 
+pub mod spi_async;
+
+pub mod wakers;
+
 //==============================================================================
 //  Sercom
 //==============================================================================
 
 /// Type-level `enum` representing a Serial Communication Interface (SERCOM)
-pub trait Sercom: Sealed + Deref<Target = sercom0::RegisterBlock> {
+///
+/// Its interrupt lines are given by [`Interrupts`].
+pub trait Sercom: Sealed + Interrupts + Deref<Target = sercom0::RegisterBlock> {
     /// SERCOM number
     const NUM: usize;
     /// RX Trigger source for DMA transactions
@@ -55,6 +67,14 @@ pub trait Sercom: Sealed + Deref<Target = sercom0::RegisterBlock> {
     const DMA_TX_TRIGGER: TriggerSource;
     /// Enable the corresponding APB clock
     fn enable_apb_clock(&mut self, ctrl: &APB_CLK_CTRL);
+    /// Returns the registers of the SERCOM, for interrupt handlers which don't
+    /// own it
+    ///
+    /// # Safety
+    ///
+    /// The registers are shared with the owner of the SERCOM, so the accesses
+    /// must not conflict with it
+    unsafe fn registers() -> &'static sercom0::RegisterBlock;
 }
 
 macro_rules! sercom {
@@ -74,6 +94,10 @@ macro_rules! sercom {
                     fn enable_apb_clock(&mut self, ctrl: &APB_CLK_CTRL) {
                         ctrl.$apbmask.modify(|_, w| w.[<sercom#N _>]().set_bit());
                     }
+                    #[inline]
+                    unsafe fn registers() -> &'static sercom0::RegisterBlock {
+                        &*Self::ptr()
+                    }
                 }
             }
         });
@@ -82,6 +106,8 @@ macro_rules! sercom {
 
 #[cfg(any(feature = "samd11", feature = "samd21"))]
 sercom!(apbcmask: (0, 1));
+#[cfg(feature = "samd11d")]
+sercom!(apbcmask: (2, 2));
 #[cfg(feature = "samd21")]
 sercom!(apbcmask: (2, 3));
 #[cfg(feature = "min-samd21g")]
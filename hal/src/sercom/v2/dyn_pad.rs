@@ -0,0 +1,176 @@
+//! Run-time checked SERCOM pads
+//!
+//! The [`pad`](super::pad) module verifies at compile-time that a [`Pin`] can
+//! act as a given SERCOM pad. That is not possible when the pins are only
+//! known at run-time, e.g. for a test fixture that reads its pin assignments
+//! from configuration data.
+//!
+//! A [`DynPad`] wraps a [`DynPin`] that was verified at run-time to be able to
+//! act as pad `N` of SERCOM `S`. The `Sercom` and [`PadNum`] are still tracked
+//! at the type level, so `DynPad`s can be passed to the `v1` UART, SPI and I2C
+//! constructors in place of typed pads, and the pad mapping is still checked
+//! at compile-time.
+//!
+//! ```
+//! use atsamd_hal::gpio::v2::DynPin;
+//! use atsamd_hal::sercom::v2::{DynPad, Pad0, Pad1, Sercom0};
+//! use atsamd_hal::sercom::v1::UART0;
+//!
+//! let rx: DynPin = pins.pa09.into();
+//! let tx: DynPin = pins.pa08.into();
+//! let rx = DynPad::<Sercom0, Pad1>::new(rx).ok().unwrap();
+//! let tx = DynPad::<Sercom0, Pad0>::new(tx).ok().unwrap();
+//! let uart = UART0::new(&clock, 115200.Hz(), sercom0, &mut mclk, (rx, tx));
+//! ```
+//!
+//! [`Pin`]: crate::gpio::v2::Pin
+#![cfg_attr(
+    feature = "min-samd51g",
+    doc = "
+# IOSET\n
+\n
+On SAMx5x chips, all pads of a SERCOM must also belong to the same
+[`IoSet`](super::IoSet). Each `DynPad` records the IOSETs that contain its
+pin, and [`common_iosets`] checks a group of pads before they are used:\n
+\n
+```
+use atsamd_hal::sercom::v2::common_iosets;
+
+let rx = DynPad::<Sercom0, Pad1>::new(rx).ok().unwrap();
+let tx = DynPad::<Sercom0, Pad0>::new(tx).ok().unwrap();
+if common_iosets(&[rx.iosets(), tx.iosets()]).is_none() {
+    // The pins can't be used together
+}
+```
+"
+)]
+
+use core::marker::PhantomData;
+#[cfg(feature = "min-samd51g")]
+use core::ops::BitAnd;
+
+use crate::gpio::v2::DynPin;
+use crate::typelevel::Sealed;
+
+use super::impl_pad::pad_mode;
+#[cfg(feature = "min-samd51g")]
+use super::{impl_pad::pad_iosets, IoSet};
+use super::{PadNum, Sercom};
+
+//==============================================================================
+// DynIoSets
+//==============================================================================
+
+/// Value-level set of SERCOM [`IoSet`]s
+///
+/// Returned by [`DynPad::iosets`]. Use [`common_iosets`] to check that a group
+/// of pads can be used together.
+#[cfg(feature = "min-samd51g")]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct DynIoSets(u8);
+
+#[cfg(feature = "min-samd51g")]
+impl DynIoSets {
+    /// Test whether the set contains the [`IoSet`] `I`
+    #[inline]
+    pub fn contains<I: IoSet>(self) -> bool {
+        self.0 & I::MASK != 0
+    }
+
+    /// Test whether the set is empty
+    #[inline]
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+#[cfg(feature = "min-samd51g")]
+impl BitAnd for DynIoSets {
+    type Output = Self;
+    #[inline]
+    fn bitand(self, rhs: Self) -> Self {
+        DynIoSets(self.0 & rhs.0)
+    }
+}
+
+/// Find the [`IoSet`]s shared by a group of [`DynPad`]s
+///
+/// SAMx5x SERCOMs only work when all of their pads belong to the same IOSET.
+/// Returns the IOSETs common to all of the `iosets`, or `None` if the pads
+/// can't be used together.
+#[cfg(feature = "min-samd51g")]
+#[inline]
+pub fn common_iosets(iosets: &[DynIoSets]) -> Option<DynIoSets> {
+    let common = iosets.iter().fold(DynIoSets(!0), |acc, &i| acc & i);
+    if common.is_empty() {
+        None
+    } else {
+        Some(common)
+    }
+}
+
+//==============================================================================
+// DynPad
+//==============================================================================
+
+/// A [`DynPin`] configured to act as pad `N` of SERCOM `S`
+pub struct DynPad<S, N>
+where
+    S: Sercom,
+    N: PadNum,
+{
+    sercom: PhantomData<S>,
+    padnum: PhantomData<N>,
+    pin: DynPin,
+    #[cfg(feature = "min-samd51g")]
+    iosets: DynIoSets,
+}
+
+impl<S, N> DynPad<S, N>
+where
+    S: Sercom,
+    N: PadNum,
+{
+    /// Verify that the [`DynPin`] can act as pad `N` of SERCOM `S` and, if it
+    /// can, change it to the corresponding alternate function
+    ///
+    /// If it can't, the pin is returned unchanged.
+    #[inline]
+    pub fn new(mut pin: DynPin) -> Result<Self, DynPin> {
+        match pad_mode(pin.id(), S::NUM, N::NUM) {
+            Some(config) => {
+                #[cfg(feature = "min-samd51g")]
+                let iosets = DynIoSets(pad_iosets(pin.id(), S::NUM, N::NUM));
+                pin.into_alternate(config);
+                Ok(Self {
+                    sercom: PhantomData,
+                    padnum: PhantomData,
+                    pin,
+                    #[cfg(feature = "min-samd51g")]
+                    iosets,
+                })
+            }
+            None => Err(pin),
+        }
+    }
+
+    /// Return the [`IoSet`]s that contain this pad
+    #[cfg(feature = "min-samd51g")]
+    #[inline]
+    pub fn iosets(&self) -> DynIoSets {
+        self.iosets
+    }
+
+    /// Consume the [`DynPad`] and recover the corresponding [`DynPin`]
+    #[inline]
+    pub fn free(self) -> DynPin {
+        self.pin
+    }
+}
+
+impl<S, N> Sealed for DynPad<S, N>
+where
+    S: Sercom,
+    N: PadNum,
+{
+}
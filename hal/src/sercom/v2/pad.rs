@@ -58,7 +58,10 @@ use crate::typelevel::{NoneT, Sealed};
 /// enum] documentation for an explanation of the pattern.
 ///
 /// [type-level enum]: crate::typelevel#type-level-enum
-pub trait PadNum: Sealed {}
+pub trait PadNum: Sealed {
+    /// Pad number
+    const NUM: usize;
+}
 
 seq!(N in 0..=3 {
     paste! {
@@ -70,7 +73,9 @@ seq!(N in 0..=3 {
         /// [type-level enum]: crate::typelevel#type-level-enum
         pub enum Pad#N {}
         impl Sealed for Pad#N {}
-        impl PadNum for Pad#N {}
+        impl PadNum for Pad#N {
+            const NUM: usize = N;
+        }
     }
 });
 
@@ -258,7 +263,10 @@ where
 ///
 /// [type-level enum]: crate::typelevel#type-level-enum
 #[cfg(feature = "min-samd51g")]
-pub trait IoSet: Sealed {}
+pub trait IoSet: Sealed {
+    /// Bit representing this `IoSet` in a [`DynIoSets`](super::DynIoSets)
+    const MASK: u8;
+}
 
 #[cfg(feature = "min-samd51g")]
 seq!(N in 1..=6 {
@@ -271,10 +279,50 @@ seq!(N in 1..=6 {
         /// [type-level enum]: crate::typelevel#type-level-enum
         pub enum IoSet#N {}
         impl Sealed for IoSet#N {}
-        impl IoSet for IoSet#N {}
+        impl IoSet for IoSet#N {
+            const MASK: u8 = (1 << N) >> 1;
+        }
     }
 });
 
+/// Type-level variant of [`IoSet`] representing an undocumented SERCOM IOSET
+///
+/// Some boards use combinations of pins which do not match any IOSET in the
+/// datasheet, yet are known to work. The undocumented IOSETs are listed here so
+/// that those boards can be expressed with the type-checked API.
+///
+/// `UndocIoSet1` is `PA16`, `PA17`, `PB22` and `PB23` on `Sercom1`, which is
+/// used for SPI by the Feather M4 and the PyGamer.
+///
+/// See the [type-level enum] documentation for more details on the pattern.
+///
+/// [type-level enum]: crate::typelevel#type-level-enum
+#[cfg(feature = "min-samd51g")]
+pub enum UndocIoSet1 {}
+#[cfg(feature = "min-samd51g")]
+impl Sealed for UndocIoSet1 {}
+#[cfg(feature = "min-samd51g")]
+impl IoSet for UndocIoSet1 {
+    const MASK: u8 = 1 << 6;
+}
+
+/// Type-level variant of [`IoSet`] representing an undocumented SERCOM IOSET
+///
+/// `UndocIoSet2` is `PA00`, `PA01`, `PB22` and `PB23` on `Sercom1`, which is
+/// used for SPI by the ItsyBitsy M4. See [`UndocIoSet1`] for details.
+///
+/// See the [type-level enum] documentation for more details on the pattern.
+///
+/// [type-level enum]: crate::typelevel#type-level-enum
+#[cfg(feature = "min-samd51g")]
+pub enum UndocIoSet2 {}
+#[cfg(feature = "min-samd51g")]
+impl Sealed for UndocIoSet2 {}
+#[cfg(feature = "min-samd51g")]
+impl IoSet for UndocIoSet2 {
+    const MASK: u8 = 1 << 7;
+}
+
 /// Type class for SERCOM pads in a given [`IoSet`]
 ///
 /// This trait is used to label each [`Pin`] implementing [`IsPad`] with its
@@ -0,0 +1,567 @@
+//! Interrupt-driven async SPI master transfers
+//!
+//! [`AsyncSpi`] wraps an enabled [`Spi`] with 8-bit words, so that transfers
+//! are futures which can be awaited by an executor, instead of spinning on the
+//! flags of the peripheral. Unlike [`SpiFuture`], the buffers are borrowed for
+//! the duration of the transfer only.
+//!
+//! While a transfer is pending, it enables the `DRE`, `RXC` or `TXC`
+//! interrupts of the SERCOM. The interrupt handlers of the SERCOM must call
+//! [`on_interrupt`], which disables the interrupts that fired and wakes the
+//! waiting task, see the [`wakers`](super::wakers) module:
+//!
+//! ```
+//! #[interrupt]
+//! fn SERCOM4() {
+//!     spi_async::on_interrupt::<Sercom4>();
+//! }
+//!
+//! async fn read_id<C: ValidConfig<Word = u8>>(spi: &mut AsyncSpi<Spi<C>>) -> [u8; 4] {
+//!     let mut id = [0x9F, 0, 0, 0];
+//!     spi.transfer(&mut id).await.unwrap();
+//!     id
+//! }
+//! ```
+//!
+//! On the SAMD51, each SERCOM has four interrupt lines, `SERCOMx_0` to
+//! `SERCOMx_3`: `on_interrupt` must be called from the handlers of the first
+//! three, for DRE, TXC and RXC. The lines must be unmasked in the NVIC, see
+//! [`Interrupts::enable_in_nvic`](crate::nvic::Interrupts::enable_in_nvic).
+//!
+//! If the receiver is disabled, because the [`Pads`] have no data in pad, a
+//! transfer completes once the last byte has been shifted out. Otherwise it
+//! completes once the last byte has been received. Either way, the SS line can
+//! be deasserted as soon as the future completes.
+//!
+//! A future which is dropped before completion leaves its interrupts enabled
+//! until they fire once, and leaves the received bytes in the receive buffer.
+//!
+//! With the `dma` feature, [`AsyncSpiDma`] has the same methods, but the
+//! bytes are moved by two DMAC channels, and only the end of the transfer
+//! wakes the task, through the `TXC` interrupt. A future which is dropped
+//! before completion stops the channels. It must not be leaked with
+//! `mem::forget`, which would leave the channels writing to the buffer.
+//!
+//! With the `async` feature, [`AsyncSpi`] and [`AsyncSpiDma`] implement the
+//! `embedded-hal-async` `SpiBus` trait.
+//!
+//! [`Spi`]: super::spi::Spi
+//! [`Pads`]: super::spi::Pads
+//! [`SpiFuture`]: super::spi_future::SpiFuture
+
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use super::spi::{AnySpi, Error, MasterMode};
+pub use super::wakers::on_interrupt;
+use super::{wakers, Sercom};
+
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+use crate::target_device::sercom0::SPI;
+#[cfg(feature = "min-samd51g")]
+use crate::target_device::sercom0::SPIM as SPI;
+
+// SAFETY: The SPI is owned by an `AsyncSpi`. Its futures and the interrupt
+// handler only modify INTENSET and INTENCLR concurrently, which are atomic.
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+fn spi<S: Sercom>() -> &'static SPI {
+    unsafe { S::registers() }.spi()
+}
+
+#[cfg(feature = "min-samd51g")]
+fn spi<S: Sercom>() -> &'static SPI {
+    unsafe { S::registers() }.spim()
+}
+
+/// An SPI master whose transfers are awaited, see the [module](self)
+/// documentation
+pub struct AsyncSpi<S: AnySpi> {
+    spi: S,
+}
+
+impl<S> AsyncSpi<S>
+where
+    S: AnySpi<Word = u8>,
+    S::OpMode: MasterMode,
+{
+    /// Makes `spi` asynchronous
+    pub fn new(spi: S) -> Self {
+        Self { spi }
+    }
+
+    /// Sends `buf`, discarding the received bytes
+    pub async fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        TransferFuture::<S::Sercom>::new(Buffer::Write(buf)).await
+    }
+
+    /// Fills `buf` with the received bytes, sending zeros
+    pub async fn read(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        TransferFuture::<S::Sercom>::new(Buffer::Read(buf)).await
+    }
+
+    /// Sends `buf` and replaces its contents with the received bytes
+    pub async fn transfer(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        TransferFuture::<S::Sercom>::new(Buffer::Transfer(buf)).await
+    }
+
+    /// Sends `write` while filling `read` with the received bytes. The
+    /// shorter one is padded with zeros sent, or with bytes discarded.
+    pub async fn transfer_split(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Error> {
+        TransferFuture::<S::Sercom>::new(Buffer::Split { read, write }).await
+    }
+
+    /// Disables the interrupts and releases the SPI
+    pub fn free(self) -> S {
+        spi::<S::Sercom>()
+            .intenclr
+            .write(|w| w.rxc().set_bit().dre().set_bit().txc().set_bit());
+        wakers::release::<S::Sercom>();
+        self.spi
+    }
+}
+
+/// The buffers of a transfer
+enum Buffer<'a> {
+    Write(&'a [u8]),
+    Read(&'a mut [u8]),
+    Transfer(&'a mut [u8]),
+    Split { read: &'a mut [u8], write: &'a [u8] },
+}
+
+impl Buffer<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Buffer::Write(buf) => buf.len(),
+            Buffer::Read(buf) | Buffer::Transfer(buf) => buf.len(),
+            Buffer::Split { read, write } => read.len().max(write.len()),
+        }
+    }
+
+    fn sent(&self, index: usize) -> u8 {
+        match self {
+            Buffer::Write(buf) => buf[index],
+            Buffer::Read(_) => 0,
+            Buffer::Transfer(buf) => buf[index],
+            Buffer::Split { write, .. } => write.get(index).copied().unwrap_or(0),
+        }
+    }
+
+    fn received(&mut self, index: usize, byte: u8) {
+        match self {
+            Buffer::Write(_) => (),
+            Buffer::Read(buf) | Buffer::Transfer(buf) => buf[index] = byte,
+            Buffer::Split { read, .. } => {
+                if let Some(slot) = read.get_mut(index) {
+                    *slot = byte;
+                }
+            }
+        }
+    }
+}
+
+struct TransferFuture<'a, S> {
+    buf: Buffer<'a>,
+    sent: usize,
+    received: usize,
+    sercom: PhantomData<fn() -> S>,
+}
+
+impl<'a, S: Sercom> TransferFuture<'a, S> {
+    fn new(buf: Buffer<'a>) -> Self {
+        Self {
+            buf,
+            sent: 0,
+            received: 0,
+            sercom: PhantomData,
+        }
+    }
+}
+
+impl<S: Sercom> Future for TransferFuture<'_, S> {
+    type Output = Result<(), Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let spi = spi::<S>();
+        let len = this.buf.len();
+        let rx = spi.ctrlb.read().rxen().bit_is_set();
+        loop {
+            let flags = spi.intflag.read();
+            if rx && this.received < this.sent && flags.rxc().bit_is_set() {
+                if spi.status.read().bufovf().bit_is_set() {
+                    spi.status.write(|w| w.bufovf().set_bit());
+                    return Poll::Ready(Err(Error::Overflow));
+                }
+                let byte = spi.data.read().bits() as u8;
+                this.buf.received(this.received, byte);
+                this.received += 1;
+                continue;
+            }
+            // With the receiver enabled, at most two bytes are in flight, so
+            // that the receive buffer can't overflow
+            let room = !rx || this.sent - this.received < 2;
+            if this.sent < len && room && flags.dre().bit_is_set() {
+                let byte = this.buf.sent(this.sent);
+                spi.data.write(|w| unsafe { w.bits(byte.into()) });
+                this.sent += 1;
+                continue;
+            }
+            break;
+        }
+        let done = if rx {
+            this.received == len
+        } else {
+            // Writing DATA clears TXC
+            this.sent == len && (len == 0 || spi.intflag.read().txc().bit_is_set())
+        };
+        if done {
+            return Poll::Ready(Ok(()));
+        }
+        wakers::register::<S>(cx.waker());
+        let wait_rxc = rx && this.received < this.sent;
+        let wait_dre = this.sent < len && (!rx || this.sent - this.received < 2);
+        let wait_txc = !rx && this.sent == len;
+        spi.intenset.write(|w| {
+            w.rxc()
+                .bit(wait_rxc)
+                .dre()
+                .bit(wait_dre)
+                .txc()
+                .bit(wait_txc)
+        });
+        Poll::Pending
+    }
+}
+
+#[cfg(feature = "dma")]
+pub use dma::AsyncSpiDma;
+
+#[cfg(feature = "dma")]
+mod dma {
+    use super::*;
+    use crate::dmac::{self, BufferPair, Busy, ChId, Channel, Ready, Transfer};
+    use crate::spi_display::TRIGGER_ACTION;
+
+    /// Memory seen by the DMAC, either a slice or a single word repeated
+    struct Memory {
+        ptr: *mut u8,
+        len: usize,
+        incrementing: bool,
+    }
+
+    impl Memory {
+        fn slice(ptr: *mut u8, len: usize) -> Self {
+            Self {
+                ptr,
+                len,
+                incrementing: len > 1,
+            }
+        }
+
+        fn word(ptr: *mut u8, len: usize) -> Self {
+            Self {
+                ptr,
+                len,
+                incrementing: false,
+            }
+        }
+    }
+
+    unsafe impl dmac::Buffer for Memory {
+        type Beat = u8;
+
+        fn dma_ptr(&mut self) -> *mut u8 {
+            if self.incrementing {
+                // SAFETY: One past the end of the slice
+                unsafe { self.ptr.add(self.len) }
+            } else {
+                self.ptr
+            }
+        }
+
+        fn incrementing(&self) -> bool {
+            self.incrementing
+        }
+
+        fn buffer_len(&self) -> usize {
+            self.len
+        }
+    }
+
+    type Running<Id> = Transfer<Channel<Id, Busy>, BufferPair<Memory, Memory>>;
+
+    /// The transfers of both channels. Stops them and gives the channels back
+    /// when dropped.
+    struct Transfers<'a, R: ChId, T: ChId> {
+        rx_channel: &'a mut Option<Channel<R, Ready>>,
+        tx_channel: &'a mut Option<Channel<T, Ready>>,
+        rx: Option<Running<R>>,
+        tx: Option<Running<T>>,
+    }
+
+    impl<R: ChId, T: ChId> Transfers<'_, R, T> {
+        /// Returns true once the last byte was shifted out and received
+        fn done(&mut self, spi: &SPI) -> bool {
+            if !self.tx.as_mut().map_or(true, |tx| tx.complete()) {
+                return false;
+            }
+            if spi.intflag.read().txc().bit_is_clear() {
+                return false;
+            }
+            // The last byte was received along with TXC, and is moved by the
+            // receive channel right after
+            if let Some(rx) = self.rx.as_mut() {
+                while !rx.complete() {}
+            }
+            self.stop();
+            true
+        }
+
+        fn stop(&mut self) {
+            if let Some(rx) = self.rx.take() {
+                *self.rx_channel = Some(rx.stop().0);
+            }
+            if let Some(tx) = self.tx.take() {
+                *self.tx_channel = Some(tx.stop().0);
+            }
+        }
+    }
+
+    impl<R: ChId, T: ChId> Drop for Transfers<'_, R, T> {
+        fn drop(&mut self) {
+            self.stop();
+        }
+    }
+
+    struct DmaFuture<'t, 'a, S, R: ChId, T: ChId> {
+        transfers: &'t mut Transfers<'a, R, T>,
+        sercom: PhantomData<fn() -> S>,
+    }
+
+    impl<S: Sercom, R: ChId, T: ChId> Future for DmaFuture<'_, '_, S, R, T> {
+        type Output = Result<(), Error>;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            let this = self.get_mut();
+            let spi = spi::<S>();
+            if this.transfers.done(spi) {
+                if spi.status.read().bufovf().bit_is_set() {
+                    spi.status.write(|w| w.bufovf().set_bit());
+                    return Poll::Ready(Err(Error::Overflow));
+                }
+                return Poll::Ready(Ok(()));
+            }
+            // TXC is also set if the channel falls behind, in which case the
+            // interrupt fires again until the channel writes DATA
+            wakers::register::<S>(cx.waker());
+            spi.intenset.write(|w| w.txc().set_bit());
+            Poll::Pending
+        }
+    }
+
+    /// An SPI master whose transfers are awaited and moved by the DMAC, see
+    /// the [module](super) documentation
+    pub struct AsyncSpiDma<S: AnySpi, R: ChId, T: ChId> {
+        spi: S,
+        rx_channel: Option<Channel<R, Ready>>,
+        tx_channel: Option<Channel<T, Ready>>,
+    }
+
+    impl<S, R, T> AsyncSpiDma<S, R, T>
+    where
+        S: AnySpi<Word = u8>,
+        S::OpMode: MasterMode,
+        R: ChId,
+        T: ChId,
+    {
+        /// Makes `spi` asynchronous, receiving on `rx_channel` and sending on
+        /// `tx_channel`
+        pub fn new(spi: S, rx_channel: Channel<R, Ready>, tx_channel: Channel<T, Ready>) -> Self {
+            Self {
+                spi,
+                rx_channel: Some(rx_channel),
+                tx_channel: Some(tx_channel),
+            }
+        }
+
+        /// Sends `buf`, discarding the received bytes
+        pub async fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+            let mut sink = 0;
+            let tx = Memory::slice(buf.as_ptr() as *mut u8, buf.len());
+            self.run(tx, Memory::word(&mut sink, 1), buf.len()).await
+        }
+
+        /// Fills `buf` with the received bytes, sending zeros
+        pub async fn read(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+            let mut zero = 0;
+            let rx = Memory::slice(buf.as_mut_ptr(), buf.len());
+            self.run(Memory::word(&mut zero, 1), rx, buf.len()).await
+        }
+
+        /// Sends `buf` and replaces its contents with the received bytes
+        pub async fn transfer(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+            // Each byte is sent before the byte replacing it is received
+            let (ptr, len) = (buf.as_mut_ptr(), buf.len());
+            self.run(Memory::slice(ptr, len), Memory::slice(ptr, len), len)
+                .await
+        }
+
+        /// Sends `write` while filling `read` with the received bytes. The
+        /// shorter one is padded with zeros sent, or with bytes discarded.
+        pub async fn transfer_split(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Error> {
+            let common = read.len().min(write.len());
+            let (read, read_rest) = read.split_at_mut(common);
+            let (write, write_rest) = write.split_at(common);
+            let tx = Memory::slice(write.as_ptr() as *mut u8, common);
+            self.run(tx, Memory::slice(read.as_mut_ptr(), common), common)
+                .await?;
+            // At most one of them is left
+            self.read(read_rest).await?;
+            self.write(write_rest).await
+        }
+
+        /// Releases the SPI and the channels
+        pub fn free(self) -> (S, Channel<R, Ready>, Channel<T, Ready>) {
+            spi::<S::Sercom>().intenclr.write(|w| w.txc().set_bit());
+            wakers::release::<S::Sercom>();
+            // The channels are only taken while a future runs, and given back
+            // when it is dropped
+            (self.spi, self.rx_channel.unwrap(), self.tx_channel.unwrap())
+        }
+
+        /// Moves `len` bytes from `tx` and to `rx`, which must stay borrowed
+        /// until the future completes or is dropped
+        async fn run(&mut self, tx: Memory, rx: Memory, len: usize) -> Result<(), Error> {
+            if len == 0 {
+                return Ok(());
+            }
+            let spi = spi::<S::Sercom>();
+            let data = spi.data.as_ptr() as *mut u8;
+            let receive = spi.ctrlb.read().rxen().bit_is_set();
+            // Drop anything left over in the receive buffer, which would
+            // otherwise be the first byte received
+            while spi.intflag.read().rxc().bit_is_set() {
+                spi.data.read();
+            }
+            // Writing DATA clears TXC, but it must not be taken for the end
+            // of the transfer before the first byte
+            spi.intflag.write(|w| w.txc().set_bit());
+
+            let mut transfers = Transfers {
+                rx_channel: &mut self.rx_channel,
+                tx_channel: &mut self.tx_channel,
+                rx: None,
+                tx: None,
+            };
+            // SAFETY: The buffers outlive `transfers`, which stops the
+            // channels when dropped. The receive channel starts first, so that
+            // it is ready for the first byte.
+            if receive {
+                let channel = transfers.rx_channel.take().unwrap();
+                let rx =
+                    unsafe { Transfer::new_unchecked(channel, Memory::word(data, len), rx, false) }
+                        .begin(S::Sercom::DMA_RX_TRIGGER, TRIGGER_ACTION);
+                transfers.rx = Some(rx);
+            }
+            let channel = transfers.tx_channel.take().unwrap();
+            let tx =
+                unsafe { Transfer::new_unchecked(channel, tx, Memory::word(data, len), false) }
+                    .begin(S::Sercom::DMA_TX_TRIGGER, TRIGGER_ACTION);
+            transfers.tx = Some(tx);
+
+            DmaFuture::<S::Sercom, R, T> {
+                transfers: &mut transfers,
+                sercom: PhantomData,
+            }
+            .await
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+mod hal_async {
+    use super::*;
+    use embedded_hal_async::spi::{ErrorKind, ErrorType, SpiBus};
+
+    impl embedded_hal_async::spi::Error for Error {
+        fn kind(&self) -> ErrorKind {
+            match self {
+                Error::Overflow => ErrorKind::Overrun,
+                #[cfg(feature = "min-samd51g")]
+                Error::LengthError => ErrorKind::Other,
+            }
+        }
+    }
+
+    impl<S: AnySpi> ErrorType for AsyncSpi<S> {
+        type Error = Error;
+    }
+
+    impl<S> SpiBus for AsyncSpi<S>
+    where
+        S: AnySpi<Word = u8>,
+        S::OpMode: MasterMode,
+    {
+        async fn read(&mut self, words: &mut [u8]) -> Result<(), Error> {
+            AsyncSpi::read(self, words).await
+        }
+
+        async fn write(&mut self, words: &[u8]) -> Result<(), Error> {
+            AsyncSpi::write(self, words).await
+        }
+
+        async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Error> {
+            self.transfer_split(read, write).await
+        }
+
+        async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Error> {
+            AsyncSpi::transfer(self, words).await
+        }
+
+        /// The transfers complete once the last byte was shifted out
+        async fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "dma")]
+    mod dma {
+        use super::*;
+        use crate::dmac::ChId;
+
+        impl<S: AnySpi, R: ChId, T: ChId> ErrorType for AsyncSpiDma<S, R, T> {
+            type Error = Error;
+        }
+
+        impl<S, R, T> SpiBus for AsyncSpiDma<S, R, T>
+        where
+            S: AnySpi<Word = u8>,
+            S::OpMode: MasterMode,
+            R: ChId,
+            T: ChId,
+        {
+            async fn read(&mut self, words: &mut [u8]) -> Result<(), Error> {
+                AsyncSpiDma::read(self, words).await
+            }
+
+            async fn write(&mut self, words: &[u8]) -> Result<(), Error> {
+                AsyncSpiDma::write(self, words).await
+            }
+
+            async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Error> {
+                self.transfer_split(read, write).await
+            }
+
+            async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Error> {
+                AsyncSpiDma::transfer(self, words).await
+            }
+
+            /// The transfers complete once the last byte was shifted out
+            async fn flush(&mut self) -> Result<(), Error> {
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Wakers of the async SERCOM drivers
+//!
+//! While one of their futures is pending, the async drivers of the SERCOMs
+//! enable some of the interrupts of the SERCOM, and register the task to wake
+//! here. The interrupt handlers of the SERCOM must call [`on_interrupt`],
+//! which disables the interrupts that fired and wakes the task. The futures
+//! clear the flags, and enable the interrupts again if they are still pending.
+//!
+//! `on_interrupt` doesn't depend on the mode of the SERCOM, so the same
+//! handlers serve all of the async drivers. It is also re-exported by each of
+//! their modules.
+//!
+//! The interrupt lines of the SERCOM, listed by its [`Interrupts`]
+//! implementation, must be unmasked in the NVIC. On the SAMD51, a SERCOM has
+//! four lines, and `on_interrupt` must be called from the handlers of those
+//! which the driver waits for. The drivers mask the lines again when they are
+//! freed.
+//!
+//! ```
+//! // Safety: the handlers of the SERCOM don't share data with code relying on
+//! // them being masked
+//! unsafe { Sercom5::enable_in_nvic(&mut core.NVIC, 1) };
+//! ```
+//!
+//! [`Interrupts`]: crate::nvic::Interrupts
+
+use core::cell::RefCell;
+use core::task::Waker;
+
+use cortex_m::interrupt::{self, Mutex};
+
+use super::Sercom;
+
+const NO_WAKER: Option<Waker> = None;
+
+/// Indexed by the SERCOM number
+static WAKERS: Mutex<RefCell<[Option<Waker>; 8]>> = Mutex::new(RefCell::new([NO_WAKER; 8]));
+
+/// Wakes the task waiting on SERCOM `S`, if one of the interrupts it waits for
+/// fired. Must be called from the interrupt handlers of the SERCOM.
+pub fn on_interrupt<S: Sercom>() {
+    // SAFETY: The SERCOM is owned by an async driver. Its futures and the
+    // interrupt handler only modify INTENSET and INTENCLR concurrently, which
+    // are atomic. The interrupt registers are at the same place in every mode,
+    // so the I2C master view serves them all.
+    let regs = unsafe { S::registers() }.i2cm();
+    let fired = regs.intenset.read().bits() & regs.intflag.read().bits();
+    if fired == 0 {
+        return;
+    }
+    regs.intenclr.write(|w| unsafe { w.bits(fired) });
+    interrupt::free(|cs| {
+        if let Some(waker) = WAKERS.borrow(cs).borrow_mut()[S::NUM].take() {
+            waker.wake();
+        }
+    });
+}
+
+/// Registers the task waiting on SERCOM `S`. Must be called before the
+/// interrupts are enabled, so that an interrupt right after enabling them
+/// finds it.
+pub(crate) fn register<S: Sercom>(waker: &Waker) {
+    interrupt::free(|cs| {
+        WAKERS.borrow(cs).borrow_mut()[S::NUM] = Some(waker.clone());
+    });
+}
+
+/// Masks the interrupt lines of SERCOM `S` and forgets the task waiting on
+/// it, once its driver is freed
+pub(crate) fn release<S: Sercom>() {
+    S::disable_in_nvic();
+    interrupt::free(|cs| {
+        WAKERS.borrow(cs).borrow_mut()[S::NUM] = None;
+    });
+}
@@ -2,7 +2,7 @@
 use core::sync::atomic;
 use cortex_m::asm;
 
-use crate::time::U32Ext;
+use crate::time::ExtU32;
 use crate::timer_traits::InterruptDrivenTimer;
 use hal::blocking::delay::{DelayMs, DelayUs};
 
@@ -49,7 +49,7 @@ where
         let mut count: u32 = 1 + (us / NUM_US_IN_S);
 
         // Start the timer and sleep!
-        self.timer.start((us / count).us());
+        self.timer.start((us / count).micros());
         self.timer.enable_interrupt();
         loop {
             asm::wfi();
@@ -0,0 +1,226 @@
+//! DMA drawing for ILI9341 and ST7735 SPI displays
+//!
+//! The display drivers send pixels through `embedded-hal` SPI writes, one byte
+//! at a time, which keeps the CPU busy for the whole refresh: a full 320x240
+//! frame is 150kB. [`DmaDisplay`] sends the same commands, but lets the DMAC
+//! stream the pixel data to the SERCOM.
+//!
+//! Both controllers share the MIPI DCS commands used to draw: `CASET` and
+//! `RASET` set the window of columns and rows, and `RAMWR` fills it with
+//! pixels, left to right then top to bottom. The pixels are sent as big-endian
+//! RGB565, the default pixel format of both drivers. The driver still does the
+//! initialization, e.g. orientation, then releases the SPI bus and pins:
+//!
+//! ```
+//! let (interface, reset) = ili9341.release();
+//! let (spi, dc, cs) = interface.release();
+//! let mut display = DmaDisplay::new(spi, dc, cs, channels.0);
+//! display.flush_region(Region::new(0, 0, 319, 239), &mut FRAME[..])?;
+//! ```
+//!
+//! Only the v2 SPI master, configured for 8-bit transactions without a MISO
+//! pad, is supported. Boards driving their display through a v1 `SPIMaster`
+//! must switch to the v2 [`Spi`] first.
+
+use crate::dmac::{
+    channel::{Channel, Ready},
+    transfer::Buffer,
+    ChId, Transfer, TriggerAction,
+};
+use crate::hal::blocking::serial::Write;
+use crate::hal::digital::v2::OutputPin;
+use crate::sercom::v2::spi::{AnySpi, Spi, ValidConfig};
+use crate::sercom::v2::Sercom;
+
+/// Column address set
+const CASET: u8 = 0x2a;
+/// Row address set
+const RASET: u8 = 0x2b;
+/// Memory write
+const RAMWR: u8 = 0x2c;
+
+/// Bytes per RGB565 pixel
+pub(crate) const BYTES_PER_PIXEL: usize = 2;
+
+/// Longest block of a DMA transfer, in beats
+pub(crate) const MAX_CHUNK: usize = u16::MAX as usize;
+
+#[cfg(any(feature = "samd11", feature = "samd21"))]
+pub(crate) const TRIGGER_ACTION: TriggerAction = TriggerAction::BEAT;
+#[cfg(feature = "min-samd51g")]
+pub(crate) const TRIGGER_ACTION: TriggerAction = TriggerAction::BURST;
+
+/// A rectangle of the display, with inclusive bounds
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Region {
+    /// First column
+    pub x0: u16,
+    /// First row
+    pub y0: u16,
+    /// Last column
+    pub x1: u16,
+    /// Last row
+    pub y1: u16,
+}
+
+impl Region {
+    /// Creates the region from `(x0, y0)` to `(x1, y1)`, both included
+    pub fn new(x0: u16, y0: u16, x1: u16, y1: u16) -> Self {
+        Self { x0, y0, x1, y1 }
+    }
+
+    /// Number of pixels of the region, zero if it is empty
+    pub fn pixels(&self) -> usize {
+        if self.x1 < self.x0 || self.y1 < self.y0 {
+            return 0;
+        }
+        (self.x1 - self.x0 + 1) as usize * (self.y1 - self.y0 + 1) as usize
+    }
+}
+
+/// Errors of [`FlushRegion::flush_region`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Error<E> {
+    /// The SPI bus reported an error
+    Spi(E),
+    /// The DC or CS pin could not be set
+    Pin,
+    /// The pixel buffer does not hold 2 bytes for each pixel of the region
+    Length,
+}
+
+/// Displays which can draw a region from a buffer of pixels
+pub trait FlushRegion {
+    /// Error type of the display
+    type Error;
+
+    /// Draws `pixels`, big-endian RGB565, to `region`
+    fn flush_region(&mut self, region: Region, pixels: &mut [u8]) -> Result<(), Self::Error>;
+}
+
+/// An ILI9341 or ST7735 display drawn with DMA transfers
+pub struct DmaDisplay<C, Id, DC, CS>
+where
+    C: ValidConfig,
+    Id: ChId,
+{
+    /// Moved into the DMA transfers while the pixels are sent
+    pub(crate) parts: Option<(Spi<C>, Channel<Id, Ready>)>,
+    dc: DC,
+    cs: CS,
+}
+
+impl<C, Id, DC, CS, E> DmaDisplay<C, Id, DC, CS>
+where
+    C: ValidConfig,
+    Id: ChId,
+    Spi<C>: Buffer<Beat = u8> + Write<u8, Error = E>,
+    DC: OutputPin,
+    CS: OutputPin,
+{
+    /// Uses `channel` to send the pixels on `spi`, an enabled 8-bit SPI
+    /// master, with `dc` as the data/command pin and `cs` as the chip select
+    pub fn new(spi: Spi<C>, dc: DC, cs: CS, channel: Channel<Id, Ready>) -> Self {
+        Self {
+            parts: Some((spi, channel)),
+            dc,
+            cs,
+        }
+    }
+
+    /// Releases the SPI bus, the pins and the DMA channel
+    pub fn free(self) -> (Spi<C>, DC, CS, Channel<Id, Ready>) {
+        let (spi, channel) = self.parts.unwrap();
+        (spi, self.dc, self.cs, channel)
+    }
+
+    fn spi(&mut self) -> &mut Spi<C> {
+        &mut self.parts.as_mut().unwrap().0
+    }
+
+    /// Sends `bytes`, and waits until the last one has been shifted out, so
+    /// that DC and CS can be changed
+    fn send(&mut self, bytes: &[u8]) -> Result<(), Error<E>> {
+        let spi = self.spi();
+        spi.bwrite_all(bytes).map_err(Error::Spi)?;
+        spi.bflush().map_err(Error::Spi)
+    }
+
+    /// Sends `cmd` with DC low, then its parameters with DC high
+    fn command(&mut self, cmd: u8, params: &[u8]) -> Result<(), Error<E>> {
+        self.dc.set_low().map_err(|_| Error::Pin)?;
+        self.send(&[cmd])?;
+        self.dc.set_high().map_err(|_| Error::Pin)?;
+        if !params.is_empty() {
+            self.send(params)?;
+        }
+        Ok(())
+    }
+
+    /// Streams `pixels` to the SERCOM, in blocks of at most 65535 bytes
+    fn send_pixels(&mut self, pixels: &mut [u8]) {
+        let (mut spi, mut channel) = self.parts.take().unwrap();
+        for chunk in pixels.chunks_mut(MAX_CHUNK) {
+            // SAFETY: The transfer is waited on before the chunk goes out of
+            // scope, so it is never dropped while running
+            let xfer = unsafe { Transfer::new_unchecked(channel, chunk, spi, false) }
+                .begin(<Spi<C> as AnySpi>::Sercom::DMA_TX_TRIGGER, TRIGGER_ACTION);
+            let (ready, _, dest) = xfer.wait();
+            channel = ready;
+            spi = dest;
+        }
+        self.parts = Some((spi, channel));
+    }
+
+    fn window(&mut self, region: Region) -> Result<(), Error<E>> {
+        let [x0h, x0l] = region.x0.to_be_bytes();
+        let [x1h, x1l] = region.x1.to_be_bytes();
+        let [y0h, y0l] = region.y0.to_be_bytes();
+        let [y1h, y1l] = region.y1.to_be_bytes();
+        self.command(CASET, &[x0h, x0l, x1h, x1l])?;
+        self.command(RASET, &[y0h, y0l, y1h, y1l])?;
+        self.command(RAMWR, &[])
+    }
+
+    /// Selects the display and starts a memory write to `region`, so that
+    /// the pixels can be sent
+    pub(crate) fn begin_write(&mut self, region: Region) -> Result<(), Error<E>> {
+        self.cs.set_low().map_err(|_| Error::Pin)?;
+        let result = self.window(region);
+        if result.is_err() {
+            // Release the bus even if the transaction failed
+            self.cs.set_high().map_err(|_| Error::Pin)?;
+        }
+        result
+    }
+
+    /// Waits for the last pixel to be shifted out, and deselects the display
+    pub(crate) fn end_write(&mut self) -> Result<(), Error<E>> {
+        let result = self.spi().bflush().map_err(Error::Spi);
+        self.cs.set_high().map_err(|_| Error::Pin)?;
+        result
+    }
+}
+
+impl<C, Id, DC, CS, E> FlushRegion for DmaDisplay<C, Id, DC, CS>
+where
+    C: ValidConfig,
+    Id: ChId,
+    Spi<C>: Buffer<Beat = u8> + Write<u8, Error = E>,
+    DC: OutputPin,
+    CS: OutputPin,
+{
+    type Error = Error<E>;
+
+    fn flush_region(&mut self, region: Region, pixels: &mut [u8]) -> Result<(), Error<E>> {
+        if pixels.len() != region.pixels() * BYTES_PER_PIXEL {
+            return Err(Error::Length);
+        }
+        if pixels.is_empty() {
+            return Ok(());
+        }
+        self.begin_write(region)?;
+        self.send_pixels(pixels);
+        self.end_write()
+    }
+}
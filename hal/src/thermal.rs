@@ -0,0 +1,267 @@
+//! Thermal throttling
+//!
+//! In a closed, fanless enclosure, the die of a busy chip can heat up until
+//! it leaves its rated range. Slowing the clock generators down is the
+//! simplest way to cut the power it dissipates.
+//!
+//! [`ThermalManager`] is given the die temperature at regular intervals, and
+//! divides GCLK0 through a list of [`Tier`]s as it rises. It can also divide
+//! the generator of the peripherals, when they don't run from GCLK0. The
+//! dividers are changed with [`ClockScaling`], so that the
+//! [`ClockListener`]s keep their rates. It only goes back to a faster tier
+//! once the temperature has dropped by a hysteresis below the threshold of
+//! the current one, so that it does not toggle between two tiers on the noise
+//! of the sensor:
+//!
+//! ```
+//! const TIERS: [Tier; 2] = [
+//!     Tier { threshold: 70_000, divider: 2, peripheral_divider: 1 },
+//!     Tier { threshold: 85_000, divider: 8, peripheral_divider: 4 },
+//! ];
+//!
+//! let log = calibration::temperature_log();
+//! let gclk2 = ClockScaling::generator(&mut clocks, ClockGenId::GCLK2).unwrap();
+//! let mut thermal = ThermalManager::new(ClockScaling::new(&mut clocks), &TIERS, 5_000)
+//!     .with_peripherals(gclk2);
+//! timer.start(1.secs());
+//! loop {
+//!     if timer.wait().is_ok() {
+//!         // On the SAMD21
+//!         let reading: u16 = adc.read(&mut adc::Temperature).unwrap();
+//!         let temperature = log.temperature(reading);
+//!         // On the SAMD51
+//!         let ptat: u16 = adc.read(&mut adc::Ptat).unwrap();
+//!         let ctat: u16 = adc.read(&mut adc::Ctat).unwrap();
+//!         let temperature = log.temperature(ptat, ctat);
+//!
+//!         thermal.update(
+//!             temperature,
+//!             &mut clocks,
+//!             &mut peripherals.NVMCTRL,
+//!             &mut [&mut delay],
+//!             &mut [&mut uart],
+//!         );
+//!     }
+//! }
+//! ```
+//!
+//! The temperatures are in m°C, as returned by the `TemperatureLog` of the
+//! [`calibration`](crate::calibration) module. The SAMD11 has no temperature
+//! log, so its readings must be converted by the application.
+//!
+//! The choice of the tier is made by [`Throttle`], which doesn't touch the
+//! clocks and can drive other ways of cooling down.
+
+#[cfg(feature = "device")]
+use crate::clock::GenericClockController;
+#[cfg(feature = "device")]
+use crate::clock_scaling::{ClockListener, ClockScaling};
+#[cfg(feature = "device")]
+use crate::target_device::NVMCTRL;
+#[cfg(feature = "device")]
+use crate::time::Hertz;
+
+/// A step of the throttling of a [`ThermalManager`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Tier {
+    /// Temperature from which the tier applies, in m°C
+    pub threshold: i32,
+    /// Divider of GCLK0 in the tier, from 1 to 255
+    pub divider: u16,
+    /// Divider of the peripheral clock generator in the tier, from 1 to 255.
+    /// Ignored if the manager has no peripheral generator.
+    pub peripheral_divider: u16,
+}
+
+/// Picks the [`Tier`] of the die temperature, with hysteresis
+#[derive(Clone, Debug)]
+pub struct Throttle<'a> {
+    tiers: &'a [Tier],
+    hysteresis: i32,
+    level: usize,
+}
+
+impl<'a> Throttle<'a> {
+    /// Steps through `tiers`, sorted by rising threshold. Below the first
+    /// threshold, there is no tier. `hysteresis` is in m°C.
+    pub fn new(tiers: &'a [Tier], hysteresis: i32) -> Self {
+        Self {
+            tiers,
+            hysteresis,
+            level: 0,
+        }
+    }
+
+    /// Returns the current tier, or `None` below the first threshold
+    pub fn tier(&self) -> Option<&Tier> {
+        self.level.checked_sub(1).map(|i| &self.tiers[i])
+    }
+
+    /// Moves to the tier of `temperature`, in m°C. Returns whether the tier
+    /// changed.
+    ///
+    /// A rise goes straight to the tier of the new temperature, while a drop
+    /// only leaves the tiers whose threshold is more than the hysteresis
+    /// above it.
+    pub fn update(&mut self, temperature: i32) -> bool {
+        let mut level = self.level;
+        while level < self.tiers.len() && temperature >= self.tiers[level].threshold {
+            level += 1;
+        }
+        while level > 0 && temperature < self.tiers[level - 1].threshold - self.hysteresis {
+            level -= 1;
+        }
+        let changed = level != self.level;
+        self.level = level;
+        changed
+    }
+}
+
+/// Throttles GCLK0, and optionally the peripheral clock generator, on the die
+/// temperature. See the [module](self) documentation.
+#[cfg(feature = "device")]
+pub struct ThermalManager<'a> {
+    throttle: Throttle<'a>,
+    scaling: ClockScaling,
+    peripherals: Option<ClockScaling>,
+}
+
+#[cfg(feature = "device")]
+impl<'a> ThermalManager<'a> {
+    /// Throttles GCLK0 through `tiers`, sorted by rising threshold. Below the
+    /// first threshold, GCLK0 runs at full speed. `hysteresis` is in m°C.
+    pub fn new(scaling: ClockScaling, tiers: &'a [Tier], hysteresis: i32) -> Self {
+        Self {
+            throttle: Throttle::new(tiers, hysteresis),
+            scaling,
+            peripherals: None,
+        }
+    }
+
+    /// Also throttles the clock generator of `scaling`, usually returned by
+    /// [`ClockScaling::generator`], with the `peripheral_divider` of the
+    /// tiers
+    pub fn with_peripherals(mut self, scaling: ClockScaling) -> Self {
+        self.peripherals = Some(scaling);
+        self
+    }
+
+    /// Returns the current tier, or `None` at full speed
+    pub fn tier(&self) -> Option<&Tier> {
+        self.throttle.tier()
+    }
+
+    /// Returns the current frequency of GCLK0
+    pub fn freq(&self) -> Hertz {
+        self.scaling.freq()
+    }
+
+    /// Returns the current frequency of the peripheral clock generator, if
+    /// any
+    pub fn peripheral_freq(&self) -> Option<Hertz> {
+        self.peripherals.as_ref().map(ClockScaling::freq)
+    }
+
+    /// Moves to the tier of `temperature`, in m°C, and changes the dividers
+    /// of the clock generators to those of the tier. The `listeners` are
+    /// notified if the divider of GCLK0 changed, and the
+    /// `peripheral_listeners` if the one of the peripheral generator did.
+    /// Returns the new frequency of GCLK0 if the tier changed.
+    ///
+    /// A rise goes straight to the tier of the new temperature, while a drop
+    /// only leaves the tiers whose threshold is more than the hysteresis
+    /// above it.
+    pub fn update(
+        &mut self,
+        temperature: i32,
+        clocks: &mut GenericClockController,
+        nvmctrl: &mut NVMCTRL,
+        listeners: &mut [&mut dyn ClockListener],
+        peripheral_listeners: &mut [&mut dyn ClockListener],
+    ) -> Option<Hertz> {
+        if !self.throttle.update(temperature) {
+            return None;
+        }
+
+        let tier = self.throttle.tier();
+        let divider = tier.map_or(1, |tier| tier.divider);
+        let peripheral_divider = tier.map_or(1, |tier| tier.peripheral_divider);
+        if divider != self.scaling.divider() {
+            self.scaling
+                .set_divider(divider, clocks, nvmctrl, listeners);
+        }
+        if let Some(peripherals) = &mut self.peripherals {
+            if peripheral_divider != peripherals.divider() {
+                peripherals.set_divider(peripheral_divider, clocks, nvmctrl, peripheral_listeners);
+            }
+        }
+        Some(self.scaling.freq())
+    }
+
+    /// Returns the `ClockScaling`s of GCLK0 and of the peripheral generator,
+    /// leaving them at their current dividers
+    pub fn free(self) -> (ClockScaling, Option<ClockScaling>) {
+        (self.scaling, self.peripherals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIERS: [Tier; 2] = [
+        Tier {
+            threshold: 70_000,
+            divider: 2,
+            peripheral_divider: 1,
+        },
+        Tier {
+            threshold: 85_000,
+            divider: 8,
+            peripheral_divider: 4,
+        },
+    ];
+
+    #[test]
+    fn full_speed_below_first_threshold() {
+        let mut throttle = Throttle::new(&TIERS, 5_000);
+        assert!(!throttle.update(69_999));
+        assert_eq!(throttle.tier(), None);
+    }
+
+    #[test]
+    fn rise_skips_to_tier_of_temperature() {
+        let mut throttle = Throttle::new(&TIERS, 5_000);
+        assert!(throttle.update(90_000));
+        assert_eq!(throttle.tier(), Some(&TIERS[1]));
+        assert!(!throttle.update(95_000));
+    }
+
+    #[test]
+    fn drop_within_hysteresis_keeps_tier() {
+        let mut throttle = Throttle::new(&TIERS, 5_000);
+        assert!(throttle.update(86_000));
+        assert!(!throttle.update(84_000));
+        assert!(!throttle.update(80_000));
+        assert_eq!(throttle.tier(), Some(&TIERS[1]));
+        assert!(throttle.update(79_999));
+        assert_eq!(throttle.tier(), Some(&TIERS[0]));
+    }
+
+    #[test]
+    fn large_drop_leaves_every_tier() {
+        let mut throttle = Throttle::new(&TIERS, 5_000);
+        assert!(throttle.update(90_000));
+        assert!(throttle.update(20_000));
+        assert_eq!(throttle.tier(), None);
+    }
+
+    #[test]
+    fn steps_up_one_tier_at_a_time() {
+        let mut throttle = Throttle::new(&TIERS, 5_000);
+        assert!(throttle.update(70_000));
+        assert_eq!(throttle.tier(), Some(&TIERS[0]));
+        assert!(throttle.update(85_000));
+        assert_eq!(throttle.tier(), Some(&TIERS[1]));
+    }
+}
@@ -1,10 +1,15 @@
 //! Analogue-to-Digital Conversion
+use crate::calibration;
 use crate::clock::GenericClockController;
 use crate::gpio::v1;
 use crate::gpio::v2::*;
 use crate::hal::adc::{Channel, OneShot};
 use crate::target_device::{adc, ADC, PM};
 
+pub use crate::adc_channel::AdcChannel;
+/// The ADC, named like the first ADC of the SAMD51 for portable drivers
+pub use crate::target_device::ADC as ADC0;
+
 /// Samples per reading
 pub use adc::avgctrl::SAMPLENUM_A as SampleRate;
 /// Clock frequency relative to the system clock
@@ -42,6 +47,11 @@ impl Adc<ADC> {
         adc.ctrla.modify(|_, w| w.swrst().set_bit());
         while adc.status.read().syncbusy().bit_is_set() {}
 
+        adc.calib.write(|w| unsafe {
+            w.linearity_cal().bits(calibration::adc_linearity_cal());
+            w.bias_cal().bits(calibration::adc_biascal_cal())
+        });
+
         adc.ctrlb.modify(|_, w| {
             w.prescaler().div32();
             w.ressel()._12bit()
@@ -110,6 +120,25 @@ impl Adc<ADC> {
         while self.adc.status.read().syncbusy().bit_is_set() {}
     }
 
+    /// Reads `pin` in millivolts. `divider` is the ratio of a resistor
+    /// divider between the measured voltage and the pin, e.g. 2 for two equal
+    /// resistors, or 1 without a divider.
+    ///
+    /// The ADC is set to a full scale of VDDANA, i.e. a 1/2 gain of the 1/2
+    /// VDDANA reference, and to 12 bit single samples, and keeps this
+    /// configuration. VDDANA is taken to be `vddana_mv`.
+    pub fn read_millivolts<PIN>(&mut self, pin: &mut PIN, vddana_mv: u16, divider: u16) -> u16
+    where
+        PIN: AdcChannel<ADC>,
+    {
+        self.resolution(Resolution::_12BIT);
+        self.samples(SampleRate::_1);
+        self.gain(Gain::DIV2);
+        self.reference(Reference::INTVCC1);
+        let raw: u16 = nb::block!(self.read(pin)).unwrap();
+        to_millivolts(raw, vddana_mv, divider)
+    }
+
     fn power_up(&mut self) {
         while self.adc.status.read().syncbusy().bit_is_set() {}
         self.adc.ctrla.modify(|_, w| w.enable().set_bit());
@@ -140,15 +169,21 @@ impl Adc<ADC> {
     }
 }
 
+/// Converts a 12 bit result to millivolts, rounding to the nearest
+fn to_millivolts(raw: u16, full_scale_mv: u16, divider: u16) -> u16 {
+    let mv = (raw as u32 * full_scale_mv as u32 * divider as u32 + 2047) / 4095;
+    mv.min(u16::MAX as u32) as u16
+}
+
 impl<WORD, PIN> OneShot<ADC, WORD, PIN> for Adc<ADC>
 where
     WORD: From<u16>,
-    PIN: Channel<ADC, ID = u8>,
+    PIN: AdcChannel<ADC>,
 {
     type Error = ();
 
     fn read(&mut self, _pin: &mut PIN) -> nb::Result<WORD, Self::Error> {
-        let chan = PIN::channel();
+        let chan = PIN::MUXPOS;
         while self.adc.status.read().syncbusy().bit_is_set() {}
 
         self.adc
@@ -173,23 +208,71 @@ macro_rules! adc_pins {
                type ID = u8;
                fn channel() -> u8 { $CHAN }
             }
+
+            impl AdcChannel<ADC> for Pin<$PinId, AlternateB> {
+               const MUXPOS: u8 = $CHAN;
+            }
         )+
     }
 }
 
+macro_rules! internal_inputs {
+    (
+        $(
+            $(#[$attr:meta])*
+            $Input:ident: $CHAN:literal,
+        )+
+    ) => {
+        $(
+            $(#[$attr])*
+            pub struct $Input;
+
+            impl Channel<ADC> for $Input {
+               type ID = u8;
+               fn channel() -> u8 { $CHAN }
+            }
+
+            impl AdcChannel<ADC> for $Input {
+               const MUXPOS: u8 = $CHAN;
+            }
+        )+
+    }
+}
+
+internal_inputs! {
+    /// Internal temperature sensor, enabled by `VREF.TSEN` in `SYSCTRL`
+    Temperature: 0x18,
+    /// Internal bandgap reference, enabled by `VREF.BGOUTEN` in `SYSCTRL`
+    Bandgap: 0x19,
+    /// Core supply, scaled by 1/4
+    ScaledCoreVcc: 0x1a,
+    /// I/O supply, scaled by 1/4
+    ScaledIoVcc: 0x1b,
+    /// Output of the DAC
+    Dac: 0x1c,
+}
+
 /// Implement [`Channel`] for [`v1::Pin`]s based on the implementations for
 /// `v2` [`Pin`]s
 impl<I> Channel<ADC> for v1::Pin<I, v1::PfB>
 where
     I: PinId,
-    Pin<I, AlternateB>: Channel<ADC, ID = u8>,
+    Pin<I, AlternateB>: AdcChannel<ADC>,
 {
     type ID = u8;
     fn channel() -> u8 {
-        Pin::<I, AlternateB>::channel()
+        Pin::<I, AlternateB>::MUXPOS
     }
 }
 
+impl<I> AdcChannel<ADC> for v1::Pin<I, v1::PfB>
+where
+    I: PinId,
+    Pin<I, AlternateB>: AdcChannel<ADC>,
+{
+    const MUXPOS: u8 = Pin::<I, AlternateB>::MUXPOS;
+}
+
 #[cfg(feature = "samd11")]
 adc_pins! {
     PA02: 0,
@@ -1,6 +1,7 @@
 //! NVM Software Calibration Area Mapping
 // For samd11, see 9.5 NVM Software Calibration Area Mapping, page 24
 // For samd21, see 10.3.2 NVM Software Calibration Area Mapping, page 46
+// and 10.3.3 Temperature Log Row, page 47
 
 use core::ptr;
 
@@ -32,6 +33,17 @@ fn cal_with_errata(
     }
 }
 
+/// ADC LINEARITY calibration value. Should be written to ADC CALIB register.
+pub fn adc_linearity_cal() -> u8 {
+    // Bits 27 to 34, across the first two words
+    (cal(0, 27, 0x1f) | cal(4, 0, 0x7) << 5) as u8
+}
+
+/// ADC BIASCAL calibration value. Should be written to ADC CALIB register.
+pub fn adc_biascal_cal() -> u8 {
+    cal(4, 3, 0x7) as u8
+}
+
 /// Returns the osc32k calibration value from the NVM calibration area
 pub fn osc32k_cal() -> u8 {
     cal(4, 6, 0x7f) as u8
@@ -59,3 +71,74 @@ pub fn usb_trim_cal() -> u8 {
     #[cfg(feature = "samd21")]
     return cal_with_errata(4, 23, 7, 7, 3) as u8;
 }
+
+// "The temperature log row can be read at address 0x00806030."
+#[cfg(feature = "samd21")]
+const TEMP_LOG_ADDR: u32 = 0x806030u32;
+
+/// Factory measurements of the temperature sensor, at room temperature and
+/// at a hot temperature, from the temperature log row
+#[cfg(feature = "samd21")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TemperatureLog {
+    /// Room temperature, in m°C
+    pub room_temp: i32,
+    /// Hot temperature, in m°C
+    pub hot_temp: i32,
+    /// Internal 1V reference at room temperature, in mV
+    pub room_int1v: i32,
+    /// Internal 1V reference at the hot temperature, in mV
+    pub hot_int1v: i32,
+    /// 12 bit reading of the sensor at room temperature, with the internal
+    /// 1V reference
+    pub room_adc: u16,
+    /// 12 bit reading of the sensor at the hot temperature, with the internal
+    /// 1V reference
+    pub hot_adc: u16,
+}
+
+/// Returns the factory measurements of the temperature sensor
+#[cfg(feature = "samd21")]
+pub fn temperature_log() -> TemperatureLog {
+    let (low, high) = unsafe {
+        let addr = TEMP_LOG_ADDR as *const u32;
+        (ptr::read(addr), ptr::read(addr.add(1)))
+    };
+    let temp = |int: u32, dec: u32| (int & 0xff) as i32 * 1000 + (dec & 0xf) as i32 * 100;
+    // The deviations of the 1V reference are signed, in mV
+    let int1v = |dev: u32| 1000 - (dev as u8 as i8) as i32;
+    TemperatureLog {
+        room_temp: temp(low, low >> 8),
+        hot_temp: temp(low >> 12, low >> 20),
+        room_int1v: int1v(low >> 24),
+        hot_int1v: int1v(high),
+        room_adc: (high >> 8 & 0xfff) as u16,
+        hot_adc: (high >> 20 & 0xfff) as u16,
+    }
+}
+
+#[cfg(feature = "samd21")]
+impl TemperatureLog {
+    /// Converts a 12 bit reading of the temperature sensor, with the
+    /// internal 1V reference, to m°C
+    ///
+    /// The deviation of the 1V reference with temperature is corrected in a
+    /// second pass, as described in the datasheet.
+    pub fn temperature(&self, adc: u16) -> i32 {
+        // In µV
+        let volts = |adc: u16, int1v: i32| adc as i64 * int1v as i64 * 1000 / 4095;
+        let room = volts(self.room_adc, self.room_int1v);
+        let hot = volts(self.hot_adc, self.hot_int1v);
+        let interpolate = |measured: i64| {
+            let span = (hot - room).max(1);
+            self.room_temp as i64
+                + (measured - room) * (self.hot_temp - self.room_temp) as i64 / span
+        };
+
+        let coarse = interpolate(volts(adc, 1000));
+        let int1v = self.room_int1v as i64
+            + (self.hot_int1v - self.room_int1v) as i64 * (coarse - self.room_temp as i64)
+                / (self.hot_temp - self.room_temp).max(1) as i64;
+        interpolate(volts(adc, int1v as i32)) as i32
+    }
+}
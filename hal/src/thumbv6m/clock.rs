@@ -3,11 +3,16 @@
 //! before you can set up most of the peripherals on the atsamd21 device.
 //! The other types in this module are used to enforce at compile time
 //! that the peripherals have been correctly configured.
+#[cfg(feature = "samd21")]
+use crate::gpio::v2::{self as gpio, AlternateH, Pin, PinId, PinMode};
 use crate::target_device::gclk::clkctrl::GEN_A::*;
 use crate::target_device::gclk::clkctrl::ID_A::*;
 use crate::target_device::gclk::genctrl::SRC_A::*;
 use crate::target_device::{self, GCLK, NVMCTRL, PM, SYSCTRL};
-use crate::time::{Hertz, U32Ext};
+use crate::time::{Hertz, RateExtU32};
+
+#[cfg(feature = "samd21")]
+pub mod v2;
 
 pub type ClockId = target_device::gclk::clkctrl::ID_A;
 pub type ClockGenId = target_device::gclk::clkctrl::GEN_A;
@@ -70,6 +75,10 @@ impl State {
             }
         }
         if divisor_invalid {
+            // Formatting the values doesn't fit the flash of the SAMD11
+            #[cfg(feature = "samd11")]
+            panic!("invalid divisor for the GCLK");
+            #[cfg(not(feature = "samd11"))]
             panic!("invalid divisor {} for GCLK {}", divider, gclk as u8);
         }
 
@@ -114,6 +123,15 @@ impl State {
         self.gclk.genctrl.modify(|_, w| w.runstdby().bit(enable));
         self.wait_for_sync();
     }
+
+    #[cfg(feature = "samd21")]
+    fn disable_gclk(&mut self, gclk: ClockGenId) {
+        self.gclk.genctrl.write(|w| unsafe {
+            w.id().bits(u8::from(gclk));
+            w.genen().clear_bit()
+        });
+        self.wait_for_sync();
+    }
 }
 
 /// `GenericClockController` encapsulates the GCLK hardware.
@@ -202,12 +220,12 @@ impl GenericClockController {
             gclks: [
                 OSC48M_FREQ,
                 OSC32K_FREQ,
-                Hertz(0),
-                Hertz(0),
-                Hertz(0),
-                Hertz(0),
-                Hertz(0),
-                Hertz(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
             ],
             used_clocks: 1u64 << u8::from(ClockId::DFLL48),
         }
@@ -252,13 +270,13 @@ impl GenericClockController {
             state,
             gclks: [
                 OSC8M_FREQ,
-                Hertz(0),
-                Hertz(0),
-                Hertz(0),
-                Hertz(0),
-                Hertz(0),
-                Hertz(0),
-                Hertz(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
             ],
             used_clocks: 0,
         }
@@ -285,7 +303,7 @@ impl GenericClockController {
     /// returns None.
     pub fn get_gclk(&mut self, gclk: ClockGenId) -> Option<GClock> {
         let idx = u8::from(gclk) as usize;
-        if self.gclks[idx].0 == 0 {
+        if self.gclks[idx].to_Hz() == 0 {
             None
         } else {
             Some(GClock {
@@ -312,21 +330,103 @@ impl GenericClockController {
         improve_duty_cycle: bool,
     ) -> Option<GClock> {
         let idx = u8::from(gclk) as usize;
-        if self.gclks[idx].0 != 0 {
+        if self.gclks[idx].to_Hz() != 0 {
             return None;
         }
         self.state
             .set_gclk_divider_and_source(gclk, divider, src, improve_duty_cycle);
-        let freq: Hertz = match src {
+        let freq = self.source_freq(src);
+        self.gclks[idx] = freq / divider as u32;
+        Some(GClock { gclk, freq })
+    }
+
+    /// Changes the divider of a configured clock generator, keeping its
+    /// source, and records `freq` as its new frequency
+    pub(crate) fn set_gclk_divider(&mut self, gclk: ClockGenId, divider: u16, freq: Hertz) {
+        // GENDIV is written whole, and doesn't affect the source in GENCTRL
+        self.state.gclk.gendiv.write(|w| unsafe {
+            w.id().bits(u8::from(gclk));
+            w.div().bits(divider)
+        });
+        self.state.wait_for_sync();
+        self.gclks[u8::from(gclk) as usize] = freq;
+    }
+
+    fn source_freq(&self, src: ClockSource) -> Hertz {
+        match src {
             XOSC32K | OSC32K | OSCULP32K => OSC32K_FREQ,
             GCLKGEN1 => self.gclks[1],
             OSC8M => OSC8M_FREQ,
             DFLL48M => OSC48M_FREQ,
-            DPLL96M => 96.mhz().into(),
+            DPLL96M => 96.MHz(),
             GCLKIN | XOSC => unimplemented!(),
+        }
+    }
+
+    /// Configures the clock generator available on `pin` to divide `src`
+    /// down to `freq`, and outputs it on the pin. See [`ClockOut`].
+    ///
+    /// The divider is rounded to the nearest integer and limited to the
+    /// range of the generator, so the actual frequency, returned by
+    /// [`ClockOut::freq`], may differ from the requested one.
+    ///
+    /// Returns the pin back if the clock generator is already configured, or
+    /// if `freq` is zero.
+    #[cfg(feature = "samd21")]
+    pub fn clock_out<I, M, F>(
+        &mut self,
+        pin: Pin<I, M>,
+        src: ClockSource,
+        freq: F,
+    ) -> Result<ClockOut<I>, Pin<I, M>>
+    where
+        I: GclkOutPin,
+        M: PinMode,
+        F: Into<Hertz>,
+    {
+        let idx = u8::from(I::GCLK) as usize;
+        let freq = freq.into().to_Hz();
+        if self.gclks[idx].to_Hz() != 0 || freq == 0 {
+            return Err(pin);
+        }
+        let max_divider = match I::GCLK {
+            GCLK1 => 0xffff,
+            GCLK2 => 0x1f,
+            _ => 0xff,
         };
-        self.gclks[idx] = Hertz(freq.0 / divider as u32);
-        Some(GClock { gclk, freq })
+        let divider = ((self.source_freq(src).to_Hz() + freq / 2) / freq)
+            .max(1)
+            .min(max_divider);
+        let gclk = self
+            .configure_gclk_divider_and_source(I::GCLK, divider as u16, src, true)
+            .unwrap();
+        Ok(ClockOut {
+            pin: pin.into_mode(),
+            gclk: gclk.gclk,
+            freq: self.gclks[idx],
+            owned: true,
+        })
+    }
+
+    /// Outputs an already configured clock generator, such as the 32KHz
+    /// `gclk1`, on `pin`. The generator is left running when the
+    /// [`ClockOut`] is freed.
+    ///
+    /// Returns the pin back if the clock generator is not configured.
+    #[cfg(feature = "samd21")]
+    pub fn gclk_out<I: GclkOutPin, M: PinMode>(
+        &mut self,
+        pin: Pin<I, M>,
+    ) -> Result<ClockOut<I>, Pin<I, M>> {
+        match self.get_gclk(I::GCLK) {
+            Some(gclk) => Ok(ClockOut {
+                pin: pin.into_mode(),
+                gclk: gclk.gclk,
+                freq: gclk.freq,
+                owned: false,
+            }),
+            None => Err(pin),
+        }
     }
 
     /// Enables or disables the given GClk from operation in standby.
@@ -453,12 +553,109 @@ clock_generator!(
     (i2s1, I2S1Clock, I2S_1),
 );
 
+/// A pin which can output a clock generator, through its `GCLK_IO`
+/// function
+#[cfg(feature = "samd21")]
+pub trait GclkOutPin: PinId {
+    /// The clock generator wired to the pin
+    const GCLK: ClockGenId;
+}
+
+#[cfg(feature = "samd21")]
+macro_rules! gclk_out_pins {
+    ($($(#[$attr:meta])* $Pin:ident: $gclk:ident,)+) => {
+        $(
+            $(#[$attr])*
+            impl GclkOutPin for gpio::$Pin {
+                const GCLK: ClockGenId = $gclk;
+            }
+        )+
+    };
+}
+
+#[cfg(feature = "samd21")]
+gclk_out_pins! {
+    PA10: GCLK4,
+    PA11: GCLK5,
+    PA14: GCLK0,
+    PA15: GCLK1,
+    PA16: GCLK2,
+    PA17: GCLK3,
+    #[cfg(feature = "min-samd21g")]
+    PA20: GCLK4,
+    #[cfg(feature = "min-samd21g")]
+    PA21: GCLK5,
+    PA22: GCLK6,
+    PA23: GCLK7,
+    PA27: GCLK0,
+    PA28: GCLK0,
+    #[cfg(feature = "min-samd21g")]
+    PB10: GCLK4,
+    #[cfg(feature = "min-samd21g")]
+    PB11: GCLK5,
+    #[cfg(feature = "min-samd21j")]
+    PB12: GCLK6,
+    #[cfg(feature = "min-samd21j")]
+    PB13: GCLK7,
+    #[cfg(feature = "min-samd21j")]
+    PB14: GCLK0,
+    #[cfg(feature = "min-samd21j")]
+    PB15: GCLK1,
+    #[cfg(feature = "min-samd21j")]
+    PB16: GCLK2,
+    #[cfg(feature = "min-samd21j")]
+    PB17: GCLK3,
+    #[cfg(feature = "min-samd21g")]
+    PB22: GCLK0,
+    #[cfg(feature = "min-samd21g")]
+    PB23: GCLK1,
+}
+
+/// A clock generator output on a pin, e.g. to clock an external audio codec,
+/// camera or radio
+///
+/// Created by [`GenericClockController::clock_out`] or
+/// [`GenericClockController::gclk_out`].
+///
+/// ```
+/// let mclk = clocks
+///     .clock_out(pins.pa17, ClockSource::DFLL48M, 12.MHz())
+///     .ok()
+///     .unwrap();
+/// assert_eq!(mclk.freq(), 12.MHz());
+/// ```
+#[cfg(feature = "samd21")]
+pub struct ClockOut<I: GclkOutPin> {
+    pin: Pin<I, AlternateH>,
+    gclk: ClockGenId,
+    freq: Hertz,
+    owned: bool,
+}
+
+#[cfg(feature = "samd21")]
+impl<I: GclkOutPin> ClockOut<I> {
+    /// The actual frequency output on the pin
+    pub fn freq(&self) -> Hertz {
+        self.freq
+    }
+
+    /// Stops the clock generator, unless it was already configured when the
+    /// output was created, and releases the pin
+    pub fn free(self, clocks: &mut GenericClockController) -> Pin<I, AlternateH> {
+        if self.owned {
+            clocks.state.disable_gclk(self.gclk);
+            clocks.gclks[u8::from(self.gclk) as usize] = Hertz::from_raw(0);
+        }
+        self.pin
+    }
+}
+
 /// The frequency of the 48Mhz source.
-pub const OSC48M_FREQ: Hertz = Hertz(48_000_000);
+pub const OSC48M_FREQ: Hertz = Hertz::from_raw(48_000_000);
 /// The frequency of the 8 Mhz source.
-pub const OSC8M_FREQ: Hertz = Hertz(8_000_000);
+pub const OSC8M_FREQ: Hertz = Hertz::from_raw(8_000_000);
 /// The frequency of the 32Khz source.
-pub const OSC32K_FREQ: Hertz = Hertz(32_768);
+pub const OSC32K_FREQ: Hertz = Hertz::from_raw(32_768);
 
 fn set_flash_to_half_auto_wait_state(nvmctrl: &mut NVMCTRL) {
     nvmctrl.ctrlb.modify(|_, w| w.rws().half());
@@ -0,0 +1,471 @@
+//! # Version 2 of the clock module (SAMD21)
+//!
+//! The [`GenericClockController`] of [`v1`](super) tracks at run-time which
+//! peripheral channels are in use, and its `sercom0_core`-style methods
+//! return `None` when a channel is configured twice. This module tracks the
+//! same state in the type system instead, so that the mistakes are caught at
+//! compile-time:
+//!
+//! * Each GCLK generator and each peripheral channel is represented by a
+//!   token, [`GclkToken`] and [`PclkToken`], which exists only once. A
+//!   peripheral channel can't be enabled twice, because enabling it consumes
+//!   its token.
+//! * An enabled generator is an [`Enabled`]`<`[`Gclk`]`<G>, N>`, where `N` is a
+//!   [`typenum`] count of the peripheral channels it feeds. A [`Pclk`] can
+//!   only be created from an enabled generator, and only a generator with a
+//!   count of zero can be disabled.
+//!
+//! The oscillators are still configured by the v1 code, which sets up the
+//! 48MHz DFLL on `GCLK0` and the 32kHz oscillator on `GCLK1`. Both
+//! generators are returned with a count of one, for the CPU and the DFLL
+//! respectively, so they can't be disabled.
+//!
+//! ```
+//! let mut clocks = Clocks::with_internal_32kosc(
+//!     peripherals.GCLK,
+//!     &mut peripherals.PM,
+//!     &mut peripherals.SYSCTRL,
+//!     &mut peripherals.NVMCTRL,
+//! );
+//! let gclk3 = Gclk::new(clocks.gclks.gclk3, GclkSource::Osc8m, 1).enable();
+//! let (sercom0, gclk3) = Pclk::enable(clocks.pclks.sercom0_core, gclk3);
+//! // Peripheral drivers still take the v1 clock types
+//! let sercom0_clock: Sercom0CoreClock = (&sercom0).into();
+//! ```
+//!
+//! [`GenericClockController`]: super::GenericClockController
+
+use core::marker::PhantomData;
+use core::ops::{Add, Sub};
+
+use typenum::{Add1, Sub1, Unsigned, B1, U0, U1};
+
+use super::{ClockGenId, ClockId, ClockSource, GenericClockController};
+use super::{OSC32K_FREQ, OSC48M_FREQ, OSC8M_FREQ};
+use crate::target_device::{GCLK, NVMCTRL, PM, SYSCTRL};
+use crate::time::{Hertz, RateExtU32};
+pub use crate::typelevel::Enabled;
+use crate::typelevel::Sealed;
+
+//==============================================================================
+// Generators
+//==============================================================================
+
+/// Type-level enum of the GCLK generators
+pub trait GenNum: Sealed {
+    /// The generator number
+    const ID: ClockGenId;
+    /// Largest divider supported by the generator
+    const MAX_DIV: u16;
+}
+
+macro_rules! generators {
+    ($($Gen:ident: $id:ident, $max_div:expr;)+) => {
+        $(
+            /// Type-level variant of [`GenNum`]
+            pub enum $Gen {}
+            impl Sealed for $Gen {}
+            impl GenNum for $Gen {
+                const ID: ClockGenId = ClockGenId::$id;
+                const MAX_DIV: u16 = $max_div;
+            }
+        )+
+    };
+}
+
+generators! {
+    Gen0: GCLK0, 0xff;
+    Gen1: GCLK1, 0xffff;
+    Gen2: GCLK2, 0x1f;
+    Gen3: GCLK3, 0xff;
+    Gen4: GCLK4, 0xff;
+    Gen5: GCLK5, 0xff;
+    Gen6: GCLK6, 0xff;
+    Gen7: GCLK7, 0xff;
+}
+
+/// Singleton token which allows a generator to be configured
+pub struct GclkToken<G: GenNum> {
+    gen: PhantomData<G>,
+}
+
+impl<G: GenNum> GclkToken<G> {
+    /// Safety: there must be only one token for each generator
+    unsafe fn new() -> Self {
+        Self { gen: PhantomData }
+    }
+}
+
+/// The clock source of a [`Gclk`]
+///
+/// The oscillator must be running; this module does not track the
+/// oscillators.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GclkSource {
+    /// The 8MHz internal oscillator
+    Osc8m,
+    /// The 48MHz DFLL
+    Dfll48m,
+    /// The 96MHz fractional DPLL
+    Dpll96m,
+    /// The 32kHz internal oscillator
+    Osc32k,
+    /// The 32kHz ultra low power internal oscillator
+    Osculp32k,
+    /// The 32kHz crystal oscillator
+    Xosc32k,
+    /// The crystal oscillator, running at the given frequency
+    Xosc(Hertz),
+    /// The `GCLK_IO` pin of the generator, clocked at the given frequency
+    GclkIn(Hertz),
+}
+
+impl GclkSource {
+    fn source(self) -> ClockSource {
+        match self {
+            GclkSource::Osc8m => ClockSource::OSC8M,
+            GclkSource::Dfll48m => ClockSource::DFLL48M,
+            GclkSource::Dpll96m => ClockSource::DPLL96M,
+            GclkSource::Osc32k => ClockSource::OSC32K,
+            GclkSource::Osculp32k => ClockSource::OSCULP32K,
+            GclkSource::Xosc32k => ClockSource::XOSC32K,
+            GclkSource::Xosc(_) => ClockSource::XOSC,
+            GclkSource::GclkIn(_) => ClockSource::GCLKIN,
+        }
+    }
+
+    fn freq(self) -> Hertz {
+        match self {
+            GclkSource::Osc8m => OSC8M_FREQ,
+            GclkSource::Dfll48m => OSC48M_FREQ,
+            GclkSource::Dpll96m => 96.MHz(),
+            GclkSource::Osc32k | GclkSource::Osculp32k | GclkSource::Xosc32k => OSC32K_FREQ,
+            GclkSource::Xosc(freq) | GclkSource::GclkIn(freq) => freq,
+        }
+    }
+}
+
+/// A GCLK generator configuration
+///
+/// Nothing is written to the hardware until the generator is
+/// [enabled](Gclk::enable).
+pub struct Gclk<G: GenNum> {
+    token: GclkToken<G>,
+    src: GclkSource,
+    div: u16,
+    improve_duty_cycle: bool,
+}
+
+impl<G: GenNum> Gclk<G> {
+    /// Divides `src` by `div`. Panics if `div` is zero or too large for the
+    /// generator: `GCLK1` supports 16 bits, `GCLK2` 5 bits and the others 8
+    /// bits.
+    pub fn new(token: GclkToken<G>, src: GclkSource, div: u16) -> Self {
+        #[cfg(feature = "samd11")]
+        assert!(div > 0 && div <= G::MAX_DIV, "invalid divisor");
+        #[cfg(not(feature = "samd11"))]
+        assert!(div > 0 && div <= G::MAX_DIV, "invalid divisor {}", div);
+        Self {
+            token,
+            src,
+            div,
+            improve_duty_cycle: false,
+        }
+    }
+
+    /// Enables a 50/50 duty cycle for odd dividers
+    pub fn improve_duty_cycle(mut self, enable: bool) -> Self {
+        self.improve_duty_cycle = enable;
+        self
+    }
+
+    /// The frequency of the generator output
+    pub fn freq(&self) -> Hertz {
+        self.src.freq() / self.div as u32
+    }
+
+    /// Starts the generator
+    pub fn enable(self) -> Enabled<Self, U0> {
+        // The GCLK registers are addressed indirectly through an ID field,
+        // but each of these writes carries the ID, so the tokens of
+        // different generators can't interfere
+        let gclk = unsafe { &*GCLK::ptr() };
+        gclk.gendiv.write(|w| unsafe {
+            w.id().bits(G::ID.into());
+            w.div().bits(self.div)
+        });
+        while gclk.status.read().syncbusy().bit_is_set() {}
+        gclk.genctrl.write(|w| unsafe {
+            w.id().bits(G::ID.into());
+            w.src().bits(self.src.source().into());
+            w.divsel().clear_bit();
+            w.idc().bit(self.improve_duty_cycle);
+            w.genen().set_bit()
+        });
+        while gclk.status.read().syncbusy().bit_is_set() {}
+        Enabled::new(self)
+    }
+
+    /// Releases the token
+    pub fn free(self) -> GclkToken<G> {
+        self.token
+    }
+}
+
+impl<G: GenNum, N: Unsigned> Enabled<Gclk<G>, N> {
+    /// The frequency of the generator output
+    pub fn freq(&self) -> Hertz {
+        self.inner.freq()
+    }
+}
+
+impl<G: GenNum> Enabled<Gclk<G>, U0> {
+    /// Stops the generator. Only possible once it feeds no peripheral
+    /// channel.
+    pub fn disable(self) -> Gclk<G> {
+        let gclk = unsafe { &*GCLK::ptr() };
+        gclk.genctrl.write(|w| unsafe {
+            w.id().bits(G::ID.into());
+            w.genen().clear_bit()
+        });
+        while gclk.status.read().syncbusy().bit_is_set() {}
+        self.inner
+    }
+}
+
+//==============================================================================
+// Peripheral channels
+//==============================================================================
+
+/// Type-level enum of the GCLK peripheral channels
+pub trait PclkId: Sealed {
+    /// The peripheral channel number
+    const ID: ClockId;
+}
+
+/// Singleton token which allows a peripheral channel to be enabled
+pub struct PclkToken<P: PclkId> {
+    pclk: PhantomData<P>,
+}
+
+impl<P: PclkId> PclkToken<P> {
+    /// Safety: there must be only one token for each peripheral channel
+    unsafe fn new() -> Self {
+        Self { pclk: PhantomData }
+    }
+}
+
+/// An enabled peripheral channel, fed by the generator `G`
+pub struct Pclk<P: PclkId, G: GenNum> {
+    token: PclkToken<P>,
+    freq: Hertz,
+    gen: PhantomData<G>,
+}
+
+impl<P: PclkId, G: GenNum> Pclk<P, G> {
+    /// Connects the peripheral channel to an enabled generator, whose count
+    /// of users is incremented
+    pub fn enable<N>(
+        token: PclkToken<P>,
+        gclk: Enabled<Gclk<G>, N>,
+    ) -> (Self, Enabled<Gclk<G>, Add1<N>>)
+    where
+        N: Unsigned + Add<B1>,
+        Add1<N>: Unsigned,
+    {
+        let gclk_regs = unsafe { &*GCLK::ptr() };
+        gclk_regs.clkctrl.write(|w| unsafe {
+            w.id().bits(P::ID.into());
+            w.gen().bits(G::ID.into());
+            w.clken().set_bit()
+        });
+        while gclk_regs.status.read().syncbusy().bit_is_set() {}
+        let pclk = Self {
+            token,
+            freq: gclk.freq(),
+            gen: PhantomData,
+        };
+        (pclk, gclk.add_user())
+    }
+
+    /// Disconnects the peripheral channel, and decrements the count of users
+    /// of its generator
+    pub fn disable<N>(self, gclk: Enabled<Gclk<G>, N>) -> (PclkToken<P>, Enabled<Gclk<G>, Sub1<N>>)
+    where
+        N: Unsigned + Sub<B1>,
+        Sub1<N>: Unsigned,
+    {
+        let gclk_regs = unsafe { &*GCLK::ptr() };
+        gclk_regs.clkctrl.write(|w| unsafe {
+            w.id().bits(P::ID.into());
+            w.gen().bits(G::ID.into());
+            w.clken().clear_bit()
+        });
+        while gclk_regs.status.read().syncbusy().bit_is_set() {}
+        (self.token, gclk.remove_user())
+    }
+
+    /// The frequency of the peripheral clock
+    pub fn freq(&self) -> Hertz {
+        self.freq
+    }
+}
+
+macro_rules! pclks {
+    ($($Pclk:ident, $field:ident: $id:ident, $Clock:ident;)+) => {
+        $(
+            /// Type-level variant of [`PclkId`]
+            pub enum $Pclk {}
+            impl Sealed for $Pclk {}
+            impl PclkId for $Pclk {
+                const ID: ClockId = ClockId::$id;
+            }
+
+            impl<G: GenNum> From<&Pclk<$Pclk, G>> for super::$Clock {
+                fn from(pclk: &Pclk<$Pclk, G>) -> Self {
+                    Self { freq: pclk.freq }
+                }
+            }
+        )+
+
+        /// The tokens of all the peripheral channels
+        pub struct PclkTokens {
+            $(
+                #[allow(missing_docs)]
+                pub $field: PclkToken<$Pclk>,
+            )+
+        }
+
+        impl PclkTokens {
+            /// Safety: must be called only once
+            unsafe fn new() -> Self {
+                Self {
+                    $($field: PclkToken::new(),)+
+                }
+            }
+        }
+    };
+}
+
+pclks! {
+    Tcc0Tcc1, tcc0_tcc1: TCC0_TCC1, Tcc0Tcc1Clock;
+    Tcc2Tc3, tcc2_tc3: TCC2_TC3, Tcc2Tc3Clock;
+    Tc4Tc5, tc4_tc5: TC4_TC5, Tc4Tc5Clock;
+    Tc6Tc7, tc6_tc7: TC6_TC7, Tc6Tc7Clock;
+    Sercom0Core, sercom0_core: SERCOM0_CORE, Sercom0CoreClock;
+    Sercom1Core, sercom1_core: SERCOM1_CORE, Sercom1CoreClock;
+    Sercom2Core, sercom2_core: SERCOM2_CORE, Sercom2CoreClock;
+    Sercom3Core, sercom3_core: SERCOM3_CORE, Sercom3CoreClock;
+    Sercom4Core, sercom4_core: SERCOM4_CORE, Sercom4CoreClock;
+    Sercom5Core, sercom5_core: SERCOM5_CORE, Sercom5CoreClock;
+    Usb, usb: USB, UsbClock;
+    Rtc, rtc: RTC, RtcClock;
+    Adc, adc: ADC, AdcClock;
+    Wdt, wdt: WDT, WdtClock;
+    Eic, eic: EIC, EicClock;
+    Evsys0, evsys0: EVSYS_0, Evsys0Clock;
+    Evsys1, evsys1: EVSYS_1, Evsys1Clock;
+    Evsys2, evsys2: EVSYS_2, Evsys2Clock;
+    Evsys3, evsys3: EVSYS_3, Evsys3Clock;
+    Evsys4, evsys4: EVSYS_4, Evsys4Clock;
+    Evsys5, evsys5: EVSYS_5, Evsys5Clock;
+    Evsys6, evsys6: EVSYS_6, Evsys6Clock;
+    Evsys7, evsys7: EVSYS_7, Evsys7Clock;
+    Evsys8, evsys8: EVSYS_8, Evsys8Clock;
+    Evsys9, evsys9: EVSYS_9, Evsys9Clock;
+    Evsys10, evsys10: EVSYS_10, Evsys10Clock;
+    Evsys11, evsys11: EVSYS_11, Evsys11Clock;
+    AcAna, ac_ana: AC_ANA, AcAnaClock;
+    AcDig, ac_dig: AC_DIG, AcDigClock;
+    Dac, dac: DAC, DacClock;
+    I2S0, i2s0: I2S_0, I2S0Clock;
+    I2S1, i2s1: I2S_1, I2S1Clock;
+}
+
+//==============================================================================
+// Clocks
+//==============================================================================
+
+/// The tokens of the generators which are not used at reset
+#[allow(missing_docs)]
+pub struct GclkTokens {
+    pub gclk2: GclkToken<Gen2>,
+    pub gclk3: GclkToken<Gen3>,
+    pub gclk4: GclkToken<Gen4>,
+    pub gclk5: GclkToken<Gen5>,
+    pub gclk6: GclkToken<Gen6>,
+    pub gclk7: GclkToken<Gen7>,
+}
+
+/// The clock tree at reset: the running generators and the tokens of the
+/// unused ones and of all the peripheral channels
+pub struct Clocks {
+    /// The 48MHz system clock, fed to the CPU
+    pub gclk0: Enabled<Gclk<Gen0>, U1>,
+    /// The 32kHz clock, fed to the DFLL
+    pub gclk1: Enabled<Gclk<Gen1>, U1>,
+    /// Tokens of the unused generators
+    pub gclks: GclkTokens,
+    /// Tokens of the peripheral channels
+    pub pclks: PclkTokens,
+}
+
+impl Clocks {
+    /// Configures the system to run at 48MHz from the DFLL, locked to the
+    /// internal 32kHz oscillator
+    pub fn with_internal_32kosc(
+        gclk: GCLK,
+        pm: &mut PM,
+        sysctrl: &mut SYSCTRL,
+        nvmctrl: &mut NVMCTRL,
+    ) -> Self {
+        let controller = GenericClockController::with_internal_32kosc(gclk, pm, sysctrl, nvmctrl);
+        Self::from_controller(controller, GclkSource::Osc32k)
+    }
+
+    /// Configures the system to run at 48MHz from the DFLL, locked to the
+    /// external 32kHz crystal
+    pub fn with_external_32kosc(
+        gclk: GCLK,
+        pm: &mut PM,
+        sysctrl: &mut SYSCTRL,
+        nvmctrl: &mut NVMCTRL,
+    ) -> Self {
+        let controller = GenericClockController::with_external_32kosc(gclk, pm, sysctrl, nvmctrl);
+        Self::from_controller(controller, GclkSource::Xosc32k)
+    }
+
+    fn from_controller(controller: GenericClockController, gclk1_source: GclkSource) -> Self {
+        // The controller was just created, so only GCLK0, GCLK1 and the
+        // DFLL48 peripheral channel are in use. Its GCLK is dropped, and the
+        // tokens access the registers directly.
+        drop(controller);
+        unsafe {
+            let gclk0 = Gclk {
+                token: GclkToken::new(),
+                src: GclkSource::Dfll48m,
+                div: 1,
+                improve_duty_cycle: true,
+            };
+            let gclk1 = Gclk {
+                token: GclkToken::new(),
+                src: gclk1_source,
+                div: 1,
+                improve_duty_cycle: false,
+            };
+            Self {
+                gclk0: Enabled::new(gclk0),
+                gclk1: Enabled::new(gclk1),
+                gclks: GclkTokens {
+                    gclk2: GclkToken::new(),
+                    gclk3: GclkToken::new(),
+                    gclk4: GclkToken::new(),
+                    gclk5: GclkToken::new(),
+                    gclk6: GclkToken::new(),
+                    gclk7: GclkToken::new(),
+                },
+                pclks: PclkTokens::new(),
+            }
+        }
+    }
+}
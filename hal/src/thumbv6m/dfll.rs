@@ -0,0 +1,173 @@
+//! Runtime trimming of the DFLL48M
+//!
+//! Boards without a 32KHz crystal run the DFLL48M either in closed loop mode
+//! against the internal OSC32K, which is only accurate to a few percent, or
+//! in open loop mode from its factory calibration. In open loop mode, the
+//! frequency is set by the coarse and fine values of the `DFLLVAL` register,
+//! and drifts with temperature.
+//!
+//! This module reads and adjusts those values at runtime, so that the DFLL
+//! can be trimmed against an external reference, such as USB SOF packets or
+//! a GPS PPS pulse, then kept on frequency by [`DfllTrim`], which periodically
+//! calls a compensation function with the current temperature.
+//!
+//! ```
+//! fn compensate(temperature: i32, base: DfllValue) -> DfllValue {
+//!     // About one fine step every 4°C away from 25°C, measured on the bench
+//!     base.with_fine_offset((temperature - 25) / 4)
+//! }
+//!
+//! dfll::open_loop(&mut sysctrl);
+//! let mut trim = DfllTrim::new(dfll::value(&sysctrl));
+//! trim.set_compensation(1000, compensate);
+//! loop {
+//!     delay.delay_ms(100u16);
+//!     trim.poll(&mut sysctrl, 100, || read_temperature(&mut adc));
+//! }
+//! ```
+
+use crate::target_device::SYSCTRL;
+
+/// Largest fine value of the DFLL48M
+pub const FINE_MAX: u16 = 0x3ff;
+/// Largest coarse value of the DFLL48M
+pub const COARSE_MAX: u8 = 0x3f;
+
+/// Coarse and fine values of the DFLL48M
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct DfllValue {
+    /// Coarse value, from 0 to [`COARSE_MAX`]
+    pub coarse: u8,
+    /// Fine value, from 0 to [`FINE_MAX`]. One step is roughly 0.1% of the
+    /// frequency.
+    pub fine: u16,
+}
+
+impl DfllValue {
+    /// Returns the value with `offset` added to the fine value, saturating
+    /// at its limits
+    pub fn with_fine_offset(self, offset: i32) -> Self {
+        let fine = (self.fine as i32 + offset).max(0).min(FINE_MAX as i32);
+        Self {
+            coarse: self.coarse,
+            fine: fine as u16,
+        }
+    }
+}
+
+fn wait_for_dfllrdy(sysctrl: &SYSCTRL) {
+    while sysctrl.pclksr.read().dfllrdy().bit_is_clear() {}
+}
+
+/// Reads back the current coarse and fine values. In closed loop mode, they
+/// are the values most recently computed by the DFLL.
+pub fn value(sysctrl: &SYSCTRL) -> DfllValue {
+    wait_for_dfllrdy(sysctrl);
+    // DFLLVAL must be synchronized before it is read in closed loop mode
+    sysctrl.dfllsync.write(|w| w.readreq().set_bit());
+    wait_for_dfllrdy(sysctrl);
+    let val = sysctrl.dfllval.read();
+    DfllValue {
+        coarse: val.coarse().bits(),
+        fine: val.fine().bits(),
+    }
+}
+
+/// Sets the coarse and fine values. In closed loop mode, the DFLL overwrites
+/// them as it tracks its reference, so call [`open_loop`] first.
+pub fn set_value(sysctrl: &mut SYSCTRL, value: DfllValue) {
+    wait_for_dfllrdy(sysctrl);
+    sysctrl.dfllval.write(|w| unsafe {
+        w.coarse().bits(value.coarse.min(COARSE_MAX));
+        w.fine().bits(value.fine.min(FINE_MAX))
+    });
+    wait_for_dfllrdy(sysctrl);
+}
+
+/// Adds `offset` to the fine value, and returns the new value
+pub fn adjust_fine(sysctrl: &mut SYSCTRL, offset: i32) -> DfllValue {
+    let value = value(sysctrl).with_fine_offset(offset);
+    set_value(sysctrl, value);
+    value
+}
+
+/// Switches the DFLL48M to open loop mode, keeping its current frequency
+///
+/// The values last computed in closed loop mode are written back, so that a
+/// DFLL locked once against an accurate reference keeps its frequency once
+/// the reference is gone.
+pub fn open_loop(sysctrl: &mut SYSCTRL) {
+    let current = value(sysctrl);
+    sysctrl.dfllctrl.modify(|_, w| w.mode().clear_bit());
+    wait_for_dfllrdy(sysctrl);
+    set_value(sysctrl, current);
+}
+
+/// Computes the DFLL values for a temperature, in °C, from the values at the
+/// time [`DfllTrim`] was created
+pub type CompensationFn = fn(temperature: i32, base: DfllValue) -> DfllValue;
+
+/// Periodic temperature compensation of the DFLL48M in open loop mode
+pub struct DfllTrim {
+    base: DfllValue,
+    compensation: Option<CompensationFn>,
+    interval: u32,
+    elapsed: u32,
+}
+
+impl DfllTrim {
+    /// Uses `base`, the values trimmed at a known temperature, as the input
+    /// of the compensation function
+    pub const fn new(base: DfllValue) -> Self {
+        Self {
+            base,
+            compensation: None,
+            interval: 0,
+            elapsed: 0,
+        }
+    }
+
+    /// The values the compensation is computed from
+    pub fn base(&self) -> DfllValue {
+        self.base
+    }
+
+    /// Replaces the values the compensation is computed from, e.g. after
+    /// trimming against an external reference
+    pub fn set_base(&mut self, base: DfllValue) {
+        self.base = base;
+    }
+
+    /// Runs `compensation` every `interval` units of the time passed to
+    /// [`poll`](Self::poll)
+    pub fn set_compensation(&mut self, interval: u32, compensation: CompensationFn) {
+        self.compensation = Some(compensation);
+        self.interval = interval;
+        self.elapsed = 0;
+    }
+
+    /// Stops the compensation, leaving the DFLL values as they are
+    pub fn clear_compensation(&mut self) {
+        self.compensation = None;
+    }
+
+    /// Accounts for `elapsed` units of time. Once the interval has passed,
+    /// reads the temperature with `temperature`, then applies and returns the
+    /// values computed by the compensation function.
+    pub fn poll<T: FnOnce() -> i32>(
+        &mut self,
+        sysctrl: &mut SYSCTRL,
+        elapsed: u32,
+        temperature: T,
+    ) -> Option<DfllValue> {
+        let compensation = self.compensation?;
+        self.elapsed = self.elapsed.saturating_add(elapsed);
+        if self.elapsed < self.interval {
+            return None;
+        }
+        self.elapsed = 0;
+        let value = compensation(temperature(), self.base);
+        set_value(sysctrl, value);
+        Some(value)
+    }
+}
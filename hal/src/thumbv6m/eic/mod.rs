@@ -2,6 +2,7 @@ use crate::clock::EicClock;
 use crate::target_device;
 
 pub mod pin;
+pub mod wake;
 
 pub struct EIC {
     eic: target_device::EIC,
@@ -0,0 +1,115 @@
+//! Wakeup from sleep on pin changes
+//!
+//! [`WakeSources`] configures the external interrupt lines of a set of pins
+//! so that a change on any of them wakes the device, and [`WakeSet`] reports
+//! which of them did once the device is awake again.
+//!
+//! ```
+//! let wake = WakeSources::new(&mut eic)
+//!     .pin(&button, Sense::FALL)
+//!     .pin(&lid, Sense::BOTH)
+//!     .enable();
+//! // ...sleep...
+//! if wake.which_woke_me().contains(&button) {
+//!     // ...
+//! }
+//! ```
+
+use super::pin::{ExternalInterrupt, ExternalInterruptID, Sense};
+use super::EIC;
+
+/// Builder for a set of pins which wake the device
+pub struct WakeSources<'a> {
+    eic: &'a mut EIC,
+    mask: u32,
+}
+
+impl<'a> WakeSources<'a> {
+    /// Starts a new, empty set of wake sources
+    pub fn new(eic: &'a mut EIC) -> Self {
+        Self { eic, mask: 0 }
+    }
+
+    /// Adds a pin, which must already be configured as an interrupt, with the
+    /// given sense. Only a single pin can be used per external interrupt line.
+    pub fn pin<P: ExternalInterrupt>(self, pin: &P, sense: Sense) -> Self {
+        self.id(pin.id(), sense)
+    }
+
+    /// Adds an external interrupt line by number
+    pub fn id(mut self, id: ExternalInterruptID, sense: Sense) -> Self {
+        let shift = (id & 0b111) * 4;
+        self.eic.eic.config[id >> 3].modify(|r, w| unsafe {
+            w.bits(r.bits() & !(0b111 << shift) | (sense as u32) << shift)
+        });
+        self.mask |= 1 << id;
+        self
+    }
+
+    /// Clears any stale flags and enables the interrupts and wakeup of all
+    /// of the lines.
+    ///
+    /// The EIC interrupt must also be unmasked in the NVIC for the device to
+    /// wake up.
+    pub fn enable(self) -> WakeSet {
+        let mask = self.mask;
+        let eic = &self.eic.eic;
+        eic.intflag.write(|w| unsafe { w.bits(mask) });
+        eic.wakeup.modify(|r, w| unsafe { w.bits(r.bits() | mask) });
+        eic.intenset.write(|w| unsafe { w.bits(mask) });
+        WakeSet { mask }
+    }
+}
+
+/// A set of external interrupt lines enabled as wake sources
+#[derive(Copy, Clone, Debug)]
+pub struct WakeSet {
+    mask: u32,
+}
+
+impl WakeSet {
+    /// Returns the lines of this set whose interrupt flag is raised, and
+    /// clears those flags.
+    pub fn which_woke_me(&self) -> WakeMask {
+        let eic = unsafe { &*crate::target_device::EIC::ptr() };
+        let woken = eic.intflag.read().bits() & self.mask;
+        eic.intflag.write(|w| unsafe { w.bits(woken) });
+        WakeMask(woken)
+    }
+
+    /// Disables the interrupts and wakeup of all of the lines
+    pub fn disable(self, eic: &mut EIC) {
+        let mask = self.mask;
+        eic.eic.intenclr.write(|w| unsafe { w.bits(mask) });
+        eic.eic
+            .wakeup
+            .modify(|r, w| unsafe { w.bits(r.bits() & !mask) });
+    }
+}
+
+/// A bitmask of external interrupt lines, one bit per line
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct WakeMask(pub u32);
+
+impl WakeMask {
+    /// Returns true if no line is set
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns true if the line of the given pin is set
+    pub fn contains<P: ExternalInterrupt>(&self, pin: &P) -> bool {
+        self.contains_id(pin.id())
+    }
+
+    /// Returns true if the given line is set
+    pub fn contains_id(&self, id: ExternalInterruptID) -> bool {
+        self.0 & (1 << id) != 0
+    }
+
+    /// Iterates over the numbers of the lines which are set
+    pub fn iter(&self) -> impl Iterator<Item = ExternalInterruptID> {
+        let mask = self.0;
+        (0..32).filter(move |id| mask & (1 << id) != 0)
+    }
+}
@@ -0,0 +1,324 @@
+//! Event system introspection and software events
+//!
+//! The drivers which route events between peripherals, such as
+//! [`TimerCounter::into_pulse_counter`](crate::timer::TimerCounter::into_pulse_counter),
+//! set up the EVSYS channels and users themselves. The functions of this
+//! module read that configuration back, and fire software events into the
+//! channels, so that an event chain can be exercised without its generator:
+//!
+//! ```
+//! let counter = timer.into_pulse_counter(&mut evsys, &mut pm, 3, 0);
+//! assert_eq!(evsys::user_channel(&evsys, 0x12), Some(0));
+//! for _ in 0..10 {
+//!     evsys::trigger(&mut evsys, 0);
+//! }
+//! assert_eq!(counter.count(), 10);
+//! ```
+//!
+//! The channel and user numbers are those of the datasheet. The CHANNEL and
+//! USER registers are read indirectly, by first writing the number of the
+//! channel or user to read, so these functions must not interrupt a driver
+//! which is setting up the event system.
+//!
+//! # Type-level channels
+//!
+//! The channel numbers above are only checked at run-time, so two drivers
+//! can be set up on the same channel by mistake. [`Channels::new`] consumes
+//! the `EVSYS` peripheral and returns a [`ChannelToken`] for each channel
+//! instead. A channel is given its generator by consuming its token, so it
+//! can't be handed out twice, and it is returned as an [`Enabled`]`<`
+//! [`EventChannel`]`<C>, N>` which counts its `N` users, in the same way as
+//! the GCLK generators of `clock::v2`. Only a channel without users can be
+//! disabled:
+//!
+//! ```
+//! let channels = Channels::new(peripherals.EVSYS, &mut peripherals.PM);
+//! let ch0 = EventChannel::enable(channels.ch0, 0x0c + 3, Path::Asynchronous);
+//! let (counter, ch0) = timer.into_event_counter(ch0);
+//! ```
+//!
+//! The counter keeps the [`Connection`] of the timer to the channel, so it
+//! can only be freed along with that same channel:
+//!
+//! ```
+//! let (timer, ch0) = counter.free_event_counter(ch0);
+//! let token = ch0.disable();
+//! ```
+//!
+//! The trigger sources of the DMAC are handed out as tokens in the same way,
+//! see [`dmac::trigger`](crate::dmac::trigger).
+
+use core::marker::PhantomData;
+use core::ops::{Add, Sub};
+use core::ptr;
+
+use paste::paste;
+use seq_macro::seq;
+use typenum::{Add1, Sub1, Unsigned, B1, U0};
+
+use crate::target_device::{evsys, EVSYS, PM};
+pub use crate::typelevel::Enabled;
+use crate::typelevel::Sealed;
+
+/// Number of event channels
+#[cfg(feature = "samd11")]
+pub const CHANNELS: u8 = 6;
+/// Number of event channels
+#[cfg(feature = "samd21")]
+pub const CHANNELS: u8 = 12;
+
+/// Returns the generator of `channel`, or `None` if it has none
+pub fn generator(evsys: &EVSYS, channel: u8) -> Option<u8> {
+    select_channel(evsys, channel);
+    let evgen = evsys.channel.read().evgen().bits();
+    if evgen == 0 {
+        None
+    } else {
+        Some(evgen)
+    }
+}
+
+/// Returns the channel connected to `user`, or `None` if it has none
+pub fn user_channel(evsys: &EVSYS, user: u8) -> Option<u8> {
+    // An 8 bit write to the USER field selects the user to read
+    unsafe { ptr::write_volatile(evsys.user.as_ptr() as *mut u8, user) };
+    // The user register holds the channel number plus one, zero disconnects
+    // the user
+    evsys.user.read().channel().bits().checked_sub(1)
+}
+
+/// Fires a software event into `channel`, as if its generator had fired
+///
+/// On the synchronous and resynchronized paths, an event fired while the
+/// channel is still busy with the previous one is lost, see [`is_busy`].
+pub fn trigger(evsys: &mut EVSYS, channel: u8) {
+    fire(evsys, channel);
+}
+
+/// Returns true while an event of `channel` has not been handled by all its
+/// users yet. Only meaningful on the synchronous and resynchronized paths.
+pub fn is_busy(evsys: &EVSYS, channel: u8) -> bool {
+    let (_, busy) = status_bits(channel);
+    evsys.chstatus.read().bits() & busy != 0
+}
+
+/// Returns true if all the users of `channel` are ready to handle an event.
+/// Only meaningful on the synchronous and resynchronized paths.
+pub fn users_ready(evsys: &EVSYS, channel: u8) -> bool {
+    let (ready, _) = status_bits(channel);
+    evsys.chstatus.read().bits() & ready != 0
+}
+
+fn fire(evsys: &evsys::RegisterBlock, channel: u8) {
+    // SWEVT is written along with the current configuration of the channel
+    select_channel(evsys, channel);
+    let config = evsys.channel.read().bits();
+    evsys
+        .channel
+        .write(|w| unsafe { w.bits(config) }.swevt().set_bit());
+}
+
+/// Selects the channel read from the CHANNEL register, with an 8 bit write
+/// to its CHANNEL field
+fn select_channel(evsys: &evsys::RegisterBlock, channel: u8) {
+    assert!(channel < CHANNELS);
+    unsafe { ptr::write_volatile(evsys.channel.as_ptr() as *mut u8, channel) };
+}
+
+/// Returns the masks of the USRRDY and CHBUSY bits of `channel` in CHSTATUS
+fn status_bits(channel: u8) -> (u32, u32) {
+    assert!(channel < CHANNELS);
+    // The bits of channels 8 to 11 are in the upper half of the register
+    if channel < 8 {
+        (1 << channel, 1 << (channel + 8))
+    } else {
+        (1 << (channel + 8), 1 << (channel + 16))
+    }
+}
+
+//==============================================================================
+// Type-level channels
+//==============================================================================
+
+/// Type-level enum of the event channels
+pub trait ChId: Sealed {
+    /// The channel number
+    const ID: u8;
+}
+
+/// Singleton token which allows a channel to be given a generator
+pub struct ChannelToken<C: ChId> {
+    ch: PhantomData<C>,
+}
+
+impl<C: ChId> ChannelToken<C> {
+    /// Safety: there must be only one token for each channel
+    unsafe fn new() -> Self {
+        Self { ch: PhantomData }
+    }
+}
+
+macro_rules! channels {
+    ($num_channels:literal) => {
+        seq!(N in 0..$num_channels {
+            paste! {
+                #(
+                    /// Type-level variant of [`ChId`]
+                    pub enum [<Ch N>] {}
+                    impl Sealed for [<Ch N>] {}
+                    impl ChId for [<Ch N>] {
+                        const ID: u8 = N;
+                    }
+                )*
+
+                /// The tokens of all the event channels
+                #[allow(missing_docs)]
+                pub struct Channels {
+                    #(
+                        pub [<ch N>]: ChannelToken<[<Ch N>]>,
+                    )*
+                    evsys: EVSYS,
+                }
+
+                impl Channels {
+                    /// Enables the bus clock of the event system and returns
+                    /// the tokens of its channels. The run-time functions of
+                    /// this module can't be used until the `EVSYS` peripheral
+                    /// is freed.
+                    pub fn new(evsys: EVSYS, pm: &mut PM) -> Self {
+                        pm.apbcmask.modify(|_, w| w.evsys_().set_bit());
+                        unsafe {
+                            Self {
+                                #(
+                                    [<ch N>]: ChannelToken::new(),
+                                )*
+                                evsys,
+                            }
+                        }
+                    }
+
+                    /// Returns the `EVSYS` peripheral, once the tokens of all
+                    /// the channels are back
+                    pub fn free(self) -> EVSYS {
+                        self.evsys
+                    }
+                }
+            }
+        });
+    };
+}
+
+#[cfg(feature = "samd11")]
+channels!(6);
+#[cfg(feature = "samd21")]
+channels!(12);
+
+/// The path of the events through a channel
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Path {
+    /// Resynchronized to the GCLK of the channel, then detected on a rising
+    /// edge
+    Synchronous,
+    /// Resynchronized to the GCLK of the channel, with the generator in
+    /// another clock domain, then detected on a rising edge
+    Resynchronized,
+    /// Passed through as is, also in standby
+    Asynchronous,
+}
+
+/// An event channel connected to a generator
+pub struct EventChannel<C: ChId> {
+    token: ChannelToken<C>,
+}
+
+impl<C: ChId> EventChannel<C> {
+    /// Connects the channel to `generator`, numbered as in the datasheet.
+    /// The channel has no users yet.
+    pub fn enable(token: ChannelToken<C>, generator: u8, path: Path) -> Enabled<Self, U0> {
+        let evsys = unsafe { &*EVSYS::ptr() };
+        evsys.channel.write(|w| {
+            unsafe {
+                w.channel().bits(C::ID);
+                w.evgen().bits(generator);
+            }
+            match path {
+                Path::Synchronous => w.path().synchronous().edgsel().rising_edge(),
+                Path::Resynchronized => w.path().resynchronized().edgsel().rising_edge(),
+                Path::Asynchronous => w.path().asynchronous().edgsel().no_evt_output(),
+            }
+        });
+        Enabled::new(Self { token })
+    }
+}
+
+impl<C: ChId, N: Unsigned> Enabled<EventChannel<C>, N> {
+    /// Returns the channel number
+    pub fn id(&self) -> u8 {
+        C::ID
+    }
+
+    /// Fires a software event into the channel, see [`trigger`]
+    pub fn trigger(&mut self) {
+        fire(unsafe { &*EVSYS::ptr() }, C::ID);
+    }
+
+    /// Connects `user`, numbered as in the datasheet, to the channel, whose
+    /// count of users is incremented. Drivers which use events call it from
+    /// their constructors.
+    pub fn connect(self, user: u8) -> (Connection<C>, Enabled<EventChannel<C>, Add1<N>>)
+    where
+        N: Add<B1>,
+        Add1<N>: Unsigned,
+    {
+        let evsys = unsafe { &*EVSYS::ptr() };
+        // The user channel is the channel number plus one
+        evsys
+            .user
+            .write(|w| unsafe { w.user().bits(user).channel().bits(C::ID + 1) });
+        let connection = Connection {
+            user,
+            ch: PhantomData,
+        };
+        (connection, self.add_user())
+    }
+}
+
+impl<C: ChId> Enabled<EventChannel<C>, U0> {
+    /// Disconnects the channel from its generator and returns its token
+    pub fn disable(self) -> ChannelToken<C> {
+        let evsys = unsafe { &*EVSYS::ptr() };
+        // A write of the channel number alone resets its configuration
+        evsys.channel.write(|w| unsafe { w.channel().bits(C::ID) });
+        self.inner.token
+    }
+}
+
+/// A user connected to the channel `C`
+pub struct Connection<C: ChId> {
+    user: u8,
+    ch: PhantomData<C>,
+}
+
+impl<C: ChId> Connection<C> {
+    /// Returns the user number
+    pub fn user(&self) -> u8 {
+        self.user
+    }
+
+    /// Disconnects the user, and decrements the count of users of the
+    /// channel
+    pub fn disconnect<N>(
+        self,
+        channel: Enabled<EventChannel<C>, N>,
+    ) -> Enabled<EventChannel<C>, Sub1<N>>
+    where
+        N: Unsigned + Sub<B1>,
+        Sub1<N>: Unsigned,
+    {
+        let evsys = unsafe { &*EVSYS::ptr() };
+        evsys
+            .user
+            .write(|w| unsafe { w.user().bits(self.user).channel().bits(0) });
+        channel.remove_user()
+    }
+}
@@ -1,4 +1,5 @@
 pub mod eic;
+pub mod evsys;
 
 mod reset_cause;
 pub use reset_cause::*;
@@ -8,6 +9,9 @@ pub use serial_number::*;
 
 pub mod calibration;
 pub mod clock;
+#[cfg(feature = "samd21")]
+pub mod dfll;
+pub mod nvmctrl;
 pub mod timer;
 
 #[cfg(feature = "unproven")]
@@ -0,0 +1,251 @@
+//! # Flash wait states and cache
+//!
+//! The flash needs read wait states which depend on the CPU frequency, and
+//! its reads go through a small cache of a few rows. The clock controller
+//! sets one wait state, which is enough up to 48MHz. Running from a slower
+//! clock, [`auto_configure`] removes the wait state that is no longer needed:
+//!
+//! ```
+//! let mut clocks = GenericClockController::with_internal_32kosc(
+//!     peripherals.GCLK,
+//!     &mut peripherals.PM,
+//!     &mut peripherals.SYSCTRL,
+//!     &mut peripherals.NVMCTRL,
+//! );
+//! // ... switch GCLK0 to 8MHz ...
+//! nvmctrl::auto_configure(&mut peripherals.NVMCTRL, &mut clocks);
+//! ```
+//!
+//! When changing the CPU frequency, add wait states before raising it, and
+//! remove them after lowering it.
+//!
+//! # Writing
+//!
+//! A [`FlashRegion`] erases and writes a part of the flash, as the
+//! [`Storage`] of a power-fail safe [`Journal`](crate::nvm::Journal).
+
+use crate::clock::GenericClockController;
+use crate::nvm::{Error, Storage};
+use crate::target_device::nvmctrl::ctrla::CMD_A;
+use crate::target_device::NVMCTRL;
+use crate::time::Hertz;
+
+/// Highest CPU frequency for each number of wait states, for a supply above
+/// 2.7V
+const MAX_FREQ: [u32; 2] = [24_000_000, 48_000_000];
+
+/// Flash read modes
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ReadMode {
+    /// The flash stays powered between reads, so that there is no penalty on
+    /// a cache miss. The default.
+    NoMissPenalty,
+    /// The flash is powered down between reads, adding a cycle to cache
+    /// misses but saving power
+    LowPower,
+    /// Every read takes as long as a cache miss, for code which must run in
+    /// a constant time
+    Deterministic,
+}
+
+/// Configuration of the flash reads
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FlashConfig {
+    /// Number of read wait states, up to 15
+    pub wait_states: u8,
+    /// Whether the NVM cache is enabled
+    pub cache: bool,
+    /// The read mode
+    pub read_mode: ReadMode,
+}
+
+impl FlashConfig {
+    /// Returns the fastest configuration that is safe with a CPU running at
+    /// `freq`, with the cache enabled
+    pub fn for_frequency(freq: impl Into<Hertz>) -> Self {
+        Self {
+            wait_states: wait_states(freq),
+            cache: true,
+            read_mode: ReadMode::NoMissPenalty,
+        }
+    }
+
+    /// Reads the current configuration
+    pub fn read(nvmctrl: &NVMCTRL) -> Self {
+        let ctrlb = nvmctrl.ctrlb.read();
+        let read_mode = if ctrlb.readmode().is_low_power() {
+            ReadMode::LowPower
+        } else if ctrlb.readmode().is_deterministic() {
+            ReadMode::Deterministic
+        } else {
+            ReadMode::NoMissPenalty
+        };
+        Self {
+            wait_states: ctrlb.rws().bits(),
+            cache: ctrlb.cachedis().bit_is_clear(),
+            read_mode,
+        }
+    }
+
+    /// Applies the configuration
+    pub fn apply(&self, nvmctrl: &mut NVMCTRL) {
+        nvmctrl.ctrlb.modify(|_, w| {
+            unsafe { w.rws().bits(self.wait_states.min(15)) };
+            match self.read_mode {
+                ReadMode::NoMissPenalty => w.readmode().no_miss_penalty(),
+                ReadMode::LowPower => w.readmode().low_power(),
+                ReadMode::Deterministic => w.readmode().deterministic(),
+            };
+            w.cachedis().bit(!self.cache)
+        });
+    }
+}
+
+/// Returns the number of wait states needed with a CPU running at `freq`,
+/// for a supply above 2.7V
+///
+/// Frequencies above the 48MHz maximum get one more wait state per 24MHz.
+pub fn wait_states(freq: impl Into<Hertz>) -> u8 {
+    let freq = freq.into().to_Hz();
+    match MAX_FREQ.iter().position(|&max| freq <= max) {
+        Some(ws) => ws as u8,
+        None => {
+            let extra = (freq - MAX_FREQ[1] + MAX_FREQ[0] - 1) / MAX_FREQ[0];
+            (1 + extra).min(15) as u8
+        }
+    }
+}
+
+/// Enables or disables the NVM cache, leaving the wait states alone
+pub fn set_cache(nvmctrl: &mut NVMCTRL, enabled: bool) {
+    nvmctrl.ctrlb.modify(|_, w| w.cachedis().bit(!enabled));
+}
+
+/// Sets the wait states for the current frequency of GCLK0, which clocks
+/// the CPU, and enables the cache
+pub fn auto_configure(nvmctrl: &mut NVMCTRL, clocks: &mut GenericClockController) {
+    let freq: Hertz = clocks.gclk0().into();
+    let config = FlashConfig {
+        read_mode: FlashConfig::read(nvmctrl).read_mode,
+        ..FlashConfig::for_frequency(freq)
+    };
+    config.apply(nvmctrl);
+}
+
+/// A part of the flash, erased in rows of 256 bytes and written in pages of
+/// 64 bytes
+pub struct FlashRegion {
+    nvmctrl: NVMCTRL,
+    start: u32,
+    len: u32,
+    /// The manual write setting to restore once the region is freed
+    manw: bool,
+}
+
+impl FlashRegion {
+    /// Uses the `len` bytes of the flash from address `start` as storage,
+    /// and selects the manual write mode until the region is freed. Panics
+    /// if they are not whole rows.
+    ///
+    /// # Safety
+    ///
+    /// The region must not hold the program, or any data which is used
+    /// while it is erased or written.
+    pub unsafe fn new(nvmctrl: NVMCTRL, start: u32, len: u32) -> Self {
+        let row = Self::ERASE_SIZE as u32;
+        assert!(
+            start % row == 0 && len % row == 0,
+            "region is not row aligned"
+        );
+        // Otherwise, filling the page buffer writes the page before the WP
+        // command writes it again
+        let manw = nvmctrl.ctrlb.read().manw().bit();
+        nvmctrl.ctrlb.modify(|_, w| w.manw().set_bit());
+        Self {
+            nvmctrl,
+            start,
+            len,
+            manw,
+        }
+    }
+
+    /// Restores the manual write setting, and releases the NVM controller
+    pub fn free(self) -> NVMCTRL {
+        let manw = self.manw;
+        self.nvmctrl.ctrlb.modify(|_, w| w.manw().bit(manw));
+        self.nvmctrl
+    }
+
+    /// Returns the address of `offset`, if `len` bytes from it are within
+    /// the region and it is a multiple of `align`
+    fn address(&self, offset: usize, len: usize, align: usize) -> Result<u32, Error> {
+        if offset % align != 0 || offset + len > self.len as usize {
+            return Err(Error::OutOfBounds);
+        }
+        Ok(self.start + offset as u32)
+    }
+
+    /// Runs `cmd` on the row or the page at `addr`
+    fn command(&mut self, cmd: CMD_A, addr: u32) -> Result<(), Error> {
+        let nvmctrl = &self.nvmctrl;
+        while nvmctrl.intflag.read().ready().bit_is_clear() {}
+        nvmctrl
+            .status
+            .write(|w| w.proge().set_bit().locke().set_bit().nvme().set_bit());
+        // ADDR is a 16 bit word address
+        nvmctrl.addr.write(|w| unsafe { w.addr().bits(addr >> 1) });
+        nvmctrl.ctrla.write(|w| {
+            w.cmd().variant(cmd);
+            w.cmdex().key()
+        });
+        while nvmctrl.intflag.read().ready().bit_is_clear() {}
+        let status = nvmctrl.status.read();
+        if status.locke().bit_is_set() {
+            Err(Error::Locked)
+        } else if status.proge().bit_is_set() || status.nvme().bit_is_set() {
+            Err(Error::Program)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Storage for FlashRegion {
+    const ERASE_SIZE: usize = 256;
+    const WRITE_SIZE: usize = 64;
+
+    fn capacity(&self) -> usize {
+        self.len as usize
+    }
+
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), Error> {
+        let addr = self.address(offset, buf.len(), 1)? as *const u8;
+        for (i, byte) in buf.iter_mut().enumerate() {
+            *byte = unsafe { core::ptr::read_volatile(addr.add(i)) };
+        }
+        Ok(())
+    }
+
+    fn erase(&mut self, offset: usize) -> Result<(), Error> {
+        let addr = self.address(offset, Self::ERASE_SIZE, Self::ERASE_SIZE)?;
+        self.command(CMD_A::ER, addr)
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), Error> {
+        let mut addr = self.address(offset, data.len(), Self::WRITE_SIZE)?;
+        for page in data.chunks(Self::WRITE_SIZE) {
+            // The cleared page buffer is all ones, which pads the page
+            self.command(CMD_A::PBC, addr)?;
+            for (i, word) in page.chunks(4).enumerate() {
+                let mut bytes = [0xff; 4];
+                bytes[..word.len()].copy_from_slice(word);
+                // The page buffer only takes 16 and 32 bit writes
+                let dst = (addr as *mut u32).wrapping_add(i);
+                unsafe { core::ptr::write_volatile(dst, u32::from_le_bytes(bytes)) };
+            }
+            self.command(CMD_A::WP, addr)?;
+            addr += Self::WRITE_SIZE as u32;
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,11 @@
 use crate::clock;
 use crate::hal::{Pwm, PwmPin};
-use crate::time::Hertz;
+use crate::hc_sr04::Trigger;
+use crate::tick::Tick;
+use crate::time::{Hertz, Nanoseconds};
 use crate::timer_params::TimerParams;
+use core::convert::Infallible;
+use core::marker::PhantomData;
 
 use crate::target_device::{PM, TCC0};
 #[cfg(feature = "samd11")]
@@ -34,7 +38,7 @@ impl $TYPE {
         let freq = freq.into();
         {
             let count = tc.count16();
-            let params = TimerParams::new(freq, clock.freq().0);
+            let params = TimerParams::new(freq, clock.freq().to_Hz());
             pm.$apmask.modify(|_, w| w.$apbits().set_bit());
             count.ctrla.write(|w| w.swrst().set_bit());
             while count.ctrla.read().bits() & 1 != 0 {}
@@ -69,7 +73,7 @@ impl $TYPE {
         P: Into<Hertz>
     {
         let period = period.into();
-        let params = TimerParams::new(period, self.clock_freq.0);
+        let params = TimerParams::new(period, self.clock_freq.to_Hz());
         let count = self.tc.count16();
         count.ctrla.modify(|_, w| w.enable().clear_bit());
         count.ctrla.modify(|_, w| {
@@ -93,7 +97,7 @@ impl $TYPE {
         let count = self.tc.count16();
         let divisor = count.ctrla.read().prescaler().bits();
         let top = count.cc[0].read().cc().bits();
-        Hertz(self.clock_freq.0 / divisor as u32 / (top + 1) as u32)
+        self.clock_freq / divisor as u32 / (top + 1) as u32
     }
 }
 
@@ -159,8 +163,75 @@ pub enum Channel {
     _3,
 }
 
+/// Counter operation of a TCC in the single-slope PWM mode
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Ramp {
+    /// Every cycle is identical. This is the default.
+    Ramp1,
+    /// Alternative dual-ramp operation. Cycles alternate between A and B,
+    /// with channel 0 active during cycle A and channel 1 during cycle B.
+    Ramp2A,
+    /// Dual-ramp operation. Cycles alternate between A and B, with CC0 and
+    /// CC1 providing the compare values of the two cycles, so that two
+    /// interleaved pulses can be generated from a single counter.
+    Ramp2,
+    /// Like [`Ramp::Ramp2`], but the compare value of cycle B is only
+    /// applied when it is above that of cycle A.
+    #[cfg(feature = "samd11")]
+    Ramp2C,
+}
+
+/// Returns the division factor of a TCC prescaler setting
+fn prescaler_divisor(bits: u8) -> u32 {
+    1 << [0, 1, 2, 3, 4, 6, 8, 10][bits as usize & 0b111]
+}
+
+/// A TCC channel in one-shot mode, used as the trigger of an
+/// [`hc_sr04::Ranger`](crate::hc_sr04::Ranger)
+///
+/// The 10µs trigger pulse is generated in hardware by
+/// [`fire_pulse`](Pwm0::fire_pulse), so the width does not depend on
+/// interrupt latency.
+pub struct PulseTrigger<P> {
+    pwm: P,
+    channel: Channel,
+}
+
+impl<P> PulseTrigger<P> {
+    /// Releases the PWM
+    pub fn free(self) -> P {
+        self.pwm
+    }
+}
+
+/// The counter of a TCC split with [`split`](Pwm0::split), which controls
+/// the period shared by its [`PwmChannel`]s
+pub struct PwmTimer<P> {
+    pwm: P,
+}
+
+/// A compare channel of a TCC split with [`split`](Pwm0::split)
+///
+/// Each channel only writes its own CC register, so the channels of a TCC can
+/// be moved into different drivers or tasks. The period, and therefore the
+/// maximum duty, is set by the [`PwmTimer`] of the TCC.
+pub struct PwmChannel<TCC> {
+    channel: Channel,
+    /// The duty written back by `enable`
+    duty: u32,
+    enabled: bool,
+    tcc: PhantomData<TCC>,
+}
+
+impl<TCC> PwmChannel<TCC> {
+    /// Returns the compare channel of the TCC driven by this handle
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+}
+
 macro_rules! pwm_tcc {
-    ($($TYPE:ident: ($TCC:ident, $clock:ident, $apmask:ident, $apbits:ident, $wrapper:ident),)+) => {
+    ($($TYPE:ident: ($TCC:ident, $clock:ident, $apmask:ident, $apbits:ident, $wrapper:ident, $n:literal, [$($ch:ident),+]),)+) => {
         $(
 
 pub struct $TYPE {
@@ -179,7 +250,7 @@ impl $TYPE {
     ) -> Self {
         let freq = freq.into();
         {
-            let params = TimerParams::new(freq, clock.freq().0);
+            let params = TimerParams::new(freq, clock.freq().to_Hz());
             pm.$apmask.modify(|_, w| w.$apbits().set_bit());
             tcc.ctrla.write(|w| w.swrst().set_bit());
             while tcc.syncbusy.read().swrst().bit_is_set() {}
@@ -213,6 +284,236 @@ impl $TYPE {
     }
 }
 
+impl $TYPE {
+    /// Selects the counter [`Ramp`] operation
+    pub fn set_ramp(&mut self, ramp: Ramp) {
+        self.tcc.ctrla.modify(|_, w| w.enable().clear_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+        self.tcc.wave.modify(|_, w| match ramp {
+            Ramp::Ramp1 => w.ramp().ramp1(),
+            Ramp::Ramp2A => w.ramp().ramp2a(),
+            Ramp::Ramp2 => w.ramp().ramp2(),
+            #[cfg(feature = "samd11")]
+            Ramp::Ramp2C => w.ramp().ramp2c(),
+        });
+        while self.tcc.syncbusy.read().wave().bit_is_set() {}
+        self.tcc.ctrla.modify(|_, w| w.enable().set_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+    }
+
+    /// Enables or disables one-shot operation.
+    ///
+    /// In one-shot operation, the counter stops on the next overflow and the
+    /// waveform outputs are driven low until the counter is restarted by
+    /// [`retrigger`](Self::retrigger). Each channel then outputs a single
+    /// pulse, from the restart until its compare match. Enabling one-shot
+    /// operation also stops the counter, so that no pulse is fired until the
+    /// first retrigger.
+    pub fn set_one_shot(&mut self, one_shot: bool) {
+        if one_shot {
+            self.tcc.ctrlbset.write(|w| w.oneshot().set_bit());
+            while self.tcc.syncbusy.read().ctrlb().bit_is_set() {}
+            self.tcc.ctrlbset.write(|w| w.cmd().stop());
+        } else {
+            self.tcc.ctrlbclr.write(|w| w.oneshot().set_bit());
+        }
+        while self.tcc.syncbusy.read().ctrlb().bit_is_set() {}
+    }
+
+    /// Restarts the counter from zero
+    pub fn retrigger(&mut self) {
+        self.tcc.ctrlbset.write(|w| w.cmd().retrigger());
+        while self.tcc.syncbusy.read().ctrlb().bit_is_set() {}
+    }
+
+    /// Returns true while the counter is stopped, such as after the end of a
+    /// one-shot cycle
+    pub fn is_stopped(&self) -> bool {
+        self.tcc.status.read().stop().bit_is_set()
+    }
+
+    /// Stops the counter, keeping its value, until [`resume`](Self::resume).
+    /// The outputs keep their current level.
+    pub fn pause(&mut self) {
+        self.tcc.ctrla.modify(|_, w| w.enable().clear_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+    }
+
+    /// Restarts the counter from the value at which it was paused
+    pub fn resume(&mut self) {
+        self.tcc.ctrla.modify(|_, w| w.enable().set_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+    }
+
+    /// Returns the current value of the counter.
+    ///
+    /// COUNT is only synchronized on request: the READSYNC command is complete
+    /// once CMD reads back as zero.
+    pub fn count(&self) -> u32 {
+        self.tcc.ctrlbset.write(|w| w.cmd().readsync());
+        while !self.tcc.ctrlbset.read().cmd().is_none() {}
+        while self.tcc.syncbusy.read().count().bit_is_set() {}
+        self.tcc.count().read().count().bits()
+    }
+
+    /// Sets the value of the counter, which keeps counting from there if it
+    /// is running
+    pub fn set_count(&mut self, value: u32) {
+        self.tcc.count().write(|w| unsafe { w.count().bits(value) });
+        while self.tcc.syncbusy.read().count().bit_is_set() {}
+    }
+
+    /// Fires a single pulse of `width` counter ticks on `channel`.
+    ///
+    /// One-shot operation must be enabled with
+    /// [`set_one_shot`](Self::set_one_shot) and the width must be less than
+    /// the period. The pulse is generated entirely in hardware, so its width
+    /// is accurate to one tick of the counter clock. Returns `false`, without
+    /// firing, if the previous cycle has not ended yet.
+    pub fn fire_pulse(&mut self, channel: Channel, width: u32) -> bool {
+        if !self.is_stopped() {
+            return false;
+        }
+        let cc = self.tcc.cc();
+        cc[channel as usize].write(|w| unsafe { w.cc().bits(width) });
+        while self.tcc.syncbusy.read().bits() & (1 << (8 + channel as u32)) != 0 {}
+        self.retrigger();
+        true
+    }
+
+    /// Converts a duration to a number of counter ticks for
+    /// [`fire_pulse`](Self::fire_pulse), rounding down
+    pub fn ticks<T: Into<Nanoseconds>>(&self, duration: T) -> u32 {
+        let divisor = prescaler_divisor(self.tcc.ctrla.read().prescaler().bits());
+        let hz = (self.clock_freq.to_Hz() / divisor) as u64;
+        (duration.into().ticks() as u64 * hz / 1_000_000_000) as u32
+    }
+}
+
+impl PulseTrigger<$TYPE> {
+    /// Puts the TCC in one-shot mode and uses `channel` as the trigger
+    /// output. The period of the TCC must be longer than 10µs.
+    pub fn new(mut pwm: $TYPE, channel: Channel) -> Self {
+        pwm.set_one_shot(true);
+        Self { pwm, channel }
+    }
+}
+
+impl Trigger for PulseTrigger<$TYPE> {
+    type Error = Infallible;
+
+    fn trigger<T: Tick>(&mut self, _tick: &T) -> Result<(), Self::Error> {
+        // Round up to make sure the pulse is at least 10µs wide
+        let width = self.pwm.ticks(Nanoseconds::micros(10)) + 1;
+        while !self.pwm.fire_pulse(self.channel, width) {}
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "samd21", feature = "dma"))]
+impl crate::ir::CarrierTcc for $TYPE {
+    fn tcc(&self) -> &crate::target_device::tcc0::RegisterBlock {
+        &self.tcc
+    }
+}
+
+impl $TYPE {
+    /// Splits the PWM into a [`PwmTimer`], which controls the period, and one
+    /// [`PwmChannel`] per compare channel. [`PwmTimer::join`] puts them back
+    /// together.
+    pub fn split(self) -> (PwmTimer<Self>, [PwmChannel<$TCC>; $n]) {
+        let cc = self.tcc.cc();
+        let channels = [$(
+            PwmChannel {
+                channel: Channel::$ch,
+                duty: cc[Channel::$ch as usize].read().cc().bits(),
+                enabled: true,
+                tcc: PhantomData,
+            },
+        )+];
+        (PwmTimer { pwm: self }, channels)
+    }
+}
+
+impl PwmTimer<$TYPE> {
+    /// Reassembles the PWM from the timer and all of its channels. The
+    /// outputs of disabled channels stay low.
+    pub fn join(self, _channels: [PwmChannel<$TCC>; $n]) -> $TYPE {
+        self.pwm
+    }
+
+    /// Sets the period of all of the channels. Their duty is not scaled, so
+    /// it must be set again relative to the new
+    /// [`get_max_duty`](Self::get_max_duty).
+    pub fn set_period<P: Into<Hertz>>(&mut self, period: P) {
+        Pwm::set_period(&mut self.pwm, period);
+    }
+
+    /// Returns the frequency of the PWM
+    pub fn get_period(&self) -> Hertz {
+        Pwm::get_period(&self.pwm)
+    }
+
+    /// Returns the duty at which the outputs are always high
+    pub fn get_max_duty(&self) -> u32 {
+        Pwm::get_max_duty(&self.pwm)
+    }
+
+    /// Stops the counter, and therefore all of the channels, until
+    /// [`resume`](Self::resume)
+    pub fn pause(&mut self) {
+        self.pwm.pause();
+    }
+
+    /// Restarts the counter from the value at which it was paused
+    pub fn resume(&mut self) {
+        self.pwm.resume();
+    }
+}
+
+impl PwmChannel<$TCC> {
+    fn tcc(&self) -> &crate::target_device::tcc0::RegisterBlock {
+        // SAFETY: The TCC is owned by the `PwmTimer`, which doesn't write the
+        // CC registers, and each channel only writes its own CC register
+        unsafe { &*$TCC::ptr() }
+    }
+
+    fn write_cc(&mut self, duty: u32) {
+        let cc = self.tcc().cc();
+        cc[self.channel as usize].write(|w| unsafe { w.cc().bits(duty) });
+    }
+}
+
+impl PwmPin for PwmChannel<$TCC> {
+    type Duty = u32;
+
+    /// Drives the output low, without stopping the other channels
+    fn disable(&mut self) {
+        self.enabled = false;
+        self.write_cc(0);
+    }
+
+    fn enable(&mut self) {
+        self.enabled = true;
+        self.write_cc(self.duty);
+    }
+
+    fn get_duty(&self) -> Self::Duty {
+        self.duty
+    }
+
+    fn get_max_duty(&self) -> Self::Duty {
+        self.tcc().per().read().bits()
+    }
+
+    fn set_duty(&mut self, duty: Self::Duty) {
+        self.duty = duty;
+        if self.enabled {
+            self.write_cc(duty);
+        }
+    }
+}
+
 impl Pwm for $TYPE {
     type Channel = Channel;
     type Time = Hertz;
@@ -229,7 +530,7 @@ impl Pwm for $TYPE {
     fn get_period(&self) -> Self::Time {
         let divisor = self.tcc.ctrla.read().prescaler().bits();
         let top = self.tcc.per().read().bits();
-        Hertz(self.clock_freq.0 / divisor as u32 / (top + 1) as u32)
+        self.clock_freq / divisor as u32 / (top + 1) as u32
     }
 
     fn get_duty(&self, channel: Self::Channel) -> Self::Duty {
@@ -253,7 +554,7 @@ impl Pwm for $TYPE {
         P: Into<Self::Time>,
     {
         let period = period.into();
-        let params = TimerParams::new(period, self.clock_freq.0);
+        let params = TimerParams::new(period, self.clock_freq.to_Hz());
         self.tcc.ctrla.modify(|_, w| w.enable().clear_bit());
         self.tcc.ctrla.modify(|_, w| {
             match params.divider {
@@ -278,12 +579,12 @@ impl Pwm for $TYPE {
 
 #[cfg(feature = "samd11")]
 pwm_tcc! {
-    Pwm0: (TCC0, Tcc0Clock, apbcmask, tcc0_, Pwm0Wrapper),
+    Pwm0: (TCC0, Tcc0Clock, apbcmask, tcc0_, Pwm0Wrapper, 4, [_0, _1, _2, _3]),
 }
 
 #[cfg(feature = "samd21")]
 pwm_tcc! {
-    Pwm0: (TCC0, Tcc0Tcc1Clock, apbcmask, tcc0_, Pwm0Wrapper),
-    Pwm1: (TCC1, Tcc0Tcc1Clock, apbcmask, tcc1_, Pwm1Wrapper),
-    Pwm2: (TCC2, Tcc2Tc3Clock, apbcmask, tcc2_, Pwm2Wrapper),
+    Pwm0: (TCC0, Tcc0Tcc1Clock, apbcmask, tcc0_, Pwm0Wrapper, 4, [_0, _1, _2, _3]),
+    Pwm1: (TCC1, Tcc0Tcc1Clock, apbcmask, tcc1_, Pwm1Wrapper, 2, [_0, _1]),
+    Pwm2: (TCC2, Tcc2Tc3Clock, apbcmask, tcc2_, Pwm2Wrapper, 2, [_0, _1]),
 }
@@ -1,8 +1,10 @@
 use core::marker::PhantomData;
 
 use crate::clock;
+use crate::clock_scaling::{rescale_sync_baud, ClockListener};
 use crate::hal::spi::{FullDuplex, Mode, Phase, Polarity};
 use crate::sercom::v1::pads::CompatiblePad;
+use crate::sercom::v1::SercomState;
 use crate::sercom::v2::*;
 use crate::spi_common::CommonSpi;
 use crate::target_device::sercom0::SPI;
@@ -156,55 +158,8 @@ macro_rules! spi_master {
                 // safe because we're exclusively owning SERCOM
                 pm.apbcmask.modify(|_, w| w.$powermask().set_bit());
 
-                // reset the sercom instance
-                sercom.spi().ctrla.modify(|_, w| w.swrst().set_bit());
-                // wait for reset to complete
-                while sercom.spi().syncbusy.read().swrst().bit_is_set()
-                    || sercom.spi().ctrla.read().swrst().bit_is_set()
-                {}
-
-                // Put the hardware into spi master mode
-                sercom.spi().ctrla.modify(|_, w| w.mode().spi_master());
-                // wait for configuration to take effect
-                while sercom.spi().syncbusy.read().enable().bit_is_set() {}
-
-                // 8 bit data size and enable the receiver
-                unsafe {
-                    sercom.spi().ctrlb.modify(|_, w| {
-                        w.chsize().bits(0);
-                        w.rxen().set_bit()
-                    });
-                }
-
-                // set the baud rate
                 let baud = Self::calculate_baud(freq, clock.freq());
-
-                unsafe {
-                    sercom.spi().baud.modify(|_, w| w.baud().bits(baud));
-
-                    sercom.spi().ctrla.modify(|_, w| {
-                        match mode.polarity {
-                            Polarity::IdleLow => w.cpol().clear_bit(),
-                            Polarity::IdleHigh => w.cpol().set_bit(),
-                        };
-
-                        match mode.phase {
-                            Phase::CaptureOnFirstTransition => w.cpha().clear_bit(),
-                            Phase::CaptureOnSecondTransition => w.cpha().set_bit(),
-                        };
-
-                        let (dipo, dopo) = padout.dipo_dopo();
-                        w.dipo().bits(dipo);
-                        w.dopo().bits(dopo);
-
-                        // MSB first
-                        w.dord().clear_bit()
-                    });
-                }
-
-                sercom.spi().ctrla.modify(|_, w| w.enable().set_bit());
-                // wait for configuration to take effect
-                while sercom.spi().syncbusy.read().enable().bit_is_set() {}
+                init(sercom.spi(), mode, padout.dipo_dopo(), baud);
 
                 Self { padout, sercom }
             }
@@ -224,6 +179,32 @@ macro_rules! spi_master {
             pub fn free(self) -> (Padout<$Sercom, MISO, MOSI, SCK>, $SERCOM) {
                 (self.padout, self.sercom)
             }
+
+            /// Captures the main registers of the SERCOM, for debugging
+            pub fn debug_state(&self) -> SercomState {
+                let regs = self.spi();
+                SercomState {
+                    ctrla: regs.ctrla.read().bits(),
+                    ctrlb: regs.ctrlb.read().bits(),
+                    baud: regs.baud.read().bits().into(),
+                    status: regs.status.read().bits(),
+                    intflag: regs.intflag.read().bits(),
+                    syncbusy: regs.syncbusy.read().bits(),
+                }
+            }
+        }
+
+        impl<MISO, MOSI, SCK> ClockListener for $Type<MISO, MOSI, SCK> {
+            /// Restores the SCK rate after the SERCOM core clock changed
+            fn clock_changed(&mut self, old: Hertz, new: Hertz) {
+                let baud = self.spi().baud.read().baud().bits();
+                let baud = rescale_sync_baud(baud, old, new);
+                self.disable();
+                unsafe {
+                    self.spi_mut().baud.modify(|_, w| w.baud().bits(baud));
+                }
+                self.enable();
+            }
         }
 
         impl<MISO, MOSI, SCK> FullDuplex<u8> for $Type<MISO, MOSI, SCK> {
@@ -281,3 +262,56 @@ spi_master!(SPIMaster3: (Sercom3, SERCOM3, sercom3_, Sercom3CoreClock));
 spi_master!(SPIMaster4: (Sercom4, SERCOM4, sercom4_, Sercom4CoreClock));
 #[cfg(feature = "min-samd21g")]
 spi_master!(SPIMaster5: (Sercom5, SERCOM5, sercom5_, Sercom5CoreClock));
+
+/// Resets the SERCOM and enables it as an 8-bit SPI master in `mode`, with
+/// the pads `dipo_dopo` and the `baud` register value
+///
+/// This is not generic, so that a single copy is shared by all of the SPI
+/// masters and pad mappings, rather than one for each combination. This
+/// matters on the SAMD11, with only 16 kB of flash.
+#[inline(never)]
+fn init(spi: &SPI, mode: Mode, (dipo, dopo): (u8, u8), baud: u8) {
+    // reset the sercom instance
+    spi.ctrla.modify(|_, w| w.swrst().set_bit());
+    // wait for reset to complete
+    while spi.syncbusy.read().swrst().bit_is_set() || spi.ctrla.read().swrst().bit_is_set() {}
+
+    // Put the hardware into spi master mode
+    spi.ctrla.modify(|_, w| w.mode().spi_master());
+    // wait for configuration to take effect
+    while spi.syncbusy.read().enable().bit_is_set() {}
+
+    // 8 bit data size and enable the receiver
+    unsafe {
+        spi.ctrlb.modify(|_, w| {
+            w.chsize().bits(0);
+            w.rxen().set_bit()
+        });
+    }
+
+    unsafe {
+        spi.baud.modify(|_, w| w.baud().bits(baud));
+
+        spi.ctrla.modify(|_, w| {
+            match mode.polarity {
+                Polarity::IdleLow => w.cpol().clear_bit(),
+                Polarity::IdleHigh => w.cpol().set_bit(),
+            };
+
+            match mode.phase {
+                Phase::CaptureOnFirstTransition => w.cpha().clear_bit(),
+                Phase::CaptureOnSecondTransition => w.cpha().set_bit(),
+            };
+
+            w.dipo().bits(dipo);
+            w.dopo().bits(dopo);
+
+            // MSB first
+            w.dord().clear_bit()
+        });
+    }
+
+    spi.ctrla.modify(|_, w| w.enable().set_bit());
+    // wait for configuration to take effect
+    while spi.syncbusy.read().enable().bit_is_set() {}
+}
@@ -1,7 +1,10 @@
 use crate::clock;
+use crate::clock_scaling::{rescale_uart_baud, ClockListener};
 use crate::hal::blocking::serial::{write::Default, Write};
 use crate::hal::serial;
+use crate::interrupt_handler::ClearStatus;
 use crate::sercom::v1::pads::CompatiblePad;
+use crate::sercom::v1::{AnyUart, CharSize, Parity, SercomState, StopBits, UartConfig};
 use crate::sercom::v2::*;
 use crate::target_device::sercom0::USART;
 use crate::target_device::{PM, SERCOM0, SERCOM1};
@@ -26,6 +29,12 @@ pub trait RxpoTxpo {
 }
 
 macro_rules! padout {
+    ( ($rxpo:literal, $txpo:literal) => $pad0:ident) => {
+        impl RxpoTxpo for ($pad0,) {
+            const RXPO: u8 = $rxpo;
+            const TXPO: u8 = $txpo;
+        }
+    };
     ( ($rxpo:literal, $txpo:literal) => $pad0:ident, $pad1:ident) => {
         impl RxpoTxpo for ($pad0, $pad1) {
             const RXPO: u8 = $rxpo;
@@ -40,6 +49,12 @@ macro_rules! padout {
     };
 }
 
+// rxpo 0-3 RX on PAD 0-3
+// txpo 0 TX PAD 0
+// txpo 1 TX PAD 2
+// txpo 2 TX PAD 0, RTS PAD 2, CTS PAD 3
+// (rxpo_txpo) => (RX, TX, RTS, CTS) or (RX and TX)
+padout!((0, 0) => Pad0);
 padout!((0, 1) => Pad0, Pad2);
 
 padout!((1, 0) => Pad1, Pad0);
@@ -47,6 +62,7 @@ padout!((1, 2) => Pad1, Pad0, Pad2, Pad3);
 padout!((1, 1) => Pad1, Pad2);
 
 padout!((2, 0) => Pad2, Pad0);
+padout!((2, 1) => Pad2);
 
 padout!((3, 0) => Pad3, Pad0);
 padout!((3, 1) => Pad3, Pad2);
@@ -122,6 +138,45 @@ where
     }
 }
 
+/// A single pad carrying both TX and RX, for half-duplex single-wire buses
+///
+/// The UARTs created with it have `()` as their RX pad, see their
+/// `send_single_wire` method.
+pub struct SingleWire<P>(pub P);
+
+/// Error of a single-wire transfer: another device drove the line while this
+/// one was sending
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Collision;
+
+/// Convert from a single wire to UARTXPadout
+impl<S, PAD> From<SingleWire<PAD>> for Padout<S, (), PAD, (), ()>
+where
+    S: Sercom,
+    PAD: CompatiblePad<Sercom = S>,
+    (PAD::PadNum,): RxpoTxpo,
+{
+    fn from(pad: SingleWire<PAD>) -> Padout<S, (), PAD, (), ()> {
+        Padout {
+            sercom: PhantomData,
+            rx: (),
+            tx: pad.0,
+            rts: (),
+            cts: (),
+        }
+    }
+}
+
+impl<S, PAD> RxpoTxpo for Padout<S, (), PAD, (), ()>
+where
+    S: Sercom,
+    PAD: CompatiblePad<Sercom = S>,
+    (PAD::PadNum,): RxpoTxpo,
+{
+    const RXPO: u8 = <(PAD::PadNum,)>::RXPO;
+    const TXPO: u8 = <(PAD::PadNum,)>::TXPO;
+}
+
 /// Convert from a tuple of (RX, TX) to UARTXPadout
 impl<S, PAD0, PAD1> From<(PAD0, PAD1)> for Padout<S, PAD0, PAD1, (), ()>
 where
@@ -209,6 +264,13 @@ macro_rules! uart {
             pub struct $Type<RX, TX, RTS, CTS> {
                 padout: Padout<$Sercom, RX, TX, RTS, CTS>,
                 sercom: $SERCOM,
+                brk: bool,
+                /// Set once a frame was written, after which TXC is set at
+                /// the end of each transmission
+                sent: bool,
+                /// Set from an auto-baud break until the sync character after
+                /// it is measured
+                sync: bool,
             }
 
             impl<RX, TX, RTS, CTS> $Type<RX, TX, RTS, CTS> {
@@ -219,7 +281,8 @@ macro_rules! uart {
                 ///
                 /// You can use any tuple of two or four SercomXPadY instances
                 /// for which there exists a From implementation for
-                /// UARTXPadout.
+                /// UARTXPadout. With four pads, RTS and CTS are managed by
+                /// the SERCOM. A [`SingleWire`] pad carries both TX and RX.
                 pub fn new<F: Into<Hertz>, T: Into<Padout<$Sercom, RX, TX, RTS, CTS>>>(
                     clock: &clock::$clock,
                     freq: F,
@@ -232,70 +295,19 @@ macro_rules! uart {
 
                     pm.apbcmask.modify(|_, w| w.$powermask().set_bit());
 
-                    // Lots of union fields which require unsafe access
-                    unsafe {
-                        // Reset
-                        sercom.usart().ctrla.modify(|_, w| w.swrst().set_bit());
-                        while sercom.usart().syncbusy.read().swrst().bit_is_set()
-                            || sercom.usart().ctrla.read().swrst().bit_is_set() {
-                            // wait for sync of CTRLA.SWRST
-                        }
-
-                        // Unsafe b/c of direct call to bits on rxpo/txpo
-                        sercom.usart().ctrla.modify(|_, w| {
-                            w.dord().set_bit();
-
-                            let (rxpo, txpo) = padout.rxpo_txpo();
-                            w.rxpo().bits(rxpo);
-                            w.txpo().bits(txpo);
-
-                            w.form().bits(0x00);
-                            w.sampr().bits(0x00); // 16x oversample fractional
-                            w.runstdby().set_bit(); // Run in standby
-                            w.form().bits(0); // 0 is no parity bits
-
-                            w.mode().usart_int_clk() // Internal clock mode
-                        });
-
-                        // Calculate value for BAUD register
-                        let sample_rate: u8 = 16;
-                        let fref = clock.freq().0;
-
-            //          TODO: Support fractional BAUD mode
-            //            let mul_ratio = (fref.0 * 1000) / (freq.into().0 * 16);
-            //
-            //            let baud = mul_ratio / 1000;
-            //            let fp = ((mul_ratio - (baud*1000))*8)/1000;
-            //
-            //            sercom.usart().baud()_frac_mode.modify(|_, w| {
-            //                w.baud().bits(baud as u16);
-            //                w.fp().bits(fp as u8)
-            //            });
-
-                        // Asynchronous arithmetic mode (Table 24-2 in datasheet)
-                        let baud = calculate_baud_value(freq.into().0, fref, sample_rate);
-
-                        sercom.usart().baud().modify(|_, w| {
-                            w.baud().bits(baud)
-                        });
-
-                        sercom.usart().ctrlb.modify(|_, w| {
-                            w.sbmode().clear_bit(); // 0 is one stop bit see sec 25.8.2
-                            w.chsize().bits(0x0);
-                            w.txen().set_bit();
-                            w.rxen().set_bit()
-                        });
-
-                        while sercom.usart().syncbusy.read().ctrlb().bit_is_set() {}
-
-                        sercom.usart().ctrla.modify(|_, w| w.enable().set_bit());
-                        // wait for sync of ENABLE
-                        while sercom.usart().syncbusy.read().enable().bit_is_set() {}
-                    }
+                    init(
+                        sercom.usart(),
+                        padout.rxpo_txpo(),
+                        freq.into().to_Hz(),
+                        clock.freq().to_Hz(),
+                    );
 
                     Self {
                         padout,
                         sercom,
+                        brk: false,
+                        sent: false,
+                        sync: false,
                     }
                 }
 
@@ -310,10 +322,12 @@ macro_rules! uart {
                         [<$Type Tx>] {
                             padout: tx_pads,
                             sercom: self.sercom,
+                            sent: self.sent,
                         },
                         [<$Type Rx>] {
                             padout: rx_pads,
                             sercom: PhantomData,
+                            brk: self.brk,
                         },
                     )
                 }
@@ -323,6 +337,22 @@ macro_rules! uart {
                     Self {
                         padout: [<$Type Padout>]::join(tx.padout, rx.padout),
                         sercom: tx.sercom,
+                        brk: rx.brk,
+                        sent: tx.sent,
+                        sync: false,
+                    }
+                }
+
+                /// Captures the main registers of the SERCOM, for debugging
+                pub fn debug_state(&self) -> SercomState {
+                    let regs = unsafe { self.usart() };
+                    SercomState {
+                        ctrla: regs.ctrla.read().bits(),
+                        ctrlb: regs.ctrlb.read().bits(),
+                        baud: regs.baud().read().bits().into(),
+                        status: regs.status.read().bits(),
+                        intflag: regs.intflag.read().bits(),
+                        syncbusy: regs.syncbusy.read().bits(),
                     }
                 }
 
@@ -360,6 +390,134 @@ macro_rules! uart {
                         self.usart().status.read()
                     }
                 }
+
+                /// Sends a break, holding the line low for 27 bit times.
+                pub fn send_break(&mut self) {
+                    send_break(self.sercom.usart(), &mut self.sent);
+                }
+
+                /// Reads a character of up to 9 bits, see
+                /// [`UartConfig::char_size`]
+                pub fn read_word(&mut self) -> nb::Result<u16, ()> {
+                    [<$Type Rx>]::<RX, CTS>::do_read(self.sercom.usart(), &mut self.brk)
+                }
+
+                /// Writes a character of up to 9 bits, see
+                /// [`UartConfig::char_size`]
+                pub fn write_word(&mut self, word: u16) -> nb::Result<(), ()> {
+                    [<$Type Tx>]::<TX, RTS>::do_write(self.sercom.usart(), &mut self.sent, word)
+                }
+
+                /// Applies the character format and line options of `config`
+                pub fn configure(&mut self, config: UartConfig) {
+                    configure(unsafe { self.usart() }, &config);
+                }
+
+                /// Returns the baud rate measured after a break, with auto-baud
+                /// enabled by [`configure`](Self::configure), once per break.
+                /// `clock` is the SERCOM core clock given to [`new`](Self::new).
+                ///
+                /// The rate is returned once a character is received after the
+                /// break, before it is read. A sync character too far off to
+                /// be measured is dropped, and the baud rate is left
+                /// unchanged.
+                pub fn detected_baud(&mut self, clock: &clock::$clock) -> Option<Hertz> {
+                    detected_baud(self.sercom.usart(), clock.freq().to_Hz(), &mut self.sync)
+                }
+
+                /// Returns true if a break was received since the last call
+                ///
+                /// A break is received as a zero character with a frame
+                /// error, which the reads drop, so it is only seen once the
+                /// characters before it have been read. The frame error
+                /// raises the `error` interrupt.
+                pub fn break_detected(&mut self) -> bool {
+                    core::mem::replace(&mut self.brk, false)
+                }
+
+                /// Returns true if a collision was detected since the last
+                /// call, with collision detection enabled by [`configure`](Self::configure)
+                pub fn collision_detected(&mut self) -> bool {
+                    let usart = unsafe { self.usart() };
+                    let coll = usart.status.read().coll().bit_is_set();
+                    if coll {
+                        usart.status.write(|w| w.coll().set_bit());
+                    }
+                    coll
+                }
+            }
+
+            /// Hardware flow control, with the `(RX, TX, RTS, CTS)` padout
+            ///
+            /// The SERCOM drives RTS high while its receive buffer is full, and
+            /// holds back the next character while CTS is high, so nothing is
+            /// lost at high baud rates even if the other end is slow.
+            impl<RX, TX, RTS, CTS> $Type<RX, TX, RTS, CTS>
+            where
+                RTS: CompatiblePad<Sercom = $Sercom>,
+                CTS: CompatiblePad<Sercom = $Sercom>,
+            {
+                /// Returns true while the CTS pin is high, i.e. while the
+                /// other end does not accept more characters
+                pub fn is_cts_high(&self) -> bool {
+                    unsafe { self.usart() }.status.read().cts().bit_is_set()
+                }
+
+                /// Returns true if the level of the CTS pin changed since the
+                /// last call. The `ctsic` interrupt is raised on each change.
+                pub fn cts_changed(&mut self) -> bool {
+                    let usart = unsafe { self.usart() };
+                    let changed = usart.intflag.read().ctsic().bit_is_set();
+                    if changed {
+                        usart.intflag.write(|w| w.ctsic().set_bit());
+                    }
+                    changed
+                }
+            }
+
+            /// Single-wire half-duplex mode, with the [`SingleWire`] padout
+            ///
+            /// TX and RX share one pad, which the transmitter drives whenever
+            /// it is enabled. It is enabled by [`new`](Self::new), so call
+            /// [`release_line`](Self::release_line) before listening to the
+            /// other devices. The line needs a pull-up while released.
+            impl<TX> $Type<(), TX, (), ()>
+            where
+                TX: CompatiblePad<Sercom = $Sercom>,
+            {
+                /// Sends `bytes`, then releases the line once the last stop
+                /// bit is out, in time for the reply of the other device
+                ///
+                /// The receiver sees the characters sent, which are dropped.
+                /// Returns [`Collision`] if a collision was detected, with
+                /// collision detection enabled by [`configure`](Self::configure).
+                pub fn send_single_wire(&mut self, bytes: &[u8]) -> Result<(), Collision> {
+                    self.sent |= !bytes.is_empty();
+                    send_single_wire(self.sercom.usart(), bytes)
+                }
+
+                /// Disables the transmitter right away, without waiting for
+                /// the characters written
+                pub fn release_line(&mut self) {
+                    set_transmitter(unsafe { self.usart() }, false);
+                }
+            }
+
+            impl<RX, TX, RTS, CTS> AnyUart for $Type<RX, TX, RTS, CTS> {
+                type Sercom = $Sercom;
+
+                fn sent(&mut self) -> &mut bool {
+                    &mut self.sent
+                }
+            }
+
+            impl<RX, TX, RTS, CTS> ClockListener for $Type<RX, TX, RTS, CTS> {
+                /// Restores the baud rate after the SERCOM core clock changed
+                fn clock_changed(&mut self, old: Hertz, new: Hertz) {
+                    let usart = unsafe { self.usart() };
+                    let baud = usart.baud().read().baud().bits();
+                    set_baud(usart, rescale_uart_baud(baud, old, new));
+                }
             }
 
             /// The transmitting half of the corresponding UARTX instance (as returned by `UARTX::split`)
@@ -368,6 +526,7 @@ macro_rules! uart {
                 /// We store the SERCOM object here so we can retrieve it later,
                 /// but conceptually, ownership is shared between the Rx and Tx halves.
                 sercom: $SERCOM,
+                sent: bool,
             }
 
             impl<TX, RTS> [<$Type Tx>]<TX, RTS> {
@@ -378,17 +537,18 @@ macro_rules! uart {
                     return &self.sercom.usart();
                 }
 
-                fn do_write(usart: &USART, word: u8) -> nb::Result<(), ()> {
+                fn do_write(usart: &USART, sent: &mut bool, word: u16) -> nb::Result<(), ()> {
                     unsafe {
                         if !usart.intflag.read().dre().bit_is_set() {
                             return Err(nb::Error::WouldBlock);
                         }
 
                         usart.data.write(|w| {
-                            w.bits(word as u16)
+                            w.bits(word)
                         });
                     }
 
+                    *sent = true;
                     Ok(())
                 }
 
@@ -402,11 +562,24 @@ macro_rules! uart {
                 }
             }
 
+            impl<TX, RTS> [<$Type Tx>]<TX, RTS> {
+                /// Sends a break, holding the line low for 27 bit times.
+                pub fn send_break(&mut self) {
+                    send_break(self.sercom.usart(), &mut self.sent);
+                }
+
+                /// Writes a character of up to 9 bits, see
+                /// [`UartConfig::char_size`]
+                pub fn write_word(&mut self, word: u16) -> nb::Result<(), ()> {
+                    Self::do_write(self.sercom.usart(), &mut self.sent, word)
+                }
+            }
+
             impl<TX, RTS> serial::Write<u8> for [<$Type Tx>]<TX, RTS> {
                 type Error = ();
 
                 fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
-                    Self::do_write(unsafe { self.usart() }, word)
+                    Self::do_write(self.sercom.usart(), &mut self.sent, word as u16)
                 }
 
                 fn flush(&mut self) -> nb::Result<(), Self::Error> {
@@ -418,7 +591,7 @@ macro_rules! uart {
                 type Error = ();
 
                 fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
-                    [<$Type Tx>]::<TX, RTS>::do_write(unsafe { self.usart() }, word)
+                    [<$Type Tx>]::<TX, RTS>::do_write(self.sercom.usart(), &mut self.sent, word as u16)
                 }
 
                 fn flush(&mut self) -> nb::Result<(), Self::Error> {
@@ -430,26 +603,67 @@ macro_rules! uart {
             pub struct [<$Type Rx>]<RX, CTS> {
                 padout: RxPadout<$Sercom, RX, CTS>,
                 sercom: PhantomData<$SERCOM>,
+                brk: bool,
             }
 
             impl<RX, CTS> [<$Type Rx>]<RX, CTS> {
                 /// # Safety
                 ///
                 /// Only this struct instance should be able to access RX-related fields on this SERCOM.
-                unsafe fn usart(&self) -> &USART {
+                unsafe fn usart(&self) -> &'static USART {
                     (*$SERCOM::ptr()).usart()
                 }
 
-                fn do_read(usart: &USART) -> nb::Result<u8, ()> {
+                fn do_read(usart: &USART, brk: &mut bool) -> nb::Result<u16, ()> {
                     let has_data = usart.intflag.read().rxc().bit_is_set();
 
                     if !has_data {
                         return Err(nb::Error::WouldBlock);
                     }
 
+                    let ferr = usart.status.read().ferr().bit_is_set();
                     let data = usart.data.read().bits();
 
-                    Ok(data as u8)
+                    // A frame error occurred, so discard the byte. A zero
+                    // with a frame error is a break.
+                    if ferr {
+                        usart.status.write(|w| w.ferr().set_bit());
+                        if data == 0 {
+                            *brk = true;
+                        }
+                        return Err(nb::Error::WouldBlock);
+                    }
+
+                    Ok(data)
+                }
+            }
+
+            impl<RX, CTS> [<$Type Rx>]<RX, CTS> {
+                fn do_clear_status(usart: &USART) {
+                    usart.status.write(|w| {
+                        w.perr().set_bit();
+                        w.ferr().set_bit();
+                        w.bufovf().set_bit();
+                        w.isf().set_bit();
+                        w.coll().set_bit()
+                    });
+                    usart.intflag.write(|w| w.error().set_bit());
+                }
+
+                /// Reads a character of up to 9 bits, see
+                /// [`UartConfig::char_size`]
+                pub fn read_word(&mut self) -> nb::Result<u16, ()> {
+                    Self::do_read(unsafe { self.usart() }, &mut self.brk)
+                }
+
+                /// Returns true if a break was received since the last call
+                ///
+                /// A break is received as a zero character with a frame
+                /// error, which the reads drop, so it is only seen once the
+                /// characters before it have been read. The frame error
+                /// raises the `error` interrupt.
+                pub fn break_detected(&mut self) -> bool {
+                    core::mem::replace(&mut self.brk, false)
                 }
             }
 
@@ -457,7 +671,7 @@ macro_rules! uart {
                 type Error = ();
 
                 fn read(&mut self) -> nb::Result<u8, Self::Error> {
-                    Self::do_read(unsafe { self.usart() })
+                    Self::do_read(unsafe { self.usart() }, &mut self.brk).map(|word| word as u8)
                 }
             }
 
@@ -465,7 +679,120 @@ macro_rules! uart {
                 type Error = ();
 
                 fn read(&mut self) -> nb::Result<u8, Self::Error> {
-                    [<$Type Rx>]::<RX, CTS>::do_read(self.sercom.usart())
+                    [<$Type Rx>]::<RX, CTS>::do_read(self.sercom.usart(), &mut self.brk).map(|word| word as u8)
+                }
+            }
+
+            impl<RX, CTS> ClearStatus for [<$Type Rx>]<RX, CTS> {
+                fn clear_status(&mut self) {
+                    Self::do_clear_status(unsafe { self.usart() });
+                }
+            }
+
+            impl<RX, TX, RTS, CTS> ClearStatus for $Type<RX, TX, RTS, CTS> {
+                fn clear_status(&mut self) {
+                    [<$Type Rx>]::<RX, CTS>::do_clear_status(self.sercom.usart());
+                }
+            }
+
+            #[cfg(feature = "dma")]
+            unsafe impl<TX, RTS> crate::dmac::Buffer for [<$Type Tx>]<TX, RTS> {
+                type Beat = u8;
+
+                #[inline]
+                fn dma_ptr(&mut self) -> *mut Self::Beat {
+                    unsafe { self.usart() }.data.as_ptr() as *mut _
+                }
+
+                #[inline]
+                fn incrementing(&self) -> bool {
+                    false
+                }
+
+                #[inline]
+                fn buffer_len(&self) -> usize {
+                    1
+                }
+            }
+
+            #[cfg(feature = "dma")]
+            impl<TX, RTS> [<$Type Tx>]<TX, RTS> {
+                /// Transform the transmitting half into a DMA
+                /// [`Transfer`](crate::dmac::Transfer) sending `buf`
+                ///
+                /// The transmitter is given back by `wait` or `stop` once the
+                /// transfer is complete. `buf` must not be empty.
+                #[inline]
+                pub fn send_with_dma<Chan, B>(
+                    mut self,
+                    buf: B,
+                    channel: Chan,
+                ) -> crate::dmac::Transfer<
+                    crate::dmac::Channel<crate::dmac::ChannelId<Chan>, crate::dmac::Busy>,
+                    crate::dmac::BufferPair<B, Self>,
+                >
+                where
+                    Chan: crate::dmac::AnyChannel<Status = crate::dmac::Ready>,
+                    B: crate::dmac::Buffer<Beat = u8> + 'static,
+                {
+                    debug_assert!(buf.buffer_len() > 0);
+                    // TXC is set at the end of the transfer
+                    self.sent = true;
+
+                    // SAFETY: The source buffer is 'static, and the
+                    // transmitter is moved into the transfer
+                    unsafe { crate::dmac::Transfer::new_unchecked(channel, buf, self, false) }
+                        .begin($Sercom::DMA_TX_TRIGGER, crate::dmac::TriggerAction::BEAT)
+                }
+            }
+
+            #[cfg(feature = "dma")]
+            unsafe impl<RX, CTS> crate::dmac::Buffer for [<$Type Rx>]<RX, CTS> {
+                type Beat = u8;
+
+                #[inline]
+                fn dma_ptr(&mut self) -> *mut Self::Beat {
+                    unsafe { self.usart() }.data.as_ptr() as *mut _
+                }
+
+                #[inline]
+                fn incrementing(&self) -> bool {
+                    false
+                }
+
+                #[inline]
+                fn buffer_len(&self) -> usize {
+                    1
+                }
+            }
+
+            #[cfg(feature = "dma")]
+            impl<RX, CTS> [<$Type Rx>]<RX, CTS> {
+                /// Transform the receiving half into a DMA
+                /// [`Transfer`](crate::dmac::Transfer) filling `buf`
+                ///
+                /// The receiver is given back by `wait` or `stop`. The DMAC
+                /// doesn't see frame errors, so characters received with an
+                /// error are stored like the others.
+                #[inline]
+                pub fn receive_with_dma<Chan, B>(
+                    self,
+                    buf: B,
+                    channel: Chan,
+                ) -> crate::dmac::Transfer<
+                    crate::dmac::Channel<crate::dmac::ChannelId<Chan>, crate::dmac::Busy>,
+                    crate::dmac::BufferPair<Self, B>,
+                >
+                where
+                    Chan: crate::dmac::AnyChannel<Status = crate::dmac::Ready>,
+                    B: crate::dmac::Buffer<Beat = u8> + 'static,
+                {
+                    debug_assert!(buf.buffer_len() > 0);
+
+                    // SAFETY: The destination buffer is 'static, and the
+                    // receiver is moved into the transfer
+                    unsafe { crate::dmac::Transfer::new_unchecked(channel, self, buf, false) }
+                        .begin($Sercom::DMA_RX_TRIGGER, crate::dmac::TriggerAction::BEAT)
                 }
             }
 
@@ -501,11 +828,227 @@ uart!(UART5: (Sercom5, SERCOM5, sercom5_, Sercom5CoreClock));
 
 const SHIFT: u8 = 32;
 
+/// Returns the BAUD value of the asynchronous arithmetic mode, whose rate is
+/// `clk_freq * (65536 - BAUD) / (n_samples * 65536)`. A rate out of reach,
+/// or a stopped clock, gives the slowest rate.
 fn calculate_baud_value(baudrate: u32, clk_freq: u32, n_samples: u8) -> u16 {
     let sample_rate = (n_samples as u64 * baudrate as u64) << 32;
-    let ratio = sample_rate / clk_freq as u64;
+    let ratio = sample_rate
+        .checked_div(clk_freq as u64)
+        .map_or(1 << SHIFT, |ratio| ratio.min(1 << SHIFT));
     let scale = (1u64 << SHIFT) - ratio;
     let baud_calculated = (65536u64 * scale) >> SHIFT;
 
     baud_calculated as u16
 }
+
+/// Resets the USART and enables it with the pads `rxpo_txpo`, at `baud_hz`
+/// from a `clk_freq` clock, with 8 data bits, no parity and one stop bit
+///
+/// This is not generic, so that a single copy is shared by all of the UARTs
+/// and pad mappings, rather than one for each combination. This matters on
+/// the SAMD11, with only 16 kB of flash.
+#[inline(never)]
+fn init(usart: &USART, (rxpo, txpo): (u8, u8), baud_hz: u32, clk_freq: u32) {
+    // Reset
+    usart.ctrla.modify(|_, w| w.swrst().set_bit());
+    while usart.syncbusy.read().swrst().bit_is_set() || usart.ctrla.read().swrst().bit_is_set() {
+        // wait for sync of CTRLA.SWRST
+    }
+
+    // Unsafe b/c of direct call to bits on rxpo/txpo
+    usart.ctrla.modify(|_, w| unsafe {
+        w.dord().set_bit();
+
+        w.rxpo().bits(rxpo);
+        w.txpo().bits(txpo);
+
+        w.form().bits(0x00);
+        w.sampr().bits(0x00); // 16x oversample fractional
+        w.runstdby().set_bit(); // Run in standby
+        w.form().bits(0); // 0 is no parity bits
+
+        w.mode().usart_int_clk() // Internal clock mode
+    });
+
+    // Calculate value for BAUD register
+    let sample_rate: u8 = 16;
+
+    // TODO: Support fractional BAUD mode
+    //   let mul_ratio = (fref.0 * 1000) / (freq.into().0 * 16);
+    //
+    //   let baud = mul_ratio / 1000;
+    //   let fp = ((mul_ratio - (baud*1000))*8)/1000;
+    //
+    //   sercom.usart().baud()_frac_mode.modify(|_, w| {
+    //       w.baud().bits(baud as u16);
+    //       w.fp().bits(fp as u8)
+    //   });
+
+    // Asynchronous arithmetic mode (Table 24-2 in datasheet)
+    let baud = calculate_baud_value(baud_hz, clk_freq, sample_rate);
+
+    usart.baud().modify(|_, w| unsafe { w.baud().bits(baud) });
+
+    usart.ctrlb.modify(|_, w| unsafe {
+        w.sbmode().clear_bit(); // 0 is one stop bit see sec 25.8.2
+        w.chsize().bits(0x0);
+        w.txen().set_bit();
+        w.rxen().set_bit()
+    });
+
+    while usart.syncbusy.read().ctrlb().bit_is_set() {}
+
+    usart.ctrla.modify(|_, w| w.enable().set_bit());
+    // wait for sync of ENABLE
+    while usart.syncbusy.read().enable().bit_is_set() {}
+}
+
+/// Sends a break: the line is held low for 27 bit times, followed by 3 bit
+/// times high, which also satisfies the mark-after-break of most protocols.
+///
+/// The SERCOM has no break generation, so a zero is sent at a third of the
+/// baud rate. `sent` tells whether a frame was written before, in which case
+/// the end of its transmission is awaited. Otherwise TXC is never set.
+pub(crate) fn send_break(usart: &USART, sent: &mut bool) {
+    // Wait for the end of the ongoing transmission
+    if *sent {
+        while usart.intflag.read().txc().bit_is_clear() {}
+    }
+    *sent = true;
+
+    // BAUD is enable-protected. In arithmetic mode, the baud rate is
+    // proportional to 65536 - BAUD.
+    let baud = usart.baud().read().baud().bits();
+    let slow = (65536 - (65536 - baud as u32) / 3) as u16;
+    set_baud(usart, slow);
+    // Writing DATA clears TXC
+    usart.data.write(|w| unsafe { w.bits(0) });
+    while usart.intflag.read().txc().bit_is_clear() {}
+    set_baud(usart, baud);
+}
+
+fn set_baud(usart: &USART, baud: u16) {
+    while_disabled(usart, || {
+        usart.baud().write(|w| unsafe { w.baud().bits(baud) });
+    });
+}
+
+fn configure(usart: &USART, config: &UartConfig) {
+    while_disabled(usart, || {
+        usart
+            .ctrla
+            .modify(|_, w| unsafe { w.form().bits(form(config)) });
+        usart.ctrlb.modify(|_, w| unsafe {
+            w.chsize().bits(match config.char_size {
+                CharSize::Eight => 0,
+                CharSize::Nine => 1,
+            });
+            w.pmode().bit(config.parity == Parity::Odd);
+            w.sbmode().bit(config.stop_bits == StopBits::Two);
+            w.colden().bit(config.collision_detection);
+            w.enc().bit(config.irda.is_some())
+        });
+        while usart.syncbusy.read().ctrlb().bit_is_set() {}
+        if let Some(rx_pulse_length) = config.irda {
+            usart
+                .rxpl
+                .write(|w| unsafe { w.rxpl().bits(rx_pulse_length) });
+        }
+    });
+}
+
+/// Enables or disables the transmitter, which drives TX while enabled
+fn set_transmitter(usart: &USART, enabled: bool) {
+    usart.ctrlb.modify(|_, w| w.txen().bit(enabled));
+    while usart.syncbusy.read().ctrlb().bit_is_set() {}
+}
+
+/// Sends `bytes` with the transmitter enabled, then releases the line. The
+/// characters received meanwhile are the echo of those sent, and are
+/// dropped.
+fn send_single_wire(usart: &USART, bytes: &[u8]) -> Result<(), Collision> {
+    let drop_echo = || {
+        while usart.intflag.read().rxc().bit_is_set() {
+            usart.data.read();
+        }
+    };
+
+    set_transmitter(usart, true);
+    for &byte in bytes {
+        while usart.intflag.read().dre().bit_is_clear() {
+            drop_echo();
+        }
+        usart.data.write(|w| unsafe { w.bits(byte as u16) });
+    }
+    // Writing DATA cleared TXC, which is set again once the shift register
+    // is empty
+    if !bytes.is_empty() {
+        while usart.intflag.read().txc().bit_is_clear() {
+            drop_echo();
+        }
+    }
+    set_transmitter(usart, false);
+    drop_echo();
+
+    let status = usart.status.read();
+    usart
+        .status
+        .write(|w| w.coll().set_bit().ferr().set_bit().bufovf().set_bit());
+    if status.coll().bit_is_set() {
+        Err(Collision)
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the CTRLA.FORM value of `config`
+fn form(config: &UartConfig) -> u8 {
+    // 1 is USART frame with parity, 4 and 5 the same with auto-baud
+    ((config.auto_baud as u8) << 2) | (config.parity != Parity::None) as u8
+}
+
+/// Returns the rate the BAUD register is set to, in the asynchronous
+/// arithmetic mode, the inverse of `calculate_baud_value`
+fn baud_rate(baud: u16, clk_freq: u32, n_samples: u8) -> u32 {
+    ((clk_freq as u64 * (65536 - baud as u64)) / (n_samples as u64 * 65536)) as u32
+}
+
+/// Returns the baud rate measured on the sync character that followed the
+/// last break, with auto-baud enabled. `sync` is set from the break until the
+/// sync character is measured.
+fn detected_baud(usart: &USART, clk_freq: u32, sync: &mut bool) -> Option<Hertz> {
+    // RXBRK is set as soon as the break is detected, before the sync
+    // character is measured
+    if usart.intflag.read().rxbrk().bit_is_set() {
+        usart.intflag.write(|w| w.rxbrk().set_bit());
+        *sync = true;
+    }
+    if !*sync {
+        return None;
+    }
+    // The sync character did not give a valid rate, and BAUD is unchanged
+    if usart.status.read().isf().bit_is_set() {
+        usart.status.write(|w| w.isf().set_bit());
+        *sync = false;
+        return None;
+    }
+    // The first character completed after the break comes once the sync
+    // character is measured, so BAUD holds the new rate by then. A character
+    // with a frame error is the break itself.
+    if usart.intflag.read().rxc().bit_is_clear() || usart.status.read().ferr().bit_is_set() {
+        return None;
+    }
+    *sync = false;
+    let baud = usart.baud().read().baud().bits();
+    Some(Hertz::from_raw(baud_rate(baud, clk_freq, 16)))
+}
+
+/// Runs `f` with the USART disabled, to write its enable-protected registers
+fn while_disabled<F: FnOnce()>(usart: &USART, f: F) {
+    usart.ctrla.modify(|_, w| w.enable().clear_bit());
+    while usart.syncbusy.read().enable().bit_is_set() {}
+    f();
+    usart.ctrla.modify(|_, w| w.enable().set_bit());
+    while usart.syncbusy.read().enable().bit_is_set() {}
+}
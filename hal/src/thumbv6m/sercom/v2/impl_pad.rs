@@ -82,6 +82,31 @@ macro_rules! pad_table {
                 }
             );
         )+
+
+        /// Value-level version of [`GetPad`], used by
+        /// [`DynPad`](crate::sercom::v2::DynPad)
+        ///
+        /// Returns the alternate function of the pin with the given ID, if it
+        /// can act as the given pad of the given SERCOM.
+        pub(crate) fn pad_mode(id: DynPinId, sercom: usize, padnum: usize) -> Option<DynAlternate> {
+            $(
+                $( #[$id_cfg] )?
+                {
+                    $(
+                        $( #[$sercom_cfg] )?
+                        {
+                            if id == <$PinId as PinId>::DYN
+                                && sercom == <$Sercom as Sercom>::NUM
+                                && padnum == <$PadNum as PadNum>::NUM
+                            {
+                                return Some(DynAlternate::$Cfg);
+                            }
+                        }
+                    )+
+                }
+            )+
+            None
+        }
     };
 }
 
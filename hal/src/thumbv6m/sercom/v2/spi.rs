@@ -98,12 +98,12 @@ type Pads = spi::PadsFromIds<Sercom0, PA08, NoneT, PA09>;
 //! configuration. Users are responsible for correctly configuring the GCLK.
 //!
 //! ```
-//! use atsamd_hal::time::U32Ext;
+//! use atsamd_hal::time::RateExtU32;
 //!
 //! let pm = peripherals.PM;
 //! let sercom = peripherals.SERCOM0;
 //! // Configure GCLK for 10 MHz
-//! let freq = 10.mhz();
+//! let freq = 10.MHz();
 //! let config = spi::Config::new(&pm, sercom, pads, freq);
 //! ```
 //!
@@ -116,7 +116,7 @@ type Pads = spi::PadsFromIds<Sercom0, PA08, NoneT, PA09>;
 //! use atsamd_hal::sercom::v2::spi::NineBit;
 //!
 //! let spi = spi::Config::new(&mclk, sercom, pads, freq)
-//!     .baud(1.mhz())
+//!     .baud(1.MHz())
 //!     .char_size::<NineBit>()
 //!     .msb_first(false)
 //!     .spi_mode(MODE_1)
@@ -174,6 +174,10 @@ struct implements the DMAC [`Buffer`] trait. The provided [`send_with_dma`]
 and [`receive_with_dma`] methods will build and begin a [`dmac::Transfer`]
 to create a non-blocking SPI transfer.
 
+These methods are also available in [`Slave`] [`OpMode`], where the external
+master paces the transfer. Enable [`Config::preload`], so that the first word
+is loaded before the master selects the slave.
+
 Optionally, interrupts can be enabled on the provided [`Channel`]. Note that
 the `dma` feature must be enabled. Refer to the [`dmac`] module-level
 documentation for more information.
@@ -192,7 +196,17 @@ let dma_transfer = spi.send_with_dma(&mut buffer, channel, ());
 let (chan0, _, spi, _) = dma_transfer.wait();
 ```
 
+[`duplex_with_dma`] sends and receives at the same time, on two channels:
+
+```
+let (chan0, chan1, spi, rx, tx) = spi
+    .duplex_with_dma(&mut RX_BUFFER, &mut TX_BUFFER, chan0, chan1)
+    .unwrap()
+    .wait();
+```
+
 [`Buffer`]: crate::dmac::transfer::Buffer
+[`duplex_with_dma`]: Spi::duplex_with_dma
 [`send_with_dma`]: Spi::send_with_dma
 [`receive_with_dma`]: Spi::receive_with_dma
 [`dmac::Transfer`]: crate::dmac::Transfer
@@ -210,7 +224,7 @@ use embedded_hal::serial::{Read, Write};
 use embedded_hal::spi::{self, FullDuplex};
 pub use embedded_hal::spi::{Phase, Polarity, MODE_0, MODE_1, MODE_2, MODE_3};
 use nb::Error::WouldBlock;
-use num_traits::{AsPrimitive, PrimInt};
+use num_traits::{AsPrimitive, PrimInt, Zero};
 
 use crate::target_device as pac;
 use pac::sercom0::spi::ctrla::MODE_A;
@@ -1123,7 +1137,7 @@ where
     #[inline]
     pub fn baud<B: Into<Hertz>>(self, baud: B) -> Self {
         let baud: Hertz = baud.into();
-        let baud = (self.freq.0 / 2 / baud.0).saturating_sub(1);
+        let baud = (self.freq.to_Hz() / 2 / baud.to_Hz()).saturating_sub(1);
         let baud = if baud <= u8::MAX as u32 {
             baud as u8
         } else {
@@ -1184,7 +1198,10 @@ where
         while self.sercom.spi().syncbusy.read().ctrlb().bit_is_set() {}
         self.sercom.spi().ctrla.modify(|_, w| w.enable().set_bit());
         while self.sercom.spi().syncbusy.read().enable().bit_is_set() {}
-        Spi { config: self }
+        Spi {
+            config: self,
+            in_flight: false,
+        }
     }
 
     /// Enable or disable the SERCOM peripheral, and wait for the ENABLE bit to
@@ -1198,6 +1215,40 @@ where
     }
 }
 
+impl<P, C> Config<P, Slave, C>
+where
+    P: ValidPads,
+    C: CharSize,
+{
+    /// Enable the detection of `SS` going low
+    ///
+    /// When set, the [`Flags::SSL`] flag is raised as soon as the master
+    /// selects the slave. In standby, this wakes the device even before the
+    /// first word is received.
+    #[inline]
+    pub fn ss_detect(self, enable: bool) -> Self {
+        self.sercom.spi().ctrlb.modify(|_, w| w.ssde().bit(enable));
+        while self.sercom.spi().syncbusy.read().ctrlb().bit_is_set() {}
+        self
+    }
+
+    /// Enable preloading of the transmit shift register
+    ///
+    /// When set, a word written to `DATA` while `SS` is high is moved to the
+    /// shift register right away, so it is sent from the first clock edge
+    /// after `SS` goes low. Otherwise, the first word sent in a transaction is
+    /// whatever was left in the shift register. See [`Spi::load_tx`].
+    #[inline]
+    pub fn preload(self, enable: bool) -> Self {
+        self.sercom
+            .spi()
+            .ctrlb
+            .modify(|_, w| w.ploaden().bit(enable));
+        while self.sercom.spi().syncbusy.read().ctrlb().bit_is_set() {}
+        self
+    }
+}
+
 //=============================================================================
 // AnyConfig
 //=============================================================================
@@ -1336,6 +1387,8 @@ where
 /// [`SpiFuture`]: crate::sercom::v2::spi_future::SpiFuture
 pub struct Spi<C: ValidConfig> {
     config: C,
+    /// Set when a word is written to DATA, cleared by `flush_blocking`
+    in_flight: bool,
 }
 
 impl<C: ValidConfig> Spi<C> {
@@ -1442,7 +1495,8 @@ impl<C: ValidConfig> Spi<C> {
     /// module.
     #[inline]
     pub unsafe fn write_data(&mut self, data: u16) {
-        self.sercom().spi().data.write(|w| w.data().bits(data))
+        self.in_flight = true;
+        self.sercom().spi().data.write(|w| w.data().bits(data));
     }
 
     /// Disable the SPI peripheral and return the [`Config`] struct
@@ -1458,6 +1512,87 @@ impl<C: ValidConfig> Spi<C> {
     }
 }
 
+/// Responding to an external master in [`Slave`] [`OpMode`]
+///
+/// The master drives the clock, so these methods never wait. Instead, they
+/// are meant to be called when the matching [`Flags`] are raised, typically
+/// from the interrupt handler of the SERCOM:
+///
+/// - `SSL`, with [`Config::ss_detect`], when the master selects the slave
+/// - `DRE`, when the next word can be loaded with [`Spi::load_tx`]
+/// - `RXC`, when a word can be taken with [`Spi::read_rx`]
+/// - `TXC`, when `SS` goes high again at the end of a transaction
+///
+/// The [`dmac`](crate::dmac) can serve `DRE` and `RXC` instead, with the
+/// `send_with_dma` and `receive_with_dma` methods.
+impl<P, C> Spi<Config<P, Slave, C>>
+where
+    Config<P, Slave, C>: ValidConfig,
+    P: ValidPads,
+    C: CharSize,
+    C::Word: PrimInt + AsPrimitive<u16>,
+    u16: AsPrimitive<C::Word>,
+{
+    /// Load the next word to send to the master, if `DATA` is empty
+    ///
+    /// With [`Config::preload`], a word loaded while the slave is not selected
+    /// is the first one sent in the next transaction.
+    #[inline]
+    pub fn load_tx(&mut self, word: C::Word) -> nb::Result<(), Error>
+    where
+        P: Tx,
+    {
+        if self.read_flags_errors()?.contains(Flags::DRE) {
+            unsafe { self.write_data(word.as_()) };
+            Ok(())
+        } else {
+            Err(WouldBlock)
+        }
+    }
+
+    /// Take the last word received from the master, if any
+    ///
+    /// Unlike [`Read`], this is available when the [`Pads`] are also [`Tx`],
+    /// since receiving does not depend on sending in [`Slave`] [`OpMode`].
+    #[inline]
+    pub fn read_rx(&mut self) -> nb::Result<C::Word, Error>
+    where
+        P: Rx,
+    {
+        if self.read_flags_errors()?.contains(Flags::RXC) {
+            unsafe { Ok(self.read_data().as_()) }
+        } else {
+            Err(WouldBlock)
+        }
+    }
+
+    /// Return whether the master selected the slave since the last call, and
+    /// clear the `SSL` flag
+    ///
+    /// This requires [`Config::ss_detect`].
+    #[inline]
+    pub fn take_selected(&mut self) -> bool {
+        let selected = self.read_flags().contains(Flags::SSL);
+        if selected {
+            self.clear_flags(Flags::SSL);
+        }
+        selected
+    }
+
+    /// Return whether the master deselected the slave since the last call,
+    /// and clear the `TXC` flag
+    ///
+    /// In [`Slave`] [`OpMode`], `TXC` is raised when `SS` goes high.
+    #[inline]
+    pub fn take_deselected(&mut self) -> bool {
+        let deselected = self.read_flags().contains(Flags::TXC);
+        if deselected {
+            self.clear_flags(Flags::TXC);
+        }
+        deselected
+    }
+}
+
 impl<C> Spi<C>
 where
     C: ValidConfig,
@@ -1473,6 +1608,71 @@ where
     }
 }
 
+/// Blocking transfers with the semantics of `embedded-hal` 1.0
+///
+/// These mirror `SpiBus::transfer_in_place`, `SpiBus::transfer` and
+/// `SpiBus::flush` from `embedded-hal` 1.0. The latter two are named
+/// [`transfer_split`](Spi::transfer_split) and
+/// [`flush_blocking`](Spi::flush_blocking) here, so they don't shadow the
+/// methods of the `embedded-hal` 0.2 traits.
+impl<C> Spi<C>
+where
+    C: ValidConfig,
+    C::Pads: Rx,
+    C::OpMode: MasterMode,
+    C::Word: PrimInt + AsPrimitive<u16>,
+    u16: AsPrimitive<C::Word>,
+{
+    /// Send each word of `words` and replace it with the word received
+    #[inline]
+    pub fn transfer_in_place(&mut self, words: &mut [C::Word]) -> Result<(), Error> {
+        for word in words.iter_mut() {
+            *word = self.exchange(*word)?;
+        }
+        Ok(())
+    }
+
+    /// Send the words of `write` while receiving into `read`
+    ///
+    /// The transaction lasts for the longer of the two slices. If `write` is
+    /// shorter, zeros are sent for the remaining words. If `read` is shorter,
+    /// the remaining words received are discarded.
+    #[inline]
+    pub fn transfer_split(&mut self, read: &mut [C::Word], write: &[C::Word]) -> Result<(), Error> {
+        let len = read.len().max(write.len());
+        for i in 0..len {
+            let word = write.get(i).copied().unwrap_or_else(C::Word::zero);
+            let word = self.exchange(word)?;
+            if let Some(r) = read.get_mut(i) {
+                *r = word;
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait until the last word has been shifted out and the bus is idle
+    ///
+    /// This waits on the `TXC` flag. It returns immediately if no word was
+    /// written since the last flush.
+    #[inline]
+    pub fn flush_blocking(&mut self) -> Result<(), Error> {
+        if !self.in_flight {
+            return Ok(());
+        }
+        while !self.read_flags_errors()?.contains(Flags::TXC) {}
+        self.in_flight = false;
+        Ok(())
+    }
+
+    #[inline]
+    fn exchange(&mut self, word: C::Word) -> Result<C::Word, Error> {
+        while !self.read_flags_errors()?.contains(Flags::DRE) {}
+        unsafe { self.write_data(word.as_()) };
+        while !self.read_flags_errors()?.contains(Flags::RXC) {}
+        Ok(unsafe { self.read_data().as_() })
+    }
+}
+
 //=============================================================================
 // AnySpi
 //=============================================================================
@@ -1546,14 +1746,14 @@ mod spi_dma {
     use crate::dmac::{
         self,
         channel::{self, Busy, Channel, ChannelId, Ready},
-        transfer, Transfer,
+        transfer, ChId, Transfer,
     };
 
     unsafe impl<P, M, C> dmac::transfer::Buffer for Spi<Config<P, M, C>>
     where
         Config<P, M, C>: ValidConfig,
         P: ValidPads,
-        M: MasterMode,
+        M: OpMode,
         C: CharSize,
         C::Word: dmac::transfer::Beat,
     {
@@ -1580,7 +1780,7 @@ mod spi_dma {
         Self: dmac::transfer::Buffer<Beat = C::Word>,
         Config<P, M, C>: ValidConfig,
         P: Tx,
-        M: MasterMode,
+        M: OpMode,
         C: CharSize,
         C::Word: dmac::transfer::Beat,
     {
@@ -1619,7 +1819,7 @@ mod spi_dma {
         Self: dmac::transfer::Buffer<Beat = C::Word>,
         Config<P, M, C>: ValidConfig,
         P: Rx,
-        M: MasterMode,
+        M: OpMode,
         C: CharSize,
         C::Word: dmac::transfer::Beat,
     {
@@ -1652,6 +1852,172 @@ mod spi_dma {
                 )
         }
     }
+
+    impl<P, M, C> Spi<Config<P, M, C>>
+    where
+        Self: dmac::transfer::Buffer<Beat = C::Word>,
+        Config<P, M, C>: ValidConfig,
+        P: Rx + Tx,
+        M: OpMode,
+        C: CharSize,
+        C::Word: dmac::transfer::Beat,
+    {
+        /// Transform an [`Spi`] into a [`DuplexTransfer`], sending `tx_buf`
+        /// while receiving into `rx_buf`.
+        ///
+        /// The buffers must have the same length, unless one of them is a
+        /// single word: a single word transmit buffer is sent repeatedly, and
+        /// a single word receive buffer only keeps the last word received.
+        /// Returns an error if the lengths do not match.
+        ///
+        /// The receive channel is started first, so that it is ready when the
+        /// first word comes in.
+        #[inline]
+        pub fn duplex_with_dma<RxChan, TxChan, RxB, TxB>(
+            mut self,
+            rx_buf: RxB,
+            tx_buf: TxB,
+            mut rx_channel: RxChan,
+            tx_channel: TxChan,
+        ) -> dmac::Result<DuplexTransfer<Self, ChannelId<RxChan>, ChannelId<TxChan>, RxB, TxB>>
+        where
+            RxChan: channel::AnyChannel<Status = Ready>,
+            TxChan: channel::AnyChannel<Status = Ready>,
+            RxB: dmac::Buffer<Beat = C::Word> + 'static,
+            TxB: dmac::Buffer<Beat = C::Word> + 'static,
+        {
+            let rx_len = rx_buf.buffer_len();
+            let tx_len = tx_buf.buffer_len();
+            if rx_len > 1 && tx_len > 1 && rx_len != tx_len {
+                return Err(dmac::Error::LengthMismatch);
+            }
+            let len = rx_len.max(tx_len);
+
+            // Drop anything left over in the receive buffer, which would
+            // otherwise be the first word received
+            while self.read_flags().contains(Flags::RXC) {
+                unsafe {
+                    self.read_data();
+                }
+            }
+
+            // The receive channel completes last, after the last word has
+            // been shifted in
+            rx_channel
+                .as_mut()
+                .enable_interrupts(dmac::channel::InterruptFlags::new().with_tcmpl(true));
+
+            let data = unsafe { self.sercom().spi().data.as_ptr() as *mut C::Word };
+            // SAFETY: Both buffers are 'static, and the transfers are never
+            // dropped while running
+            let rx = unsafe {
+                Transfer::new_unchecked(rx_channel, SpiData { data, len }, rx_buf, false)
+            }
+            .begin(
+                <Self as AnySpi>::Sercom::DMA_RX_TRIGGER,
+                dmac::TriggerAction::BEAT,
+            );
+            let tx = unsafe {
+                Transfer::new_unchecked(tx_channel, tx_buf, SpiData { data, len }, false)
+            }
+            .begin(
+                <Self as AnySpi>::Sercom::DMA_TX_TRIGGER,
+                dmac::TriggerAction::BEAT,
+            );
+
+            Ok(DuplexTransfer { spi: self, rx, tx })
+        }
+    }
+
+    /// DMA access to the `DATA` register of an [`Spi`] owned by a
+    /// [`DuplexTransfer`]
+    pub struct SpiData<W> {
+        data: *mut W,
+        len: usize,
+    }
+
+    unsafe impl<W: dmac::transfer::Beat> dmac::transfer::Buffer for SpiData<W> {
+        type Beat = W;
+
+        #[inline]
+        fn dma_ptr(&mut self) -> *mut Self::Beat {
+            self.data
+        }
+
+        #[inline]
+        fn incrementing(&self) -> bool {
+            false
+        }
+
+        /// The length of the transfer, so that a single word memory buffer
+        /// on the other side is repeated
+        #[inline]
+        fn buffer_len(&self) -> usize {
+            self.len
+        }
+    }
+
+    /// A full-duplex SPI transfer, using one DMA channel in each direction
+    ///
+    /// Created by [`Spi::duplex_with_dma`].
+    pub struct DuplexTransfer<S, RxC, TxC, RxB, TxB>
+    where
+        S: dmac::Buffer,
+        RxC: ChId,
+        TxC: ChId,
+        RxB: dmac::Buffer<Beat = S::Beat>,
+        TxB: dmac::Buffer<Beat = S::Beat>,
+    {
+        spi: S,
+        rx: Transfer<Channel<RxC, Busy>, transfer::BufferPair<SpiData<S::Beat>, RxB>>,
+        tx: Transfer<Channel<TxC, Busy>, transfer::BufferPair<TxB, SpiData<S::Beat>>>,
+    }
+
+    impl<S, RxC, TxC, RxB, TxB> DuplexTransfer<S, RxC, TxC, RxB, TxB>
+    where
+        S: dmac::Buffer,
+        RxC: ChId,
+        TxC: ChId,
+        RxB: dmac::Buffer<Beat = S::Beat>,
+        TxB: dmac::Buffer<Beat = S::Beat>,
+    {
+        /// Check if both directions of the transfer have completed
+        #[inline]
+        pub fn complete(&mut self) -> bool {
+            self.tx.complete() && self.rx.complete()
+        }
+
+        /// Checks and clears the block transfer complete interrupt flag of
+        /// the receive channel
+        #[inline]
+        pub fn block_transfer_interrupt(&mut self) -> bool {
+            self.rx.block_transfer_interrupt()
+        }
+
+        /// Wait for the transfer to complete and release the receive
+        /// channel, the transmit channel, the [`Spi`], the receive buffer and
+        /// the transmit buffer
+        ///
+        /// # Blocking: This method may block
+        #[inline]
+        #[allow(clippy::type_complexity)]
+        pub fn wait(mut self) -> (Channel<RxC, Ready>, Channel<TxC, Ready>, S, RxB, TxB) {
+            while !self.complete() {}
+            self.stop()
+        }
+
+        /// Non-blocking; Immediately stop both channels and release all
+        /// owned resources
+        #[inline]
+        #[allow(clippy::type_complexity)]
+        pub fn stop(self) -> (Channel<RxC, Ready>, Channel<TxC, Ready>, S, RxB, TxB) {
+            // Stop sending first, so that nothing is received once the
+            // receive channel is stopped
+            let (tx_channel, tx_buf, _) = self.tx.stop();
+            let (rx_channel, _, rx_buf) = self.rx.stop();
+            (rx_channel, tx_channel, self.spi, rx_buf, tx_buf)
+        }
+    }
 }
 
 //=============================================================================
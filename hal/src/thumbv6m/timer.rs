@@ -1,8 +1,8 @@
 //! Working with timer counter hardware
 #[cfg(feature = "samd11")]
-use crate::target_device::tc1::COUNT16;
+use crate::target_device::tc1::{count16::ctrla::W as CTRLA_W, COUNT16};
 #[cfg(feature = "samd21")]
-use crate::target_device::tc3::COUNT16;
+use crate::target_device::tc3::{count16::ctrla::W as CTRLA_W, COUNT16};
 #[allow(unused)]
 #[cfg(feature = "samd11")]
 use crate::target_device::{PM, TC1};
@@ -13,8 +13,16 @@ use crate::timer_params::TimerParams;
 use hal::timer::{CountDown, Periodic};
 
 use crate::clock;
+use crate::clock_scaling::{rescale, ClockListener};
+use crate::eic::pin::ExternalInterruptID;
+use crate::evsys::{ChId, Connection, Enabled, EventChannel};
+use crate::monotonic::OverflowTick;
+use crate::target_device::EVSYS;
+use crate::tick::Tick;
 use crate::time::{Hertz, Nanoseconds};
-use crate::timer_traits::InterruptDrivenTimer;
+use crate::timer_traits::{InterruptDrivenTimer, PulseCapture};
+use core::ops::{Add, Sub};
+use typenum::{Add1, Sub1, Unsigned, B1};
 use void::Void;
 
 use cortex_m::asm::delay as cycle_delay;
@@ -45,6 +53,18 @@ pub trait Count16 {
     fn count_16(&self) -> &COUNT16;
 }
 
+/// Reads the COUNT register of a 16-bit counter.
+///
+/// COUNT is only synchronized on request: a read request for its address,
+/// 0x10, is complete once the counter is no longer busy synchronizing.
+pub(crate) fn read_count_16(count: &COUNT16) -> u16 {
+    count
+        .readreq
+        .write(|w| unsafe { w.rreq().set_bit().addr().bits(0x10) });
+    while count.status.read().syncbusy().bit_is_set() {}
+    count.count.read().count().bits()
+}
+
 impl<TC> Periodic for TimerCounter<TC> {}
 impl<TC> CountDown for TimerCounter<TC>
 where
@@ -56,7 +76,7 @@ where
     where
         T: Into<Self::Time>,
     {
-        let params = TimerParams::new_us(timeout, self.freq.0);
+        let params = TimerParams::new_us(timeout, self.freq.to_Hz());
         let divider = params.divider;
         let cycles = params.cycles;
 
@@ -115,6 +135,25 @@ where
     }
 }
 
+impl<TC> ClockListener for TimerCounter<TC>
+where
+    TC: Count16,
+{
+    /// Updates the frequency of the timer, and rescales the period of a
+    /// running timer. The prescaler is kept, so the period may saturate when
+    /// the clock speeds up.
+    fn clock_changed(&mut self, old: Hertz, new: Hertz) {
+        self.freq = new;
+        let count = self.tc.count_16();
+        if count.ctrla.read().enable().bit_is_set() {
+            let cycles = count.cc[0].read().cc().bits() as u32;
+            let cycles = rescale(cycles, old, new).max(1).min(0xffff);
+            count.cc[0].write(|w| unsafe { w.cc().bits(cycles as u16) });
+            while count.status.read().syncbusy().bit_is_set() {}
+        }
+    }
+}
+
 impl<TC> InterruptDrivenTimer for TimerCounter<TC>
 where
     TC: Count16,
@@ -136,6 +175,512 @@ where
     }
 }
 
+#[cfg(all(feature = "samd21", feature = "unproven", feature = "dma"))]
+impl<TC> TimerCounter<TC> {
+    /// Returns the timer counter instance
+    pub(crate) fn tc(&self) -> &TC {
+        &self.tc
+    }
+
+    /// Returns the frequency of the timer clock
+    pub(crate) fn freq(&self) -> Hertz {
+        self.freq
+    }
+}
+
+impl<TC> TimerCounter<TC>
+where
+    TC: Count16,
+{
+    /// Stops the counter, keeping its value, until [`resume`](Self::resume)
+    pub fn pause(&mut self) {
+        let count = self.tc.count_16();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.status.read().syncbusy().bit_is_set() {}
+    }
+
+    /// Restarts the counter from the value at which it was paused
+    pub fn resume(&mut self) {
+        let count = self.tc.count_16();
+        count.ctrla.modify(|_, w| w.enable().set_bit());
+        while count.status.read().syncbusy().bit_is_set() {}
+    }
+
+    /// Returns true while the counter is paused, or not started
+    pub fn is_paused(&self) -> bool {
+        self.tc.count_16().ctrla.read().enable().bit_is_clear()
+    }
+
+    /// Returns the current value of the counter
+    pub fn count(&self) -> u16 {
+        read_count_16(self.tc.count_16())
+    }
+
+    /// Sets the value of the counter, which keeps counting from there if it
+    /// is running
+    pub fn set_count(&mut self, value: u16) {
+        let count = self.tc.count_16();
+        count.count.write(|w| unsafe { w.count().bits(value) });
+        while count.status.read().syncbusy().bit_is_set() {}
+    }
+
+    /// Reconfigures the timer as a free-running counter incrementing at `HZ`,
+    /// for use as a [`Tick`] source which keeps running in standby.
+    ///
+    /// Panics if `HZ` cannot be reached by dividing the timer clock by one of
+    /// the available prescalers (1, 2, 4, 8, 16, 64, 256 or 1024).
+    pub fn into_tick<const HZ: u32>(self) -> TimerTick<TC, HZ> {
+        let divider = self.freq.to_Hz() / HZ;
+        assert!(
+            divider * HZ == self.freq.to_Hz(),
+            "timer clock is not a multiple of the tick rate"
+        );
+
+        let count = self.tc.count_16();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.status.read().syncbusy().bit_is_set() {}
+        count.ctrla.write(|w| w.swrst().set_bit());
+        // the SVD erroneously marks swrst as write-only, so we
+        // need to manually read the bit here
+        while count.ctrla.read().bits() & 1 != 0 {}
+
+        count.ctrla.modify(|_, w| {
+            prescale(w, divider);
+            // Normal frequency: count from zero to 0xffff and wrap
+            w.wavegen().nfrq();
+            w.enable().set_bit();
+            w.runstdby().set_bit()
+        });
+        while count.status.read().syncbusy().bit_is_set() {}
+        TimerTick {
+            freq: self.freq,
+            tc: self.tc,
+        }
+    }
+}
+
+pub(crate) fn prescale(w: &mut CTRLA_W, divider: u32) {
+    match divider {
+        1 => w.prescaler().div1(),
+        2 => w.prescaler().div2(),
+        4 => w.prescaler().div4(),
+        8 => w.prescaler().div8(),
+        16 => w.prescaler().div16(),
+        64 => w.prescaler().div64(),
+        256 => w.prescaler().div256(),
+        1024 => w.prescaler().div1024(),
+        _ => {
+            // Formatting the divider doesn't fit the flash of the SAMD11
+            #[cfg(feature = "samd11")]
+            panic!("no timer prescaler has this divider");
+            #[cfg(not(feature = "samd11"))]
+            panic!("no timer prescaler divides by {}", divider);
+        }
+    };
+}
+
+/// A timer counter running as a free-running 16-bit counter at `HZ`, for use
+/// as a [`Tick`] source. Created by [`TimerCounter::into_tick`].
+pub struct TimerTick<TC, const HZ: u32> {
+    freq: Hertz,
+    tc: TC,
+}
+
+impl<TC, const HZ: u32> TimerTick<TC, HZ>
+where
+    TC: Count16,
+{
+    /// Stops the counter and returns the timer
+    pub fn free(self) -> TimerCounter<TC> {
+        self.tc
+            .count_16()
+            .ctrla
+            .modify(|_, w| w.enable().clear_bit());
+        TimerCounter {
+            freq: self.freq,
+            tc: self.tc,
+        }
+    }
+}
+
+impl<TC, const HZ: u32> Tick for TimerTick<TC, HZ>
+where
+    TC: Count16,
+{
+    const HZ: u32 = HZ;
+    const MAX: u32 = 0xffff;
+
+    fn ticks(&self) -> u32 {
+        read_count_16(self.tc.count_16()) as u32
+    }
+}
+
+impl<TC, const HZ: u32> OverflowTick for TimerTick<TC, HZ>
+where
+    TC: Count16,
+{
+    fn enable_overflow_interrupt(&mut self) {
+        self.tc.count_16().intenset.write(|w| w.ovf().set_bit());
+    }
+
+    fn disable_overflow_interrupt(&mut self) {
+        self.tc.count_16().intenclr.write(|w| w.ovf().set_bit());
+    }
+
+    fn overflow_pending(&self) -> bool {
+        self.tc.count_16().intflag.read().ovf().bit_is_set()
+    }
+
+    fn clear_overflow(&mut self) {
+        // Writing a 1 clears the flag
+        self.tc.count_16().intflag.write(|w| w.ovf().set_bit());
+    }
+}
+
+/// Event system generator number of external interrupt line 0. The other
+/// lines follow in order.
+const EVSYS_GEN_EIC_EXTINT_0: u8 = 0x0c;
+
+/// A timer counter which can count events from the event system
+pub trait EventUser: Count16 {
+    /// Event system user number of the timer counter event input
+    const EVSYS_USER: u8;
+}
+
+#[cfg(feature = "samd21")]
+impl EventUser for TC3 {
+    const EVSYS_USER: u8 = 0x12;
+}
+
+#[cfg(feature = "samd21")]
+impl EventUser for TC4 {
+    const EVSYS_USER: u8 = 0x13;
+}
+
+#[cfg(feature = "samd21")]
+impl EventUser for TC5 {
+    const EVSYS_USER: u8 = 0x14;
+}
+
+impl<TC> TimerCounter<TC>
+where
+    TC: EventUser,
+{
+    /// Reconfigures the timer to count the events of external interrupt
+    /// `line`, routed through the event system channel `channel`.
+    ///
+    /// The external interrupt must be set to sense rising edges and to emit
+    /// events, see `enable_event`. The event path is asynchronous and the
+    /// counter runs in standby, so pulses are counted while the CPU sleeps,
+    /// as long as the timer clock also runs in standby.
+    pub fn into_pulse_counter(
+        self,
+        evsys: &mut EVSYS,
+        pm: &mut PM,
+        line: ExternalInterruptID,
+        channel: u8,
+    ) -> PulseCounter<TC> {
+        route_extint(evsys, pm, line, channel, TC::EVSYS_USER);
+        self.count_events(channel)
+    }
+
+    /// Reconfigures the timer to capture the period and the width of the
+    /// pulses of external interrupt `line`, routed through the event system
+    /// channel `channel`. See [`PulseCapture`].
+    ///
+    /// The timer counts the timer clock divided by `divider`, one of the
+    /// prescalers (1, 2, 4, 8, 16, 64, 256 or 1024), and the longest period
+    /// it captures is 65535 of these ticks. The external interrupt must be
+    /// set to sense a high level and to emit events, see `enable_event`.
+    /// With `invert`, the pulses are the low levels of the input instead,
+    /// e.g. the marks of an IR receiver.
+    pub fn into_pulse_capture(
+        self,
+        evsys: &mut EVSYS,
+        pm: &mut PM,
+        line: ExternalInterruptID,
+        channel: u8,
+        divider: u32,
+        invert: bool,
+    ) -> CaptureCounter<TC> {
+        route_extint(evsys, pm, line, channel, TC::EVSYS_USER);
+
+        let count = self.tc.count_16();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.status.read().syncbusy().bit_is_set() {}
+        count.ctrla.write(|w| w.swrst().set_bit());
+        // the SVD erroneously marks swrst as write-only, so we
+        // need to manually read the bit here
+        while count.ctrla.read().bits() & 1 != 0 {}
+
+        // The start of a pulse captures the period in CC0 and restarts the
+        // counter, its end captures the width in CC1
+        count
+            .evctrl
+            .write(|w| w.evact().ppw().tcinv().bit(invert).tcei().set_bit());
+        count
+            .ctrlc
+            .write(|w| w.cpten0().set_bit().cpten1().set_bit());
+        while count.status.read().syncbusy().bit_is_set() {}
+        count.ctrla.modify(|_, w| {
+            prescale(w, divider);
+            w.wavegen().nfrq();
+            w.enable().set_bit();
+            w.runstdby().set_bit()
+        });
+        while count.status.read().syncbusy().bit_is_set() {}
+        CaptureCounter {
+            freq: self.freq,
+            tc: self.tc,
+            channel,
+            divider,
+        }
+    }
+
+    /// Reconfigures the timer to count the events of a type-level event
+    /// system channel, whose count of users is incremented. See the
+    /// [`evsys`](crate::evsys) module.
+    pub fn into_event_counter<C, N>(
+        self,
+        channel: Enabled<EventChannel<C>, N>,
+    ) -> (
+        PulseCounter<TC, Connection<C>>,
+        Enabled<EventChannel<C>, Add1<N>>,
+    )
+    where
+        C: ChId,
+        N: Unsigned + Add<B1>,
+        Add1<N>: Unsigned,
+    {
+        let (connection, channel) = channel.connect(TC::EVSYS_USER);
+        (self.count_events(connection), channel)
+    }
+
+    fn count_events<E>(self, event: E) -> PulseCounter<TC, E> {
+        let count = self.tc.count_16();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.status.read().syncbusy().bit_is_set() {}
+        count.ctrla.write(|w| w.swrst().set_bit());
+        // the SVD erroneously marks swrst as write-only, so we
+        // need to manually read the bit here
+        while count.ctrla.read().bits() & 1 != 0 {}
+
+        count.evctrl.write(|w| w.evact().count().tcei().set_bit());
+        count.ctrla.modify(|_, w| {
+            w.wavegen().nfrq();
+            w.enable().set_bit();
+            w.runstdby().set_bit()
+        });
+        while count.status.read().syncbusy().bit_is_set() {}
+        PulseCounter {
+            freq: self.freq,
+            tc: self.tc,
+            event,
+            last: 0,
+        }
+    }
+}
+
+/// A timer counter counting pulses on a pin, e.g. from a flow meter or an
+/// anemometer. Created by [`TimerCounter::into_pulse_counter`] or
+/// [`TimerCounter::into_event_counter`].
+///
+/// `E` is the event system channel the timer counts: its number when created
+/// by `into_pulse_counter`, or its [`Connection`] when created by
+/// `into_event_counter`.
+pub struct PulseCounter<TC, E = u8> {
+    freq: Hertz,
+    tc: TC,
+    event: E,
+    last: u16,
+}
+
+impl<TC, E> PulseCounter<TC, E>
+where
+    TC: EventUser,
+{
+    /// Returns the raw value of the 16-bit counter
+    pub fn count(&self) -> u16 {
+        read_count_16(self.tc.count_16())
+    }
+
+    /// Returns the number of pulses since the previous call, and starts
+    /// counting again from there.
+    ///
+    /// The counter itself keeps running, so no pulse is lost between the
+    /// read and the clear. The result wraps after 65535 pulses; count the
+    /// overflow interrupts to measure longer intervals.
+    pub fn take(&mut self) -> u16 {
+        let now = self.count();
+        let pulses = now.wrapping_sub(self.last);
+        self.last = now;
+        pulses
+    }
+
+    /// Enables the overflow interrupt, fired every 65536 pulses
+    pub fn enable_overflow_interrupt(&mut self) {
+        self.tc.count_16().intenset.write(|w| w.ovf().set_bit());
+    }
+
+    /// Disables the overflow interrupt
+    pub fn disable_overflow_interrupt(&mut self) {
+        self.tc.count_16().intenclr.write(|w| w.ovf().set_bit());
+    }
+
+    /// Checks and clears the overflow flag
+    pub fn overflowed(&mut self) -> bool {
+        let intflag = &self.tc.count_16().intflag;
+        let overflowed = intflag.read().ovf().bit_is_set();
+        if overflowed {
+            // Writing a 1 clears the flag
+            intflag.write(|w| w.ovf().set_bit());
+        }
+        overflowed
+    }
+
+    fn stop(&self) {
+        let count = self.tc.count_16();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.status.read().syncbusy().bit_is_set() {}
+        count.evctrl.reset();
+    }
+}
+
+impl<TC> PulseCounter<TC>
+where
+    TC: EventUser,
+{
+    /// Stops counting, disconnects the event system channel and returns the
+    /// timer
+    pub fn free(self, evsys: &mut EVSYS) -> TimerCounter<TC> {
+        self.stop();
+        evsys
+            .user
+            .write(|w| unsafe { w.user().bits(TC::EVSYS_USER).channel().bits(0) });
+        evsys
+            .channel
+            .write(|w| unsafe { w.channel().bits(self.event) });
+        TimerCounter {
+            freq: self.freq,
+            tc: self.tc,
+        }
+    }
+}
+
+impl<TC, C> PulseCounter<TC, Connection<C>>
+where
+    TC: EventUser,
+    C: ChId,
+{
+    /// Stops counting, disconnects the timer from the type-level channel it
+    /// was created with, and returns both
+    pub fn free_event_counter<N>(
+        self,
+        channel: Enabled<EventChannel<C>, N>,
+    ) -> (TimerCounter<TC>, Enabled<EventChannel<C>, Sub1<N>>)
+    where
+        N: Unsigned + Sub<B1>,
+        Sub1<N>: Unsigned,
+    {
+        self.stop();
+        let channel = self.event.disconnect(channel);
+        let timer = TimerCounter {
+            freq: self.freq,
+            tc: self.tc,
+        };
+        (timer, channel)
+    }
+}
+
+/// Routes the events of external interrupt `line` to `user` through the
+/// event system channel `channel`, on the asynchronous path
+fn route_extint(evsys: &mut EVSYS, pm: &mut PM, line: ExternalInterruptID, channel: u8, user: u8) {
+    pm.apbcmask.modify(|_, w| w.evsys_().set_bit());
+    evsys.channel.write(|w| {
+        unsafe {
+            w.channel().bits(channel);
+            w.evgen().bits(EVSYS_GEN_EIC_EXTINT_0 + line as u8);
+        }
+        w.path().asynchronous().edgsel().no_evt_output()
+    });
+    // The user channel is the channel number plus one, zero disconnects
+    // the user
+    evsys
+        .user
+        .write(|w| unsafe { w.user().bits(user).channel().bits(channel + 1) });
+}
+
+/// A timer counter capturing the period and the width of the pulses of a
+/// pin. Created by [`TimerCounter::into_pulse_capture`].
+pub struct CaptureCounter<TC> {
+    freq: Hertz,
+    tc: TC,
+    channel: u8,
+    divider: u32,
+}
+
+impl<TC> CaptureCounter<TC>
+where
+    TC: EventUser,
+{
+    /// Stops capturing, disconnects the event system channel and returns
+    /// the timer
+    pub fn free(self, evsys: &mut EVSYS) -> TimerCounter<TC> {
+        let count = self.tc.count_16();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.status.read().syncbusy().bit_is_set() {}
+        count.evctrl.reset();
+        count.ctrlc.reset();
+        while count.status.read().syncbusy().bit_is_set() {}
+        evsys
+            .user
+            .write(|w| unsafe { w.user().bits(TC::EVSYS_USER).channel().bits(0) });
+        evsys
+            .channel
+            .write(|w| unsafe { w.channel().bits(self.channel) });
+        TimerCounter {
+            freq: self.freq,
+            tc: self.tc,
+        }
+    }
+
+    /// Reads capture channel `cc` if it captured a value since the last
+    /// read, in microseconds
+    fn read_capture(&mut self, cc: usize) -> Option<u32> {
+        let count = self.tc.count_16();
+        let flags = count.intflag.read();
+        let captured = if cc == 0 {
+            flags.mc0().bit_is_set()
+        } else {
+            flags.mc1().bit_is_set()
+        };
+        if !captured {
+            return None;
+        }
+        // CC0 and CC1 are at 0x18 and 0x1a. Reading the captured value
+        // clears its flag.
+        count
+            .readreq
+            .write(|w| unsafe { w.rreq().set_bit().addr().bits(0x18 + 2 * cc as u8) });
+        while count.status.read().syncbusy().bit_is_set() {}
+        let ticks = count.cc[cc].read().cc().bits() as u64;
+        Some((ticks * self.divider as u64 * 1_000_000 / self.freq.to_Hz() as u64) as u32)
+    }
+}
+
+impl<TC> PulseCapture for CaptureCounter<TC>
+where
+    TC: EventUser,
+{
+    fn read_width(&mut self) -> Option<u32> {
+        self.read_capture(1)
+    }
+
+    fn read_period(&mut self) -> Option<u32> {
+        self.read_capture(0)
+    }
+}
+
 macro_rules! tc {
     ($($TYPE:ident: ($TC:ident, $pm:ident, $clock:ident),)+) => {
         $(
@@ -13,7 +13,8 @@ use crate::target_device;
 use crate::target_device::usb::DEVICE;
 use crate::target_device::{PM, USB};
 use crate::usb::devicedesc::DeviceDescBank;
-use core::cell::{Ref, RefCell, RefMut};
+use crate::usb_power::UsbEvents;
+use core::cell::{Cell, Ref, RefCell, RefMut};
 use core::marker::PhantomData;
 use core::mem;
 use cortex_m::interrupt::{free as disable_interrupts, Mutex};
@@ -202,6 +203,8 @@ struct Inner {
     _dp_pad: Pin<PA25, AlternateG>,
     endpoints: RefCell<AllEndpoints>,
     buffers: RefCell<BufferAllocator>,
+    /// Set when a reset is reported to `usb-device`, until `take_events`
+    reset: Cell<bool>,
 }
 
 pub struct UsbBus {
@@ -587,6 +590,7 @@ impl UsbBus {
         _usb: USB,
     ) -> Self {
         dbgprint!("******** UsbBus::new\n");
+        crate::usb_composite::reset_endpoints();
         pm.apbbmask.modify(|_, w| w.usb_().set_bit());
 
         let desc = RefCell::new(Descriptors::new());
@@ -597,12 +601,22 @@ impl UsbBus {
             desc,
             buffers: RefCell::new(BufferAllocator::new()),
             endpoints: RefCell::new(AllEndpoints::new()),
+            reset: Cell::new(false),
         };
 
         Self {
             inner: Mutex::new(RefCell::new(inner)),
         }
     }
+
+    /// Returns the bus events since the last call
+    ///
+    /// The suspend and wakeup events are not reported to `usb-device`, see
+    /// the [`usb_power`](crate::usb_power) module. Their flags are polled,
+    /// so this should be called along with `UsbDevice::poll`.
+    pub fn take_events(&self) -> UsbEvents {
+        disable_interrupts(|cs| self.inner.borrow(cs).borrow().take_events())
+    }
 }
 
 impl Inner {
@@ -797,6 +811,22 @@ impl Inner {
         dbgprint!("UsbBus::resume\n");
     }
 
+    fn take_events(&self) -> UsbEvents {
+        let usb = self.usb();
+        let flags = usb.intflag.read();
+        // Only clear the flags read, so that none is lost
+        usb.intflag.write(|w| {
+            w.suspend().bit(flags.suspend().bit());
+            w.wakeup().bit(flags.wakeup().bit());
+            w.eorsm().bit(flags.eorsm().bit())
+        });
+        UsbEvents {
+            reset: self.reset.replace(false),
+            suspend: flags.suspend().bit(),
+            wakeup: flags.wakeup().bit() || flags.eorsm().bit(),
+        }
+    }
+
     fn alloc_ep(
         &mut self,
         dir: UsbDirection,
@@ -849,6 +879,7 @@ impl Inner {
         )?;
 
         dbgprint!("alloc_ep -> {:?}\n", addr);
+        crate::usb_composite::record_endpoint(addr, allocated_size);
 
         Ok(addr)
     }
@@ -865,6 +896,7 @@ impl Inner {
         if intflags.eorst().bit() {
             // end of reset interrupt
             self.usb().intflag.write(|w| w.eorst().set_bit());
+            self.reset.set(true);
             dbgprint!("PollResult::Reset\n");
             return PollResult::Reset;
         }
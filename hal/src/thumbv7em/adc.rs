@@ -8,6 +8,8 @@ use crate::target_device::gclk::genctrl::SRC_A::DFLL;
 use crate::target_device::gclk::pchctrl::GEN_A;
 use crate::target_device::{adc0, ADC0, ADC1, MCLK};
 
+pub use crate::adc_channel::AdcChannel;
+
 use crate::calibration;
 
 /// Samples per reading
@@ -119,13 +121,30 @@ impl Adc<$ADC> {
         while self.adc.syncbusy.read().ctrlb().bit_is_set() {}
     }
 
-    fn power_up(&mut self) {
+    /// Reads `pin` in millivolts. `divider` is the ratio of a resistor
+    /// divider between the measured voltage and the pin, e.g. 2 for two equal
+    /// resistors, or 1 without a divider.
+    ///
+    /// The ADC is set to the VDDANA reference and to 12 bit single samples,
+    /// and keeps this configuration. VDDANA is taken to be `vddana_mv`.
+    pub fn read_millivolts<PIN>(&mut self, pin: &mut PIN, vddana_mv: u16, divider: u16) -> u16
+    where
+        PIN: AdcChannel<$ADC>,
+    {
+        self.resolution(Resolution::_12BIT);
+        self.samples(SampleRate::_1);
+        self.reference(Reference::INTVCC1);
+        let raw: u16 = nb::block!(self.read(pin)).unwrap();
+        to_millivolts(raw, vddana_mv, divider)
+    }
+
+    pub(crate) fn power_up(&mut self) {
         while self.adc.syncbusy.read().enable().bit_is_set() {}
         self.adc.ctrla.modify(|_, w| w.enable().set_bit());
         while self.adc.syncbusy.read().enable().bit_is_set() {}
     }
 
-    fn power_down(&mut self) {
+    pub(crate) fn power_down(&mut self) {
         while self.adc.syncbusy.read().enable().bit_is_set() {}
         self.adc.ctrla.modify(|_, w| w.enable().clear_bit());
         while self.adc.syncbusy.read().enable().bit_is_set() {}
@@ -157,17 +176,17 @@ impl Adc<$ADC> {
     }
 
     /// Enables an interrupt when conversion is ready.
-    fn enable_interrupts(&mut self) {
+    pub(crate) fn enable_interrupts(&mut self) {
         self.adc.intflag.write(|w| w.resrdy().set_bit());
         self.adc.intenset.write(|w| w.resrdy().set_bit());
     }
 
     /// Disables the interrupt for when conversion is ready.
-    fn disable_interrupts(&mut self) {
+    pub(crate) fn disable_interrupts(&mut self) {
         self.adc.intenclr.write(|w| w.resrdy().set_bit());
     }
 
-    fn service_interrupt_ready(&mut self) -> Option<u16> {
+    pub(crate) fn service_interrupt_ready(&mut self) -> Option<u16> {
         if self.adc.intflag.read().resrdy().bit_is_set() {
             self.adc.intflag.write(|w| w.resrdy().set_bit());
 
@@ -177,10 +196,16 @@ impl Adc<$ADC> {
         }
     }
 
+    /// Enables or disables starting a conversion on each incoming event.
+    /// Must be called while the peripheral is disabled.
+    pub(crate) fn set_start_event(&mut self, enable: bool) {
+        self.adc.evctrl.modify(|_, w| w.startei().bit(enable));
+    }
+
     /// Sets the mux to a particular pin. The pin mux is enabled-protected,
     /// so must be called while the peripheral is disabled.
-    fn mux<PIN: Channel<$ADC, ID=u8>>(&mut self, _pin: &mut PIN) {
-        let chan = PIN::channel();
+    pub(crate) fn mux<PIN: AdcChannel<$ADC>>(&mut self, _pin: &mut PIN) {
+        let chan = PIN::MUXPOS;
         while self.adc.syncbusy.read().inputctrl().bit_is_set() {}
         self.adc.inputctrl.modify(|_, w| w.muxpos().bits(chan));
     }
@@ -223,7 +248,7 @@ impl<C> InterruptAdc<$ADC, C>
     }
 
     /// Starts a conversion sampling the specified pin.
-    pub fn start_conversion<PIN: Channel<$ADC, ID=u8>>(&mut self, pin: &mut PIN) {
+    pub fn start_conversion<PIN: AdcChannel<$ADC>>(&mut self, pin: &mut PIN) {
         self.adc.mux(pin);
         self.adc.power_up();
         C::on_start(&mut self.adc);
@@ -250,7 +275,7 @@ impl<C> From<Adc<$ADC>> for InterruptAdc<$ADC, C>
 impl<WORD, PIN> OneShot<$ADC, WORD, PIN> for Adc<$ADC>
 where
    WORD: From<u16>,
-   PIN: Channel<$ADC, ID=u8>,
+   PIN: AdcChannel<$ADC>,
 {
    type Error = ();
 
@@ -266,11 +291,188 @@ where
     }
 }
 
+/// Converts a 12 bit result to millivolts, rounding to the nearest
+fn to_millivolts(raw: u16, full_scale_mv: u16, divider: u16) -> u16 {
+    let mv = (raw as u32 * full_scale_mv as u32 * divider as u32 + 2047) / 4095;
+    mv.min(u16::MAX as u32) as u16
+}
+
 adc_hal! {
     ADC0: (adc0, apbdmask, adc0_, adc0_biascomp_scale_cal, adc0_biasref_scale_cal, adc0_biasr2r_scale_cal),
     ADC1: (adc1, apbdmask, adc1_, adc1_biascomp_scale_cal, adc1_biasref_scale_cal, adc1_biasr2r_scale_cal),
 }
 
+//==============================================================================
+// DMA sequencing
+//==============================================================================
+#[cfg(feature = "dma")]
+pub use adc_dseq::*;
+
+#[cfg(feature = "dma")]
+mod adc_dseq {
+    use super::*;
+    use crate::dmac::{transfer::Buffer, TriggerSource};
+    use core::marker::PhantomData;
+
+    /// DMA trigger sources of an ADC
+    pub trait AdcDmaTrigger {
+        /// Trigger requesting the next word of the DMA sequence
+        const DMA_SEQ_TRIGGER: TriggerSource;
+        /// Trigger signaling that a result is ready
+        const DMA_RESRDY_TRIGGER: TriggerSource;
+    }
+
+    /// An [`Adc`] in DMA sequencing mode
+    ///
+    /// DMA sequencing (DSEQ) lets the DMAC rewrite the ADC input selection
+    /// between conversions, so that a set of channels can be scanned without
+    /// any CPU involvement.
+    ///
+    /// Two DMA transfers are needed. The first one writes one `INPUTCTRL` word
+    /// per conversion to [`DseqData`], and is triggered by the ADC `SEQ`
+    /// trigger. Each write automatically starts a conversion. The second one
+    /// reads the results from [`DseqResult`], and is triggered by the ADC
+    /// `RESRDY` trigger.
+    ///
+    /// ```
+    /// static mut INPUTS: [u32; 2] = [0; 2];
+    /// static mut RESULTS: [u16; 2] = [0; 2];
+    ///
+    /// let inputs = unsafe { &mut INPUTS };
+    /// inputs[0] = Adc::<ADC0>::dseq_input(&a0, false);
+    /// inputs[1] = Adc::<ADC0>::dseq_input(&a1, true);
+    ///
+    /// let (dseq, data, result) = adc.into_dseq();
+    /// // Start collecting results before the first conversion is started
+    /// let results = Transfer::new(chan1, result, unsafe { &mut RESULTS }, false)
+    ///     .unwrap()
+    ///     .begin(ADC0::DMA_RESRDY_TRIGGER, TriggerAction::BURST);
+    /// let inputs = Transfer::new(chan0, inputs, data, false)
+    ///     .unwrap()
+    ///     .begin(ADC0::DMA_SEQ_TRIGGER, TriggerAction::BURST);
+    /// let (chan1, result, results) = results.wait();
+    /// let (chan0, inputs, data) = inputs.wait();
+    /// let adc = dseq.free(data, result);
+    /// ```
+    pub struct Dseq<ADC> {
+        adc: Adc<ADC>,
+    }
+
+    /// DMA destination for the `INPUTCTRL` words of a sequence
+    pub struct DseqData<ADC> {
+        adc: PhantomData<ADC>,
+    }
+
+    /// DMA source for the conversion results of a sequence
+    pub struct DseqResult<ADC> {
+        adc: PhantomData<ADC>,
+    }
+
+    macro_rules! adc_dseq {
+        ($($ADC:ident: ($seq:ident, $resrdy:ident),)+) => {
+            $(
+                impl AdcDmaTrigger for $ADC {
+                    const DMA_SEQ_TRIGGER: TriggerSource = TriggerSource::$seq;
+                    const DMA_RESRDY_TRIGGER: TriggerSource = TriggerSource::$resrdy;
+                }
+
+                impl Adc<$ADC> {
+                    /// Builds the `INPUTCTRL` word selecting `pin`, for use in a
+                    /// DMA sequence. If `last` is set, sequencing stops after
+                    /// this conversion.
+                    pub fn dseq_input<PIN: AdcChannel<$ADC>>(_pin: &PIN, last: bool) -> u32 {
+                        let muxneg = adc0::inputctrl::MUXNEG_A::GND as u32;
+                        PIN::MUXPOS as u32 | muxneg << 8 | (last as u32) << 15
+                    }
+
+                    /// Enables DMA sequencing of the `INPUTCTRL` register,
+                    /// with conversions started automatically after each
+                    /// write, and powers up the ADC.
+                    pub fn into_dseq(mut self) -> (Dseq<$ADC>, DseqData<$ADC>, DseqResult<$ADC>) {
+                        self.adc
+                            .dseqctrl
+                            .write(|w| w.inputctrl().set_bit().autostart().set_bit());
+                        self.power_up();
+                        (
+                            Dseq { adc: self },
+                            DseqData { adc: PhantomData },
+                            DseqResult { adc: PhantomData },
+                        )
+                    }
+                }
+
+                impl Dseq<$ADC> {
+                    /// Returns true while a sequence is in progress
+                    pub fn busy(&self) -> bool {
+                        self.adc.adc.dseqstat.read().busy().bit_is_set()
+                    }
+
+                    /// Disables DMA sequencing, powers down the ADC and
+                    /// releases it
+                    pub fn free(mut self, _data: DseqData<$ADC>, _result: DseqResult<$ADC>) -> Adc<$ADC> {
+                        self.adc.power_down();
+                        self.adc.adc.dseqctrl.reset();
+                        self.adc
+                    }
+                }
+
+                unsafe impl Buffer for DseqData<$ADC> {
+                    type Beat = u32;
+
+                    #[inline]
+                    fn dma_ptr(&mut self) -> *mut Self::Beat {
+                        unsafe { (*$ADC::ptr()).dseqdata.as_ptr() }
+                    }
+
+                    #[inline]
+                    fn incrementing(&self) -> bool {
+                        false
+                    }
+
+                    #[inline]
+                    fn buffer_len(&self) -> usize {
+                        1
+                    }
+
+                    #[inline]
+                    fn standby_capable(&self) -> bool {
+                        unsafe { (*$ADC::ptr()).ctrla.read().runstdby().bit_is_set() }
+                    }
+                }
+
+                unsafe impl Buffer for DseqResult<$ADC> {
+                    type Beat = u16;
+
+                    #[inline]
+                    fn dma_ptr(&mut self) -> *mut Self::Beat {
+                        unsafe { (*$ADC::ptr()).result.as_ptr() as *mut _ }
+                    }
+
+                    #[inline]
+                    fn incrementing(&self) -> bool {
+                        false
+                    }
+
+                    #[inline]
+                    fn buffer_len(&self) -> usize {
+                        1
+                    }
+
+                    #[inline]
+                    fn standby_capable(&self) -> bool {
+                        unsafe { (*$ADC::ptr()).ctrla.read().runstdby().bit_is_set() }
+                    }
+                }
+            )+
+        };
+    }
+
+    adc_dseq! {
+        ADC0: (ADC0_SEQ, ADC0_RESRDY),
+        ADC1: (ADC1_SEQ, ADC1_RESRDY),
+    }
+}
+
 macro_rules! adc_pins {
     (
         $(
@@ -282,23 +484,78 @@ macro_rules! adc_pins {
                type ID = u8;
                fn channel() -> u8 { $CHAN }
             }
+
+            impl AdcChannel<$ADC> for Pin<$PinId, AlternateB> {
+               const MUXPOS: u8 = $CHAN;
+            }
+        )+
+    }
+}
+
+macro_rules! internal_inputs {
+    (
+        $(
+            $(#[$attr:meta])*
+            $Input:ident: $CHAN:literal,
+        )+
+    ) => {
+        $(
+            $(#[$attr])*
+            pub struct $Input;
+
+            impl Channel<ADC0> for $Input {
+               type ID = u8;
+               fn channel() -> u8 { $CHAN }
+            }
+
+            impl AdcChannel<ADC0> for $Input {
+               const MUXPOS: u8 = $CHAN;
+            }
         )+
     }
 }
 
+// The internal sources are only wired to ADC0
+internal_inputs! {
+    /// Core supply, scaled by 1/4
+    ScaledCoreVcc: 0x18,
+    /// Backup battery supply, scaled by 1/4
+    ScaledVbat: 0x19,
+    /// I/O supply, scaled by 1/4
+    ScaledIoVcc: 0x1a,
+    /// Internal bandgap reference, selected by `VREF.SEL` in `SUPC`
+    Bandgap: 0x1b,
+    /// Temperature sensor proportional to absolute temperature, enabled by
+    /// `VREF.TSEN` in `SUPC`
+    Ptat: 0x1c,
+    /// Temperature sensor complementary to absolute temperature, enabled by
+    /// `VREF.TSEN` in `SUPC`
+    Ctat: 0x1d,
+    /// Output of the DAC
+    Dac: 0x1e,
+}
+
 /// Implement [`Channel`] for [`v1::Pin`]s based on the implementations for
 /// `v2` [`Pin`]s
 impl<I, A> Channel<A> for v1::Pin<I, v1::PfB>
 where
     I: PinId,
-    Pin<I, AlternateB>: Channel<A, ID = u8>,
+    Pin<I, AlternateB>: AdcChannel<A>,
 {
     type ID = u8;
     fn channel() -> u8 {
-        Pin::<I, AlternateB>::channel()
+        Pin::<I, AlternateB>::MUXPOS
     }
 }
 
+impl<I, A> AdcChannel<A> for v1::Pin<I, v1::PfB>
+where
+    I: PinId,
+    Pin<I, AlternateB>: AdcChannel<A>,
+{
+    const MUXPOS: u8 = Pin::<I, AlternateB>::MUXPOS;
+}
+
 adc_pins! {
     PA02: (ADC0, 0),
     PA03: (ADC0, 1),
@@ -0,0 +1,281 @@
+//! AES hardware accelerator
+//!
+//! [`Aes`] encrypts single 16-byte blocks in electronic code book mode, with a
+//! 128, 192 or 256-bit key. This is the building block of the
+//! [`ccm`](crate::ccm) module, which implements the CCM* mode of the IEEE
+//! 802.15.4 and Bluetooth LE link layers on top of it:
+//!
+//! ```
+//! let mut aes = Aes::new(&mut peripherals.MCLK, peripherals.AES, Key::Aes128(&key));
+//! let mut ccm = Ccm::new(&mut aes, 4)?;
+//! ccm.decrypt(&nonce, &[&header], &mut [&mut payload], &mic)?;
+//! ```
+//!
+//! A block takes 57 cycles of the APB clock for a 128-bit key, which is short
+//! enough for the CPU to feed single blocks itself. With the `dma` feature,
+//! [`AesDma`] lets two DMA channels stream the payload through the counter
+//! mode of the peripheral instead, while the CPU only computes the CBC-MAC:
+//!
+//! ```
+//! let mut aes = AesDma::new(aes, channels.0, channels.1);
+//! let mut ccm = Ccm::new(&mut aes, 4)?;
+//! ```
+
+use crate::ccm::BlockCipher;
+use crate::target_device::{AES, MCLK};
+
+#[cfg(feature = "dma")]
+pub use dma::AesDma;
+
+/// An AES key
+pub enum Key<'a> {
+    /// A 128-bit key
+    Aes128(&'a [u8; 16]),
+    /// A 192-bit key
+    Aes192(&'a [u8; 24]),
+    /// A 256-bit key
+    Aes256(&'a [u8; 32]),
+}
+
+impl<'a> Key<'a> {
+    fn bytes(&self) -> &'a [u8] {
+        match *self {
+            Key::Aes128(key) => key,
+            Key::Aes192(key) => key,
+            Key::Aes256(key) => key,
+        }
+    }
+}
+
+/// Modes of operation of the peripheral
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Mode {
+    /// Electronic code book, started by the CPU
+    Ecb,
+    /// Counter mode, started once a block is written, by the CPU or the DMAC
+    #[cfg_attr(not(feature = "dma"), allow(dead_code))]
+    Counter,
+}
+
+/// The AES peripheral, encrypting blocks in ECB mode
+pub struct Aes {
+    aes: AES,
+    /// The key is written again whenever the mode changes
+    key: [u32; 8],
+    key_words: usize,
+}
+
+impl Aes {
+    /// Enables the APB clock of the AES peripheral, and sets the key
+    pub fn new(mclk: &mut MCLK, aes: AES, key: Key) -> Self {
+        mclk.apbcmask.modify(|_, w| w.aes_().set_bit());
+        let mut aes = Self {
+            aes,
+            key: [0; 8],
+            key_words: 0,
+        };
+        aes.set_key(key);
+        aes
+    }
+
+    /// Resets the peripheral and sets a new key
+    pub fn set_key(&mut self, key: Key) {
+        let bytes = key.bytes();
+        for (word, bytes) in self.key.iter_mut().zip(bytes.chunks(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        self.key_words = bytes.len() / 4;
+        self.configure(Mode::Ecb);
+    }
+
+    /// Resets the peripheral, then sets the mode and the key
+    fn configure(&mut self, mode: Mode) {
+        self.aes.ctrla.write(|w| w.swrst().set_bit());
+        while self.aes.ctrla.read().swrst().bit_is_set() {}
+        // CTRLA is enable-protected, so the mode is set before enabling
+        let key_words = self.key_words;
+        self.aes.ctrla.write(|w| {
+            match mode {
+                Mode::Ecb => w.aesmode().ecb().startmode().manual(),
+                Mode::Counter => w.aesmode().counter().startmode().auto(),
+            };
+            w.cipher().enc();
+            match key_words {
+                4 => w.keysize()._128bit(),
+                6 => w.keysize()._192bit(),
+                _ => w.keysize()._256bit(),
+            }
+        });
+        self.aes.ctrla.modify(|_, w| w.enable().set_bit());
+        for (word, &value) in self.aes.keyword.iter().zip(&self.key[..key_words]) {
+            word.write(|w| unsafe { w.bits(value) });
+        }
+    }
+
+    /// Encrypts `block` in place. In ECB mode, the CPU starts the encryption
+    /// once the block is written, in counter mode it starts by itself.
+    fn process(&mut self, block: &mut [u8], mode: Mode) {
+        // INDATA accesses the word of the data buffer selected by DATABUFPTR,
+        // which increments after each access
+        self.aes
+            .databufptr
+            .write(|w| unsafe { w.indataptr().bits(0) });
+        for bytes in block.chunks(4) {
+            let value = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            self.aes.indata.write(|w| unsafe { w.bits(value) });
+        }
+        if mode == Mode::Ecb {
+            self.aes.ctrlb.write(|w| w.start().set_bit());
+        }
+        while self.aes.intflag.read().enccmp().bit_is_clear() {}
+        // Reading the result clears ENCCMP
+        self.aes
+            .databufptr
+            .write(|w| unsafe { w.indataptr().bits(0) });
+        for bytes in block.chunks_mut(4) {
+            bytes.copy_from_slice(&self.aes.indata.read().bits().to_le_bytes());
+        }
+    }
+
+    /// Disables the peripheral and its APB clock
+    pub fn free(self, mclk: &mut MCLK) -> AES {
+        self.aes.ctrla.write(|w| w.swrst().set_bit());
+        while self.aes.ctrla.read().swrst().bit_is_set() {}
+        mclk.apbcmask.modify(|_, w| w.aes_().clear_bit());
+        self.aes
+    }
+}
+
+impl BlockCipher for Aes {
+    fn encrypt_block(&mut self, block: &mut [u8; 16]) {
+        self.process(block, Mode::Ecb);
+    }
+}
+
+#[cfg(feature = "dma")]
+mod dma {
+    use super::{Aes, Mode};
+    use crate::ccm::{self, BlockCipher};
+    use crate::dmac::{self, ChId, Channel, Ready, Transfer, TriggerAction, TriggerSource};
+
+    const BLOCK: usize = 16;
+
+    /// Words seen by the DMAC, either a slice or the INDATA register
+    struct Words {
+        ptr: *mut u32,
+        len: usize,
+        incrementing: bool,
+    }
+
+    unsafe impl dmac::Buffer for Words {
+        type Beat = u32;
+
+        fn dma_ptr(&mut self) -> *mut u32 {
+            if self.incrementing {
+                // SAFETY: One past the end of the slice
+                unsafe { self.ptr.add(self.len) }
+            } else {
+                self.ptr
+            }
+        }
+
+        fn incrementing(&self) -> bool {
+            self.incrementing
+        }
+
+        fn buffer_len(&self) -> usize {
+            self.len
+        }
+    }
+
+    /// The AES peripheral, streaming the counter mode key stream with two DMA
+    /// channels: one writes the blocks to INDATA, the other reads the
+    /// results back in place
+    ///
+    /// Single blocks, e.g. those of the CBC-MAC, are still encrypted by the
+    /// CPU. The peripheral only increments the counter between blocks, so the
+    /// DMAC is used for payloads of two blocks or more, stored at a word
+    /// aligned address; others are encrypted one block at a time.
+    pub struct AesDma<W: ChId, R: ChId> {
+        aes: Aes,
+        /// Moved into the DMA transfers while the blocks are streamed
+        channels: Option<(Channel<W, Ready>, Channel<R, Ready>)>,
+    }
+
+    impl<W: ChId, R: ChId> AesDma<W, R> {
+        /// Uses `write_channel` to feed the peripheral and `read_channel` to
+        /// read the results back
+        pub fn new(
+            aes: Aes,
+            write_channel: Channel<W, Ready>,
+            read_channel: Channel<R, Ready>,
+        ) -> Self {
+            Self {
+                aes,
+                channels: Some((write_channel, read_channel)),
+            }
+        }
+
+        /// Returns the peripheral and the DMA channels
+        pub fn free(self) -> (Aes, Channel<W, Ready>, Channel<R, Ready>) {
+            let (write, read) = self.channels.unwrap();
+            (self.aes, write, read)
+        }
+
+        /// Streams `blocks` through the peripheral, configured in counter
+        /// mode, and waits for the last result
+        fn stream(&mut self, blocks: &mut [u8]) {
+            let (write, read) = self.channels.take().unwrap();
+            let len = blocks.len() / 4;
+            let ptr = blocks.as_mut_ptr() as *mut u32;
+            let indata = self.aes.aes.indata.as_ptr();
+            let slice = || Words {
+                ptr,
+                len,
+                incrementing: true,
+            };
+            let register = || Words {
+                ptr: indata,
+                len,
+                incrementing: false,
+            };
+            // SAFETY: Both transfers are waited on before `blocks` goes out of
+            // scope. The result of a block is only read after the block was
+            // written, so they can share the buffer.
+            let read = unsafe { Transfer::new_unchecked(read, register(), slice(), false) }
+                .begin(TriggerSource::AES_RD, TriggerAction::BURST);
+            let write = unsafe { Transfer::new_unchecked(write, slice(), register(), false) }
+                .begin(TriggerSource::AES_WR, TriggerAction::BURST);
+            let (write, _, _) = write.wait();
+            let (read, _, _) = read.wait();
+            self.channels = Some((write, read));
+        }
+    }
+
+    impl<W: ChId, R: ChId> BlockCipher for AesDma<W, R> {
+        fn encrypt_block(&mut self, block: &mut [u8; 16]) {
+            self.aes.encrypt_block(block);
+        }
+
+        fn apply_keystream(&mut self, counter: &[u8; 16], data: &mut [u8]) {
+            // The DMAC moves whole words
+            if data.len() < 2 * BLOCK || data.as_ptr() as usize % 4 != 0 {
+                return ccm::keystream(self, counter, data);
+            }
+            self.aes.configure(Mode::Counter);
+            for (word, bytes) in self.aes.aes.intvectv.iter().zip(counter.chunks(4)) {
+                let value = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                word.write(|w| unsafe { w.bits(value) });
+            }
+            // NEWMSG loads the counter for the first block, and must be cleared
+            // for the next ones, which the DMAC can't do. The first block is
+            // encrypted by the CPU.
+            let (first, rest) = data.split_at_mut(BLOCK);
+            self.aes.aes.ctrlb.write(|w| w.newmsg().set_bit());
+            self.aes.process(first, Mode::Counter);
+            self.aes.aes.ctrlb.write(|w| w.newmsg().clear_bit());
+            self.stream(rest);
+            self.aes.configure(Mode::Ecb);
+        }
+    }
+}
@@ -1,5 +1,6 @@
 //! NVM Software Calibration Area Mapping
 // See 9.5 NVM Software Calibration Area Mapping, page 57
+// and 9.6 Temperature Log Row, page 58
 
 use core::ptr;
 
@@ -59,3 +60,59 @@ pub fn adc1_biasref_scale_cal() -> u8 {
 pub fn adc1_biasr2r_scale_cal() -> u8 {
     cal(3, 0, 0b111) as u8
 }
+
+// "The temperature log row can be read at address 0x00800100."
+const TEMP_LOG_ADDR: u32 = 0x00800100;
+
+/// Factory measurements of the PTAT and CTAT temperature sensors, at a low
+/// and a high temperature, from the temperature log row
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TemperatureLog {
+    /// Low temperature, in m°C
+    pub low_temp: i32,
+    /// High temperature, in m°C
+    pub high_temp: i32,
+    /// 12 bit reading of the PTAT sensor at the low temperature
+    pub ptat_low: u16,
+    /// 12 bit reading of the PTAT sensor at the high temperature
+    pub ptat_high: u16,
+    /// 12 bit reading of the CTAT sensor at the low temperature
+    pub ctat_low: u16,
+    /// 12 bit reading of the CTAT sensor at the high temperature
+    pub ctat_high: u16,
+}
+
+/// Returns the factory measurements of the temperature sensors
+pub fn temperature_log() -> TemperatureLog {
+    let (w0, w1, w2) = unsafe {
+        let addr = TEMP_LOG_ADDR as *const u32;
+        (
+            ptr::read(addr),
+            ptr::read(addr.add(1)),
+            ptr::read(addr.add(2)),
+        )
+    };
+    let temp = |int: u32, dec: u32| (int & 0xff) as i32 * 1000 + (dec & 0xf) as i32 * 100;
+    TemperatureLog {
+        low_temp: temp(w0, w0 >> 8),
+        high_temp: temp(w0 >> 12, w0 >> 20),
+        ptat_low: (w1 >> 8 & 0xfff) as u16,
+        ptat_high: (w1 >> 20 & 0xfff) as u16,
+        ctat_low: (w2 & 0xfff) as u16,
+        ctat_high: (w2 >> 12 & 0xfff) as u16,
+    }
+}
+
+impl TemperatureLog {
+    /// Converts 12 bit readings of the PTAT and CTAT sensors, with the same
+    /// reference, to m°C
+    pub fn temperature(&self, ptat: u16, ctat: u16) -> i32 {
+        let (tl, th) = (self.low_temp as i64, self.high_temp as i64);
+        let (vpl, vph) = (self.ptat_low as i64, self.ptat_high as i64);
+        let (vcl, vch) = (self.ctat_low as i64, self.ctat_high as i64);
+        let (tp, tc) = (ptat as i64, ctat as i64);
+        let num = tl * vph * tc - vpl * th * tc - tl * vch * tp + th * vcl * tp;
+        let den = vcl * tp - vch * tp - vpl * tc + vph * tc;
+        (num / den.max(1)) as i32
+    }
+}
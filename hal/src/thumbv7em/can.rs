@@ -0,0 +1,463 @@
+//! # Controller Area Network
+//!
+//! Driver for the MCAN peripherals of the SAME51 and SAME54, used as classic
+//! CAN 2.0 controllers. Every frame on the bus is accepted into RX FIFO 0, and
+//! frames are sent through the TX FIFO. Both FIFOs live in a [`MessageRam`]
+//! lent to the driver for its whole life. The driver implements the
+//! [`embedded_hal::can::nb::Can`] trait.
+//!
+//! The MCAN only addresses the low 16 bits of its message RAM, so the
+//! `MessageRam` must be in the first 64 kB of SRAM. A `static` in `.bss` is
+//! placed there by the default linker script.
+//!
+//! The TX and RX pins are not handled by the driver: they must be put in
+//! function I for CAN0, or function H for CAN1.
+//!
+//! ```
+//! static mut RAM: MessageRam<16, 8> = MessageRam::new();
+//!
+//! let gclk0 = clocks.gclk0();
+//! let clock = clocks.can1(&gclk0).unwrap();
+//! let ram = unsafe { &mut RAM };
+//! let mut can = Can::new(peripherals.CAN1, &clock, &mut peripherals.MCLK, ram, 500.kHz())?;
+//!
+//! let id = StandardId::new(0x123).unwrap();
+//! nb::block!(can.transmit(&Frame::new(id, &[1, 2, 3]).unwrap()))?;
+//! let reply = nb::block!(can.receive())?;
+//! ```
+
+use core::ops::Deref;
+use core::ptr;
+
+use embedded_hal::can::{self, ErrorKind, ExtendedId, Id, StandardId};
+
+use crate::clock::Can0Clock;
+#[cfg(any(feature = "same51j", feature = "same51n", feature = "same54"))]
+use crate::clock::Can1Clock;
+use crate::target_device::can0::psr::LEC_A;
+use crate::target_device::can0::RegisterBlock;
+#[cfg(any(feature = "same51j", feature = "same51n", feature = "same54"))]
+use crate::target_device::CAN1;
+use crate::target_device::{CAN0, MCLK};
+use crate::time::Hertz;
+use crate::typelevel::Sealed;
+
+//==============================================================================
+// Instances
+//==============================================================================
+
+/// An MCAN peripheral
+pub trait Instance: Sealed + Deref<Target = RegisterBlock> {
+    /// The generic clock of the peripheral
+    type Clock;
+
+    /// Returns the frequency of the generic clock
+    fn freq(clock: &Self::Clock) -> Hertz;
+
+    /// Enables the AHB clock of the peripheral
+    fn enable_mclk(mclk: &mut MCLK);
+}
+
+macro_rules! can {
+    ($(#[$attr:meta])* $CAN:ident: ($Clock:ident, $ahb:ident)) => {
+        $(#[$attr])*
+        impl Sealed for $CAN {}
+
+        $(#[$attr])*
+        impl Instance for $CAN {
+            type Clock = $Clock;
+
+            fn freq(clock: &$Clock) -> Hertz {
+                clock.freq()
+            }
+
+            fn enable_mclk(mclk: &mut MCLK) {
+                mclk.ahbmask.modify(|_, w| w.$ahb().set_bit());
+            }
+        }
+    };
+}
+
+can!(CAN0: (Can0Clock, can0_));
+can!(
+    #[cfg(any(feature = "same51j", feature = "same51n", feature = "same54"))]
+    CAN1: (Can1Clock, can1_)
+);
+
+//==============================================================================
+// Message RAM
+//==============================================================================
+
+/// A message RAM element, sized for the 8 data bytes of a classic frame
+type Element = [u32; 4];
+
+/// The message RAM of an MCAN, with `RX` elements in RX FIFO 0 and `TX`
+/// elements in the TX FIFO
+///
+/// RX FIFO 0 holds up to 64 elements and the TX FIFO up to 32.
+#[repr(C)]
+pub struct MessageRam<const RX: usize, const TX: usize> {
+    rx: [Element; RX],
+    tx: [Element; TX],
+}
+
+impl<const RX: usize, const TX: usize> MessageRam<RX, TX> {
+    /// Creates an empty message RAM
+    pub const fn new() -> Self {
+        assert!(RX >= 1 && RX <= 64, "RX FIFO 0 holds 1 to 64 elements");
+        assert!(TX >= 1 && TX <= 32, "The TX FIFO holds 1 to 32 elements");
+        Self {
+            rx: [[0; 4]; RX],
+            tx: [[0; 4]; TX],
+        }
+    }
+}
+
+impl<const RX: usize, const TX: usize> Default for MessageRam<RX, TX> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//==============================================================================
+// Frames
+//==============================================================================
+
+/// The identifier bits of the first word of an element
+const XTD: u32 = 1 << 30;
+const RTR: u32 = 1 << 29;
+const STD_SHIFT: u32 = 18;
+
+/// A classic CAN frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    id: Id,
+    remote: bool,
+    dlc: u8,
+    data: [u8; 8],
+}
+
+impl Frame {
+    /// Encodes the frame into a TX FIFO element
+    fn to_element(&self) -> Element {
+        let mut t0 = match self.id {
+            Id::Standard(id) => (id.as_raw() as u32) << STD_SHIFT,
+            Id::Extended(id) => XTD | id.as_raw(),
+        };
+        if self.remote {
+            t0 |= RTR;
+        }
+        let t1 = (self.dlc as u32) << 16;
+        [
+            t0,
+            t1,
+            u32::from_le_bytes([self.data[0], self.data[1], self.data[2], self.data[3]]),
+            u32::from_le_bytes([self.data[4], self.data[5], self.data[6], self.data[7]]),
+        ]
+    }
+
+    /// Decodes an RX FIFO element
+    fn from_element(element: &Element) -> Self {
+        let [r0, r1, r2, r3] = *element;
+        // Safety: the MCAN only stores 11 and 29 bit identifiers
+        let id = if r0 & XTD != 0 {
+            Id::Extended(unsafe { ExtendedId::new_unchecked(r0 & 0x1fff_ffff) })
+        } else {
+            Id::Standard(unsafe { StandardId::new_unchecked((r0 >> STD_SHIFT) as u16 & 0x7ff) })
+        };
+        let mut data = [0; 8];
+        data[..4].copy_from_slice(&r2.to_le_bytes());
+        data[4..].copy_from_slice(&r3.to_le_bytes());
+        Self {
+            id,
+            remote: r0 & RTR != 0,
+            dlc: (r1 >> 16) as u8 & 0xf,
+            data,
+        }
+    }
+}
+
+impl can::Frame for Frame {
+    fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+        if data.len() > 8 {
+            return None;
+        }
+        let mut frame = Self {
+            id: id.into(),
+            remote: false,
+            dlc: data.len() as u8,
+            data: [0; 8],
+        };
+        frame.data[..data.len()].copy_from_slice(data);
+        Some(frame)
+    }
+
+    fn new_remote(id: impl Into<Id>, dlc: usize) -> Option<Self> {
+        if dlc > 8 {
+            return None;
+        }
+        Some(Self {
+            id: id.into(),
+            remote: true,
+            dlc: dlc as u8,
+            data: [0; 8],
+        })
+    }
+
+    fn is_extended(&self) -> bool {
+        matches!(self.id, Id::Extended(_))
+    }
+
+    fn is_remote_frame(&self) -> bool {
+        self.remote
+    }
+
+    fn id(&self) -> Id {
+        self.id
+    }
+
+    fn dlc(&self) -> usize {
+        self.dlc as usize
+    }
+
+    fn data(&self) -> &[u8] {
+        if self.remote {
+            &[]
+        } else {
+            // A DLC of 9 to 15 still means 8 bytes in a classic frame
+            &self.data[..(self.dlc as usize).min(8)]
+        }
+    }
+}
+
+//==============================================================================
+// Errors
+//==============================================================================
+
+/// Errors of the CAN driver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The bitrate can't be reached exactly from the clock of the peripheral
+    BitTiming,
+    /// The controller left the bus after too many errors. It joins it again
+    /// after [`Can::recover`].
+    BusOff,
+    /// Frames were lost because RX FIFO 0 was full
+    Overrun,
+}
+
+impl can::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Overrun => ErrorKind::Overrun,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+//==============================================================================
+// Bit timing
+//==============================================================================
+
+/// Nominal bit timing, as the values of the NBTP fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BitTiming {
+    brp: u16,
+    tseg1: u8,
+    tseg2: u8,
+}
+
+impl BitTiming {
+    /// Finds the bit timing with the most time quanta per bit which reaches
+    /// `bitrate` exactly, sampling at about 87.5% of the bit
+    fn new(clock: u32, bitrate: u32) -> Option<Self> {
+        (8..=25u32).rev().find_map(|quanta| {
+            let rate = bitrate.checked_mul(quanta).filter(|&r| r != 0)?;
+            if clock % rate != 0 {
+                return None;
+            }
+            let prescaler = clock / rate;
+            if !(1..=512).contains(&prescaler) {
+                return None;
+            }
+            // The sync segment takes one time quantum, and the phase segment
+            // after the sample point at least two
+            let sample = ((quanta * 7 + 4) / 8).min(quanta - 2);
+            Some(Self {
+                brp: (prescaler - 1) as u16,
+                tseg1: (sample - 2) as u8,
+                tseg2: (quanta - sample - 1) as u8,
+            })
+        })
+    }
+}
+
+//==============================================================================
+// Driver
+//==============================================================================
+
+/// A classic CAN controller, with its message RAM
+pub struct Can<I: Instance, const RX: usize, const TX: usize> {
+    can: I,
+    ram: &'static mut MessageRam<RX, TX>,
+}
+
+impl<I: Instance, const RX: usize, const TX: usize> Can<I, RX, TX> {
+    /// Sets up the controller at `bitrate`, and joins the bus
+    ///
+    /// Panics if `ram` is not in the first 64 kB of SRAM.
+    pub fn new(
+        can: I,
+        clock: &I::Clock,
+        mclk: &mut MCLK,
+        ram: &'static mut MessageRam<RX, TX>,
+        bitrate: impl Into<Hertz>,
+    ) -> Result<Self, Error> {
+        let timing = BitTiming::new(I::freq(clock).to_Hz(), bitrate.into().to_Hz())
+            .ok_or(Error::BitTiming)?;
+        let start = ram as *const _ as u32;
+        let end = start + core::mem::size_of::<MessageRam<RX, TX>>() as u32;
+        assert!(
+            start >= 0x2000_0000 && end <= 0x2001_0000,
+            "The message RAM must be in the first 64 kB of SRAM"
+        );
+
+        I::enable_mclk(mclk);
+        let mut can = Self { can, ram };
+        can.enter_init();
+
+        // Safety: the fields are in range, and the FIFOs are in the message RAM
+        unsafe {
+            can.can.nbtp.write(|w| {
+                w.nbrp().bits(timing.brp);
+                w.ntseg1().bits(timing.tseg1);
+                w.ntseg2().bits(timing.tseg2);
+                w.nsjw().bits(timing.tseg2)
+            });
+            can.can.sidfc.write(|w| w.lss().bits(0));
+            can.can.xidfc.write(|w| w.lse().bits(0));
+            can.can.rxf0c.write(|w| {
+                w.f0sa().bits(can.ram.rx.as_ptr() as u16);
+                w.f0s().bits(RX as u8)
+            });
+            can.can.txbc.write(|w| {
+                w.tbsa().bits(can.ram.tx.as_ptr() as u16);
+                w.ndtb().bits(0);
+                w.tfqs().bits(TX as u8)
+            });
+        }
+        can.can.rxesc.write(|w| w.f0ds().data8());
+        can.can.txesc.write(|w| w.tbds().data8());
+        // Without filters, every frame is accepted into RX FIFO 0
+        can.can.gfc.write(|w| {
+            w.anfs().rxf0();
+            w.anfe().rxf0();
+            w.rrfs().clear_bit();
+            w.rrfe().clear_bit()
+        });
+
+        can.leave_init();
+        Ok(can)
+    }
+
+    /// Leaves the bus, and returns the peripheral and the message RAM
+    pub fn free(mut self) -> (I, &'static mut MessageRam<RX, TX>) {
+        self.enter_init();
+        (self.can, self.ram)
+    }
+
+    /// Joins the bus again once the controller went bus-off
+    ///
+    /// The controller waits for 129 occurrences of 11 recessive bits before it
+    /// takes part in the traffic again.
+    pub fn recover(&mut self) {
+        if self.can.psr.read().bo().bit_is_set() {
+            self.leave_init();
+        }
+    }
+
+    /// Returns the error on the bus since the last call, if any
+    pub fn last_error(&mut self) -> Option<ErrorKind> {
+        // Reading PSR resets LEC to NC
+        match self.can.psr.read().lec().variant() {
+            LEC_A::NONE | LEC_A::NC => None,
+            LEC_A::STUFF => Some(ErrorKind::Stuff),
+            LEC_A::FORM => Some(ErrorKind::Form),
+            LEC_A::ACK => Some(ErrorKind::Acknowledge),
+            LEC_A::BIT1 | LEC_A::BIT0 => Some(ErrorKind::Bit),
+            LEC_A::CRC => Some(ErrorKind::Crc),
+        }
+    }
+
+    /// Returns the transmit and receive error counters
+    pub fn error_counters(&self) -> (u8, u8) {
+        let ecr = self.can.ecr.read();
+        (ecr.tec().bits(), ecr.rec().bits())
+    }
+
+    /// Stops the controller and allows its configuration to be changed
+    fn enter_init(&mut self) {
+        self.can.cccr.modify(|_, w| w.init().set_bit());
+        while self.can.cccr.read().init().bit_is_clear() {}
+        self.can.cccr.modify(|_, w| w.cce().set_bit());
+    }
+
+    /// Starts the controller, which joins the bus after 11 recessive bits
+    fn leave_init(&mut self) {
+        self.can
+            .cccr
+            .modify(|_, w| w.cce().clear_bit().init().clear_bit());
+        while self.can.cccr.read().init().bit_is_set() {}
+    }
+
+    fn check_bus_off(&self) -> Result<(), Error> {
+        if self.can.psr.read().bo().bit_is_set() {
+            Err(Error::BusOff)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<I: Instance, const RX: usize, const TX: usize> can::nb::Can for Can<I, RX, TX> {
+    type Frame = Frame;
+    type Error = Error;
+
+    /// Queues `frame` in the TX FIFO. The frames are sent in order, so none is
+    /// ever replaced.
+    fn transmit(&mut self, frame: &Frame) -> nb::Result<Option<Frame>, Error> {
+        self.check_bus_off()?;
+        let status = self.can.txfqs.read();
+        if status.tfqf().bit_is_set() {
+            return Err(nb::Error::WouldBlock);
+        }
+        let index = status.tfqpi().bits() as usize;
+        for (word, value) in self.ram.tx[index].iter_mut().zip(frame.to_element()) {
+            // Safety: the MCAN doesn't read an element before it is requested
+            unsafe { ptr::write_volatile(word, value) };
+        }
+        self.can.txbar.write(|w| unsafe { w.bits(1 << index) });
+        Ok(None)
+    }
+
+    fn receive(&mut self) -> nb::Result<Frame, Error> {
+        if self.can.ir.read().rf0l().bit_is_set() {
+            self.can.ir.write(|w| w.rf0l().set_bit());
+            return Err(nb::Error::Other(Error::Overrun));
+        }
+        let status = self.can.rxf0s.read();
+        if status.f0fl().bits() == 0 {
+            self.check_bus_off()?;
+            return Err(nb::Error::WouldBlock);
+        }
+        let index = status.f0gi().bits();
+        let mut element = [0; 4];
+        for (value, word) in element.iter_mut().zip(&self.ram.rx[index as usize]) {
+            // Safety: the MCAN doesn't write an element until it is
+            // acknowledged
+            *value = unsafe { ptr::read_volatile(word) };
+        }
+        self.can.rxf0a.write(|w| unsafe { w.f0ai().bits(index) });
+        Ok(Frame::from_element(&element))
+    }
+}
@@ -3,10 +3,13 @@
 //! before you can set up most of the peripherals on the atsamd51 device.
 //! The other types in this module are used to enforce at compile time
 //! that the peripherals have been correctly configured.
+use crate::gpio::v2::{self as gpio, AlternateM, Pin, PinId, PinMode};
 use crate::target_device::gclk::genctrl::SRC_A::*;
 use crate::target_device::gclk::pchctrl::GEN_A::*;
+#[cfg(feature = "overclock")]
+use crate::target_device::SUPC;
 use crate::target_device::{self, GCLK, MCLK, NVMCTRL, OSC32KCTRL, OSCCTRL};
-use crate::time::{Hertz, MegaHertz};
+use crate::time::Hertz;
 
 pub type ClockGenId = target_device::gclk::pchctrl::GEN_A;
 pub type ClockSource = target_device::gclk::genctrl::SRC_A;
@@ -136,6 +139,11 @@ impl State {
         self.wait_for_sync();
     }
 
+    fn disable_gclk(&mut self, gclk: ClockGenId) {
+        self.gclk.genctrl[u8::from(gclk) as usize].modify(|_, w| w.genen().clear_bit());
+        self.wait_for_sync();
+    }
+
     fn enable_clock_generator(&mut self, clock: ClockId, generator: ClockGenId) {
         self.gclk.pchctrl[u8::from(clock) as usize].write(|w| unsafe {
             w.gen().bits(generator.into());
@@ -160,6 +168,7 @@ pub struct GenericClockController {
     state: State,
     gclks: [Hertz; 12],
     used_clocks: u64,
+    dpll0: Hertz,
 }
 
 impl GenericClockController {
@@ -248,18 +257,19 @@ impl GenericClockController {
             gclks: [
                 OSC120M_FREQ,
                 OSC32K_FREQ,
-                Hertz(0),
-                Hertz(0),
-                Hertz(0),
-                MegaHertz(2).into(),
-                Hertz(0),
-                Hertz(0),
-                Hertz(0),
-                Hertz(0),
-                Hertz(0),
-                Hertz(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
+                Hertz::MHz(2),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
+                Hertz::from_raw(0),
             ],
             used_clocks: 1u64 << u8::from(ClockId::FDPLL0),
+            dpll0: OSC120M_FREQ,
         }
     }
 
@@ -284,7 +294,7 @@ impl GenericClockController {
     /// returns None.
     pub fn get_gclk(&mut self, gclk: ClockGenId) -> Option<GClock> {
         let idx = u8::from(gclk) as usize;
-        if self.gclks[idx].0 == 0 {
+        if self.gclks[idx].to_Hz() == 0 {
             None
         } else {
             Some(GClock {
@@ -311,20 +321,98 @@ impl GenericClockController {
         improve_duty_cycle: bool,
     ) -> Option<GClock> {
         let idx = u8::from(gclk) as usize;
-        if self.gclks[idx].0 != 0 {
+        if self.gclks[idx].to_Hz() != 0 {
             return None;
         }
         self.state
             .set_gclk_divider_and_source(gclk, divider, src, improve_duty_cycle);
-        let freq: Hertz = match src {
+        let freq = self.source_freq(src);
+        self.gclks[idx] = freq / divider as u32;
+        Some(GClock { gclk, freq })
+    }
+
+    /// Changes the divider of a configured clock generator, keeping its
+    /// source, and records `freq` as its new frequency
+    pub(crate) fn set_gclk_divider(&mut self, gclk: ClockGenId, divider: u16, freq: Hertz) {
+        let idx = u8::from(gclk) as usize;
+        self.state.gclk.genctrl[idx].modify(|_, w| unsafe { w.div().bits(divider) });
+        while self.state.gclk.syncbusy.read().genctrl().bits() & (1 << idx) != 0 {}
+        self.gclks[idx] = freq;
+    }
+
+    /// Gives read access to the generator and peripheral channel registers
+    pub(crate) fn gclk(&self) -> &GCLK {
+        &self.state.gclk
+    }
+
+    fn source_freq(&self, src: ClockSource) -> Hertz {
+        match src {
             XOSC32K | OSCULP32K => OSC32K_FREQ,
             GCLKGEN1 => self.gclks[1],
             DFLL => OSC48M_FREQ,
-            DPLL0 => OSC120M_FREQ,
+            DPLL0 => self.dpll0,
             XOSC0 | XOSC1 | GCLKIN | DPLL1 => unimplemented!(),
-        };
-        self.gclks[idx] = Hertz(freq.0 / divider as u32);
-        Some(GClock { gclk, freq })
+        }
+    }
+
+    /// Configures the clock generator available on `pin` to divide `src`
+    /// down to `freq`, and outputs it on the pin. See [`ClockOut`].
+    ///
+    /// The divider is rounded to the nearest integer and limited to the
+    /// range of the generator, so the actual frequency, returned by
+    /// [`ClockOut::freq`], may differ from the requested one.
+    ///
+    /// Returns the pin back if the clock generator is already configured, or
+    /// if `freq` is zero.
+    pub fn clock_out<I, M, F>(
+        &mut self,
+        pin: Pin<I, M>,
+        src: ClockSource,
+        freq: F,
+    ) -> Result<ClockOut<I>, Pin<I, M>>
+    where
+        I: GclkOutPin,
+        M: PinMode,
+        F: Into<Hertz>,
+    {
+        let idx = u8::from(I::GCLK) as usize;
+        let freq = freq.into().to_Hz();
+        if self.gclks[idx].to_Hz() != 0 || freq == 0 {
+            return Err(pin);
+        }
+        let max_divider = if I::GCLK == GCLK1 { 0xffff } else { 0xff };
+        let divider = ((self.source_freq(src).to_Hz() + freq / 2) / freq)
+            .max(1)
+            .min(max_divider);
+        let gclk = self
+            .configure_gclk_divider_and_source(I::GCLK, divider as u16, src, true)
+            .unwrap();
+        Ok(ClockOut {
+            pin: pin.into_mode(),
+            gclk: gclk.gclk,
+            freq: self.gclks[idx],
+            owned: true,
+        })
+    }
+
+    /// Outputs an already configured clock generator, such as the 32KHz
+    /// `gclk1`, on `pin`. The generator is left running when the
+    /// [`ClockOut`] is freed.
+    ///
+    /// Returns the pin back if the clock generator is not configured.
+    pub fn gclk_out<I: GclkOutPin, M: PinMode>(
+        &mut self,
+        pin: Pin<I, M>,
+    ) -> Result<ClockOut<I>, Pin<I, M>> {
+        match self.get_gclk(I::GCLK) {
+            Some(gclk) => Ok(ClockOut {
+                pin: pin.into_mode(),
+                gclk: gclk.gclk,
+                freq: gclk.freq,
+                owned: false,
+            }),
+            None => Err(pin),
+        }
     }
 
     /// Enables or disables the given GClk from operation in standby.
@@ -451,12 +539,207 @@ clock_generator!(
     (cm4_trace, Cm4TraceClock, CM4_TRACE),
 );
 
+/// A pin which can output a clock generator, through its `GCLK_IO`
+/// function
+pub trait GclkOutPin: PinId {
+    /// The clock generator wired to the pin
+    const GCLK: ClockGenId;
+}
+
+macro_rules! gclk_out_pins {
+    ($($(#[$attr:meta])* $Pin:ident: $gclk:ident,)+) => {
+        $(
+            $(#[$attr])*
+            impl GclkOutPin for gpio::$Pin {
+                const GCLK: ClockGenId = $gclk;
+            }
+        )+
+    };
+}
+
+gclk_out_pins! {
+    PA10: GCLK4,
+    PA11: GCLK5,
+    PA14: GCLK0,
+    PA15: GCLK1,
+    PA16: GCLK2,
+    PA17: GCLK3,
+    PB10: GCLK4,
+    PB11: GCLK5,
+    #[cfg(feature = "min-samd51j")]
+    PB12: GCLK6,
+    #[cfg(feature = "min-samd51j")]
+    PB13: GCLK7,
+    #[cfg(feature = "min-samd51j")]
+    PB14: GCLK0,
+    #[cfg(feature = "min-samd51j")]
+    PB15: GCLK1,
+    #[cfg(feature = "min-samd51j")]
+    PB16: GCLK2,
+    #[cfg(feature = "min-samd51j")]
+    PB17: GCLK3,
+    #[cfg(feature = "min-samd51n")]
+    PB18: GCLK4,
+    #[cfg(feature = "min-samd51n")]
+    PB19: GCLK5,
+    #[cfg(feature = "min-samd51n")]
+    PB20: GCLK6,
+    #[cfg(feature = "min-samd51n")]
+    PB21: GCLK7,
+    PB22: GCLK0,
+    PB23: GCLK1,
+}
+
+/// A clock generator output on a pin, e.g. to clock an external audio codec,
+/// camera or radio
+///
+/// Created by [`GenericClockController::clock_out`] or
+/// [`GenericClockController::gclk_out`].
+///
+/// ```
+/// let mclk = clocks
+///     .clock_out(pins.pb16, ClockSource::DPLL0, 12.MHz())
+///     .ok()
+///     .unwrap();
+/// assert_eq!(mclk.freq(), 12.MHz());
+/// ```
+pub struct ClockOut<I: GclkOutPin> {
+    pin: Pin<I, AlternateM>,
+    gclk: ClockGenId,
+    freq: Hertz,
+    owned: bool,
+}
+
+impl<I: GclkOutPin> ClockOut<I> {
+    /// The actual frequency output on the pin
+    pub fn freq(&self) -> Hertz {
+        self.freq
+    }
+
+    /// Stops the clock generator, unless it was already configured when the
+    /// output was created, and releases the pin
+    pub fn free(self, clocks: &mut GenericClockController) -> Pin<I, AlternateM> {
+        if self.owned {
+            clocks.state.disable_gclk(self.gclk);
+            clocks.gclks[u8::from(self.gclk) as usize] = Hertz::from_raw(0);
+        }
+        self.pin
+    }
+}
+
+#[cfg(feature = "overclock")]
+impl GenericClockController {
+    /// Runs DPLL0, and GCLK0 with it, at `speed`. The flash wait states are
+    /// raised first, and the main regulator is set to the LDO.
+    ///
+    /// Other generators fed by DPLL0 speed up too, and peripherals clocked
+    /// from them must be set up again.
+    pub fn overclock(
+        &mut self,
+        speed: Overclock,
+        nvmctrl: &mut NVMCTRL,
+        oscctrl: &mut OSCCTRL,
+        supc: &mut SUPC,
+        _ack: OutOfSpec,
+    ) -> GClock {
+        let freq = speed.freq();
+
+        // The buck converter needs an inductor which most boards don't have,
+        // and the LDO holds the core voltage better under fast load steps
+        supc.vreg.modify(|_, w| w.sel().ldo());
+        while supc.status.read().vregrdy().bit_is_clear() {}
+
+        let mut flash = crate::nvmctrl::FlashConfig::read(nvmctrl);
+        flash.wait_states = crate::nvmctrl::wait_states(freq);
+        flash.auto_wait_states = false;
+        flash.apply(nvmctrl);
+
+        // Run from the DFLL while DPLL0 locks on the new ratio
+        let gclk = &mut self.state.gclk;
+        gclk.genctrl[0].modify(|_, w| w.src().dfll());
+        while gclk.syncbusy.read().genctrl().is_gclk0() {}
+
+        let dpll = &oscctrl.dpll[0];
+        dpll.dpllctrla.modify(|_, w| w.enable().clear_bit());
+        while dpll.dpllsyncbusy.read().enable().bit_is_set() {}
+        dpll.dpllratio.write(|w| unsafe {
+            w.ldr().bits(speed.ratio());
+            w.ldrfrac().bits(0)
+        });
+        while dpll.dpllsyncbusy.read().dpllratio().bit_is_set() {}
+        dpll.dpllctrla.modify(|_, w| w.enable().set_bit());
+        while dpll.dpllsyncbusy.read().enable().bit_is_set() {}
+        wait_for_dpllrdy(oscctrl);
+
+        gclk.genctrl[0].modify(|_, w| w.src().dpll0());
+        while gclk.syncbusy.read().genctrl().is_gclk0() {}
+
+        self.dpll0 = freq;
+        self.gclks[0] = freq;
+        self.gclk0()
+    }
+}
+
+/// Acknowledges running the device outside of its specification
+///
+/// The SAMx5x is specified up to 120MHz. Most parts run faster at room
+/// temperature, but nothing guarantees it: flash reads, peripherals or the
+/// core may fail, at any speed above 120MHz, on some parts or at some
+/// temperatures.
+#[cfg(feature = "overclock")]
+pub struct OutOfSpec(());
+
+#[cfg(feature = "overclock")]
+impl OutOfSpec {
+    /// # Safety
+    ///
+    /// The device is run outside of its specification. The application must
+    /// be validated on the actual hardware, and must not be relied upon
+    /// where a failure matters.
+    pub unsafe fn acknowledge() -> Self {
+        OutOfSpec(())
+    }
+}
+
+/// Speeds of [`GenericClockController::overclock`]
+///
+/// These keep DPLL0 within its 200MHz output range and use the 2MHz
+/// reference of GCLK5 set up by the clock controller.
+#[cfg(feature = "overclock")]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Overclock {
+    /// 150MHz
+    Mhz150,
+    /// 180MHz
+    Mhz180,
+    /// 200MHz, the maximum of DPLL0
+    Mhz200,
+}
+
+#[cfg(feature = "overclock")]
+impl Overclock {
+    /// Returns the frequency of GCLK0
+    pub const fn freq(self) -> Hertz {
+        Hertz::from_raw(match self {
+            Overclock::Mhz150 => 150_000_000,
+            Overclock::Mhz180 => 180_000_000,
+            Overclock::Mhz200 => 200_000_000,
+        })
+    }
+
+    /// Returns the LDR value of DPLL0, which multiplies its reference by
+    /// `LDR + 1`
+    const fn ratio(self) -> u16 {
+        (self.freq().to_Hz() / 2_000_000 - 1) as u16
+    }
+}
+
 /// The frequency of the 48Mhz source.
-pub const OSC48M_FREQ: Hertz = Hertz(48_000_000);
+pub const OSC48M_FREQ: Hertz = Hertz::from_raw(48_000_000);
 /// The frequency of the 32Khz source.
-pub const OSC32K_FREQ: Hertz = Hertz(32_768);
+pub const OSC32K_FREQ: Hertz = Hertz::from_raw(32_768);
 /// The frequency of the 120Mhz source.
-pub const OSC120M_FREQ: Hertz = Hertz(120_000_000);
+pub const OSC120M_FREQ: Hertz = Hertz::from_raw(120_000_000);
 
 fn set_flash_to_half_auto_wait_state(nvmctrl: &mut NVMCTRL) {
     // Zero indicates zero wait states, one indicates one wait state, etc.,
@@ -2,6 +2,7 @@ use crate::clock::EicClock;
 use crate::target_device;
 
 pub mod pin;
+pub mod wake;
 
 /// An External Interrupt Controller which is being configured.
 pub struct ConfigurableEIC {
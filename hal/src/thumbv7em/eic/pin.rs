@@ -147,6 +147,18 @@ crate::paste::item! {
                 }
             });
         }
+
+        /// Returns true if the pin is high, e.g. to tell which edge raised
+        /// the interrupt when sensing both
+        pub fn is_high(&self) -> bool {
+            self._pin._is_high()
+        }
+    }
+
+    impl<GPIO: AnyPin> ExternalInterrupt for [<$PadType $num>]<GPIO> {
+        fn id(&self) -> ExternalInterruptID {
+            $num
+        }
     }
 
     $(
@@ -0,0 +1,314 @@
+//! AC energy metering with sampling synchronized to the mains zero-crossings
+//!
+//! [`Meter`] samples the voltage and the current of an AC line with ADC0
+//! and ADC1, at a fixed number of points per mains cycle, and accumulates
+//! the samples into per-cycle RMS and active power readings. The sampling
+//! runs without the CPU, through the event system:
+//!
+//! * Comparator 0 of the AC compares the voltage signal on AIN0 (PA04) with
+//!   its bias on AIN1 (PA05), and emits an event on each rising
+//!   zero-crossing.
+//! * An event system channel routes that event to a TC, which restarts its
+//!   count, so that the sampling points are aligned to the zero-crossing.
+//! * A second channel routes the overflow events of the TC to both ADCs,
+//!   which sample the voltage and the current at the same instant.
+//!
+//! The CPU reads each pair of samples, e.g. from the ADC1 interrupt, and
+//! [`Meter::read`] returns a [`CycleReading`] at the end of each cycle.
+//!
+//! ```
+//! let timer = TimerCounter2::tc2_(&tc23_clock, peripherals.TC2, &mut mclk);
+//! let mut meter = Meter::new(
+//!     ac, &ac_clock, pa04, pa05, timer, adc0, adc1, &mut voltage, &mut current,
+//!     &mut evsys, &mut mclk, [0, 1], (50 * 64).Hz(),
+//! );
+//! meter.set_offsets(2048, 2048);
+//! loop {
+//!     if let Some(reading) = meter.read() {
+//!         let watts = reading.active_power() as f32 * POWER_SCALE;
+//!     }
+//! }
+//! ```
+
+use crate::adc::{Adc, AdcChannel};
+use crate::clock::AcClock;
+use crate::gpio::v2::{AlternateB, Pin, PA04, PA05};
+use crate::hal::timer::CountDown;
+use crate::target_device::{AC, ADC0, ADC1, EVSYS, MCLK, TC2, TC3};
+#[cfg(feature = "min-samd51j")]
+use crate::target_device::{TC4, TC5};
+use crate::time::{Hertz, Nanoseconds};
+use crate::timer::{EventUser, TimerCounter};
+
+/// Event system generator number of the comparator 0 output
+const GEN_AC_COMP0: u8 = 0x6b;
+/// Event system user number of the ADC0 start conversion input
+const USER_ADC0_START: usize = 55;
+/// Event system user number of the ADC1 start conversion input
+const USER_ADC1_START: usize = 57;
+
+/// A timer counter which can pace the sampling of a [`Meter`]
+pub trait SampleClock: EventUser {
+    /// Event system generator number of the TC overflow event
+    const OVF_EVENT: u8;
+}
+
+impl SampleClock for TC2 {
+    const OVF_EVENT: u8 = 0x4f;
+}
+
+impl SampleClock for TC3 {
+    const OVF_EVENT: u8 = 0x52;
+}
+
+#[cfg(feature = "min-samd51j")]
+impl SampleClock for TC4 {
+    const OVF_EVENT: u8 = 0x55;
+}
+
+#[cfg(feature = "min-samd51j")]
+impl SampleClock for TC5 {
+    const OVF_EVENT: u8 = 0x58;
+}
+
+/// Sums of the samples of one mains cycle
+///
+/// Values are in ADC counts, after subtraction of the offsets set with
+/// [`Meter::set_offsets`]. Multiply them by the scale factors of the voltage
+/// divider and of the current transformer to get volts, amperes and watts.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct CycleReading {
+    /// Number of sample pairs
+    pub samples: u32,
+    /// Sum of the squared voltage samples
+    pub v_squared: u64,
+    /// Sum of the squared current samples
+    pub i_squared: u64,
+    /// Sum of the products of the voltage and current samples
+    pub vi: i64,
+}
+
+impl CycleReading {
+    fn add(&mut self, v: i32, i: i32) {
+        self.samples += 1;
+        self.v_squared += (v as i64 * v as i64) as u64;
+        self.i_squared += (i as i64 * i as i64) as u64;
+        self.vi += v as i64 * i as i64;
+    }
+
+    /// RMS voltage, in ADC counts
+    pub fn v_rms(&self) -> u32 {
+        isqrt(self.v_squared / self.samples.max(1) as u64)
+    }
+
+    /// RMS current, in ADC counts
+    pub fn i_rms(&self) -> u32 {
+        isqrt(self.i_squared / self.samples.max(1) as u64)
+    }
+
+    /// Active power, the mean of the instantaneous power, in ADC counts
+    /// squared
+    pub fn active_power(&self) -> i64 {
+        self.vi / self.samples.max(1) as i64
+    }
+
+    /// Apparent power, the product of the RMS voltage and current, in ADC
+    /// counts squared
+    pub fn apparent_power(&self) -> u64 {
+        self.v_rms() as u64 * self.i_rms() as u64
+    }
+}
+
+fn isqrt(n: u64) -> u32 {
+    // Newton's method, starting above the root
+    let mut x = n;
+    let mut y = (x + 1) / 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x as u32
+}
+
+/// Voltage and current sampling synchronized to the zero-crossings of the
+/// voltage
+pub struct Meter<TC> {
+    ac: AC,
+    pins: (Pin<PA04, AlternateB>, Pin<PA05, AlternateB>),
+    timer: TimerCounter<TC>,
+    adc0: Adc<ADC0>,
+    adc1: Adc<ADC1>,
+    channels: [usize; 2],
+    offsets: (i32, i32),
+    cycle: CycleReading,
+}
+
+impl<TC: SampleClock> Meter<TC> {
+    /// Configures the comparator, the event system channels `channels`, the
+    /// timer and both ADCs, and starts sampling `voltage` with ADC0 and
+    /// `current` with ADC1, `sample_rate` times per second.
+    ///
+    /// The sample rate should be a multiple of the mains frequency. Both
+    /// ADCs should be set to the same resolution, sample length and
+    /// prescaler, so that their conversions end together.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new<V, I, R>(
+        ac: AC,
+        _clock: &AcClock,
+        ain0: Pin<PA04, AlternateB>,
+        ain1: Pin<PA05, AlternateB>,
+        mut timer: TimerCounter<TC>,
+        mut adc0: Adc<ADC0>,
+        mut adc1: Adc<ADC1>,
+        voltage: &mut V,
+        current: &mut I,
+        evsys: &mut EVSYS,
+        mclk: &mut MCLK,
+        channels: [u8; 2],
+        sample_rate: R,
+    ) -> Self
+    where
+        V: AdcChannel<ADC0>,
+        I: AdcChannel<ADC1>,
+        R: Into<Hertz>,
+    {
+        let channels = [channels[0] as usize, channels[1] as usize];
+        assert!(channels[0] != channels[1]);
+        assert!(channels.iter().all(|&c| c < evsys.channel.len()));
+
+        mclk.apbcmask.modify(|_, w| w.ac_().set_bit());
+        ac.ctrla.write(|w| w.swrst().set_bit());
+        while ac.syncbusy.read().swrst().bit_is_set() {}
+        ac.compctrl[0].write(|w| {
+            w.muxpos().pin0();
+            w.muxneg().pin1();
+            w.speed().high();
+            w.hysten().set_bit();
+            w.hyst().hyst50();
+            w.intsel().rising();
+            w.out().off()
+        });
+        ac.evctrl.write(|w| w.compeo0().set_bit());
+        ac.compctrl[0].modify(|_, w| w.enable().set_bit());
+        while ac.syncbusy.read().compctrl0().bit_is_set() {}
+        ac.ctrla.write(|w| w.enable().set_bit());
+        while ac.syncbusy.read().enable().bit_is_set() {}
+
+        let period: Nanoseconds = sample_rate.into().into_duration();
+        timer.start(period);
+        timer.set_overflow_event(true);
+        timer.set_retrigger_event(true);
+
+        mclk.apbbmask.modify(|_, w| w.evsys_().set_bit());
+        evsys.channel[channels[0]].channel.write(|w| {
+            unsafe { w.evgen().bits(GEN_AC_COMP0) };
+            w.path().asynchronous().edgsel().no_evt_output()
+        });
+        evsys.channel[channels[1]].channel.write(|w| {
+            unsafe { w.evgen().bits(TC::OVF_EVENT) };
+            w.path().asynchronous().edgsel().no_evt_output()
+        });
+        // The user registers hold the channel number plus one, zero
+        // disconnects the user
+        let zero_cross = channels[0] as u8 + 1;
+        let sample = channels[1] as u8 + 1;
+        evsys.user[TC::EVSYS_USER as usize].write(|w| unsafe { w.channel().bits(zero_cross) });
+        evsys.user[USER_ADC0_START].write(|w| unsafe { w.channel().bits(sample) });
+        evsys.user[USER_ADC1_START].write(|w| unsafe { w.channel().bits(sample) });
+
+        adc0.mux(voltage);
+        adc0.set_start_event(true);
+        adc0.power_up();
+        adc1.mux(current);
+        adc1.set_start_event(true);
+        adc1.power_up();
+
+        Self {
+            ac,
+            pins: (ain0, ain1),
+            timer,
+            adc0,
+            adc1,
+            channels,
+            offsets: (0, 0),
+            cycle: CycleReading::default(),
+        }
+    }
+
+    /// Sets the ADC counts of zero volts and zero amperes, usually the bias
+    /// at mid-scale
+    pub fn set_offsets(&mut self, voltage: u16, current: u16) {
+        self.offsets = (voltage as i32, current as i32);
+    }
+
+    /// Accumulates the last pair of samples, if a new one is ready. Returns
+    /// the reading of the previous cycle once a zero-crossing has been seen.
+    ///
+    /// Must be called at least once per sample, e.g. from the ADC1
+    /// interrupt, or samples are lost.
+    pub fn read(&mut self) -> Option<CycleReading> {
+        // Both conversions are started by the same event and take as long,
+        // so the result of ADC1 being ready means ADC0's is as well
+        let i = self.adc1.service_interrupt_ready()?;
+        let v = loop {
+            if let Some(v) = self.adc0.service_interrupt_ready() {
+                break v;
+            }
+        };
+        // The sampling restarts on the zero-crossing, so a pending
+        // comparator flag means this pair is the first one of a new cycle
+        let intflag = &self.ac.intflag;
+        let reading = if intflag.read().comp0().bit_is_set() {
+            intflag.write(|w| w.comp0().set_bit());
+            Some(core::mem::take(&mut self.cycle))
+        } else {
+            None
+        };
+        self.cycle
+            .add(v as i32 - self.offsets.0, i as i32 - self.offsets.1);
+        // The first cycle is partial
+        reading.filter(|reading| reading.samples > 0)
+    }
+
+    /// Enables the ADC1 result ready interrupt, which fires once per pair of
+    /// samples
+    pub fn enable_interrupt(&mut self) {
+        self.adc1.enable_interrupts();
+    }
+
+    /// Disables the ADC1 result ready interrupt
+    pub fn disable_interrupt(&mut self) {
+        self.adc1.disable_interrupts();
+    }
+
+    /// Stops sampling, disconnects the event system channels and releases
+    /// the peripherals and the pins
+    #[allow(clippy::type_complexity)]
+    pub fn free(
+        mut self,
+        evsys: &mut EVSYS,
+    ) -> (
+        AC,
+        (Pin<PA04, AlternateB>, Pin<PA05, AlternateB>),
+        TimerCounter<TC>,
+        Adc<ADC0>,
+        Adc<ADC1>,
+    ) {
+        self.ac.ctrla.write(|w| w.enable().clear_bit());
+        while self.ac.syncbusy.read().enable().bit_is_set() {}
+        self.timer.set_retrigger_event(false);
+        self.timer.set_overflow_event(false);
+        evsys.user[TC::EVSYS_USER as usize].reset();
+        evsys.user[USER_ADC0_START].reset();
+        evsys.user[USER_ADC1_START].reset();
+        for &channel in self.channels.iter() {
+            evsys.channel[channel].channel.reset();
+        }
+        self.adc1.disable_interrupts();
+        self.adc0.power_down();
+        self.adc0.set_start_event(false);
+        self.adc1.power_down();
+        self.adc1.set_start_event(false);
+        (self.ac, self.pins, self.timer, self.adc0, self.adc1)
+    }
+}
@@ -1,8 +1,15 @@
+pub mod aes;
 pub mod calibration;
+#[cfg(any(feature = "same51", feature = "same54"))]
+pub mod can;
 pub mod clock;
 pub mod eic;
+pub mod evsys;
+pub mod nvmctrl;
 pub mod qspi;
 pub(crate) mod sercom;
+pub mod sleep;
+pub mod sleepwalking;
 pub mod timer;
 pub mod trng;
 
@@ -18,8 +25,17 @@ pub use serial_number::*;
 #[cfg(feature = "unproven")]
 pub mod adc;
 
+#[cfg(feature = "unproven")]
+pub mod metering;
+
+#[cfg(feature = "unproven")]
+pub mod motor;
+
 #[cfg(feature = "unproven")]
 pub mod pwm;
 
+#[cfg(all(feature = "unproven", feature = "dma"))]
+pub mod pwm_audio;
+
 #[cfg(feature = "unproven")]
 pub mod watchdog;
@@ -0,0 +1,188 @@
+//! Synchronized PWM and current sampling for motor control
+//!
+//! Field-oriented control needs the phase currents to be sampled at a fixed
+//! point of the PWM cycle, usually in the middle of the period of
+//! center-aligned PWM, where all of the low-side switches are on and the
+//! current flows through the shunt resistors.
+//!
+//! [`CurrentSense`] sets this up across four peripherals in a single call:
+//!
+//! * The TCC is switched to center-aligned PWM, with its overflow, at the top
+//!   of the count, emitted as an event.
+//! * An event system channel routes that event to both ADCs.
+//! * ADC0 and ADC1 each sample one phase, starting a conversion on every
+//!   event, so that both phases are sampled at the same instant.
+//!
+//! ```
+//! let pwm = Tcc0Pwm::new(&clock, 20.kHz(), tcc0, pinout, &mut mclk);
+//! let mut sense = CurrentSense::new(
+//!     pwm, adc0, adc1, &mut phase_a, &mut phase_b, &mut evsys, &mut mclk, 0,
+//! );
+//! sense.pwm().set_duty(Channel::_0, duty);
+//! if let Some((ia, ib)) = sense.read() {
+//!     // ...
+//! }
+//! ```
+
+use crate::adc::{Adc, AdcChannel};
+use crate::gpio::v2::{PinId, PinMode};
+use crate::pwm::{Tcc0Pwm, Tcc1Pwm, Tcc2Pwm};
+#[cfg(feature = "min-samd51j")]
+use crate::pwm::{Tcc3Pwm, Tcc4Pwm};
+use crate::target_device::{ADC0, ADC1, EVSYS, MCLK};
+
+/// Event system user number of the ADC0 start conversion input
+const USER_ADC0_START: usize = 55;
+/// Event system user number of the ADC1 start conversion input
+const USER_ADC1_START: usize = 57;
+
+/// A TCC PWM which can drive synchronized current sampling
+pub trait SamplingPwm {
+    /// Event system generator number of the TCC overflow event
+    const OVF_EVENT: u8;
+
+    /// Switches to center-aligned PWM
+    fn set_center_aligned(&mut self);
+
+    /// Enables or disables the overflow event output
+    fn set_overflow_event(&mut self, enable: bool);
+}
+
+macro_rules! sampling_pwm {
+    ($($TYPE:ident: $ovf:literal,)+) => {
+        $(
+            impl<I: PinId, M: PinMode> SamplingPwm for $TYPE<I, M> {
+                const OVF_EVENT: u8 = $ovf;
+
+                fn set_center_aligned(&mut self) {
+                    $TYPE::set_center_aligned(self);
+                }
+
+                fn set_overflow_event(&mut self, enable: bool) {
+                    $TYPE::set_overflow_event(self, enable);
+                }
+            }
+        )+
+    };
+}
+
+sampling_pwm! {
+    Tcc0Pwm: 0x29,
+    Tcc1Pwm: 0x32,
+    Tcc2Pwm: 0x39,
+}
+
+#[cfg(feature = "min-samd51j")]
+sampling_pwm! {
+    Tcc3Pwm: 0x3f,
+    Tcc4Pwm: 0x44,
+}
+
+/// Center-aligned PWM with two phase currents sampled by ADC0 and ADC1 at
+/// the top of each PWM cycle
+pub struct CurrentSense<P> {
+    pwm: P,
+    adc0: Adc<ADC0>,
+    adc1: Adc<ADC1>,
+    channel: usize,
+}
+
+impl<P: SamplingPwm> CurrentSense<P> {
+    /// Configures the PWM, the event system channel `channel` and both ADCs,
+    /// and starts sampling `phase_a` with ADC0 and `phase_b` with ADC1.
+    ///
+    /// Both ADCs should be set to the same resolution, sample length and
+    /// prescaler, so that their conversions end together. The ADC conversion
+    /// time must be shorter than the PWM period.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new<A, B>(
+        mut pwm: P,
+        mut adc0: Adc<ADC0>,
+        mut adc1: Adc<ADC1>,
+        phase_a: &mut A,
+        phase_b: &mut B,
+        evsys: &mut EVSYS,
+        mclk: &mut MCLK,
+        channel: u8,
+    ) -> Self
+    where
+        A: AdcChannel<ADC0>,
+        B: AdcChannel<ADC1>,
+    {
+        let channel = channel as usize;
+        assert!(channel < evsys.channel.len());
+
+        pwm.set_center_aligned();
+        pwm.set_overflow_event(true);
+
+        mclk.apbbmask.modify(|_, w| w.evsys_().set_bit());
+        evsys.channel[channel].channel.write(|w| {
+            unsafe { w.evgen().bits(P::OVF_EVENT) };
+            w.path().asynchronous().edgsel().no_evt_output()
+        });
+        // The user registers hold the channel number plus one, zero
+        // disconnects the user
+        let user = channel as u8 + 1;
+        evsys.user[USER_ADC0_START].write(|w| unsafe { w.channel().bits(user) });
+        evsys.user[USER_ADC1_START].write(|w| unsafe { w.channel().bits(user) });
+
+        adc0.mux(phase_a);
+        adc0.set_start_event(true);
+        adc0.power_up();
+        adc1.mux(phase_b);
+        adc1.set_start_event(true);
+        adc1.power_up();
+
+        Self {
+            pwm,
+            adc0,
+            adc1,
+            channel,
+        }
+    }
+
+    /// Gives access to the PWM, e.g. to update the duty cycles
+    pub fn pwm(&mut self) -> &mut P {
+        &mut self.pwm
+    }
+
+    /// Returns the currents of both phases, if a new pair of samples is
+    /// ready
+    pub fn read(&mut self) -> Option<(u16, u16)> {
+        // Both conversions are started by the same event and take as long,
+        // so the result of ADC1 being ready means ADC0's is as well
+        let b = self.adc1.service_interrupt_ready()?;
+        let a = loop {
+            if let Some(a) = self.adc0.service_interrupt_ready() {
+                break a;
+            }
+        };
+        Some((a, b))
+    }
+
+    /// Enables the ADC1 result ready interrupt, which fires once per PWM
+    /// cycle when a new pair of samples is ready
+    pub fn enable_interrupt(&mut self) {
+        self.adc1.enable_interrupts();
+    }
+
+    /// Disables the ADC1 result ready interrupt
+    pub fn disable_interrupt(&mut self) {
+        self.adc1.disable_interrupts();
+    }
+
+    /// Stops sampling, disconnects the event system channel and releases the
+    /// PWM and both ADCs. The PWM is left center-aligned.
+    pub fn free(mut self, evsys: &mut EVSYS) -> (P, Adc<ADC0>, Adc<ADC1>) {
+        self.pwm.set_overflow_event(false);
+        evsys.user[USER_ADC0_START].reset();
+        evsys.user[USER_ADC1_START].reset();
+        evsys.channel[self.channel].channel.reset();
+        self.adc1.disable_interrupts();
+        self.adc0.power_down();
+        self.adc0.set_start_event(false);
+        self.adc1.power_down();
+        self.adc1.set_start_event(false);
+        (self.pwm, self.adc0, self.adc1)
+    }
+}
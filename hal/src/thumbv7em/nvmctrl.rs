@@ -0,0 +1,320 @@
+//! # Flash wait states, caches and AHB access
+//!
+//! The flash needs read wait states which depend on the CPU frequency. The
+//! clock controller sets a conservative number of them for 120MHz, which
+//! slows down code running from flash at lower clocks, and is not enough if
+//! the CPU is overclocked. [`auto_configure`] sets the number needed by the
+//! current frequency of GCLK0:
+//!
+//! ```
+//! let mut clocks = GenericClockController::with_internal_32kosc(
+//!     peripherals.GCLK,
+//!     &mut peripherals.MCLK,
+//!     &mut peripherals.OSC32KCTRL,
+//!     &mut peripherals.OSCCTRL,
+//!     &mut peripherals.NVMCTRL,
+//! );
+//! nvmctrl::auto_configure(&mut peripherals.NVMCTRL, &mut clocks);
+//! ```
+//!
+//! When changing the CPU frequency, add wait states before raising it, and
+//! remove them after lowering it.
+//!
+//! The NVM controller has two AHB ports: AHB0 serves the CPU and AHB1 the
+//! DMA and the other bus masters. Each has its own line cache, and may force
+//! non-sequential accesses so that bursts from other masters are arbitrated
+//! at every transfer, which reduces the latency seen by the other port at the
+//! cost of throughput.
+//!
+//! The AHB masters which compete with the CPU for the flash and the RAM, the
+//! DMAC and the USB, each have a quality of service ([`Qos`]), which sets
+//! their priority in the bus matrix. It is set with
+//! `DmaController::set_qos`, per DMA priority level, and with
+//! `UsbBus::set_qos`. Lowering it keeps DMA or USB traffic from stalling the
+//! CPU fetching code from flash, raising it keeps a latency critical transfer
+//! from being stalled by the CPU.
+//!
+//! # Writing
+//!
+//! A [`FlashRegion`] erases and writes a part of the flash, as the
+//! [`Storage`] of a power-fail safe [`Journal`](crate::nvm::Journal).
+
+use crate::clock::GenericClockController;
+use crate::nvm::{Error, Storage};
+use crate::target_device::nvmctrl::ctrla::WMODE_A;
+use crate::target_device::nvmctrl::ctrlb::CMD_AW;
+use crate::target_device::NVMCTRL;
+use crate::time::Hertz;
+
+/// Quality of service of an AHB master
+pub use crate::target_device::dmac::prictrl0::QOS0_A as Qos;
+
+/// Highest CPU frequency for each number of wait states
+const MAX_FREQ: [u32; 6] = [
+    24_000_000,
+    51_000_000,
+    77_000_000,
+    101_000_000,
+    119_000_000,
+    120_000_000,
+];
+
+/// Frequency step of each extra wait state above 120MHz
+const OVERCLOCK_STEP: u32 = 24_000_000;
+
+/// Configuration of one of the AHB ports of the NVM controller
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct AhbPort {
+    /// Whether the line cache of the port is enabled
+    pub cache: bool,
+    /// Whether accesses are forced to be non-sequential
+    pub non_sequential: bool,
+}
+
+impl AhbPort {
+    /// Cache enabled and bursts allowed, as after reset
+    pub const DEFAULT: Self = Self {
+        cache: true,
+        non_sequential: false,
+    };
+}
+
+/// Configuration of the flash reads
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FlashConfig {
+    /// Number of read wait states, up to 15
+    pub wait_states: u8,
+    /// Whether the controller chooses the wait states itself, ignoring
+    /// `wait_states`
+    pub auto_wait_states: bool,
+    /// The port used by the CPU
+    pub ahb0: AhbPort,
+    /// The port used by the DMA and the other bus masters
+    pub ahb1: AhbPort,
+}
+
+impl FlashConfig {
+    /// Returns the fastest configuration that is safe with a CPU running at
+    /// `freq`, with both caches enabled
+    pub fn for_frequency(freq: impl Into<Hertz>) -> Self {
+        Self {
+            wait_states: wait_states(freq),
+            auto_wait_states: false,
+            ahb0: AhbPort::DEFAULT,
+            ahb1: AhbPort::DEFAULT,
+        }
+    }
+
+    /// Reads the current configuration
+    pub fn read(nvmctrl: &NVMCTRL) -> Self {
+        let ctrla = nvmctrl.ctrla.read();
+        Self {
+            wait_states: ctrla.rws().bits(),
+            auto_wait_states: ctrla.autows().bit_is_set(),
+            ahb0: AhbPort {
+                cache: ctrla.cachedis0().bit_is_clear(),
+                non_sequential: ctrla.ahbns0().bit_is_set(),
+            },
+            ahb1: AhbPort {
+                cache: ctrla.cachedis1().bit_is_clear(),
+                non_sequential: ctrla.ahbns1().bit_is_set(),
+            },
+        }
+    }
+
+    /// Applies the configuration
+    pub fn apply(&self, nvmctrl: &mut NVMCTRL) {
+        nvmctrl.ctrla.modify(|_, w| {
+            unsafe { w.rws().bits(self.wait_states.min(15)) };
+            w.autows().bit(self.auto_wait_states);
+            w.cachedis0().bit(!self.ahb0.cache);
+            w.ahbns0().bit(self.ahb0.non_sequential);
+            w.cachedis1().bit(!self.ahb1.cache);
+            w.ahbns1().bit(self.ahb1.non_sequential)
+        });
+    }
+}
+
+/// Returns the number of wait states needed with a CPU running at `freq`
+///
+/// Frequencies above the 120MHz maximum get one more wait state per 24MHz.
+/// This margin is not characterized by the datasheet.
+pub fn wait_states(freq: impl Into<Hertz>) -> u8 {
+    let freq = freq.into().to_Hz();
+    let last = MAX_FREQ.len() - 1;
+    match MAX_FREQ.iter().position(|&max| freq <= max) {
+        Some(ws) => ws as u8,
+        None => {
+            let extra = (freq - MAX_FREQ[last] + OVERCLOCK_STEP - 1) / OVERCLOCK_STEP;
+            (last as u32 + extra).min(15) as u8
+        }
+    }
+}
+
+/// Enables or disables the line caches of both AHB ports, leaving the wait
+/// states alone
+pub fn set_cache(nvmctrl: &mut NVMCTRL, enabled: bool) {
+    nvmctrl.ctrla.modify(|_, w| {
+        w.cachedis0().bit(!enabled);
+        w.cachedis1().bit(!enabled)
+    });
+}
+
+/// Sets the wait states for the current frequency of GCLK0, which clocks
+/// the CPU, keeping the configuration of the AHB ports
+pub fn auto_configure(nvmctrl: &mut NVMCTRL, clocks: &mut GenericClockController) {
+    let freq: Hertz = clocks.gclk0().into();
+    let current = FlashConfig::read(nvmctrl);
+    let config = FlashConfig {
+        ahb0: current.ahb0,
+        ahb1: current.ahb1,
+        ..FlashConfig::for_frequency(freq)
+    };
+    config.apply(nvmctrl);
+}
+
+/// A part of the flash, erased in blocks of 8KiB and written in quad words
+/// of 16 bytes
+pub struct FlashRegion {
+    nvmctrl: NVMCTRL,
+    start: u32,
+    len: u32,
+    /// The write mode to restore once the region is freed
+    wmode: WMODE_A,
+}
+
+impl FlashRegion {
+    /// Uses the `len` bytes of the flash from address `start` as storage,
+    /// and selects the manual write mode until the region is freed. Panics
+    /// if they are not whole blocks.
+    ///
+    /// # Safety
+    ///
+    /// The region must not hold the program, or any data which is used
+    /// while it is erased or written.
+    pub unsafe fn new(nvmctrl: NVMCTRL, start: u32, len: u32) -> Self {
+        let block = Self::ERASE_SIZE as u32;
+        assert!(
+            start % block == 0 && len % block == 0,
+            "region is not block aligned"
+        );
+        let wmode = nvmctrl.ctrla.read().wmode().variant();
+        nvmctrl.ctrla.modify(|_, w| w.wmode().man());
+        Self {
+            nvmctrl,
+            start,
+            len,
+            wmode,
+        }
+    }
+
+    /// Restores the write mode, and releases the NVM controller
+    pub fn free(self) -> NVMCTRL {
+        self.nvmctrl
+            .ctrla
+            .modify(|_, w| w.wmode().variant(self.wmode));
+        self.nvmctrl
+    }
+
+    /// Returns the address of `offset`, if `len` bytes from it are within
+    /// the region and it is a multiple of `align`
+    fn address(&self, offset: usize, len: usize, align: usize) -> Result<u32, Error> {
+        if offset % align != 0 || offset + len > self.len as usize {
+            return Err(Error::OutOfBounds);
+        }
+        Ok(self.start + offset as u32)
+    }
+
+    /// Runs `cmd` on the block or the quad word at `addr`
+    fn command(&mut self, cmd: CMD_AW, addr: u32) -> Result<(), Error> {
+        let nvmctrl = &self.nvmctrl;
+        while nvmctrl.status.read().ready().bit_is_clear() {}
+        nvmctrl.intflag.write(|w| {
+            w.done().set_bit();
+            w.addre().set_bit();
+            w.proge().set_bit();
+            w.locke().set_bit();
+            w.nvme().set_bit()
+        });
+        nvmctrl.addr.write(|w| unsafe { w.addr().bits(addr) });
+        nvmctrl.ctrlb.write(|w| {
+            w.cmd().variant(cmd);
+            w.cmdex().key()
+        });
+        while nvmctrl.intflag.read().done().bit_is_clear() {}
+        let flags = nvmctrl.intflag.read();
+        if flags.addre().bit_is_set() {
+            Err(Error::OutOfBounds)
+        } else if flags.locke().bit_is_set() {
+            Err(Error::Locked)
+        } else if flags.proge().bit_is_set() || flags.nvme().bit_is_set() {
+            Err(Error::Program)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Runs `f` with the line caches disabled, so that they do not return
+    /// stale data afterwards
+    fn uncached<F>(&mut self, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Self) -> Result<(), Error>,
+    {
+        let ctrla = self.nvmctrl.ctrla.read();
+        let (cachedis0, cachedis1) = (ctrla.cachedis0().bit(), ctrla.cachedis1().bit());
+        self.nvmctrl.ctrla.modify(|_, w| {
+            w.cachedis0().set_bit();
+            w.cachedis1().set_bit()
+        });
+        let result = f(self);
+        self.nvmctrl.ctrla.modify(|_, w| {
+            w.cachedis0().bit(cachedis0);
+            w.cachedis1().bit(cachedis1)
+        });
+        result
+    }
+}
+
+impl Storage for FlashRegion {
+    const ERASE_SIZE: usize = 8192;
+    const WRITE_SIZE: usize = 16;
+
+    fn capacity(&self) -> usize {
+        self.len as usize
+    }
+
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), Error> {
+        let addr = self.address(offset, buf.len(), 1)? as *const u8;
+        for (i, byte) in buf.iter_mut().enumerate() {
+            *byte = unsafe { core::ptr::read_volatile(addr.add(i)) };
+        }
+        Ok(())
+    }
+
+    fn erase(&mut self, offset: usize) -> Result<(), Error> {
+        let addr = self.address(offset, Self::ERASE_SIZE, Self::ERASE_SIZE)?;
+        self.uncached(|region| region.command(CMD_AW::EB, addr))
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), Error> {
+        let start = self.address(offset, data.len(), Self::WRITE_SIZE)?;
+        self.uncached(|region| {
+            let mut addr = start;
+            for quad_word in data.chunks(Self::WRITE_SIZE) {
+                // The cleared page buffer is all ones, which pads the quad
+                // word
+                region.command(CMD_AW::PBC, addr)?;
+                for (i, word) in quad_word.chunks(4).enumerate() {
+                    let mut bytes = [0xff; 4];
+                    bytes[..word.len()].copy_from_slice(word);
+                    // The page buffer only takes 32 bit writes
+                    let dst = (addr as *mut u32).wrapping_add(i);
+                    unsafe { core::ptr::write_volatile(dst, u32::from_le_bytes(bytes)) };
+                }
+                region.command(CMD_AW::WQW, addr)?;
+                addr += Self::WRITE_SIZE as u32;
+            }
+            Ok(())
+        })
+    }
+}
@@ -4,8 +4,12 @@ use crate::clock;
 use crate::gpio::v2::{AlternateE, AnyPin, Pin};
 use crate::gpio::*;
 use crate::hal::{Pwm, PwmPin};
-use crate::time::Hertz;
+use crate::hc_sr04::Trigger;
+use crate::tick::Tick;
+use crate::time::{Hertz, Nanoseconds};
 use crate::timer_params::TimerParams;
+use core::convert::Infallible;
+use core::marker::PhantomData;
 
 use crate::target_device::{MCLK, TC0, TC1, TC2, TC3, TCC0, TCC1, TCC2};
 #[cfg(feature = "min-samd51j")]
@@ -99,7 +103,7 @@ impl<I: PinId> $TYPE<I> {
         let freq = freq.into();
         {
             let count = tc.count16();
-            let params = TimerParams::new(freq, clock.freq().0);
+            let params = TimerParams::new(freq, clock.freq().to_Hz());
             mclk.$apmask.modify(|_, w| w.$apbits().set_bit());
             count.ctrla.write(|w| w.swrst().set_bit());
             while count.ctrla.read().bits() & 1 != 0 {}
@@ -136,7 +140,7 @@ impl<I: PinId> $TYPE<I> {
         let count = self.tc.count16();
         let divisor = count.ctrla.read().prescaler().bits();
         let top = count.cc[0].read().cc().bits();
-        Hertz(self.clock_freq.0 / divisor as u32 / (top + 1) as u32)
+        self.clock_freq / divisor as u32 / (top + 1) as u32
     }
 
     pub fn set_period<P>(&mut self, period: P)
@@ -144,7 +148,7 @@ impl<I: PinId> $TYPE<I> {
         P: Into<Hertz>
     {
         let period = period.into();
-        let params = TimerParams::new(period, self.clock_freq.0);
+        let params = TimerParams::new(period, self.clock_freq.to_Hz());
         let count = self.tc.count16();
         count.ctrla.modify(|_, w| w.enable().clear_bit());
         count.ctrla.modify(|_, w| {
@@ -428,8 +432,101 @@ impl_tcc_pinout!(TCC4Pinout: [
     (Pb31, PB31, AlternateF)
 ]);
 
+/// Counter operation of a TCC in the single-slope PWM mode
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Ramp {
+    /// Every cycle is identical. This is the default.
+    Ramp1,
+    /// Alternative dual-ramp operation. Cycles alternate between A and B,
+    /// with channel 0 active during cycle A and channel 1 during cycle B.
+    Ramp2A,
+    /// Dual-ramp operation. Cycles alternate between A and B, with CC0 and
+    /// CC1 providing the compare values of the two cycles, so that two
+    /// interleaved pulses can be generated from a single counter.
+    Ramp2,
+    /// Like [`Ramp::Ramp2`], but the compare value of cycle B is only
+    /// applied when it is above that of cycle A.
+    Ramp2C,
+}
+
+/// Routing of the TCC compare channels to the waveform outputs
+///
+/// The output matrix is applied after the dead-time insertion and the swap
+/// of the output pairs. With [`OutputMatrix::Default`], each output `WO[x]`
+/// is driven by channel `x` modulo the number of channels of the TCC.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum OutputMatrix {
+    /// `WO[x]` is driven by channel `x` modulo the number of channels
+    Default,
+    /// `WO[x]` is driven by channel 0 for even `x`, channel 1 for odd `x`
+    Pairs,
+    /// All outputs are driven by channel 0
+    Channel0,
+    /// `WO[0]` is driven by channel 0, the other outputs by channel 1
+    Channel1,
+}
+
+/// A complementary output pair of a TCC with dead-time insertion: pair `n`
+/// drives `WO[n]` as its low side and `WO[n + 4]` as its high side
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum OutputPair {
+    _0,
+    _1,
+    _2,
+    _3,
+}
+
+/// Returns the division factor of a TCC prescaler setting
+fn prescaler_divisor(bits: u8) -> u32 {
+    1 << [0, 1, 2, 3, 4, 6, 8, 10][bits as usize & 0b111]
+}
+
+/// A TCC channel in one-shot mode, used as the trigger of an
+/// [`hc_sr04::Ranger`](crate::hc_sr04::Ranger)
+///
+/// The 10µs trigger pulse is generated in hardware by
+/// [`fire_pulse`](Tcc0Pwm::fire_pulse), so the width does not depend on
+/// interrupt latency.
+pub struct PulseTrigger<P> {
+    pwm: P,
+    channel: Channel,
+}
+
+impl<P> PulseTrigger<P> {
+    /// Releases the PWM
+    pub fn free(self) -> P {
+        self.pwm
+    }
+}
+
+/// The counter of a TCC split with [`split`](Tcc0Pwm::split), which controls
+/// the period shared by its [`PwmChannel`]s
+pub struct PwmTimer<P> {
+    pwm: P,
+}
+
+/// A compare channel of a TCC split with [`split`](Tcc0Pwm::split)
+///
+/// Each channel only writes its own CC register, so the channels of a TCC can
+/// be moved into different drivers or tasks. The period, and therefore the
+/// maximum duty, is set by the [`PwmTimer`] of the TCC.
+pub struct PwmChannel<TCC> {
+    channel: Channel,
+    /// The duty written back by `enable`
+    duty: u32,
+    enabled: bool,
+    tcc: PhantomData<TCC>,
+}
+
+impl<TCC> PwmChannel<TCC> {
+    /// Returns the compare channel of the TCC driven by this handle
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+}
+
 macro_rules! pwm_tcc {
-    ($($TYPE:ident: ($TCC:ident, $pinout:ident, $clock:ident, $apmask:ident, $apbits:ident, $wrapper:ident),)+) => {
+    ($($TYPE:ident: ($TCC:ident, $pinout:ident, $clock:ident, $apmask:ident, $apbits:ident, $wrapper:ident, $n:literal, [$($ch:ident),+]),)+) => {
         $(
 
 pub struct $TYPE<I: PinId, M: PinMode> {
@@ -451,7 +548,7 @@ impl<I: PinId, M: PinMode> $TYPE<I, M> {
     ) -> Self {
         let freq = freq.into();
         {
-            let params = TimerParams::new(freq, clock.freq().0);
+            let params = TimerParams::new(freq, clock.freq().to_Hz());
             mclk.$apmask.modify(|_, w| w.$apbits().set_bit());
             tcc.ctrla.write(|w| w.swrst().set_bit());
             while tcc.syncbusy.read().swrst().bit_is_set() {}
@@ -486,6 +583,258 @@ impl<I: PinId, M: PinMode> $TYPE<I, M> {
     }
 }
 
+impl<I: PinId, M: PinMode> $TYPE<I, M> {
+    /// Selects the counter [`Ramp`] operation
+    pub fn set_ramp(&mut self, ramp: Ramp) {
+        self.tcc.ctrla.modify(|_, w| w.enable().clear_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+        self.tcc.wave.modify(|_, w| match ramp {
+            Ramp::Ramp1 => w.ramp().ramp1(),
+            Ramp::Ramp2A => w.ramp().ramp2a(),
+            Ramp::Ramp2 => w.ramp().ramp2(),
+            Ramp::Ramp2C => w.ramp().ramp2c(),
+        });
+        while self.tcc.syncbusy.read().wave().bit_is_set() {}
+        self.tcc.ctrla.modify(|_, w| w.enable().set_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+    }
+
+    /// Enables or disables one-shot operation.
+    ///
+    /// In one-shot operation, the counter stops on the next overflow and the
+    /// waveform outputs are driven low until the counter is restarted by
+    /// [`retrigger`](Self::retrigger). Each channel then outputs a single
+    /// pulse, from the restart until its compare match. Enabling one-shot
+    /// operation also stops the counter, so that no pulse is fired until the
+    /// first retrigger.
+    pub fn set_one_shot(&mut self, one_shot: bool) {
+        if one_shot {
+            self.tcc.ctrlbset.write(|w| w.oneshot().set_bit());
+            while self.tcc.syncbusy.read().ctrlb().bit_is_set() {}
+            self.tcc.ctrlbset.write(|w| w.cmd().stop());
+        } else {
+            self.tcc.ctrlbclr.write(|w| w.oneshot().set_bit());
+        }
+        while self.tcc.syncbusy.read().ctrlb().bit_is_set() {}
+    }
+
+    /// Restarts the counter from zero
+    pub fn retrigger(&mut self) {
+        self.tcc.ctrlbset.write(|w| w.cmd().retrigger());
+        while self.tcc.syncbusy.read().ctrlb().bit_is_set() {}
+    }
+
+    /// Returns true while the counter is stopped, such as after the end of a
+    /// one-shot cycle
+    pub fn is_stopped(&self) -> bool {
+        self.tcc.status.read().stop().bit_is_set()
+    }
+
+    /// Stops the counter, keeping its value, until [`resume`](Self::resume).
+    /// The outputs keep their current level.
+    pub fn pause(&mut self) {
+        self.tcc.ctrla.modify(|_, w| w.enable().clear_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+    }
+
+    /// Restarts the counter from the value at which it was paused
+    pub fn resume(&mut self) {
+        self.tcc.ctrla.modify(|_, w| w.enable().set_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+    }
+
+    /// Returns the current value of the counter.
+    ///
+    /// COUNT is only synchronized on request: the READSYNC command is complete
+    /// once CMD reads back as zero.
+    pub fn count(&self) -> u32 {
+        self.tcc.ctrlbset.write(|w| w.cmd().readsync());
+        while !self.tcc.ctrlbset.read().cmd().is_none() {}
+        while self.tcc.syncbusy.read().count().bit_is_set() {}
+        self.tcc.count().read().count().bits()
+    }
+
+    /// Sets the value of the counter, which keeps counting from there if it
+    /// is running
+    pub fn set_count(&mut self, value: u32) {
+        self.tcc.count().write(|w| unsafe { w.count().bits(value) });
+        while self.tcc.syncbusy.read().count().bit_is_set() {}
+    }
+
+    /// Fires a single pulse of `width` counter ticks on `channel`.
+    ///
+    /// One-shot operation must be enabled with
+    /// [`set_one_shot`](Self::set_one_shot) and the width must be less than
+    /// the period. The pulse is generated entirely in hardware, so its width
+    /// is accurate to one tick of the counter clock. Returns `false`, without
+    /// firing, if the previous cycle has not ended yet.
+    pub fn fire_pulse(&mut self, channel: Channel, width: u32) -> bool {
+        if !self.is_stopped() {
+            return false;
+        }
+        let cc = self.tcc.cc();
+        cc[channel as usize].write(|w| unsafe { w.cc().bits(width) });
+        while self.tcc.syncbusy.read().bits() & (1 << (8 + channel as u32)) != 0 {}
+        self.retrigger();
+        true
+    }
+
+    /// Converts a duration to a number of counter ticks for
+    /// [`fire_pulse`](Self::fire_pulse), rounding down
+    pub fn ticks<T: Into<Nanoseconds>>(&self, duration: T) -> u32 {
+        let divisor = prescaler_divisor(self.tcc.ctrla.read().prescaler().bits());
+        let hz = (self.clock_freq.to_Hz() / divisor) as u64;
+        (duration.into().ticks() as u64 * hz / 1_000_000_000) as u32
+    }
+
+    /// Switches to center-aligned (dual-slope) PWM.
+    ///
+    /// The counter counts up to the period and back down, and overflows at
+    /// the top. The period is halved so that the PWM frequency is unchanged,
+    /// which also halves [`get_max_duty`](Pwm::get_max_duty). Each output is
+    /// set while the counter is below its compare value, so all outputs are
+    /// cleared around the overflow.
+    pub fn set_center_aligned(&mut self) {
+        self.tcc.ctrla.modify(|_, w| w.enable().clear_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+        let top = self.tcc.per().read().bits();
+        self.tcc.wave.modify(|_, w| w.wavegen().dstop());
+        while self.tcc.syncbusy.read().wave().bit_is_set() {}
+        self.tcc.per().write(|w| unsafe { w.bits((top + 1) / 2) });
+        while self.tcc.syncbusy.read().per().bit_is_set() {}
+        self.tcc.ctrla.modify(|_, w| w.enable().set_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+    }
+
+    /// Enables or disables the overflow event output, which can be routed to
+    /// other peripherals by the event system
+    pub fn set_overflow_event(&mut self, enable: bool) {
+        // EVCTRL is enable-protected
+        self.tcc.ctrla.modify(|_, w| w.enable().clear_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+        self.tcc.evctrl.modify(|_, w| w.ovfeo().bit(enable));
+        self.tcc.ctrla.modify(|_, w| w.enable().set_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+    }
+}
+
+impl<I: PinId, M: PinMode> PulseTrigger<$TYPE<I, M>> {
+    /// Puts the TCC in one-shot mode and uses `channel` as the trigger
+    /// output. The period of the TCC must be longer than 10µs.
+    pub fn new(mut pwm: $TYPE<I, M>, channel: Channel) -> Self {
+        pwm.set_one_shot(true);
+        Self { pwm, channel }
+    }
+}
+
+impl<I: PinId, M: PinMode> Trigger for PulseTrigger<$TYPE<I, M>> {
+    type Error = Infallible;
+
+    fn trigger<T: Tick>(&mut self, _tick: &T) -> Result<(), Self::Error> {
+        // Round up to make sure the pulse is at least 10µs wide
+        let width = self.pwm.ticks(Nanoseconds::micros(10)) + 1;
+        while !self.pwm.fire_pulse(self.channel, width) {}
+        Ok(())
+    }
+}
+
+impl<I: PinId, M: PinMode> $TYPE<I, M> {
+    /// Splits the PWM into a [`PwmTimer`], which controls the period, and one
+    /// [`PwmChannel`] per compare channel. [`PwmTimer::join`] puts them back
+    /// together.
+    pub fn split(self) -> (PwmTimer<Self>, [PwmChannel<$TCC>; $n]) {
+        let cc = self.tcc.cc();
+        let channels = [$(
+            PwmChannel {
+                channel: Channel::$ch,
+                duty: cc[Channel::$ch as usize].read().cc().bits(),
+                enabled: true,
+                tcc: PhantomData,
+            },
+        )+];
+        (PwmTimer { pwm: self }, channels)
+    }
+}
+
+impl<I: PinId, M: PinMode> PwmTimer<$TYPE<I, M>> {
+    /// Reassembles the PWM from the timer and all of its channels. The
+    /// outputs of disabled channels stay low.
+    pub fn join(self, _channels: [PwmChannel<$TCC>; $n]) -> $TYPE<I, M> {
+        self.pwm
+    }
+
+    /// Sets the period of all of the channels. Their duty is not scaled, so
+    /// it must be set again relative to the new
+    /// [`get_max_duty`](Self::get_max_duty).
+    pub fn set_period<P: Into<Hertz>>(&mut self, period: P) {
+        Pwm::set_period(&mut self.pwm, period);
+    }
+
+    /// Returns the frequency of the PWM
+    pub fn get_period(&self) -> Hertz {
+        Pwm::get_period(&self.pwm)
+    }
+
+    /// Returns the duty at which the outputs are always high
+    pub fn get_max_duty(&self) -> u32 {
+        Pwm::get_max_duty(&self.pwm)
+    }
+
+    /// Stops the counter, and therefore all of the channels, until
+    /// [`resume`](Self::resume)
+    pub fn pause(&mut self) {
+        self.pwm.pause();
+    }
+
+    /// Restarts the counter from the value at which it was paused
+    pub fn resume(&mut self) {
+        self.pwm.resume();
+    }
+}
+
+impl PwmChannel<$TCC> {
+    fn tcc(&self) -> &crate::target_device::tcc0::RegisterBlock {
+        // SAFETY: The TCC is owned by the `PwmTimer`, which doesn't write the
+        // CC registers, and each channel only writes its own CC register
+        unsafe { &*$TCC::ptr() }
+    }
+
+    fn write_cc(&mut self, duty: u32) {
+        let cc = self.tcc().cc();
+        cc[self.channel as usize].write(|w| unsafe { w.cc().bits(duty) });
+    }
+}
+
+impl PwmPin for PwmChannel<$TCC> {
+    type Duty = u32;
+
+    /// Drives the output low, without stopping the other channels
+    fn disable(&mut self) {
+        self.enabled = false;
+        self.write_cc(0);
+    }
+
+    fn enable(&mut self) {
+        self.enabled = true;
+        self.write_cc(self.duty);
+    }
+
+    fn get_duty(&self) -> Self::Duty {
+        self.duty
+    }
+
+    fn get_max_duty(&self) -> Self::Duty {
+        self.tcc().per().read().bits()
+    }
+
+    fn set_duty(&mut self, duty: Self::Duty) {
+        self.duty = duty;
+        if self.enabled {
+            self.write_cc(duty);
+        }
+    }
+}
+
 impl<I: PinId, M: PinMode> Pwm for $TYPE<I, M> {
     type Channel = Channel;
     type Time = Hertz;
@@ -504,7 +853,7 @@ impl<I: PinId, M: PinMode> Pwm for $TYPE<I, M> {
     fn get_period(&self) -> Self::Time {
         let divisor = self.tcc.ctrla.read().prescaler().bits();
         let top = self.tcc.per().read().bits();
-        Hertz(self.clock_freq.0 / divisor as u32 / (top + 1) as u32)
+        self.clock_freq / divisor as u32 / (top + 1) as u32
     }
 
     fn get_duty(&self, channel: Self::Channel) -> Self::Duty {
@@ -528,7 +877,7 @@ impl<I: PinId, M: PinMode> Pwm for $TYPE<I, M> {
         P: Into<Self::Time>,
     {
         let period = period.into();
-        let params = TimerParams::new(period, self.clock_freq.0);
+        let params = TimerParams::new(period, self.clock_freq.to_Hz());
         self.tcc.ctrla.modify(|_, w| w.enable().clear_bit());
         while self.tcc.syncbusy.read().enable().bit_is_set() {}
         self.tcc.ctrla.modify(|_, w| {
@@ -556,13 +905,99 @@ impl<I: PinId, M: PinMode> Pwm for $TYPE<I, M> {
 }
 
 pwm_tcc! {
-    Tcc0Pwm: (TCC0, TCC0Pinout, Tcc0Tcc1Clock, apbbmask, tcc0_, TccPwm0Wrapper),
-    Tcc1Pwm: (TCC1, TCC1Pinout, Tcc0Tcc1Clock, apbbmask, tcc1_, TccPwm1Wrapper),
-    Tcc2Pwm: (TCC2, TCC2Pinout, Tcc2Tcc3Clock, apbcmask, tcc2_, TccPwm2Wrapper),
+    Tcc0Pwm: (TCC0, TCC0Pinout, Tcc0Tcc1Clock, apbbmask, tcc0_, TccPwm0Wrapper, 6, [_0, _1, _2, _3, _4, _5]),
+    Tcc1Pwm: (TCC1, TCC1Pinout, Tcc0Tcc1Clock, apbbmask, tcc1_, TccPwm1Wrapper, 4, [_0, _1, _2, _3]),
+    Tcc2Pwm: (TCC2, TCC2Pinout, Tcc2Tcc3Clock, apbcmask, tcc2_, TccPwm2Wrapper, 3, [_0, _1, _2]),
 }
 
 #[cfg(feature = "min-samd51j")]
 pwm_tcc! {
-    Tcc3Pwm: (TCC3, TCC3Pinout, Tcc2Tcc3Clock, apbcmask, tcc3_, TccPwm3Wrapper),
-    Tcc4Pwm: (TCC4, TCC4Pinout, Tcc4Clock,     apbdmask, tcc4_, TccPwm4Wrapper),
+    Tcc3Pwm: (TCC3, TCC3Pinout, Tcc2Tcc3Clock, apbcmask, tcc3_, TccPwm3Wrapper, 2, [_0, _1]),
+    Tcc4Pwm: (TCC4, TCC4Pinout, Tcc4Clock,     apbdmask, tcc4_, TccPwm4Wrapper, 2, [_0, _1]),
+}
+
+macro_rules! tcc_output_matrix {
+    ($($TYPE:ident,)+) => {
+        $(
+impl<I: PinId, M: PinMode> $TYPE<I, M> {
+    /// Selects which compare channel drives each waveform output, so that
+    /// the channel assignments of the firmware don't depend on the routing
+    /// of the board
+    pub fn set_output_matrix(&mut self, matrix: OutputMatrix) {
+        let otmx = match matrix {
+            OutputMatrix::Default => 0,
+            OutputMatrix::Pairs => 1,
+            OutputMatrix::Channel0 => 2,
+            OutputMatrix::Channel1 => 3,
+        };
+        // WEXCTRL is enable-protected
+        self.tcc.ctrla.modify(|_, w| w.enable().clear_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+        self.tcc.wexctrl.modify(|_, w| unsafe { w.otmx().bits(otmx) });
+        self.tcc.ctrla.modify(|_, w| w.enable().set_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+    }
+}
+        )+
+    };
+}
+
+tcc_output_matrix! {
+    Tcc0Pwm,
+    Tcc1Pwm,
+    Tcc2Pwm,
+}
+
+macro_rules! tcc_dead_time {
+    ($($TYPE:ident,)+) => {
+        $(
+impl<I: PinId, M: PinMode> $TYPE<I, M> {
+    /// Enables or disables dead-time insertion on `pair`.
+    ///
+    /// Both outputs of the pair are then driven by the low-side channel, the
+    /// high side as its complement, with `low` and `high` counter ticks of
+    /// dead time inserted before the low side and the high side are set.
+    /// The dead times are shared by all of the pairs.
+    pub fn set_dead_time(&mut self, pair: OutputPair, enable: bool, low: u8, high: u8) {
+        // WEXCTRL is enable-protected
+        self.tcc.ctrla.modify(|_, w| w.enable().clear_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+        self.tcc.wexctrl.modify(|_, w| {
+            match pair {
+                OutputPair::_0 => w.dtien0().bit(enable),
+                OutputPair::_1 => w.dtien1().bit(enable),
+                OutputPair::_2 => w.dtien2().bit(enable),
+                OutputPair::_3 => w.dtien3().bit(enable),
+            };
+            unsafe {
+                w.dtls().bits(low);
+                w.dths().bits(high)
+            }
+        });
+        self.tcc.ctrla.modify(|_, w| w.enable().set_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+    }
+
+    /// Swaps the low-side and high-side outputs of `pair`
+    pub fn set_swap(&mut self, pair: OutputPair, swap: bool) {
+        self.tcc.ctrla.modify(|_, w| w.enable().clear_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+        self.tcc.wave.modify(|_, w| match pair {
+            OutputPair::_0 => w.swap0().bit(swap),
+            OutputPair::_1 => w.swap1().bit(swap),
+            OutputPair::_2 => w.swap2().bit(swap),
+            OutputPair::_3 => w.swap3().bit(swap),
+        });
+        while self.tcc.syncbusy.read().wave().bit_is_set() {}
+        self.tcc.ctrla.modify(|_, w| w.enable().set_bit());
+        while self.tcc.syncbusy.read().enable().bit_is_set() {}
+    }
+}
+        )+
+    };
+}
+
+tcc_dead_time! {
+    Tcc0Pwm,
+    Tcc1Pwm,
 }
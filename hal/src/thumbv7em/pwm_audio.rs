@@ -0,0 +1,162 @@
+//! PCM audio playback through PWM
+//!
+//! Boards whose speaker amplifier is not connected to a DAC pin can still
+//! play sampled audio by low-pass filtering a PWM output. [`PwmAudio`] runs a
+//! TCC at the sample rate, and a DMA transfer triggered by the TCC overflow
+//! writes one sample per PWM period to the `CCBUF` register of the speaker's
+//! channel. At 120MHz and 22.05kHz, that is a resolution of more than 12 bits,
+//! with the carrier above the audible range.
+//!
+//! The DMA source must hold duty cycles, not raw PCM samples. Convert signed
+//! 16-bit PCM with [`PwmAudio::encode`], which also applies the volume.
+//!
+//! ```
+//! static mut SAMPLES: [u16; 1024] = [0; 1024];
+//!
+//! let pwm = Tcc0Pwm::new(&clock, 22_050.Hz(), tcc0, pinout, &mut mclk);
+//! let (mut audio, sink) = PwmAudio::new(pwm, Channel::_0, 22_050.Hz());
+//! audio.set_volume(128);
+//! let samples = unsafe { &mut SAMPLES };
+//! audio.encode(&pcm, samples);
+//! let xfer = Transfer::new(chan0, samples, sink, false)
+//!     .unwrap()
+//!     .with_waker(buffer_played)
+//!     .begin(Tcc0Pwm::<PA08, AlternateF>::DMA_TRIGGER, TriggerAction::BEAT);
+//! ```
+
+use crate::dmac::{transfer::Buffer, TriggerSource};
+use crate::gpio::v2::{PinId, PinMode};
+use crate::hal::Pwm;
+use crate::pwm::{Channel, Tcc0Pwm, Tcc1Pwm, Tcc2Pwm};
+#[cfg(feature = "min-samd51j")]
+use crate::pwm::{Tcc3Pwm, Tcc4Pwm};
+use crate::target_device::{TCC0, TCC1, TCC2};
+#[cfg(feature = "min-samd51j")]
+use crate::target_device::{TCC3, TCC4};
+use crate::time::Hertz;
+
+/// A TCC PWM whose compare buffers can be written by the DMAC
+pub trait AudioPwm: Pwm<Channel = Channel, Time = Hertz, Duty = u32> {
+    /// DMA trigger fired on each overflow of the TCC
+    const DMA_TRIGGER: TriggerSource;
+
+    /// Address of the `CCBUF` register of `channel`
+    fn ccbuf_ptr(channel: Channel) -> *mut u16;
+}
+
+macro_rules! audio_pwm {
+    ($($TYPE:ident: ($TCC:ident, $trigger:ident),)+) => {
+        $(
+            impl<I: PinId, M: PinMode> AudioPwm for $TYPE<I, M> {
+                const DMA_TRIGGER: TriggerSource = TriggerSource::$trigger;
+
+                fn ccbuf_ptr(channel: Channel) -> *mut u16 {
+                    unsafe { (*$TCC::ptr()).ccbuf()[channel as usize].as_ptr() as *mut u16 }
+                }
+            }
+        )+
+    };
+}
+
+audio_pwm! {
+    Tcc0Pwm: (TCC0, TCC0_OVF),
+    Tcc1Pwm: (TCC1, TCC1_OVF),
+    Tcc2Pwm: (TCC2, TCC2_OVF),
+}
+
+#[cfg(feature = "min-samd51j")]
+audio_pwm! {
+    Tcc3Pwm: (TCC3, TCC3_OVF),
+    Tcc4Pwm: (TCC4, TCC4_OVF),
+}
+
+/// A PWM channel playing PCM audio
+pub struct PwmAudio<P> {
+    pwm: P,
+    channel: Channel,
+    top: u32,
+    volume: u16,
+}
+
+/// DMA destination writing samples to the compare buffer of a [`PwmAudio`]
+/// channel
+pub struct AudioSink {
+    ccbuf: *mut u16,
+}
+
+impl<P: AudioPwm> PwmAudio<P> {
+    /// Sets the PWM frequency to `sample_rate`, and centers the output of
+    /// `channel`, i.e. silence. Returns the DMA destination for the samples.
+    ///
+    /// Panics if the resulting PWM resolution does not fit in 16 bits.
+    pub fn new<F: Into<Hertz>>(mut pwm: P, channel: Channel, sample_rate: F) -> (Self, AudioSink) {
+        pwm.set_period(sample_rate.into());
+        let top = pwm.get_max_duty();
+        assert!(top <= u16::MAX as u32, "sample rate too low");
+        pwm.set_duty(channel, top / 2);
+        pwm.enable(channel);
+        let audio = Self {
+            pwm,
+            channel,
+            top,
+            volume: 256,
+        };
+        let sink = AudioSink {
+            ccbuf: P::ccbuf_ptr(channel),
+        };
+        (audio, sink)
+    }
+
+    /// Sets the volume used by [`encode`](Self::encode), from 0 (mute) to
+    /// 256 (full scale)
+    pub fn set_volume(&mut self, volume: u16) {
+        self.volume = volume.min(256);
+    }
+
+    /// Converts signed 16-bit PCM samples to duty cycles, scaled by the
+    /// volume. Only the first `min(pcm.len(), duty.len())` samples are
+    /// converted.
+    pub fn encode(&self, pcm: &[i16], duty: &mut [u16]) {
+        for (d, &s) in duty.iter_mut().zip(pcm) {
+            *d = self.duty(s);
+        }
+    }
+
+    /// Converts a single sample to a duty cycle
+    #[inline]
+    pub fn duty(&self, sample: i16) -> u16 {
+        let scaled = (sample as i32 * self.volume as i32) >> 8;
+        (((scaled + 0x8000) as u32 * self.top) >> 16) as u16
+    }
+
+    /// Gives access to the PWM
+    pub fn pwm(&mut self) -> &mut P {
+        &mut self.pwm
+    }
+
+    /// Centers the output and releases the PWM. The DMA transfer must be
+    /// stopped first.
+    pub fn free(mut self, _sink: AudioSink) -> P {
+        self.pwm.set_duty(self.channel, self.top / 2);
+        self.pwm
+    }
+}
+
+unsafe impl Buffer for AudioSink {
+    type Beat = u16;
+
+    #[inline]
+    fn dma_ptr(&mut self) -> *mut Self::Beat {
+        self.ccbuf
+    }
+
+    #[inline]
+    fn incrementing(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn buffer_len(&self) -> usize {
+        1
+    }
+}
@@ -1,8 +1,10 @@
 // Note: section 7.2.3 shows which pins support I2C Hs mode
 
 use crate::clock;
+use crate::clock_scaling::{rescale_sync_baud, ClockListener};
 use crate::hal::blocking::i2c::{Read, Write, WriteRead};
 use crate::sercom::v1::pads::CompatiblePad;
+use crate::sercom::v1::{AnyI2c, SercomState};
 use crate::sercom::v2::{Pad0, Pad1};
 use crate::target_device::sercom0::I2CM;
 use crate::target_device::{MCLK, SERCOM0, SERCOM1, SERCOM2, SERCOM3, SERCOM4, SERCOM5};
@@ -12,6 +14,7 @@ use crate::time::Hertz;
 
 const BUS_STATE_IDLE: u8 = 1;
 const BUS_STATE_OWNED: u8 = 2;
+const BUS_STATE_BUSY: u8 = 3;
 
 const MASTER_ACT_READ: u8 = 2;
 const MASTER_ACT_STOP: u8 = 3;
@@ -43,6 +46,7 @@ where
     sda: P0,
     scl: P1,
     sercom: $SERCOM,
+    arbitration_retries: u8,
 }
 
 impl<P0, P1> $Type<P0, P1>
@@ -54,8 +58,8 @@ where
     /// The clock is obtained via the `GenericClockGenerator` type.
     /// `freq` specifies the bus frequency to use for I2C communication.
     /// There are typically a handful of values that tend to be supported;
-    /// standard mode is 100.khz(), full speed mode is 400.khz().
-    /// The hardware in the atsamd device supports fast mode at 1.mhz()
+    /// standard mode is 100.kHz(), full speed mode is 400.kHz().
+    /// The hardware in the atsamd device supports fast mode at 1.MHz()
     /// and fast mode, but there may be additional hardware configuration
     /// missing from the current software implementation that prevents that
     /// from working as-written today.
@@ -63,7 +67,7 @@ where
     /// ```no_run
     /// let mut i2c = I2CMaster3::new(
     ///     &clocks.sercom3_core(&gclk0).unwrap(),
-    ///     400.khz(),
+    ///     400.kHz(),
     ///     p.device.SERCOM3,
     ///     &mut p.device.MCLK,
     ///     // Metro M0 express has I2C on pins PA22, PA23
@@ -98,7 +102,7 @@ where
 
             // set the baud rate
             let gclk = clock.freq();
-            let baud = (gclk.0 / (2 * freq.into().0) - 1) as u8;
+            let baud = (gclk.to_Hz() / (2 * freq.into().to_Hz()) - 1) as u8;
             sercom.i2cm().baud.modify(|_, w| w.baud().bits(baud));
 
             sercom.i2cm().ctrla.modify(|_, w| w.enable().set_bit());
@@ -114,7 +118,12 @@ where
             while sercom.i2cm().syncbusy.read().sysop().bit_is_set() {}
         }
 
-        Self { sda, scl, sercom }
+        Self {
+            sda,
+            scl,
+            sercom,
+            arbitration_retries: 0,
+        }
     }
 
     /// Breaks the sercom device up into its constituent pins and the SERCOM
@@ -123,13 +132,141 @@ where
         (self.sda, self.scl, self.sercom)
     }
 
-    fn start_tx_write(&mut self, addr: u8) -> Result<(), I2CError> {
+    /// Captures the main registers of the SERCOM, for debugging
+    pub fn debug_state(&self) -> SercomState {
+        let regs = self.sercom.i2cm();
+        SercomState {
+            ctrla: regs.ctrla.read().bits(),
+            ctrlb: regs.ctrlb.read().bits(),
+            baud: regs.baud.read().bits(),
+            status: regs.status.read().bits(),
+            intflag: regs.intflag.read().bits(),
+            syncbusy: regs.syncbusy.read().bits(),
+        }
+    }
+
+    /// Checks whether a device answers at the 7-bit address `addr`, by
+    /// sending the address for a write and stopping without data.
+    ///
+    /// Returns `Err(I2CError::AddressNack)` if no device acknowledged it.
+    /// The other errors point to a problem with the bus itself.
+    pub fn probe(&mut self, addr: u8) -> Result<(), I2CError> {
+        self.transaction(|i2c| i2c.start_tx_write(addr))
+    }
+
+    /// Probes every address outside of the reserved ones, 0x08 to 0x77,
+    /// and returns a bitmap of the addresses which were acknowledged: bit
+    /// `n` is set if a device answered at address `n`.
+    ///
+    /// Stops at the first error other than an address NACK, as the bus
+    /// then needs attention before any device can be told apart.
+    ///
+    /// ```no_run
+    /// let found = i2c.scan().unwrap();
+    /// for addr in 0..128 {
+    ///     if found & (1 << addr) != 0 {
+    ///         // a device answered at `addr`
+    ///     }
+    /// }
+    /// ```
+    pub fn scan(&mut self) -> Result<u128, I2CError> {
+        let mut found = 0;
+        for addr in 0x08..=0x77 {
+            match self.probe(addr) {
+                Ok(()) => found |= 1 << addr,
+                Err(I2CError::AddressNack) => (),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(found)
+    }
+
+    /// Prepares the master to share the bus with other masters.
+    ///
+    /// `inactive` lets the hardware see the bus as idle again once SCL has
+    /// been high for that long, in case another master went away without
+    /// sending a STOP. SCL low timeouts are enabled, so that a master or
+    /// device holding SCL low for more than 25ms is reported as
+    /// `I2CError::Timeout` rather than hanging the bus.
+    ///
+    /// Transactions which lose arbitration, or find the bus busy with
+    /// another master, are retried up to `retries` times once the bus is
+    /// free. Without retries, which is the default, they fail at once with
+    /// `I2CError::ArbitrationLost` or `I2CError::BusBusy`.
+    ///
+    /// The SCL clock is synchronized with the other masters by the hardware,
+    /// which only counts the high period once SCL is actually high.
+    ///
+    /// ```no_run
+    /// i2c.enable_multi_master(InactiveTimeout::Us205, 3);
+    /// ```
+    pub fn enable_multi_master(&mut self, inactive: InactiveTimeout, retries: u8) {
+        let i2cm = self.i2cm();
+        // INACTOUT and LOWTOUTEN are enable-protected
+        i2cm.ctrla.modify(|_, w| w.enable().clear_bit());
+        while i2cm.syncbusy.read().enable().bit_is_set() {}
+        i2cm.ctrla.modify(|_, w| {
+            w.inactout().bits(inactive as u8);
+            w.lowtouten().set_bit()
+        });
+        i2cm.ctrla.modify(|_, w| w.enable().set_bit());
+        while i2cm.syncbusy.read().enable().bit_is_set() {}
+
+        unsafe {
+            i2cm.status.modify(|_, w| w.busstate().bits(BUS_STATE_IDLE));
+        }
+        while i2cm.syncbusy.read().sysop().bit_is_set() {}
+
+        self.arbitration_retries = retries;
+    }
+
+    /// Checks that no other master is using the bus before sending a START.
+    /// The hardware tracks the state of the bus from the START and STOP
+    /// conditions it sees.
+    ///
+    /// This does not wait for the bus. A busy bus is reported at once as
+    /// `I2CError::BusBusy`, which `transaction` retries if
+    /// `enable_multi_master` allowed it.
+    fn check_bus(&mut self) -> Result<(), I2CError> {
+        match self.i2cm().status.read().busstate().bits() {
+            BUS_STATE_IDLE | BUS_STATE_OWNED => Ok(()),
+            BUS_STATE_BUSY => Err(I2CError::BusBusy),
+            // Unknown, the state after the SERCOM is enabled
+            _ => Err(I2CError::BusError),
+        }
+    }
+
+    /// Runs `transfer` and sends a STOP. After an arbitration loss or a busy
+    /// bus, waits for the other master to release the bus and tries again,
+    /// as many times as allowed by `enable_multi_master`.
+    ///
+    /// No STOP is sent once the retries are used up, as the bus then
+    /// belongs to the other master.
+    fn transaction<F>(&mut self, mut transfer: F) -> Result<(), I2CError>
+    where
+        F: FnMut(&mut Self) -> Result<(), I2CError>,
+    {
+        let mut retries = self.arbitration_retries;
         loop {
-            match self.i2cm().status.read().busstate().bits() {
-                BUS_STATE_IDLE | BUS_STATE_OWNED => break,
-                _ => continue,
+            let res = transfer(self);
+            match res {
+                Err(I2CError::ArbitrationLost) | Err(I2CError::BusBusy) if retries > 0 => {
+                    retries -= 1;
+                    // The STOP of the other master, or the inactive timeout,
+                    // brings the bus back to idle
+                    while self.i2cm().status.read().busstate().bits() == BUS_STATE_BUSY {}
+                }
+                Err(I2CError::ArbitrationLost) | Err(I2CError::BusBusy) => return res,
+                _ => {
+                    self.cmd_stop();
+                    return res;
+                }
             }
         }
+    }
+
+    fn start_tx_write(&mut self, addr: u8) -> Result<(), I2CError> {
+        self.check_bus()?;
 
         // Signal start and transmit encoded address.
         unsafe {
@@ -141,10 +278,12 @@ where
         // wait for transmission to complete
         while !self.i2cm().intflag.read().mb().bit_is_set() {}
 
-        self.status_to_err()
+        self.status_to_err(I2CError::AddressNack)
     }
 
-    fn status_to_err(&mut self) -> Result<(), I2CError> {
+    /// Returns the error flagged in STATUS, if any. A NACK is reported as
+    /// `nack`, as its meaning depends on the byte which was sent.
+    fn status_to_err(&mut self, nack: I2CError) -> Result<(), I2CError> {
         let status = self.i2cm().status.read();
         if status.arblost().bit_is_set() {
             return Err(I2CError::ArbitrationLost);
@@ -153,7 +292,7 @@ where
             return Err(I2CError::BusError);
         }
         if status.rxnack().bit_is_set() {
-            return Err(I2CError::Nack);
+            return Err(nack);
         }
         if status.lowtout().bit_is_set() || status.sexttout().bit_is_set()
             || status.mexttout().bit_is_set()
@@ -165,12 +304,7 @@ where
     }
 
     fn start_tx_read(&mut self, addr: u8) -> Result<(), I2CError> {
-        loop {
-            match self.i2cm().status.read().busstate().bits() {
-                BUS_STATE_IDLE | BUS_STATE_OWNED => break,
-                _ => continue,
-            }
-        }
+        self.check_bus()?;
 
         self.i2cm().intflag.modify(|_, w| w.error().clear_bit());
 
@@ -194,7 +328,7 @@ where
             }
         }
 
-        self.status_to_err()
+        self.status_to_err(I2CError::AddressNack)
     }
 
     fn wait_sync(&mut self) {
@@ -239,7 +373,7 @@ where
                     break;
                 }
             }
-            self.status_to_err()?;
+            self.status_to_err(I2CError::DataNack)?;
         }
         Ok(())
     }
@@ -290,6 +424,38 @@ where
     }
 }
 
+impl<P0, P1> AnyI2c for $Type<P0, P1>
+where
+    P0: CompatiblePad<Sercom = $SERCOM, PadNum = Pad0>,
+    P1: CompatiblePad<Sercom = $SERCOM, PadNum = Pad1>,
+{
+    type Sercom = $SERCOM;
+}
+
+impl<P0, P1> ClockListener for $Type<P0, P1>
+where
+    P0: CompatiblePad<Sercom = $SERCOM, PadNum = Pad0>,
+    P1: CompatiblePad<Sercom = $SERCOM, PadNum = Pad1>,
+{
+    /// Restores the SCL rate after the SERCOM core clock changed. Call this
+    /// between transactions, as the bus is forced back to idle.
+    fn clock_changed(&mut self, old: Hertz, new: Hertz) {
+        let baud = rescale_sync_baud(self.i2cm().baud.read().baud().bits(), old, new);
+        let i2cm = self.i2cm();
+        unsafe {
+            // BAUD is enable-protected
+            i2cm.ctrla.modify(|_, w| w.enable().clear_bit());
+            while i2cm.syncbusy.read().enable().bit_is_set() {}
+            i2cm.baud.modify(|_, w| w.baud().bits(baud));
+            i2cm.ctrla.modify(|_, w| w.enable().set_bit());
+            while i2cm.syncbusy.read().enable().bit_is_set() {}
+
+            i2cm.status.modify(|_, w| w.busstate().bits(BUS_STATE_IDLE));
+            while i2cm.syncbusy.read().sysop().bit_is_set() {}
+        }
+    }
+}
+
 impl<P0, P1> Write for $Type<P0, P1>
 where
     P0: CompatiblePad<Sercom = $SERCOM, PadNum = Pad0>,
@@ -299,9 +465,7 @@ where
 
     /// Sends bytes to slave with address `addr`
     fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Self::Error> {
-        let res = self.do_write(addr, bytes);
-        self.cmd_stop();
-        res
+        self.transaction(|i2c| i2c.do_write(addr, bytes))
     }
 }
 
@@ -313,9 +477,7 @@ where
     type Error = I2CError;
 
     fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
-        let res = self.do_read(addr, buffer);
-        self.cmd_stop();
-        res
+        self.transaction(|i2c| i2c.do_read(addr, buffer))
     }
 }
 
@@ -327,9 +489,7 @@ where
     type Error = I2CError;
 
     fn write_read(&mut self, addr: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Self::Error> {
-        let res = self.do_write_read(addr, bytes, buffer);
-        self.cmd_stop();
-        res
+        self.transaction(|i2c| i2c.do_write_read(addr, bytes, buffer))
     }
 }
 
@@ -417,11 +577,38 @@ i2c!([
         ),
 ]);
 
-#[derive(Debug)]
+/// How long SCL must stay high before the bus is considered idle, when
+/// sharing it with other masters
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(u8)]
+pub enum InactiveTimeout {
+    /// The bus only becomes idle on a STOP condition
+    Disabled = 0,
+    /// 55µs
+    Us55 = 1,
+    /// 105µs
+    Us105 = 2,
+    /// 205µs
+    Us205 = 3,
+}
+
+/// Errors of the I2C masters
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum I2CError {
+    /// Another master took over the bus
     ArbitrationLost,
+    /// Another master was using the bus
+    BusBusy,
     AddressError,
+    /// A misplaced start or stop condition was seen, or the bus was busy
     BusError,
+    /// SCL was held low for too long
     Timeout,
+    /// No device acknowledged the address
+    AddressNack,
+    /// The device acknowledged its address, but not a data byte
+    DataNack,
+    /// No longer returned, a NACK is reported as `AddressNack` or `DataNack`
+    #[deprecated(note = "use `AddressNack` or `DataNack`")]
     Nack,
 }
@@ -1,8 +1,10 @@
 use core::marker::PhantomData;
 
 use crate::clock;
+use crate::clock_scaling::{rescale_sync_baud, ClockListener};
 use crate::hal::spi::{FullDuplex, Mode, Phase, Polarity};
 use crate::sercom::v1::pads::CompatiblePad;
+use crate::sercom::v1::SercomState;
 use crate::sercom::v2::*;
 use crate::spi_common::CommonSpi;
 use crate::target_device::sercom0::SPIM;
@@ -221,6 +223,32 @@ macro_rules! spi_master {
             pub fn free(self) -> (Padout<$Sercom, MISO, MOSI, SCK>, $SERCOM) {
                 (self.padout, self.sercom)
             }
+
+            /// Captures the main registers of the SERCOM, for debugging
+            pub fn debug_state(&self) -> SercomState {
+                let regs = self.spi();
+                SercomState {
+                    ctrla: regs.ctrla.read().bits(),
+                    ctrlb: regs.ctrlb.read().bits(),
+                    baud: regs.baud.read().bits().into(),
+                    status: regs.status.read().bits(),
+                    intflag: regs.intflag.read().bits(),
+                    syncbusy: regs.syncbusy.read().bits(),
+                }
+            }
+        }
+
+        impl<MISO, MOSI, SCK> ClockListener for $Type<MISO, MOSI, SCK> {
+            /// Restores the SCK rate after the SERCOM core clock changed
+            fn clock_changed(&mut self, old: Hertz, new: Hertz) {
+                let baud = self.spi().baud.read().baud().bits();
+                let baud = rescale_sync_baud(baud, old, new);
+                self.disable();
+                unsafe {
+                    self.spi_mut().baud.modify(|_, w| w.baud().bits(baud));
+                }
+                self.enable();
+            }
         }
 
         impl<MISO, MOSI, SCK> FullDuplex<u8> for $Type<MISO, MOSI, SCK> {
@@ -1,7 +1,10 @@
 use crate::clock;
+use crate::clock_scaling::{rescale_uart_baud, ClockListener};
 use crate::hal::blocking::serial::{write::Default, Write};
 use crate::hal::serial;
+use crate::interrupt_handler::ClearStatus;
 use crate::sercom::v1::pads::CompatiblePad;
+use crate::sercom::v1::{AnyUart, CharSize, Parity, SercomState, StopBits, UartConfig};
 use crate::sercom::v2::*;
 use crate::target_device::sercom0::USART_INT;
 use crate::target_device::{MCLK, SERCOM0, SERCOM1, SERCOM2, SERCOM3, SERCOM4, SERCOM5};
@@ -11,6 +14,9 @@ use crate::time::Hertz;
 use core::fmt;
 use core::marker::PhantomData;
 
+pub mod lin;
+pub use lin::{LinMaster, LinSlave};
+
 /// The RxpoTxpo trait defines a way to get the data in and data out pin out
 /// values for a given UARTXPadout configuration. You should not implement
 /// this trait for yourself; only the implementations in the sercom module make
@@ -24,12 +30,24 @@ pub trait RxpoTxpo {
 }
 
 macro_rules! padout {
+    ( ($rxpo:literal, $txpo:literal) => $pad0:ident) => {
+        impl RxpoTxpo for ($pad0,) {
+            const RXPO: u8 = $rxpo;
+            const TXPO: u8 = $txpo;
+        }
+    };
     ( ($rxpo:literal, $txpo:literal) => $pad0:ident, $pad1:ident) => {
         impl RxpoTxpo for ($pad0, $pad1) {
             const RXPO: u8 = $rxpo;
             const TXPO: u8 = $txpo;
         }
     };
+    ( ($rxpo:literal, $txpo:literal) => $pad0:ident, $pad1:ident, $pad2:ident) => {
+        impl RxpoTxpo for ($pad0, $pad1, $pad2) {
+            const RXPO: u8 = $rxpo;
+            const TXPO: u8 = $txpo;
+        }
+    };
     ( ($rxpo:literal, $txpo:literal) => $pad0:ident, $pad1:ident, $pad2:ident, $pad3:ident) => {
         impl RxpoTxpo for ($pad0, $pad1, $pad2, $pad3) {
             const RXPO: u8 = $rxpo;
@@ -43,19 +61,17 @@ macro_rules! padout {
 // txpo 0 no RTS/CTS
 // txpo 1 reserved and can't be used
 // txpo 2 RTS PAD 2, CTS PAD 3
-// txpo 3 RTS PAD 2, no CTS
-// (rxpo_txpo) => (RX, TX, RTS, CTS)
+// txpo 3 RS-485, TE PAD 2
+// (rxpo_txpo) => (RX, TX, RTS, CTS) or (RX, TX, TE) or (RX and TX)
+padout!((0, 0) => Pad0);
+
 padout!((1, 0) => Pad1, Pad0);
 padout!((1, 2) => Pad1, Pad0, Pad2, Pad3);
-
-// todo we could support an RTS without a CTS
-// padout!((1, 3) => Pad1, Pad0, Pad2);
+padout!((1, 3) => Pad1, Pad0, Pad2);
 
 padout!((2, 0) => Pad2, Pad0);
 padout!((3, 0) => Pad3, Pad0);
-
-// todo we could support an RTS without a CTS
-// padout!((3, 3) => Pad3, Pad0, Pad2);
+padout!((3, 3) => Pad3, Pad0, Pad2);
 
 /// A pad mapping configuration for the SERCOM in UART mode.
 ///
@@ -128,6 +144,45 @@ where
     }
 }
 
+/// A single pad carrying both TX and RX, for half-duplex single-wire buses
+///
+/// The UARTs created with it have `()` as their RX pad, see their
+/// `send_single_wire` method.
+pub struct SingleWire<P>(pub P);
+
+/// Error of a single-wire transfer: another device drove the line while this
+/// one was sending
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Collision;
+
+/// Convert from a single wire to UARTXPadout
+impl<S, PAD> From<SingleWire<PAD>> for Padout<S, (), PAD, (), ()>
+where
+    S: Sercom,
+    PAD: CompatiblePad<Sercom = S>,
+    (PAD::PadNum,): RxpoTxpo,
+{
+    fn from(pad: SingleWire<PAD>) -> Padout<S, (), PAD, (), ()> {
+        Padout {
+            sercom: PhantomData,
+            rx: (),
+            tx: pad.0,
+            rts: (),
+            cts: (),
+        }
+    }
+}
+
+impl<S, PAD> RxpoTxpo for Padout<S, (), PAD, (), ()>
+where
+    S: Sercom,
+    PAD: CompatiblePad<Sercom = S>,
+    (PAD::PadNum,): RxpoTxpo,
+{
+    const RXPO: u8 = <(PAD::PadNum,)>::RXPO;
+    const TXPO: u8 = <(PAD::PadNum,)>::TXPO;
+}
+
 /// Convert from a tuple of (RX, TX) to UARTXPadout
 impl<S, PAD0, PAD1> From<(PAD0, PAD1)> for Padout<S, PAD0, PAD1, (), ()>
 where
@@ -158,6 +213,38 @@ where
     const TXPO: u8 = <(PAD0::PadNum, PAD1::PadNum)>::TXPO;
 }
 
+/// Convert from a tuple of (RX, TX, TE) to UARTXPadout
+impl<S, PAD0, PAD1, PAD2> From<(PAD0, PAD1, PAD2)> for Padout<S, PAD0, PAD1, PAD2, ()>
+where
+    S: Sercom,
+    PAD0: CompatiblePad<Sercom = S>,
+    PAD1: CompatiblePad<Sercom = S>,
+    PAD2: CompatiblePad<Sercom = S>,
+    (PAD0::PadNum, PAD1::PadNum, PAD2::PadNum): RxpoTxpo,
+{
+    fn from(pads: (PAD0, PAD1, PAD2)) -> Padout<S, PAD0, PAD1, PAD2, ()> {
+        Padout {
+            sercom: PhantomData,
+            rx: pads.0,
+            tx: pads.1,
+            rts: pads.2,
+            cts: (),
+        }
+    }
+}
+
+impl<S, PAD0, PAD1, PAD2> RxpoTxpo for Padout<S, PAD0, PAD1, PAD2, ()>
+where
+    S: Sercom,
+    PAD0: CompatiblePad<Sercom = S>,
+    PAD1: CompatiblePad<Sercom = S>,
+    PAD2: CompatiblePad<Sercom = S>,
+    (PAD0::PadNum, PAD1::PadNum, PAD2::PadNum): RxpoTxpo,
+{
+    const RXPO: u8 = <(PAD0::PadNum, PAD1::PadNum, PAD2::PadNum)>::RXPO;
+    const TXPO: u8 = <(PAD0::PadNum, PAD1::PadNum, PAD2::PadNum)>::TXPO;
+}
+
 /// Convert from a tuple of (RX, TX, RTS, CTS) to UARTXPadout
 impl<S, PAD0, PAD1, PAD2, PAD3> From<(PAD0, PAD1, PAD2, PAD3)> for Padout<S, PAD0, PAD1, PAD2, PAD3>
 where
@@ -224,9 +311,28 @@ macro_rules! uart {
             pub struct $Type<RX, TX, RTS, CTS> {
                 padout: Padout<$Sercom, RX, TX, RTS, CTS>,
                 sercom: $SERCOM,
+                brk: bool,
+                /// Set once a frame was written, after which TXC is set at
+                /// the end of each transmission
+                sent: bool,
+                /// Set from an auto-baud break until the sync character after
+                /// it is measured
+                sync: bool,
             }
 
             impl<RX, TX, RTS, CTS> $Type<RX, TX, RTS, CTS> {
+                /// Power on and configure SERCOMX to work as a UART Master operating
+                /// with the specified frequency. The padout specifies
+                /// which pins are bound to the RX, TX and optionally RTS and CTS
+                /// functions.
+                ///
+                /// You can use any tuple of two, three or four SercomXPadY
+                /// instances for which there exists a From implementation for
+                /// UARTXPadout. With four pads, RTS and CTS are managed by
+                /// the SERCOM. With three pads, the third one is the TE
+                /// output of RS-485 mode, see
+                /// [`set_guard_time`](Self::set_guard_time). A [`SingleWire`]
+                /// pad carries both TX and RX.
                 pub fn new<F: Into<Hertz>, T: Into<Padout<$Sercom, RX, TX, RTS, CTS>>>(
                     clock: &clock::$clock,
                     freq: F,
@@ -266,7 +372,7 @@ macro_rules! uart {
 
                         // Calculate value for BAUD register
                         let sample_rate: u8 = 16;
-                        let fref = clock.freq().0;
+                        let fref = clock.freq().to_Hz();
 
                         // TODO: Support fractional BAUD mode
                         // let mul_ratio = (fref.0 * 1000) / (freq.into().0 * 16);
@@ -280,7 +386,7 @@ macro_rules! uart {
                         // });
 
                         // Asynchronous arithmetic mode (Table 24-2 in datasheet)
-                        let baud = calculate_baud_value(freq.into().0, fref, sample_rate);
+                        let baud = calculate_baud_value(freq.into().to_Hz(), fref, sample_rate);
 
                         sercom.usart_int().baud().modify(|_, w| {
                             w.baud().bits(baud)
@@ -309,6 +415,9 @@ macro_rules! uart {
                     Self {
                         padout,
                         sercom,
+                        brk: false,
+                        sent: false,
+                        sync: false,
                     }
                 }
 
@@ -323,10 +432,12 @@ macro_rules! uart {
                         [<$Type Tx>] {
                             padout: tx_pads,
                             sercom: self.sercom,
+                            sent: self.sent,
                         },
                         [<$Type Rx>] {
                             padout: rx_pads,
                             sercom: PhantomData,
+                            brk: self.brk,
                         },
                     )
                 }
@@ -336,6 +447,22 @@ macro_rules! uart {
                     Self {
                         padout: Padout::join(tx.padout, rx.padout),
                         sercom: tx.sercom,
+                        brk: rx.brk,
+                        sent: tx.sent,
+                        sync: false,
+                    }
+                }
+
+                /// Captures the main registers of the SERCOM, for debugging
+                pub fn debug_state(&self) -> SercomState {
+                    let regs = self.usart();
+                    SercomState {
+                        ctrla: regs.ctrla.read().bits(),
+                        ctrlb: regs.ctrlb.read().bits(),
+                        baud: regs.baud().read().bits().into(),
+                        status: regs.status.read().bits(),
+                        intflag: regs.intflag.read().bits(),
+                        syncbusy: regs.syncbusy.read().bits(),
                     }
                 }
 
@@ -364,6 +491,156 @@ macro_rules! uart {
                 pub fn flags(&self) -> crate::target_device::sercom0::usart_int::status::R {
                     self.usart().status.read()
                 }
+
+                /// Sends a break, holding the line low for 27 bit times.
+                pub fn send_break(&mut self) {
+                    send_break(self.sercom.usart_int(), &mut self.sent);
+                }
+
+                /// Reads a character of up to 9 bits, see
+                /// [`UartConfig::char_size`]
+                pub fn read_word(&mut self) -> nb::Result<u16, ()> {
+                    [<$Type Rx>]::<RX, CTS>::do_read(self.sercom.usart_int(), &mut self.brk)
+                }
+
+                /// Writes a character of up to 9 bits, see
+                /// [`UartConfig::char_size`]
+                pub fn write_word(&mut self, word: u16) -> nb::Result<(), ()> {
+                    [<$Type Tx>]::<TX, RTS>::do_write(self.sercom.usart_int(), &mut self.sent, word)
+                }
+
+                /// Applies the character format and line options of `config`
+                pub fn configure(&mut self, config: UartConfig) {
+                    configure(self.usart(), &config);
+                }
+
+                /// Returns the baud rate measured after a break, with auto-baud
+                /// enabled by [`configure`](Self::configure), once per break.
+                /// `clock` is the SERCOM core clock given to [`new`](Self::new).
+                ///
+                /// The rate is returned once a character is received after the
+                /// break, before it is read. A sync character too far off to
+                /// be measured is dropped, and the baud rate is left
+                /// unchanged.
+                pub fn detected_baud(&mut self, clock: &clock::$clock) -> Option<Hertz> {
+                    detected_baud(self.sercom.usart_int(), clock.freq().to_Hz(), &mut self.sync)
+                }
+
+                /// Returns true if a break was received since the last call
+                ///
+                /// A break is received as a zero character with a frame
+                /// error, which the reads drop, so it is only seen once the
+                /// characters before it have been read. The frame error
+                /// raises the `error` interrupt.
+                pub fn break_detected(&mut self) -> bool {
+                    core::mem::replace(&mut self.brk, false)
+                }
+
+                /// Returns true if a collision was detected since the last
+                /// call, with collision detection enabled by [`configure`](Self::configure)
+                pub fn collision_detected(&mut self) -> bool {
+                    let usart = self.usart();
+                    let coll = usart.status.read().coll().bit_is_set();
+                    if coll {
+                        usart.status.write(|w| w.coll().set_bit());
+                    }
+                    coll
+                }
+            }
+
+            /// Hardware flow control, with the `(RX, TX, RTS, CTS)` padout
+            ///
+            /// The SERCOM drives RTS high while its receive buffer is full, and
+            /// holds back the next character while CTS is high, so nothing is
+            /// lost at high baud rates even if the other end is slow.
+            impl<RX, TX, RTS, CTS> $Type<RX, TX, RTS, CTS>
+            where
+                RTS: CompatiblePad<Sercom = $Sercom>,
+                CTS: CompatiblePad<Sercom = $Sercom>,
+            {
+                /// Returns true while the CTS pin is high, i.e. while the
+                /// other end does not accept more characters
+                pub fn is_cts_high(&self) -> bool {
+                    self.usart().status.read().cts().bit_is_set()
+                }
+
+                /// Returns true if the level of the CTS pin changed since the
+                /// last call. The `ctsic` interrupt is raised on each change.
+                pub fn cts_changed(&mut self) -> bool {
+                    let usart = self.usart();
+                    let changed = usart.intflag.read().ctsic().bit_is_set();
+                    if changed {
+                        usart.intflag.write(|w| w.ctsic().set_bit());
+                    }
+                    changed
+                }
+            }
+
+            /// RS-485 mode, with the `(RX, TX, TE)` padout
+            ///
+            /// The SERCOM drives TE high from the start bit of the first
+            /// character written until the guard time has passed after the
+            /// last stop bit, so it can enable the driver of a half-duplex
+            /// transceiver without any software timing.
+            impl<RX, TX, TE> $Type<RX, TX, TE, ()>
+            where
+                TE: CompatiblePad<Sercom = $Sercom>,
+            {
+                /// Sets the time TE stays high after the last stop bit, from
+                /// 0 to 7 bit times. It is 2 bit times after [`new`](Self::new).
+                pub fn set_guard_time(&mut self, bits: u8) {
+                    let usart = self.usart();
+                    while_disabled(usart, || {
+                        usart
+                            .ctrlc
+                            .modify(|_, w| unsafe { w.gtime().bits(bits.min(7)) });
+                    });
+                }
+            }
+
+            /// Single-wire half-duplex mode, with the [`SingleWire`] padout
+            ///
+            /// TX and RX share one pad, which the transmitter drives whenever
+            /// it is enabled. It is enabled by [`new`](Self::new), so call
+            /// [`release_line`](Self::release_line) before listening to the
+            /// other devices. The line needs a pull-up while released.
+            impl<TX> $Type<(), TX, (), ()>
+            where
+                TX: CompatiblePad<Sercom = $Sercom>,
+            {
+                /// Sends `bytes`, then releases the line once the last stop
+                /// bit is out, in time for the reply of the other device
+                ///
+                /// The receiver sees the characters sent, which are dropped.
+                /// Returns [`Collision`] if a collision was detected, with
+                /// collision detection enabled by [`configure`](Self::configure).
+                pub fn send_single_wire(&mut self, bytes: &[u8]) -> Result<(), Collision> {
+                    self.sent |= !bytes.is_empty();
+                    send_single_wire(self.sercom.usart_int(), bytes)
+                }
+
+                /// Disables the transmitter right away, without waiting for
+                /// the characters written
+                pub fn release_line(&mut self) {
+                    set_transmitter(self.usart(), false);
+                }
+            }
+
+            impl<RX, TX, RTS, CTS> AnyUart for $Type<RX, TX, RTS, CTS> {
+                type Sercom = $Sercom;
+
+                fn sent(&mut self) -> &mut bool {
+                    &mut self.sent
+                }
+            }
+
+            impl<RX, TX, RTS, CTS> ClockListener for $Type<RX, TX, RTS, CTS> {
+                /// Restores the baud rate after the SERCOM core clock changed
+                fn clock_changed(&mut self, old: Hertz, new: Hertz) {
+                    let usart = self.usart();
+                    let baud = usart.baud().read().baud().bits();
+                    set_baud(usart, rescale_uart_baud(baud, old, new));
+                }
             }
 
             /// The transmitting half of the corresponding UARTX instance (as returned by `UARTX::split`)
@@ -372,6 +649,7 @@ macro_rules! uart {
                 /// We store the SERCOM object here so we can retrieve it later,
                 /// but conceptually, ownership is shared between the Rx and Tx halves.
                 sercom: $SERCOM,
+                sent: bool,
             }
 
             impl<TX, RTS> [<$Type Tx>]<TX, RTS> {
@@ -382,7 +660,7 @@ macro_rules! uart {
                     (*$SERCOM::ptr()).usart_int()
                 }
 
-                fn do_write(usart: &USART_INT, word: u8) -> nb::Result<(), ()> {
+                fn do_write(usart: &USART_INT, sent: &mut bool, word: u16) -> nb::Result<(), ()> {
                     unsafe {
                         if !usart.intflag.read().dre().bit_is_set() {
                             return Err(nb::Error::WouldBlock);
@@ -393,6 +671,7 @@ macro_rules! uart {
                         });
                     }
 
+                    *sent = true;
                     Ok(())
                 }
 
@@ -406,11 +685,24 @@ macro_rules! uart {
                 }
             }
 
+            impl<TX, RTS> [<$Type Tx>]<TX, RTS> {
+                /// Sends a break, holding the line low for 27 bit times.
+                pub fn send_break(&mut self) {
+                    send_break(self.sercom.usart_int(), &mut self.sent);
+                }
+
+                /// Writes a character of up to 9 bits, see
+                /// [`UartConfig::char_size`]
+                pub fn write_word(&mut self, word: u16) -> nb::Result<(), ()> {
+                    Self::do_write(self.sercom.usart_int(), &mut self.sent, word)
+                }
+            }
+
             impl<TX, RTS> serial::Write<u8> for [<$Type Tx>]<TX, RTS> {
                 type Error = ();
 
                 fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
-                    Self::do_write(unsafe { self.usart() }, word)
+                    Self::do_write(self.sercom.usart_int(), &mut self.sent, word as u16)
                 }
 
                 fn flush(&mut self) -> nb::Result<(), Self::Error> {
@@ -422,7 +714,7 @@ macro_rules! uart {
                 type Error = ();
 
                 fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
-                    [<$Type Tx>]::<TX, RTS>::do_write(self.sercom.usart_int(), word)
+                    [<$Type Tx>]::<TX, RTS>::do_write(self.sercom.usart_int(), &mut self.sent, word as u16)
                 }
 
                 fn flush(&mut self) -> nb::Result<(), Self::Error> {
@@ -434,20 +726,24 @@ macro_rules! uart {
             pub struct [<$Type Rx>]<RX, CTS> {
                 padout: RxPadout<$Sercom, RX, CTS>,
                 sercom: PhantomData<$SERCOM>,
+                brk: bool,
             }
 
             impl<RX, CTS> [<$Type Rx>]<RX, CTS> {
                 /// # Safety
                 ///
                 /// Only this struct instance should be able to access RX-related fields on this SERCOM.
-                unsafe fn usart(&self) -> &USART_INT {
+                unsafe fn usart(&self) -> &'static USART_INT {
                     (*$SERCOM::ptr()).usart_int()
                 }
 
-                fn do_read(usart: &USART_INT) -> nb::Result<u8, ()> {
-                    // A frame error occurred, so discard the byte in DATA.
+                fn do_read(usart: &USART_INT, brk: &mut bool) -> nb::Result<u16, ()> {
+                    // A frame error occurred, so discard the byte in DATA. A
+                    // zero with a frame error is a break.
                     if usart.status.read().ferr().bit_is_set() {
-                        usart.data.read();
+                        if usart.data.read().bits() == 0 {
+                            *brk = true;
+                        }
                         usart.status.write(|w| w.ferr().set_bit());
                     }
 
@@ -457,7 +753,36 @@ macro_rules! uart {
                     }
 
                     let data = usart.data.read().bits();
-                    Ok(data as u8)
+                    Ok(data as u16)
+                }
+            }
+
+            impl<RX, CTS> [<$Type Rx>]<RX, CTS> {
+                fn do_clear_status(usart: &USART_INT) {
+                    usart.status.write(|w| {
+                        w.perr().set_bit();
+                        w.ferr().set_bit();
+                        w.bufovf().set_bit();
+                        w.isf().set_bit();
+                        w.coll().set_bit()
+                    });
+                    usart.intflag.write(|w| w.error().set_bit());
+                }
+
+                /// Reads a character of up to 9 bits, see
+                /// [`UartConfig::char_size`]
+                pub fn read_word(&mut self) -> nb::Result<u16, ()> {
+                    Self::do_read(unsafe { self.usart() }, &mut self.brk)
+                }
+
+                /// Returns true if a break was received since the last call
+                ///
+                /// A break is received as a zero character with a frame
+                /// error, which the reads drop, so it is only seen once the
+                /// characters before it have been read. The frame error
+                /// raises the `error` interrupt.
+                pub fn break_detected(&mut self) -> bool {
+                    core::mem::replace(&mut self.brk, false)
                 }
             }
 
@@ -465,7 +790,7 @@ macro_rules! uart {
                 type Error = ();
 
                 fn read(&mut self) -> nb::Result<u8, Self::Error> {
-                    Self::do_read(unsafe { self.usart() })
+                    Self::do_read(unsafe { self.usart() }, &mut self.brk).map(|word| word as u8)
                 }
             }
 
@@ -473,7 +798,120 @@ macro_rules! uart {
                 type Error = ();
 
                 fn read(&mut self) -> nb::Result<u8, Self::Error> {
-                    [<$Type Rx>]::<RX, CTS>::do_read(self.sercom.usart_int())
+                    [<$Type Rx>]::<RX, CTS>::do_read(self.sercom.usart_int(), &mut self.brk).map(|word| word as u8)
+                }
+            }
+
+            impl<RX, CTS> ClearStatus for [<$Type Rx>]<RX, CTS> {
+                fn clear_status(&mut self) {
+                    Self::do_clear_status(unsafe { self.usart() });
+                }
+            }
+
+            impl<RX, TX, RTS, CTS> ClearStatus for $Type<RX, TX, RTS, CTS> {
+                fn clear_status(&mut self) {
+                    [<$Type Rx>]::<RX, CTS>::do_clear_status(self.sercom.usart_int());
+                }
+            }
+
+            #[cfg(feature = "dma")]
+            unsafe impl<TX, RTS> crate::dmac::Buffer for [<$Type Tx>]<TX, RTS> {
+                type Beat = u8;
+
+                #[inline]
+                fn dma_ptr(&mut self) -> *mut Self::Beat {
+                    unsafe { self.usart() }.data.as_ptr() as *mut _
+                }
+
+                #[inline]
+                fn incrementing(&self) -> bool {
+                    false
+                }
+
+                #[inline]
+                fn buffer_len(&self) -> usize {
+                    1
+                }
+            }
+
+            #[cfg(feature = "dma")]
+            impl<TX, RTS> [<$Type Tx>]<TX, RTS> {
+                /// Transform the transmitting half into a DMA
+                /// [`Transfer`](crate::dmac::Transfer) sending `buf`
+                ///
+                /// The transmitter is given back by `wait` or `stop` once the
+                /// transfer is complete. `buf` must not be empty.
+                #[inline]
+                pub fn send_with_dma<Chan, B>(
+                    mut self,
+                    buf: B,
+                    channel: Chan,
+                ) -> crate::dmac::Transfer<
+                    crate::dmac::Channel<crate::dmac::ChannelId<Chan>, crate::dmac::Busy>,
+                    crate::dmac::BufferPair<B, Self>,
+                >
+                where
+                    Chan: crate::dmac::AnyChannel<Status = crate::dmac::Ready>,
+                    B: crate::dmac::Buffer<Beat = u8> + 'static,
+                {
+                    debug_assert!(buf.buffer_len() > 0);
+                    // TXC is set at the end of the transfer
+                    self.sent = true;
+
+                    // SAFETY: The source buffer is 'static, and the
+                    // transmitter is moved into the transfer
+                    unsafe { crate::dmac::Transfer::new_unchecked(channel, buf, self, false) }
+                        .begin($Sercom::DMA_TX_TRIGGER, crate::dmac::TriggerAction::BURST)
+                }
+            }
+
+            #[cfg(feature = "dma")]
+            unsafe impl<RX, CTS> crate::dmac::Buffer for [<$Type Rx>]<RX, CTS> {
+                type Beat = u8;
+
+                #[inline]
+                fn dma_ptr(&mut self) -> *mut Self::Beat {
+                    unsafe { self.usart() }.data.as_ptr() as *mut _
+                }
+
+                #[inline]
+                fn incrementing(&self) -> bool {
+                    false
+                }
+
+                #[inline]
+                fn buffer_len(&self) -> usize {
+                    1
+                }
+            }
+
+            #[cfg(feature = "dma")]
+            impl<RX, CTS> [<$Type Rx>]<RX, CTS> {
+                /// Transform the receiving half into a DMA
+                /// [`Transfer`](crate::dmac::Transfer) filling `buf`
+                ///
+                /// The receiver is given back by `wait` or `stop`. The DMAC
+                /// doesn't see frame errors, so characters received with an
+                /// error are stored like the others.
+                #[inline]
+                pub fn receive_with_dma<Chan, B>(
+                    self,
+                    buf: B,
+                    channel: Chan,
+                ) -> crate::dmac::Transfer<
+                    crate::dmac::Channel<crate::dmac::ChannelId<Chan>, crate::dmac::Busy>,
+                    crate::dmac::BufferPair<Self, B>,
+                >
+                where
+                    Chan: crate::dmac::AnyChannel<Status = crate::dmac::Ready>,
+                    B: crate::dmac::Buffer<Beat = u8> + 'static,
+                {
+                    debug_assert!(buf.buffer_len() > 0);
+
+                    // SAFETY: The destination buffer is 'static, and the
+                    // receiver is moved into the transfer
+                    unsafe { crate::dmac::Transfer::new_unchecked(channel, self, buf, false) }
+                        .begin($Sercom::DMA_RX_TRIGGER, crate::dmac::TriggerAction::BURST)
                 }
             }
 
@@ -612,11 +1050,167 @@ uart!(
 
 const SHIFT: u8 = 32;
 
+/// Returns the BAUD value of the asynchronous arithmetic mode, whose rate is
+/// `clk_freq * (65536 - BAUD) / (n_samples * 65536)`. A rate out of reach,
+/// or a stopped clock, gives the slowest rate.
 fn calculate_baud_value(baudrate: u32, clk_freq: u32, n_samples: u8) -> u16 {
     let sample_rate = (n_samples as u64 * baudrate as u64) << 32;
-    let ratio = sample_rate / clk_freq as u64;
+    let ratio = sample_rate
+        .checked_div(clk_freq as u64)
+        .map_or(1 << SHIFT, |ratio| ratio.min(1 << SHIFT));
     let scale = (1u64 << SHIFT) - ratio;
     let baud_calculated = (65536u64 * scale) >> SHIFT;
 
     baud_calculated as u16
 }
+
+/// Sends a break: the line is held low for 27 bit times, followed by 3 bit
+/// times high, which also satisfies the mark-after-break of most protocols.
+///
+/// The SERCOM has no break generation, so a zero is sent at a third of the
+/// baud rate. `sent` tells whether a frame was written before, in which case
+/// the end of its transmission is awaited. Otherwise TXC is never set.
+pub(crate) fn send_break(usart: &USART_INT, sent: &mut bool) {
+    // Wait for the end of the ongoing transmission
+    if *sent {
+        while usart.intflag.read().txc().bit_is_clear() {}
+    }
+    *sent = true;
+
+    // BAUD is enable-protected. In arithmetic mode, the baud rate is
+    // proportional to 65536 - BAUD.
+    let baud = usart.baud().read().baud().bits();
+    let slow = (65536 - (65536 - baud as u32) / 3) as u16;
+    set_baud(usart, slow);
+    // Writing DATA clears TXC
+    usart.data.write(|w| unsafe { w.bits(0) });
+    while usart.intflag.read().txc().bit_is_clear() {}
+    set_baud(usart, baud);
+}
+
+fn set_baud(usart: &USART_INT, baud: u16) {
+    while_disabled(usart, || {
+        usart.baud().write(|w| unsafe { w.baud().bits(baud) });
+    });
+}
+
+fn configure(usart: &USART_INT, config: &UartConfig) {
+    while_disabled(usart, || {
+        usart.ctrla.modify(|_, w| unsafe {
+            w.rxinv().bit(config.rx_inverted);
+            w.txinv().bit(config.tx_inverted);
+            w.form().bits(form(config))
+        });
+        usart.ctrlb.modify(|_, w| unsafe {
+            w.chsize().bits(match config.char_size {
+                CharSize::Eight => 0,
+                CharSize::Nine => 1,
+            });
+            w.pmode().bit(config.parity == Parity::Odd);
+            w.sbmode().bit(config.stop_bits == StopBits::Two);
+            w.colden().bit(config.collision_detection);
+            w.enc().bit(config.irda.is_some())
+        });
+        while usart.syncbusy.read().ctrlb().bit_is_set() {}
+        if let Some(rx_pulse_length) = config.irda {
+            usart
+                .rxpl
+                .write(|w| unsafe { w.rxpl().bits(rx_pulse_length) });
+        }
+    });
+}
+
+/// Enables or disables the transmitter, which drives TX while enabled
+fn set_transmitter(usart: &USART_INT, enabled: bool) {
+    usart.ctrlb.modify(|_, w| w.txen().bit(enabled));
+    while usart.syncbusy.read().ctrlb().bit_is_set() {}
+}
+
+/// Sends `bytes` with the transmitter enabled, then releases the line. The
+/// characters received meanwhile are the echo of those sent, and are
+/// dropped.
+fn send_single_wire(usart: &USART_INT, bytes: &[u8]) -> Result<(), Collision> {
+    let drop_echo = || {
+        while usart.intflag.read().rxc().bit_is_set() {
+            usart.data.read();
+        }
+    };
+
+    set_transmitter(usart, true);
+    for &byte in bytes {
+        while usart.intflag.read().dre().bit_is_clear() {
+            drop_echo();
+        }
+        usart.data.write(|w| unsafe { w.bits(byte as u32) });
+    }
+    // Writing DATA cleared TXC, which is set again once the shift register
+    // is empty
+    if !bytes.is_empty() {
+        while usart.intflag.read().txc().bit_is_clear() {
+            drop_echo();
+        }
+    }
+    set_transmitter(usart, false);
+    drop_echo();
+
+    let status = usart.status.read();
+    usart
+        .status
+        .write(|w| w.coll().set_bit().ferr().set_bit().bufovf().set_bit());
+    if status.coll().bit_is_set() {
+        Err(Collision)
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the CTRLA.FORM value of `config`
+fn form(config: &UartConfig) -> u8 {
+    // 1 is USART frame with parity, 4 and 5 the same with auto-baud
+    ((config.auto_baud as u8) << 2) | (config.parity != Parity::None) as u8
+}
+
+/// Returns the rate the BAUD register is set to, in the asynchronous
+/// arithmetic mode, the inverse of `calculate_baud_value`
+fn baud_rate(baud: u16, clk_freq: u32, n_samples: u8) -> u32 {
+    ((clk_freq as u64 * (65536 - baud as u64)) / (n_samples as u64 * 65536)) as u32
+}
+
+/// Returns the baud rate measured on the sync character that followed the
+/// last break, with auto-baud enabled. `sync` is set from the break until the
+/// sync character is measured.
+fn detected_baud(usart: &USART_INT, clk_freq: u32, sync: &mut bool) -> Option<Hertz> {
+    // RXBRK is set as soon as the break is detected, before the sync
+    // character is measured
+    if usart.intflag.read().rxbrk().bit_is_set() {
+        usart.intflag.write(|w| w.rxbrk().set_bit());
+        *sync = true;
+    }
+    if !*sync {
+        return None;
+    }
+    // The sync character did not give a valid rate, and BAUD is unchanged
+    if usart.status.read().isf().bit_is_set() {
+        usart.status.write(|w| w.isf().set_bit());
+        *sync = false;
+        return None;
+    }
+    // The first character completed after the break comes once the sync
+    // character is measured, so BAUD holds the new rate by then. A character
+    // with a frame error is the break itself.
+    if usart.intflag.read().rxc().bit_is_clear() || usart.status.read().ferr().bit_is_set() {
+        return None;
+    }
+    *sync = false;
+    let baud = usart.baud().read().baud().bits();
+    Some(Hertz::from_raw(baud_rate(baud, clk_freq, 16)))
+}
+
+/// Runs `f` with the USART disabled, to write its enable-protected registers
+fn while_disabled<F: FnOnce()>(usart: &USART_INT, f: F) {
+    usart.ctrla.modify(|_, w| w.enable().clear_bit());
+    while usart.syncbusy.read().enable().bit_is_set() {}
+    f();
+    usart.ctrla.modify(|_, w| w.enable().set_bit());
+    while usart.syncbusy.read().enable().bit_is_set() {}
+}
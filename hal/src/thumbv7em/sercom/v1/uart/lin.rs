@@ -0,0 +1,407 @@
+//! LIN master and slave on the SERCOM USART of the SAMD5x
+//!
+//! A LIN frame is a header sent by the master, made of a break, the sync
+//! character `0x55` and the protected identifier, followed by a response of
+//! up to eight data bytes and a checksum, sent by the master or by one of
+//! the slaves.
+//!
+//! [`LinMaster`] generates the whole header from the identifier written to
+//! `DATA`. [`LinSlave`] detects the break, and adjusts its baud rate on the
+//! sync character, so that it follows a master whose clock is not accurate.
+//! Both send and receive the response with the [`serial`] traits, and
+//! [`checksum`] computes its last byte:
+//!
+//! ```
+//! let mut lin = LinMaster::new(
+//!     &mclk,
+//!     peripherals.SERCOM0,
+//!     pins.a05.into_pad(&mut pins.port),
+//!     pins.a04.into_pad(&mut pins.port),
+//!     clock.freq(),
+//!     19_200.Hz(),
+//! );
+//! let data = [0x12, 0x34];
+//! nb::block!(lin.send_header(0x10)).unwrap();
+//! lin.bwrite_all(&data).unwrap();
+//! lin.bwrite_all(&[checksum(Checksum::Enhanced, protected_id(0x10), &data)]).unwrap();
+//! ```
+//!
+//! TX is on pad 0 and RX on pad 1. With a LIN transceiver, the receiver
+//! also sees the bytes sent, which are read back like any other.
+
+use super::calculate_baud_value;
+use crate::hal::blocking::serial::write::Default;
+use crate::hal::serial;
+use crate::sercom::v1::pads::Pad;
+use crate::sercom::v2::{IsPad, Pad0, Pad1, Sercom};
+use crate::target_device as pac;
+use crate::time::Hertz;
+
+use pac::{sercom0::USART_INT as USART, MCLK as APB_CLK_CTRL};
+
+/// Errors of the LIN transfers
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Error {
+    /// A character had no stop bit
+    Frame,
+    /// A character was lost because the previous one was not read in time
+    Overflow,
+    /// The sync character of a header did not match the current baud rate
+    /// closely enough, and the header was dropped
+    InconsistentSync,
+    /// The parity bits of a protected identifier are wrong
+    Parity,
+}
+
+/// Checksum models of the LIN specification
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Checksum {
+    /// Sum of the data bytes only, as in LIN 1.x and for the diagnostic
+    /// frames
+    Classic,
+    /// Sum of the protected identifier and of the data bytes, as in LIN 2.x
+    Enhanced,
+}
+
+/// Length of the break sent by a [`LinMaster`], in bit times
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BreakLength {
+    Bits13 = 0,
+    Bits17 = 1,
+    Bits21 = 2,
+    Bits26 = 3,
+}
+
+/// Delay between the break and the sync character sent by a [`LinMaster`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum HeaderDelay {
+    /// One bit time
+    Bits1 = 0,
+    Bits4 = 1,
+    Bits8 = 2,
+    Bits14 = 3,
+}
+
+/// Returns the protected identifier of the 6 bit identifier `id`, with its
+/// parity bits
+pub const fn protected_id(id: u8) -> u8 {
+    let id = id & 0x3f;
+    let p0 = (id ^ id >> 1 ^ id >> 2 ^ id >> 4) & 1;
+    let p1 = !(id >> 1 ^ id >> 3 ^ id >> 4 ^ id >> 5) & 1;
+    id | p0 << 6 | p1 << 7
+}
+
+/// Returns the identifier of the protected identifier `pid`, or `None` if
+/// its parity bits are wrong
+pub const fn identifier(pid: u8) -> Option<u8> {
+    if protected_id(pid) == pid {
+        Some(pid & 0x3f)
+    } else {
+        None
+    }
+}
+
+/// Returns the checksum byte of the response `data` to the protected
+/// identifier `pid`
+pub fn checksum(model: Checksum, pid: u8, data: &[u8]) -> u8 {
+    let start = match model {
+        Checksum::Classic => 0,
+        Checksum::Enhanced => pid as u16,
+    };
+    // Sum with the carries added back in
+    let sum = data.iter().fold(start, |sum, &byte| {
+        let sum = sum + byte as u16;
+        (sum & 0xff) + (sum >> 8)
+    });
+    !(sum as u8)
+}
+
+/// Resets the USART and sets it up for LIN in the frame format `form`
+fn init<S: Sercom>(
+    apb_clk_ctrl: &APB_CLK_CTRL,
+    sercom: &mut S,
+    form: u8,
+    freq: Hertz,
+    baud: Hertz,
+) {
+    sercom.enable_apb_clock(apb_clk_ctrl);
+    let usart = usart(sercom);
+
+    usart.ctrla.write(|w| w.swrst().set_bit());
+    while usart.syncbusy.read().swrst().bit_is_set() || usart.ctrla.read().swrst().bit_is_set() {
+        // wait for sync of CTRLA.SWRST
+    }
+
+    usart.ctrla.modify(|_, w| {
+        w.mode().usart_int_clk();
+        w.dord().set_bit();
+        w.sampr()._16x_arithmetic();
+        // TX on pad 0 and RX on pad 1
+        unsafe {
+            w.txpo().bits(0);
+            w.rxpo().bits(1);
+            w.form().bits(form)
+        };
+        w.runstdby().set_bit()
+    });
+
+    let baud = calculate_baud_value(baud.to_Hz(), freq.to_Hz(), 16);
+    usart.baud().write(|w| unsafe { w.baud().bits(baud) });
+
+    usart.ctrlb.modify(|_, w| {
+        unsafe { w.chsize().bits(0) };
+        w.sbmode().clear_bit();
+        w.txen().set_bit();
+        w.rxen().set_bit()
+    });
+    while usart.syncbusy.read().ctrlb().bit_is_set() {}
+
+    usart.ctrla.modify(|_, w| w.enable().set_bit());
+    while usart.syncbusy.read().enable().bit_is_set() {}
+}
+
+/// Disables the USART
+fn disable<S: Sercom>(sercom: &S) {
+    let usart = usart(sercom);
+    usart.ctrla.modify(|_, w| w.enable().clear_bit());
+    while usart.syncbusy.read().enable().bit_is_set() {}
+}
+
+/// Reads a received character, or returns the error it came with
+fn read<S: Sercom>(sercom: &S) -> nb::Result<u8, Error> {
+    let usart = usart(sercom);
+    let status = usart.status.read();
+    if status.bufovf().bit_is_set() {
+        usart.status.write(|w| w.bufovf().set_bit());
+        return Err(nb::Error::Other(Error::Overflow));
+    }
+    if status.ferr().bit_is_set() {
+        usart.data.read();
+        usart.status.write(|w| w.ferr().set_bit());
+        return Err(nb::Error::Other(Error::Frame));
+    }
+    if usart.intflag.read().rxc().bit_is_clear() {
+        return Err(nb::Error::WouldBlock);
+    }
+    Ok(usart.data.read().bits() as u8)
+}
+
+/// Writes a character once the data register is empty
+fn write<S: Sercom>(sercom: &S, word: u8) -> nb::Result<(), Error> {
+    let usart = usart(sercom);
+    if usart.intflag.read().dre().bit_is_clear() {
+        return Err(nb::Error::WouldBlock);
+    }
+    usart.data.write(|w| unsafe { w.bits(word as _) });
+    Ok(())
+}
+
+/// Waits for the end of the last character
+fn flush<S: Sercom>(sercom: &S) -> nb::Result<(), Error> {
+    if usart(sercom).intflag.read().txc().bit_is_clear() {
+        return Err(nb::Error::WouldBlock);
+    }
+    Ok(())
+}
+
+/// The master of a LIN bus. See the [module](self) documentation.
+pub struct LinMaster<S, RX, TX> {
+    sercom: S,
+    rx: RX,
+    tx: TX,
+}
+
+impl<S, PRX, PTX> LinMaster<S, Pad<S, Pad1, PRX>, Pad<S, Pad0, PTX>>
+where
+    S: Sercom,
+    PRX: IsPad<Sercom = S, PadNum = Pad1>,
+    PTX: IsPad<Sercom = S, PadNum = Pad0>,
+{
+    /// Sets up the USART as a LIN master, sending 13 bit breaks followed by
+    /// the sync character after one bit time. `freq` is the frequency of the
+    /// SERCOM core clock.
+    pub fn new(
+        apb_clk_ctrl: &APB_CLK_CTRL,
+        mut sercom: S,
+        rx: Pad<S, Pad1, PRX>,
+        tx: Pad<S, Pad0, PTX>,
+        freq: impl Into<Hertz>,
+        baud: impl Into<Hertz>,
+    ) -> Self {
+        // 2 is the LIN master frame format
+        init(apb_clk_ctrl, &mut sercom, 2, freq.into(), baud.into());
+        Self { sercom, rx, tx }
+    }
+
+    /// Disables the USART and returns the SERCOM and the pads
+    pub fn free(self) -> (S, Pad<S, Pad1, PRX>, Pad<S, Pad0, PTX>) {
+        disable(&self.sercom);
+        (self.sercom, self.rx, self.tx)
+    }
+}
+
+impl<S: Sercom, RX, TX> LinMaster<S, RX, TX> {
+    /// Sets the length of the breaks
+    pub fn set_break_length(&mut self, length: BreakLength) {
+        self.while_disabled(|usart| {
+            usart
+                .ctrlc
+                .modify(|_, w| unsafe { w.brklen().bits(length as u8) })
+        });
+    }
+
+    /// Sets the delay between the breaks and the sync characters
+    pub fn set_header_delay(&mut self, delay: HeaderDelay) {
+        self.while_disabled(|usart| {
+            usart
+                .ctrlc
+                .modify(|_, w| unsafe { w.hdrdly().bits(delay as u8) })
+        });
+    }
+
+    /// Sends the header of a frame with the 6 bit identifier `id`
+    ///
+    /// The response may be written as soon as this returns, it is sent
+    /// after the header.
+    pub fn send_header(&mut self, id: u8) -> nb::Result<(), Error> {
+        let usart = usart(&self.sercom);
+        if usart.intflag.read().dre().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+        // 2 sends the break and the sync character before the next character
+        usart.ctrlb.modify(|_, w| unsafe { w.lincmd().bits(2) });
+        while usart.syncbusy.read().ctrlb().bit_is_set() {}
+        usart
+            .data
+            .write(|w| unsafe { w.bits(protected_id(id) as _) });
+        Ok(())
+    }
+
+    fn while_disabled(&mut self, f: impl FnOnce(&USART)) {
+        let usart = usart(&self.sercom);
+        usart.ctrla.modify(|_, w| w.enable().clear_bit());
+        while usart.syncbusy.read().enable().bit_is_set() {}
+        f(usart);
+        usart.ctrla.modify(|_, w| w.enable().set_bit());
+        while usart.syncbusy.read().enable().bit_is_set() {}
+    }
+}
+
+impl<S: Sercom, RX, TX> serial::Write<u8> for LinMaster<S, RX, TX> {
+    type Error = Error;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Error> {
+        write(&self.sercom, word)
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Error> {
+        flush(&self.sercom)
+    }
+}
+
+impl<S: Sercom, RX, TX> Default<u8> for LinMaster<S, RX, TX> {}
+
+impl<S: Sercom, RX, TX> serial::Read<u8> for LinMaster<S, RX, TX> {
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        read(&self.sercom)
+    }
+}
+
+/// A slave of a LIN bus. See the [module](self) documentation.
+pub struct LinSlave<S, RX, TX> {
+    sercom: S,
+    rx: RX,
+    tx: TX,
+    in_header: bool,
+}
+
+impl<S, PRX, PTX> LinSlave<S, Pad<S, Pad1, PRX>, Pad<S, Pad0, PTX>>
+where
+    S: Sercom,
+    PRX: IsPad<Sercom = S, PadNum = Pad1>,
+    PTX: IsPad<Sercom = S, PadNum = Pad0>,
+{
+    /// Sets up the USART as a LIN slave. `baud` is the nominal rate of the
+    /// bus, which is then adjusted on each sync character. `freq` is the
+    /// frequency of the SERCOM core clock.
+    pub fn new(
+        apb_clk_ctrl: &APB_CLK_CTRL,
+        mut sercom: S,
+        rx: Pad<S, Pad1, PRX>,
+        tx: Pad<S, Pad0, PTX>,
+        freq: impl Into<Hertz>,
+        baud: impl Into<Hertz>,
+    ) -> Self {
+        // 4 is the auto-baud frame format, with break detection
+        init(apb_clk_ctrl, &mut sercom, 4, freq.into(), baud.into());
+        Self {
+            sercom,
+            rx,
+            tx,
+            in_header: false,
+        }
+    }
+
+    /// Disables the USART and returns the SERCOM and the pads
+    pub fn free(self) -> (S, Pad<S, Pad1, PRX>, Pad<S, Pad0, PTX>) {
+        disable(&self.sercom);
+        (self.sercom, self.rx, self.tx)
+    }
+}
+
+impl<S: Sercom, RX, TX> LinSlave<S, RX, TX> {
+    /// Waits for a header, and returns its 6 bit identifier
+    ///
+    /// The characters received outside of a header, such as the responses
+    /// to other slaves, are dropped. The response to the header is then
+    /// sent or received with the [`serial`] traits.
+    pub fn read_header(&mut self) -> nb::Result<u8, Error> {
+        let usart = usart(&self.sercom);
+        if usart.intflag.read().rxbrk().bit_is_set() {
+            usart.intflag.write(|w| w.rxbrk().set_bit());
+            self.in_header = true;
+        }
+        if usart.status.read().isf().bit_is_set() {
+            usart.status.write(|w| w.isf().set_bit());
+            self.in_header = false;
+            return Err(nb::Error::Other(Error::InconsistentSync));
+        }
+        match read(&self.sercom) {
+            Ok(pid) if self.in_header => {
+                self.in_header = false;
+                identifier(pid).ok_or(nb::Error::Other(Error::Parity))
+            }
+            // The break itself ends with a frame error
+            Ok(_) | Err(nb::Error::Other(_)) => Err(nb::Error::WouldBlock),
+            Err(nb::Error::WouldBlock) => Err(nb::Error::WouldBlock),
+        }
+    }
+}
+
+impl<S: Sercom, RX, TX> serial::Write<u8> for LinSlave<S, RX, TX> {
+    type Error = Error;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Error> {
+        write(&self.sercom, word)
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Error> {
+        flush(&self.sercom)
+    }
+}
+
+impl<S: Sercom, RX, TX> Default<u8> for LinSlave<S, RX, TX> {}
+
+impl<S: Sercom, RX, TX> serial::Read<u8> for LinSlave<S, RX, TX> {
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        read(&self.sercom)
+    }
+}
+
+fn usart<S: Sercom>(sercom: &S) -> &USART {
+    sercom.usart_int()
+}
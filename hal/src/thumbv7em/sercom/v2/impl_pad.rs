@@ -78,15 +78,65 @@ macro_rules! pad_table {
                 }
             );
         )+
+
+        /// Value-level version of [`GetPad`], used by
+        /// [`DynPad`](crate::sercom::v2::DynPad)
+        ///
+        /// Returns the alternate function of the pin with the given ID, if it
+        /// can act as the given pad of the given SERCOM.
+        pub(crate) fn pad_mode(id: DynPinId, sercom: usize, padnum: usize) -> Option<DynAlternate> {
+            $(
+                $( #[$id_cfg] )?
+                {
+                    $(
+                        $( #[$sercom_cfg] )?
+                        {
+                            if id == <$PinId as PinId>::DYN
+                                && sercom == <$Sercom as Sercom>::NUM
+                                && padnum == <$PadNum as PadNum>::NUM
+                            {
+                                return Some(DynAlternate::$Cfg);
+                            }
+                        }
+                    )+
+                }
+            )+
+            None
+        }
+
+        /// Value-level version of [`InIoSet`], used by
+        /// [`DynPad`](crate::sercom::v2::DynPad)
+        ///
+        /// Returns the mask of [`IoSet`]s containing the pin with the given ID,
+        /// when it acts as the given pad of the given SERCOM.
+        pub(crate) fn pad_iosets(id: DynPinId, sercom: usize, padnum: usize) -> u8 {
+            $(
+                $( #[$id_cfg] )?
+                {
+                    $(
+                        $( #[$sercom_cfg] )?
+                        {
+                            if id == <$PinId as PinId>::DYN
+                                && sercom == <$Sercom as Sercom>::NUM
+                                && padnum == <$PadNum as PadNum>::NUM
+                            {
+                                return 0 $( | <$IoSet as IoSet>::MASK )+;
+                            }
+                        }
+                    )+
+                }
+            )+
+            0
+        }
     };
 }
 
 pad_table!(
     PA00 {
-        D: (Sercom1, Pad0, IoSet4),
+        D: (Sercom1, Pad0, IoSet4, UndocIoSet2),
     }
     PA01 {
-        D: (Sercom1, Pad1, IoSet4),
+        D: (Sercom1, Pad1, IoSet4, UndocIoSet2),
     }
     PA04 {
         D: (Sercom0, Pad0, IoSet3),
@@ -133,11 +183,11 @@ pad_table!(
         D: (Sercom4, Pad3, IoSet3),
     }
     PA16 {
-        C: (Sercom1, Pad0, IoSet1),
+        C: (Sercom1, Pad0, IoSet1, UndocIoSet1),
         D: (Sercom3, Pad1, IoSet3),
     }
     PA17 {
-        C: (Sercom1, Pad1, IoSet1),
+        C: (Sercom1, Pad1, IoSet1, UndocIoSet1),
         D: (Sercom3, Pad0, IoSet3),
     }
     PA18 {
@@ -253,11 +303,11 @@ pad_table!(
         D: (Sercom7, Pad0, IoSet4),
     }
     PB22 {
-        C: (Sercom1, Pad2, IoSet3),
+        C: (Sercom1, Pad2, IoSet3, UndocIoSet1, UndocIoSet2),
         D: (Sercom5, Pad2, IoSet4),
     }
     PB23 {
-        C: (Sercom1, Pad3, IoSet3),
+        C: (Sercom1, Pad3, IoSet3, UndocIoSet1, UndocIoSet2),
         D: (Sercom5, Pad3, IoSet4),
     }
     #[cfg(feature = "min-samd51n")]
@@ -427,3 +477,124 @@ pad_table!(
         D: (Sercom3, Pad3, IoSet4),
     }
 );
+
+//==============================================================================
+//  Tests
+//==============================================================================
+
+/// Checks the pad table against the IOSET table of the datasheet (section
+/// 6.2.8.1). Each row lists the pins of `Pad0` through `Pad3`, along with the
+/// peripheral function (`C` or `D`) which routes each pin to the `Sercom`,
+/// taken from the I/O multiplexing table (section 6.1). A row compiles only if
+/// every pin is a pad of the `Sercom`, with the right `PadNum` and
+/// [`AlternateConfig`], in the given `IoSet`. The test also checks that
+/// [`pad_mode`] and [`pad_iosets`], used by
+/// [`DynPad`](crate::sercom::v2::DynPad), agree with the type-level table.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_pad<S, N, I, Id, C>()
+    where
+        S: Sercom,
+        N: PadNum,
+        I: IoSet,
+        C: AlternateConfig,
+        Id: GetPad<S, PadNum = N, PinMode = Alternate<C>>,
+        Pin<Id, Alternate<C>>: IsPad<Sercom = S, PadNum = N> + InIoSet<I>,
+    {
+        assert!(pad_mode(Id::DYN, S::NUM, N::NUM) == Some(C::DYN));
+        assert!(pad_iosets(Id::DYN, S::NUM, N::NUM) & I::MASK != 0);
+    }
+
+    macro_rules! ioset_table {
+        (
+            $(
+                $( #[$cfg:meta] )?
+                $Sercom:ident, $IoSet:ident: [
+                    $Pad0:ident: $Cfg0:ident,
+                    $Pad1:ident: $Cfg1:ident,
+                    $Pad2:ident: $Cfg2:ident,
+                    $Pad3:ident: $Cfg3:ident $(,)?
+                ],
+            )+
+        ) => {
+            #[test]
+            fn iosets() {
+                $(
+                    $( #[$cfg] )?
+                    {
+                        assert_pad::<$Sercom, Pad0, $IoSet, $Pad0, $Cfg0>();
+                        assert_pad::<$Sercom, Pad1, $IoSet, $Pad1, $Cfg1>();
+                        assert_pad::<$Sercom, Pad2, $IoSet, $Pad2, $Cfg2>();
+                        assert_pad::<$Sercom, Pad3, $IoSet, $Pad3, $Cfg3>();
+                    }
+                )+
+            }
+        };
+    }
+
+    ioset_table!(
+        Sercom0, IoSet1: [PA08: C, PA09: C, PA10: C, PA11: C],
+        #[cfg(feature = "min-samd51n")]
+        Sercom0, IoSet2: [PB24: C, PB25: C, PC24: C, PC25: C],
+        Sercom0, IoSet3: [PA04: D, PA05: D, PA06: D, PA07: D],
+        #[cfg(feature = "min-samd51n")]
+        Sercom0, IoSet4: [PC17: D, PC16: D, PC18: D, PC19: D],
+        Sercom1, IoSet1: [PA16: C, PA17: C, PA18: C, PA19: C],
+        #[cfg(feature = "min-samd51p")]
+        Sercom1, IoSet2: [PC22: C, PC23: C, PD20: C, PD21: C],
+        #[cfg(feature = "min-samd51n")]
+        Sercom1, IoSet3: [PC27: C, PC28: C, PB22: C, PB23: C],
+        Sercom1, IoSet4: [PA00: D, PA01: D, PA30: D, PA31: D],
+        Sercom1, UndocIoSet1: [PA16: C, PA17: C, PB22: C, PB23: C],
+        Sercom1, UndocIoSet2: [PA00: D, PA01: D, PB22: C, PB23: C],
+        Sercom2, IoSet1: [PA12: C, PA13: C, PA14: C, PA15: C],
+        #[cfg(feature = "min-samd51p")]
+        Sercom2, IoSet2: [PB26: C, PB27: C, PB28: C, PB29: C],
+        Sercom2, IoSet3: [PA09: D, PA08: D, PA10: D, PA11: D],
+        #[cfg(feature = "min-samd51n")]
+        Sercom2, IoSet4: [PB25: D, PB24: D, PC24: D, PC25: D],
+        Sercom3, IoSet1: [PA22: C, PA23: C, PA24: C, PA25: C],
+        #[cfg(feature = "min-samd51n")]
+        Sercom3, IoSet2: [PB20: C, PB21: C, PA20: D, PA21: D],
+        Sercom3, IoSet3: [PA17: D, PA16: D, PA18: D, PA19: D],
+        #[cfg(feature = "min-samd51p")]
+        Sercom3, IoSet4: [PC23: D, PC22: D, PD20: D, PD21: D],
+        #[cfg(feature = "min-samd51j")]
+        Sercom4, IoSet1: [PB12: C, PB13: C, PB14: C, PB15: C],
+        Sercom4, IoSet2: [PB08: D, PB09: D, PB10: D, PB11: D],
+        Sercom4, IoSet3: [PA13: D, PA12: D, PA14: D, PA15: D],
+        #[cfg(feature = "min-samd51p")]
+        Sercom4, IoSet4: [PB27: D, PB26: D, PB28: D, PB29: D],
+        #[cfg(feature = "min-samd51n")]
+        Sercom5, IoSet1: [PB16: C, PB17: C, PB18: C, PB19: C],
+        Sercom5, IoSet2: [PA23: D, PA22: D, PA20: C, PA21: C],
+        Sercom5, IoSet3: [PA23: D, PA22: D, PA24: D, PA25: D],
+        Sercom5, IoSet4: [PA23: D, PA22: D, PB22: D, PB23: D],
+        #[cfg(feature = "min-samd51j")]
+        Sercom5, IoSet5: [PB31: D, PB30: D, PB00: D, PB01: D],
+        #[cfg(feature = "min-samd51j")]
+        Sercom5, IoSet6: [PB02: D, PB03: D, PB00: D, PB01: D],
+        #[cfg(feature = "min-samd51n")]
+        Sercom6, IoSet1: [PC16: C, PC17: C, PC18: C, PC19: C],
+        #[cfg(feature = "min-samd51p")]
+        Sercom6, IoSet2: [PC04: C, PC05: C, PC06: C, PC07: C],
+        #[cfg(feature = "min-samd51p")]
+        Sercom6, IoSet3: [PD09: D, PD08: D, PD10: D, PD11: D],
+        #[cfg(feature = "min-samd51n")]
+        Sercom6, IoSet4: [PC13: D, PC12: D, PC14: D, PC15: D],
+        #[cfg(feature = "min-samd51n")]
+        Sercom6, IoSet5: [PC13: D, PC12: D, PC10: C, PC11: C],
+        #[cfg(feature = "min-samd51n")]
+        Sercom7, IoSet1: [PC12: C, PC13: C, PC14: C, PC15: C],
+        #[cfg(feature = "min-samd51p")]
+        Sercom7, IoSet2: [PD08: C, PD09: C, PD10: C, PD11: C],
+        #[cfg(feature = "min-samd51n")]
+        Sercom7, IoSet3: [PC12: C, PC13: C, PC10: D, PC11: D],
+        #[cfg(feature = "min-samd51n")]
+        Sercom7, IoSet4: [PB21: D, PB20: D, PB18: D, PB19: D],
+        #[cfg(feature = "min-samd51n")]
+        Sercom7, IoSet5: [PB30: C, PB31: C, PA30: C, PA31: C],
+    );
+}
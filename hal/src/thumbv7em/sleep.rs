@@ -0,0 +1,236 @@
+//! # Sleep modes
+//!
+//! In the IDLE and STANDBY sleep modes, execution resumes after the `WFI`
+//! instruction once an interrupt wakes the device. In STANDBY, all clocks are
+//! stopped except those with `RUNSTDBY` or `ONDEMAND` set, and the power
+//! drawn depends heavily on the [`StandbyConfig`]; the defaults keep the whole
+//! RAM powered and the fast wakeup options off, which is what the datasheet
+//! standby current figures assume.
+//!
+//! The SAMx5x also supports three sleep modes in which the core power domain
+//! is switched off: HIBERNATE, BACKUP and OFF. Waking from any of them resets
+//! the device, so the functions which enter them never return. Use
+//! [`wakeup_reason`] early in `main` to find out why the device came back.
+//!
+//! * In HIBERNATE, the RTC, the backup RAM and optionally the main RAM are
+//!   kept powered. Any RTC event can wake the device.
+//! * In BACKUP, only the backup domain (RTC, backup RAM and the battery backup
+//!   power switch) stays powered. The device is woken by an RTC event, such as
+//!   a compare match or a tamper input configured with
+//!   [`Rtc::enable_tamper_wakeup`](crate::rtc::Rtc::enable_tamper_wakeup), or
+//!   by the battery backup power switch.
+//! * In OFF, everything is powered down. Only the reset pin or a power cycle
+//!   wakes the device.
+//!
+//! ```
+//! let mut sleep = Sleep::new(peripherals.PM);
+//! match wakeup_reason(&peripherals.RSTC, &peripherals.RTC) {
+//!     WakeupReason::Rtc { .. } => { /* woken by the RTC */ }
+//!     _ => { /* first boot */ }
+//! }
+//! rtc.enable_tamper_wakeup(TamperInput::In0, TamperLevel::Low, true);
+//! sleep.backup(RamRetention::Retain);
+//! ```
+
+use crate::target_device::{PM, RSTC, RTC};
+use crate::ResetCause;
+
+/// Power policy for a block of RAM while asleep
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RamRetention {
+    /// The whole RAM is retained
+    Retain,
+    /// Only part of the RAM is retained. For the main RAM, the amount is set
+    /// by the PRAM bits of the NVM user row; for the backup RAM it is the
+    /// first 4KB.
+    Partial,
+    /// The RAM is powered off and its contents are lost
+    Off,
+}
+
+/// Which regulators are kept ready in STANDBY to shorten the wakeup time
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FastWakeup {
+    /// Lowest standby current, slowest wakeup
+    Disabled,
+    /// Keep the NVM powered
+    Nvm,
+    /// Keep the main voltage regulator ready
+    MainVreg,
+    /// Keep both the NVM and the main voltage regulator ready. This draws
+    /// the most current in standby.
+    Both,
+}
+
+/// Configuration applied while in STANDBY
+///
+/// The SAMx5x has a single core power domain, so unlike the SAML2x there is
+/// no power domain (PDCFG) setting; the RAM retention policy is the main
+/// lever on the standby current.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct StandbyConfig {
+    /// Which part of the main RAM stays powered. Partial retention keeps the
+    /// amount given by the PRAM bits of the NVM user row; anything outside of
+    /// it is lost.
+    pub ram: RamRetention,
+    /// Whether to trade standby current for a shorter wakeup time
+    pub fast_wakeup: FastWakeup,
+}
+
+impl Default for StandbyConfig {
+    fn default() -> Self {
+        Self {
+            ram: RamRetention::Retain,
+            fast_wakeup: FastWakeup::Disabled,
+        }
+    }
+}
+
+/// The reason the device reset
+#[derive(Copy, Clone, Debug)]
+pub enum WakeupReason {
+    /// The device did not wake from BACKUP or HIBERNATE; the reset cause is
+    /// reported instead.
+    Reset(ResetCause),
+    /// Woken from BACKUP by an RTC event. `tamper` holds a bit for each
+    /// tamper input which fired, as reported by the RTC TAMPID register.
+    Rtc { tamper: u8 },
+    /// Woken from BACKUP because the main supply returned and the battery
+    /// backup power switch moved back to it.
+    PowerSwitch,
+    /// Woken from HIBERNATE
+    Hibernate,
+}
+
+/// Returns the reason for the last reset, distinguishing the various ways
+/// of leaving the deep sleep modes.
+pub fn wakeup_reason(rstc: &RSTC, rtc: &RTC) -> WakeupReason {
+    let cause = ResetCause::from(rstc.rcause.read().bits());
+    if let ResetCause::Backup = cause {
+        let exit = rstc.bkupexit.read();
+        if exit.hib().bit_is_set() {
+            WakeupReason::Hibernate
+        } else if exit.bbps().bit_is_set() {
+            WakeupReason::PowerSwitch
+        } else {
+            // Only the five tamper inputs are reported as a bitfield
+            let tamper = rtc.mode0().tampid.read().bits() as u8 & 0x1f;
+            WakeupReason::Rtc { tamper }
+        }
+    } else {
+        WakeupReason::Reset(cause)
+    }
+}
+
+/// Entry into the sleep modes
+pub struct Sleep {
+    pm: PM,
+}
+
+impl Sleep {
+    /// Takes ownership of the power manager
+    pub fn new(pm: PM) -> Self {
+        Self { pm }
+    }
+
+    /// Releases the power manager
+    pub fn free(self) -> PM {
+        self.pm
+    }
+
+    /// Keeps the I/O pins frozen in their current state while in BACKUP or
+    /// HIBERNATE, rather than letting them float.
+    ///
+    /// After waking, the pins remain frozen until this is disabled again, so
+    /// reconfigure the pins before calling `set_io_retention(false)`.
+    pub fn set_io_retention(&mut self, enable: bool) {
+        self.pm.ctrla.modify(|_, w| w.ioret().bit(enable));
+    }
+
+    /// Sets the configuration used whenever STANDBY is entered
+    pub fn configure_standby(&mut self, config: StandbyConfig) {
+        self.pm.stdbycfg.write(|w| {
+            match config.ram {
+                RamRetention::Retain => w.ramcfg().ret(),
+                RamRetention::Partial => w.ramcfg().partial(),
+                RamRetention::Off => w.ramcfg().off(),
+            };
+            match config.fast_wakeup {
+                FastWakeup::Disabled => w.fastwkup().no(),
+                FastWakeup::Nvm => w.fastwkup().nvm(),
+                FastWakeup::MainVreg => w.fastwkup().mainvreg(),
+                FastWakeup::Both => w.fastwkup().both(),
+            }
+        });
+    }
+
+    /// Enters IDLE mode, returning once an interrupt wakes the device
+    pub fn idle(&mut self) {
+        self.pm.sleepcfg.write(|w| w.sleepmode().idle());
+        while !self.pm.sleepcfg.read().sleepmode().is_idle() {}
+        cortex_m::asm::dsb();
+        cortex_m::asm::wfi();
+    }
+
+    /// Enters STANDBY mode, returning once an interrupt wakes the device
+    pub fn standby(&mut self) {
+        self.pm.sleepcfg.write(|w| w.sleepmode().standby());
+        while !self.pm.sleepcfg.read().sleepmode().is_standby() {}
+        cortex_m::asm::dsb();
+        cortex_m::asm::wfi();
+    }
+
+    /// Enters HIBERNATE mode. Does not return; the device resets when woken.
+    pub fn hibernate(&mut self, ram: RamRetention, backup_ram: RamRetention) -> ! {
+        self.pm.hibcfg.write(|w| {
+            match ram {
+                RamRetention::Retain => w.ramcfg().ret(),
+                RamRetention::Partial => w.ramcfg().partial(),
+                RamRetention::Off => w.ramcfg().off(),
+            };
+            match backup_ram {
+                RamRetention::Retain => w.bramcfg().ret(),
+                RamRetention::Partial => w.bramcfg().partial(),
+                RamRetention::Off => w.bramcfg().off(),
+            }
+        });
+        self.enter(
+            |w| w.sleepmode().hibernate(),
+            |r| r.sleepmode().is_hibernate(),
+        )
+    }
+
+    /// Enters BACKUP mode. Does not return; the device resets when woken.
+    pub fn backup(&mut self, backup_ram: RamRetention) -> ! {
+        self.pm.bkupcfg.write(|w| match backup_ram {
+            RamRetention::Retain => w.bramcfg().ret(),
+            RamRetention::Partial => w.bramcfg().partial(),
+            RamRetention::Off => w.bramcfg().off(),
+        });
+        self.enter(|w| w.sleepmode().backup(), |r| r.sleepmode().is_backup())
+    }
+
+    /// Enters OFF mode. Does not return; only an external reset or a power
+    /// cycle will wake the device.
+    pub fn off(&mut self) -> ! {
+        self.enter(|w| w.sleepmode().off(), |r| r.sleepmode().is_off())
+    }
+
+    fn enter<W, R>(&mut self, mode: W, is_set: R) -> !
+    where
+        W: FnOnce(
+            &mut crate::target_device::pm::sleepcfg::W,
+        ) -> &mut crate::target_device::pm::sleepcfg::W,
+        R: Fn(&crate::target_device::pm::sleepcfg::R) -> bool,
+    {
+        self.pm.sleepcfg.write(mode);
+        // The write must have taken effect before WFI is executed
+        while !is_set(&self.pm.sleepcfg.read()) {}
+
+        cortex_m::interrupt::disable();
+        loop {
+            cortex_m::asm::dsb();
+            cortex_m::asm::wfi();
+        }
+    }
+}
@@ -0,0 +1,613 @@
+//! # Sleepwalking
+//!
+//! In STANDBY sleep, a peripheral keeps working only if its whole clock tree
+//! keeps running: the peripheral must have its `RUNSTDBY` bit set, and so
+//! must the GCLK generator which feeds it and the oscillator which feeds that
+//! generator. Oscillators with `ONDEMAND` set then only run while a
+//! peripheral actually requests their clock, e.g. while a SERCOM receives a
+//! byte, which is where the power savings of sleepwalking come from.
+//!
+//! These bits are spread over the peripherals, GCLK, OSCCTRL and OSC32KCTRL.
+//! [`StandbyClockTree`] sets them together: list the peripherals which must
+//! run in standby, and [`apply`](StandbyClockTree::apply) follows the clock
+//! of each one back to its oscillator, and sets every `RUNSTDBY` bit on the
+//! way. The returned [`StandbyReport`] describes the resulting tree, and
+//! [`StandbyReport::check`] finds the links which would stop in standby.
+//!
+//! The peripheral drivers reset their peripheral when they are created, and
+//! configuring a clock generator again clears its `RUNSTDBY` bit, so apply
+//! the tree once the drivers and clocks are set up.
+//!
+//! ```
+//! let tree = StandbyClockTree::new()
+//!     .peripheral(Peripheral::Sercom(0), false)
+//!     .peripheral(Peripheral::Adc(0), true)
+//!     .oscillator(Oscillator::Dfll, true);
+//! let report = unsafe { tree.apply(&mut clocks, &mut oscctrl, &mut osc32kctrl) }.unwrap();
+//! writeln!(uart, "{}", report).unwrap();
+//! // SERCOM0 (runs in standby) <- GCLK3 (runs in standby) <- XOSC32K (runs in standby)
+//! // ADC0 (runs in standby, on demand) <- GCLK4 (runs in standby) <- DFLL (runs in standby, on demand)
+//! ```
+
+use core::fmt;
+
+use crate::clock::{ClockGenId, ClockId, GenericClockController};
+use crate::target_device::gclk::pchctrl::GEN_A::*;
+use crate::target_device::{adc0, sercom0, tc0, tcc0};
+use crate::target_device::{ADC0, ADC1, DAC, OSC32KCTRL, OSCCTRL};
+use crate::target_device::{SERCOM0, SERCOM1, SERCOM2, SERCOM3, SERCOM4, SERCOM5};
+#[cfg(feature = "min-samd51n")]
+use crate::target_device::{SERCOM6, SERCOM7, TC6, TC7};
+use crate::target_device::{TC0, TC1, TC2, TC3, TCC0, TCC1, TCC2};
+#[cfg(feature = "min-samd51j")]
+use crate::target_device::{TC4, TC5, TCC3, TCC4};
+
+/// Most peripherals a [`StandbyClockTree`] can hold
+pub const MAX_PERIPHERALS: usize = 8;
+
+/// A peripheral which can run in standby
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Peripheral {
+    /// SERCOMn
+    Sercom(u8),
+    /// TCn
+    Tc(u8),
+    /// TCCn
+    Tcc(u8),
+    /// ADCn
+    Adc(u8),
+    /// Channel n of the DAC
+    Dac(u8),
+}
+
+/// An oscillator which can run in standby
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Oscillator {
+    Xosc0,
+    Xosc1,
+    Xosc32k,
+    Dfll,
+    Dpll0,
+    Dpll1,
+}
+
+/// Raw register blocks of a peripheral
+enum Registers {
+    Sercom(&'static sercom0::RegisterBlock),
+    Tc(&'static tc0::RegisterBlock),
+    Tcc(&'static tcc0::RegisterBlock),
+    Adc(&'static adc0::RegisterBlock),
+    Dac(usize),
+}
+
+impl Peripheral {
+    /// Index of the peripheral channel of the GCLK, and the registers of the
+    /// peripheral, if it exists on this chip
+    fn lookup(self) -> Option<(ClockId, Registers)> {
+        use Registers as R;
+        // Safety: only the bits owned by the sleepwalking configuration are
+        // modified, see `StandbyClockTree::apply`
+        unsafe {
+            Some(match self {
+                Peripheral::Sercom(0) => (ClockId::SERCOM0_CORE, R::Sercom(&*SERCOM0::ptr())),
+                Peripheral::Sercom(1) => (ClockId::SERCOM1_CORE, R::Sercom(&*SERCOM1::ptr())),
+                Peripheral::Sercom(2) => (ClockId::SERCOM2_CORE, R::Sercom(&*SERCOM2::ptr())),
+                Peripheral::Sercom(3) => (ClockId::SERCOM3_CORE, R::Sercom(&*SERCOM3::ptr())),
+                Peripheral::Sercom(4) => (ClockId::SERCOM4_CORE, R::Sercom(&*SERCOM4::ptr())),
+                Peripheral::Sercom(5) => (ClockId::SERCOM5_CORE, R::Sercom(&*SERCOM5::ptr())),
+                #[cfg(feature = "min-samd51n")]
+                Peripheral::Sercom(6) => (ClockId::SERCOM6_CORE, R::Sercom(&*SERCOM6::ptr())),
+                #[cfg(feature = "min-samd51n")]
+                Peripheral::Sercom(7) => (ClockId::SERCOM7_CORE, R::Sercom(&*SERCOM7::ptr())),
+                Peripheral::Tc(0) => (ClockId::TC0_TC1, R::Tc(&*TC0::ptr())),
+                Peripheral::Tc(1) => (ClockId::TC0_TC1, R::Tc(&*TC1::ptr())),
+                Peripheral::Tc(2) => (ClockId::TC2_TC3, R::Tc(&*TC2::ptr())),
+                Peripheral::Tc(3) => (ClockId::TC2_TC3, R::Tc(&*TC3::ptr())),
+                #[cfg(feature = "min-samd51j")]
+                Peripheral::Tc(4) => (ClockId::TC4_TC5, R::Tc(&*TC4::ptr())),
+                #[cfg(feature = "min-samd51j")]
+                Peripheral::Tc(5) => (ClockId::TC4_TC5, R::Tc(&*TC5::ptr())),
+                #[cfg(feature = "min-samd51n")]
+                Peripheral::Tc(6) => (ClockId::TC6_TC7, R::Tc(&*TC6::ptr())),
+                #[cfg(feature = "min-samd51n")]
+                Peripheral::Tc(7) => (ClockId::TC6_TC7, R::Tc(&*TC7::ptr())),
+                Peripheral::Tcc(0) => (ClockId::TCC0_TCC1, R::Tcc(&*TCC0::ptr())),
+                Peripheral::Tcc(1) => (ClockId::TCC0_TCC1, R::Tcc(&*TCC1::ptr())),
+                Peripheral::Tcc(2) => (ClockId::TCC2_TCC3, R::Tcc(&*TCC2::ptr())),
+                #[cfg(feature = "min-samd51j")]
+                Peripheral::Tcc(3) => (ClockId::TCC2_TCC3, R::Tcc(&*TCC3::ptr())),
+                #[cfg(feature = "min-samd51j")]
+                Peripheral::Tcc(4) => (ClockId::TCC4, R::Tcc(&*TCC4::ptr())),
+                Peripheral::Adc(0) => (ClockId::ADC0, R::Adc(&*ADC0::ptr())),
+                Peripheral::Adc(1) => (ClockId::ADC1, R::Adc(&*ADC1::ptr())),
+                Peripheral::Dac(channel @ 0..=1) => (ClockId::DAC, R::Dac(channel as usize)),
+                _ => return None,
+            })
+        }
+    }
+}
+
+impl fmt::Display for Peripheral {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Peripheral::Sercom(n) => write!(f, "SERCOM{}", n),
+            Peripheral::Tc(n) => write!(f, "TC{}", n),
+            Peripheral::Tcc(n) => write!(f, "TCC{}", n),
+            Peripheral::Adc(n) => write!(f, "ADC{}", n),
+            Peripheral::Dac(n) => write!(f, "DAC{}", n),
+        }
+    }
+}
+
+impl Registers {
+    /// Returns the `RUNSTDBY` bit, and the `ONDEMAND` bit if the peripheral
+    /// has one
+    fn read(&self) -> (bool, Option<bool>) {
+        match self {
+            Registers::Sercom(sercom) => (sercom.usart_int().ctrla.read().runstdby().bit(), None),
+            Registers::Tc(tc) => {
+                let ctrla = tc.count16().ctrla.read();
+                (ctrla.runstdby().bit(), Some(ctrla.ondemand().bit()))
+            }
+            Registers::Tcc(tcc) => (tcc.ctrla.read().runstdby().bit(), None),
+            Registers::Adc(adc) => {
+                let ctrla = adc.ctrla.read();
+                (ctrla.runstdby().bit(), Some(ctrla.ondemand().bit()))
+            }
+            Registers::Dac(channel) => {
+                let dac = unsafe { &*DAC::ptr() };
+                (dac.dacctrl[*channel].read().runstdby().bit(), None)
+            }
+        }
+    }
+
+    /// Sets the `RUNSTDBY` bit, and the `ONDEMAND` bit if the peripheral has
+    /// one. The bits are enable-protected, so the peripheral is disabled
+    /// while they are written.
+    fn write(&self, on_demand: bool) {
+        match self {
+            Registers::Sercom(sercom) => {
+                let usart = sercom.usart_int();
+                let enabled = usart.ctrla.read().enable().bit();
+                usart.ctrla.modify(|_, w| w.enable().clear_bit());
+                while usart.syncbusy.read().enable().bit_is_set() {}
+                usart
+                    .ctrla
+                    .modify(|_, w| w.runstdby().set_bit().enable().bit(enabled));
+                while usart.syncbusy.read().enable().bit_is_set() {}
+            }
+            Registers::Tc(tc) => {
+                let count = tc.count16();
+                let enabled = count.ctrla.read().enable().bit();
+                count.ctrla.modify(|_, w| w.enable().clear_bit());
+                while count.syncbusy.read().enable().bit_is_set() {}
+                count.ctrla.modify(|_, w| {
+                    w.runstdby().set_bit();
+                    w.ondemand().bit(on_demand);
+                    w.enable().bit(enabled)
+                });
+                while count.syncbusy.read().enable().bit_is_set() {}
+            }
+            Registers::Tcc(tcc) => {
+                let enabled = tcc.ctrla.read().enable().bit();
+                tcc.ctrla.modify(|_, w| w.enable().clear_bit());
+                while tcc.syncbusy.read().enable().bit_is_set() {}
+                tcc.ctrla
+                    .modify(|_, w| w.runstdby().set_bit().enable().bit(enabled));
+                while tcc.syncbusy.read().enable().bit_is_set() {}
+            }
+            Registers::Adc(adc) => {
+                let enabled = adc.ctrla.read().enable().bit();
+                adc.ctrla.modify(|_, w| w.enable().clear_bit());
+                while adc.syncbusy.read().enable().bit_is_set() {}
+                adc.ctrla.modify(|_, w| {
+                    w.runstdby().set_bit();
+                    w.ondemand().bit(on_demand);
+                    w.enable().bit(enabled)
+                });
+                while adc.syncbusy.read().enable().bit_is_set() {}
+            }
+            Registers::Dac(channel) => {
+                let dac = unsafe { &*DAC::ptr() };
+                let enabled = dac.ctrla.read().enable().bit();
+                dac.ctrla.modify(|_, w| w.enable().clear_bit());
+                while dac.syncbusy.read().enable().bit_is_set() {}
+                dac.dacctrl[*channel].modify(|_, w| w.runstdby().set_bit());
+                dac.ctrla.modify(|_, w| w.enable().bit(enabled));
+                while dac.syncbusy.read().enable().bit_is_set() {}
+            }
+        }
+    }
+}
+
+impl Oscillator {
+    /// Returns the `RUNSTDBY` and `ONDEMAND` bits
+    fn read(self, oscctrl: &OSCCTRL, osc32kctrl: &OSC32KCTRL) -> (bool, bool) {
+        match self {
+            Oscillator::Xosc0 | Oscillator::Xosc1 => {
+                let xosc = oscctrl.xoscctrl[self as usize].read();
+                (xosc.runstdby().bit(), xosc.ondemand().bit())
+            }
+            Oscillator::Xosc32k => {
+                let xosc32k = osc32kctrl.xosc32k.read();
+                (xosc32k.runstdby().bit(), xosc32k.ondemand().bit())
+            }
+            Oscillator::Dfll => {
+                let dfll = oscctrl.dfllctrla.read();
+                (dfll.runstdby().bit(), dfll.ondemand().bit())
+            }
+            Oscillator::Dpll0 | Oscillator::Dpll1 => {
+                let index = self as usize - Oscillator::Dpll0 as usize;
+                let dpll = oscctrl.dpll[index].dpllctrla.read();
+                (dpll.runstdby().bit(), dpll.ondemand().bit())
+            }
+        }
+    }
+
+    /// Sets the `RUNSTDBY` bit, and the `ONDEMAND` bit if `on_demand` is
+    /// given
+    fn write(self, on_demand: Option<bool>, oscctrl: &mut OSCCTRL, osc32kctrl: &mut OSC32KCTRL) {
+        let on_demand = on_demand.unwrap_or_else(|| self.read(oscctrl, osc32kctrl).1);
+        match self {
+            Oscillator::Xosc0 | Oscillator::Xosc1 => {
+                oscctrl.xoscctrl[self as usize]
+                    .modify(|_, w| w.runstdby().set_bit().ondemand().bit(on_demand));
+            }
+            Oscillator::Xosc32k => {
+                osc32kctrl
+                    .xosc32k
+                    .modify(|_, w| w.runstdby().set_bit().ondemand().bit(on_demand));
+            }
+            Oscillator::Dfll => {
+                oscctrl
+                    .dfllctrla
+                    .modify(|_, w| w.runstdby().set_bit().ondemand().bit(on_demand));
+                while oscctrl.dfllsync.read().enable().bit_is_set() {}
+            }
+            Oscillator::Dpll0 | Oscillator::Dpll1 => {
+                let dpll = &oscctrl.dpll[self as usize - Oscillator::Dpll0 as usize];
+                dpll.dpllctrla
+                    .modify(|_, w| w.runstdby().set_bit().ondemand().bit(on_demand));
+                while dpll.dpllsyncbusy.read().enable().bit_is_set() {}
+            }
+        }
+    }
+}
+
+impl fmt::Display for Oscillator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Oscillator::Xosc0 => "XOSC0",
+            Oscillator::Xosc1 => "XOSC1",
+            Oscillator::Xosc32k => "XOSC32K",
+            Oscillator::Dfll => "DFLL",
+            Oscillator::Dpll0 => "DPLL0",
+            Oscillator::Dpll1 => "DPLL1",
+        })
+    }
+}
+
+fn generator_id(index: u8) -> ClockGenId {
+    match index {
+        0 => GCLK0,
+        1 => GCLK1,
+        2 => GCLK2,
+        3 => GCLK3,
+        4 => GCLK4,
+        5 => GCLK5,
+        6 => GCLK6,
+        7 => GCLK7,
+        8 => GCLK8,
+        9 => GCLK9,
+        10 => GCLK10,
+        _ => GCLK11,
+    }
+}
+
+/// Why a [`StandbyClockTree`] cannot be applied, or a peripheral would stop
+/// in standby
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StandbyIssue {
+    /// The peripheral does not exist on this chip
+    NoSuchPeripheral(Peripheral),
+    /// The peripheral has no `ONDEMAND` bit. Only TCs and ADCs have one.
+    NoOnDemand(Peripheral),
+    /// The GCLK channel of the peripheral is disabled
+    NoClock(Peripheral),
+    /// The generator feeding the peripheral is disabled
+    GeneratorDisabled(Peripheral, ClockGenId),
+    /// The peripheral does not have `RUNSTDBY` set
+    PeripheralStops(Peripheral),
+    /// The generator feeding the peripheral does not have `RUNSTDBY` set
+    GeneratorStops(Peripheral, ClockGenId),
+    /// The oscillator feeding the peripheral does not have `RUNSTDBY` set
+    OscillatorStops(Peripheral, Oscillator),
+}
+
+/// The last link of a clock path
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Source {
+    Oscillator {
+        oscillator: Oscillator,
+        run_standby: bool,
+        on_demand: bool,
+    },
+    /// The internal ultra low power 32kHz oscillator, which always runs
+    Osculp32k,
+    /// A GCLK_IN pin
+    Pin(u8),
+}
+
+/// A generator in a clock path
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Generator {
+    id: u8,
+    run_standby: bool,
+}
+
+/// The clock path of a peripheral, as read from the hardware
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Path {
+    peripheral: Peripheral,
+    run_standby: bool,
+    on_demand: Option<bool>,
+    generator: Generator,
+    /// GCLK1, when the generator is fed by it
+    gclk1: Option<Generator>,
+    source: Source,
+}
+
+impl Path {
+    fn trace(
+        peripheral: Peripheral,
+        clocks: &GenericClockController,
+        oscctrl: &OSCCTRL,
+        osc32kctrl: &OSC32KCTRL,
+    ) -> Result<Self, StandbyIssue> {
+        let (clock, registers) = peripheral
+            .lookup()
+            .ok_or(StandbyIssue::NoSuchPeripheral(peripheral))?;
+        let (run_standby, on_demand) = registers.read();
+
+        let gclk = clocks.gclk();
+        let pchctrl = gclk.pchctrl[clock as usize].read();
+        if !pchctrl.chen().bit() {
+            return Err(StandbyIssue::NoClock(peripheral));
+        }
+        let mut id = pchctrl.gen().bits();
+        let mut gclk1 = None;
+        let (generator, source) = loop {
+            let genctrl = gclk.genctrl[id as usize].read();
+            if !genctrl.genen().bit() {
+                return Err(StandbyIssue::GeneratorDisabled(
+                    peripheral,
+                    generator_id(id),
+                ));
+            }
+            let generator = Generator {
+                id,
+                run_standby: genctrl.runstdby().bit(),
+            };
+            let oscillator = match genctrl.src().bits() {
+                0 => Oscillator::Xosc0,
+                1 => Oscillator::Xosc1,
+                // GCLK_IN[n] is the input pin of generator n
+                2 => break (generator, Source::Pin(id)),
+                3 if id != 1 => {
+                    gclk1 = Some(generator);
+                    id = 1;
+                    continue;
+                }
+                4 => break (generator, Source::Osculp32k),
+                5 => Oscillator::Xosc32k,
+                6 => Oscillator::Dfll,
+                7 => Oscillator::Dpll0,
+                8 => Oscillator::Dpll1,
+                _ => {
+                    return Err(StandbyIssue::GeneratorDisabled(
+                        peripheral,
+                        generator_id(id),
+                    ))
+                }
+            };
+            let (run_standby, on_demand) = oscillator.read(oscctrl, osc32kctrl);
+            break (
+                generator,
+                Source::Oscillator {
+                    oscillator,
+                    run_standby,
+                    on_demand,
+                },
+            );
+        };
+        // When GCLK1 was followed, `gclk1` holds the generator feeding the
+        // peripheral, and `generator` is GCLK1
+        let (generator, gclk1) = match gclk1 {
+            Some(first) => (first, Some(generator)),
+            None => (generator, None),
+        };
+        Ok(Self {
+            peripheral,
+            run_standby,
+            on_demand,
+            generator,
+            gclk1,
+            source,
+        })
+    }
+
+    fn check(&self) -> Result<(), StandbyIssue> {
+        let peripheral = self.peripheral;
+        if !self.run_standby {
+            return Err(StandbyIssue::PeripheralStops(peripheral));
+        }
+        for generator in core::iter::once(&self.generator).chain(self.gclk1.iter()) {
+            if !generator.run_standby {
+                return Err(StandbyIssue::GeneratorStops(
+                    peripheral,
+                    generator_id(generator.id),
+                ));
+            }
+        }
+        match self.source {
+            Source::Oscillator {
+                oscillator,
+                run_standby: false,
+                ..
+            } => Err(StandbyIssue::OscillatorStops(peripheral, oscillator)),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn standby(f: &mut fmt::Formatter, run_standby: bool, on_demand: Option<bool>) -> fmt::Result {
+    match (run_standby, on_demand) {
+        (false, _) => f.write_str(" (stops in standby)"),
+        (true, Some(true)) => f.write_str(" (runs in standby, on demand)"),
+        (true, _) => f.write_str(" (runs in standby)"),
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.peripheral)?;
+        standby(f, self.run_standby, self.on_demand)?;
+        for generator in core::iter::once(&self.generator).chain(self.gclk1.iter()) {
+            write!(f, " <- GCLK{}", generator.id)?;
+            standby(f, generator.run_standby, None)?;
+        }
+        match self.source {
+            Source::Oscillator {
+                oscillator,
+                run_standby,
+                on_demand,
+            } => {
+                write!(f, " <- {}", oscillator)?;
+                standby(f, run_standby, Some(on_demand))
+            }
+            Source::Osculp32k => f.write_str(" <- OSCULP32K (always runs)"),
+            Source::Pin(n) => write!(f, " <- GCLK_IN{} (external)", n),
+        }
+    }
+}
+
+/// The standby clock tree of a set of peripherals, as read from the hardware
+///
+/// Its `Display` implementation prints the clock path of each peripheral on
+/// its own line.
+#[derive(Clone, Debug)]
+pub struct StandbyReport {
+    paths: [Option<Path>; MAX_PERIPHERALS],
+}
+
+impl StandbyReport {
+    /// Checks that every peripheral of the report, and every generator and
+    /// oscillator feeding them, keeps running in standby
+    pub fn check(&self) -> Result<(), StandbyIssue> {
+        self.paths.iter().flatten().try_for_each(Path::check)
+    }
+}
+
+impl fmt::Display for StandbyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for path in self.paths.iter().flatten() {
+            writeln!(f, "{}", path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builder for the standby clock tree of a set of peripherals
+#[derive(Clone, Debug, Default)]
+pub struct StandbyClockTree {
+    peripherals: [Option<(Peripheral, bool)>; MAX_PERIPHERALS],
+    oscillators: [Option<(Oscillator, bool)>; 6],
+}
+
+impl StandbyClockTree {
+    /// Creates an empty tree
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a peripheral which must keep running in standby. With
+    /// `on_demand`, TCs and ADCs only request their clock while they are
+    /// busy.
+    ///
+    /// Panics if the tree already holds [`MAX_PERIPHERALS`] peripherals.
+    pub fn peripheral(mut self, peripheral: Peripheral, on_demand: bool) -> Self {
+        let slot = self
+            .peripherals
+            .iter_mut()
+            .find(|slot| slot.map_or(true, |(p, _)| p == peripheral))
+            .expect("too many peripherals");
+        *slot = Some((peripheral, on_demand));
+        self
+    }
+
+    /// Sets whether `oscillator` only runs while its clock is requested, when
+    /// it feeds one of the peripherals. By default, the `ONDEMAND` bit of the
+    /// oscillators is left as it is.
+    pub fn oscillator(mut self, oscillator: Oscillator, on_demand: bool) -> Self {
+        self.oscillators[oscillator as usize] = Some((oscillator, on_demand));
+        self
+    }
+
+    /// Reads the current clock path of each peripheral from the hardware
+    pub fn report(
+        &self,
+        clocks: &GenericClockController,
+        oscctrl: &OSCCTRL,
+        osc32kctrl: &OSC32KCTRL,
+    ) -> Result<StandbyReport, StandbyIssue> {
+        let mut paths = [None; MAX_PERIPHERALS];
+        for (path, &(peripheral, _)) in paths.iter_mut().zip(self.peripherals.iter().flatten()) {
+            *path = Some(Path::trace(peripheral, clocks, oscctrl, osc32kctrl)?);
+        }
+        Ok(StandbyReport { paths })
+    }
+
+    /// Sets the `RUNSTDBY` bits of the peripherals, of their generators and
+    /// of their oscillators, and the requested `ONDEMAND` bits. Nothing is
+    /// written if the clock of a peripheral is not set up, or if `ONDEMAND`
+    /// is requested for a peripheral which lacks it.
+    ///
+    /// # Safety
+    ///
+    /// This writes the `CTRLA` registers of peripherals which are owned by
+    /// their drivers, and briefly disables them. It must not race with any
+    /// other access to these peripherals, e.g. from an interrupt handler.
+    pub unsafe fn apply(
+        &self,
+        clocks: &mut GenericClockController,
+        oscctrl: &mut OSCCTRL,
+        osc32kctrl: &mut OSC32KCTRL,
+    ) -> Result<StandbyReport, StandbyIssue> {
+        let report = self.report(clocks, oscctrl, osc32kctrl)?;
+        for &(peripheral, on_demand) in self.peripherals.iter().flatten() {
+            let (_, registers) = peripheral.lookup().unwrap();
+            if on_demand && registers.read().1.is_none() {
+                return Err(StandbyIssue::NoOnDemand(peripheral));
+            }
+        }
+
+        for (path, &(_, on_demand)) in report
+            .paths
+            .iter()
+            .flatten()
+            .zip(self.peripherals.iter().flatten())
+        {
+            let (_, registers) = path.peripheral.lookup().unwrap();
+            registers.write(on_demand);
+            for generator in core::iter::once(&path.generator).chain(path.gclk1.iter()) {
+                clocks.configure_standby(generator_id(generator.id), true);
+            }
+            if let Source::Oscillator { oscillator, .. } = path.source {
+                let on_demand =
+                    self.oscillators[oscillator as usize].map(|(_, on_demand)| on_demand);
+                oscillator.write(on_demand, oscctrl, osc32kctrl);
+            }
+        }
+        self.report(clocks, oscctrl, osc32kctrl)
+    }
+}
@@ -1,16 +1,27 @@
 //! Working with timer counter hardware
 use crate::hal::timer::{CountDown, Periodic};
-use crate::target_device::tc0::COUNT16;
+use crate::target_device::tc0::{
+    count16::ctrla::W as CTRLA_W, count16::evctrl::W as EVCTRL_W, count8::ctrla::W as CTRLA8_W,
+    COUNT16, COUNT8,
+};
 #[allow(unused)]
 use crate::target_device::{MCLK, TC2, TC3};
 use crate::timer_params::TimerParams;
 // Only the G variants are missing these timers
 #[cfg(feature = "min-samd51j")]
 use crate::target_device::{TC4, TC5};
-use crate::timer_traits::InterruptDrivenTimer;
+use crate::timer_traits::{InterruptDrivenTimer, PulseCapture};
 
 use crate::clock;
+use crate::clock_scaling::{rescale, ClockListener};
+use crate::eic::pin::ExternalInterruptID;
+use crate::evsys::{ChId, Connection, Enabled, EventChannel};
+use crate::monotonic::OverflowTick;
+use crate::target_device::EVSYS;
+use crate::tick::Tick;
 use crate::time::{Hertz, Nanoseconds};
+use core::ops::{Add, Sub};
+use typenum::{Add1, Sub1, Unsigned, B1};
 use void::Void;
 
 use cortex_m::asm::delay as cycle_delay;
@@ -20,11 +31,11 @@ use cortex_m::asm::delay as cycle_delay;
 // TC5 + TC6 can be paired to make a 32-bit counter
 
 /// A generic hardware timer counter.
-/// The counters are exposed in 16-bit mode only.
-/// The hardware allows configuring the 8-bit mode
-/// and pairing up some instances to run in 32-bit
-/// mode, but that functionality is not currently
-/// exposed by this hal implementation.
+/// The counters are exposed in 16-bit mode, or in
+/// 8-bit mode with [`TimerCounter::into_8bit`].
+/// The hardware allows pairing up some instances
+/// to run in 32-bit mode, but that functionality
+/// is not currently exposed by this hal implementation.
 /// TimerCounter implements both the `Periodic` and
 /// the `CountDown` embedded_hal timer traits.
 /// Before a hardware timer can be used, it must first
@@ -41,6 +52,23 @@ pub trait Count16 {
     fn count_16(&self) -> &COUNT16;
 }
 
+/// The same as [`Count16`], for the 8-bit mode of the counter
+pub trait Count8: Count16 {
+    fn count_8(&self) -> &COUNT8;
+}
+
+/// Reads the COUNT register of a 16-bit counter.
+///
+/// COUNT is only synchronized on request: the READSYNC command is complete
+/// once CMD reads back as zero, and COUNT can be read once it is no longer
+/// busy synchronizing.
+pub(crate) fn read_count_16(count: &COUNT16) -> u16 {
+    count.ctrlbset.write(|w| w.cmd().readsync());
+    while !count.ctrlbset.read().cmd().is_none() {}
+    while count.syncbusy.read().count().bit_is_set() {}
+    count.count.read().count().bits()
+}
+
 impl<TC> Periodic for TimerCounter<TC> {}
 impl<TC> CountDown for TimerCounter<TC>
 where
@@ -52,7 +80,7 @@ where
     where
         T: Into<Self::Time>,
     {
-        let params = TimerParams::new_us(timeout, self.freq.0);
+        let params = TimerParams::new_us(timeout, self.freq.to_Hz());
         let divider = params.divider;
         let cycles = params.cycles;
         let count = self.tc.count_16();
@@ -112,6 +140,25 @@ where
     }
 }
 
+impl<TC> ClockListener for TimerCounter<TC>
+where
+    TC: Count16,
+{
+    /// Updates the frequency of the timer, and rescales the period of a
+    /// running timer. The prescaler is kept, so the period may saturate when
+    /// the clock speeds up.
+    fn clock_changed(&mut self, old: Hertz, new: Hertz) {
+        self.freq = new;
+        let count = self.tc.count_16();
+        if count.ctrla.read().enable().bit_is_set() {
+            let cycles = count.cc[0].read().cc().bits() as u32;
+            let cycles = rescale(cycles, old, new).max(1).min(0xffff);
+            count.cc[0].write(|w| unsafe { w.cc().bits(cycles as u16) });
+            while count.syncbusy.read().cc0().bit_is_set() {}
+        }
+    }
+}
+
 impl<TC> InterruptDrivenTimer for TimerCounter<TC>
 where
     TC: Count16,
@@ -133,6 +180,690 @@ where
     }
 }
 
+impl<TC> TimerCounter<TC>
+where
+    TC: Count16,
+{
+    /// Stops the counter, keeping its value, until [`resume`](Self::resume)
+    pub fn pause(&mut self) {
+        let count = self.tc.count_16();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.syncbusy.read().enable().bit_is_set() {}
+    }
+
+    /// Restarts the counter from the value at which it was paused
+    pub fn resume(&mut self) {
+        let count = self.tc.count_16();
+        count.ctrla.modify(|_, w| w.enable().set_bit());
+        while count.syncbusy.read().enable().bit_is_set() {}
+    }
+
+    /// Returns true while the counter is paused, or not started
+    pub fn is_paused(&self) -> bool {
+        self.tc.count_16().ctrla.read().enable().bit_is_clear()
+    }
+
+    /// Returns the current value of the counter
+    pub fn count(&self) -> u16 {
+        read_count_16(self.tc.count_16())
+    }
+
+    /// Sets the value of the counter, which keeps counting from there if it
+    /// is running
+    pub fn set_count(&mut self, value: u16) {
+        let count = self.tc.count_16();
+        count.count.write(|w| unsafe { w.count().bits(value) });
+        while count.syncbusy.read().count().bit_is_set() {}
+    }
+
+    /// Reconfigures the timer as a free-running counter incrementing at `HZ`,
+    /// for use as a [`Tick`] source which keeps running in standby.
+    ///
+    /// Panics if `HZ` cannot be reached by dividing the timer clock by one of
+    /// the available prescalers (1, 2, 4, 8, 16, 64, 256 or 1024).
+    pub fn into_tick<const HZ: u32>(self) -> TimerTick<TC, HZ> {
+        let divider = self.freq.to_Hz() / HZ;
+        assert!(
+            divider * HZ == self.freq.to_Hz(),
+            "timer clock is not a multiple of the tick rate"
+        );
+
+        let count = self.tc.count_16();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.syncbusy.read().enable().bit_is_set() {}
+        count.ctrla.write(|w| w.swrst().set_bit());
+        // the SVD erroneously marks swrst as write-only, so we
+        // need to manually read the bit here
+        while count.ctrla.read().bits() & 1 != 0 {}
+
+        // Normal frequency: count from zero to 0xffff and wrap
+        count.wave.modify(|_, w| w.wavegen().nfrq());
+        count.ctrla.modify(|_, w| {
+            prescale(w, divider);
+            w.enable().set_bit();
+            w.runstdby().set_bit()
+        });
+        while count.syncbusy.read().enable().bit_is_set() {}
+        TimerTick {
+            freq: self.freq,
+            tc: self.tc,
+        }
+    }
+}
+
+fn prescale(w: &mut CTRLA_W, divider: u32) {
+    match divider {
+        1 => w.prescaler().div1(),
+        2 => w.prescaler().div2(),
+        4 => w.prescaler().div4(),
+        8 => w.prescaler().div8(),
+        16 => w.prescaler().div16(),
+        64 => w.prescaler().div64(),
+        256 => w.prescaler().div256(),
+        1024 => w.prescaler().div1024(),
+        _ => panic!("no timer prescaler divides by {}", divider),
+    };
+}
+
+/// A timer counter running as a free-running 16-bit counter at `HZ`, for use
+/// as a [`Tick`] source. Created by [`TimerCounter::into_tick`].
+pub struct TimerTick<TC, const HZ: u32> {
+    freq: Hertz,
+    tc: TC,
+}
+
+impl<TC, const HZ: u32> TimerTick<TC, HZ>
+where
+    TC: Count16,
+{
+    /// Stops the counter and returns the timer
+    pub fn free(self) -> TimerCounter<TC> {
+        self.tc
+            .count_16()
+            .ctrla
+            .modify(|_, w| w.enable().clear_bit());
+        TimerCounter {
+            freq: self.freq,
+            tc: self.tc,
+        }
+    }
+}
+
+impl<TC, const HZ: u32> Tick for TimerTick<TC, HZ>
+where
+    TC: Count16,
+{
+    const HZ: u32 = HZ;
+    const MAX: u32 = 0xffff;
+
+    fn ticks(&self) -> u32 {
+        read_count_16(self.tc.count_16()) as u32
+    }
+}
+
+impl<TC, const HZ: u32> OverflowTick for TimerTick<TC, HZ>
+where
+    TC: Count16,
+{
+    fn enable_overflow_interrupt(&mut self) {
+        self.tc.count_16().intenset.write(|w| w.ovf().set_bit());
+    }
+
+    fn disable_overflow_interrupt(&mut self) {
+        self.tc.count_16().intenclr.write(|w| w.ovf().set_bit());
+    }
+
+    fn overflow_pending(&self) -> bool {
+        self.tc.count_16().intflag.read().ovf().bit_is_set()
+    }
+
+    fn clear_overflow(&mut self) {
+        // Writing a 1 clears the flag
+        self.tc.count_16().intflag.write(|w| w.ovf().set_bit());
+    }
+}
+
+impl<TC> TimerCounter<TC>
+where
+    TC: Count8,
+{
+    /// Reconfigures the timer as an 8-bit counter, whose period is set by its
+    /// `PER` register.
+    ///
+    /// The 8-bit mode gives finer control of short periods than the 16-bit
+    /// mode, where the period is a compare channel, and its period can be
+    /// changed without a glitch. This makes it a cheap source of fast,
+    /// low-jitter periodic interrupts when all the TCCs are busy generating
+    /// PWM.
+    pub fn into_8bit(self) -> TimerCounter8<TC> {
+        let count = self.tc.count_8();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.syncbusy.read().enable().bit_is_set() {}
+        count.ctrla.write(|w| w.swrst().set_bit());
+        // the SVD erroneously marks swrst as write-only, so we
+        // need to manually read the bit here
+        while count.ctrla.read().bits() & 1 != 0 {}
+        count.ctrla.modify(|_, w| w.mode().count8());
+        // Normal frequency: count from zero to PER and wrap
+        count.wave.write(|w| w.wavegen().nfrq());
+        TimerCounter8 {
+            freq: self.freq,
+            tc: self.tc,
+        }
+    }
+}
+
+/// A timer counter in 8-bit mode. Created by [`TimerCounter::into_8bit`].
+///
+/// It implements the same `Periodic`, `CountDown` and
+/// [`InterruptDrivenTimer`] traits as the [`TimerCounter`], for periods of up
+/// to 256 ticks of the divided timer clock.
+pub struct TimerCounter8<TC> {
+    freq: Hertz,
+    tc: TC,
+}
+
+impl<TC> TimerCounter8<TC>
+where
+    TC: Count8,
+{
+    /// Stops the counter and returns the timer, back in 16-bit mode
+    pub fn free(self) -> TimerCounter<TC> {
+        let count = self.tc.count_8();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.syncbusy.read().enable().bit_is_set() {}
+        count.ctrla.write(|w| w.swrst().set_bit());
+        while count.ctrla.read().bits() & 1 != 0 {}
+        TimerCounter {
+            freq: self.freq,
+            tc: self.tc,
+        }
+    }
+
+    /// Starts counting from zero, overflowing every `period + 1` ticks of the
+    /// timer clock divided by `divider`, one of 1, 2, 4, 8, 16, 64, 256 or
+    /// 1024.
+    pub fn start_ticks(&mut self, divider: u32, period: u8) {
+        let count = self.tc.count_8();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.syncbusy.read().enable().bit_is_set() {}
+        count.count.write(|w| unsafe { w.count().bits(0) });
+        while count.syncbusy.read().count().bit_is_set() {}
+        count.per.write(|w| unsafe { w.per().bits(period) });
+        while count.syncbusy.read().per().bit_is_set() {}
+        count.intflag.write(|w| w.ovf().set_bit());
+        count.ctrla.modify(|_, w| {
+            prescale8(w, divider);
+            w.enable().set_bit();
+            w.runstdby().set_bit()
+        });
+        while count.syncbusy.read().enable().bit_is_set() {}
+    }
+
+    /// Changes the period, in ticks minus one, from the next overflow on.
+    /// The current cycle is not disturbed, so the period can be changed on
+    /// the fly without a glitch.
+    pub fn set_period(&mut self, period: u8) {
+        self.tc
+            .count_8()
+            .perbuf
+            .write(|w| unsafe { w.perbuf().bits(period) });
+    }
+
+    /// Stops the counter, keeping its value, until [`resume`](Self::resume)
+    pub fn pause(&mut self) {
+        let count = self.tc.count_8();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.syncbusy.read().enable().bit_is_set() {}
+    }
+
+    /// Restarts the counter from the value at which it was paused
+    pub fn resume(&mut self) {
+        let count = self.tc.count_8();
+        count.ctrla.modify(|_, w| w.enable().set_bit());
+        while count.syncbusy.read().enable().bit_is_set() {}
+    }
+
+    /// Returns true while the counter is paused, or not started
+    pub fn is_paused(&self) -> bool {
+        self.tc.count_8().ctrla.read().enable().bit_is_clear()
+    }
+
+    /// Sets the value of the counter, which keeps counting from there if it
+    /// is running
+    pub fn set_count(&mut self, value: u8) {
+        let count = self.tc.count_8();
+        count.count.write(|w| unsafe { w.count().bits(value) });
+        while count.syncbusy.read().count().bit_is_set() {}
+    }
+
+    /// Returns the current value of the counter
+    pub fn count(&self) -> u8 {
+        let count = self.tc.count_8();
+        // Request a synchronized read of the COUNT register, see
+        // `read_count_16`
+        count.ctrlbset.write(|w| w.cmd().readsync());
+        while !count.ctrlbset.read().cmd().is_none() {}
+        while count.syncbusy.read().count().bit_is_set() {}
+        count.count.read().count().bits()
+    }
+}
+
+impl<TC> Periodic for TimerCounter8<TC> {}
+impl<TC> CountDown for TimerCounter8<TC>
+where
+    TC: Count8,
+{
+    type Time = Nanoseconds;
+
+    fn start<T>(&mut self, timeout: T)
+    where
+        T: Into<Self::Time>,
+    {
+        let params = TimerParams::new_us_8bit(timeout, self.freq.to_Hz());
+        let period = params.cycles.max(1) - 1;
+        self.start_ticks(params.divider as u32, period as u8);
+    }
+
+    fn wait(&mut self) -> nb::Result<(), Void> {
+        let count = self.tc.count_8();
+        if count.intflag.read().ovf().bit_is_set() {
+            // Writing a 1 clears the flag
+            count.intflag.write(|w| w.ovf().set_bit());
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl<TC> InterruptDrivenTimer for TimerCounter8<TC>
+where
+    TC: Count8,
+{
+    fn enable_interrupt(&mut self) {
+        self.tc.count_8().intenset.write(|w| w.ovf().set_bit());
+    }
+
+    fn disable_interrupt(&mut self) {
+        self.tc.count_8().intenclr.write(|w| w.ovf().set_bit());
+    }
+}
+
+fn prescale8(w: &mut CTRLA8_W, divider: u32) {
+    match divider {
+        1 => w.prescaler().div1(),
+        2 => w.prescaler().div2(),
+        4 => w.prescaler().div4(),
+        8 => w.prescaler().div8(),
+        16 => w.prescaler().div16(),
+        64 => w.prescaler().div64(),
+        256 => w.prescaler().div256(),
+        1024 => w.prescaler().div1024(),
+        _ => panic!("no timer prescaler divides by {}", divider),
+    };
+}
+
+/// Event system generator number of external interrupt line 0. The other
+/// lines follow in order.
+const EVSYS_GEN_EIC_EXTINT_0: u8 = 0x12;
+
+/// A timer counter which can count events from the event system
+pub trait EventUser: Count16 {
+    /// Event system user number of the timer counter event input
+    const EVSYS_USER: u8;
+}
+
+impl EventUser for TC2 {
+    const EVSYS_USER: u8 = 46;
+}
+
+impl EventUser for TC3 {
+    const EVSYS_USER: u8 = 47;
+}
+
+#[cfg(feature = "min-samd51j")]
+impl EventUser for TC4 {
+    const EVSYS_USER: u8 = 48;
+}
+
+#[cfg(feature = "min-samd51j")]
+impl EventUser for TC5 {
+    const EVSYS_USER: u8 = 49;
+}
+
+impl<TC> TimerCounter<TC>
+where
+    TC: EventUser,
+{
+    /// Reconfigures the timer to count the events of external interrupt
+    /// `line`, routed through the event system channel `channel`.
+    ///
+    /// The external interrupt must be set to sense rising edges and to emit
+    /// events, see `enable_event`. The event path is asynchronous and the
+    /// counter runs in standby, so pulses are counted while the CPU sleeps,
+    /// as long as the timer clock also runs in standby.
+    pub fn into_pulse_counter(
+        self,
+        evsys: &mut EVSYS,
+        mclk: &mut MCLK,
+        line: ExternalInterruptID,
+        channel: u8,
+    ) -> PulseCounter<TC> {
+        route_extint(evsys, mclk, line, channel, TC::EVSYS_USER);
+        self.count_events(channel)
+    }
+
+    /// Reconfigures the timer to capture the period and the width of the
+    /// pulses of external interrupt `line`, routed through the event system
+    /// channel `channel`. See [`PulseCapture`].
+    ///
+    /// The timer counts the timer clock divided by `divider`, one of the
+    /// prescalers (1, 2, 4, 8, 16, 64, 256 or 1024), and the longest period
+    /// it captures is 65535 of these ticks. The external interrupt must be
+    /// set to sense a high level and to emit events, see `enable_event`.
+    /// With `invert`, the pulses are the low levels of the input instead,
+    /// e.g. the marks of an IR receiver.
+    pub fn into_pulse_capture(
+        self,
+        evsys: &mut EVSYS,
+        mclk: &mut MCLK,
+        line: ExternalInterruptID,
+        channel: u8,
+        divider: u32,
+        invert: bool,
+    ) -> CaptureCounter<TC> {
+        route_extint(evsys, mclk, line, channel, TC::EVSYS_USER);
+
+        let count = self.tc.count_16();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.syncbusy.read().enable().bit_is_set() {}
+        count.ctrla.write(|w| w.swrst().set_bit());
+        // the SVD erroneously marks swrst as write-only, so we
+        // need to manually read the bit here
+        while count.ctrla.read().bits() & 1 != 0 {}
+
+        // The start of a pulse captures the period in CC0 and restarts the
+        // counter, its end captures the width in CC1
+        count
+            .evctrl
+            .write(|w| w.evact().ppw().tcinv().bit(invert).tcei().set_bit());
+        count.wave.modify(|_, w| w.wavegen().nfrq());
+        count.ctrla.modify(|_, w| {
+            prescale(w, divider);
+            w.capten0().set_bit();
+            w.capten1().set_bit();
+            w.enable().set_bit();
+            w.runstdby().set_bit()
+        });
+        while count.syncbusy.read().enable().bit_is_set() {}
+        CaptureCounter {
+            freq: self.freq,
+            tc: self.tc,
+            channel: channel as usize,
+            divider,
+        }
+    }
+
+    /// Reconfigures the timer to count the events of a type-level event
+    /// system channel, whose count of users is incremented. See the
+    /// [`evsys`](crate::evsys) module.
+    pub fn into_event_counter<C, N>(
+        self,
+        channel: Enabled<EventChannel<C>, N>,
+    ) -> (
+        PulseCounter<TC, Connection<C>>,
+        Enabled<EventChannel<C>, Add1<N>>,
+    )
+    where
+        C: ChId,
+        N: Unsigned + Add<B1>,
+        Add1<N>: Unsigned,
+    {
+        let (connection, channel) = channel.connect(TC::EVSYS_USER);
+        (self.count_events(connection), channel)
+    }
+
+    fn count_events<E>(self, event: E) -> PulseCounter<TC, E> {
+        let count = self.tc.count_16();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.syncbusy.read().enable().bit_is_set() {}
+        count.ctrla.write(|w| w.swrst().set_bit());
+        // the SVD erroneously marks swrst as write-only, so we
+        // need to manually read the bit here
+        while count.ctrla.read().bits() & 1 != 0 {}
+
+        count.evctrl.write(|w| w.evact().count().tcei().set_bit());
+        count.wave.modify(|_, w| w.wavegen().nfrq());
+        count.ctrla.modify(|_, w| {
+            w.enable().set_bit();
+            w.runstdby().set_bit()
+        });
+        while count.syncbusy.read().enable().bit_is_set() {}
+        PulseCounter {
+            freq: self.freq,
+            tc: self.tc,
+            event,
+            last: 0,
+        }
+    }
+
+    /// Enables or disables the overflow event output, emitted once per
+    /// period
+    pub fn set_overflow_event(&mut self, enable: bool) {
+        self.modify_evctrl(|w| w.ovfeo().bit(enable));
+    }
+
+    /// Enables or disables restarting the count on each incoming event,
+    /// e.g. to align the periods to an external signal
+    pub fn set_retrigger_event(&mut self, enable: bool) {
+        self.modify_evctrl(|w| w.evact().retrigger().tcei().bit(enable));
+    }
+
+    fn modify_evctrl<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut EVCTRL_W) -> &mut EVCTRL_W,
+    {
+        // EVCTRL is enable-protected
+        let count = self.tc.count_16();
+        let enabled = count.ctrla.read().enable().bit_is_set();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.syncbusy.read().enable().bit_is_set() {}
+        count.evctrl.modify(|_, w| f(w));
+        count.ctrla.modify(|_, w| w.enable().bit(enabled));
+        while count.syncbusy.read().enable().bit_is_set() {}
+    }
+}
+
+/// A timer counter counting pulses on a pin, e.g. from a flow meter or an
+/// anemometer. Created by [`TimerCounter::into_pulse_counter`] or
+/// [`TimerCounter::into_event_counter`].
+///
+/// `E` is the event system channel the timer counts: its number when created
+/// by `into_pulse_counter`, or its [`Connection`] when created by
+/// `into_event_counter`.
+pub struct PulseCounter<TC, E = u8> {
+    freq: Hertz,
+    tc: TC,
+    event: E,
+    last: u16,
+}
+
+impl<TC, E> PulseCounter<TC, E>
+where
+    TC: EventUser,
+{
+    /// Returns the raw value of the 16-bit counter
+    pub fn count(&self) -> u16 {
+        read_count_16(self.tc.count_16())
+    }
+
+    /// Returns the number of pulses since the previous call, and starts
+    /// counting again from there.
+    ///
+    /// The counter itself keeps running, so no pulse is lost between the
+    /// read and the clear. The result wraps after 65535 pulses; count the
+    /// overflow interrupts to measure longer intervals.
+    pub fn take(&mut self) -> u16 {
+        let now = self.count();
+        let pulses = now.wrapping_sub(self.last);
+        self.last = now;
+        pulses
+    }
+
+    /// Enables the overflow interrupt, fired every 65536 pulses
+    pub fn enable_overflow_interrupt(&mut self) {
+        self.tc.count_16().intenset.write(|w| w.ovf().set_bit());
+    }
+
+    /// Disables the overflow interrupt
+    pub fn disable_overflow_interrupt(&mut self) {
+        self.tc.count_16().intenclr.write(|w| w.ovf().set_bit());
+    }
+
+    /// Checks and clears the overflow flag
+    pub fn overflowed(&mut self) -> bool {
+        let intflag = &self.tc.count_16().intflag;
+        let overflowed = intflag.read().ovf().bit_is_set();
+        if overflowed {
+            // Writing a 1 clears the flag
+            intflag.write(|w| w.ovf().set_bit());
+        }
+        overflowed
+    }
+
+    fn stop(&self) {
+        let count = self.tc.count_16();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.syncbusy.read().enable().bit_is_set() {}
+        count.evctrl.reset();
+    }
+}
+
+impl<TC> PulseCounter<TC>
+where
+    TC: EventUser,
+{
+    /// Stops counting, disconnects the event system channel and returns the
+    /// timer
+    pub fn free(self, evsys: &mut EVSYS) -> TimerCounter<TC> {
+        self.stop();
+        evsys.user[TC::EVSYS_USER as usize].reset();
+        evsys.channel[self.event as usize].channel.reset();
+        TimerCounter {
+            freq: self.freq,
+            tc: self.tc,
+        }
+    }
+}
+
+impl<TC, C> PulseCounter<TC, Connection<C>>
+where
+    TC: EventUser,
+    C: ChId,
+{
+    /// Stops counting, disconnects the timer from the type-level channel it
+    /// was created with, and returns both
+    pub fn free_event_counter<N>(
+        self,
+        channel: Enabled<EventChannel<C>, N>,
+    ) -> (TimerCounter<TC>, Enabled<EventChannel<C>, Sub1<N>>)
+    where
+        N: Unsigned + Sub<B1>,
+        Sub1<N>: Unsigned,
+    {
+        self.stop();
+        let channel = self.event.disconnect(channel);
+        let timer = TimerCounter {
+            freq: self.freq,
+            tc: self.tc,
+        };
+        (timer, channel)
+    }
+}
+
+/// Routes the events of external interrupt `line` to `user` through the
+/// event system channel `channel`, on the asynchronous path
+fn route_extint(
+    evsys: &mut EVSYS,
+    mclk: &mut MCLK,
+    line: ExternalInterruptID,
+    channel: u8,
+    user: u8,
+) {
+    let channel = channel as usize;
+    assert!(channel < evsys.channel.len());
+
+    mclk.apbbmask.modify(|_, w| w.evsys_().set_bit());
+    evsys.channel[channel].channel.write(|w| {
+        unsafe { w.evgen().bits(EVSYS_GEN_EIC_EXTINT_0 + line as u8) };
+        w.path().asynchronous().edgsel().no_evt_output()
+    });
+    // The user registers hold the channel number plus one, zero
+    // disconnects the user
+    evsys.user[user as usize].write(|w| unsafe { w.channel().bits(channel as u8 + 1) });
+}
+
+/// A timer counter capturing the period and the width of the pulses of a
+/// pin. Created by [`TimerCounter::into_pulse_capture`].
+pub struct CaptureCounter<TC> {
+    freq: Hertz,
+    tc: TC,
+    channel: usize,
+    divider: u32,
+}
+
+impl<TC> CaptureCounter<TC>
+where
+    TC: EventUser,
+{
+    /// Stops capturing, disconnects the event system channel and returns
+    /// the timer
+    pub fn free(self, evsys: &mut EVSYS) -> TimerCounter<TC> {
+        let count = self.tc.count_16();
+        count.ctrla.modify(|_, w| w.enable().clear_bit());
+        while count.syncbusy.read().enable().bit_is_set() {}
+        count.evctrl.reset();
+        evsys.user[TC::EVSYS_USER as usize].reset();
+        evsys.channel[self.channel].channel.reset();
+        TimerCounter {
+            freq: self.freq,
+            tc: self.tc,
+        }
+    }
+
+    /// Reads capture channel `cc` if it captured a value since the last
+    /// read, in microseconds
+    fn read_capture(&mut self, cc: usize) -> Option<u32> {
+        let count = self.tc.count_16();
+        let flags = count.intflag.read();
+        let captured = if cc == 0 {
+            flags.mc0().bit_is_set()
+        } else {
+            flags.mc1().bit_is_set()
+        };
+        if !captured {
+            return None;
+        }
+        // Reading the captured value clears its flag
+        let ticks = count.cc[cc].read().cc().bits() as u64;
+        Some((ticks * self.divider as u64 * 1_000_000 / self.freq.to_Hz() as u64) as u32)
+    }
+}
+
+impl<TC> PulseCapture for CaptureCounter<TC>
+where
+    TC: EventUser,
+{
+    fn read_width(&mut self) -> Option<u32> {
+        self.read_capture(1)
+    }
+
+    fn read_period(&mut self) -> Option<u32> {
+        self.read_capture(0)
+    }
+}
+
 macro_rules! tc {
     ($($TYPE:ident: ($TC:ident, $mclk:ident, $clock:ident, $apmask:ident),)+) => {
         $(
@@ -144,6 +875,12 @@ impl Count16 for $TC {
     }
 }
 
+impl Count8 for $TC {
+    fn count_8(&self) -> &COUNT8 {
+        self.count8()
+    }
+}
+
 impl TimerCounter<$TC>
 {
     /// Configure this timer counter instance.
@@ -9,11 +9,13 @@ use super::Descriptors;
 use crate::calibration::{usb_transn_cal, usb_transp_cal, usb_trim_cal};
 use crate::clock;
 use crate::gpio::v2::{AlternateH, AnyPin, Pin, PA24, PA25};
+use crate::nvmctrl::Qos;
 use crate::target_device;
 use crate::target_device::usb::DEVICE;
 use crate::target_device::{MCLK, USB};
 use crate::usb::devicedesc::DeviceDescBank;
-use core::cell::{Ref, RefCell, RefMut};
+use crate::usb_power::UsbEvents;
+use core::cell::{Cell, Ref, RefCell, RefMut};
 use core::marker::PhantomData;
 use core::mem;
 use cortex_m::interrupt::{free as disable_interrupts, Mutex};
@@ -204,6 +206,8 @@ struct Inner {
     _dp_pad: Pin<PA25, AlternateH>,
     endpoints: RefCell<AllEndpoints>,
     buffers: RefCell<BufferAllocator>,
+    /// Set when a reset is reported to `usb-device`, until `take_events`
+    reset: Cell<bool>,
 }
 
 pub struct UsbBus {
@@ -532,6 +536,7 @@ impl UsbBus {
         _usb: USB,
     ) -> Self {
         dbgprint!("******** UsbBus::new\n");
+        crate::usb_composite::reset_endpoints();
         mclk.ahbmask.modify(|_, w| w.usb_().set_bit());
         mclk.apbbmask.modify(|_, w| w.usb_().set_bit());
 
@@ -543,12 +548,35 @@ impl UsbBus {
             desc,
             buffers: RefCell::new(BufferAllocator::new()),
             endpoints: RefCell::new(AllEndpoints::new()),
+            reset: Cell::new(false),
         };
 
         Self {
             inner: Mutex::new(RefCell::new(inner)),
         }
     }
+
+    /// Returns the bus events since the last call
+    ///
+    /// The suspend and wakeup events are not reported to `usb-device`, see
+    /// the [`usb_power`](crate::usb_power) module. Their flags are polled,
+    /// so this should be called along with `UsbDevice::poll`.
+    pub fn take_events(&self) -> UsbEvents {
+        disable_interrupts(|cs| self.inner.borrow(cs).borrow().take_events())
+    }
+
+    /// Sets the quality of service of the USB on the AHB, for the accesses to
+    /// the endpoint descriptors (`config`) and to the endpoint buffers
+    /// (`data`). See [`Qos`].
+    pub fn set_qos(&self, config: Qos, data: Qos) {
+        disable_interrupts(|cs| {
+            let inner = self.inner.borrow(cs).borrow();
+            inner.usb().qosctrl.write(|w| unsafe {
+                w.cqos().bits(config.into());
+                w.dqos().bits(data.into())
+            });
+        });
+    }
 }
 
 impl Inner {
@@ -743,6 +771,22 @@ impl Inner {
         dbgprint!("UsbBus::resume\n");
     }
 
+    fn take_events(&self) -> UsbEvents {
+        let usb = self.usb();
+        let flags = usb.intflag.read();
+        // Only clear the flags read, so that none is lost
+        usb.intflag.write(|w| {
+            w.suspend().bit(flags.suspend().bit());
+            w.wakeup().bit(flags.wakeup().bit());
+            w.eorsm().bit(flags.eorsm().bit())
+        });
+        UsbEvents {
+            reset: self.reset.replace(false),
+            suspend: flags.suspend().bit(),
+            wakeup: flags.wakeup().bit() || flags.eorsm().bit(),
+        }
+    }
+
     fn alloc_ep(
         &mut self,
         dir: UsbDirection,
@@ -795,6 +839,7 @@ impl Inner {
         )?;
 
         dbgprint!("alloc_ep -> {:?}\n", addr);
+        crate::usb_composite::record_endpoint(addr, allocated_size);
 
         Ok(addr)
     }
@@ -811,6 +856,7 @@ impl Inner {
         if intflags.eorst().bit() {
             // end of reset interrupt
             self.usb().intflag.write(|w| w.eorst().set_bit());
+            self.reset.set(true);
             dbgprint!("PollResult::Reset\n");
             return PollResult::Reset;
         }
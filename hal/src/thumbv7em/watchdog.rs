@@ -20,6 +20,11 @@ pub enum WatchdogTimeout {
     Cycles16K,
 }
 
+/// The watchdog is in always-on mode, and can't be disabled or reconfigured
+/// until the next reset
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct AlwaysOn;
+
 pub struct Watchdog {
     wdt: WDT,
 }
@@ -28,23 +33,71 @@ impl Watchdog {
     pub fn new(wdt: WDT) -> Self {
         Self { wdt }
     }
+
+    /// Returns true if the watchdog is in always-on mode. The mode is set by
+    /// software, or at reset by the WDT Always-On fuse of the NVM user row.
+    pub fn is_always_on(&self) -> bool {
+        self.wdt.ctrla.read().alwayson().bit_is_set()
+    }
+
+    /// Returns true if the watchdog is running
+    pub fn is_enabled(&self) -> bool {
+        let ctrl = self.wdt.ctrla.read();
+        ctrl.enable().bit_is_set() || ctrl.alwayson().bit_is_set()
+    }
+
+    /// Changes the timeout period. A running watchdog is disabled while its
+    /// configuration is written, which also restarts its period.
+    pub fn set_period(&mut self, period: WatchdogTimeout) -> Result<(), AlwaysOn> {
+        if self.is_always_on() {
+            return Err(AlwaysOn);
+        }
+        let enabled = self.is_enabled();
+        // CONFIG is enable-protected
+        if enabled {
+            self.set_enabled(false);
+        }
+        self.wdt
+            .config
+            .write(|w| unsafe { w.per().bits(period as u8) });
+        if enabled {
+            self.set_enabled(true);
+        }
+        Ok(())
+    }
+
+    /// Disables the watchdog, unless it is in always-on mode
+    pub fn try_disable(&mut self) -> Result<(), AlwaysOn> {
+        if self.is_always_on() {
+            return Err(AlwaysOn);
+        }
+        self.set_enabled(false);
+        Ok(())
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.wdt.ctrla.modify(|_, w| w.enable().bit(enabled));
+        // Wait for the watchdog timer to be enabled or disabled.
+        while self.wdt.syncbusy.read().enable().bit_is_set() {}
+    }
 }
 
 impl watchdog::Watchdog for Watchdog {
     /// Feeds an existing watchdog to ensure the processor isn't reset.
     /// Sometimes commonly referred to as "kicking" or "refreshing".
     fn feed(&mut self) {
+        // A clear is ignored while the previous one is being synchronized
+        while self.wdt.syncbusy.read().clear().bit_is_set() {}
         self.wdt.clear.write(|w| unsafe { w.clear().bits(0xA5) });
     }
 }
 
 /// Disables a running watchdog timer so the processor won't be reset.
+///
+/// This has no effect in always-on mode, see [`Watchdog::try_disable`].
 impl watchdog::WatchdogDisable for Watchdog {
     fn disable(&mut self) {
-        // Disable the watchdog timer.
-        self.wdt.ctrla.write(|w| w.enable().clear_bit());
-        // Wait for watchdog timer to be disabled.
-        while self.wdt.syncbusy.read().enable().bit_is_set() {}
+        self.try_disable().ok();
     }
 }
 
@@ -53,17 +106,24 @@ impl watchdog::WatchdogEnable for Watchdog {
 
     /// Enables a watchdog timer to reset the processor if software is frozen
     /// or stalled.
+    ///
+    /// A running watchdog is reconfigured with the new period. In always-on
+    /// mode, the period can't be changed, so the watchdog is only fed.
     fn start<T>(&mut self, period: T)
     where
         T: Into<Self::Time>,
     {
+        if self.is_always_on() {
+            watchdog::Watchdog::feed(self);
+            return;
+        }
+        if self.is_enabled() {
+            self.set_enabled(false);
+        }
         // Write the timeout configuration.
         self.wdt
             .config
             .write(|w| unsafe { w.per().bits(period.into()) });
-        // Enable the watchdog timer.
-        self.wdt.ctrla.write(|w| w.enable().set_bit());
-        // Wait for watchdog timer to be enabled.
-        while self.wdt.syncbusy.read().enable().bit_is_set() {}
+        self.set_enabled(true);
     }
 }
@@ -0,0 +1,189 @@
+//! SysTick-free time base
+//!
+//! The [`Tick`] trait describes a free-running hardware counter with a rate
+//! fixed at compile time, such as the RTC in 32-bit counter mode
+//! ([`Rtc::into_tick`](crate::rtc::Rtc::into_tick)) or a timer counter
+//! ([`TimerCounter::into_tick`](crate::timer::TimerCounter::into_tick)).
+//! Unlike the SysTick timer, these counters can keep running in standby, so
+//! the helpers in this module allow an application to do without SysTick
+//! entirely.
+//!
+//! * [`Delay::from_tick`](crate::delay::Delay::from_tick) implements the
+//!   embedded-hal delay traits.
+//! * [`Timeout`] measures a deadline against a tick source.
+//! * [`Debouncer`] filters a noisy digital input.
+//!
+//! Because the rate is a constant, the conversions between time units and
+//! ticks are resolved by the compiler.
+
+use crate::time::Microseconds;
+
+/// A free-running counter incrementing at a fixed rate
+pub trait Tick {
+    /// The counter frequency in Hz
+    const HZ: u32;
+
+    /// The largest value the counter reaches before wrapping to zero
+    const MAX: u32 = u32::MAX;
+
+    /// Returns the current counter value
+    fn ticks(&self) -> u32;
+
+    /// Returns the number of ticks since `start`, an earlier value of
+    /// [`Tick::ticks`], accounting for a single wrap of the counter.
+    #[inline]
+    fn ticks_since(&self, start: u32) -> u32 {
+        self.ticks().wrapping_sub(start) & Self::MAX
+    }
+
+    /// Converts a duration to a number of ticks, rounding up
+    #[inline]
+    fn to_ticks<T: Into<Microseconds>>(duration: T) -> u32 {
+        let us = duration.into().ticks() as u64;
+        ((us * Self::HZ as u64 + 999_999) / 1_000_000) as u32
+    }
+}
+
+impl<T: Tick> Tick for &T {
+    const HZ: u32 = T::HZ;
+    const MAX: u32 = T::MAX;
+
+    #[inline]
+    fn ticks(&self) -> u32 {
+        (*self).ticks()
+    }
+}
+
+/// A deadline measured against a [`Tick`] source
+///
+/// The duration must be shorter than the period of the counter, otherwise
+/// the timeout can never expire.
+#[derive(Clone, Copy, Debug)]
+pub struct Timeout {
+    start: u32,
+    ticks: u32,
+}
+
+impl Timeout {
+    /// Starts a new timeout of the given duration
+    pub fn new<T: Tick, D: Into<Microseconds>>(tick: &T, duration: D) -> Self {
+        Self {
+            start: tick.ticks(),
+            ticks: T::to_ticks(duration),
+        }
+    }
+
+    /// Returns true once the duration has elapsed
+    pub fn expired<T: Tick>(&self, tick: &T) -> bool {
+        tick.ticks_since(self.start) >= self.ticks
+    }
+
+    /// Restarts the timeout from the current time
+    pub fn restart<T: Tick>(&mut self, tick: &T) {
+        self.start = tick.ticks();
+    }
+}
+
+/// Debounces a digital input sampled against a [`Tick`] source
+///
+/// A change of the input is only accepted once the input has been stable for
+/// the debounce period.
+#[derive(Clone, Copy, Debug)]
+pub struct Debouncer {
+    state: bool,
+    pending: Option<u32>,
+    ticks: u32,
+}
+
+impl Debouncer {
+    /// Creates a new debouncer with the given initial state and period
+    pub fn new<T: Tick, D: Into<Microseconds>>(initial: bool, period: D) -> Self {
+        Self {
+            state: initial,
+            pending: None,
+            ticks: T::to_ticks(period),
+        }
+    }
+
+    /// Feeds in a new sample of the input. Returns the new debounced state
+    /// if it changed.
+    pub fn update<T: Tick>(&mut self, tick: &T, input: bool) -> Option<bool> {
+        if input == self.state {
+            self.pending = None;
+            return None;
+        }
+        match self.pending {
+            None => {
+                self.pending = Some(tick.ticks());
+                None
+            }
+            Some(since) if tick.ticks_since(since) >= self.ticks => {
+                self.state = input;
+                self.pending = None;
+                Some(input)
+            }
+            Some(_) => None,
+        }
+    }
+
+    /// Returns the current debounced state
+    pub fn state(&self) -> bool {
+        self.state
+    }
+
+    /// Returns true while a change of the input is waiting out the period
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    struct FakeTick(Cell<u32>);
+
+    impl Tick for FakeTick {
+        const HZ: u32 = 32_768;
+        const MAX: u32 = 0xFFFF;
+
+        fn ticks(&self) -> u32 {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn to_ticks_rounds_up() {
+        assert_eq!(
+            FakeTick::to_ticks(Microseconds::from_ticks(1_000_000)),
+            32_768
+        );
+        assert_eq!(FakeTick::to_ticks(Microseconds::from_ticks(1)), 1);
+        assert_eq!(FakeTick::to_ticks(Microseconds::from_ticks(0)), 0);
+    }
+
+    #[test]
+    fn timeout_survives_wrap() {
+        let tick = FakeTick(Cell::new(0xFFF0));
+        let timeout = Timeout::new(&tick, Microseconds::from_ticks(1_000));
+        assert!(!timeout.expired(&tick));
+        tick.0.set(0x0010);
+        assert!(!timeout.expired(&tick));
+        tick.0.set(0x0020);
+        assert!(timeout.expired(&tick));
+    }
+
+    #[test]
+    fn debouncer_ignores_glitches() {
+        let tick = FakeTick(Cell::new(0));
+        let mut debouncer = Debouncer::new::<FakeTick, _>(false, Microseconds::from_ticks(1_000));
+        assert_eq!(debouncer.update(&tick, true), None);
+        tick.0.set(10);
+        assert_eq!(debouncer.update(&tick, false), None);
+        assert_eq!(debouncer.update(&tick, true), None);
+        tick.0.set(100);
+        assert_eq!(debouncer.update(&tick, true), Some(true));
+        assert!(debouncer.state());
+    }
+}
@@ -1,246 +1,127 @@
 //! Time units
+//!
+//! The time units are the [`fugit`] rates and durations, whose arithmetic is
+//! `const` and which are shared with RTIC and other frameworks. They are
+//! built with the [`RateExtU32`] and [`ExtU32`] extension methods, which
+//! infer the unit expected by the API they are passed to:
+//!
+//! ```
+//! use atsamd_hal::prelude::*;
+//!
+//! let uart = UART0::new(&clock, 115_200.Hz(), sercom0, &mut mclk, (rx, tx));
+//! pwm.set_period(20.kHz());
+//! timer.start(250.millis());
+//! ```
+//!
+//! A frequency is turned into the period expected by the timers with
+//! `into_duration`, e.g. `Hertz::kHz(5).into_duration()`, and the raw value
+//! is read with `to_Hz` or `ticks`.
+
+pub use fugit::{ExtU32, RateExtU32};
 
 // Frequency based
 
 /// Bits per second
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-pub struct Bps(pub u32);
+pub type Bps = fugit::HertzU32;
 
 /// Hertz
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-pub struct Hertz(pub u32);
+pub type Hertz = fugit::HertzU32;
 
 /// KiloHertz
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-pub struct KiloHertz(pub u32);
+pub type KiloHertz = fugit::KilohertzU32;
 
 /// MegaHertz
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-pub struct MegaHertz(pub u32);
+pub type MegaHertz = fugit::MegahertzU32;
 
 // Period based
 
 /// Seconds
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-pub struct Seconds(pub u32);
+pub type Seconds = fugit::SecsDurationU32;
 
 /// Milliseconds
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-pub struct Milliseconds(pub u32);
+pub type Milliseconds = fugit::MillisDurationU32;
 
 /// Microseconds
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-pub struct Microseconds(pub u32);
+pub type Microseconds = fugit::MicrosDurationU32;
 
 /// Nanoseconds
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-pub struct Nanoseconds(pub u32);
-
-/// Extension trait that adds convenience methods to the `u32` type
+pub type Nanoseconds = fugit::NanosDurationU32;
+
+/// Extension trait that adds the former unit methods to the `u32` type
+///
+/// The methods are kept to ease the move to [`fugit`]. Like the [`fugit`]
+/// extension methods they now return a rate or duration in the unit
+/// inferred from their use, so `48.mhz()` passed as a [`Hertz`] is
+/// 48,000,000 Hz.
 pub trait U32Ext {
-    /// Wrap in `Bps`
-    fn bps(self) -> Bps;
+    /// Rate in bits per second
+    #[deprecated(note = "use `RateExtU32::Hz`")]
+    fn bps<const NOM: u32, const DENOM: u32>(self) -> fugit::Rate<u32, NOM, DENOM>;
 
-    /// Wrap in `Hertz`
-    fn hz(self) -> Hertz;
+    /// Rate in Hertz
+    #[deprecated(note = "use `RateExtU32::Hz`")]
+    fn hz<const NOM: u32, const DENOM: u32>(self) -> fugit::Rate<u32, NOM, DENOM>;
 
-    /// Wrap in `KiloHertz`
-    fn khz(self) -> KiloHertz;
+    /// Rate in KiloHertz
+    #[deprecated(note = "use `RateExtU32::kHz`")]
+    fn khz<const NOM: u32, const DENOM: u32>(self) -> fugit::Rate<u32, NOM, DENOM>;
 
-    /// Wrap in `MegaHertz`
-    fn mhz(self) -> MegaHertz;
+    /// Rate in MegaHertz
+    #[deprecated(note = "use `RateExtU32::MHz`")]
+    fn mhz<const NOM: u32, const DENOM: u32>(self) -> fugit::Rate<u32, NOM, DENOM>;
 
-    /// Wrap in `Seconds`
-    fn s(self) -> Seconds;
+    /// Duration in seconds
+    #[deprecated(note = "use `ExtU32::secs`")]
+    fn s<const NOM: u32, const DENOM: u32>(self) -> fugit::Duration<u32, NOM, DENOM>;
 
-    /// Wrap in `Milliseconds`
-    fn ms(self) -> Milliseconds;
+    /// Duration in milliseconds
+    #[deprecated(note = "use `ExtU32::millis`")]
+    fn ms<const NOM: u32, const DENOM: u32>(self) -> fugit::Duration<u32, NOM, DENOM>;
 
-    /// Wrap in `Microseconds`
-    fn us(self) -> Microseconds;
+    /// Duration in microseconds
+    #[deprecated(note = "use `ExtU32::micros`")]
+    fn us<const NOM: u32, const DENOM: u32>(self) -> fugit::Duration<u32, NOM, DENOM>;
 
-    /// Wrap in `NanoSeconds`
-    fn ns(self) -> Nanoseconds;
+    /// Duration in nanoseconds
+    #[deprecated(note = "use `ExtU32::nanos`")]
+    fn ns<const NOM: u32, const DENOM: u32>(self) -> fugit::Duration<u32, NOM, DENOM>;
 }
 
 impl U32Ext for u32 {
     // Frequency based
 
-    fn bps(self) -> Bps {
-        Bps(self)
+    fn bps<const NOM: u32, const DENOM: u32>(self) -> fugit::Rate<u32, NOM, DENOM> {
+        self.Hz()
     }
 
-    fn hz(self) -> Hertz {
-        Hertz(self)
+    fn hz<const NOM: u32, const DENOM: u32>(self) -> fugit::Rate<u32, NOM, DENOM> {
+        self.Hz()
     }
 
-    fn khz(self) -> KiloHertz {
-        KiloHertz(self)
+    fn khz<const NOM: u32, const DENOM: u32>(self) -> fugit::Rate<u32, NOM, DENOM> {
+        self.kHz()
     }
 
-    fn mhz(self) -> MegaHertz {
-        MegaHertz(self)
+    fn mhz<const NOM: u32, const DENOM: u32>(self) -> fugit::Rate<u32, NOM, DENOM> {
+        self.MHz()
     }
 
     // Period based
 
-    fn s(self) -> Seconds {
-        Seconds(self)
-    }
-
-    fn ms(self) -> Milliseconds {
-        Milliseconds(self)
-    }
-
-    fn us(self) -> Microseconds {
-        Microseconds(self)
-    }
-
-    fn ns(self) -> Nanoseconds {
-        Nanoseconds(self)
-    }
-}
-
-// Frequency based
-
-impl From<KiloHertz> for Hertz {
-    fn from(item: KiloHertz) -> Self {
-        Hertz(item.0 * 1_000)
-    }
-}
-
-impl From<MegaHertz> for Hertz {
-    fn from(item: MegaHertz) -> Self {
-        Hertz(item.0 * 1_000_000)
+    fn s<const NOM: u32, const DENOM: u32>(self) -> fugit::Duration<u32, NOM, DENOM> {
+        self.secs()
     }
-}
 
-impl From<MegaHertz> for KiloHertz {
-    fn from(item: MegaHertz) -> Self {
-        KiloHertz(item.0 * 1_000)
+    fn ms<const NOM: u32, const DENOM: u32>(self) -> fugit::Duration<u32, NOM, DENOM> {
+        self.millis()
     }
-}
 
-impl From<Hertz> for KiloHertz {
-    fn from(item: Hertz) -> Self {
-        KiloHertz(item.0 / 1_000)
+    fn us<const NOM: u32, const DENOM: u32>(self) -> fugit::Duration<u32, NOM, DENOM> {
+        self.micros()
     }
-}
 
-impl From<Hertz> for MegaHertz {
-    fn from(item: Hertz) -> Self {
-        MegaHertz(item.0 / 1_000_000)
-    }
-}
-
-impl From<KiloHertz> for MegaHertz {
-    fn from(item: KiloHertz) -> Self {
-        MegaHertz(item.0 / 1_000)
-    }
-}
-
-// Period based
-
-impl From<Seconds> for Milliseconds {
-    fn from(item: Seconds) -> Self {
-        Milliseconds(item.0 * 1_000)
-    }
-}
-impl From<Seconds> for Microseconds {
-    fn from(item: Seconds) -> Self {
-        Microseconds(item.0 * 1_000_000)
-    }
-}
-
-impl From<Seconds> for Nanoseconds {
-    fn from(item: Seconds) -> Self {
-        Nanoseconds(item.0 * 1_000_000_000)
-    }
-}
-
-impl From<Milliseconds> for Microseconds {
-    fn from(item: Milliseconds) -> Self {
-        Microseconds(item.0 * 1_000)
-    }
-}
-
-impl From<Microseconds> for Nanoseconds {
-    fn from(item: Microseconds) -> Self {
-        Nanoseconds(item.0 * 1_000)
-    }
-}
-
-impl From<Milliseconds> for Seconds {
-    fn from(item: Milliseconds) -> Self {
-        Seconds(item.0 / 1_000)
-    }
-}
-
-impl From<Microseconds> for Seconds {
-    fn from(item: Microseconds) -> Self {
-        Seconds(item.0 / 1_000_000)
-    }
-}
-
-impl From<Microseconds> for Milliseconds {
-    fn from(item: Microseconds) -> Self {
-        Milliseconds(item.0 / 1_000)
-    }
-}
-
-impl From<Milliseconds> for Nanoseconds {
-    fn from(item: Milliseconds) -> Self {
-        Nanoseconds(item.0 * 1_000_000)
-    }
-}
-
-// Frequency <-> Period
-
-impl From<Nanoseconds> for Hertz {
-    fn from(item: Nanoseconds) -> Self {
-        Hertz(1_000_000_000_u32 / item.0)
-    }
-}
-
-impl From<Microseconds> for Hertz {
-    fn from(item: Microseconds) -> Self {
-        Hertz(1_000_000_u32 / item.0)
-    }
-}
-
-impl From<Nanoseconds> for KiloHertz {
-    fn from(item: Nanoseconds) -> Self {
-        KiloHertz(1_000_000_u32 / item.0)
-    }
-}
-
-impl From<Nanoseconds> for MegaHertz {
-    fn from(item: Nanoseconds) -> Self {
-        MegaHertz(1_000_u32 / item.0)
-    }
-}
-
-impl From<Hertz> for Microseconds {
-    fn from(item: Hertz) -> Self {
-        Microseconds(1_000_000_u32 / item.0)
-    }
-}
-
-impl From<Hertz> for Nanoseconds {
-    fn from(item: Hertz) -> Self {
-        Nanoseconds(1_000_000_000u32 / item.0)
-    }
-}
-
-impl From<KiloHertz> for Nanoseconds {
-    fn from(item: KiloHertz) -> Self {
-        Nanoseconds(1_000_000u32 / item.0)
-    }
-}
-
-impl From<MegaHertz> for Nanoseconds {
-    fn from(item: MegaHertz) -> Self {
-        Nanoseconds(1_000u32 / item.0)
+    fn ns<const NOM: u32, const DENOM: u32>(self) -> fugit::Duration<u32, NOM, DENOM> {
+        self.nanos()
     }
 }
 
@@ -249,32 +130,41 @@ mod tests {
     use crate::time::*;
 
     #[test]
-    fn convert_us_to_hz() {
-        let as_us: Microseconds = 3.hz().into();
-        assert_eq!(as_us.0, 333_333_u32);
+    fn convert_hz_to_us() {
+        let as_us: Microseconds = Hertz::Hz(3).into_duration();
+        assert_eq!(as_us.ticks(), 333_333_u32);
     }
 
     #[test]
     fn convert_ms_to_us() {
-        let as_us: Microseconds = 3.ms().into();
-        assert_eq!(as_us.0, 3_000_u32);
+        let as_us: Microseconds = Milliseconds::from_ticks(3).convert();
+        assert_eq!(as_us.ticks(), 3_000_u32);
     }
 
     #[test]
     fn convert_mhz_to_hz() {
-        let as_hz: Hertz = 48.mhz().into();
-        assert_eq!(as_hz.0, 48_000_000_u32);
+        let as_hz: Hertz = 48.MHz();
+        assert_eq!(as_hz.raw(), 48_000_000_u32);
     }
 
     #[test]
     fn convert_hz_to_ns() {
-        let as_ns: Nanoseconds = 3.mhz().into();
-        assert_eq!(as_ns.0, 333_u32);
+        let as_ns: Nanoseconds = Hertz::MHz(3).into_duration();
+        assert_eq!(as_ns.ticks(), 333_u32);
     }
 
     #[test]
     fn convert_hz_to_ns_even() {
-        let as_ns: Nanoseconds = 2.mhz().into();
-        assert_eq!(as_ns.0, 500_u32);
+        let as_ns: Nanoseconds = Hertz::MHz(2).into_duration();
+        assert_eq!(as_ns.ticks(), 500_u32);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn former_unit_methods() {
+        let as_hz: Hertz = 48.mhz();
+        assert_eq!(as_hz.to_Hz(), 48_000_000_u32);
+        let as_ns: Nanoseconds = 3.ms();
+        assert_eq!(as_ns.ticks(), 3_000_000_u32);
     }
 }
@@ -15,8 +15,8 @@ impl TimerParams {
         T: Into<Hertz>,
     {
         let timeout = timeout.into();
-        let ticks: u32 = src_freq / timeout.0.max(1);
-        Self::new_from_ticks(ticks)
+        let ticks: u32 = src_freq / timeout.to_Hz().max(1);
+        Self::new_from_ticks(ticks, 16)
     }
 
     /// calculates TimerParams from a given period based timeout.
@@ -25,12 +25,34 @@ impl TimerParams {
         T: Into<Nanoseconds>,
     {
         let timeout = timeout.into();
-        let ticks: u32 = (timeout.0 as u64 * src_freq as u64 / 1_000_000_000_u64) as u32;
-        Self::new_from_ticks(ticks)
+        let ticks: u32 = (timeout.ticks() as u64 * src_freq as u64 / 1_000_000_000_u64) as u32;
+        Self::new_from_ticks(ticks, 16)
     }
 
-    fn new_from_ticks(ticks: u32) -> Self {
-        let divider = ((ticks >> 16) + 1).next_power_of_two();
+    /// calculates TimerParams for an 8-bit counter from a given frequency
+    /// based timeout.
+    pub fn new_8bit<T>(timeout: T, src_freq: u32) -> Self
+    where
+        T: Into<Hertz>,
+    {
+        let timeout = timeout.into();
+        let ticks: u32 = src_freq / timeout.to_Hz().max(1);
+        Self::new_from_ticks(ticks, 8)
+    }
+
+    /// calculates TimerParams for an 8-bit counter from a given period based
+    /// timeout.
+    pub fn new_us_8bit<T>(timeout: T, src_freq: u32) -> Self
+    where
+        T: Into<Nanoseconds>,
+    {
+        let timeout = timeout.into();
+        let ticks: u32 = (timeout.ticks() as u64 * src_freq as u64 / 1_000_000_000_u64) as u32;
+        Self::new_from_ticks(ticks, 8)
+    }
+
+    fn new_from_ticks(ticks: u32, bits: u32) -> Self {
+        let divider = ((ticks >> bits) + 1).next_power_of_two();
         let divider = match divider {
             1 | 2 | 4 | 8 | 16 | 64 | 256 | 1024 => divider,
             // There are a couple of gaps, so we round up to the next largest
@@ -45,8 +67,15 @@ impl TimerParams {
 
         let cycles: u32 = ticks / divider as u32;
 
-        if cycles > u16::max_value() as u32 {
-            panic!("cycles {} is out of range for a 16 bit counter", cycles);
+        if cycles >= 1 << bits {
+            // Formatting the values doesn't fit the flash of the SAMD11
+            #[cfg(feature = "samd11")]
+            panic!("cycles are out of range for the counter");
+            #[cfg(not(feature = "samd11"))]
+            panic!(
+                "cycles {} is out of range for a {} bit counter",
+                cycles, bits
+            );
         }
 
         TimerParams {
@@ -58,13 +87,13 @@ impl TimerParams {
 
 #[cfg(test)]
 mod tests {
-    use crate::time::U32Ext;
+    use crate::time::{ExtU32, RateExtU32};
     use crate::timer_params::TimerParams;
 
     #[test]
     fn timer_params_hz_and_us_same_1hz() {
-        let tp_from_hz = TimerParams::new(1_u32.hz(), 48_000_000_u32);
-        let tp_from_us = TimerParams::new_us(1_000_000_u32.us(), 48_000_000_u32);
+        let tp_from_hz = TimerParams::new(1_u32.Hz(), 48_000_000_u32);
+        let tp_from_us = TimerParams::new_us(1_000_000_u32.micros(), 48_000_000_u32);
 
         assert_eq!(tp_from_hz.divider, tp_from_us.divider);
         assert_eq!(tp_from_hz.cycles, tp_from_us.cycles);
@@ -72,12 +101,25 @@ mod tests {
 
     #[test]
     fn timer_params_hz_and_us_same_3hz() {
-        let tp_from_hz = TimerParams::new(3_u32.hz(), 48_000_000_u32);
-        let tp_from_us = TimerParams::new_us(333_333_u32.us(), 48_000_000_u32);
+        let tp_from_hz = TimerParams::new(3_u32.Hz(), 48_000_000_u32);
+        let tp_from_us = TimerParams::new_us(333_333_u32.micros(), 48_000_000_u32);
 
         // There's some rounding error here, but it is extremely small (1 cycle
         // difference)
         assert_eq!(tp_from_hz.divider, tp_from_us.divider);
         assert!((tp_from_hz.cycles as i32 - tp_from_us.cycles as i32).abs() <= 1);
     }
+
+    #[test]
+    fn timer_params_8bit() {
+        // 100kHz from 48MHz: 480 ticks, divided by 2 to fit in 8 bits
+        let tp = TimerParams::new_8bit(100_u32.kHz(), 48_000_000_u32);
+        assert_eq!(tp.divider, 2);
+        assert_eq!(tp.cycles, 240);
+
+        // 1kHz: 48000 ticks, divided by 256 as there is no 128 divider
+        let tp = TimerParams::new_us_8bit(1_000_u32.micros(), 48_000_000_u32);
+        assert_eq!(tp.divider, 256);
+        assert_eq!(tp.cycles, 187);
+    }
 }
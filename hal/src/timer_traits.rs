@@ -10,3 +10,14 @@ pub trait InterruptDrivenTimer: CountDown<Time = time::Nanoseconds> + Periodic {
     /// Disable the timer interrupt
     fn disable_interrupt(&mut self);
 }
+
+/// Trait for timers capturing the period and the width of the pulses of an
+/// input, in microseconds
+pub trait PulseCapture {
+    /// Returns the width of the last pulse, once its end was captured
+    fn read_width(&mut self) -> Option<u32>;
+
+    /// Returns the period of the last pulse, from its start to the start of
+    /// the next one, once the start of the next one was captured
+    fn read_period(&mut self) -> Option<u32>;
+}
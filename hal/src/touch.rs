@@ -0,0 +1,191 @@
+//! Capacitive touch slider and wheel positions
+//!
+//! A slider is a strip of touch pads side by side, and a wheel a ring of
+//! them. A finger covers parts of two or three adjacent pads, so its position
+//! can be estimated far more finely than the pad pitch, from the centroid of
+//! the signals of the strongest pad and its neighbours.
+//!
+//! [`Slider`] works on the deltas of each pad, i.e. the difference between
+//! the current measurement and the untouched baseline, however they are
+//! acquired. It reports positions from zero to the configured resolution,
+//! minus one, with hysteresis so that the position does not jitter while the
+//! finger rests:
+//!
+//! ```
+//! let mut slider = Slider::<4>::new(256).threshold(40).hysteresis(3);
+//! loop {
+//!     let deltas = read_pads();
+//!     if let Some(position) = slider.update(&deltas) {
+//!         set_volume(position);
+//!     }
+//! }
+//! ```
+
+/// A touch slider or wheel of `N` pads
+#[derive(Clone, Debug)]
+pub struct Slider<const N: usize> {
+    wheel: bool,
+    resolution: u16,
+    threshold: u16,
+    hysteresis: u16,
+    position: Option<u16>,
+}
+
+impl<const N: usize> Slider<N> {
+    /// Creates a linear slider reporting `resolution` positions, the first
+    /// pad being at zero and the last one at `resolution - 1`
+    pub const fn new(resolution: u16) -> Self {
+        Self {
+            wheel: false,
+            resolution,
+            threshold: 1,
+            hysteresis: 0,
+            position: None,
+        }
+    }
+
+    /// Creates a wheel reporting `resolution` positions over a full turn, the
+    /// first pad being at zero. Positions wrap around between the last pad
+    /// and the first.
+    pub const fn wheel(resolution: u16) -> Self {
+        Self {
+            wheel: true,
+            ..Self::new(resolution)
+        }
+    }
+
+    /// Sets the smallest delta of the strongest pad for which the slider is
+    /// touched. Defaults to one.
+    pub const fn threshold(mut self, threshold: u16) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Sets the smallest change of position, in steps of the resolution,
+    /// that is reported while the slider stays touched. Defaults to zero.
+    pub const fn hysteresis(mut self, hysteresis: u16) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    /// Returns the last reported position, or `None` if the slider was not
+    /// touched
+    pub fn position(&self) -> Option<u16> {
+        self.position
+    }
+
+    /// Forgets the last position, so that the next one is reported without
+    /// hysteresis
+    pub fn reset(&mut self) {
+        self.position = None;
+    }
+
+    /// Updates the position from the deltas of the pads. Negative deltas
+    /// should be clamped to zero. Returns the new position, or `None` if the
+    /// slider is not touched.
+    pub fn update(&mut self, deltas: &[u16; N]) -> Option<u16> {
+        let (peak, &max) = deltas.iter().enumerate().max_by_key(|&(_, d)| *d)?;
+        if max < self.threshold || self.resolution == 0 {
+            self.position = None;
+            return None;
+        }
+        let raw = self.estimate(deltas, peak);
+        let position = match self.position {
+            Some(last) if self.distance(last, raw) < self.hysteresis => last,
+            _ => raw,
+        };
+        self.position = Some(position);
+        self.position
+    }
+
+    /// Computes the centroid of the peak pad and its neighbours, scaled to
+    /// the resolution
+    fn estimate(&self, deltas: &[u16; N], peak: usize) -> u16 {
+        let neighbour = |offset: isize| -> u32 {
+            let index = peak as isize + offset;
+            if self.wheel {
+                deltas[index.rem_euclid(N as isize) as usize] as u32
+            } else if index < 0 || index >= N as isize {
+                0
+            } else {
+                deltas[index as usize] as u32
+            }
+        };
+        // A wheel of two pads sees the same pad on both sides
+        let prev = if N == 2 && self.wheel {
+            0
+        } else {
+            neighbour(-1)
+        };
+        let next = neighbour(1);
+        let sum = prev + deltas[peak] as u32 + next;
+
+        // The centroid is `peak + (next - prev) / sum` pads, kept in fixed
+        // point by scaling everything by `sum`
+        let centroid = (peak as i64 * sum as i64) + next as i64 - prev as i64;
+        let resolution = self.resolution as i64;
+        if self.wheel {
+            let turn = N as i64 * sum as i64;
+            let scaled = (centroid.rem_euclid(turn) * resolution + turn / 2) / turn;
+            (scaled % resolution) as u16
+        } else if N < 2 {
+            0
+        } else {
+            let span = (N as i64 - 1) * sum as i64;
+            let centroid = centroid.max(0).min(span);
+            ((centroid * (resolution - 1) + span / 2) / span) as u16
+        }
+    }
+
+    /// Distance between two positions, the short way round on a wheel
+    fn distance(&self, a: u16, b: u16) -> u16 {
+        let d = a.max(b) - a.min(b);
+        if self.wheel {
+            d.min(self.resolution - d)
+        } else {
+            d
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slider_centroid() {
+        let mut slider = Slider::<4>::new(301).threshold(10);
+        assert_eq!(slider.update(&[0, 0, 0, 5]), None);
+        assert_eq!(slider.update(&[100, 0, 0, 0]), Some(0));
+        assert_eq!(slider.update(&[0, 0, 0, 100]), Some(300));
+        // Halfway between the second and third pads
+        assert_eq!(slider.update(&[0, 80, 80, 0]), Some(150));
+        // A fifth of a pad past the second one
+        assert_eq!(slider.update(&[10, 60, 30, 0]), Some(120));
+        assert_eq!(slider.position(), Some(120));
+    }
+
+    #[test]
+    fn wheel_wraps() {
+        let mut wheel = Slider::<4>::wheel(360);
+        assert_eq!(wheel.update(&[100, 0, 0, 0]), Some(0));
+        assert_eq!(wheel.update(&[0, 100, 0, 0]), Some(90));
+        // Between the last pad and the first
+        assert_eq!(wheel.update(&[50, 0, 0, 50]), Some(315));
+        assert_eq!(wheel.update(&[60, 0, 0, 20]), Some(338));
+        assert_eq!(wheel.update(&[60, 20, 0, 0]), Some(23));
+    }
+
+    #[test]
+    fn hysteresis() {
+        let mut wheel = Slider::<4>::wheel(360).hysteresis(50);
+        assert_eq!(wheel.update(&[60, 20, 0, 0]), Some(23));
+        // Small moves, also across zero, are ignored
+        assert_eq!(wheel.update(&[60, 0, 0, 20]), Some(23));
+        assert_eq!(wheel.update(&[50, 50, 0, 0]), Some(23));
+        assert_eq!(wheel.update(&[0, 100, 0, 0]), Some(90));
+        // Releasing the wheel forgets the position
+        assert_eq!(wheel.update(&[0, 0, 0, 0]), None);
+        assert_eq!(wheel.update(&[60, 0, 0, 20]), Some(338));
+    }
+}
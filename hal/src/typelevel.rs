@@ -631,6 +631,14 @@
 //! you put back an instance of `P` exactly. The final use of [`Into`] is key
 //! here. It transforms the `SpecificPin` back into `P` itself.
 
+#[cfg(feature = "device")]
+use core::marker::PhantomData;
+#[cfg(feature = "device")]
+use core::ops::{Add, Sub};
+
+#[cfg(feature = "device")]
+use typenum::{Add1, Sub1, Unsigned, B1};
+
 mod private {
     /// Super trait used to mark traits with an exhaustive set of
     /// implementations
@@ -644,6 +652,44 @@ pub(crate) use private::Sealed;
 pub struct NoneT;
 impl Sealed for NoneT {}
 
+/// A resource which is enabled and shared by `N` users
+///
+/// The count is a [`typenum`] unsigned integer. It is incremented when a
+/// user is added, and the resource can only be disabled when it is zero.
+/// It counts the users of the GCLK generators in `clock::v2`, and of the
+/// event channels in `evsys`.
+#[cfg(feature = "device")]
+pub struct Enabled<T, N: Unsigned> {
+    pub(crate) inner: T,
+    users: PhantomData<N>,
+}
+
+#[cfg(feature = "device")]
+impl<T, N: Unsigned> Enabled<T, N> {
+    pub(crate) fn new(inner: T) -> Self {
+        Self {
+            inner,
+            users: PhantomData,
+        }
+    }
+
+    pub(crate) fn add_user(self) -> Enabled<T, Add1<N>>
+    where
+        N: Add<B1>,
+        Add1<N>: Unsigned,
+    {
+        Enabled::new(self.inner)
+    }
+
+    pub(crate) fn remove_user(self) -> Enabled<T, Sub1<N>>
+    where
+        N: Sub<B1>,
+        Sub1<N>: Unsigned,
+    {
+        Enabled::new(self.inner)
+    }
+}
+
 /// Marker trait for type identity
 ///
 /// This trait is used as part of the [`AnyKind`] trait pattern. It represents
@@ -0,0 +1,252 @@
+//! Composite USB devices
+//!
+//! The USB peripheral has seven endpoints besides the control endpoint, each
+//! with an IN and an OUT bank, and 2kB of RAM for the endpoint buffers. The
+//! `usb-device` classes allocate their endpoints when they are created, and
+//! most of them panic when the allocation fails, while a device combining
+//! classes without an interface association fails to enumerate on some hosts
+//! with little explanation.
+//!
+//! [`CompositeUsb`] checks what each class needs, given as a [`ClassBudget`],
+//! against what is left before creating it, so that a combination which does
+//! not fit is reported by [`CompositeUsb::add`] instead. The endpoints the
+//! class then allocates on the bus are counted, and a class exceeding its
+//! budget is reported too, so that a wrong budget can't hide an overflow. It
+//! then sets the device class so that the host reads the interface
+//! associations.
+//!
+//! ```
+//! let mut composite = CompositeUsb::new(&bus_allocator);
+//! let mut serial = composite
+//!     .add(ClassBudget::cdc_acm(64), |alloc| SerialPort::new(alloc))
+//!     .unwrap();
+//! let mut hid = composite
+//!     .add(ClassBudget::hid(8, 0), |alloc| {
+//!         HIDClass::new(alloc, KeyboardReport::desc(), 10)
+//!     })
+//!     .unwrap();
+//! let mut device = composite
+//!     .build(UsbVidPid(0x16c0, 0x27dd))
+//!     .manufacturer("Fake company")
+//!     .product("Serial and keyboard")
+//!     .build();
+//! loop {
+//!     device.poll(&mut [&mut serial, &mut hid]);
+//! }
+//! ```
+
+use crate::usb::UsbBus;
+use core::cell::Cell;
+use cortex_m::interrupt::{free as disable_interrupts, Mutex};
+use usb_device::bus::UsbBusAllocator;
+use usb_device::endpoint::EndpointAddress;
+use usb_device::prelude::*;
+use usb_device::UsbDirection;
+
+/// Number of endpoints in each direction, besides the control endpoint
+pub const ENDPOINTS: u8 = 7;
+
+/// Endpoint buffer RAM left once the control endpoint is allocated, in bytes
+pub const BUFFER_SIZE: u16 = 2048 - 2 * 64;
+
+/// Miscellaneous device class, with the interface association protocol
+const MISC_CLASS: u8 = 0xef;
+const COMMON_SUBCLASS: u8 = 0x02;
+const IAD_PROTOCOL: u8 = 0x01;
+
+/// Returns the size of the buffer allocated to an endpoint, which the
+/// hardware rounds up to a power of two
+const fn bank_size(max_packet_size: u16) -> u16 {
+    let mut size = 8;
+    while size < max_packet_size {
+        size *= 2;
+    }
+    size
+}
+
+/// Endpoints and buffer RAM needed by a USB class
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ClassBudget {
+    /// Number of IN endpoints
+    pub in_endpoints: u8,
+    /// Number of OUT endpoints
+    pub out_endpoints: u8,
+    /// Endpoint buffer RAM, in bytes, with each endpoint rounded up to a
+    /// power of two
+    pub buffer: u16,
+    /// Number of interfaces. Classes with several interfaces, such as CDC,
+    /// need an interface association in a composite device.
+    pub interfaces: u8,
+}
+
+impl ClassBudget {
+    /// Nothing at all
+    pub const NONE: Self = Self {
+        in_endpoints: 0,
+        out_endpoints: 0,
+        buffer: 0,
+        interfaces: 0,
+    };
+
+    /// A CDC ACM serial port, e.g. `usbd_serial::SerialPort`, whose data
+    /// endpoints have the given maximum packet size
+    pub const fn cdc_acm(max_packet_size: u16) -> Self {
+        Self {
+            in_endpoints: 2,
+            out_endpoints: 1,
+            buffer: bank_size(8) + 2 * bank_size(max_packet_size),
+            interfaces: 2,
+        }
+    }
+
+    /// A HID class with the given maximum packet sizes of its interrupt
+    /// endpoints. An `out_packet_size` of zero means no OUT endpoint.
+    pub const fn hid(in_packet_size: u16, out_packet_size: u16) -> Self {
+        let out = if out_packet_size > 0 { 1 } else { 0 };
+        Self {
+            in_endpoints: 1,
+            out_endpoints: out,
+            buffer: bank_size(in_packet_size) + out as u16 * bank_size(out_packet_size),
+            interfaces: 1,
+        }
+    }
+
+    /// A mass storage class, with the bulk-only transport, whose endpoints
+    /// have the given maximum packet size
+    pub const fn msc(max_packet_size: u16) -> Self {
+        Self {
+            in_endpoints: 1,
+            out_endpoints: 1,
+            buffer: 2 * bank_size(max_packet_size),
+            interfaces: 1,
+        }
+    }
+}
+
+/// Errors of [`CompositeUsb::add`]
+///
+/// Each variant holds the index of the class that does not fit, in the order
+/// the classes were added.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CompositeError {
+    /// All the IN endpoints are taken
+    InEndpoints(usize),
+    /// All the OUT endpoints are taken
+    OutEndpoints(usize),
+    /// The endpoint buffer RAM is exhausted
+    Buffer(usize),
+    /// The class allocated more endpoints or buffer RAM than its budget
+    OverBudget(usize),
+}
+
+/// Endpoints and buffer RAM allocated on the USB bus, besides the control
+/// endpoint
+static ALLOCATED: Mutex<Cell<ClassBudget>> = Mutex::new(Cell::new(ClassBudget::NONE));
+
+/// Records an endpoint allocated on the USB bus, with the size of its buffer
+pub(crate) fn record_endpoint(addr: EndpointAddress, buffer: u16) {
+    if addr.index() == 0 {
+        return;
+    }
+    disable_interrupts(|cs| {
+        let cell = ALLOCATED.borrow(cs);
+        let mut allocated = cell.get();
+        match addr.direction() {
+            UsbDirection::In => allocated.in_endpoints += 1,
+            UsbDirection::Out => allocated.out_endpoints += 1,
+        }
+        allocated.buffer += buffer;
+        cell.set(allocated);
+    });
+}
+
+/// Forgets the endpoints allocated on a previous USB bus
+pub(crate) fn reset_endpoints() {
+    disable_interrupts(|cs| ALLOCATED.borrow(cs).set(ClassBudget::NONE));
+}
+
+/// Returns the endpoints and buffer RAM allocated on the USB bus so far,
+/// besides the control endpoint. Interfaces are not counted.
+pub fn allocated() -> ClassBudget {
+    disable_interrupts(|cs| ALLOCATED.borrow(cs).get())
+}
+
+/// Builds a composite USB device, checking the endpoint budget of each class.
+/// See the [module](self) documentation.
+pub struct CompositeUsb<'a> {
+    allocator: &'a UsbBusAllocator<UsbBus>,
+    interfaces: u8,
+    classes: usize,
+    association: bool,
+}
+
+impl<'a> CompositeUsb<'a> {
+    /// Starts a new device on the given USB bus. Classes created on the bus
+    /// before are taken into account, but not their interfaces.
+    pub fn new(allocator: &'a UsbBusAllocator<UsbBus>) -> Self {
+        Self {
+            allocator,
+            interfaces: 0,
+            classes: 0,
+            association: false,
+        }
+    }
+
+    /// Returns what is left for the next classes
+    pub fn remaining(&self) -> ClassBudget {
+        let used = allocated();
+        ClassBudget {
+            in_endpoints: ENDPOINTS.saturating_sub(used.in_endpoints),
+            out_endpoints: ENDPOINTS.saturating_sub(used.out_endpoints),
+            buffer: BUFFER_SIZE.saturating_sub(used.buffer),
+            interfaces: u8::MAX - self.interfaces,
+        }
+    }
+
+    /// Creates a class with `new`, if what is left covers its `budget`.
+    /// Returns [`CompositeError::OverBudget`] if the class then allocated
+    /// more than its budget.
+    pub fn add<C, F>(&mut self, budget: ClassBudget, new: F) -> Result<C, CompositeError>
+    where
+        F: FnOnce(&'a UsbBusAllocator<UsbBus>) -> C,
+    {
+        let remaining = self.remaining();
+        if budget.in_endpoints > remaining.in_endpoints {
+            return Err(CompositeError::InEndpoints(self.classes));
+        }
+        if budget.out_endpoints > remaining.out_endpoints {
+            return Err(CompositeError::OutEndpoints(self.classes));
+        }
+        if budget.buffer > remaining.buffer {
+            return Err(CompositeError::Buffer(self.classes));
+        }
+        let before = allocated();
+        let class = new(self.allocator);
+        let after = allocated();
+        if after.in_endpoints - before.in_endpoints > budget.in_endpoints
+            || after.out_endpoints - before.out_endpoints > budget.out_endpoints
+            || after.buffer - before.buffer > budget.buffer
+        {
+            return Err(CompositeError::OverBudget(self.classes));
+        }
+        self.interfaces = self.interfaces.saturating_add(budget.interfaces);
+        self.association |= budget.interfaces > 1;
+        self.classes += 1;
+        Ok(class)
+    }
+
+    /// Returns the device builder, with the device class set for interface
+    /// associations if the device has several classes and one of them needs
+    /// it
+    pub fn build(self, vid_pid: UsbVidPid) -> UsbDeviceBuilder<'a, UsbBus> {
+        let builder = UsbDeviceBuilder::new(self.allocator, vid_pid);
+        if self.association && self.classes > 1 {
+            builder
+                .device_class(MISC_CLASS)
+                .device_sub_class(COMMON_SUBCLASS)
+                .device_protocol(IAD_PROTOCOL)
+        } else {
+            builder
+        }
+    }
+}
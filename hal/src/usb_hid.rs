@@ -0,0 +1,219 @@
+//! USB HID keyboard and mouse
+//!
+//! Enabled by the `usb-hid` feature, [`HidKeyboard`] turns a [`Keypad`]
+//! into a USB boot protocol keyboard: it bundles the USB device, the HID
+//! class of `usbd-hid` with the standard keyboard report descriptor, and a
+//! keymap giving the HID usage ID of each key. Two things must be driven
+//! from interrupts:
+//!
+//! * [`HidKeyboard::poll`] from the USB interrupts, so that the host's
+//!   requests are serviced promptly.
+//! * [`HidKeyboard::scan`] from a periodic timer interrupt (1kHz works
+//!   well), which scans the keypad and sends a new report whenever a key
+//!   changes state once debounced.
+//!
+//! Both interrupts use the keyboard, so give them the same priority, so that
+//! one can't preempt the other. The keypad is debounced against a [`Tick`]
+//! source, which [`HidKeyboard::scan`] is given on each scan:
+//!
+//! ```
+//! let keypad = Keypad::new(rows, cols, Scanner::new::<TimerTick<TC4, 750_000>, _>(debounce));
+//! // HID keyboard usage IDs: a, b / c, left shift
+//! let keymap = [[0x04, 0x05], [0x06, 0xE1]];
+//! let keyboard = HidKeyboard::new(&bus_allocator, vid_pid, "Maker", "Keyboard", keypad, keymap);
+//! ```
+//!
+//! [`HidMouse`] does the same for a boot protocol mouse, whose movements and
+//! buttons are reported by the application, e.g. from switches read with
+//! [`mouse_buttons`].
+
+use usb_device::bus::{UsbBus, UsbBusAllocator};
+use usb_device::prelude::*;
+use usb_device::UsbError;
+use usbd_hid::descriptor::generator_prelude::*;
+use usbd_hid::descriptor::{KeyboardReport, MouseReport};
+use usbd_hid::hid_class::HIDClass;
+
+use crate::gpio::v2::{DynPin, Error};
+use crate::hal::digital::v2::InputPin;
+use crate::keypad::Keypad;
+use crate::tick::Tick;
+
+/// Maximum number of non-modifier keys which can be reported at once
+const MAX_KEYS: usize = 6;
+
+/// Interval at which the host polls the HID endpoints, in milliseconds
+const POLL_MS: u8 = 10;
+
+/// A USB keyboard scanning a [`Keypad`] of `R` rows and `C` columns. See the
+/// [module](self) documentation.
+///
+/// The keymap holds the HID keyboard usage ID of each key, indexed as
+/// `keymap[row][col]`. Usage IDs `0xE0..=0xE7` are the modifier keys and are
+/// reported in the modifier byte; `0x00` marks an unused position.
+pub struct HidKeyboard<'a, B: UsbBus, const R: usize, const C: usize> {
+    device: UsbDevice<'a, B>,
+    hid: HIDClass<'a, B>,
+    keypad: Keypad<R, C>,
+    keymap: [[u8; C]; R],
+    /// A change was not sent yet, as the host had not collected the
+    /// previous report
+    pending: bool,
+}
+
+impl<'a, B: UsbBus, const R: usize, const C: usize> HidKeyboard<'a, B, R, C> {
+    /// Creates a new keyboard on the given USB bus
+    pub fn new(
+        bus_allocator: &'a UsbBusAllocator<B>,
+        vid_pid: UsbVidPid,
+        manufacturer: &'a str,
+        product: &'a str,
+        keypad: Keypad<R, C>,
+        keymap: [[u8; C]; R],
+    ) -> Self {
+        // The class must allocate its endpoint before the device is built
+        let hid = HIDClass::new(bus_allocator, KeyboardReport::desc(), POLL_MS);
+        let device = UsbDeviceBuilder::new(bus_allocator, vid_pid)
+            .manufacturer(manufacturer)
+            .product(product)
+            .serial_number("KBD")
+            .build();
+        Self {
+            device,
+            hid,
+            keypad,
+            keymap,
+            pending: false,
+        }
+    }
+
+    /// Services the USB peripheral. Call this from the `USB` interrupt, or
+    /// from all three USB interrupts of the SAMD51.
+    pub fn poll(&mut self) {
+        self.device.poll(&mut [&mut self.hid]);
+    }
+
+    /// Scans the keypad at the current time of `tick`, and sends a new
+    /// report to the host if a key changed state. Call this periodically,
+    /// e.g. from a timer interrupt.
+    ///
+    /// If the host has not collected the previous report yet, the new one is
+    /// sent on a later scan.
+    pub fn scan<T: Tick>(&mut self, tick: &T) -> Result<(), Error> {
+        self.keypad.scan(tick)?;
+        // The report is built from the state of the keys, so the events are
+        // only needed to know that something changed
+        while self.keypad.pop().is_some() {
+            self.pending = true;
+        }
+        if self.pending {
+            let report = self.report();
+            self.pending = self.hid.push_input(&report).is_err();
+        }
+        Ok(())
+    }
+
+    /// Builds a report of the keys currently pressed
+    pub fn report(&self) -> KeyboardReport {
+        let mut modifier = 0;
+        let mut keycodes = [0; MAX_KEYS];
+        let mut nkeys = 0;
+
+        let scanner = self.keypad.scanner();
+        for (row, codes) in self.keymap.iter().enumerate() {
+            for (col, &code) in codes.iter().enumerate() {
+                match code {
+                    _ if !scanner.is_pressed(row, col) => {}
+                    0x00 => {}
+                    0xE0..=0xE7 => modifier |= 1 << (code - 0xE0),
+                    _ if nkeys < MAX_KEYS => {
+                        keycodes[nkeys] = code;
+                        nkeys += 1;
+                    }
+                    // Too many keys held: report ErrorRollOver in every slot
+                    _ => keycodes = [0x01; MAX_KEYS],
+                }
+            }
+        }
+
+        KeyboardReport {
+            modifier,
+            leds: 0,
+            keycodes,
+        }
+    }
+
+    /// Returns the state of the USB device
+    pub fn state(&self) -> UsbDeviceState {
+        self.device.state()
+    }
+
+    /// Releases the keypad
+    pub fn free(self) -> Keypad<R, C> {
+        self.keypad
+    }
+}
+
+/// A USB boot protocol mouse. See the [module](self) documentation.
+pub struct HidMouse<'a, B: UsbBus> {
+    device: UsbDevice<'a, B>,
+    hid: HIDClass<'a, B>,
+    buttons: u8,
+}
+
+impl<'a, B: UsbBus> HidMouse<'a, B> {
+    /// Creates a new mouse on the given USB bus
+    pub fn new(
+        bus_allocator: &'a UsbBusAllocator<B>,
+        vid_pid: UsbVidPid,
+        manufacturer: &'a str,
+        product: &'a str,
+    ) -> Self {
+        let hid = HIDClass::new(bus_allocator, MouseReport::desc(), POLL_MS);
+        let device = UsbDeviceBuilder::new(bus_allocator, vid_pid)
+            .manufacturer(manufacturer)
+            .product(product)
+            .serial_number("MOUSE")
+            .build();
+        Self {
+            device,
+            hid,
+            buttons: 0,
+        }
+    }
+
+    /// Services the USB peripheral. Call this from the `USB` interrupt, or
+    /// from all three USB interrupts of the SAMD51.
+    pub fn poll(&mut self) {
+        self.device.poll(&mut [&mut self.hid]);
+    }
+
+    /// Sends a movement by `x` and `y` with the given `buttons` held. Nothing
+    /// is sent if there is no movement and the buttons did not change.
+    pub fn report(&mut self, x: i8, y: i8, buttons: u8) -> Result<(), UsbError> {
+        if x == 0 && y == 0 && buttons == self.buttons {
+            return Ok(());
+        }
+        self.hid.push_input(&MouseReport { buttons, x, y })?;
+        self.buttons = buttons;
+        Ok(())
+    }
+
+    /// Returns the state of the USB device
+    pub fn state(&self) -> UsbDeviceState {
+        self.device.state()
+    }
+}
+
+/// Reads the mouse buttons from pulled-up switches to ground, the first one
+/// being the left button, the second the right one and the third the middle
+/// one, for [`HidMouse::report`]
+pub fn mouse_buttons(switches: &[DynPin]) -> Result<u8, Error> {
+    let mut buttons = 0;
+    for (i, switch) in switches.iter().take(3).enumerate() {
+        if switch.is_low()? {
+            buttons |= 1 << i;
+        }
+    }
+    Ok(buttons)
+}
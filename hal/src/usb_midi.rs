@@ -0,0 +1,505 @@
+//! USB MIDI device
+//!
+//! Enabled by the `usb-midi` feature, [`UsbMidi`] bundles a USB device, a
+//! USB MIDI 1.0 streaming class with one embedded jack in each direction, and
+//! a queue of [`MidiEvent`]s in each direction. All the endpoint work happens in [`UsbMidi::poll`], which must
+//! be called from the USB interrupts, while the application only pushes and
+//! pops events:
+//!
+//! * [`UsbMidi::send`] queues an event and writes it right away if the IN
+//!   endpoint is free. Otherwise, the next poll writes it as soon as the host
+//!   collects the previous packet.
+//! * [`UsbMidi::receive`] pops the oldest event received. Incoming packets
+//!   are only read when the queue has room for a full packet, so the host is
+//!   held off rather than events being dropped when the application falls
+//!   behind.
+//!
+//! Share the [`UsbMidi`] between the interrupt handler and the application
+//! with a `Mutex<RefCell<_>>`, and keep the critical sections short:
+//!
+//! ```
+//! let event = cortex_m::interrupt::free(|cs| {
+//!     MIDI.borrow(cs).borrow_mut().as_mut().unwrap().receive()
+//! });
+//! ```
+//!
+//! The queues hold `N` events each. `N` must be at least 16, the number of
+//! events of a full-speed bulk packet.
+
+use usb_device::bus::{InterfaceNumber, UsbBus, UsbBusAllocator};
+use usb_device::class::UsbClass;
+use usb_device::descriptor::DescriptorWriter;
+use usb_device::endpoint::{EndpointIn, EndpointOut};
+use usb_device::prelude::*;
+
+/// Size of a USB MIDI event packet, in bytes
+const EVENT_SIZE: usize = 4;
+
+/// Size of a full-speed bulk packet, in bytes
+const MAX_PACKET_SIZE: u16 = 64;
+
+/// Number of events of a full-speed bulk packet
+const EVENTS_PER_PACKET: usize = MAX_PACKET_SIZE as usize / EVENT_SIZE;
+
+const USB_CLASS_AUDIO: u8 = 0x01;
+const AUDIO_SUBCLASS_CONTROL: u8 = 0x01;
+const AUDIO_SUBCLASS_MIDI_STREAMING: u8 = 0x03;
+
+const CS_INTERFACE: u8 = 0x24;
+const CS_ENDPOINT: u8 = 0x25;
+
+const AC_HEADER: u8 = 0x01;
+const MS_HEADER: u8 = 0x01;
+const MS_IN_JACK: u8 = 0x02;
+const MS_OUT_JACK: u8 = 0x03;
+const MS_GENERAL: u8 = 0x01;
+
+const JACK_EMBEDDED: u8 = 0x01;
+const JACK_EXTERNAL: u8 = 0x02;
+
+/// IDs of the jacks: the host's OUT endpoint feeds the embedded IN jack, which
+/// is wired to the external OUT jack, and the external IN jack is wired to the
+/// embedded OUT jack feeding the host's IN endpoint
+const EMBEDDED_IN_JACK: u8 = 1;
+const EXTERNAL_IN_JACK: u8 = 2;
+const EMBEDDED_OUT_JACK: u8 = 3;
+const EXTERNAL_OUT_JACK: u8 = 4;
+
+/// Total size of the class-specific MIDI streaming descriptors: the header,
+/// two IN jacks, two OUT jacks, and both endpoints with their class-specific
+/// descriptors
+const MS_TOTAL_LENGTH: u16 = 7 + 2 * 6 + 2 * 9 + 2 * (7 + 5);
+
+/// The USB MIDI 1.0 streaming class, with a bulk endpoint in each direction
+struct MidiClass<'a, B: UsbBus> {
+    audio_control: InterfaceNumber,
+    midi_streaming: InterfaceNumber,
+    ep_out: EndpointOut<'a, B>,
+    ep_in: EndpointIn<'a, B>,
+}
+
+impl<'a, B: UsbBus> MidiClass<'a, B> {
+    fn new(alloc: &'a UsbBusAllocator<B>) -> Self {
+        Self {
+            audio_control: alloc.interface(),
+            midi_streaming: alloc.interface(),
+            ep_out: alloc.bulk(MAX_PACKET_SIZE),
+            ep_in: alloc.bulk(MAX_PACKET_SIZE),
+        }
+    }
+}
+
+impl<B: UsbBus> UsbClass<B> for MidiClass<'_, B> {
+    fn get_configuration_descriptors(
+        &self,
+        writer: &mut DescriptorWriter,
+    ) -> usb_device::Result<()> {
+        writer.interface(
+            self.audio_control,
+            USB_CLASS_AUDIO,
+            AUDIO_SUBCLASS_CONTROL,
+            0,
+        )?;
+        writer.write(
+            CS_INTERFACE,
+            &[
+                AC_HEADER,
+                0x00,
+                0x01, // Audio 1.0
+                0x09,
+                0x00, // Total size of this descriptor
+                0x01, // One streaming interface
+                self.midi_streaming.into(),
+            ],
+        )?;
+
+        writer.interface(
+            self.midi_streaming,
+            USB_CLASS_AUDIO,
+            AUDIO_SUBCLASS_MIDI_STREAMING,
+            0,
+        )?;
+        let [len_lo, len_hi] = MS_TOTAL_LENGTH.to_le_bytes();
+        writer.write(CS_INTERFACE, &[MS_HEADER, 0x00, 0x01, len_lo, len_hi])?;
+        writer.write(
+            CS_INTERFACE,
+            &[MS_IN_JACK, JACK_EMBEDDED, EMBEDDED_IN_JACK, 0x00],
+        )?;
+        writer.write(
+            CS_INTERFACE,
+            &[MS_IN_JACK, JACK_EXTERNAL, EXTERNAL_IN_JACK, 0x00],
+        )?;
+        writer.write(
+            CS_INTERFACE,
+            &[
+                MS_OUT_JACK,
+                JACK_EMBEDDED,
+                EMBEDDED_OUT_JACK,
+                0x01, // One input pin
+                EXTERNAL_IN_JACK,
+                0x01, // connected to output pin 1 of the source
+                0x00,
+            ],
+        )?;
+        writer.write(
+            CS_INTERFACE,
+            &[
+                MS_OUT_JACK,
+                JACK_EXTERNAL,
+                EXTERNAL_OUT_JACK,
+                0x01,
+                EMBEDDED_IN_JACK,
+                0x01,
+                0x00,
+            ],
+        )?;
+
+        writer.endpoint(&self.ep_out)?;
+        writer.write(CS_ENDPOINT, &[MS_GENERAL, 0x01, EMBEDDED_IN_JACK])?;
+        writer.endpoint(&self.ep_in)?;
+        writer.write(CS_ENDPOINT, &[MS_GENERAL, 0x01, EMBEDDED_OUT_JACK])?;
+        Ok(())
+    }
+}
+
+/// A MIDI message
+///
+/// Channels are numbered from 0 to 15, and data bytes are 7-bit values.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MidiMessage {
+    NoteOff {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    NoteOn {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    PolyPressure {
+        channel: u8,
+        note: u8,
+        pressure: u8,
+    },
+    ControlChange {
+        channel: u8,
+        control: u8,
+        value: u8,
+    },
+    ProgramChange {
+        channel: u8,
+        program: u8,
+    },
+    ChannelPressure {
+        channel: u8,
+        pressure: u8,
+    },
+    /// A 14-bit bend, centred on 0x2000
+    PitchBend {
+        channel: u8,
+        value: u16,
+    },
+    /// Any other event, e.g. system exclusive or real-time messages, given as
+    /// the code index number of the USB packet and its three MIDI bytes
+    Other {
+        cin: u8,
+        bytes: [u8; 3],
+    },
+}
+
+/// A MIDI message on one of the 16 virtual cables of the USB interface
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MidiEvent {
+    pub cable: u8,
+    pub message: MidiMessage,
+}
+
+impl MidiEvent {
+    /// Creates an event on the first cable
+    pub const fn new(message: MidiMessage) -> Self {
+        Self { cable: 0, message }
+    }
+
+    /// Decodes a USB MIDI event packet
+    pub fn from_packet(packet: [u8; EVENT_SIZE]) -> Self {
+        use MidiMessage::*;
+        let cable = packet[0] >> 4;
+        let cin = packet[0] & 0x0f;
+        let channel = packet[1] & 0x0f;
+        let (a, b) = (packet[2] & 0x7f, packet[3] & 0x7f);
+        // Channel messages have the same code index number as their status
+        let message = match (cin, packet[1] >> 4 == cin) {
+            (0x8, true) => NoteOff {
+                channel,
+                note: a,
+                velocity: b,
+            },
+            (0x9, true) => NoteOn {
+                channel,
+                note: a,
+                velocity: b,
+            },
+            (0xa, true) => PolyPressure {
+                channel,
+                note: a,
+                pressure: b,
+            },
+            (0xb, true) => ControlChange {
+                channel,
+                control: a,
+                value: b,
+            },
+            (0xc, true) => ProgramChange {
+                channel,
+                program: a,
+            },
+            (0xd, true) => ChannelPressure {
+                channel,
+                pressure: a,
+            },
+            (0xe, true) => PitchBend {
+                channel,
+                value: a as u16 | (b as u16) << 7,
+            },
+            _ => Other {
+                cin,
+                bytes: [packet[1], packet[2], packet[3]],
+            },
+        };
+        Self { cable, message }
+    }
+
+    /// Encodes the event as a USB MIDI event packet
+    pub fn to_packet(&self) -> [u8; EVENT_SIZE] {
+        use MidiMessage::*;
+        let (status, channel, a, b) = match self.message {
+            NoteOff {
+                channel,
+                note,
+                velocity,
+            } => (0x8, channel, note, velocity),
+            NoteOn {
+                channel,
+                note,
+                velocity,
+            } => (0x9, channel, note, velocity),
+            PolyPressure {
+                channel,
+                note,
+                pressure,
+            } => (0xa, channel, note, pressure),
+            ControlChange {
+                channel,
+                control,
+                value,
+            } => (0xb, channel, control, value),
+            ProgramChange { channel, program } => (0xc, channel, program, 0),
+            ChannelPressure { channel, pressure } => (0xd, channel, pressure, 0),
+            PitchBend { channel, value } => (0xe, channel, value as u8, (value >> 7) as u8),
+            Other { cin, bytes } => {
+                return [self.cable << 4 | cin & 0x0f, bytes[0], bytes[1], bytes[2]];
+            }
+        };
+        [
+            self.cable << 4 | status,
+            status << 4 | channel & 0x0f,
+            a & 0x7f,
+            b & 0x7f,
+        ]
+    }
+}
+
+/// A fixed capacity FIFO of event packets
+struct Queue<const N: usize> {
+    packets: [[u8; EVENT_SIZE]; N],
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> Queue<N> {
+    const fn new() -> Self {
+        Self {
+            packets: [[0; EVENT_SIZE]; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn free(&self) -> usize {
+        N - self.len
+    }
+
+    fn push(&mut self, packet: [u8; EVENT_SIZE]) -> bool {
+        if self.len == N {
+            return false;
+        }
+        self.packets[(self.head + self.len) % N] = packet;
+        self.len += 1;
+        true
+    }
+
+    /// Returns the `i`th oldest packet
+    fn get(&self, i: usize) -> Option<[u8; EVENT_SIZE]> {
+        if i < self.len {
+            Some(self.packets[(self.head + i) % N])
+        } else {
+            None
+        }
+    }
+
+    fn pop(&mut self) -> Option<[u8; EVENT_SIZE]> {
+        let packet = self.get(0)?;
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(packet)
+    }
+}
+
+/// A USB MIDI device with event queues. See the [module](self) documentation.
+pub struct UsbMidi<'a, B: UsbBus, const N: usize> {
+    device: UsbDevice<'a, B>,
+    midi: MidiClass<'a, B>,
+    rx: Queue<N>,
+    tx: Queue<N>,
+}
+
+impl<'a, B: UsbBus, const N: usize> UsbMidi<'a, B, N> {
+    /// Creates a new MIDI device on the given USB bus
+    pub fn new(
+        bus_allocator: &'a UsbBusAllocator<B>,
+        vid_pid: UsbVidPid,
+        manufacturer: &'a str,
+        product: &'a str,
+    ) -> Self {
+        assert!(N >= EVENTS_PER_PACKET);
+        // The class must allocate its endpoints before the device is built
+        let midi = MidiClass::new(bus_allocator);
+        let device = UsbDeviceBuilder::new(bus_allocator, vid_pid)
+            .manufacturer(manufacturer)
+            .product(product)
+            .build();
+        Self {
+            device,
+            midi,
+            rx: Queue::new(),
+            tx: Queue::new(),
+        }
+    }
+
+    /// Services the USB peripheral. Call this from the `USB` interrupt, or
+    /// from all three USB interrupts of the SAMD51.
+    pub fn poll(&mut self) {
+        if self.device.poll(&mut [&mut self.midi]) {
+            self.read_packet();
+        }
+        self.flush();
+    }
+
+    /// Queues an event for the host, and starts sending it if the endpoint is
+    /// free. Returns the event back if the queue is full.
+    pub fn send(&mut self, event: MidiEvent) -> Result<(), MidiEvent> {
+        if !self.tx.push(event.to_packet()) {
+            return Err(event);
+        }
+        self.flush();
+        Ok(())
+    }
+
+    /// Pops the oldest event received from the host
+    pub fn receive(&mut self) -> Option<MidiEvent> {
+        let packet = self.rx.pop()?;
+        // A packet may have been held off while the queue was full
+        self.read_packet();
+        Some(MidiEvent::from_packet(packet))
+    }
+
+    /// Returns the state of the USB device
+    pub fn state(&self) -> UsbDeviceState {
+        self.device.state()
+    }
+
+    /// Reads a packet from the OUT endpoint, if the queue has room for all
+    /// its events
+    fn read_packet(&mut self) {
+        if self.rx.free() < EVENTS_PER_PACKET {
+            return;
+        }
+        let mut buffer = [0; EVENTS_PER_PACKET * EVENT_SIZE];
+        if let Ok(len) = self.midi.ep_out.read(&mut buffer) {
+            for event in buffer[..len].chunks_exact(EVENT_SIZE) {
+                // Code index number 0 is reserved, and pads some packets
+                if event[0] & 0x0f != 0 {
+                    self.rx.push([event[0], event[1], event[2], event[3]]);
+                }
+            }
+        }
+    }
+
+    /// Writes as many queued events as fit in a packet to the IN endpoint,
+    /// unless the host has not collected the previous packet yet
+    fn flush(&mut self) {
+        let mut buffer = [0; EVENTS_PER_PACKET * EVENT_SIZE];
+        let mut count = 0;
+        while let Some(packet) = self.tx.get(count).filter(|_| count < EVENTS_PER_PACKET) {
+            buffer[count * EVENT_SIZE..][..EVENT_SIZE].copy_from_slice(&packet);
+            count += 1;
+        }
+        if count > 0 && self.midi.ep_in.write(&buffer[..count * EVENT_SIZE]).is_ok() {
+            for _ in 0..count {
+                self.tx.pop();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packet_round_trip() {
+        use MidiMessage::*;
+        for &message in &[
+            NoteOn {
+                channel: 3,
+                note: 60,
+                velocity: 100,
+            },
+            NoteOff {
+                channel: 0,
+                note: 127,
+                velocity: 0,
+            },
+            ControlChange {
+                channel: 15,
+                control: 7,
+                value: 64,
+            },
+            ProgramChange {
+                channel: 9,
+                program: 42,
+            },
+            PitchBend {
+                channel: 1,
+                value: 0x3fff,
+            },
+            Other {
+                cin: 0xf,
+                bytes: [0xf8, 0, 0],
+            },
+        ] {
+            let event = MidiEvent { cable: 2, message };
+            assert_eq!(MidiEvent::from_packet(event.to_packet()), event);
+        }
+        assert_eq!(
+            MidiEvent::new(NoteOn {
+                channel: 3,
+                note: 60,
+                velocity: 100
+            })
+            .to_packet(),
+            [0x09, 0x93, 60, 100]
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! USB bus power negotiation
+//!
+//! A bus-powered device may draw 100 mA from VBUS until the host selects a
+//! configuration, then up to the `bMaxPower` of that configuration, at most
+//! 500 mA, and no more than 2.5 mA while the bus is suspended. Turning on a
+//! motor or a charger as soon as VBUS is present breaks this, and some hosts
+//! then shut the port down.
+//!
+//! [`PowerConfig`] sets `bMaxPower` and the self-powered attribute of the
+//! configuration descriptor. [`PowerBudget`] follows the state of the device
+//! and the suspend and wakeup events of the `UsbBus`, which `usb-device`
+//! does not see, and reports each change of the current available:
+//!
+//! ```
+//! let power = PowerConfig::bus_powered(500);
+//! let mut device = power
+//!     .apply(UsbDeviceBuilder::new(&bus_allocator, UsbVidPid(0x16c0, 0x27dd)))
+//!     .product("Charger")
+//!     .build();
+//! let mut budget = PowerBudget::new(power);
+//!
+//! loop {
+//!     device.poll(&mut [&mut serial]);
+//!     budget.update(device.state(), device.bus().take_events(), |available_ma| {
+//!         charger.set_enabled(available_ma >= 500);
+//!     });
+//! }
+//! ```
+
+use usb_device::bus::UsbBus;
+use usb_device::device::{UsbDeviceBuilder, UsbDeviceState};
+
+/// Current available before the host selects a configuration, in mA
+pub const UNCONFIGURED_MA: u16 = 100;
+
+/// Current available while the bus is suspended, in mA, rounded down from
+/// 2.5 mA
+pub const SUSPENDED_MA: u16 = 2;
+
+/// Largest `bMaxPower` of a USB 2.0 device, in mA
+pub const MAX_POWER_MA: u16 = 500;
+
+/// Bus events of the `UsbBus`, returned by its `take_events` method
+///
+/// The suspend and wakeup events are not reported to `usb-device`, as the
+/// bus also goes idle while the cable is unplugged.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct UsbEvents {
+    /// The host reset the bus
+    pub reset: bool,
+    /// The bus has been idle for 3 ms, i.e. suspended or unplugged
+    pub suspend: bool,
+    /// The bus is active again after a suspend
+    pub wakeup: bool,
+}
+
+/// Power attributes of the configuration descriptor
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PowerConfig {
+    max_power_ma: u16,
+    self_powered: bool,
+}
+
+impl PowerConfig {
+    /// A device drawing up to `max_power_ma` from VBUS once configured,
+    /// limited to [`MAX_POWER_MA`]
+    pub const fn bus_powered(max_power_ma: u16) -> Self {
+        Self {
+            max_power_ma: if max_power_ma > MAX_POWER_MA {
+                MAX_POWER_MA
+            } else {
+                max_power_ma
+            },
+            self_powered: false,
+        }
+    }
+
+    /// A device with its own supply, which still draws up to `max_power_ma`
+    /// from VBUS once configured
+    pub const fn self_powered(max_power_ma: u16) -> Self {
+        Self {
+            self_powered: true,
+            ..Self::bus_powered(max_power_ma)
+        }
+    }
+
+    /// Returns the `bMaxPower` of the configuration, in mA
+    pub const fn max_power_ma(&self) -> u16 {
+        self.max_power_ma
+    }
+
+    /// Returns true if the device has its own supply
+    pub const fn is_self_powered(&self) -> bool {
+        self.self_powered
+    }
+
+    /// Sets `bMaxPower` and the self-powered attribute of `builder`
+    pub fn apply<'a, B: UsbBus>(
+        &self,
+        builder: UsbDeviceBuilder<'a, B>,
+    ) -> UsbDeviceBuilder<'a, B> {
+        builder
+            .max_power(self.max_power_ma as usize)
+            .self_powered(self.self_powered)
+    }
+}
+
+/// Current a device may draw from VBUS, see the [module](self)
+/// documentation
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PowerBudget {
+    config: PowerConfig,
+    configured: bool,
+    suspended: bool,
+}
+
+impl PowerBudget {
+    /// Starts with the device unconfigured
+    pub const fn new(config: PowerConfig) -> Self {
+        Self {
+            config,
+            configured: false,
+            suspended: false,
+        }
+    }
+
+    /// Returns the current the device may draw from VBUS, in mA
+    pub fn available_ma(&self) -> u16 {
+        if self.suspended {
+            SUSPENDED_MA
+        } else if self.configured {
+            self.config.max_power_ma
+        } else {
+            UNCONFIGURED_MA
+        }
+    }
+
+    /// Returns true if the host selected a configuration, and the bus is not
+    /// suspended
+    pub fn is_configured(&self) -> bool {
+        self.configured && !self.suspended
+    }
+
+    /// Follows the `state` of the device and the `events` of the bus, and
+    /// calls `on_change` with the current available if it changed
+    ///
+    /// `on_change` is called with the `bMaxPower` of the configuration when
+    /// the host sets it, and with a lower current as soon as the host resets
+    /// or suspends the bus, when the loads must be turned off.
+    pub fn update<F: FnOnce(u16)>(
+        &mut self,
+        state: UsbDeviceState,
+        events: UsbEvents,
+        on_change: F,
+    ) {
+        let before = self.available_ma();
+        if events.suspend {
+            self.suspended = true;
+        }
+        // With both, the bus was suspended then woken up since the last call
+        if events.wakeup || events.reset {
+            self.suspended = false;
+        }
+        match state {
+            UsbDeviceState::Configured => self.configured = !events.reset,
+            // The state before the suspend is kept
+            UsbDeviceState::Suspend => self.suspended = true,
+            _ => self.configured = false,
+        }
+        let after = self.available_ma();
+        if after != before {
+            on_change(after);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(budget: &mut PowerBudget, state: UsbDeviceState, events: UsbEvents) -> Option<u16> {
+        let mut changed = None;
+        budget.update(state, events, |ma| changed = Some(ma));
+        changed
+    }
+
+    #[test]
+    fn config() {
+        assert_eq!(PowerConfig::bus_powered(900).max_power_ma(), 500);
+        let config = PowerConfig::self_powered(100);
+        assert_eq!(config.max_power_ma(), 100);
+        assert!(config.is_self_powered());
+    }
+
+    #[test]
+    fn budget() {
+        let none = UsbEvents::default();
+        let mut budget = PowerBudget::new(PowerConfig::bus_powered(400));
+        assert_eq!(budget.available_ma(), UNCONFIGURED_MA);
+        assert_eq!(update(&mut budget, UsbDeviceState::Addressed, none), None);
+        assert_eq!(
+            update(&mut budget, UsbDeviceState::Configured, none),
+            Some(400)
+        );
+        assert!(budget.is_configured());
+        assert_eq!(update(&mut budget, UsbDeviceState::Configured, none), None);
+
+        let suspend = UsbEvents {
+            suspend: true,
+            ..none
+        };
+        let wakeup = UsbEvents {
+            wakeup: true,
+            ..none
+        };
+        assert_eq!(
+            update(&mut budget, UsbDeviceState::Configured, suspend),
+            Some(2)
+        );
+        assert!(!budget.is_configured());
+        assert_eq!(
+            update(&mut budget, UsbDeviceState::Configured, wakeup),
+            Some(400)
+        );
+        let both = UsbEvents {
+            suspend: true,
+            wakeup: true,
+            ..none
+        };
+        assert_eq!(update(&mut budget, UsbDeviceState::Configured, both), None);
+
+        // usb-device only sees the reset on its next poll
+        let reset = UsbEvents {
+            reset: true,
+            ..none
+        };
+        assert_eq!(
+            update(&mut budget, UsbDeviceState::Configured, reset),
+            Some(100)
+        );
+        assert_eq!(update(&mut budget, UsbDeviceState::Default, none), None);
+    }
+}
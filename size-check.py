@@ -0,0 +1,46 @@
+#!/usr/bin/env python3
+
+# Checks that the examples of the boards with a "size" entry in crates.json
+# fit in the flash and RAM of their chip. The examples must have been built
+# with the "build" command of the board, in release mode.
+
+import json
+import subprocess
+import sys
+from pathlib import PurePath
+
+TARGETS = ["thumbv6m-none-eabi", "thumbv7em-none-eabihf"]
+
+
+with open("crates.json", "r") as f:
+    crates = json.load(f)
+
+failed = False
+for (board, jobs) in crates["boards"].items():
+    if "size" not in jobs:
+        continue
+    size = jobs["size"]
+    print(f"Crate:   {board}")
+
+    for example in size["examples"]:
+        for target in TARGETS:
+            path = PurePath("boards", board, "target", target, "release", "examples", example)
+            try:
+                output = subprocess.check_output(["arm-none-eabi-size", str(path)], text=True)
+            except (FileNotFoundError, subprocess.CalledProcessError):
+                continue
+            # Berkeley format: text data bss dec hex filename
+            text, data, bss = (int(field) for field in output.splitlines()[1].split()[:3])
+            flash = text + data
+            ram = data + bss
+            status = "ok"
+            if flash > size["flash"] or ram > size["ram"]:
+                status = "TOO LARGE"
+                failed = True
+            print(f"  {example}: flash {flash}/{size['flash']}, RAM {ram}/{size['ram']} {status}")
+            break
+        else:
+            print(f"  {example}: not built")
+            failed = True
+
+sys.exit(1 if failed else 0)